@@ -1,10 +1,35 @@
+use std::collections::BTreeMap;
 use std::io;
+use std::io::IsTerminal;
 use std::io::Write;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use clap::Parser;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
 use tokio::time::Instant;
+use notation::ci::{github_annotation, write_step_outputs};
+use notation::commands;
+use notation::commands::{ReplacePlan, Workspace, EXPORT_PARENT_PAGE_ID};
+use notation::markdown::diagnostic::Diagnostic;
+use notation::notion::compare::Difference;
+use notation::markdown::template::parse_variable;
 use notation::notion::client::NotionClient;
+use notation::notion::parent::AmbiguousParentError;
+use notation::settings::notation::NotationSettings;
+use notation::notify::notify;
+use notation::notion::report::ShipReport;
+use notation::notion::snapshot::Snapshot;
+use notation::notion::tree::Confirmation;
+use notation::notion::usage::ApiUsage;
+use notation::notion::summary::{page_url, write_manifest, Manifest, ShipSummary};
+use notation::redact::{redact, redact_error};
+use notation::settings::check::CheckOutcome;
+use notation::sync::daemon;
+use notation::sync::daemon::parse_interval;
+use notation::sync::status::{ConflictError, ConflictPolicy, LocalChange};
 
 const BANNER: &str = r#"
  _,  _,____, ____,____,____,__, ____, _,  _,
@@ -17,8 +42,83 @@ const BANNER: &str = r#"
 #[clap(name = "notation")]
 #[clap(bin_name = "notation")]
 enum NotationCLI {
-    Clear,
-    Ship(ShipParams)
+    Clear(ClearParams),
+    Ship(ShipParams),
+    /// Write the blocks and pages under the parent page to a JSON file
+    Snapshot(SnapshotParams),
+    /// Recreate the blocks and pages of a snapshot on the parent page
+    Restore(RestoreParams),
+    /// Lint the text of the documentation files
+    Check(CheckParams),
+    /// Show which shipped pages changed locally or were edited in Notion since the last ship
+    Status(StatusParams),
+    /// Experimental: merge edits made in Notion since the last ship into the markdown files
+    Merge(MergeParams),
+    /// Write the Notion requests of every page as JSON instead of shipping them
+    Export(ExportParams),
+    /// Compare the requests of every page with those of an earlier export, block by block
+    Compare(CompareParams),
+    /// Serve the converted pages as HTML on localhost, without calling Notion
+    Preview(PreviewParams),
+    /// Append raw Notion block JSON to a page
+    Import(ImportParams),
+    /// Generate the API reference of a crate from its rustdoc JSON and ship it
+    ApiReference(ApiReferenceParams),
+    /// Generate a page per endpoint of an OpenAPI spec and ship them
+    Openapi(OpenapiParams),
+    /// Ship a page per release of a Keep a Changelog style file
+    Changelog(ChangelogParams),
+    /// Ship the architecture decision records of a directory, with their index
+    Adr(AdrParams),
+    /// Start a page from a template, like meeting notes
+    New(NewParams),
+    /// Ship what changed every --interval, until stopped
+    Daemon(DaemonParams),
+    /// Serve an HTTP API to ship, clear and ask the status of the documentation
+    Serve(ServeParams),
+    /// Ship to a scratch page, read the pages back and report what the conversion lost
+    Roundtrip(RoundtripParams),
+    #[clap(subcommand)]
+    Config(ConfigCommand),
+    #[clap(subcommand)]
+    Trash(TrashCommand),
+    #[clap(subcommand)]
+    Index(IndexCommand),
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum TrashCommand {
+    /// List the shipped pages in the Notion trash
+    List(TrashListParams),
+    /// Take a page out of the Notion trash
+    Restore(TrashRestoreParams),
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum IndexCommand {
+    /// Read every page the integration can see into the local page index
+    Refresh,
+}
+
+#[derive(clap::Args, Debug)]
+struct TrashListParams {
+    #[clap(short, long, value_parser)]
+    pub src: String,
+}
+
+#[derive(clap::Args, Debug)]
+struct TrashRestoreParams {
+    /// Id of the page
+    pub id: String,
+    /// Track the page again in the sync state of this directory
+    #[clap(short, long)]
+    pub src: Option<String>,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum ConfigCommand {
+    /// Validate the configuration and the Notion connection
+    Check,
 }
 
 #[derive(clap::Args, Debug)]
@@ -26,59 +126,1136 @@ enum NotationCLI {
 struct ShipParams {
     #[clap(short, long, value_parser)]
     pub src: String,
+    /// Never prompt, report problems as GitHub Actions annotations and fail on conversion warnings
+    #[clap(long)]
+    pub ci: bool,
+    /// Only ship markdown files changed since this git ref, updating the pages of the last ship
+    #[clap(long)]
+    pub since: Option<String>,
+    /// Run the [code_check] commands on fenced code blocks first, and don't ship if any fails
+    #[clap(long)]
+    pub check_code: bool,
+    /// Convert everything without calling Notion and print the tree of pages that would be created
+    #[clap(long, conflicts_with = "since")]
+    pub dry_run: bool,
+    /// Ship everything as new pages, then archive the pages of the previous ship
+    #[clap(long, conflicts_with_all = ["since", "dry_run"])]
+    pub replace: bool,
+    /// With --since, overwrite pages edited in Notion since the last ship
+    #[clap(long, conflicts_with = "skip_conflicts")]
+    pub force: bool,
+    /// With --since, leave pages edited in Notion since the last ship as they are
+    #[clap(long)]
+    pub skip_conflicts: bool,
+    /// Only ship to this one of the [[destinations]], can be repeated
+    #[clap(long)]
+    pub destination: Vec<String>,
+    /// Only ship this one of the [[locales]], can be repeated
+    #[clap(long, conflicts_with = "destination")]
+    pub locale: Vec<String>,
+    /// Write a JSON manifest of the page every file was shipped to
+    #[clap(long, conflicts_with = "dry_run")]
+    pub manifest: Option<PathBuf>,
+    /// Only ship the top level sections under this heading, and what comes before the first
+    /// section, can be repeated
+    #[clap(long)]
+    pub only_heading: Vec<String>,
+    /// Ship under the page of this id instead of looking up [notion] parent_page by its title
+    #[clap(long, conflicts_with_all = ["destination", "locale"])]
+    pub parent_id: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct CheckParams {
+    #[clap(short, long, value_parser)]
+    pub src: String,
+    /// Also request every external link and report the dead ones
+    #[clap(long)]
+    pub links: bool,
+    /// Report issues as GitHub Actions annotations
+    #[clap(long)]
+    pub ci: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct StatusParams {
+    #[clap(short, long, value_parser)]
+    pub src: String,
+    /// Compare with the last ship to this one of the [[destinations]]
+    #[clap(long)]
+    pub destination: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct MergeParams {
+    #[clap(short, long, value_parser)]
+    pub src: String,
+    /// Merge the edits made to this one of the [[destinations]]
+    #[clap(long)]
+    pub destination: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct ExportParams {
+    #[clap(short, long, value_parser)]
+    pub src: String,
+    /// Directory the JSON files are written to
+    #[clap(short, long, value_parser)]
+    pub out: PathBuf,
+}
+
+#[derive(clap::Args, Debug)]
+struct CompareParams {
+    #[clap(short, long, value_parser)]
+    pub src: String,
+    /// Directory an earlier export wrote its JSON files to
+    #[clap(short, long, value_parser)]
+    pub against: PathBuf,
+}
+
+#[derive(clap::Args, Debug)]
+struct DaemonParams {
+    #[clap(short, long, value_parser)]
+    pub src: String,
+    /// How long to wait between syncs, like 30s, 15m or 1h
+    #[clap(long, default_value = "15m")]
+    pub interval: String,
+    /// Sync to this one of the [[destinations]] instead of [notion] parent_page
+    #[clap(long)]
+    pub destination: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct ServeParams {
+    #[clap(short, long, value_parser)]
+    pub src: String,
+    #[clap(short, long, default_value_t = 4100)]
+    pub port: u16,
+    /// Address to listen on, 0.0.0.0 to take requests from other machines
+    #[clap(long, default_value = "127.0.0.1")]
+    pub host: IpAddr,
+    /// Ship to this one of the [[destinations]] instead of [notion] parent_page
+    #[clap(long)]
+    pub destination: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct PreviewParams {
+    #[clap(short, long, value_parser)]
+    pub src: String,
+    #[clap(short, long, default_value_t = 4000)]
+    pub port: u16,
+}
+
+#[derive(clap::Args, Debug)]
+struct ImportParams {
+    /// Id of the page the blocks are appended to
+    #[clap(short, long, value_parser)]
+    pub page: String,
+    /// JSON file holding an append request, a list of blocks or a page written by export
+    #[clap(short, long, value_parser)]
+    pub blocks: PathBuf,
+}
+
+#[derive(clap::Args, Debug)]
+struct ApiReferenceParams {
+    /// JSON written by `cargo +nightly rustdoc -- -Z unstable-options --output-format json`
+    #[clap(short, long, value_parser)]
+    pub json: PathBuf,
+    /// Directory the markdown pages are written to and shipped from
+    #[clap(short, long, value_parser, default_value = "api-reference")]
+    pub out: PathBuf,
+    /// Ship under the page of this id instead of looking up [notion] parent_page by its title
+    #[clap(long)]
+    pub parent_id: Option<String>,
+    /// Never prompt for the parent page when several match
+    #[clap(long)]
+    pub ci: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct OpenapiParams {
+    /// OpenAPI 3 or Swagger 2 spec, in YAML or JSON
+    #[clap(short, long, value_parser)]
+    pub spec: PathBuf,
+    /// Directory the markdown pages are written to and shipped from
+    #[clap(short, long, value_parser, default_value = "openapi")]
+    pub out: PathBuf,
+    /// Ship under the page of this id instead of looking up [notion] parent_page by its title
+    #[clap(long)]
+    pub parent_id: Option<String>,
+    /// Never prompt for the parent page when several match
+    #[clap(long)]
+    pub ci: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct ChangelogParams {
+    #[clap(short, long, value_parser, default_value = "CHANGELOG.md")]
+    pub file: PathBuf,
+    /// Directory the release pages are written to and shipped from
+    #[clap(short, long, value_parser, default_value = "changelog")]
+    pub out: PathBuf,
+    /// Ship under the page of this id instead of looking up [notion] parent_page by its title
+    #[clap(long)]
+    pub parent_id: Option<String>,
+    /// Never prompt for the parent page when several match
+    #[clap(long)]
+    pub ci: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct AdrParams {
+    /// Directory of the records, like 0001-use-postgres.md
+    #[clap(short, long, value_parser, default_value = "docs/adr")]
+    pub dir: PathBuf,
+    /// Directory the record pages are written to and shipped from
+    #[clap(short, long, value_parser, default_value = "adr")]
+    pub out: PathBuf,
+    /// Ship under the page of this id instead of looking up [notion] parent_page by its title
+    #[clap(long)]
+    pub parent_id: Option<String>,
+    /// Never prompt for the parent page when several match
+    #[clap(long)]
+    pub ci: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct NewParams {
+    /// A template of the [templates] directory, or a built-in one
+    #[clap(short, long, default_value = "meeting-notes")]
+    pub template: String,
+    #[clap(long)]
+    pub title: String,
+    /// A value of a template variable, like --var team=Docs
+    #[clap(long)]
+    pub var: Vec<String>,
+    /// The documentation the page is added to
+    #[clap(short, long, value_parser, default_value = ".")]
+    pub src: String,
+    /// Directory of the page, relative to --src
+    #[clap(short, long, value_parser)]
+    pub dir: Option<PathBuf>,
+    /// Ship the page right away
+    #[clap(long)]
+    pub ship: bool,
+    /// Ship under the page of this id instead of looking up [notion] parent_page by its title
+    #[clap(long, requires = "ship")]
+    pub parent_id: Option<String>,
+    /// Never prompt for the parent page when several match
+    #[clap(long)]
+    pub ci: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct RoundtripParams {
+    #[clap(short, long, value_parser)]
+    pub src: String,
+    /// Keep the scratch page instead of archiving it
+    #[clap(long)]
+    pub keep: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct ClearParams {
+    /// Never prompt for confirmation
+    #[clap(long)]
+    pub ci: bool,
+    /// Write a snapshot of the parent page here before clearing it
+    #[clap(long)]
+    pub snapshot: Option<PathBuf>,
+    /// Refuse to clear when more pages than this would be archived
+    #[clap(long, default_value_t = 100)]
+    pub max_pages: usize,
+    /// Only clear the pages of this file or directory, relative to --src
+    #[clap(long, requires = "src")]
+    pub path: Option<String>,
+    /// The shipped directory whose sync state tracks the cleared pages, which it stops tracking
+    #[clap(short, long)]
+    pub src: Option<String>,
+    /// Clear the page of this id instead of looking up [notion] parent_page by its title
+    #[clap(long)]
+    pub parent_id: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct SnapshotParams {
+    /// Where to write the snapshot, notation-snapshot-<time>.json by default
+    #[clap(short, long)]
+    pub out: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug)]
+struct RestoreParams {
+    /// A snapshot written by `notation snapshot`
+    pub snapshot: PathBuf,
+    /// Point the sync state of this directory at the recreated pages
+    #[clap(short, long)]
+    pub src: Option<String>,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let args = NotationCLI::parse();
-    let nc = NotionClient::new()?;
-    let parent_page_id = nc.get_parent_id_by_name(nc.parent_page_name()).await?;
-    let parsed_page_name = nc.parent_page_name().replace(" ", "-").to_lowercase();
-    let parent_page_url = format!("https://www.notion.so/{}-{}", parsed_page_name, parent_page_id.replace("-", ""));
+    run().await.map_err(redact_error)
+}
 
-    println!("\n{}\n", BANNER);
-    println!("👋👋 Notation workspace hosted by parent page \"{}\"", nc.parent_page_name());
-    println!("🔗🔗 {}\n", parent_page_url);
+async fn run() -> Result<()> {
+    let args = NotationCLI::parse();
 
     match args {
-        NotationCLI::Clear => {
-            let page_content = nc.get_page_content_by_id(nc.get_parent_id_by_name(nc.parent_page_name()).await?).await?;
-            let page_content_len = page_content.results.len();
-            println!("This page has {} pieces of content on it.", page_content_len);
-            if page_content_len > 0 {
-                println!("\nFor example...\n");
-                for (i, r) in page_content.results.iter().take(5).enumerate() {
-                    println!("Content ({}): {}", i, r.content_type);
-                }
-                println!();
-                println!("Press ENTER to proceed with clearing this Notation workspace...");
-                let mut line = String::new();
-                let _ = io::stdin().read_line(&mut line).unwrap();
-            }
-            nc.clear().await?;
-            println!("\n🧹🧹 Notation workspace cleared! ✅ ");
+        NotationCLI::Config(ConfigCommand::Check) => {
+            check().await?;
         }
-        NotationCLI::Ship(params) => {
-            let nc_clone = nc.clone();
-            let mut h = tokio::spawn(async move {
-                nc_clone.create_pages(params.src, false).await
+        NotationCLI::Index(IndexCommand::Refresh) => {
+            let nc = NotionClient::new()?;
+            let (path, index) = nc.refresh_index().await?;
+            println!("🗂️🗂️ Indexed {} page(s) in {} ✅", index.pages.len(), path.display());
+            if !nc.settings().index.enabled {
+                println!("Set enabled = true under [index] to look pages up in it");
+            }
+        }
+        NotationCLI::Trash(TrashCommand::List(params)) => {
+            let nc = NotionClient::new()?;
+            let trashed = commands::trash(&nc, &params.src).await?;
+            if trashed.is_empty() {
+                println!("🗑️🗑️ No page of {} is in the trash", params.src);
+            }
+            for t in trashed.iter() {
+                let tracked = if t.is_tracked { " (archived in Notion, still tracked)" } else { "" };
+                println!("🗑️  {}  {}  {} ({}){}", t.archived_at, t.page_id, t.title, t.path, tracked);
+            }
+        }
+        NotationCLI::Trash(TrashCommand::Restore(params)) => {
+            let nc = NotionClient::new()?;
+            let is_tracked = commands::restore_from_trash(&nc, params.src.as_deref(), &params.id).await?;
+            println!("♻️♻️ Restored {} ✅", page_url(&params.id));
+            if is_tracked {
+                println!("It is tracked again, the next ship updates it");
+            }
+        }
+        NotationCLI::Clear(params) => {
+            let workspace = connect_client(parent_client(params.parent_id.clone())?, params.ci).await?;
+            let plan = match (&params.path, &params.src) {
+                (Some(path), Some(src)) => commands::clear_path_plan(&workspace, src, path).await?,
+                _ => commands::clear_plan(&workspace).await?,
+            };
+            match &params.path {
+                Some(path) => println!("The pages of {} have {} pieces of content in them.", path, plan.items.len()),
+                None => println!("This page has {} pieces of content under it.", plan.items.len()),
+            }
+            for (depth, count) in plan.by_depth() {
+                println!("  Depth {}: {} page(s), {} block(s)", depth, count.pages, count.blocks);
+            }
+            if plan.page_count() > params.max_pages {
+                return Err(anyhow!(
+                    "clearing would archive {} pages, more than --max-pages {}",
+                    plan.page_count(),
+                    params.max_pages
+                ));
+            }
+            if !params.ci {
+                let titles: Vec<&str> = plan.items.iter().filter_map(|i| i.title.as_deref()).take(5).collect();
+                if !titles.is_empty() {
+                    println!("\nFor example: {}\n", titles.join(", "));
+                }
+                match plan.confirmation() {
+                    Confirmation::None => {}
+                    Confirmation::Enter => {
+                        println!("Press ENTER to proceed with clearing this Notation workspace...");
+                        let mut line = String::new();
+                        let _ = io::stdin().read_line(&mut line).unwrap();
+                    }
+                    Confirmation::PageName => {
+                        let name = workspace.client.parent_page_name();
+                        println!("Type \"{}\" to archive {} pages with everything on them...", name, plan.page_count());
+                        let mut line = String::new();
+                        let _ = io::stdin().read_line(&mut line).unwrap();
+                        if line.trim() != name {
+                            return Err(anyhow!("clear aborted, nothing was archived"));
+                        }
+                    }
+                }
+            }
+            if let Some(path) = params.snapshot {
+                take_snapshot(&workspace, &path).await?;
+            }
+            match (&params.path, &params.src) {
+                (Some(path), Some(src)) => {
+                    commands::clear_path(&workspace, src, path, &plan).await?;
+                    println!("\n🧹🧹 Pages of {} cleared! ✅ ", path);
+                }
+                (None, Some(src)) => {
+                    commands::clear_path(&workspace, src, "", &plan).await?;
+                    println!("\n🧹🧹 Notation workspace cleared! ✅ ");
+                }
+                _ => {
+                    commands::clear(&workspace, &plan).await?;
+                    println!("\n🧹🧹 Notation workspace cleared! ✅ ");
+                }
+            }
+        }
+        NotationCLI::Snapshot(params) => {
+            let workspace = connect().await?;
+            let path = params.out.unwrap_or_else(|| {
+                PathBuf::from(format!("notation-snapshot-{}.json", chrono::Utc::now().format("%Y%m%dT%H%M%S")))
             });
-            let start = Instant::now();
-            loop {
-                tokio::select! {
-                    r = &mut h => {
-                        r??;
-                        break;
+            take_snapshot(&workspace, &path).await?;
+        }
+        NotationCLI::Restore(params) => {
+            let snapshot = Snapshot::read(&params.snapshot)?;
+            let workspace = connect().await?;
+            println!(
+                "♻️♻️ Restoring {} page(s) and {} block(s) from {} onto {}...",
+                snapshot.page_count(),
+                snapshot.block_count(),
+                snapshot.taken_at,
+                workspace.parent_page_url
+            );
+            let report = commands::restore(&workspace, &snapshot, params.src.as_deref()).await?;
+            println!("♻️♻️ Restored {} page(s) and {} block(s) ✅", report.pages, report.blocks);
+        }
+        NotationCLI::Ship(params) if params.dry_run => {
+            let nc = NotionClient::new()?.with_sections(params.only_heading.clone());
+            let locales = nc.settings().locales(&params.locale)?;
+            let plans = if locales.is_empty() {
+                vec![(None, nc.clone(), params.src.clone())]
+            } else {
+                locales
+                    .into_iter()
+                    .map(|l| Ok((Some(l.name.clone()), nc.for_locale(l)?, l.src(&params.src))))
+                    .collect::<Result<Vec<_>>>()?
+            };
+            let mut pages = 0;
+            for (locale, client, src) in plans {
+                if let Some(locale) = locale {
+                    println!("🌐 {}", locale);
+                }
+                let (summary, plan) = commands::plan(&client, src).await?;
+                print!("{}", plan.tree(EXPORT_PARENT_PAGE_ID, &client.parent_page_name()));
+                for w in summary.warnings.iter() {
+                    println!("⚠️  {}", w);
+                }
+                for d in summary.drafts.iter() {
+                    println!("📝 {} is a draft, not shipped", d);
+                }
+                for r in summary.redactions.iter() {
+                    println!("🙈 {}", r);
+                }
+                pages += summary.pages.len();
+            }
+            println!("\n🧪🧪 Dry run, {} page(s) would be shipped", pages);
+        }
+        NotationCLI::Ship(params) => {
+            let nc = parent_client(params.parent_id.clone())?.with_sections(params.only_heading.clone());
+            let settings = nc.settings();
+            if settings.destinations.is_empty()
+                && params.destination.is_empty()
+                && settings.locales.is_empty()
+                && params.locale.is_empty()
+            {
+                ship(connect_client(nc, params.ci).await?, params).await?;
+            } else {
+                ship_destinations(nc, params).await?;
+            }
+        }
+        NotationCLI::Check(params) => {
+            let nc = NotionClient::new()?;
+            let linted = lint(&nc, &params.src, params.ci);
+            if params.links {
+                check_links(&nc, &params.src, params.ci).await?;
+            }
+            linted?;
+        }
+        NotationCLI::Status(params) => {
+            let nc = destination_client(params.destination)?;
+            status(&nc, &params.src).await?;
+        }
+        NotationCLI::Merge(params) => {
+            let nc = destination_client(params.destination)?;
+            println!("🔀🔀 Merging edits made in Notion since the last ship...");
+            let report = commands::merge(&nc, &params.src).await?;
+            for d in report.skipped.iter() {
+                println!("⚠️  {}", d);
+            }
+            for f in report.files.iter() {
+                match (f.conflicts, f.is_changed) {
+                    (0, false) => println!("   {} already has the edits", f.path),
+                    (0, true) => println!("✅ {} merged", f.path),
+                    (n, _) => println!("✋ {} merged with {} conflict(s)", f.path, n),
+                }
+            }
+            let conflicts: usize = report.files.iter().map(|f| f.conflicts).sum();
+            if report.files.is_empty() {
+                println!("\nNo page was edited in Notion since the last ship");
+            } else if conflicts > 0 {
+                println!("\nResolve the conflict markers, then ship with --force to overwrite the pages");
+            } else {
+                println!("\nReview the changes, then ship with --force to overwrite the pages");
+            }
+        }
+        NotationCLI::Export(params) => {
+            let nc = NotionClient::new()?;
+            let summary = commands::export(&nc, params.src, params.out.clone()).await?;
+            for w in summary.warnings.iter() {
+                println!("⚠️  {}", w);
+            }
+            println!("📦📦 Exported {} page(s) to {}", summary.pages.len(), params.out.display());
+        }
+        NotationCLI::Compare(params) => {
+            let nc = NotionClient::new()?;
+            let (summary, report) = commands::compare(&nc, params.src, &params.against).await?;
+            for w in summary.warnings.iter() {
+                println!("⚠️  {}", w);
+            }
+            for d in report.differences.iter() {
+                match d {
+                    Difference::AddedPage(file) => println!("\n➕ {} is new", file.display()),
+                    Difference::RemovedPage(file) => println!("\n➖ {} is gone", file.display()),
+                    Difference::ChangedPage { file, diff } => print!("\n✏️  {} page changed\n{}", file.display(), diff),
+                    Difference::AddedBlock { file, index, block } => {
+                        println!("\n➕ {} block {} added\n{}", file.display(), index, block)
+                    }
+                    Difference::RemovedBlock { file, index, block } => {
+                        println!("\n➖ {} block {} removed\n{}", file.display(), index, block)
                     }
-                    _ = tokio::time::sleep(Duration::from_millis(500)) => {
-                        print!("\r🚢🚢 Shipping pages, one moment... {}s", start.elapsed().as_secs());
-                        io::stdout().flush().unwrap();
+                    Difference::ChangedBlock { file, index, diff } => {
+                        print!("\n✏️  {} block {} changed\n{}", file.display(), index, diff)
                     }
                 }
             }
-            println!("\n\nNotation pages shipped! ✅ \nSee you next time 🫡");
+            if report.is_same() {
+                println!("\n✅✅ {} page(s) match {}", report.pages, params.against.display());
+            } else {
+                let mut files: Vec<&Path> = report.differences.iter().map(|d| d.file()).collect();
+                files.dedup();
+                println!(
+                    "\n🔍🔍 {} difference(s) in {} of {} page(s)",
+                    report.differences.len(),
+                    files.len(),
+                    report.pages
+                );
+            }
         }
+        NotationCLI::Daemon(params) => {
+            let interval = parse_interval(&params.interval)?;
+            let workspace = Workspace::from_client(destination_client(params.destination)?).await?;
+            daemon::run(&workspace, &params.src, interval).await?;
+        }
+        NotationCLI::Serve(params) => {
+            let workspace = Workspace::from_client(destination_client(params.destination)?).await?;
+            let is_open = workspace.client.settings().serve.token.as_deref().is_none_or(|t| t.trim().is_empty());
+            if is_open && !params.host.is_loopback() {
+                println!("⚠️ Anyone reaching {} can ship and clear, set [serve] token to require it", params.host);
+            }
+            println!("🛰️🛰️ Serving {} at http://{}:{}/ (Ctrl-C to stop)", params.src, params.host, params.port);
+            commands::serve(workspace, params.src, SocketAddr::new(params.host, params.port)).await?;
+        }
+        NotationCLI::Preview(params) => {
+            let nc = NotionClient::new()?;
+            println!("👀👀 Previewing {} at http://localhost:{}/ (Ctrl-C to stop)", params.src, params.port);
+            commands::preview(nc, params.src, params.port).await?;
+        }
+        NotationCLI::Import(params) => {
+            let nc = NotionClient::new()?;
+            let report = commands::import(&nc, params.page, &params.blocks).await?;
+            println!("📥📥 Appended {} block(s) ✅", report.appended);
+        }
+        NotationCLI::ApiReference(params) => {
+            let workspace = connect_client(parent_client(params.parent_id)?, params.ci).await?;
+            println!("📚📚 Generating the API reference of {}...", params.json.display());
+            let summary = commands::api_reference(&workspace, &params.json, &params.out).await?;
+            for w in summary.warnings.iter() {
+                println!("⚠️  {}", w);
+            }
+            println!("✅ Shipped {} page(s) from {}", summary.pages.len(), params.out.display());
+        }
+        NotationCLI::Openapi(params) => {
+            let workspace = connect_client(parent_client(params.parent_id)?, params.ci).await?;
+            println!("📚📚 Generating the pages of {}...", params.spec.display());
+            let summary = commands::openapi(&workspace, &params.spec, &params.out).await?;
+            for w in summary.warnings.iter() {
+                println!("⚠️  {}", w);
+            }
+            println!("✅ Shipped {} page(s) from {}", summary.pages.len(), params.out.display());
+        }
+        NotationCLI::Changelog(params) => {
+            let workspace = connect_client(parent_client(params.parent_id)?, params.ci).await?;
+            println!("📰📰 Shipping the releases of {}...", params.file.display());
+            let (releases, summary) = commands::changelog(&workspace, &params.file, &params.out).await?;
+            for w in summary.warnings.iter() {
+                println!("⚠️  {}", w);
+            }
+            println!("✅ Shipped {} page(s) of {} release(s)", summary.pages.len(), releases);
+        }
+        NotationCLI::Adr(params) => {
+            let workspace = connect_client(parent_client(params.parent_id)?, params.ci).await?;
+            println!("🏛️🏛️ Shipping the decision records of {}...", params.dir.display());
+            let (records, summary) = commands::adr(&workspace, &params.dir, &params.out).await?;
+            for w in summary.warnings.iter() {
+                println!("⚠️  {}", w);
+            }
+            println!("✅ Shipped {} page(s) of {} record(s)", summary.pages.len(), records);
+        }
+        NotationCLI::New(params) => {
+            let settings = NotationSettings::new()?;
+            let vars = params.var.iter().map(|v| parse_variable(v)).collect::<Result<Vec<_>>>()?;
+            let dir = Path::new(&params.src).join(params.dir.unwrap_or_default());
+            let path = commands::new_page(&settings.templates, &params.template, &params.title, &vars, &dir)?;
+            println!("📝 Wrote {}", path.display());
+            if params.ship {
+                let workspace = connect_client(parent_client(params.parent_id)?, params.ci).await?;
+                let summary = commands::ship_file(&workspace, &params.src, &path).await?;
+                for w in summary.warnings.iter() {
+                    println!("⚠️  {}", w);
+                }
+                for p in summary.pages.iter() {
+                    println!("✅ Shipped {}", p.url);
+                }
+            }
+        }
+        NotationCLI::Roundtrip(params) => {
+            let workspace = connect().await?;
+            println!("🔁🔁 Shipping to a scratch page and reading it back...");
+            let report = commands::roundtrip(&workspace, params.src, params.keep).await?;
+            for f in report.files.iter().filter(|f| !f.is_lossless()) {
+                println!("\n📄 {}", f.path);
+                for e in f.fidelity.iter().filter(|e| e.matched < e.expected) {
+                    println!("   {} {}/{} kept", e.kind, e.matched, e.expected);
+                }
+                print!("{}", f.diff);
+            }
+            println!("\nFidelity over {} file(s):", report.files.len());
+            for e in report.totals() {
+                println!(
+                    "  {:<20} {:>5}/{:<5} {:>5.1}%",
+                    e.kind,
+                    e.matched,
+                    e.expected,
+                    e.ratio() * 100.0
+                );
+            }
+            if let Some(url) = report.scratch_url {
+                println!("\n🔗🔗 Scratch page kept at {}", url);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The client of one of the `[[destinations]]`, or of `[notion]` without one.
+fn destination_client(name: Option<String>) -> Result<NotionClient> {
+    let nc = NotionClient::new()?;
+    match name {
+        Some(name) => {
+            let destination = nc.settings().destinations(&[name])?[0].clone();
+            nc.for_destination(&destination)
+        }
+        None => Ok(nc),
+    }
+}
+
+/// The client of `[notion]`, shipping under the page `parent_id` when given.
+fn parent_client(parent_id: Option<String>) -> Result<NotionClient> {
+    let nc = NotionClient::new()?;
+    Ok(match parent_id {
+        Some(id) => nc.with_parent_page_id(id),
+        None => nc,
+    })
+}
+
+async fn connect() -> Result<Workspace> {
+    connect_client(NotionClient::new()?, false).await
+}
+
+/// Finds the parent page of `client`, asking which one it is when several match and there is
+/// someone at the terminal to answer.
+async fn connect_client(client: NotionClient, ci: bool) -> Result<Workspace> {
+    let workspace = match Workspace::from_client(client.clone()).await {
+        Ok(workspace) => workspace,
+        Err(e) => match e.downcast_ref::<AmbiguousParentError>() {
+            Some(ambiguous) if !ci && io::stdin().is_terminal() => {
+                let id = pick_parent_page(ambiguous)?;
+                Workspace::from_client(client.with_parent_page_id(id)).await?
+            }
+            _ => return Err(e),
+        },
+    };
+
+    println!("\n{}\n", BANNER);
+    println!("👋👋 Notation workspace hosted by parent page \"{}\"", workspace.client.parent_page_name());
+    println!("🔗🔗 {}\n", workspace.parent_page_url);
+
+    Ok(workspace)
+}
+
+/// Lints and checks the code samples when asked to, before anything is shipped.
+async fn before_ship(client: &NotionClient, params: &ShipParams) -> Result<()> {
+    if client.settings().lint.block_ship {
+        lint(client, &params.src, params.ci)?;
+    }
+    if params.check_code {
+        check_code(client, &params.src, params.ci).await?;
+    }
+    Ok(())
+}
+
+async fn ship(workspace: Workspace, params: ShipParams) -> Result<()> {
+    before_ship(&workspace.client, &params).await?;
+    let replace_plan = if params.replace {
+        let plan = commands::replace_plan(&workspace, &params.src).await?;
+        confirm_replace(&plan, params.ci);
+        Some(plan)
+    } else {
+        None
+    };
+    let workspace = Workspace {
+        client: workspace.client.with_stop(stop_on_ctrl_c()),
+        ..workspace
+    };
+    let mut on_conflict = conflict_policy(&params);
+    let started = chrono::Utc::now();
+    let api = workspace.client.api_usage();
+    let outcome = loop {
+        let ws = workspace.clone();
+        let src = params.src.clone();
+        let since = params.since.clone();
+        let replace_plan = replace_plan.clone();
+        let h = tokio::spawn(async move {
+            match replace_plan {
+                Some(plan) => commands::replace(&ws, src, plan).await,
+                None => commands::ship(&ws, src, since, on_conflict).await,
+            }
+        });
+        match wait_for_ship(h, params.ci).await? {
+            Ok(summary) => break Ok(summary),
+            Err(e) => {
+                if let (Some(conflicts), false) = (e.downcast_ref::<ConflictError>(), params.ci) {
+                    on_conflict = resolve_conflicts(conflicts)?;
+                    continue;
+                }
+                break Err(e);
+            }
+        }
+    };
+
+    let mut report = ShipReport::new(&params.src, started);
+    report.add(
+        &workspace.client.parent_page_name(),
+        Some(workspace.parent_page_url.clone()),
+        outcome.as_ref().map_err(|e| redact(&format!("{:#}", e))),
+    );
+    let report = report.finish(chrono::Utc::now()).with_api_usage(workspace.client.api_usage() - api);
+    print_api_usage(&report.api);
+    after_ship(&workspace.client, report).await?;
+    let summary = match outcome {
+        Ok(summary) => summary,
+        Err(e) => {
+            if params.ci {
+                if let Some(d) = e.downcast_ref::<Diagnostic>() {
+                    println!("{}", redact(&github_annotation(d)));
+                }
+            }
+            return Err(e);
+        }
+    };
+    if summary.is_interrupted() {
+        return Err(interrupted(&summary));
+    }
+    if let Some(path) = &params.manifest {
+        write_manifest(path, &Manifest::new(Some(workspace.parent_page_url.clone()), &summary))?;
+    }
+
+    if params.ci {
+        for w in summary.warnings.iter() {
+            println!("{}", redact(&github_annotation(w)));
+        }
+        for p in summary.pages.iter() {
+            println!("📄 {} -> {}", p.path, p.url);
+        }
+        write_step_outputs(&workspace.parent_page_url, &summary)?;
+        commands::check_warnings(&workspace.client, &summary)?;
+    } else {
+        for w in summary.warnings.iter() {
+            println!("\n⚠️  {}", w);
+        }
+    }
+    for d in summary.drafts.iter() {
+        println!("\n📝 {} is a draft, not shipped", d);
+    }
+    if !summary.redactions.is_empty() {
+        println!("\n🙈 {} redaction(s) left out or masked", summary.redactions.len());
+    }
+    println!("\n\nNotation pages shipped! ✅ \nSee you next time 🫡");
+    Ok(())
+}
+
+async fn ship_destinations(client: NotionClient, params: ShipParams) -> Result<()> {
+    if params.replace {
+        return Err(anyhow!(
+            "--replace ships to [notion] parent_page only, it can't be used with [[destinations]] or [[locales]]"
+        ));
+    }
+    if params.parent_id.is_some() {
+        return Err(anyhow!(
+            "--parent-id replaces [notion] parent_page only, it can't be used with [[destinations]] or [[locales]]"
+        ));
+    }
+    let is_locales = !client.settings().locales.is_empty() || !params.locale.is_empty();
+    let kind = if is_locales { "locale(s)" } else { "destination(s)" };
+    println!("\n{}\n", BANNER);
+    before_ship(&client, &params).await?;
+    let client = client.with_stop(stop_on_ctrl_c());
+    let nc = client.clone();
+    let src = params.src.clone();
+    let since = params.since.clone();
+    let on_conflict = conflict_policy(&params);
+    let started = chrono::Utc::now();
+    let api = client.api_usage();
+    let h = if is_locales {
+        let names = params.locale.clone();
+        tokio::spawn(async move { commands::ship_locales(&nc, src, since, on_conflict, &names).await })
+    } else {
+        let names = params.destination.clone();
+        tokio::spawn(async move { commands::ship_destinations(&nc, src, since, on_conflict, &names).await })
+    };
+    let report = wait_for_ship(h, params.ci).await??;
+
+    let mut ship_report = ShipReport::new(&params.src, started);
+    for d in report.destinations.iter() {
+        ship_report.add(&d.name, d.parent_page_url.clone(), d.outcome.as_ref().map_err(|e| redact(e)));
+    }
+    let ship_report = ship_report.finish(chrono::Utc::now()).with_api_usage(client.api_usage() - api);
+    print_api_usage(&ship_report.api);
+    after_ship(&client, ship_report).await?;
+
+    // Every destination gets the same files, so their warnings are only printed once, while
+    // each locale has files of its own.
+    let summaries = report.destinations.iter().filter_map(|d| d.outcome.as_ref().ok());
+    for summary in summaries.take(if is_locales { usize::MAX } else { 1 }) {
+        for w in summary.warnings.iter() {
+            if params.ci {
+                println!("{}", redact(&github_annotation(w)));
+            } else {
+                println!("\n⚠️  {}", w);
+            }
+        }
+    }
+    if is_locales {
+        println!("\n\nShipped {} locale(s):", report.destinations.len());
+    } else {
+        println!("\n\nShipped to {} destination(s):", report.destinations.len());
+    }
+    for d in report.destinations.iter() {
+        let url = d.parent_page_url.as_deref().unwrap_or("-");
+        match &d.outcome {
+            Ok(summary) if summary.is_interrupted() => println!(
+                "  ✋ {:<16} stopped with {} file(s) left to ship",
+                d.name,
+                summary.interrupted.len()
+            ),
+            Ok(summary) => println!(
+                "  ✅ {:<16} {:>4} page(s) {:>4} warning(s)  {}",
+                d.name,
+                summary.pages.len(),
+                summary.warnings.len(),
+                url
+            ),
+            Err(e) => println!("  ❌ {:<16} {}", d.name, redact(e)),
+        }
+    }
+    if let Some(path) = &params.manifest {
+        // Destinations that failed have no pages to list.
+        let manifests: BTreeMap<&str, Manifest> = report
+            .destinations
+            .iter()
+            .filter_map(|d| Some((d.name.as_str(), Manifest::new(d.parent_page_url.clone(), d.outcome.as_ref().ok()?))))
+            .collect();
+        write_manifest(path, &manifests)?;
+    }
+    if let Some(summary) = report.destinations.iter().filter_map(|d| d.outcome.as_ref().ok()).find(|s| s.is_interrupted()) {
+        return Err(interrupted(summary));
     }
+    let failed = report.failed().count();
+    if failed > 0 {
+        return Err(anyhow!("{} of {} {} failed to ship", failed, report.destinations.len(), kind));
+    }
+    if params.ci {
+        for summary in report.destinations.iter().filter_map(|d| d.outcome.as_ref().ok()) {
+            commands::check_warnings(&client, summary)?;
+        }
+    }
+    println!("\nNotation pages shipped! ✅ \nSee you next time 🫡");
+    Ok(())
+}
+
+/// Turns true at the first Ctrl-C, for ships to stop at once the requests under way are done.
+/// A second Ctrl-C quits right away.
+fn stop_on_ctrl_c() -> watch::Receiver<bool> {
+    let (stop, stopped) = watch::channel(false);
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_err() {
+            return;
+        }
+        println!("\n\n✋ Stopping once the requests under way are done, Ctrl-C again to quit now...");
+        let _ = stop.send(true);
+        if tokio::signal::ctrl_c().await.is_ok() {
+            std::process::exit(130);
+        }
+    });
+    stopped
+}
+
+fn interrupted(summary: &ShipSummary) -> anyhow::Error {
+    let shipped: Vec<_> = summary.pages.iter().filter(|p| !summary.interrupted.contains(&p.path)).collect();
+    if !shipped.is_empty() {
+        println!("\nShipped before stopping:");
+        for p in shipped {
+            println!("  📄 {} -> {}", p.path, p.url);
+        }
+    }
+    println!("\nLeft to ship:");
+    for path in summary.interrupted.iter() {
+        println!("  ⏸️  {}", path);
+    }
+    anyhow!(
+        "ship stopped with {} file(s) left, the sync state has what was shipped",
+        summary.interrupted.len()
+    )
+}
+
+fn print_api_usage(api: &ApiUsage) {
+    println!(
+        "\n📡 {} Notion request(s), {} retried, {} rate limited, {:.1}s of backoff",
+        api.calls,
+        api.retries,
+        api.rate_limited,
+        api.backoff_ms as f64 / 1000.0
+    );
+}
+
+/// Writes the report of a ship and tells the `[notify]` webhook about it. A webhook that can't
+/// be reached doesn't fail the ship.
+async fn after_ship(client: &NotionClient, report: ShipReport) -> Result<()> {
+    if let Some(path) = commands::write_report(client, &report)? {
+        println!("\n🧾 Report written to {}", path.display());
+    }
+    if let Err(e) = notify(&client.settings().notify, &report).await {
+        println!("\n⚠️  {}", e);
+    }
+    Ok(())
+}
 
+async fn take_snapshot(workspace: &Workspace, path: &Path) -> Result<()> {
+    println!("📸📸 Taking a snapshot of {}...", workspace.parent_page_url);
+    let snapshot = commands::snapshot(workspace).await?;
+    snapshot.write(path)?;
+    for l in snapshot.left_out.iter() {
+        println!("⚠️  {} left out, it can't be restored", l);
+    }
+    println!(
+        "📸📸 Wrote {} page(s) and {} block(s) to {} ✅",
+        snapshot.page_count(),
+        snapshot.block_count(),
+        path.display()
+    );
     Ok(())
-}
\ No newline at end of file
+}
+
+fn conflict_policy(params: &ShipParams) -> ConflictPolicy {
+    if params.force {
+        ConflictPolicy::Overwrite
+    } else if params.skip_conflicts {
+        ConflictPolicy::Skip
+    } else {
+        ConflictPolicy::Fail
+    }
+}
+
+/// Lists the pages that could be the parent page and asks which one it is.
+fn pick_parent_page(ambiguous: &AmbiguousParentError) -> Result<String> {
+    println!("\n✋ {} pages match the parent page \"{}\":", ambiguous.candidates.len(), ambiguous.name);
+    for (i, c) in ambiguous.candidates.iter().enumerate() {
+        println!("   [{}] {}  {}", i + 1, c.breadcrumb(), c.url);
+    }
+    print!("Ship under which one [1-{}]? ", ambiguous.candidates.len());
+    io::stdout().flush()?;
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+    let candidate = line
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .and_then(|i| ambiguous.candidates.get(i.checked_sub(1)?))
+        .ok_or(anyhow!("aborted, no parent page was picked"))?;
+    println!("Pass --parent-id {} to skip this question next time", candidate.id);
+    Ok(candidate.id.clone())
+}
+
+/// Lists the pages edited in Notion since the last ship and asks whether to overwrite them.
+fn resolve_conflicts(conflicts: &ConflictError) -> Result<ConflictPolicy> {
+    println!("\n\n✋ These pages were edited in Notion since the last ship:");
+    for p in conflicts.pages.iter() {
+        println!("   {}", p);
+    }
+    print!("Overwrite them [o], skip them and ship the rest [s], or abort [a]? ");
+    io::stdout().flush()?;
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+    match line.trim().to_lowercase().as_str() {
+        "o" | "overwrite" => Ok(ConflictPolicy::Overwrite),
+        "s" | "skip" => Ok(ConflictPolicy::Skip),
+        _ => Err(anyhow!("aborted, nothing was shipped")),
+    }
+}
+
+/// Waits for a spawned ship, printing how long it has taken so far unless running in CI.
+async fn wait_for_ship<T>(mut h: JoinHandle<T>, ci: bool) -> Result<T> {
+    if ci {
+        println!("🚢🚢 Shipping pages...");
+        return Ok(h.await?);
+    }
+    let start = Instant::now();
+    loop {
+        tokio::select! {
+            r = &mut h => {
+                return Ok(r?);
+            }
+            _ = tokio::time::sleep(Duration::from_millis(500)) => {
+                print!("\r🚢🚢 Shipping pages, one moment... {}s", start.elapsed().as_secs());
+                io::stdout().flush().unwrap();
+            }
+        }
+    }
+}
+
+fn confirm_replace(plan: &ReplacePlan, ci: bool) {
+    if plan.is_tracked {
+        println!("♻️  After shipping, {} page(s) and block(s) of the previous ship will be archived.", plan.previous.len());
+    } else {
+        println!(
+            "♻️  No sync state of a previous ship was found, so after shipping everything that is on the parent page now ({} piece(s) of content) will be archived.",
+            plan.previous.len()
+        );
+    }
+    if !ci && !plan.previous.is_empty() {
+        println!("Press ENTER to proceed...");
+        let mut line = String::new();
+        let _ = io::stdin().read_line(&mut line).unwrap();
+    }
+}
+
+fn lint(client: &NotionClient, src: &str, ci: bool) -> Result<()> {
+    println!("🔎🔎 Linting...");
+    let report = commands::lint(client, src)?;
+    for i in report.issues.iter() {
+        print_diagnostic(i, ci);
+    }
+    if !report.issues.is_empty() {
+        return Err(anyhow!(
+            "lint found {} issue(s) in {} file(s)",
+            report.issues.len(),
+            report.files
+        ));
+    }
+    println!("✅ {} file(s) are clean\n", report.files);
+    Ok(())
+}
+
+async fn check_links(client: &NotionClient, src: &str, ci: bool) -> Result<()> {
+    println!("🔗🔗 Checking external links...");
+    let report = commands::check_links(client, src).await?;
+    for d in report.dead.iter() {
+        print_diagnostic(d, ci);
+    }
+    if !report.dead.is_empty() {
+        return Err(anyhow!("found {} dead link(s)", report.dead.len()));
+    }
+    println!("✅ {} link(s) are alive\n", report.checked);
+    Ok(())
+}
+
+async fn status(client: &NotionClient, src: &str) -> Result<()> {
+    let report = commands::status(client, src).await?;
+    let mut conflicts = 0;
+    for p in report.pages.iter() {
+        let local = match p.local {
+            None => "directory",
+            Some(LocalChange::Unchanged) => "unchanged",
+            Some(LocalChange::Modified) => "modified",
+            Some(LocalChange::Deleted) => "deleted",
+            Some(LocalChange::Unknown) => "not hashed",
+        };
+        let notion = match (p.is_in_trash, p.is_edited_in_notion) {
+            (true, _) => "in trash",
+            (false, Some(true)) => "edited in Notion",
+            (false, Some(false)) => "as shipped",
+            (false, None) => "unknown",
+        };
+        let marker = if p.is_conflict() {
+            conflicts += 1;
+            "⚠️ "
+        } else {
+            "  "
+        };
+        println!("{} {:<40} {:<10} {:<17} {}", marker, p.path, local, notion, p.url);
+    }
+    for f in report.new_files.iter() {
+        println!("   {:<40} new", f);
+    }
+    if let Some(refreshed_at) = &report.index_refreshed_at {
+        println!("\n🗂️  Edits in Notion as of the page index refreshed at {}, run `notation index refresh` to catch up", refreshed_at);
+    }
+    if conflicts > 0 {
+        println!("\n⚠️  {} page(s) were edited in Notion since the last ship, shipping them again overwrites those edits", conflicts);
+    } else {
+        println!("\n✅ No page was edited in Notion since the last ship");
+    }
+    Ok(())
+}
+
+fn print_diagnostic(d: &Diagnostic, ci: bool) {
+    if ci {
+        println!("{}", redact(&github_annotation(d)));
+    } else {
+        println!("⚠️  {}", d);
+    }
+}
+
+async fn check_code(client: &NotionClient, src: &str, ci: bool) -> Result<()> {
+    println!("🧪🧪 Checking code samples...");
+    let report = commands::check_code(client, src).await?;
+    for f in report.failures.iter() {
+        if ci {
+            println!("{}", redact(&github_annotation(f)));
+        } else {
+            println!("\n❌ {}", f);
+        }
+    }
+    if !report.failures.is_empty() {
+        return Err(anyhow!(
+            "{} of {} code sample(s) failed, nothing was shipped",
+            report.failures.len(),
+            report.checked
+        ));
+    }
+    println!("✅ {} code sample(s) passed\n", report.checked);
+    Ok(())
+}
+
+async fn check() -> Result<()> {
+    let report = commands::check().await;
+    for c in report.checks.iter() {
+        let marker = match c.outcome {
+            CheckOutcome::Pass => "✅",
+            CheckOutcome::Warn => "⚠️ ",
+            CheckOutcome::Fail => "❌",
+        };
+        println!("{} {}: {}", marker, c.name, redact(&c.detail));
+    }
+    if !report.is_ok() {
+        return Err(anyhow!("configuration check failed"));
+    }
+    println!("\nConfiguration looks good 🫡");
+    Ok(())
+}
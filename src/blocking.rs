@@ -0,0 +1,202 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use tokio::runtime::{Builder, Runtime};
+
+use crate::markdown::directive::DirectiveBuilder;
+use crate::markdown::parse::NotationParseResult;
+use crate::notion::block::{AppendBlockRequest, AppendBlockRequestChild};
+use crate::notion::client::{NotionClient, ShipOptions};
+use crate::notion::database::ChildDatabaseParent;
+use crate::notion::page::{GetPageContentResponse, PageContentResult, PageContentType, PageResponse};
+use crate::notion::search::{SearchRequest, SearchResultItem};
+use crate::notion::summary::ShipSummary;
+use crate::notion::transform::BlockTransform;
+use crate::notion::user::UserResponse;
+use crate::settings::notation::NotationSettings;
+
+fn runtime() -> Result<Runtime> {
+    Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| anyhow!("failed to start the runtime of the blocking client: {}", e))
+}
+
+/// Parses a documentation file, like [`crate::markdown::parse::parse_file`].
+pub fn parse_file(path: &Path) -> Result<NotationParseResult> {
+    runtime()?.block_on(crate::markdown::parse::parse_file(path))
+}
+
+/// A synchronous facade over [`NotionClient`], for build scripts and other callers without an
+/// async runtime. Every call blocks on a runtime owned by the client, so none of them may be
+/// made from inside an async context.
+#[derive(Clone)]
+pub struct BlockingNotionClient {
+    inner: NotionClient,
+    runtime: Arc<Runtime>,
+}
+
+impl BlockingNotionClient {
+    pub fn new() -> Result<Self> {
+        BlockingNotionClient::from_client(NotionClient::new()?)
+    }
+
+    pub fn from_settings(settings: NotationSettings) -> Result<Self> {
+        BlockingNotionClient::from_client(NotionClient::from_settings(settings)?)
+    }
+
+    pub fn from_client(inner: NotionClient) -> Result<Self> {
+        Ok(BlockingNotionClient {
+            inner,
+            runtime: Arc::new(runtime()?),
+        })
+    }
+
+    pub fn with_transform(mut self, transform: impl BlockTransform + 'static) -> Self {
+        self.inner = self.inner.with_transform(transform);
+        self
+    }
+
+    pub fn with_directive(mut self, name: &str, builder: impl DirectiveBuilder + 'static) -> Self {
+        self.inner = self.inner.with_directive(name, builder);
+        self
+    }
+
+    pub fn with_sections(mut self, sections: Vec<String>) -> Self {
+        self.inner = self.inner.with_sections(sections);
+        self
+    }
+
+    /// The async client this one wraps.
+    pub fn inner(&self) -> &NotionClient {
+        &self.inner
+    }
+
+    pub fn parent_page_name(&self) -> String {
+        self.inner.parent_page_name()
+    }
+
+    pub fn settings(&self) -> &NotationSettings {
+        self.inner.settings()
+    }
+
+    pub fn get_current_user(&self) -> Result<UserResponse> {
+        self.runtime.block_on(self.inner.get_current_user())
+    }
+
+    pub fn create_page_by_parent_name(
+        &self,
+        parent_name: String,
+        page_name: String,
+        emoji: Option<String>,
+    ) -> Result<String> {
+        self.runtime
+            .block_on(self.inner.create_page_by_parent_name(parent_name, page_name, emoji))
+    }
+
+    pub fn create_page_by_parent_id(
+        &self,
+        parent_id: String,
+        page_name: String,
+        emoji: Option<String>,
+    ) -> Result<String> {
+        self.runtime
+            .block_on(self.inner.create_page_by_parent_id(parent_id, page_name, emoji))
+    }
+
+    pub fn get_parent_id_by_name(&self, parent_name: String) -> Result<String> {
+        self.runtime.block_on(self.inner.get_parent_id_by_name(parent_name))
+    }
+
+    pub fn parent_page_id(&self) -> Result<String> {
+        self.runtime.block_on(self.inner.parent_page_id())
+    }
+
+    pub fn delete(&self, resource_id: String, resource_type: &PageContentType) -> Result<()> {
+        self.runtime.block_on(self.inner.delete(resource_id, resource_type))
+    }
+
+    pub fn append_block(
+        &self,
+        page_or_block_id: String,
+        request: &AppendBlockRequest,
+    ) -> Result<GetPageContentResponse> {
+        self.runtime.block_on(self.inner.append_block(page_or_block_id, request))
+    }
+
+    pub fn create_database(&self, parent_id: &str, database: &ChildDatabaseParent) -> Result<String> {
+        self.runtime.block_on(self.inner.create_database(parent_id, database))
+    }
+
+    pub fn upload_file(&self, name: &str, content_type: &str, contents: Vec<u8>) -> Result<String> {
+        self.runtime.block_on(self.inner.upload_file(name, content_type, contents))
+    }
+
+    pub fn find_page_by_name(&self, page_name: String) -> Result<Vec<SearchResultItem>> {
+        self.runtime.block_on(self.inner.find_page_by_name(page_name))
+    }
+
+    /// Every result of `search_request`, see [`NotionClient::search_iter`].
+    pub fn search(&self, search_request: SearchRequest) -> Result<Vec<Value>> {
+        self.runtime.block_on(self.inner.search_iter(search_request).collect())
+    }
+
+    pub fn get_page_content_by_id(&self, page_id: String) -> Result<GetPageContentResponse> {
+        self.runtime.block_on(self.inner.get_page_content_by_id(page_id))
+    }
+
+    pub fn get_all_page_content_by_id(&self, page_id: String) -> Result<Vec<PageContentResult>> {
+        self.runtime.block_on(self.inner.get_all_page_content_by_id(page_id))
+    }
+
+    pub fn get_page_blocks(&self, page_id: &str) -> Result<Vec<AppendBlockRequestChild>> {
+        self.runtime.block_on(self.inner.get_page_blocks(page_id))
+    }
+
+    pub fn get_page(&self, page_id: &str) -> Result<PageResponse> {
+        self.runtime.block_on(self.inner.get_page(page_id))
+    }
+
+    pub fn update_page(&self, page_id: String, title: String, emoji: Option<String>) -> Result<()> {
+        self.runtime.block_on(self.inner.update_page(page_id, title, emoji))
+    }
+
+    pub fn replace_page_content(&self, page_id: String, request: AppendBlockRequest) -> Result<()> {
+        self.runtime.block_on(self.inner.replace_page_content(page_id, request))
+    }
+
+    pub fn clear(&self) -> Result<()> {
+        self.runtime.block_on(self.inner.clear())
+    }
+
+    pub fn create_pages(&self, dir: String, is_simulate: bool) -> Result<ShipSummary> {
+        self.runtime.block_on(self.inner.create_pages(dir, is_simulate))
+    }
+
+    pub fn ship(&self, dir: String, options: &ShipOptions) -> Result<ShipSummary> {
+        self.runtime.block_on(self.inner.ship(dir, options))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::blocking::parse_file;
+    use crate::markdown::parse::ConversionOptions;
+
+    #[test]
+    pub fn test_blocking_parse_file() {
+        let dir = std::env::temp_dir().join(format!("notation-blocking-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("page.md"), "# Page\n\nHello").unwrap();
+        let page = parse_file(&dir.join("page.md")).unwrap();
+        let conversion = page
+            .to_notion("page", &HashMap::new(), &ConversionOptions::default())
+            .unwrap();
+        assert_eq!(conversion.request.children.len(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,84 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+
+use anyhow::Result;
+
+use crate::markdown::diagnostic::{Diagnostic, Severity};
+use crate::notion::summary::ShipSummary;
+
+pub const GITHUB_OUTPUT_ENV: &str = "GITHUB_OUTPUT";
+const OUTPUT_DELIMITER: &str = "NOTATION_OUTPUT_EOF";
+
+/// Formats a diagnostic as a GitHub Actions workflow command, so it shows up as an annotation
+/// on the offending file.
+pub fn github_annotation(d: &Diagnostic) -> String {
+    let command = match d.severity {
+        Severity::Warning => "warning",
+        Severity::Error => "error",
+    };
+    let mut properties = vec![format!("file={}", escape_property(&d.file))];
+    if let Some(line) = d.line {
+        properties.push(format!("line={}", line));
+    }
+    if let Some(column) = d.column {
+        properties.push(format!("col={}", column));
+    }
+    format!(
+        "::{} {}::{}",
+        command,
+        properties.join(","),
+        escape_data(&d.message)
+    )
+}
+
+fn escape_data(s: &str) -> String {
+    s.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+fn escape_property(s: &str) -> String {
+    escape_data(s).replace(':', "%3A").replace(',', "%2C")
+}
+
+/// Writes the parent page URL and the shipped page URLs as step outputs when running inside
+/// GitHub Actions. Returns whether the outputs were written.
+pub fn write_step_outputs(parent_url: &str, summary: &ShipSummary) -> Result<bool> {
+    let output_path = match std::env::var(GITHUB_OUTPUT_ENV) {
+        Ok(p) => p,
+        Err(_) => return Ok(false),
+    };
+    let mut f = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(output_path)?;
+    writeln!(f, "parent_url={}", parent_url)?;
+    writeln!(f, "page_count={}", summary.pages.len())?;
+    writeln!(f, "page_urls<<{}", OUTPUT_DELIMITER)?;
+    for p in summary.pages.iter() {
+        writeln!(f, "{}", p.url)?;
+    }
+    writeln!(f, "{}", OUTPUT_DELIMITER)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ci::github_annotation;
+    use crate::markdown::diagnostic::{Diagnostic, Severity};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_github_annotation() {
+        let d = Diagnostic {
+            severity: Severity::Error,
+            file: "docs/a,b.md".to_string(),
+            line: Some(3),
+            column: Some(7),
+            message: "invalid link url: ./missing.md\n50% sure".to_string(),
+        };
+        assert_eq!(
+            github_annotation(&d),
+            "::error file=docs/a%2Cb.md,line=3,col=7::invalid link url: ./missing.md%0A50%25 sure"
+        );
+        let w = Diagnostic::warning("docs/a.md", None, "heads up".to_string());
+        assert_eq!(github_annotation(&w), "::warning file=docs/a.md::heads up");
+    }
+}
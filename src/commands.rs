@@ -0,0 +1,614 @@
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+
+use crate::markdown::adr::write_adrs;
+use crate::markdown::changelog::write_changelog;
+use crate::markdown::links::LinkCheckReport;
+use crate::markdown::rustdoc::ApiReference;
+use crate::markdown::lint::{LintReport, TextLinter};
+use crate::markdown::samples::CodeCheckReport;
+use crate::markdown::template::{page_path, template_values, Template};
+use crate::notion::client::{NotionClient, ShipOptions};
+use crate::notion::compare::{plan_pages, read_export, CompareReport};
+use crate::notion::export::ExportPlan;
+use crate::notion::import::read_blocks;
+use crate::notion::page::{PageContentResult, PageContentType};
+use crate::notion::parent::parent_path;
+use crate::notion::report::ShipReport;
+use crate::notion::snapshot::{RestoreReport, Snapshot};
+use crate::notion::tree::{find_by_titles, ClearPlan};
+use crate::notion::summary::ShipSummary;
+use crate::roundtrip::RoundtripReport;
+use crate::settings::check::{check_config, CheckOutcome, ConfigCheck};
+use crate::settings::directory::SourceTree;
+use crate::settings::notation::TemplateSettings;
+use crate::sync::merge::MergeReport;
+use crate::sync::state::SyncState;
+use crate::sync::status::{ConflictPolicy, StatusReport};
+use crate::sync::trash::TrashedPage;
+
+/// Stands in for the id of the parent page in exported requests.
+pub const EXPORT_PARENT_PAGE_ID: &str = "PARENT_PAGE_ID";
+
+/// The client, and the parent page hosting the Notation workspace.
+#[derive(Clone)]
+pub struct Workspace {
+    pub client: NotionClient,
+    pub parent_page_id: String,
+    pub parent_page_url: String,
+}
+
+impl Workspace {
+    /// Connects with the configured settings and finds the parent page.
+    pub async fn connect() -> Result<Self> {
+        Workspace::from_client(NotionClient::new()?).await
+    }
+
+    pub async fn from_client(client: NotionClient) -> Result<Self> {
+        let parent_page_id = client.parent_page_id().await?;
+        let (title, _) = parent_path(&client.parent_page_name());
+        let parsed_page_name = title.replace(" ", "-").to_lowercase();
+        let parent_page_url = format!(
+            "https://www.notion.so/{}-{}",
+            parsed_page_name,
+            parent_page_id.replace("-", "")
+        );
+        Ok(Workspace {
+            client,
+            parent_page_id,
+            parent_page_url,
+        })
+    }
+
+    /// The blocks and pages directly on the parent page.
+    pub async fn content(&self) -> Result<Vec<PageContentResult>> {
+        self.client
+            .get_all_page_content_by_id(self.parent_page_id.clone())
+            .await
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ClearReport {
+    /// How many blocks and pages were archived.
+    pub removed: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct ImportReport {
+    pub appended: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct ConfigReport {
+    pub checks: Vec<ConfigCheck>,
+}
+
+impl ConfigReport {
+    pub fn is_ok(&self) -> bool {
+        !self.checks.iter().any(|c| c.outcome == CheckOutcome::Fail)
+    }
+}
+
+/// Everything a clear would archive: what is on the parent page and on every page under it.
+pub async fn clear_plan(workspace: &Workspace) -> Result<ClearPlan> {
+    ClearPlan::read(&workspace.client, &workspace.parent_page_id).await
+}
+
+/// Everything a clear of `path`, a file or directory relative to `src`, would archive: the
+/// pages the sync state of `src` tracks under it, or, without any, the page found by following
+/// the names in `path` down from the parent page.
+pub async fn clear_path_plan(workspace: &Workspace, src: &str, path: &str) -> Result<ClearPlan> {
+    let key = SyncState::key(Path::new(path));
+    let state = load_state(workspace, src)?;
+    let mut pages: Vec<(String, String)> = state
+        .map(|(_, s)| s.tracked_under(&key))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(_, p)| (p.page_id, p.title))
+        .collect();
+    if pages.is_empty() {
+        let titles: Vec<String> = key.split('/').filter(|t| !t.is_empty()).map(|t| t.to_string()).collect();
+        let found = find_by_titles(&workspace.client, &workspace.parent_page_id, &titles).await?;
+        pages = found.into_iter().collect();
+    }
+    if pages.is_empty() {
+        return Err(anyhow!("no page tracked, or titled, for {} under the parent page", path));
+    }
+    ClearPlan::read_pages(&workspace.client, &pages).await
+}
+
+/// Archives everything in `plan` like [`clear`], and stops tracking the pages of `path`, all
+/// of them when empty, in the sync state of `src`, so the next ship creates them again. The
+/// state remembers them as archived, for `notation trash` to find.
+pub async fn clear_path(workspace: &Workspace, src: &str, path: &str, plan: &ClearPlan) -> Result<ClearReport> {
+    let report = clear(workspace, plan).await?;
+    if let Some((state_path, mut state)) = load_state(workspace, src)? {
+        state.archive_under(&SyncState::key(Path::new(path)));
+        state.save(&state_path)?;
+    }
+    Ok(report)
+}
+
+/// The sync state of `src`, when it was shipped to the workspace's parent page.
+fn load_state(workspace: &Workspace, src: &str) -> Result<Option<(PathBuf, SyncState)>> {
+    let settings = workspace.client.settings();
+    let tree = SourceTree::load(src, settings.content.clone())?;
+    let state_path = SyncState::path(&tree.root, settings.sync.state_file.as_deref());
+    let state = SyncState::load(&state_path)?.filter(|s| s.parent_page_id == workspace.parent_page_id);
+    Ok(state.map(|s| (state_path, s)))
+}
+
+/// Archives everything in `plan`, by archiving what is at the top of it.
+pub async fn clear(workspace: &Workspace, plan: &ClearPlan) -> Result<ClearReport> {
+    Ok(ClearReport {
+        removed: plan.archive(&workspace.client).await?,
+    })
+}
+
+/// Reads everything under the parent page, blocks and pages, into a snapshot.
+pub async fn snapshot(workspace: &Workspace) -> Result<Snapshot> {
+    crate::notion::snapshot::take(&workspace.client, &workspace.parent_page_id, &workspace.client.parent_page_name()).await
+}
+
+/// Recreates the blocks and pages of a snapshot on the parent page. With `src`, the sync
+/// state of that directory is pointed at the recreated pages.
+pub async fn restore(workspace: &Workspace, snapshot: &Snapshot, src: Option<&str>) -> Result<RestoreReport> {
+    let report = crate::notion::snapshot::restore(&workspace.client, snapshot, &workspace.parent_page_id).await?;
+    if let Some((state_path, mut state)) = src.map(|src| load_state(workspace, src)).transpose()?.flatten() {
+        state.replace_page_ids(&report.page_ids);
+        state.save(&state_path)?;
+    }
+    Ok(report)
+}
+
+/// Ships the documentation under `src` to the parent page. With `since`, only what changed
+/// since that git ref is shipped, and `on_conflict` decides what happens to pages edited in
+/// Notion since the last ship.
+pub async fn ship(
+    workspace: &Workspace,
+    src: String,
+    since: Option<String>,
+    on_conflict: ConflictPolicy,
+) -> Result<ShipSummary> {
+    let options = ShipOptions {
+        since,
+        on_conflict,
+        parent_page_id: Some(workspace.parent_page_id.clone()),
+        ..Default::default()
+    };
+    workspace.client.ship(src, &options).await
+}
+
+/// How a ship to one of the `[[destinations]]`, or of one of the `[[locales]]`, went.
+#[derive(Clone, Debug)]
+pub struct DestinationShip {
+    pub name: String,
+    /// The URL of the destination's parent page, when it was found.
+    pub parent_page_url: Option<String>,
+    /// The summary of the ship, or why it failed.
+    pub outcome: std::result::Result<ShipSummary, String>,
+}
+
+/// The ships of a fan-out, in the order of `[[destinations]]` or `[[locales]]`.
+#[derive(Clone, Debug, Default)]
+pub struct FanOutReport {
+    pub destinations: Vec<DestinationShip>,
+}
+
+impl FanOutReport {
+    pub fn failed(&self) -> impl Iterator<Item = &DestinationShip> {
+        self.destinations.iter().filter(|d| d.outcome.is_err())
+    }
+}
+
+/// Ships `src` to each of the `[[destinations]]` named in `names` (all of them when empty),
+/// one after another. A destination failing doesn't stop the others from being shipped, but
+/// a ship stopped with [`NotionClient::with_stop`] leaves the rest out.
+pub async fn ship_destinations(
+    client: &NotionClient,
+    src: String,
+    since: Option<String>,
+    on_conflict: ConflictPolicy,
+    names: &[String],
+) -> Result<FanOutReport> {
+    let settings = client.settings();
+    let destinations = settings.destinations(names)?;
+    if destinations.is_empty() {
+        return Err(anyhow!("no destinations to ship to, add some under [[destinations]]"));
+    }
+    let ships = destinations
+        .into_iter()
+        .map(|d| Ok((d.name.clone(), client.for_destination(d)?, src.clone())))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(fan_out(ships, since, on_conflict).await)
+}
+
+/// Ships the subdirectory of `src` of each of the `[[locales]]` named in `names` (all of them
+/// when empty) to the locale's parent page, one after another, like [`ship_destinations`].
+/// Links from one locale to another ship as text.
+pub async fn ship_locales(
+    client: &NotionClient,
+    src: String,
+    since: Option<String>,
+    on_conflict: ConflictPolicy,
+    names: &[String],
+) -> Result<FanOutReport> {
+    let settings = client.settings();
+    if !settings.destinations.is_empty() {
+        return Err(anyhow!("[[locales]] have parent pages of their own, they can't be shipped to [[destinations]]"));
+    }
+    let locales = settings.locales(names)?;
+    if locales.is_empty() {
+        return Err(anyhow!("no locales to ship, add some under [[locales]]"));
+    }
+    let ships = locales
+        .into_iter()
+        .map(|l| Ok((l.name.clone(), client.for_locale(l)?, l.src(&src))))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(fan_out(ships, since, on_conflict).await)
+}
+
+/// Ships each `(name, client, src)` in turn. A ship failing doesn't stop the others, but a
+/// ship stopped with [`NotionClient::with_stop`] leaves the rest out.
+async fn fan_out(
+    ships: Vec<(String, NotionClient, String)>,
+    since: Option<String>,
+    on_conflict: ConflictPolicy,
+) -> FanOutReport {
+    let mut report = FanOutReport::default();
+    for (name, client, src) in ships {
+        let mut parent_page_url = None;
+        let outcome = async {
+            let workspace = Workspace::from_client(client).await?;
+            parent_page_url = Some(workspace.parent_page_url.clone());
+            ship(&workspace, src, since.clone(), on_conflict).await
+        }
+        .await;
+        let is_interrupted = matches!(&outcome, Ok(s) if s.is_interrupted());
+        report.destinations.push(DestinationShip {
+            name,
+            parent_page_url,
+            outcome: outcome.map_err(|e| e.to_string()),
+        });
+        if is_interrupted {
+            break;
+        }
+    }
+    report
+}
+
+/// Runs the configured `[code_check]` commands on the code samples under `src`.
+pub async fn check_code(client: &NotionClient, src: &str) -> Result<CodeCheckReport> {
+    crate::markdown::samples::check_code(client.settings(), src).await
+}
+
+/// Lints the prose under `src` with the `[lint]` rules.
+pub fn lint(client: &NotionClient, src: &str) -> Result<LintReport> {
+    let settings = client.settings();
+    TextLinter::from_settings(&settings.lint).lint_tree(settings, src)
+}
+
+/// Requests the external links under `src` and reports the dead ones.
+pub async fn check_links(client: &NotionClient, src: &str) -> Result<LinkCheckReport> {
+    crate::markdown::links::check_links(client.settings(), src).await
+}
+
+/// What a replacing ship archives once the new pages are in place.
+#[derive(Clone, Debug)]
+pub struct ReplacePlan {
+    /// The pages and blocks on the parent page left by the previous ship.
+    pub previous: Vec<PageContentResult>,
+    /// Whether `previous` comes from the sync state. Without one, everything on the parent
+    /// page is replaced, like `clear` does.
+    pub is_tracked: bool,
+}
+
+/// Finds what a ship of `src` with [`replace`] would archive: the blocks on the parent page and
+/// the pages the previous ship created there. Pages added by hand are kept when the sync state
+/// of the previous ship is around.
+pub async fn replace_plan(workspace: &Workspace, src: &str) -> Result<ReplacePlan> {
+    let settings = workspace.client.settings();
+    let tree = SourceTree::load(src, settings.content.clone())?;
+    let state = SyncState::load(&SyncState::path(&tree.root, settings.sync.state_file.as_deref()))?
+        .filter(|s| s.parent_page_id == workspace.parent_page_id);
+    let content = workspace.content().await?;
+    let previous = match &state {
+        Some(state) => {
+            let tracked: HashSet<&String> = state
+                .pages
+                .values()
+                .chain(state.directories.values())
+                .map(|p| &p.page_id)
+                .collect();
+            content
+                .into_iter()
+                .filter(|c| !matches!(c.content_type, PageContentType::ChildPage) || tracked.contains(&c.id))
+                .collect()
+        }
+        None => content,
+    };
+    Ok(ReplacePlan {
+        previous,
+        is_tracked: state.is_some(),
+    })
+}
+
+/// Ships `src` as new pages, then archives what `plan` holds. The old pages are only archived
+/// once the ship went through, so a failed ship leaves them in place.
+pub async fn replace(workspace: &Workspace, src: String, plan: ReplacePlan) -> Result<ShipSummary> {
+    let summary = ship(workspace, src, None, ConflictPolicy::default()).await?;
+    // Half a new ship doesn't replace the previous one.
+    if summary.is_interrupted() {
+        return Ok(summary);
+    }
+    for c in plan.previous.iter() {
+        workspace.client.delete(c.id.clone(), &c.content_type).await?;
+    }
+    Ok(summary)
+}
+
+/// Fails when `summary` has warnings and the `[ci]` settings don't allow them.
+pub fn check_warnings(client: &NotionClient, summary: &ShipSummary) -> Result<()> {
+    if client.settings().ci.fail_on_warnings && !summary.warnings.is_empty() {
+        return Err(anyhow!(
+            "conversion raised {} warning(s), set fail_on_warnings = false under [ci] to allow them",
+            summary.warnings.len()
+        ));
+    }
+    Ok(())
+}
+
+/// Writes `report` to `[report] path`, unless `[report] enabled` is off, returning where.
+pub fn write_report(client: &NotionClient, report: &ShipReport) -> Result<Option<PathBuf>> {
+    let settings = &client.settings().report;
+    if !settings.enabled {
+        return Ok(None);
+    }
+    let path = PathBuf::from(&settings.path);
+    report.write(&path)?;
+    Ok(Some(path))
+}
+
+/// Writes the requests a ship of `src` would send as JSON under `out`, without calling Notion.
+pub async fn export(client: &NotionClient, src: String, out: PathBuf) -> Result<ShipSummary> {
+    let options = ShipOptions {
+        parent_page_id: Some(EXPORT_PARENT_PAGE_ID.to_string()),
+        ephemeral: true,
+        export_dir: Some(out),
+        ..Default::default()
+    };
+    client.ship(src, &options).await
+}
+
+/// Converts `src` without calling Notion, returning the pages a ship would create under
+/// [`EXPORT_PARENT_PAGE_ID`].
+pub async fn plan(client: &NotionClient, src: String) -> Result<(ShipSummary, ExportPlan)> {
+    client.plan(src, EXPORT_PARENT_PAGE_ID).await
+}
+
+/// Converts `src` without calling Notion and compares the requests with those of an earlier
+/// export to `previous`, block by block.
+pub async fn compare(client: &NotionClient, src: String, previous: &Path) -> Result<(ShipSummary, CompareReport)> {
+    let previous = read_export(previous)?;
+    let (summary, plan) = client.plan(src, EXPORT_PARENT_PAGE_ID).await?;
+    Ok((summary, crate::notion::compare::compare(previous, plan_pages(&plan)?)))
+}
+
+/// Serves the pages a ship of `src` would create as HTML on `port` of localhost, converting
+/// again on every request. Runs until the process is stopped.
+pub async fn preview(client: NotionClient, src: String, port: u16) -> Result<()> {
+    crate::preview::serve(client, src, SocketAddr::from(([127, 0, 0, 1], port))).await
+}
+
+/// Serves the HTTP API to ship `src` to the parent page, and ask how it stands, on `addr`.
+/// Runs until the process is stopped.
+pub async fn serve(workspace: Workspace, src: String, addr: SocketAddr) -> Result<()> {
+    crate::serve::serve(workspace, src, addr).await
+}
+
+/// Compares the pages of the last ship of `src` with the files and with Notion, flagging
+/// pages edited in Notion that the next ship would overwrite.
+pub async fn status(client: &NotionClient, src: &str) -> Result<StatusReport> {
+    crate::sync::status::status(client, src).await
+}
+
+/// The pages of `src` in the Notion trash, the latest first.
+pub async fn trash(client: &NotionClient, src: &str) -> Result<Vec<TrashedPage>> {
+    crate::sync::trash::list(client, src).await
+}
+
+/// Takes a page out of the Notion trash, tracking it again in the sync state of `src` if it
+/// was archived by a ship or a clear. Returns whether it was.
+pub async fn restore_from_trash(client: &NotionClient, src: Option<&str>, page_id: &str) -> Result<bool> {
+    crate::sync::trash::restore(client, src, page_id).await
+}
+
+/// Merges the edits made in Notion since the last ship of `src` into its markdown files,
+/// writing conflict markers where a block changed on both sides. Experimental.
+pub async fn merge(client: &NotionClient, src: &str) -> Result<MergeReport> {
+    crate::sync::merge::merge_tree(client, src).await
+}
+
+/// Appends the blocks of a JSON file to a page.
+pub async fn import(client: &NotionClient, page_id: String, blocks: &Path) -> Result<ImportReport> {
+    let request = read_blocks(blocks)?;
+    let appended = request.children.len();
+    client.append_content(&page_id, request).await?;
+    Ok(ImportReport { appended })
+}
+
+/// Writes the API reference of the rustdoc JSON at `json` as markdown under `out`, then ships
+/// `out` to the parent page. The pages are generated, so edits made to them in Notion are
+/// overwritten.
+pub async fn api_reference(workspace: &Workspace, json: &Path, out: &Path) -> Result<ShipSummary> {
+    ApiReference::load(json)?.write(out)?;
+    ship_generated(workspace, out).await
+}
+
+/// Writes a page per endpoint of the OpenAPI spec at `spec` as markdown under `out`, then ships
+/// `out` to the parent page, overwriting edits made to them in Notion like [`api_reference`].
+pub async fn openapi(workspace: &Workspace, spec: &Path, out: &Path) -> Result<ShipSummary> {
+    ApiReference::load_openapi(spec)?.write(out)?;
+    ship_generated(workspace, out).await
+}
+
+/// Writes a page per release of the changelog at `changelog` into `out`, then ships `out` to
+/// the parent page, or as rows of the `[database]`. Returns how many releases there are.
+pub async fn changelog(workspace: &Workspace, changelog: &Path, out: &Path) -> Result<(usize, ShipSummary)> {
+    let releases = write_changelog(changelog, out)?;
+    let summary = ship_generated(workspace, out).await?;
+    Ok((releases, summary))
+}
+
+/// Writes a page per decision record of `dir` into `out`, with their index as its intro, then
+/// ships `out` to the parent page, or as rows of the `[database]`. Returns how many records
+/// there are.
+pub async fn adr(workspace: &Workspace, dir: &Path, out: &Path) -> Result<(usize, ShipSummary)> {
+    let records = write_adrs(dir, out)?;
+    let summary = ship_generated(workspace, out).await?;
+    Ok((records, summary))
+}
+
+/// Every file of `src`, for a ship to write them all over the pages of its last ship to the
+/// parent page. `None` when it was never shipped there, and a plain ship creates them.
+pub fn all_files(workspace: &Workspace, src: &str) -> Result<Option<HashSet<PathBuf>>> {
+    if load_state(workspace, src)?.is_none() {
+        return Ok(None);
+    }
+    let tree = SourceTree::load(src, workspace.client.settings().content.clone())?;
+    Ok(Some(tree.files.into_iter().collect()))
+}
+
+/// Ships the pages generated under `out` to the parent page. Once they were shipped there, the
+/// next runs ship every generated file over its page, since a ship without a state to start
+/// from would create them all again next to the old ones.
+async fn ship_generated(workspace: &Workspace, out: &Path) -> Result<ShipSummary> {
+    let src = out.display().to_string();
+    let options = ShipOptions {
+        only: all_files(workspace, &src)?,
+        on_conflict: ConflictPolicy::Overwrite,
+        parent_page_id: Some(workspace.parent_page_id.clone()),
+        ..Default::default()
+    };
+    workspace.client.ship(src, &options).await
+}
+
+/// Writes a page titled `title` under `dir` from the template `template`, filled with `vars`
+/// over the default values. Returns where it was written. An existing file is left alone.
+pub fn new_page(settings: &TemplateSettings, template: &str, title: &str, vars: &[(String, String)], dir: &Path) -> Result<PathBuf> {
+    let template = Template::find(template, settings)?;
+    let markdown = template.render(&template_values(title, settings, vars))?;
+    let path = page_path(dir, title);
+    if path.exists() {
+        return Err(anyhow!("{} already exists, give the page another --title", path.display()));
+    }
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(&path, markdown)?;
+    Ok(path)
+}
+
+/// Ships only the page of `path`, a file under `src`, leaving the rest of `src` as it is in
+/// Notion. `src` has to have been shipped to the workspace's parent page before, for the page
+/// to find its place among the others.
+pub async fn ship_file(workspace: &Workspace, src: &str, path: &Path) -> Result<ShipSummary> {
+    let relative = path
+        .strip_prefix(src)
+        .map_err(|_| anyhow!("{} is not under {}", path.display(), src))?;
+    if load_state(workspace, src)?.is_none() {
+        return Err(anyhow!(
+            "new --ship only ships the new page, and {} was never shipped to this parent page: run `notation ship --src {}` to ship it all, the new page with it",
+            src,
+            src
+        ));
+    }
+    let options = ShipOptions {
+        only: Some(HashSet::from([relative.to_path_buf()])),
+        parent_page_id: Some(workspace.parent_page_id.clone()),
+        ..Default::default()
+    };
+    workspace.client.ship(src.to_string(), &options).await
+}
+
+/// Ships `src` to a scratch page and reports what the conversion lost.
+pub async fn roundtrip(workspace: &Workspace, src: String, keep: bool) -> Result<RoundtripReport> {
+    crate::roundtrip::roundtrip(&workspace.client, src, keep).await
+}
+
+/// Validates the configuration and the Notion connection.
+pub async fn check() -> ConfigReport {
+    ConfigReport {
+        checks: check_config().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::commands::{changelog, new_page, ship_file, Workspace};
+    use crate::notion::client::ShipOptions;
+    use crate::notion::fake::FakeNotion;
+    use crate::settings::notation::TemplateSettings;
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_new_page_ship() {
+        let dir = std::env::temp_dir().join(format!("notation-new-ship-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let src = dir.display().to_string();
+        let fake = FakeNotion::start().await;
+        let docs = fake.add_page(None, "Docs");
+        let workspace = Workspace {
+            client: fake.client(&docs, json!({})),
+            parent_page_id: docs.clone(),
+            parent_page_url: String::new(),
+        };
+        let templates = TemplateSettings::default();
+
+        let kickoff = new_page(&templates, "meeting-notes", "Kickoff", &[], &dir).unwrap();
+        let error = ship_file(&workspace, &src, &kickoff).await.unwrap_err();
+        assert!(error.to_string().contains(&format!("run `notation ship --src {}`", src)));
+        assert!(fake.page_titles(&docs).is_empty());
+
+        workspace.client.ship(src.clone(), &ShipOptions::default()).await.unwrap();
+        let retro = new_page(&templates, "meeting-notes", "Retro", &[], &dir).unwrap();
+        let summary = ship_file(&workspace, &src, &retro).await.unwrap();
+        assert_eq!(summary.pages.len(), 1);
+        assert_eq!(fake.page_titles(&docs).len(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_changelog_reruns() {
+        let dir = std::env::temp_dir().join(format!("notation-changelog-reruns-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("CHANGELOG.md");
+        let out = dir.join("releases");
+        std::fs::write(&path, "# Changelog\n\n## [1.1.0] - 2024-05-01\n\n### Fixed\n- A crash\n\n## [1.0.0] - 2024-01-15\n\n### Added\n- Everything\n").unwrap();
+        let fake = FakeNotion::start().await;
+        let docs = fake.add_page(None, "Docs");
+        let workspace = Workspace {
+            client: fake.client(&docs, json!({})),
+            parent_page_id: docs.clone(),
+            parent_page_url: String::new(),
+        };
+
+        changelog(&workspace, &path, &out).await.unwrap();
+        let titles = fake.page_titles(&docs);
+        assert_eq!(titles.len(), 2);
+
+        // Running it again rewrites the same pages, a new release adds its own.
+        let (releases, summary) = changelog(&workspace, &path, &out).await.unwrap();
+        assert_eq!(releases, 2);
+        assert_eq!(summary.pages.len(), 2);
+        assert_eq!(fake.page_titles(&docs), titles);
+        let added = std::fs::read_to_string(&path).unwrap().replace("# Changelog\n\n", "# Changelog\n\n## [1.2.0] - 2024-06-01\n\n### Added\n- More\n\n");
+        std::fs::write(&path, added).unwrap();
+        changelog(&workspace, &path, &out).await.unwrap();
+        assert_eq!(fake.page_titles(&docs).len(), 3);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
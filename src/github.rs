@@ -0,0 +1,156 @@
+use std::path::{Path, PathBuf};
+
+use openssl::error::ErrorStack;
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use serde::Deserialize;
+
+use crate::markdown::source::is_source_file;
+
+/// Pushes with this many commits or more may not list them all.
+const MAX_LISTED_COMMITS: usize = 20;
+
+/// The parts of a GitHub `push` webhook event that decide what to ship.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct PushEvent {
+    #[serde(rename = "ref")]
+    pub git_ref: String,
+    #[serde(default)]
+    pub before: String,
+    #[serde(default)]
+    pub after: String,
+    /// Whether the push deleted the branch.
+    #[serde(default)]
+    pub deleted: bool,
+    #[serde(default)]
+    pub forced: bool,
+    #[serde(default)]
+    pub commits: Vec<PushCommit>,
+    #[serde(default)]
+    pub repository: PushRepository,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct PushCommit {
+    #[serde(default)]
+    pub added: Vec<String>,
+    #[serde(default)]
+    pub modified: Vec<String>,
+    #[serde(default)]
+    pub removed: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct PushRepository {
+    #[serde(default)]
+    pub full_name: String,
+    #[serde(default)]
+    pub default_branch: String,
+}
+
+impl PushEvent {
+    /// The branch pushed to, `None` for tags.
+    pub fn branch(&self) -> Option<&str> {
+        self.git_ref.strip_prefix("refs/heads/")
+    }
+
+    /// Whether the push created the branch, which leaves nothing to compare it with.
+    pub fn is_new_branch(&self) -> bool {
+        self.before.chars().all(|c| c == '0')
+    }
+
+    /// Whether the commits of the event are all the push changed. GitHub leaves them out of
+    /// force pushes, and cuts the list short for large ones.
+    pub fn lists_every_change(&self) -> bool {
+        !self.forced && !self.commits.is_empty() && self.commits.len() < MAX_LISTED_COMMITS
+    }
+
+    /// Whether the commits of the event touch any documentation file under `root`, a directory
+    /// relative to the repository, removed ones included.
+    pub fn touches(&self, root: &Path) -> bool {
+        self.commits
+            .iter()
+            .flat_map(|c| c.added.iter().chain(c.modified.iter()).chain(c.removed.iter()))
+            .any(|path| relative_source_file(path, root).is_some())
+    }
+}
+
+fn relative_source_file(path: &str, root: &Path) -> Option<PathBuf> {
+    let path = Path::new(path);
+    if !is_source_file(path) {
+        return None;
+    }
+    path.strip_prefix(root).ok().map(|p| p.to_path_buf())
+}
+
+/// Whether `signature`, the `X-Hub-Signature-256` header of a webhook delivery, is the
+/// HMAC-SHA256 of `body` with `secret`.
+pub fn verify_signature(secret: &str, body: &[u8], signature: Option<&str>) -> bool {
+    let expected = match signature.and_then(|s| s.strip_prefix("sha256=")) {
+        Some(expected) => expected.trim().to_lowercase(),
+        None => return false,
+    };
+    let digest: String = match hmac_sha256(secret, body) {
+        Ok(digest) => digest.iter().map(|b| format!("{:02x}", b)).collect(),
+        Err(_) => return false,
+    };
+    digest.len() == expected.len() && openssl::memcmp::eq(digest.as_bytes(), expected.as_bytes())
+}
+
+fn hmac_sha256(secret: &str, body: &[u8]) -> Result<Vec<u8>, ErrorStack> {
+    let key = PKey::hmac(secret.as_bytes())?;
+    let mut signer = Signer::new(MessageDigest::sha256(), &key)?;
+    signer.update(body)?;
+    signer.sign_to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::github::{verify_signature, PushEvent};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_github_push() {
+        let secret = "It's a Secret to Everybody";
+        let signature = "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+        assert!(verify_signature(secret, b"Hello, World!", Some(signature)));
+        assert!(!verify_signature(secret, b"Hello, World?", Some(signature)));
+        assert!(!verify_signature("another secret", b"Hello, World!", Some(signature)));
+        assert!(!verify_signature(secret, b"Hello, World!", None));
+
+        let event: PushEvent = serde_json::from_str(
+            r#"{
+                "ref": "refs/heads/main",
+                "before": "aaa",
+                "after": "bbb",
+                "repository": { "full_name": "acme/app", "default_branch": "main" },
+                "commits": [
+                    { "added": ["docs/guide.md", "docs/draft.md", "src/main.rs"], "modified": ["README.md"], "removed": [] },
+                    { "added": [], "modified": ["docs/api/auth.md"], "removed": ["docs/draft.md", "docs/old.md"] }
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(event.branch(), Some("main"));
+        assert!(event.lists_every_change() && !event.is_new_branch());
+        assert!(event.touches(Path::new("docs/api")));
+        assert!(!event.touches(Path::new("src")));
+
+        let removal: PushEvent = serde_json::from_str(
+            r#"{ "ref": "refs/heads/main", "before": "0000000000000000000000000000000000000000", "commits": [{ "removed": ["docs/old.md"] }] }"#,
+        )
+        .unwrap();
+        assert!(removal.touches(Path::new("docs")));
+        assert!(removal.is_new_branch());
+
+        let forced: PushEvent = serde_json::from_str(r#"{ "ref": "refs/heads/main", "before": "aaa", "forced": true }"#).unwrap();
+        assert!(!forced.lists_every_change());
+        let large = PushEvent {
+            commits: vec![Default::default(); 20],
+            ..Default::default()
+        };
+        assert!(!large.lists_every_change());
+    }
+}
@@ -1,14 +1,14 @@
-use rand::distributions::Alphanumeric;
-use rand::Rng;
-
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod ci;
+pub mod commands;
+pub mod github;
 pub mod markdown;
 pub mod notion;
+pub mod notify;
+pub mod preview;
+pub mod redact;
+pub mod roundtrip;
+pub mod serve;
 pub mod settings;
-
-fn generate_random_string(length: usize) -> String {
-    let rng = rand::thread_rng();
-    rng.sample_iter(&Alphanumeric)
-        .take(length)
-        .map(char::from)
-        .collect()
-}
+pub mod sync;
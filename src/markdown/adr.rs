@@ -0,0 +1,216 @@
+use std::path::Path;
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, Result};
+use regex::Regex;
+
+use crate::markdown::generated::{check_out_dir, write_generated};
+
+static FILE_NAME: OnceLock<Regex> = OnceLock::new();
+static DATE_LINE: OnceLock<Regex> = OnceLock::new();
+static TITLE_NUMBER: OnceLock<Regex> = OnceLock::new();
+
+const STATUS_EMOJIS: [(&str, &str); 5] = [
+    ("proposed", "📝"),
+    ("accepted", "✅"),
+    ("rejected", "❌"),
+    ("deprecated", "⚠️"),
+    ("superseded", "♻️"),
+];
+const DEFAULT_ADR_EMOJI: &str = "📄";
+
+/// An architecture decision record, a `0001-use-postgres.md` file with a status.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Adr {
+    /// The number of the file name, as written, like `0001`.
+    pub number: String,
+    pub file_name: String,
+    pub title: String,
+    /// The first word of the `## Status` section or the `status:` of the frontmatter, like
+    /// `Accepted`.
+    pub status: String,
+    pub date: Option<String>,
+    /// The markdown of the record, without its frontmatter and title heading.
+    pub body: String,
+}
+
+impl Adr {
+    /// The record in `markdown`, read from the file `file_name`. `None` when the file isn't
+    /// named like one, is a template or has no status.
+    pub fn parse(file_name: &str, markdown: &str) -> Option<Self> {
+        let name = FILE_NAME.get_or_init(|| Regex::new(r"^(?P<number>\d{3,5})-(?P<slug>.+)\.md$").unwrap());
+        let date_line = DATE_LINE
+            .get_or_init(|| Regex::new(r"(?im)^\s*[-*]?\s*\**date\**:?\**\s*(?P<date>\d{4}-\d{2}-\d{2})").unwrap());
+        let title_number = TITLE_NUMBER.get_or_init(|| Regex::new(r"^(?i:adr)?[-\s]*\d+[.:]?\s+").unwrap());
+        let captures = name.captures(file_name).filter(|c| !c["slug"].eq_ignore_ascii_case("template"))?;
+
+        let (frontmatter, content) = split_frontmatter(markdown);
+        let field = |key: &str| {
+            frontmatter.lines().find_map(|l| {
+                let (k, v) = l.split_once(':')?;
+                let v = v.trim().trim_matches(['"', '\'']);
+                (k.trim().eq_ignore_ascii_case(key) && !v.is_empty()).then(|| v.to_string())
+            })
+        };
+
+        let mut title = None;
+        let mut body = Vec::new();
+        let mut in_status = false;
+        let mut status = field("status");
+        for line in content.lines() {
+            match line.strip_prefix("# ") {
+                Some(heading) if title.is_none() => {
+                    title = Some(title_number.replace(heading.trim(), "").to_string());
+                    continue;
+                }
+                _ => {}
+            }
+            if line.starts_with('#') {
+                in_status = line.trim_start_matches('#').trim().eq_ignore_ascii_case("status");
+            } else if in_status && status.is_none() && !line.trim().is_empty() {
+                status = line
+                    .split_whitespace()
+                    .next()
+                    .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()).to_string())
+                    .filter(|w| !w.is_empty());
+            }
+            body.push(line);
+        }
+        let status = capitalize(&status?);
+        let date = field("date").or_else(|| date_line.captures(content).map(|c| c["date"].to_string()));
+        Some(Adr {
+            number: captures["number"].to_string(),
+            file_name: file_name.to_string(),
+            title: title.unwrap_or_else(|| capitalize(&captures["slug"].replace(['-', '_'], " "))),
+            status,
+            date,
+            body: body.join("\n").trim().to_string(),
+        })
+    }
+
+    pub fn emoji(&self) -> &'static str {
+        let status = self.status.to_lowercase();
+        STATUS_EMOJIS
+            .iter()
+            .find(|(s, _)| *s == status)
+            .map_or(DEFAULT_ADR_EMOJI, |(_, emoji)| emoji)
+    }
+
+    /// The page of the record, with its status and date in the frontmatter for the
+    /// `[database]` properties.
+    pub fn markdown(&self) -> String {
+        let mut page = format!(
+            "---\ntitle: \"ADR-{}: {}\"\nemoji: {}\nstatus: {}\n",
+            self.number,
+            self.title.replace('"', "'"),
+            self.emoji(),
+            self.status
+        );
+        if let Some(date) = &self.date {
+            page.push_str(&format!("date: {}\n", date));
+        }
+        page.push_str("---\n\n");
+        page.push_str(&self.body);
+        page.push('\n');
+        page
+    }
+}
+
+/// The index of `adrs`: a table of their numbers, titles, statuses and dates, linking to
+/// their pages.
+pub fn adr_index(adrs: &[Adr]) -> String {
+    let mut index = String::from("| ADR | Title | Status | Date |\n| --- | --- | --- | --- |\n");
+    for adr in adrs {
+        index.push_str(&format!(
+            "| [{}]({}) | {} | {} {} | {} |\n",
+            adr.number,
+            adr.file_name,
+            adr.title.replace('|', "\\|"),
+            adr.emoji(),
+            adr.status,
+            adr.date.as_deref().unwrap_or("")
+        ));
+    }
+    index
+}
+
+/// Writes a page per record among the markdown files of `dir` into `out`, under an intro
+/// holding their index, replacing the pages of an earlier run. Files that aren't records,
+/// like a README or a template, are left out. `out` can't be `dir`, or hold it. Returns how
+/// many records were written.
+pub fn write_adrs(dir: &Path, out: &Path) -> Result<usize> {
+    check_out_dir(out, dir)?;
+    let mut adrs = Vec::new();
+    for entry in std::fs::read_dir(dir).map_err(|e| anyhow!("failed to read ADR directory {}: {}", dir.display(), e))? {
+        let path = entry?.path();
+        let file_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        if !path.is_file() || !file_name.ends_with(".md") {
+            continue;
+        }
+        if let Some(adr) = Adr::parse(&file_name, &std::fs::read_to_string(&path)?) {
+            adrs.push(adr);
+        }
+    }
+    if adrs.is_empty() {
+        return Err(anyhow!(
+            "found no ADR in {}, like 0001-use-postgres.md with a ## Status section",
+            dir.display()
+        ));
+    }
+    adrs.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+
+    let mut files: Vec<(String, String)> = adrs.iter().map(|a| (a.file_name.clone(), a.markdown())).collect();
+    files.push(("intro.md".to_string(), adr_index(&adrs)));
+    write_generated(out, &files)?;
+    Ok(adrs.len())
+}
+
+/// The frontmatter of `markdown`, empty without one, and the rest of it.
+fn split_frontmatter(markdown: &str) -> (&str, &str) {
+    let rest = match markdown.strip_prefix("---\n") {
+        Some(rest) => rest,
+        None => return ("", markdown),
+    };
+    match rest.find("\n---") {
+        Some(end) => {
+            let after = &rest[end + 4..];
+            (&rest[..end], after.strip_prefix('\n').unwrap_or(after))
+        }
+        None => ("", markdown),
+    }
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars.flat_map(|c| c.to_lowercase())).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::markdown::adr::{adr_index, Adr};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_parse_adr() {
+        let nygard = "# 2. Use Postgres\n\nDate: 2024-05-01\n\n## Status\n\nSuperseded by [ADR-0005](0005-use-sqlite.md)\n\n## Context\n\nWe need a database.\n\n## Decision\n\nPostgres.\n";
+        let adr = Adr::parse("0002-use-postgres.md", nygard).unwrap();
+        assert_eq!(adr.number, "0002");
+        assert_eq!(adr.title, "Use Postgres");
+        assert_eq!(adr.status, "Superseded");
+        assert_eq!(adr.date.as_deref(), Some("2024-05-01"));
+        assert_eq!(adr.emoji(), "♻️");
+        assert!(adr.body.starts_with("Date: 2024-05-01\n\n## Status"));
+        assert!(adr.markdown().starts_with("---\ntitle: \"ADR-0002: Use Postgres\"\nemoji: ♻️\nstatus: Superseded\ndate: 2024-05-01\n---\n\nDate:"));
+
+        let madr = "---\nstatus: accepted\ndate: 2024-06-02\n---\n\n# Use SQLite\n\n## Context and Problem Statement\n\nSmaller.\n";
+        let adr = Adr::parse("0005-use-sqlite.md", madr).unwrap();
+        assert_eq!((adr.status.as_str(), adr.date.as_deref()), ("Accepted", Some("2024-06-02")));
+        assert_eq!(adr.body, "## Context and Problem Statement\n\nSmaller.");
+
+        assert!(Adr::parse("README.md", nygard).is_none());
+        assert!(Adr::parse("0000-template.md", "# Title\n\n## Context\n").is_none());
+        assert!(adr_index(&[adr]).contains("| [0005](0005-use-sqlite.md) | Use SQLite | ✅ Accepted | 2024-06-02 |"));
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::Result;
+use markdown::mdast::Node;
+
+use crate::markdown::directive::walk;
+use crate::markdown::source::{parser_for, MarkdownParser};
+use crate::notion::block::AppendBlockRequestChild;
+
+/// The anchor GitHub gives a heading: lowercased, with punctuation dropped and spaces turned
+/// into hyphens.
+pub fn github_slug(text: &str) -> String {
+    text.to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_' || *c == ' ')
+        .map(|c| if c == ' ' { '-' } else { c })
+        .collect()
+}
+
+/// Hands out the anchors of a page's headings in order, suffixing repeated ones with `-1`,
+/// `-2` and so on like GitHub does.
+#[derive(Clone, Debug, Default)]
+pub struct Slugger {
+    occurrences: HashMap<String, usize>,
+}
+
+impl Slugger {
+    pub fn slug(&mut self, text: &str) -> String {
+        let original = github_slug(text);
+        let mut slug = original.clone();
+        while self.occurrences.contains_key(&slug) {
+            let count = self.occurrences.entry(original.clone()).or_default();
+            *count += 1;
+            slug = format!("{}-{}", original, count);
+        }
+        self.occurrences.insert(slug.clone(), 0);
+        slug
+    }
+}
+
+/// The anchors of the headings below `node`, those in the bodies of directives included, in
+/// document order.
+pub fn heading_slugs(node: &Node) -> Vec<String> {
+    let mut slugger = Slugger::default();
+    let mut slugs = Vec::new();
+    walk(node, 0, &mut |n, _| {
+        if let Node::Heading(_) = n {
+            slugs.push(slugger.slug(&n.to_string()));
+        }
+    });
+    slugs
+}
+
+/// The anchors of the headings in the documentation file at `path`.
+pub fn file_heading_slugs(path: &Path) -> Result<Vec<String>> {
+    let contents = std::fs::read_to_string(path)?;
+    let parser = parser_for(path).unwrap_or(&MarkdownParser);
+    Ok(heading_slugs(&parser.parse(&contents)?))
+}
+
+/// Links to the heading `slug` on a page. The slug is swapped for the id of the heading block
+/// once the page is written, see [`parse_anchor_url`].
+pub fn anchor_url(page_id: &str, slug: &str) -> String {
+    format!("https://www.notion.so/{}#{}", page_id.replace("-", ""), slug)
+}
+
+/// The page id, without dashes, and the fragment of a link to a Notion page. Notion hands back
+/// links to its own pages as paths, so those are read too.
+pub fn parse_anchor_url(url: &str) -> Option<(String, String)> {
+    let path = url
+        .strip_prefix("https://www.notion.so/")
+        .or_else(|| url.strip_prefix("/"))?;
+    let (page, fragment) = path.split_once('#')?;
+    let page = page.replace("-", "");
+    let page_id = page.get(page.len().checked_sub(32)?..)?;
+    if fragment.is_empty() || !page_id.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some((page_id.to_string(), fragment.to_string()))
+}
+
+/// Whether any of `children`, or the blocks nested in them, link to a heading with an
+/// [`anchor_url`].
+pub fn links_to_headings(children: &[AppendBlockRequestChild]) -> bool {
+    children.iter().any(|c| {
+        let is_linking = c
+            .get_rich_text_blocks()
+            .into_iter()
+            .flatten()
+            .filter_map(|t| t.text.and_then(|t| t.link))
+            .any(|l| parse_anchor_url(&l.url).is_some());
+        is_linking || links_to_headings(c.nested())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::markdown::anchors::{anchor_url, github_slug, heading_slugs, parse_anchor_url, Slugger};
+    use crate::markdown::source::{MarkdownParser, SourceParser};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_github_slugs() {
+        assert_eq!(github_slug("Getting Started"), "getting-started");
+        assert_eq!(github_slug("What's new in v1.2?"), "whats-new-in-v12");
+        assert_eq!(github_slug("The `ship` command"), "the-ship-command");
+        assert_eq!(github_slug("snake_case & kebab-case"), "snake_case--kebab-case");
+        assert_eq!(github_slug("Ünïcödé Heading 🚀"), "ünïcödé-heading-");
+
+        let mut slugger = Slugger::default();
+        assert_eq!(slugger.slug("Usage"), "usage");
+        assert_eq!(slugger.slug("Usage"), "usage-1");
+        assert_eq!(slugger.slug("Usage 1"), "usage-1-1");
+        assert_eq!(slugger.slug("Usage"), "usage-2");
+
+        let node = MarkdownParser
+            .parse("# Title\n\n## Install\n\n> ### Note\n\n:::toggle\n## Install\n:::\n\n## Install\n")
+            .unwrap();
+        assert_eq!(heading_slugs(&node), vec!["title", "install", "note", "install-1", "install-2"]);
+
+        let page_id = "0123abcd-0123-abcd-0123-0123456789ab";
+        let url = anchor_url(page_id, "install-1");
+        assert_eq!(url, "https://www.notion.so/0123abcd0123abcd01230123456789ab#install-1");
+        let expected = Some(("0123abcd0123abcd01230123456789ab".to_string(), "install-1".to_string()));
+        assert_eq!(parse_anchor_url(&url), expected);
+        assert_eq!(
+            parse_anchor_url("https://www.notion.so/Some-Page-0123abcd0123abcd01230123456789ab#install-1"),
+            expected
+        );
+        assert_eq!(parse_anchor_url("/0123abcd0123abcd01230123456789ab#install-1"), expected);
+        assert_eq!(parse_anchor_url("https://www.notion.so/0123abcd0123abcd01230123456789ab"), None);
+        assert_eq!(parse_anchor_url("https://example.com/page#install"), None);
+    }
+}
@@ -0,0 +1,346 @@
+use std::sync::OnceLock;
+
+use anyhow::Result;
+use markdown::mdast::{BlockQuote, Emphasis, InlineCode, Link, Node, Strong, Table, TableCell, TableRow, ThematicBreak};
+use regex::Regex;
+
+use crate::markdown::source::{
+    code, decode_entities, heading, image, line_position, list, paragraph, root, text, unescape, SourceParser,
+};
+
+static INLINE: OnceLock<Regex> = OnceLock::new();
+static LIST_ITEM: OnceLock<Regex> = OnceLock::new();
+
+/// Reads AsciiDoc: section titles, paragraphs, lists, listing and literal blocks, quote
+/// blocks, tables, images and the common inline formatting. Document attributes, comments and
+/// block attributes other than `[source]` are dropped.
+pub struct AsciiDocParser;
+
+impl SourceParser for AsciiDocParser {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["adoc", "asciidoc", "asc"]
+    }
+
+    fn parse(&self, contents: &str) -> Result<Node> {
+        let lines: Vec<&str> = contents.lines().collect();
+        Ok(root(parse_blocks(&lines, 1)))
+    }
+}
+
+/// Parses `lines`, the first of which is line `first_line` of the file.
+fn parse_blocks(lines: &[&str], first_line: usize) -> Vec<Node> {
+    let list_item = LIST_ITEM.get_or_init(|| Regex::new(r"^(\*+|-|\.+|\d+\.)\s+(.*)$").unwrap());
+    let mut blocks = Vec::new();
+    let mut paragraph_lines: Vec<&str> = Vec::new();
+    let mut paragraph_start = 0;
+    let mut items: Vec<Vec<Node>> = Vec::new();
+    let mut item_lines: Vec<&str> = Vec::new();
+    let mut ordered = false;
+    let mut list_start = 0;
+    let mut source_lang: Option<String> = None;
+    let mut block_title: Option<String> = None;
+
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i].trim_end();
+        let line_number = first_line + i;
+
+        let is_item = list_item.captures(line);
+        if !item_lines.is_empty() && (line.is_empty() || is_item.is_some()) {
+            items.push(inline(&item_lines.join("\n"), list_start));
+            item_lines.clear();
+        }
+        if !items.is_empty() && item_lines.is_empty() && is_item.is_none() && !line.is_empty() {
+            blocks.push(list(std::mem::take(&mut items), ordered, list_start));
+        }
+        if !paragraph_lines.is_empty() && (line.is_empty() || is_item.is_some() || is_block_start(line)) {
+            blocks.push(paragraph(inline(&paragraph_lines.join("\n"), paragraph_start), paragraph_start));
+            paragraph_lines.clear();
+        }
+
+        if line.is_empty() {
+            i += 1;
+            continue;
+        }
+        if let Some(c) = is_item {
+            let marker = c.get(1).map_or("", |m| m.as_str());
+            if items.is_empty() && item_lines.is_empty() {
+                ordered = marker.starts_with('.') || marker.ends_with('.');
+                list_start = line_number;
+            }
+            item_lines.push(c.get(2).map_or("", |m| m.as_str()));
+            i += 1;
+            continue;
+        }
+        if !item_lines.is_empty() {
+            item_lines.push(line);
+            i += 1;
+            continue;
+        }
+
+        if line == "////" {
+            i = closing_delimiter(lines, i, line) + 1;
+        } else if line.starts_with("//") {
+            i += 1;
+        } else if line.starts_with(':') && line[1..].contains(':') && !line.starts_with("::") {
+            // A document attribute, like `:toc:`.
+            i += 1;
+        } else if let Some(title) = heading_title(line) {
+            blocks.push(heading(&literal(title.1), title.0, line_number));
+            i += 1;
+        } else if line.starts_with('[') && line.ends_with(']') {
+            source_lang = source_language(line);
+            i += 1;
+            continue;
+        } else if line.len() > 1 && line.starts_with('.') && !line.starts_with("..") && !line.starts_with(". ") {
+            block_title = Some(line[1..].to_string());
+            i += 1;
+            continue;
+        } else if line == "----" || line == "...." || line == "```" {
+            let end = closing_delimiter(lines, i, line);
+            let body = lines[i + 1..end.min(lines.len())].join("\n");
+            let meta = block_title.take().map(|t| format!("title=\"{}\"", t.replace('"', "'")));
+            blocks.push(code(body, source_lang.take(), meta, line_number));
+            i = end + 1;
+        } else if line == "____" {
+            let end = closing_delimiter(lines, i, line);
+            let body = &lines[i + 1..end.min(lines.len())];
+            blocks.push(Node::BlockQuote(BlockQuote {
+                children: parse_blocks(body, line_number + 1),
+                position: line_position(line_number),
+            }));
+            i = end + 1;
+        } else if line == "|===" {
+            let end = closing_delimiter(lines, i, line);
+            blocks.push(table(&lines[i + 1..end.min(lines.len())], line_number));
+            i = end + 1;
+        } else if line == "'''" {
+            blocks.push(Node::ThematicBreak(ThematicBreak {
+                position: line_position(line_number),
+            }));
+            i += 1;
+        } else if let Some((url, alt)) = block_image(line) {
+            blocks.push(image(url, alt, line_number));
+            i += 1;
+        } else {
+            if paragraph_lines.is_empty() {
+                paragraph_start = line_number;
+            }
+            paragraph_lines.push(line);
+            i += 1;
+            continue;
+        }
+        source_lang = None;
+        block_title = None;
+    }
+
+    if !item_lines.is_empty() {
+        items.push(inline(&item_lines.join("\n"), list_start));
+    }
+    if !items.is_empty() {
+        blocks.push(list(items, ordered, list_start));
+    }
+    if !paragraph_lines.is_empty() {
+        blocks.push(paragraph(inline(&paragraph_lines.join("\n"), paragraph_start), paragraph_start));
+    }
+    blocks
+}
+
+fn is_block_start(line: &str) -> bool {
+    heading_title(line).is_some()
+        || matches!(line, "----" | "...." | "```" | "____" | "|===" | "'''" | "////")
+        || block_image(line).is_some()
+}
+
+/// The depth and text of a section title: `= Title`, the document title, is of depth 1 and
+/// `== Section` of depth 2.
+fn heading_title(line: &str) -> Option<(u8, &str)> {
+    let depth = line.chars().take_while(|c| *c == '=').count();
+    if depth == 0 || depth > 6 {
+        return None;
+    }
+    let title = line[depth..].strip_prefix(' ')?.trim();
+    if title.is_empty() {
+        return None;
+    }
+    Some((depth as u8, title))
+}
+
+/// The language of a `[source,rust]` block attribute line.
+fn source_language(line: &str) -> Option<String> {
+    let attributes: Vec<&str> = line[1..line.len() - 1].split(',').map(str::trim).collect();
+    match attributes.as_slice() {
+        ["source", lang, ..] if !lang.is_empty() => Some(lang.to_string()),
+        _ => None,
+    }
+}
+
+fn block_image(line: &str) -> Option<(&str, &str)> {
+    let rest = line.strip_prefix("image::")?;
+    let (url, alt) = rest.split_once('[')?;
+    let alt = alt.strip_suffix(']')?;
+    Some((url, alt.split(',').next().unwrap_or("")))
+}
+
+/// The index of the line closing the delimited block opened at `start`, or the end of the file
+/// when it isn't closed.
+fn closing_delimiter(lines: &[&str], start: usize, delimiter: &str) -> usize {
+    lines[start + 1..]
+        .iter()
+        .position(|l| l.trim_end() == delimiter)
+        .map_or(lines.len(), |p| start + 1 + p)
+}
+
+fn table(lines: &[&str], line: usize) -> Node {
+    let mut rows = Vec::new();
+    for row in lines.iter().map(|l| l.trim()).filter(|l| l.starts_with('|')) {
+        let cells = row[1..]
+            .split('|')
+            .map(|c| {
+                Node::TableCell(TableCell {
+                    children: vec![text(&literal(c.trim()), line)],
+                    position: line_position(line),
+                })
+            })
+            .collect();
+        rows.push(Node::TableRow(TableRow {
+            children: cells,
+            position: line_position(line),
+        }));
+    }
+    Node::Table(Table {
+        children: rows,
+        position: line_position(line),
+        align: vec![],
+    })
+}
+
+/// Splits a paragraph into text, `*strong*`, `_emphasis_`, `` `code` `` and links, written as
+/// `https://example.com[label]`, `link:url[label]` or `xref:other.adoc[label]`.
+fn inline(value: &str, line: usize) -> Vec<Node> {
+    let pattern = INLINE.get_or_init(|| {
+        Regex::new(concat!(
+            r"\*\*?(?P<strong>[^*\n]+?)\*\*?",
+            r"|__?(?P<em>[^_\n]+?)__?",
+            r"|`(?P<code>[^`\n]+)`",
+            r"|(?P<kind>link:|xref:)(?P<target>[^\s\[]+)\[(?P<target_label>[^\]]*)\]",
+            r"|(?P<url>https?://[^\s\[]+)(?:\[(?P<label>[^\]]*)\])?",
+        ))
+        .unwrap()
+    });
+    let mut nodes = Vec::new();
+    let mut last = 0;
+    for c in pattern.captures_iter(value) {
+        let m = c.get(0).unwrap();
+        // Single `*` and `_` only format whole words, `snake_case` stays as it is.
+        let is_constrained = c.name("strong").or(c.name("em")).is_some();
+        let in_word = value[..m.start()].chars().last().is_some_and(char::is_alphanumeric)
+            || value[m.end()..].chars().next().is_some_and(char::is_alphanumeric);
+        let is_escaped = value[..m.start()].ends_with('\\');
+        if (is_constrained && in_word) || is_escaped {
+            continue;
+        }
+        if m.start() > last {
+            nodes.push(text(&literal(&value[last..m.start()]), line));
+        }
+        last = m.end();
+        if let Some(s) = c.name("strong") {
+            nodes.push(Node::Strong(Strong {
+                children: vec![text(&literal(s.as_str()), line)],
+                position: line_position(line),
+            }));
+        } else if let Some(e) = c.name("em") {
+            nodes.push(Node::Emphasis(Emphasis {
+                children: vec![text(&literal(e.as_str()), line)],
+                position: line_position(line),
+            }));
+        } else if let Some(code) = c.name("code") {
+            nodes.push(Node::InlineCode(InlineCode {
+                value: code.as_str().to_string(),
+                position: line_position(line),
+            }));
+        } else if let Some(target) = c.name("target") {
+            let mut url = target.as_str().to_string();
+            if c.name("kind").map(|k| k.as_str()) == Some("xref:") && !url.starts_with('.') {
+                url = format!("./{}", url);
+            }
+            let label = c.name("target_label").map_or("", |l| l.as_str());
+            nodes.push(link(&url, label, line));
+        } else if let Some(url) = c.name("url") {
+            let label = c.name("label").map_or("", |l| l.as_str());
+            nodes.push(link(url.as_str(), label, line));
+        }
+    }
+    if last < value.len() {
+        nodes.push(text(&literal(&value[last..]), line));
+    }
+    nodes
+}
+
+/// Text as it reads, without the backslashes escaping markup and with entities decoded.
+fn literal(value: &str) -> String {
+    decode_entities(&unescape(value))
+}
+
+fn link(url: &str, label: &str, line: usize) -> Node {
+    let label = if label.is_empty() { url } else { label };
+    Node::Link(Link {
+        children: vec![text(&literal(label), line)],
+        position: line_position(line),
+        url: url.to_string(),
+        title: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use markdown::mdast::Node;
+
+    use crate::markdown::asciidoc::AsciiDocParser;
+    use crate::markdown::source::SourceParser;
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_parse_asciidoc() {
+        let doc = "= Guide\n:toc:\n\nSome *bold* and `code`, see https://example.com[the site].\n\n== Setup\n\n* one\n* two\n\n[source,rust]\n.main.rs\n----\nfn main() {}\n----\n\n|===\n|a |b\n|c |d\n|===\n";
+        let root = AsciiDocParser.parse(doc).unwrap();
+        let children = root.children().unwrap();
+        let kinds: Vec<&str> = children
+            .iter()
+            .map(|c| match c {
+                Node::Heading(_) => "heading",
+                Node::Paragraph(_) => "paragraph",
+                Node::List(_) => "list",
+                Node::Code(_) => "code",
+                Node::Table(_) => "table",
+                _ => "other",
+            })
+            .collect();
+        assert_eq!(kinds, vec!["heading", "paragraph", "heading", "list", "code", "table"]);
+        match &children[4] {
+            Node::Code(c) => {
+                assert_eq!(c.lang.as_deref(), Some("rust"));
+                assert_eq!(c.meta.as_deref(), Some("title=\"main.rs\""));
+                assert_eq!(c.value, "fn main() {}");
+            }
+            _ => unreachable!(),
+        }
+        match &children[1] {
+            Node::Paragraph(p) => assert_eq!(p.children.len(), 7),
+            _ => unreachable!(),
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_asciidoc_escapes() {
+        let root = AsciiDocParser.parse("== Tom &amp; Jerry\n\n\\*not bold* &copy; *bold*\n").unwrap();
+        let children = root.children().unwrap();
+        assert_eq!(children[0].to_string(), "Tom & Jerry");
+        match &children[1] {
+            Node::Paragraph(p) => {
+                assert!(matches!(&p.children[0], Node::Text(t) if t.value == "*not bold* © "));
+                assert!(matches!(&p.children[1], Node::Strong(s) if s.children[0].to_string() == "bold"));
+            }
+            _ => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, Result};
+use regex::Regex;
+
+use crate::markdown::generated::{check_out_dir, write_generated};
+
+static RELEASE_HEADING: OnceLock<Regex> = OnceLock::new();
+static LINK_DEFINITION: OnceLock<Regex> = OnceLock::new();
+
+/// The kinds of change of Keep a Changelog, by the emoji of their section, the most notable
+/// first. A release takes the emoji of the most notable kind it has.
+const CHANGE_TYPES: [(&str, &str); 6] = [
+    ("security", "🔒"),
+    ("removed", "🗑️"),
+    ("deprecated", "⚠️"),
+    ("added", "✨"),
+    ("changed", "🔄"),
+    ("fixed", "🐛"),
+];
+const DEFAULT_RELEASE_EMOJI: &str = "📦";
+
+/// A `## [1.2.0] - 2024-05-01` section of a changelog.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Release {
+    pub version: String,
+    pub date: Option<String>,
+    /// What follows the version and date on the heading, like `[YANKED]`.
+    pub note: Option<String>,
+    /// The markdown of the section, below its heading.
+    pub body: String,
+    /// Where the changes of the release can be seen, from the link definition of its version.
+    pub url: Option<String>,
+}
+
+impl Release {
+    pub fn title(&self) -> String {
+        let mut title = self.version.clone();
+        if let Some(date) = &self.date {
+            title.push_str(&format!(" — {}", date));
+        }
+        if let Some(note) = &self.note {
+            title.push_str(&format!(" {}", note));
+        }
+        title
+    }
+
+    /// The emoji of the most notable kind of change of the release.
+    pub fn emoji(&self) -> &'static str {
+        let kinds: Vec<String> = self.body.lines().filter_map(change_type).collect();
+        CHANGE_TYPES
+            .iter()
+            .find(|(kind, _)| kinds.iter().any(|k| k == kind))
+            .map_or(DEFAULT_RELEASE_EMOJI, |(_, emoji)| emoji)
+    }
+
+    /// The page of the release, with its version and date in the frontmatter for the
+    /// `[database]` properties, and an emoji before the heading of each kind of change.
+    pub fn markdown(&self) -> String {
+        let mut page = format!(
+            "---\ntitle: \"{}\"\nemoji: {}\nversion: \"{}\"\n",
+            self.title().replace('"', "'"),
+            self.emoji(),
+            self.version
+        );
+        if let Some(date) = &self.date {
+            page.push_str(&format!("date: {}\n", date));
+        }
+        page.push_str("---\n\n");
+        for line in self.body.lines() {
+            match change_type(line) {
+                Some(kind) => {
+                    let emoji = CHANGE_TYPES.iter().find(|(k, _)| *k == kind).map_or("", |(_, e)| e);
+                    let (hashes, heading) = line.split_once(' ').unwrap_or((line, ""));
+                    page.push_str(&format!("{} {} {}", hashes, emoji, heading.trim()));
+                }
+                None => page.push_str(line),
+            }
+            page.push('\n');
+        }
+        if let Some(url) = &self.url {
+            page.push_str(&format!("\n[All changes of {}]({})\n", self.version, url));
+        }
+        page
+    }
+
+    /// The name of the file of the release page, its version.
+    pub fn file_name(&self) -> String {
+        let name: String = self
+            .version
+            .chars()
+            .map(|c| if c.is_alphanumeric() || c == '.' || c == '-' { c } else { '_' })
+            .collect();
+        format!("{}.md", name)
+    }
+}
+
+/// The kind of change a `### Added` line starts, lowercased, `None` for other lines.
+fn change_type(line: &str) -> Option<String> {
+    let heading = line.strip_prefix("###")?.trim().to_lowercase();
+    CHANGE_TYPES.iter().any(|(k, _)| *k == heading).then_some(heading)
+}
+
+/// The releases of a Keep a Changelog style file, in the order of the file, newest first.
+/// `Unreleased` is left out, and so is everything above the first release. Releases are the
+/// `##` headings starting with a version.
+pub fn parse_changelog(markdown: &str) -> Vec<Release> {
+    let heading = RELEASE_HEADING.get_or_init(|| {
+        Regex::new(r"^##\s+\[?(?P<version>[vV]?\d[^\]\s]*|(?i:unreleased))\]?(?:\s*[-–—(]?\s*(?P<date>\d{4}-\d{2}-\d{2})\)?)?\s*(?P<note>.*)$")
+            .unwrap()
+    });
+    let definition = LINK_DEFINITION.get_or_init(|| Regex::new(r"^\s*\[(?P<label>[^\]]+)\]:\s*(?P<url>\S+)").unwrap());
+
+    let mut links = HashMap::new();
+    let mut releases: Vec<Release> = Vec::new();
+    let mut current: Option<Release> = None;
+    let mut fence: Option<&str> = None;
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if let Some(f) = fence {
+            if trimmed.starts_with(f) {
+                fence = None;
+            }
+        } else if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+        } else if let Some(c) = heading.captures(line) {
+            releases.extend(current.take());
+            let note = c["note"].trim();
+            current = Some(Release {
+                version: c["version"].to_string(),
+                date: c.name("date").map(|d| d.as_str().to_string()),
+                note: (!note.is_empty()).then(|| note.to_string()),
+                body: String::new(),
+                url: None,
+            });
+            continue;
+        } else if let Some(c) = definition.captures(line) {
+            links.insert(c["label"].to_lowercase(), c["url"].to_string());
+            continue;
+        }
+        if let Some(release) = current.as_mut() {
+            release.body.push_str(line);
+            release.body.push('\n');
+        }
+    }
+    releases.extend(current);
+    releases
+        .into_iter()
+        .filter(|r| !r.version.eq_ignore_ascii_case("unreleased"))
+        .map(|mut r| {
+            r.body = r.body.trim().to_string();
+            r.url = links.get(&r.version.to_lowercase()).cloned();
+            r
+        })
+        .collect()
+}
+
+/// Writes a page per release of the changelog at `path` into `out`, newest first, replacing
+/// the pages of an earlier run. `out` can't be the directory of the changelog, or hold it.
+/// Returns how many releases were written.
+pub fn write_changelog(path: &Path, out: &Path) -> Result<usize> {
+    check_out_dir(out, path.parent().unwrap_or(Path::new("")))?;
+    let markdown = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("failed to read changelog {}: {}", path.display(), e))?;
+    let releases = parse_changelog(&markdown);
+    if releases.is_empty() {
+        return Err(anyhow!("found no release in {}, like \"## [1.0.0] - 2024-05-01\"", path.display()));
+    }
+    let mut files: Vec<(String, String)> = releases.iter().map(|r| (r.file_name(), r.markdown())).collect();
+    let order: Vec<String> = files.iter().map(|(name, _)| format!("\"{}\"", name)).collect();
+    files.push((".notation.toml".to_string(), format!("order = [{}]\n", order.join(", "))));
+    write_generated(out, &files)?;
+    Ok(releases.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::markdown::changelog::{parse_changelog, write_changelog};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_parse_changelog() {
+        let changelog = "# Changelog\n\nAll notable changes.\n\n## [Unreleased]\n\n### Added\n- Soon\n\n\
+            ## [1.1.0] - 2024-05-01\n\n### Fixed\n- A crash\n\n```\n## not a release\n```\n\n### Security\n- A leak\n\n\
+            ## 1.0.0 (2024-01-15) [YANKED]\n\n### Added\n- Everything\n\n\
+            [unreleased]: https://example.com/compare/v1.1.0...HEAD\n[1.1.0]: https://example.com/compare/v1.0.0...v1.1.0\n";
+        let releases = parse_changelog(changelog);
+        assert_eq!(releases.len(), 2);
+        assert_eq!(releases[0].title(), "1.1.0 — 2024-05-01");
+        assert_eq!(releases[0].emoji(), "🔒");
+        assert!(releases[0].body.contains("## not a release"));
+        assert_eq!(releases[0].url.as_deref(), Some("https://example.com/compare/v1.0.0...v1.1.0"));
+        assert_eq!(releases[1].title(), "1.0.0 — 2024-01-15 [YANKED]");
+        assert_eq!(releases[1].emoji(), "✨");
+        assert_eq!(releases[1].body, "### Added\n- Everything");
+
+        let page = releases[0].markdown();
+        assert!(page.starts_with("---\ntitle: \"1.1.0 — 2024-05-01\"\nemoji: 🔒\nversion: \"1.1.0\"\ndate: 2024-05-01\n---"));
+        assert!(page.contains("### 🐛 Fixed\n"));
+        assert_eq!(releases[0].file_name(), "1.1.0.md");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_write_changelog() {
+        let root = std::env::temp_dir().join(format!("notation-changelog-{}", std::process::id()));
+        let out = root.join("releases");
+        std::fs::create_dir_all(&out).unwrap();
+        let changelog = root.join("CHANGELOG.md");
+        std::fs::write(&changelog, "# Changelog\n\n## [1.1.0] - 2024-05-01\n\n- Faster\n\n## [1.0.0] - 2024-01-15\n\n- First\n").unwrap();
+        std::fs::write(out.join("notes.md"), "Not a release").unwrap();
+
+        assert_eq!(write_changelog(&changelog, &out).unwrap(), 2);
+        std::fs::write(&changelog, "# Changelog\n\n## [1.1.0] - 2024-05-01\n\n- Faster\n").unwrap();
+        assert_eq!(write_changelog(&changelog, &out).unwrap(), 1);
+        assert!(out.join("1.1.0.md").exists());
+        assert!(!out.join("1.0.0.md").exists());
+        assert_eq!(std::fs::read_to_string(out.join("notes.md")).unwrap(), "Not a release");
+        assert_eq!(std::fs::read_to_string(out.join(".notation.toml")).unwrap(), "order = [\"1.1.0.md\"]\n");
+
+        assert!(write_changelog(&changelog, &root).is_err());
+        assert!(changelog.exists());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::BTreeSet;
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::notion::block::{NotionBlock, TextAnnotations};
+
+static TITLE_PATTERN: OnceLock<Regex> = OnceLock::new();
+static LINES_PATTERN: OnceLock<Regex> = OnceLock::new();
+
+/// What follows the language on a code fence, like ```` ```rust title="main.rs" {1,3-5} ````.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CodeMeta {
+    pub title: Option<String>,
+    /// Highlighted lines, starting at 1.
+    pub highlighted_lines: BTreeSet<usize>,
+}
+
+impl CodeMeta {
+    pub fn parse(meta: &str) -> Self {
+        let title_pattern = TITLE_PATTERN
+            .get_or_init(|| Regex::new(r#"title=(?:"([^"]*)"|'([^']*)'|(\S+))"#).unwrap());
+        let lines_pattern = LINES_PATTERN.get_or_init(|| Regex::new(r"\{([\d,\s-]*)\}").unwrap());
+
+        let title = title_pattern.captures(meta).and_then(|c| {
+            c.get(1)
+                .or(c.get(2))
+                .or(c.get(3))
+                .map(|m| m.as_str().to_string())
+        });
+        let mut highlighted_lines = BTreeSet::new();
+        if let Some(c) = lines_pattern.captures(meta) {
+            for range in c[1].split(',').map(str::trim).filter(|r| !r.is_empty()) {
+                let (start, end) = range.split_once('-').unwrap_or((range, range));
+                if let (Ok(start), Ok(end)) = (start.trim().parse::<usize>(), end.trim().parse::<usize>()) {
+                    highlighted_lines.extend(start..=end);
+                }
+            }
+        }
+        CodeMeta {
+            title,
+            highlighted_lines,
+        }
+    }
+}
+
+/// Splits `code` into rich text of at most `max_length` characters per block. With
+/// `bold_lines`, those lines (starting at 1) are bolded.
+pub fn build_code_rich_text(
+    code: &str,
+    bold_lines: &BTreeSet<usize>,
+    max_length: usize,
+) -> Vec<NotionBlock> {
+    let mut segments: Vec<(String, bool)> = Vec::new();
+    let line_count = code.split('\n').count();
+    for (i, line) in code.split('\n').enumerate() {
+        let is_bold = bold_lines.contains(&(i + 1));
+        let line = if i + 1 < line_count {
+            format!("{}\n", line)
+        } else {
+            line.to_string()
+        };
+        match segments.last_mut() {
+            Some((text, bold)) if *bold == is_bold => text.push_str(&line),
+            _ => segments.push((line, is_bold)),
+        }
+    }
+
+    let mut rich_text = Vec::new();
+    for (text, is_bold) in segments {
+        for chunk in chunk_text(&text, max_length) {
+            let block = NotionBlock::new_code_block(chunk);
+            rich_text.push(if is_bold {
+                block.with_annotations(TextAnnotations::new().bold())
+            } else {
+                block
+            });
+        }
+    }
+    rich_text
+}
+
+fn chunk_text(text: &str, max_length: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    chars
+        .chunks(max_length)
+        .map(|c| c.iter().collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use crate::markdown::code::{build_code_rich_text, CodeMeta};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_parse_code_meta() {
+        let meta = CodeMeta::parse(r#"title="main.rs" {1,3-5}"#);
+        assert_eq!(meta.title, Some("main.rs".to_string()));
+        assert_eq!(meta.highlighted_lines, BTreeSet::from([1, 3, 4, 5]));
+        assert_eq!(CodeMeta::parse("title=lib.rs").title, Some("lib.rs".to_string()));
+        assert_eq!(CodeMeta::parse(""), CodeMeta::default());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_build_code_rich_text() {
+        let rich_text = build_code_rich_text("a\nb\nc", &BTreeSet::from([2]), 2000);
+        let contents: Vec<String> = rich_text
+            .iter()
+            .map(|b| b.text.as_ref().unwrap().content.clone())
+            .collect();
+        assert_eq!(contents, vec!["a\n", "b\n", "c"]);
+        assert!(rich_text[1].annotations.as_ref().unwrap().bold);
+
+        let rich_text = build_code_rich_text("ééé", &BTreeSet::new(), 2);
+        assert_eq!(rich_text.len(), 2);
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::Result;
+use serde_json::Value;
+
+use crate::markdown::parse::{parse_file, ConversionOptions};
+use crate::markdown::source::is_source_file;
+use crate::notion::schema::append_problems;
+use crate::settings::notation::UnsupportedNodePolicy;
+
+/// How one construct of a corpus like `samples_md/coverage/`, a file of its own, converts.
+#[derive(Clone, Debug)]
+pub struct ConstructCoverage {
+    /// The file stem, like `nested_list`.
+    pub construct: String,
+    /// The types of the top level blocks it converted to.
+    pub block_types: Vec<String>,
+    /// What is wrong with those blocks, see [`append_problems`] and [`block_problems`].
+    pub problems: Vec<String>,
+}
+
+impl ConstructCoverage {
+    pub fn is_covered(&self) -> bool {
+        !self.block_types.is_empty() && self.problems.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CoverageReport {
+    pub constructs: Vec<ConstructCoverage>,
+}
+
+impl CoverageReport {
+    /// The share of constructs that converted to well-formed blocks, out of 100.
+    pub fn percentage(&self) -> f64 {
+        if self.constructs.is_empty() {
+            return 100.0;
+        }
+        let covered = self.constructs.iter().filter(|c| c.is_covered()).count();
+        covered as f64 * 100.0 / self.constructs.len() as f64
+    }
+
+    /// The constructs that converted to nothing, or to blocks Notion would turn down.
+    pub fn uncovered(&self) -> Vec<&str> {
+        self.constructs
+            .iter()
+            .filter(|c| !c.is_covered())
+            .map(|c| c.construct.as_str())
+            .collect()
+    }
+}
+
+/// Converts every file of `dir`, each holding one construct, with unsupported nodes left out
+/// silently, so that constructs the conversion drops show up as converting to nothing.
+pub async fn coverage(dir: &Path) -> Result<CoverageReport> {
+    let mut paths: Vec<_> = std::fs::read_dir(dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| is_source_file(p))
+        .collect();
+    paths.sort();
+    let options = ConversionOptions {
+        unsupported_nodes: UnsupportedNodePolicy::Ignore,
+        ..Default::default()
+    };
+    let mut report = CoverageReport::default();
+    for path in paths {
+        let conversion = parse_file(&path).await?.to_notion("coverage", &HashMap::new(), &options)?;
+        let blocks: Vec<Value> = conversion
+            .request
+            .children
+            .iter()
+            .map(serde_json::to_value)
+            .collect::<serde_json::Result<_>>()?;
+        let mut problems = append_problems(&conversion.request);
+        problems.extend(blocks.iter().flat_map(block_problems));
+        report.constructs.push(ConstructCoverage {
+            construct: path.file_stem().unwrap_or_default().to_string_lossy().to_string(),
+            block_types: blocks.iter().filter_map(|b| b["type"].as_str()).map(|t| t.to_string()).collect(),
+            problems,
+        });
+    }
+    Ok(report)
+}
+
+/// What a serialized block, and the blocks nested in it, have that the request schema lets
+/// through but Notion turns down, or that drops content: a payload that doesn't match its type,
+/// empty rich text, a table whose rows don't match its width. Text lengths, image sources and
+/// the like are the schema's, see [`append_problems`].
+pub fn block_problems(block: &Value) -> Vec<String> {
+    let mut problems = Vec::new();
+    let block_type = match block["type"].as_str() {
+        Some(t) => t,
+        None => return vec!["block without a type".to_string()],
+    };
+    let payload = &block[block_type];
+    if !payload.is_object() {
+        problems.push(format!("{} block without a {} payload", block_type, block_type));
+        return problems;
+    }
+    let payloads: Vec<&String> = block
+        .as_object()
+        .map(|o| o.keys().filter(|k| !["object", "type", block_type].contains(&k.as_str())).collect())
+        .unwrap_or_default();
+    if !payloads.is_empty() {
+        problems.push(format!("{} block with other payloads: {:?}", block_type, payloads));
+    }
+    if payload.get("rich_text").is_some_and(|r| r.as_array().is_none_or(|t| t.is_empty())) {
+        problems.push(format!("{} block with empty rich_text", block_type));
+    }
+    if block_type == "table" {
+        let width = payload["table_width"].as_u64().unwrap_or(0);
+        for row in payload["children"].as_array().into_iter().flatten() {
+            let cells = row["table_row"]["cells"].as_array().map_or(0, |c| c.len());
+            if cells as u64 != width {
+                problems.push(format!("table row of {} cells in a table {} wide", cells, width));
+            }
+        }
+    }
+    if block_type != "table" {
+        for child in payload["children"].as_array().into_iter().flatten() {
+            problems.extend(block_problems(child));
+        }
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use serde_json::json;
+
+    use crate::markdown::coverage::{block_problems, coverage};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_coverage() {
+        let report = coverage(Path::new("samples_md/coverage")).await.unwrap();
+        for c in report.constructs.iter() {
+            assert!(c.problems.is_empty(), "{}: {:?}", c.construct, c.problems);
+        }
+        // Supporting one of these is good news: take it off the list.
+        assert_eq!(report.uncovered(), vec!["block_quote", "footnote", "html", "thematic_break"]);
+
+        let first_type = |construct: &str| {
+            let c = report.constructs.iter().find(|c| c.construct == construct).unwrap();
+            c.block_types[0].clone()
+        };
+        assert_eq!(first_type("heading"), "heading_2");
+        assert_eq!(first_type("nested_list"), "bulleted_list_item");
+        assert_eq!(first_type("numbered_list"), "numbered_list_item");
+        assert_eq!(first_type("code"), "code");
+        assert_eq!(first_type("table"), "table");
+        assert_eq!(first_type("database"), "child_database");
+        assert_eq!(first_type("image"), "image");
+        assert_eq!(first_type("callout"), "callout");
+        assert_eq!(first_type("toggle"), "toggle");
+        assert_eq!(first_type("columns"), "column_list");
+
+        let table = json!({
+            "object": "block",
+            "type": "table",
+            "table": { "table_width": 2, "children": [{ "type": "table_row", "table_row": { "cells": [[]] } }] },
+        });
+        assert_eq!(block_problems(&table), vec!["table row of 1 cells in a table 2 wide"]);
+        let paragraph = json!({ "object": "block", "type": "paragraph", "paragraph": { "rich_text": [] }, "code": {} });
+        assert_eq!(
+            block_problems(&paragraph),
+            vec!["paragraph block with other payloads: [\"code\"]", "paragraph block with empty rich_text"]
+        );
+    }
+}
@@ -0,0 +1,54 @@
+use std::fmt::{Display, Formatter};
+
+use markdown::unist::Position;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A problem found while converting a markdown file, pointing at where it happened.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub file: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, file: &str, position: Option<&Position>, message: String) -> Self {
+        Diagnostic {
+            severity,
+            file: file.to_string(),
+            line: position.map(|p| p.start.line),
+            column: position.map(|p| p.start.column),
+            message,
+        }
+    }
+
+    pub fn warning(file: &str, position: Option<&Position>, message: String) -> Self {
+        Diagnostic::new(Severity::Warning, file, position, message)
+    }
+
+    pub fn error(file: &str, position: Option<&Position>, message: String) -> Self {
+        Diagnostic::new(Severity::Error, file, position, message)
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => {
+                write!(f, "(page={}:{}:{}) {}", self.file, line, column, self.message)
+            }
+            _ => write!(f, "(page={}) {}", self.file, self.message),
+        }
+    }
+}
+
+impl std::error::Error for Diagnostic {}
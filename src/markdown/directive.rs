@@ -0,0 +1,396 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, OnceLock};
+
+use anyhow::Result;
+use markdown::mdast::Node;
+use markdown::unist::Position;
+use regex::Regex;
+
+use crate::markdown::parse::ConversionContext;
+use crate::markdown::source::{MarkdownParser, SourceParser};
+use crate::notion::block::{AppendBlockRequestChild, BlockContent, BlockType, NotionBlock};
+use crate::notion::page::validate_emoji;
+
+/// The language of the fenced code blocks `:::name` blocks are rewritten to before parsing,
+/// since markdown has no syntax of its own for them.
+pub const DIRECTIVE_LANG: &str = "notation-directive";
+
+static OPENER: OnceLock<Regex> = OnceLock::new();
+
+/// A `:::name args` block, up to the line with as many colons that closes it.
+#[derive(Clone, Debug)]
+pub struct Directive {
+    pub name: String,
+    /// The rest of the opening line.
+    pub args: String,
+    /// The markdown between the opening and closing lines.
+    pub body: String,
+    pub position: Option<Position>,
+}
+
+impl Directive {
+    /// Converts the body, with positions pointing at the lines of the file it came from.
+    pub fn convert_body(&self, ctx: &ConversionContext) -> Result<Vec<AppendBlockRequestChild>> {
+        let line = self.position.as_ref().map_or(0, |p| p.start.line);
+        ctx.convert_directive_body(&self.name, &format!("{}{}", "\n".repeat(line), self.body))
+    }
+}
+
+/// Builds the blocks of a directive.
+pub trait DirectiveBuilder: Send + Sync {
+    fn build(&self, directive: &Directive, ctx: &ConversionContext) -> Result<Vec<AppendBlockRequestChild>>;
+}
+
+impl<F> DirectiveBuilder for F
+where
+    F: Fn(&Directive, &ConversionContext) -> Result<Vec<AppendBlockRequestChild>> + Send + Sync,
+{
+    fn build(&self, directive: &Directive, ctx: &ConversionContext) -> Result<Vec<AppendBlockRequestChild>> {
+        self(directive, ctx)
+    }
+}
+
+/// The directives a conversion knows, by name. Starts with `callout`, `warning`, `toggle`,
+/// `columns`, `column`, `tabs` and `tab`.
+#[derive(Clone)]
+pub struct DirectiveRegistry {
+    builders: HashMap<String, Arc<dyn DirectiveBuilder>>,
+}
+
+impl Default for DirectiveRegistry {
+    fn default() -> Self {
+        let mut registry = DirectiveRegistry {
+            builders: HashMap::new(),
+        };
+        registry.register("callout", |d: &Directive, ctx: &ConversionContext| {
+            build_callout(d, ctx, "💡", "gray_background")
+        });
+        registry.register("warning", |d: &Directive, ctx: &ConversionContext| {
+            build_callout(d, ctx, "⚠️", "yellow_background")
+        });
+        registry.register("toggle", |d: &Directive, ctx: &ConversionContext| build_toggle(d, ctx, "Details"));
+        registry.register("tab", |d: &Directive, ctx: &ConversionContext| build_toggle(d, ctx, "Tab"));
+        registry.register("tabs", build_tabs);
+        registry.register("column", build_column);
+        registry.register("columns", build_columns);
+        registry
+    }
+}
+
+impl Debug for DirectiveRegistry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut names: Vec<&String> = self.builders.keys().collect();
+        names.sort();
+        f.debug_struct("DirectiveRegistry").field("names", &names).finish()
+    }
+}
+
+impl DirectiveRegistry {
+    /// Registers `builder` for `:::name` blocks, replacing any builder of the same name.
+    pub fn register(&mut self, name: &str, builder: impl DirectiveBuilder + 'static) {
+        self.builders.insert(name.to_lowercase(), Arc::new(builder));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn DirectiveBuilder>> {
+        self.builders.get(&name.to_lowercase())
+    }
+}
+
+/// A callout with `args` as its text, or the first paragraph of the body when there are no
+/// args. An emoji leading the args replaces the default icon.
+fn build_callout(
+    directive: &Directive,
+    ctx: &ConversionContext,
+    emoji: &str,
+    color: &str,
+) -> Result<Vec<AppendBlockRequestChild>> {
+    let mut children = directive.convert_body(ctx)?;
+    let (emoji, title) = match directive.args.split_once(' ') {
+        Some((first, rest)) if is_emoji(first) => (first.to_string(), rest.trim().to_string()),
+        _ if is_emoji(&directive.args) => (directive.args.clone(), String::new()),
+        _ => (emoji.to_string(), directive.args.clone()),
+    };
+    let rich_text = if !title.is_empty() {
+        vec![NotionBlock::new_text_block(title)]
+    } else if children.first().is_some_and(|c| matches!(c.block_type(), BlockType::Paragraph)) {
+        children.remove(0).get_rich_text_blocks().unwrap_or_default()
+    } else {
+        vec![]
+    };
+    Ok(vec![AppendBlockRequestChild::new_callout_block(
+        rich_text,
+        emoji,
+        color.to_string(),
+        children,
+    )])
+}
+
+fn build_toggle(directive: &Directive, ctx: &ConversionContext, default_title: &str) -> Result<Vec<AppendBlockRequestChild>> {
+    let title = if directive.args.is_empty() { default_title } else { directive.args.as_str() };
+    Ok(vec![AppendBlockRequestChild::new_toggle_block(
+        vec![NotionBlock::new_text_block(title.to_string())],
+        directive.convert_body(ctx)?,
+    )])
+}
+
+/// A column of a `:::columns` block. Notion only takes columns inside a column list, so
+/// anywhere else the column gives up its content.
+fn build_column(directive: &Directive, ctx: &ConversionContext) -> Result<Vec<AppendBlockRequestChild>> {
+    if ctx.directive.as_deref() != Some("columns") {
+        ctx.warn(
+            directive.position.as_ref(),
+            ":::column is only valid inside :::columns, shipping its content as is".to_string(),
+        );
+        return directive.convert_body(ctx);
+    }
+    Ok(vec![AppendBlockRequestChild::new_column_block(directive.convert_body(ctx)?)])
+}
+
+/// Columns side by side, one per `:::column` block in the body. Blocks between them make up
+/// columns of their own.
+fn build_columns(directive: &Directive, ctx: &ConversionContext) -> Result<Vec<AppendBlockRequestChild>> {
+    let mut columns: Vec<Vec<AppendBlockRequestChild>> = Vec::new();
+    let mut loose = Vec::new();
+    for block in directive.convert_body(ctx)? {
+        match block.content {
+            BlockContent::Column { column } => {
+                if !loose.is_empty() {
+                    columns.push(std::mem::take(&mut loose));
+                }
+                // Notion turns down a column without blocks.
+                if column.children.is_empty() {
+                    ctx.warn(directive.position.as_ref(), "a :::column is empty, leaving it out".to_string());
+                    continue;
+                }
+                columns.push(column.children);
+            }
+            _ => loose.push(block),
+        }
+    }
+    if !loose.is_empty() {
+        columns.push(loose);
+    }
+    if columns.len() < 2 {
+        ctx.warn(
+            directive.position.as_ref(),
+            "columns need at least two :::column blocks, shipping the content as is".to_string(),
+        );
+        return Ok(columns.into_iter().flatten().collect());
+    }
+    Ok(vec![AppendBlockRequestChild::new_column_list_block(columns)])
+}
+
+/// Tabs, one per `:::tab` block in the body. Code blocks right in the body, like the same
+/// snippet in several languages, each make a toggle titled after their `title=` or language,
+/// or with `:::tabs table`, the rows of a single table.
+fn build_tabs(directive: &Directive, ctx: &ConversionContext) -> Result<Vec<AppendBlockRequestChild>> {
+    let as_table = directive.args.trim().eq_ignore_ascii_case("table");
+    let mut blocks = Vec::new();
+    let mut rows = Vec::new();
+    let mut table_at = None;
+    for block in directive.convert_body(ctx)? {
+        let mut code = match block.content {
+            BlockContent::Code { code } => code,
+            _ => {
+                blocks.push(block);
+                continue;
+            }
+        };
+        let title = match code.caption.take() {
+            Some(caption) if !caption.is_empty() => caption,
+            _ => vec![NotionBlock::new_text_block(tab_title(code.language.as_deref().unwrap_or_default()))],
+        };
+        if as_table {
+            let cells: Vec<NotionBlock> = code
+                .rich_text
+                .into_iter()
+                .map(|t| {
+                    let annotations = t.annotations.clone().unwrap_or_default();
+                    t.with_annotations(annotations.code())
+                })
+                .collect();
+            rows.push(AppendBlockRequestChild::new_table_row_block(vec![title, cells]));
+            table_at.get_or_insert(blocks.len());
+        } else {
+            blocks.push(AppendBlockRequestChild::new_toggle_block(title, vec![BlockContent::Code { code }.into()]));
+        }
+    }
+    if let Some(at) = table_at {
+        rows.insert(
+            0,
+            AppendBlockRequestChild::new_table_row_block(vec![
+                vec![NotionBlock::new_text_block("Language".to_string())],
+                vec![NotionBlock::new_text_block("Code".to_string())],
+            ]),
+        );
+        blocks.insert(at, AppendBlockRequestChild::new_table_block(2, true, false, rows));
+    }
+    Ok(blocks)
+}
+
+/// A Notion language name as the title of a tab, `python` as `Python`.
+fn tab_title(language: &str) -> String {
+    let mut chars = language.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => "Code".to_string(),
+    }
+}
+
+/// Whether `s` is a single emoji, see [`validate_emoji`].
+fn is_emoji(s: &str) -> bool {
+    validate_emoji(s).is_ok()
+}
+
+/// Calls `f` on `node` and every node below it, including those in the bodies of directives,
+/// with the line offset to add to their positions to get the line in the file.
+pub fn walk(node: &Node, line_offset: usize, f: &mut impl FnMut(&Node, usize)) {
+    f(node, line_offset);
+    if let Node::Code(c) = node {
+        if c.lang.as_deref() == Some(DIRECTIVE_LANG) {
+            if let Ok(body) = MarkdownParser.parse(&c.value) {
+                let line = c.position.as_ref().map_or(0, |p| p.start.line) + line_offset;
+                walk(&body, line, f);
+            }
+        }
+        return;
+    }
+    for child in node.children().into_iter().flatten() {
+        walk(child, line_offset, f);
+    }
+}
+
+/// Rewrites every `:::name args` block into a fenced code block of [`DIRECTIVE_LANG`], keeping
+/// every other line where it was. A block closes at the first line made of as many colons as
+/// opened it, so nested directives take fewer colons than the ones around them. Code fences are
+/// left alone.
+pub fn preprocess(markdown: &str) -> String {
+    let opener = OPENER.get_or_init(|| Regex::new(r"^(:{3,})\s*([A-Za-z][\w-]*)\s*(.*)$").unwrap());
+    let lines: Vec<&str> = markdown.lines().collect();
+    let mut out = Vec::with_capacity(lines.len());
+    let mut fence: Option<String> = None;
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim();
+        if let Some(f) = &fence {
+            if trimmed.starts_with(f.as_str()) && trimmed.chars().all(|c| c == f.chars().next().unwrap_or('`')) {
+                fence = None;
+            }
+            out.push(line.to_string());
+            i += 1;
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            let marker = trimmed.chars().next().unwrap_or('`');
+            fence = Some(trimmed.chars().take_while(|c| *c == marker).collect());
+            out.push(line.to_string());
+            i += 1;
+            continue;
+        }
+        let c = match opener.captures(line) {
+            Some(c) => c,
+            None => {
+                out.push(line.to_string());
+                i += 1;
+                continue;
+            }
+        };
+        let colons = &c[1];
+        let end = lines[i + 1..]
+            .iter()
+            .position(|l| l.trim() == colons)
+            .map(|p| i + 1 + p);
+        let end = match end {
+            Some(end) => end,
+            None => {
+                out.push(line.to_string());
+                i += 1;
+                continue;
+            }
+        };
+        let body = &lines[i + 1..end];
+        let longest_run = body
+            .iter()
+            .map(|l| l.trim_start().chars().take_while(|c| *c == '`').count())
+            .max()
+            .unwrap_or(0);
+        let code_fence = "`".repeat(longest_run.max(2) + 1);
+        out.push(format!("{}{} {} {}", code_fence, DIRECTIVE_LANG, &c[2], &c[3]).trim_end().to_string());
+        out.extend(body.iter().map(|l| l.to_string()));
+        out.push(code_fence);
+        i = end + 1;
+    }
+    out.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::markdown::directive::{preprocess, Directive, DIRECTIVE_LANG};
+    use crate::markdown::parse::{ConversionContext, ConversionOptions, NotationParseResult};
+    use crate::markdown::source::{MarkdownParser, SourceParser};
+    use crate::notion::block::{AppendBlockRequestChild, BlockContent, BlockType};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_preprocess() {
+        let markdown = "# Page\n\n::::columns\n:::column\nLeft\n:::\n:::column\nRight\n:::\n::::\n\n```\n:::note\n```\n\n:::unclosed\n";
+        let processed = preprocess(markdown);
+        assert_eq!(processed.lines().count(), markdown.lines().count());
+        assert!(processed.contains(&format!("```{} columns", DIRECTIVE_LANG)));
+        assert!(processed.contains("```\n:::note\n```"));
+        assert!(processed.ends_with(":::unclosed"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_directives() {
+        let markdown = "# Page\n\n:::warning 🔥 Careful\nThis **breaks** things.\n:::\n\n::::columns\n:::column\nLeft\n:::\n:::column\n:::\n:::column\nRight\n:::\n::::\n\n:::badge new\nText\n:::\n\n:::mine\n:::\n\n:::warning — Quoted\n:::\n";
+        let mut options = ConversionOptions::default();
+        options.directives.register("mine", |d: &Directive, _: &ConversionContext| {
+            Ok(vec![AppendBlockRequestChild::new_paragraph_block(d.name.clone())])
+        });
+        let node = MarkdownParser.parse(markdown).unwrap();
+        let page = NotationParseResult::new(node, "page.md".to_string()).unwrap();
+        let conversion = page.to_notion("page", &HashMap::new(), &options).unwrap();
+        let types: Vec<BlockType> = conversion.request.children.iter().map(|c| c.block_type()).collect();
+        assert!(matches!(
+            types.as_slice(),
+            [BlockType::Heading1, BlockType::Callout, BlockType::ColumnList, BlockType::Paragraph, BlockType::Paragraph, BlockType::Callout]
+        ));
+        let callout = conversion.request.children[1].callout().unwrap();
+        assert_eq!(callout.rich_text[0].text.as_ref().unwrap().content, "Careful");
+        assert_eq!(callout.children.as_ref().unwrap().len(), 1);
+        let columns = conversion.request.children[2].column_list().unwrap();
+        assert_eq!(columns.children.len(), 2);
+        // A dash isn't an emoji, it stays in the text.
+        let note = conversion.request.children[5].callout().unwrap();
+        assert_eq!(note.rich_text[0].text.as_ref().unwrap().content, "— Quoted");
+        assert_eq!(conversion.warnings.len(), 2);
+        assert!(conversion.warnings[0].message.contains("is empty"));
+        assert!(conversion.warnings[1].message.contains(":::badge"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_tabs() {
+        let snippets = "```bash title=\"curl\"\ncurl https://example.com\n```\n\n```python\nrequests.get(\"https://example.com\")\n```\n";
+        let markdown = format!("::::tabs\n{}\n:::tab Go\nSoon.\n:::\n::::\n\n:::tabs table\n{}:::\n", snippets, snippets);
+        let node = MarkdownParser.parse(&markdown).unwrap();
+        let page = NotationParseResult::new(node, "page.md".to_string()).unwrap();
+        let conversion = page.to_notion("page", &HashMap::new(), &ConversionOptions::default()).unwrap();
+        let types: Vec<BlockType> = conversion.request.children.iter().map(|c| c.block_type()).collect();
+        assert!(matches!(
+            types.as_slice(),
+            [BlockType::Toggle, BlockType::Toggle, BlockType::Toggle, BlockType::Table]
+        ));
+        let title = |i: usize| conversion.request.children[i].get_rich_text_blocks().unwrap()[0].text.clone().unwrap().content;
+        assert_eq!(title(0), "curl");
+        assert_eq!(title(1), "Python");
+        assert_eq!(title(2), "Go");
+        match &conversion.request.children[3].content {
+            BlockContent::Table { table } => assert_eq!(table.children.len(), 3),
+            _ => unreachable!(),
+        }
+    }
+}
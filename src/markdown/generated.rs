@@ -0,0 +1,102 @@
+use std::collections::BTreeSet;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+
+/// The file listing what notation generated into a directory, so that the next run only
+/// removes those files and leaves the others alone.
+pub const MANIFEST_FILE: &str = ".notation-generated";
+
+/// Writes `files`, by name and contents, into `out`, removing the files an earlier run wrote
+/// there that are gone. Files notation didn't write are never removed, and overwriting one
+/// fails instead.
+pub fn write_generated(out: &Path, files: &[(String, String)]) -> Result<()> {
+    let manifest = out.join(MANIFEST_FILE);
+    let written: BTreeSet<String> = match std::fs::read_to_string(&manifest) {
+        Ok(m) => m.lines().map(|l| l.trim().to_string()).filter(|l| !l.is_empty()).collect(),
+        Err(_) => BTreeSet::new(),
+    };
+    for (name, _) in files.iter() {
+        if out.join(name).exists() && !written.contains(name) {
+            return Err(anyhow!(
+                "{} is already there and wasn't written by notation, remove it or pick another --out",
+                out.join(name).display()
+            ));
+        }
+    }
+    for name in written.iter().filter(|w| !files.iter().any(|(n, _)| n == *w)) {
+        // Only plain names are listed, a manifest edited to point elsewhere is ignored.
+        let path = out.join(name);
+        if Path::new(name).components().count() == 1 && path.is_file() {
+            std::fs::remove_file(path)?;
+        }
+    }
+    std::fs::create_dir_all(out)?;
+    for (name, contents) in files.iter() {
+        std::fs::write(out.join(name), contents)?;
+    }
+    let names: Vec<&str> = files.iter().map(|(n, _)| n.as_str()).collect();
+    std::fs::write(manifest, format!("{}\n", names.join("\n")))?;
+    Ok(())
+}
+
+/// Fails when `out` is `source`, or a directory above it, which generating into would mix the
+/// generated pages with the files they are generated from.
+pub fn check_out_dir(out: &Path, source: &Path) -> Result<()> {
+    let (out_path, source_path) = (normalize(out)?, normalize(source)?);
+    if source_path.starts_with(&out_path) {
+        return Err(anyhow!(
+            "--out {} holds {}, pick a directory of its own for the generated pages",
+            out.display(),
+            source.display()
+        ));
+    }
+    Ok(())
+}
+
+/// `path` made absolute, without `.` and `..`. An empty path is the working directory.
+fn normalize(path: &Path) -> Result<PathBuf> {
+    let path = if path.as_os_str().is_empty() { Path::new(".") } else { path };
+    let mut normalized = PathBuf::new();
+    for component in std::path::absolute(path)?.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            c => normalized.push(c),
+        }
+    }
+    Ok(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::markdown::generated::{check_out_dir, write_generated, MANIFEST_FILE};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_write_generated() {
+        let out = std::env::temp_dir().join(format!("notation-generated-{}", std::process::id()));
+        std::fs::create_dir_all(&out).unwrap();
+        std::fs::write(out.join("README.md"), "Mine").unwrap();
+        let file = |name: &str| (name.to_string(), format!("# {}\n", name));
+
+        write_generated(&out, &[file("0001-a.md"), file("0002-b.md")]).unwrap();
+        write_generated(&out, &[file("0002-b.md")]).unwrap();
+        assert!(!out.join("0001-a.md").exists());
+        assert!(out.join("0002-b.md").exists());
+        assert_eq!(std::fs::read_to_string(out.join("README.md")).unwrap(), "Mine");
+        assert_eq!(std::fs::read_to_string(out.join(MANIFEST_FILE)).unwrap(), "0002-b.md\n");
+        assert!(write_generated(&out, &[file("README.md")]).is_err());
+        std::fs::remove_dir_all(&out).unwrap();
+
+        assert!(check_out_dir(Path::new("docs/adr"), Path::new("docs/adr/")).is_err());
+        assert!(check_out_dir(Path::new("."), Path::new("docs/adr")).is_err());
+        assert!(check_out_dir(Path::new("."), Path::new("")).is_err());
+        assert!(check_out_dir(Path::new("docs/adr/../.."), Path::new("docs/adr")).is_err());
+        assert!(check_out_dir(Path::new("adr"), Path::new("docs/adr")).is_ok());
+        assert!(check_out_dir(Path::new("docs/adr/pages"), Path::new("docs/adr")).is_ok());
+    }
+}
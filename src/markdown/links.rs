@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use glob::Pattern;
+use markdown::mdast::Node;
+use reqwest::{Client, StatusCode};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::markdown::diagnostic::Diagnostic;
+use crate::markdown::directive::walk;
+use crate::markdown::parse::parse_file;
+use crate::markdown::source::line_position;
+use crate::settings::directory::SourceTree;
+use crate::settings::notation::NotationSettings;
+
+/// A link or image pointing outside the docs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExternalLink {
+    pub url: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct LinkCheckReport {
+    /// How many distinct URLs were requested.
+    pub checked: usize,
+    pub dead: Vec<Diagnostic>,
+}
+
+/// The `http` and `https` links, images and link definitions below `node`.
+pub fn extract_links(node: &Node) -> Vec<ExternalLink> {
+    let mut links = Vec::new();
+    walk(node, 0, &mut |n, offset| {
+        let (url, position) = match n {
+            Node::Link(l) => (&l.url, &l.position),
+            Node::Image(i) => (&i.url, &i.position),
+            Node::Definition(d) => (&d.url, &d.position),
+            _ => return,
+        };
+        if url.starts_with("http://") || url.starts_with("https://") {
+            links.push(ExternalLink {
+                url: url.clone(),
+                line: position.as_ref().map_or(0, |p| p.start.line) + offset,
+                column: position.as_ref().map_or(1, |p| p.start.column),
+            });
+        }
+    });
+    links
+}
+
+/// Requests every external URL under `dir` once, at most `[link_check] concurrency` at a
+/// time, and reports each place a dead one is linked from. URLs matching an `allow` pattern
+/// aren't requested.
+pub async fn check_links(settings: &NotationSettings, dir: &str) -> Result<LinkCheckReport> {
+    let link_settings = &settings.link_check;
+    let allow = link_settings
+        .allow
+        .iter()
+        .map(|p| Pattern::new(p).map_err(|e| anyhow!("invalid allowed link pattern \"{}\": {}", p, e)))
+        .collect::<Result<Vec<Pattern>>>()?;
+    let tree = SourceTree::load(dir, settings.content.clone())?;
+
+    let mut occurrences: HashMap<String, Vec<(String, usize, usize)>> = HashMap::new();
+    for relative_path in tree.files.iter() {
+        let path = tree.root.join(relative_path);
+        let parsed = parse_file(&path).await?;
+        for link in extract_links(parsed.node()) {
+            if allow.iter().any(|p| p.matches(&link.url)) {
+                continue;
+            }
+            occurrences
+                .entry(link.url)
+                .or_default()
+                .push((path.display().to_string(), link.line, link.column));
+        }
+    }
+
+    let client = Client::builder()
+        .timeout(Duration::from_secs(link_settings.timeout_secs))
+        .user_agent(concat!("notation/", env!("CARGO_PKG_VERSION")))
+        .build()?;
+    let permits = Arc::new(Semaphore::new(link_settings.concurrency.max(1)));
+    let mut requests = JoinSet::new();
+    for url in occurrences.keys().cloned() {
+        let client = client.clone();
+        let permits = permits.clone();
+        requests.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            let result = request(&client, &url).await;
+            (url, result)
+        });
+    }
+
+    let mut report = LinkCheckReport {
+        checked: occurrences.len(),
+        ..Default::default()
+    };
+    while let Some(joined) = requests.join_next().await {
+        let (url, result) = joined?;
+        if let Err(reason) = result {
+            for (file, line, column) in occurrences.get(&url).into_iter().flatten() {
+                let mut position = line_position(*line);
+                if let Some(p) = position.as_mut() {
+                    p.start.column = *column;
+                }
+                report.dead.push(Diagnostic::warning(
+                    file,
+                    position.as_ref(),
+                    format!("dead link {}: {}", url, reason),
+                ));
+            }
+        }
+    }
+    report
+        .dead
+        .sort_by(|a, b| (&a.file, a.line, a.column).cmp(&(&b.file, b.line, b.column)));
+    Ok(report)
+}
+
+/// HEADs `url`, falling back to a GET for servers that don't answer HEAD requests.
+async fn request(client: &Client, url: &str) -> std::result::Result<(), String> {
+    let is_refused = |s: StatusCode| {
+        matches!(
+            s,
+            StatusCode::METHOD_NOT_ALLOWED | StatusCode::FORBIDDEN | StatusCode::NOT_IMPLEMENTED
+        )
+    };
+    let status = match client.head(url).send().await {
+        Ok(r) if !is_refused(r.status()) => r.status(),
+        _ => client.get(url).send().await.map_err(|e| e.to_string())?.status(),
+    };
+    if status.is_client_error() || status.is_server_error() {
+        return Err(status.to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::markdown::links::{extract_links, ExternalLink};
+    use crate::markdown::source::{MarkdownParser, SourceParser};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_extract_links() {
+        let markdown = "# Page\n\nSee [docs](https://example.com/docs) and [local](./other.md).\n\n![logo](http://example.com/logo.png)\n\n:::callout\n<https://example.com/inner>\n:::\n\n[ref]: https://example.com/ref\n";
+        let node = MarkdownParser.parse(markdown).unwrap();
+        let links = extract_links(&node);
+        let link = |url: &str, line, column| ExternalLink {
+            url: url.to_string(),
+            line,
+            column,
+        };
+        assert_eq!(
+            links,
+            vec![
+                link("https://example.com/docs", 3, 5),
+                link("http://example.com/logo.png", 5, 1),
+                link("https://example.com/inner", 8, 1),
+                link("https://example.com/ref", 11, 1),
+            ]
+        );
+    }
+}
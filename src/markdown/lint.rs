@@ -0,0 +1,332 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, OnceLock};
+
+use anyhow::{anyhow, Result};
+use regex::Regex;
+
+use crate::markdown::diagnostic::Diagnostic;
+use crate::markdown::source::line_position;
+use crate::settings::directory::SourceTree;
+use crate::settings::notation::{LintSettings, NotationSettings};
+
+static INLINE_CODE: OnceLock<Regex> = OnceLock::new();
+static URL: OnceLock<Regex> = OnceLock::new();
+static WORD: OnceLock<Regex> = OnceLock::new();
+
+/// A line of prose, outside of code fences.
+#[derive(Clone, Debug)]
+pub struct ProseLine<'a> {
+    /// Starting at 1.
+    pub number: usize,
+    pub text: &'a str,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LintIssue {
+    pub line: usize,
+    /// Starting at 1.
+    pub column: usize,
+    pub message: String,
+}
+
+/// Checks the prose of a file.
+pub trait LintRule: Send + Sync {
+    fn check(&self, lines: &[ProseLine]) -> Result<Vec<LintIssue>>;
+}
+
+impl<F> LintRule for F
+where
+    F: Fn(&[ProseLine]) -> Result<Vec<LintIssue>> + Send + Sync,
+{
+    fn check(&self, lines: &[ProseLine]) -> Result<Vec<LintIssue>> {
+        self(lines)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct LintReport {
+    pub files: usize,
+    pub issues: Vec<Diagnostic>,
+}
+
+/// The rules a lint runs, in order.
+#[derive(Clone, Default)]
+pub struct TextLinter {
+    rules: Vec<Arc<dyn LintRule>>,
+}
+
+impl TextLinter {
+    /// The rules enabled under `[lint]`.
+    pub fn from_settings(settings: &LintSettings) -> Self {
+        let mut linter = TextLinter::default();
+        if settings.trailing_whitespace {
+            linter.push(trailing_whitespace);
+        }
+        if settings.double_spaces {
+            linter.push(double_spaces);
+        }
+        if !settings.banned_words.is_empty() {
+            linter.push(BannedWords::new(&settings.banned_words));
+        }
+        if let Some(dictionary) = &settings.hunspell {
+            linter.push(Hunspell {
+                dictionary: dictionary.clone(),
+                allowed_words: settings.allowed_words.clone(),
+            });
+        }
+        linter
+    }
+
+    pub fn push(&mut self, rule: impl LintRule + 'static) {
+        self.rules.push(Arc::new(rule));
+    }
+
+    pub fn with_rule(mut self, rule: impl LintRule + 'static) -> Self {
+        self.push(rule);
+        self
+    }
+
+    /// The issues of every rule in the prose of `contents`, by line and column.
+    pub fn lint(&self, contents: &str) -> Result<Vec<LintIssue>> {
+        let lines = prose_lines(contents);
+        let mut issues = Vec::new();
+        for rule in self.rules.iter() {
+            issues.extend(rule.check(&lines)?);
+        }
+        issues.sort_by_key(|i| (i.line, i.column));
+        Ok(issues)
+    }
+
+    /// Lints every file a ship of `dir` picks up.
+    pub fn lint_tree(&self, settings: &NotationSettings, dir: &str) -> Result<LintReport> {
+        let tree = SourceTree::load(dir, settings.content.clone())?;
+        let mut report = LintReport::default();
+        for relative_path in tree.files.iter() {
+            let path = tree.root.join(relative_path);
+            let contents = std::fs::read_to_string(&path)
+                .map_err(|e| anyhow!("failed to read {}: {}", path.display(), e))?;
+            for issue in self.lint(&contents)? {
+                let mut position = line_position(issue.line);
+                if let Some(p) = position.as_mut() {
+                    p.start.column = issue.column;
+                }
+                report.issues.push(Diagnostic::warning(
+                    &path.display().to_string(),
+                    position.as_ref(),
+                    issue.message,
+                ));
+            }
+            report.files += 1;
+        }
+        Ok(report)
+    }
+}
+
+/// The lines of `contents` outside of code fences.
+pub fn prose_lines(contents: &str) -> Vec<ProseLine<'_>> {
+    let mut lines = Vec::new();
+    let mut fence: Option<String> = None;
+    for (i, text) in contents.lines().enumerate() {
+        let trimmed = text.trim_start();
+        if let Some(f) = &fence {
+            if trimmed.trim_end().starts_with(f.as_str())
+                && trimmed.trim_end().chars().all(|c| f.starts_with(c))
+            {
+                fence = None;
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            let marker = trimmed.chars().next().unwrap_or('`');
+            fence = Some(trimmed.chars().take_while(|c| *c == marker).collect());
+            continue;
+        }
+        lines.push(ProseLine { number: i + 1, text });
+    }
+    lines
+}
+
+fn column(text: &str, byte_index: usize) -> usize {
+    text[..byte_index].chars().count() + 1
+}
+
+/// Whitespace at the end of a line. Two spaces or more after some text, with the paragraph going
+/// on the next line, are a markdown hard break and left alone.
+pub fn trailing_whitespace(lines: &[ProseLine]) -> Result<Vec<LintIssue>> {
+    let is_hard_break = |i: usize| {
+        let l = &lines[i];
+        let trailing = &l.text[l.text.trim_end().len()..];
+        let is_continued = lines
+            .get(i + 1)
+            .is_some_and(|n| n.number == l.number + 1 && !n.text.trim().is_empty());
+        trailing.len() >= 2 && !trailing.contains('\t') && !l.text.trim().is_empty() && is_continued
+    };
+    Ok(lines
+        .iter()
+        .enumerate()
+        .filter(|(i, l)| l.text.ends_with([' ', '\t']) && !is_hard_break(*i))
+        .map(|(_, l)| LintIssue {
+            line: l.number,
+            column: column(l.text, l.text.trim_end().len()),
+            message: "trailing whitespace".to_string(),
+        })
+        .collect())
+}
+
+/// Two spaces between words. Indentation and table rows, which are often aligned by hand,
+/// are left alone.
+pub fn double_spaces(lines: &[ProseLine]) -> Result<Vec<LintIssue>> {
+    let mut issues = Vec::new();
+    for l in lines {
+        let trimmed = l.text.trim();
+        if trimmed.starts_with('|') {
+            continue;
+        }
+        let indent = l.text.len() - l.text.trim_start().len();
+        if let Some(i) = trimmed.find("  ") {
+            issues.push(LintIssue {
+                line: l.number,
+                column: column(l.text, indent + i),
+                message: "double space".to_string(),
+            });
+        }
+    }
+    Ok(issues)
+}
+
+/// Words and phrases that shouldn't show up in the docs, matched as whole words regardless
+/// of case.
+pub struct BannedWords {
+    patterns: Vec<(String, Regex)>,
+}
+
+impl BannedWords {
+    pub fn new(words: &[String]) -> Self {
+        BannedWords {
+            patterns: words
+                .iter()
+                .filter_map(|w| {
+                    Regex::new(&format!(r"(?i)\b{}\b", regex::escape(w)))
+                        .ok()
+                        .map(|r| (w.clone(), r))
+                })
+                .collect(),
+        }
+    }
+}
+
+impl LintRule for BannedWords {
+    fn check(&self, lines: &[ProseLine]) -> Result<Vec<LintIssue>> {
+        let mut issues = Vec::new();
+        for l in lines {
+            for (word, pattern) in self.patterns.iter() {
+                for m in pattern.find_iter(l.text) {
+                    issues.push(LintIssue {
+                        line: l.number,
+                        column: column(l.text, m.start()),
+                        message: format!("banned word \"{}\"", word),
+                    });
+                }
+            }
+        }
+        Ok(issues)
+    }
+}
+
+/// Spellchecks with `hunspell -l`, which has to be installed with the dictionary. Inline code
+/// and URLs aren't checked.
+pub struct Hunspell {
+    /// Like `en_US`.
+    pub dictionary: String,
+    pub allowed_words: Vec<String>,
+}
+
+impl LintRule for Hunspell {
+    fn check(&self, lines: &[ProseLine]) -> Result<Vec<LintIssue>> {
+        let inline_code = INLINE_CODE.get_or_init(|| Regex::new(r"`[^`]*`").unwrap());
+        let url = URL.get_or_init(|| Regex::new(r"(\]\([^)]*\)|https?://\S+|<[^>]*>)").unwrap());
+        let word = WORD.get_or_init(|| Regex::new(r"[\p{L}']+").unwrap());
+        let masked: Vec<String> = lines
+            .iter()
+            .map(|l| {
+                let text = inline_code.replace_all(l.text, |c: &regex::Captures| " ".repeat(c[0].len()));
+                url.replace_all(&text, |c: &regex::Captures| " ".repeat(c[0].len())).into_owned()
+            })
+            .collect();
+
+        let mut child = Command::new("hunspell")
+            .args(["-l", "-d", &self.dictionary])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| anyhow!("failed to run hunspell, is it installed? {}", e))?;
+        // Written from its own thread, since hunspell fills its output while it reads: waiting
+        // on a long file's input before reading any output would hang both.
+        let writer = child.stdin.take().map(|mut stdin| {
+            let input = masked.join("\n");
+            std::thread::spawn(move || stdin.write_all(input.as_bytes()))
+        });
+        let output = child.wait_with_output()?;
+        if let Some(writer) = writer {
+            writer
+                .join()
+                .map_err(|_| anyhow!("failed to write to hunspell"))??;
+        }
+        if !output.status.success() {
+            return Err(anyhow!(
+                "hunspell failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        let misspelled: Vec<String> = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::to_string)
+            .filter(|w| !self.allowed_words.iter().any(|a| a.eq_ignore_ascii_case(w)))
+            .collect();
+
+        let mut issues = Vec::new();
+        for (l, text) in lines.iter().zip(masked.iter()) {
+            for m in word.find_iter(text) {
+                if misspelled.iter().any(|w| w == m.as_str()) {
+                    issues.push(LintIssue {
+                        line: l.number,
+                        column: column(text, m.start()),
+                        message: format!("unknown word \"{}\"", m.as_str()),
+                    });
+                }
+            }
+        }
+        Ok(issues)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::markdown::lint::{LintIssue, TextLinter};
+    use crate::settings::notation::LintSettings;
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_lint() {
+        let settings = LintSettings {
+            banned_words: vec!["simply".to_string()],
+            ..Default::default()
+        };
+        let contents = "# Page \n\nJust  run it.\n\n```\nindented  code \n```\n\n| a  | b |\n\nSimply ship, simplyfied.\n\nFirst line  \nsecond line.  \n";
+        let issues = TextLinter::from_settings(&settings).lint(contents).unwrap();
+        let found: Vec<(usize, usize)> = issues.iter().map(|i| (i.line, i.column)).collect();
+        // The hard break on line 13 is fine, the spaces ending the paragraph on line 14 aren't.
+        assert_eq!(found, vec![(1, 7), (3, 5), (11, 1), (14, 13)]);
+        assert_eq!(issues[2].message, "banned word \"simply\"");
+
+        let linter = TextLinter::default().with_rule(|_: &[crate::markdown::lint::ProseLine]| {
+            Ok(vec![LintIssue {
+                line: 1,
+                column: 1,
+                message: "custom".to_string(),
+            }])
+        });
+        assert_eq!(linter.lint("text").unwrap()[0].message, "custom");
+    }
+}
@@ -1,2 +1,24 @@
+pub mod adr;
+pub mod anchors;
+#[cfg(feature = "asciidoc")]
+pub mod asciidoc;
+pub mod changelog;
+pub mod code;
+pub mod coverage;
+pub mod diagnostic;
+pub mod directive;
+pub mod generated;
+pub mod links;
+pub mod lint;
+pub mod openapi;
 pub mod parse;
+pub mod redaction;
+#[cfg(feature = "rst")]
+pub mod rst;
+pub mod rustdoc;
+pub mod samples;
+pub mod sections;
+pub mod source;
+pub mod svg;
+pub mod template;
 mod util;
@@ -0,0 +1,529 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Map, Value};
+use yaml_rust2::{Yaml, YamlLoader};
+
+use crate::markdown::anchors::github_slug;
+use crate::markdown::rustdoc::{ApiPage, ApiReference};
+
+const METHODS: [&str; 8] = ["get", "post", "put", "patch", "delete", "head", "options", "trace"];
+/// How deep examples are built out of nested schemas, so recursive schemas end.
+const MAX_EXAMPLE_DEPTH: usize = 6;
+const SCHEMAS_PAGE: &str = "schemas.md";
+
+/// An operation of the spec, a method on a path.
+struct Endpoint<'a> {
+    method: &'a str,
+    path: &'a str,
+    operation: &'a Value,
+    /// The parameters of the path, which apply to all its operations.
+    path_parameters: &'a Value,
+}
+
+impl Endpoint<'_> {
+    fn title(&self) -> String {
+        format!("{} {}", self.method.to_uppercase(), self.path)
+    }
+
+    /// The file name of the endpoint's page, without `.md`.
+    fn file_stem(&self) -> String {
+        let path = github_slug(&self.path.replace(['/', '{', '}', '.'], " "));
+        let path = path.trim_matches('-');
+        match path.is_empty() {
+            true => self.method.to_string(),
+            false => format!("{}-{}", self.method, path.replace("--", "-")),
+        }
+    }
+
+    /// The directory of the endpoint, after its first tag.
+    fn tag(&self) -> Option<&str> {
+        self.operation["tags"][0].as_str()
+    }
+}
+
+impl ApiReference {
+    /// The reference of the OpenAPI spec at `path`, in YAML or JSON.
+    pub fn load_openapi(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("failed to read OpenAPI spec {}: {}", path.display(), e))?;
+        let spec = match serde_json::from_str::<Value>(&contents) {
+            Ok(spec) => spec,
+            Err(_) => {
+                let docs = YamlLoader::load_from_str(&contents)
+                    .map_err(|e| anyhow!("failed to read OpenAPI spec {}: {}", path.display(), e))?;
+                docs.first().map(yaml_to_json).unwrap_or(Value::Null)
+            }
+        };
+        ApiReference::from_openapi(&spec)
+    }
+
+    /// A page per endpoint of an OpenAPI 3 (or Swagger 2) spec, in a directory per tag, under an
+    /// intro listing them all, and a page of the schemas they refer to.
+    pub fn from_openapi(spec: &Value) -> Result<Self> {
+        let paths = spec["paths"]
+            .as_object()
+            .ok_or_else(|| anyhow!("the OpenAPI spec has no paths"))?;
+        let title = spec["info"]["title"].as_str().unwrap_or("API");
+        let name = github_slug(title);
+        let root = PathBuf::from(&name);
+
+        let mut endpoints = Vec::new();
+        for (path, item) in paths {
+            for method in METHODS {
+                if item[method].is_object() {
+                    endpoints.push(Endpoint {
+                        method,
+                        path,
+                        operation: &item[method],
+                        path_parameters: &item["parameters"],
+                    });
+                }
+            }
+        }
+
+        let mut intro = frontmatter(title);
+        if let Some(version) = spec["info"]["version"].as_str() {
+            intro.push_str(&format!("Version `{}`\n\n", version));
+        }
+        if let Some(description) = spec["info"]["description"].as_str() {
+            intro.push_str(&format!("{}\n\n", description.trim()));
+        }
+        let servers: Vec<String> = spec["servers"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|s| s["url"].as_str())
+            .map(|url| format!("- `{}`\n", url))
+            .collect();
+        if !servers.is_empty() {
+            intro.push_str(&format!("# Servers\n\n{}\n", servers.concat()));
+        }
+        intro.push_str("# Endpoints\n\n");
+
+        let mut pages = Vec::new();
+        let mut files = HashSet::new();
+        for endpoint in endpoints.iter() {
+            let dir = endpoint.tag().map_or(PathBuf::new(), |t| PathBuf::from(github_slug(t)));
+            let depth = if endpoint.tag().is_some() { 1 } else { 0 };
+            // Paths like `/pets/{id}` and `/pets/id` make the same name, the later ones are numbered.
+            let stem = endpoint.file_stem();
+            let mut file = dir.join(format!("{}.md", stem));
+            for n in 2.. {
+                if files.insert(file.clone()) {
+                    break;
+                }
+                file = dir.join(format!("{}-{}.md", stem, n));
+            }
+            let summary = endpoint.operation["summary"]
+                .as_str()
+                .map(|s| format!(" — {}", s))
+                .unwrap_or_default();
+            intro.push_str(&format!(
+                "- [{}]({}){}\n",
+                escape(&endpoint.title()),
+                file.display(),
+                summary
+            ));
+            pages.push(ApiPage {
+                path: root.join(file),
+                markdown: endpoint_page(spec, endpoint, &"../".repeat(depth)),
+            });
+        }
+
+        let mut tags: Vec<&str> = endpoints.iter().filter_map(|e| e.tag()).collect();
+        tags.sort();
+        tags.dedup();
+        for tag in tags {
+            let mut tag_intro = frontmatter(tag);
+            let description = spec["tags"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .find(|t| t["name"] == tag)
+                .and_then(|t| t["description"].as_str());
+            if let Some(description) = description {
+                tag_intro.push_str(&format!("{}\n", description.trim()));
+            }
+            pages.push(ApiPage {
+                path: root.join(github_slug(tag)).join("intro.md"),
+                markdown: tag_intro,
+            });
+        }
+
+        let schemas = schemas(spec);
+        if !schemas.is_empty() {
+            intro.push_str(&format!("\nThe bodies of requests and responses are described under [Schemas]({}).\n", SCHEMAS_PAGE));
+            let mut page = frontmatter("Schemas");
+            for (name, schema) in schemas {
+                page.push_str(&format!("# {}\n\n", name));
+                if let Some(description) = schema["description"].as_str() {
+                    page.push_str(&format!("{}\n\n", description.trim()));
+                }
+                let example = serde_json::to_string_pretty(&example(spec, schema, 0))?;
+                page.push_str(&code("json", &example));
+            }
+            pages.push(ApiPage {
+                path: root.join(SCHEMAS_PAGE),
+                markdown: page,
+            });
+        }
+        pages.push(ApiPage {
+            path: root.join("intro.md"),
+            markdown: intro,
+        });
+        Ok(ApiReference { name, pages })
+    }
+}
+
+/// The page of an endpoint. `up` leads from its directory back to that of the intro.
+fn endpoint_page(spec: &Value, endpoint: &Endpoint, up: &str) -> String {
+    let operation = endpoint.operation;
+    let mut page = frontmatter(&endpoint.title());
+    if let Some(summary) = operation["summary"].as_str() {
+        page.push_str(&format!("**{}**\n\n", summary.trim()));
+    }
+    if let Some(description) = operation["description"].as_str() {
+        page.push_str(&format!("{}\n\n", description.trim()));
+    }
+    if operation["deprecated"] == true {
+        page.push_str("> ⚠️ Deprecated\n\n");
+    }
+
+    let mut parameters: Vec<&Value> = Vec::new();
+    for p in endpoint.path_parameters.as_array().into_iter().chain(operation["parameters"].as_array()).flatten() {
+        let p = resolve(spec, p);
+        // Parameters of the operation replace those of the path with the same name and place.
+        parameters.retain(|q| q["name"] != p["name"] || q["in"] != p["in"]);
+        parameters.push(p);
+    }
+    let (body_parameters, parameters): (Vec<&Value>, Vec<&Value>) = parameters.into_iter().partition(|p| p["in"] == "body");
+    if !parameters.is_empty() {
+        page.push_str("# Parameters\n\n| Name | In | Type | Required | Description |\n| --- | --- | --- | --- | --- |\n");
+        for p in parameters {
+            let schema = if p["schema"].is_null() { p } else { &p["schema"] };
+            page.push_str(&format!(
+                "| `{}` | {} | {} | {} | {} |\n",
+                p["name"].as_str().unwrap_or_default(),
+                p["in"].as_str().unwrap_or_default(),
+                escape(&type_name(schema, up)),
+                if p["required"] == true { "yes" } else { "no" },
+                escape(p["description"].as_str().unwrap_or_default())
+            ));
+        }
+        page.push('\n');
+    }
+
+    let body = resolve(spec, &operation["requestBody"]);
+    if body.is_object() || !body_parameters.is_empty() {
+        page.push_str("# Request body\n\n");
+        if let Some(description) = body["description"].as_str().or(body_parameters.first().and_then(|p| p["description"].as_str())) {
+            page.push_str(&format!("{}\n\n", description.trim()));
+        }
+        match body_parameters.first() {
+            Some(p) => page.push_str(&media(spec, None, p, up)),
+            None => page.push_str(&content(spec, &body["content"], up)),
+        }
+    }
+
+    if let Some(responses) = operation["responses"].as_object() {
+        page.push_str("# Responses\n\n");
+        for (status, response) in responses {
+            let response = resolve(spec, response);
+            let description = response["description"].as_str().unwrap_or_default().trim();
+            match description.is_empty() {
+                true => page.push_str(&format!("## {}\n\n", status)),
+                false => page.push_str(&format!("## {} — {}\n\n", status, description)),
+            }
+            match response["content"].is_object() {
+                true => page.push_str(&content(spec, &response["content"], up)),
+                false if !response["schema"].is_null() => page.push_str(&media(spec, None, response, up)),
+                false => {}
+            }
+        }
+    }
+    page
+}
+
+/// The media types of a request or response body, each with its schema and an example.
+fn content(spec: &Value, content: &Value, up: &str) -> String {
+    content
+        .as_object()
+        .into_iter()
+        .flatten()
+        .map(|(media_type, m)| media(spec, Some(media_type), m, up))
+        .collect()
+}
+
+fn media(spec: &Value, media_type: Option<&str>, media: &Value, up: &str) -> String {
+    let mut out = String::new();
+    let schema = &media["schema"];
+    match (media_type, schema.is_null()) {
+        (Some(t), false) => out.push_str(&format!("`{}`, {}\n\n", t, type_name(schema, up))),
+        (Some(t), true) => out.push_str(&format!("`{}`\n\n", t)),
+        (None, false) => out.push_str(&format!("{}\n\n", type_name(schema, up))),
+        (None, true) => {}
+    }
+    let named = media["examples"].as_object().into_iter().flatten().filter_map(|(name, e)| {
+        let e = resolve(spec, e);
+        (!e["value"].is_null()).then(|| (Some(name.as_str()), e["value"].clone()))
+    });
+    let mut examples: Vec<(Option<&str>, Value)> = named.collect();
+    if examples.is_empty() {
+        let example = match &media["example"] {
+            Value::Null => example(spec, schema, 0),
+            example => example.clone(),
+        };
+        if !example.is_null() {
+            examples.push((None, example));
+        }
+    }
+    let lang = match media_type {
+        Some(t) if !t.contains("json") && t.contains("xml") => "xml",
+        _ => "json",
+    };
+    for (name, example) in examples {
+        if let Some(name) = name {
+            out.push_str(&format!("Example `{}`:\n\n", name));
+        }
+        let text = match example {
+            Value::String(s) => s,
+            example => serde_json::to_string_pretty(&example).unwrap_or_default(),
+        };
+        out.push_str(&code(lang, &text));
+    }
+    out
+}
+
+/// The type of a schema, linking to the schemas page for references to named schemas.
+fn type_name(schema: &Value, up: &str) -> String {
+    if let Some(reference) = schema["$ref"].as_str() {
+        let name = reference.rsplit('/').next().unwrap_or(reference);
+        return format!("[{}]({}{}#{})", name, up, SCHEMAS_PAGE, github_slug(name));
+    }
+    for combinator in ["oneOf", "anyOf", "allOf"] {
+        if let Some(options) = schema[combinator].as_array() {
+            let separator = if combinator == "allOf" { " & " } else { " or " };
+            let names: Vec<String> = options.iter().map(|o| type_name(o, up)).collect();
+            return names.join(separator);
+        }
+    }
+    let ty = schema["type"].as_str().unwrap_or("object");
+    match (ty, schema["format"].as_str()) {
+        ("array", _) => format!("array of {}", type_name(&schema["items"], up)),
+        (ty, Some(format)) => format!("{} ({})", ty, format),
+        (ty, None) => ty.to_string(),
+    }
+}
+
+/// An example of a schema: its own `example`, or one made up from its properties.
+fn example(spec: &Value, schema: &Value, depth: usize) -> Value {
+    if depth > MAX_EXAMPLE_DEPTH || schema.is_null() {
+        return Value::Null;
+    }
+    let schema = resolve(spec, schema);
+    if !schema["example"].is_null() {
+        return schema["example"].clone();
+    }
+    if let Some(value) = schema["default"].as_str().map(Value::from).or(schema["enum"][0].as_str().map(Value::from)) {
+        return value;
+    }
+    if let Some(all) = schema["allOf"].as_array() {
+        let mut merged = Map::new();
+        for s in all {
+            if let Value::Object(o) = example(spec, s, depth + 1) {
+                merged.extend(o);
+            }
+        }
+        return Value::Object(merged);
+    }
+    if let Some(first) = schema["oneOf"][0].as_object().or(schema["anyOf"][0].as_object()) {
+        return example(spec, &Value::Object(first.clone()), depth + 1);
+    }
+    match schema["type"].as_str() {
+        Some("array") => json!([example(spec, &schema["items"], depth + 1)]),
+        Some("string") => match schema["format"].as_str() {
+            Some("date-time") => json!("2024-01-01T00:00:00Z"),
+            Some("date") => json!("2024-01-01"),
+            Some("uuid") => json!("3fa85f64-5717-4562-b3fc-2c963f66afa6"),
+            Some("email") => json!("user@example.com"),
+            Some("uri") | Some("url") => json!("https://example.com"),
+            _ => json!("string"),
+        },
+        Some("integer") => json!(0),
+        Some("number") => json!(0.0),
+        Some("boolean") => json!(true),
+        _ => {
+            let properties = schema["properties"].as_object().into_iter().flatten();
+            Value::Object(
+                properties
+                    .map(|(name, p)| (name.clone(), example(spec, p, depth + 1)))
+                    .collect(),
+            )
+        }
+    }
+}
+
+/// The named schemas of the spec, under `components/schemas` in OpenAPI 3 and `definitions`
+/// in Swagger 2.
+fn schemas(spec: &Value) -> Vec<(&String, &Value)> {
+    spec["components"]["schemas"]
+        .as_object()
+        .or(spec["definitions"].as_object())
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// What a `{"$ref": "#/components/..."}` points to in the spec, or `value` itself.
+fn resolve<'a>(spec: &'a Value, value: &'a Value) -> &'a Value {
+    let mut value = value;
+    // References can point to references, but not forever.
+    for _ in 0..MAX_EXAMPLE_DEPTH {
+        match value["$ref"].as_str().and_then(|r| r.strip_prefix('#')) {
+            Some(pointer) => value = spec.pointer(pointer).unwrap_or(&Value::Null),
+            None => break,
+        }
+    }
+    value
+}
+
+fn frontmatter(title: &str) -> String {
+    format!("---\ntitle: \"{}\"\n---\n\n", title.replace('"', "'"))
+}
+
+fn code(lang: &str, text: &str) -> String {
+    format!("```{}\n{}\n```\n\n", lang, text.trim_end())
+}
+
+/// Text that stays on its line and in its table cell.
+fn escape(text: &str) -> String {
+    text.replace('|', "\\|").replace(['\n', '\r'], " ")
+}
+
+fn yaml_to_json(yaml: &Yaml) -> Value {
+    match yaml {
+        Yaml::Real(s) => s.parse::<f64>().map(Value::from).unwrap_or(Value::String(s.clone())),
+        Yaml::Integer(i) => Value::from(*i),
+        Yaml::String(s) => Value::String(s.clone()),
+        Yaml::Boolean(b) => Value::Bool(*b),
+        Yaml::Array(a) => Value::Array(a.iter().map(yaml_to_json).collect()),
+        Yaml::Hash(h) => Value::Object(
+            h.iter()
+                .map(|(k, v)| {
+                    let key = match k {
+                        Yaml::String(s) | Yaml::Real(s) => s.clone(),
+                        Yaml::Integer(i) => i.to_string(),
+                        Yaml::Boolean(b) => b.to_string(),
+                        _ => String::new(),
+                    };
+                    (key, yaml_to_json(v))
+                })
+                .collect(),
+        ),
+        Yaml::Alias(_) | Yaml::Null | Yaml::BadValue => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::markdown::rustdoc::ApiReference;
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_openapi() {
+        let spec = r##"
+openapi: 3.0.3
+info:
+  title: Pet Store
+  version: 1.2.0
+paths:
+  /pets/{petId}:
+    parameters:
+      - name: petId
+        in: path
+        required: true
+        description: The id of the pet
+        schema:
+          type: integer
+          format: int64
+    get:
+      tags: [pets]
+      summary: Find a pet
+      responses:
+        200:
+          description: The pet
+          content:
+            application/json:
+              schema:
+                $ref: "#/components/schemas/Pet"
+        404:
+          description: No such pet
+  /toys/id:
+    get:
+      tags: [toys]
+      summary: The toy with the id "id"
+      responses:
+        200:
+          description: The toy
+  /toys/{id}:
+    get:
+      tags: [toys]
+      summary: Find a toy
+      responses:
+        200:
+          description: The toy
+  /health:
+    get:
+      summary: Check health
+      responses:
+        "204":
+          description: Healthy
+components:
+  schemas:
+    Pet:
+      type: object
+      properties:
+        id: {type: integer}
+        name: {type: string, example: Rex}
+        tags:
+          type: array
+          items: {type: string}
+"##;
+        let path = std::env::temp_dir().join(format!("notation-openapi-{}.yaml", std::process::id()));
+        std::fs::write(&path, spec).unwrap();
+        let reference = ApiReference::load_openapi(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(reference.name, "pet-store");
+        let mut paths: Vec<String> = reference.pages.iter().map(|p| p.path.display().to_string()).collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            [
+                "pet-store/get-health.md",
+                "pet-store/intro.md",
+                "pet-store/pets/get-pets-petid.md",
+                "pet-store/pets/intro.md",
+                "pet-store/schemas.md",
+                "pet-store/toys/get-toys-id-2.md",
+                "pet-store/toys/get-toys-id.md",
+                "pet-store/toys/intro.md",
+            ]
+        );
+        let page = |path: &str| reference.pages.iter().find(|p| p.path.ends_with(path)).unwrap().markdown.clone();
+
+        let pet = page("pets/get-pets-petid.md");
+        assert!(pet.starts_with("---\ntitle: \"GET /pets/{petId}\"\n---"));
+        assert!(pet.contains("| `petId` | path | integer (int64) | yes | The id of the pet |"));
+        assert!(pet.contains("## 200 — The pet\n\n`application/json`, [Pet](../schemas.md#pet)"));
+        assert!(pet.contains("```json\n{\n  \"id\": 0,\n  \"name\": \"Rex\",\n  \"tags\": [\n    \"string\"\n  ]\n}\n```"));
+        assert!(pet.contains("## 404 — No such pet"));
+
+        let intro = page("pet-store/intro.md");
+        assert!(intro.contains("Version `1.2.0`"));
+        assert!(intro.contains("- [GET /pets/{petId}](pets/get-pets-petid.md) — Find a pet"));
+        assert!(intro.contains("- [GET /toys/{id}](toys/get-toys-id-2.md) — Find a toy"));
+        assert!(page("schemas.md").contains("# Pet"));
+    }
+}
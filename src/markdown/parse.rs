@@ -1,283 +1,771 @@
-use std::collections::HashMap;
-use std::path::{Component, Path, PathBuf};
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::ffi::{OsStr, OsString};
+use std::path::{Component, Path, PathBuf, Prefix};
 use std::str::FromStr;
+use std::sync::OnceLock;
 
 use anyhow::{anyhow, Result};
-use clap::{Parser};
-use markdown::mdast::{List, Node, Paragraph, Table};
-use markdown::ParseOptions;
+use chrono::NaiveDate;
+use clap::{Parser, ValueEnum};
+use glob::Pattern;
+use markdown::mdast::{Code, Link, List, Node, Paragraph, Table};
+use markdown::unist::Position;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use regex::Regex;
 use reqwest::Url;
 
+use crate::markdown::anchors::{anchor_url, file_heading_slugs};
+use crate::markdown::code::{build_code_rich_text, CodeMeta};
+use crate::markdown::diagnostic::Diagnostic;
+use crate::markdown::directive::{walk, Directive, DirectiveRegistry, DIRECTIVE_LANG};
+use crate::markdown::redaction::{Redaction, Redactor};
+use crate::markdown::sections::{parse_section_names, select_sections};
+use crate::markdown::source::{is_source_file, parser_for, MarkdownParser, SourceParser};
+use crate::markdown::svg::is_svg;
 use crate::markdown::util::split_args;
 use crate::notion::block::{AppendBlockRequest, AppendBlockRequestChild, BlockType, NotionBlock, TextAnnotations};
+use crate::notion::append::MAX_CHILDREN_PER_REQUEST;
+use crate::notion::database::{ChildDatabaseParent, ColumnType, DatabaseColumn};
 use crate::notion::language::NotionCodeLanguage;
+use crate::settings::notation::{
+    HeadingStrategy, LineBreakPolicy, LinkedImageStrategy, OutsideLinkPolicy, RedactSettings, SvgStrategy, SymlinkPolicy, TitleStrategy,
+    UnsupportedNodePolicy,
+};
+use crate::sync::git::repository_root;
 
 pub static MAX_CODE_LENGTH: usize = 2000;
 
+/// What is escaped in a segment of a URL path.
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+
+static INCLUDE_DIRECTIVE: OnceLock<Regex> = OnceLock::new();
+static ARGUMENTS_MARKER: OnceLock<Regex> = OnceLock::new();
+static DATABASE_MARKER: OnceLock<Regex> = OnceLock::new();
+static COLUMN_TYPE: OnceLock<Regex> = OnceLock::new();
+static HASHTAG: OnceLock<Regex> = OnceLock::new();
+
 #[derive(Debug, Clone)]
 pub struct NotationParseResult {
     inner: Node,
     path: String,
     file_name: String,
+    /// The text handed to the parser, which node positions point into, see
+    /// [`SourceParser::parse_with_source`].
+    source: Option<String>,
 }
 
-#[derive(Debug, Clone, Parser)]
+#[derive(Debug, Clone, Default, Parser)]
 pub struct NotationDocArguments {
     #[clap(short, long, value_parser)]
     pub emoji: Option<String>,
     #[clap(short, long, value_parser)]
     pub title: Option<String>,
+    /// Identifies the page in the sync state instead of the file path, so the page survives
+    /// renaming the file.
+    #[clap(short, long, value_parser)]
+    pub slug: Option<String>,
+    /// Parses and checks the file without shipping it, to keep drafts in the tree.
+    #[clap(long)]
+    pub skip: bool,
+    /// Notion's "Full width" page setting.
+    #[clap(long)]
+    pub full_width: bool,
+    /// Notion's "Small text" page setting.
+    #[clap(long)]
+    pub small_text: bool,
+    #[clap(long, value_enum)]
+    pub font: Option<PageFont>,
+    /// The URL of the page's cover, over the one `[cover]` gives it.
+    #[clap(long, value_parser)]
+    pub cover: Option<String>,
+    /// The description of the page's database row, over the excerpt of its first paragraph.
+    #[clap(long, value_parser)]
+    pub description: Option<String>,
+    /// In an intro, the file or subdirectory names of its directory shipped first, in this
+    /// order, over the `order` of `.notation.toml`.
+    #[clap(long, value_parser, value_delimiter = ',')]
+    pub order: Vec<String>,
 }
 
-impl Default for NotationDocArguments {
-    fn default() -> Self {
-        NotationDocArguments {
-            emoji: None,
-            title: None,
+/// The fonts a Notion page can be set in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PageFont {
+    Default,
+    Serif,
+    Mono,
+}
+
+impl NotationDocArguments {
+    /// The page settings asked for that the Notion API can't change, as the arguments asking.
+    pub fn unsupported_page_settings(&self) -> Vec<String> {
+        let mut settings = Vec::new();
+        if self.full_width {
+            settings.push("--full-width".to_string());
+        }
+        if self.small_text {
+            settings.push("--small-text".to_string());
+        }
+        match self.font {
+            Some(PageFont::Serif) => settings.push("--font serif".to_string()),
+            Some(PageFont::Mono) => settings.push("--font mono".to_string()),
+            Some(PageFont::Default) | None => {}
         }
+        settings
     }
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct ConversionOptions {
+    pub heading_strategy: HeadingStrategy,
+    pub title_strategy: TitleStrategy,
+    pub pretty_titles: bool,
+    pub bold_highlighted_lines: bool,
+    pub detect_code_language: bool,
+    pub image_base_url: Option<String>,
+    pub svg: SvgStrategy,
+    pub unsupported_nodes: UnsupportedNodePolicy,
+    pub linked_images: LinkedImageStrategy,
+    pub line_break: LineBreakPolicy,
+    pub symlinks: SymlinkPolicy,
+    pub directives: DirectiveRegistry,
+    /// Only the top level sections under these headings are converted, over the `sections:`
+    /// of the frontmatter.
+    pub sections: Vec<String>,
+    pub redact: RedactSettings,
+    /// The locale being shipped, when its docs are, see [`crate::settings::notation::Locale`].
+    /// Links to files outside of its tree ship as text.
+    pub locale: Option<String>,
+    /// The shipped directory, canonical. Links out of it follow `outside_links`, and fail
+    /// when it is unknown.
+    pub root: Option<PathBuf>,
+    pub outside_links: OutsideLinkPolicy,
+    pub link_base_url: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConversionContext<'a> {
+    pub file_path: String,
+    pub page_id: String,
+    pub path_to_page_id: &'a HashMap<PathBuf, String>,
+    pub page_title: String,
+    pub options: ConversionOptions,
+    pub warnings: RefCell<Vec<Diagnostic>>,
+    /// The files being converted, outermost first, to catch include cycles.
+    pub include_stack: Vec<PathBuf>,
+    /// The markdown being converted, when it could be read, which node positions point into.
+    pub source: Option<String>,
+    /// The name of the directive whose body is being converted, if any.
+    pub directive: Option<String>,
+    pub redactions: RefCell<Vec<Redaction>>,
+}
+
+impl ConversionContext<'_> {
+    pub fn warn(&self, position: Option<&Position>, message: String) {
+        self.warnings
+            .borrow_mut()
+            .push(Diagnostic::warning(&self.file_path, position, message));
+    }
+
+    pub fn error(&self, position: Option<&Position>, message: String) -> anyhow::Error {
+        Diagnostic::error(&self.file_path, position, message).into()
+    }
+
+    /// Text with its soft breaks following `[content] line_break`.
+    fn soft_breaks(&self, text: &str) -> String {
+        match self.options.line_break {
+            LineBreakPolicy::Reflow => text.replace("\n", " "),
+            LineBreakPolicy::Preserve => text.to_string(),
+        }
+    }
+
+    /// The URL an image is shipped with: `url` itself, or for a path relative to the file, the
+    /// image's path in the repository appended to `[content] image_base_url`.
+    fn image_url(&self, url: &str, position: Option<&Position>) -> Result<String> {
+        let e = match Url::parse(url) {
+            Ok(_) => return Ok(url.to_string()),
+            Err(e) => e,
+        };
+        let base_url = self.options.image_base_url.as_ref().ok_or_else(|| {
+            self.error(position, format!("failed to build paragraph, detected invalid image url: {}, err: {:?}, set [content] image_base_url to ship images from the repository", url, e))
+        })?;
+        let path = self.local_image(url, position)?;
+        let root = repository_root(&path)
+            .or_else(|| std::env::current_dir().ok())
+            .and_then(|r| r.canonicalize().ok())
+            .unwrap_or_default();
+        let in_repository = path.strip_prefix(&root).map_err(|_| {
+            self.error(position, format!("image {} is outside of {}", url, root.display()))
+        })?;
+        let encoded: Vec<String> = in_repository
+            .components()
+            .map(|c| utf8_percent_encode(&c.as_os_str().to_string_lossy(), PATH_SEGMENT).to_string())
+            .collect();
+        Ok(format!("{}/{}", base_url.trim_end_matches('/'), encoded.join("/")))
+    }
+
+    /// The absolute path of `relative`, a link of the file being converted, when it is out of
+    /// the shipped directory.
+    fn outside_root(&self, relative: &Path) -> Result<Option<PathBuf>> {
+        let root = match &self.options.root {
+            Some(root) => root,
+            None => return Ok(None),
+        };
+        let dir = Path::new(&self.file_path).parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let dir = dir.canonicalize().or_else(|_| std::path::absolute(dir))?;
+        let target = reconcile_path(&plain_path(&dir).join(relative))?;
+        Ok((!target.starts_with(root)).then_some(target))
+    }
+
+    /// What the link `l` to `target`, out of the shipped directory, ships as, following
+    /// `[content] outside_links`. `None` for its text only.
+    fn outside_link(&self, l: &Link, target: &Path, fragment: Option<&str>) -> Result<Option<String>> {
+        let position = l.position.as_ref();
+        match self.options.outside_links {
+            OutsideLinkPolicy::Error => Err(self.error(
+                position,
+                format!("{} is out of the shipped docs, set [content] outside_links to ship it as text or a link", l.url),
+            )),
+            OutsideLinkPolicy::Text => {
+                self.warn(position, format!("{} is out of the shipped docs, shipping the link text only", l.url));
+                Ok(None)
+            }
+            OutsideLinkPolicy::Rewrite => {
+                let base_url = self.options.link_base_url.as_ref().ok_or_else(|| {
+                    self.error(position, format!("{} is out of the shipped docs, outside_links = \"rewrite\" needs [content] link_base_url", l.url))
+                })?;
+                let root = self.options.root.clone().unwrap_or_default();
+                let repository = repository_root(&root)
+                    .and_then(|r| r.canonicalize().ok())
+                    .map_or(root, |r| plain_path(&r));
+                let in_repository = match target.strip_prefix(&repository) {
+                    Ok(p) => p,
+                    Err(_) => {
+                        self.warn(position, format!("{} is out of the repository, shipping the link text only", l.url));
+                        return Ok(None);
+                    }
+                };
+                let encoded: Vec<String> = in_repository
+                    .components()
+                    .map(|c| utf8_percent_encode(&c.as_os_str().to_string_lossy(), PATH_SEGMENT).to_string())
+                    .collect();
+                let mut url = format!("{}/{}", base_url.trim_end_matches('/'), encoded.join("/"));
+                if let Some(fragment) = fragment {
+                    url = format!("{}#{}", url, fragment);
+                }
+                self.warn(position, format!("{} is out of the shipped docs, linking to {}", l.url, url));
+                Ok(Some(url))
+            }
+        }
+    }
+
+    /// The path of an image referenced relative to the file being converted.
+    fn local_image(&self, url: &str, position: Option<&Position>) -> Result<PathBuf> {
+        let relative = url.split(['?', '#']).next().unwrap_or(url);
+        let relative = percent_encoding::percent_decode_str(relative).decode_utf8_lossy();
+        let base_path = Path::new(&self.file_path).parent().unwrap_or(Path::new(""));
+        base_path
+            .join(relative.as_ref())
+            .canonicalize()
+            .map_err(|_| self.error(position, format!("image {} not found", url)))
+    }
+
+    /// The block an image is shipped as, SVGs following `[content] svg`.
+    fn image_block(&self, url: &str, position: Option<&Position>) -> Result<AppendBlockRequestChild> {
+        if !is_svg(url) {
+            return Ok(AppendBlockRequestChild::new_external_image_block(self.image_url(url, position)?));
+        }
+        match self.options.svg {
+            SvgStrategy::PassThrough => {}
+            SvgStrategy::File => {
+                return Ok(AppendBlockRequestChild::new_external_file_block(self.image_url(url, position)?));
+            }
+            SvgStrategy::Png if cfg!(feature = "svg") => {
+                let source = match Url::parse(url) {
+                    Ok(_) => url.to_string(),
+                    Err(_) => self.local_image(url, position)?.to_string_lossy().to_string(),
+                };
+                return Ok(AppendBlockRequestChild::new_svg_image_block(source));
+            }
+            SvgStrategy::Png => self.warn(
+                position,
+                format!("svg = \"png\" needs notation built with the svg feature, shipping {} as is", url),
+            ),
+        }
+        Ok(AppendBlockRequestChild::new_external_image_block(self.image_url(url, position)?))
+    }
+
+    /// Links to the heading `fragment` names in `target`, the file shipped to `page_id`, using
+    /// GitHub's anchors. Falls back to `page_url` when the file has no such heading.
+    fn heading_link(
+        &self,
+        position: Option<&Position>,
+        target: &Path,
+        page_id: &str,
+        fragment: &str,
+        page_url: String,
+    ) -> String {
+        let fragment = percent_encoding::percent_decode_str(fragment)
+            .decode_utf8_lossy()
+            .to_string();
+        if fragment.is_empty() {
+            return page_url;
+        }
+        match file_heading_slugs(target) {
+            Ok(slugs) if slugs.contains(&fragment) => anchor_url(page_id, &fragment),
+            Ok(_) => {
+                self.warn(
+                    position,
+                    format!(
+                        "no heading #{} in {}, linking to the page instead",
+                        fragment,
+                        target.display()
+                    ),
+                );
+                page_url
+            }
+            Err(_) => page_url,
+        }
+    }
+
+    /// Converts a piece of markdown from the file being converted, like the body of a
+    /// directive, into blocks.
+    pub fn convert_markdown(&self, markdown: &str) -> Result<Vec<AppendBlockRequestChild>> {
+        self.convert_nested(markdown, self.directive.clone())
+    }
+
+    /// Converts the body of the directive `name`, see [`Directive::convert_body`].
+    pub fn convert_directive_body(&self, name: &str, body: &str) -> Result<Vec<AppendBlockRequestChild>> {
+        self.convert_nested(body, Some(name.to_lowercase()))
+    }
+
+    fn convert_nested(&self, markdown: &str, directive: Option<String>) -> Result<Vec<AppendBlockRequestChild>> {
+        let (mut node, source) = MarkdownParser.parse_with_source(markdown)?;
+        let redactions = Redactor::new(&self.options.redact)?.redact(&self.file_path, &mut node);
+        self.redactions.borrow_mut().extend(redactions);
+        let ctx = ConversionContext {
+            file_path: self.file_path.clone(),
+            page_id: self.page_id.clone(),
+            path_to_page_id: self.path_to_page_id,
+            page_title: self.page_title.clone(),
+            options: self.options.clone(),
+            warnings: RefCell::new(Vec::new()),
+            include_stack: self.include_stack.clone(),
+            source,
+            directive,
+            redactions: RefCell::new(Vec::new()),
+        };
+        let mut request = AppendBlockRequest::new();
+        let converted = recurse_markdown_tree(&mut request, &node, &node, &ctx);
+        self.warnings.borrow_mut().extend(ctx.warnings.into_inner());
+        self.redactions.borrow_mut().extend(ctx.redactions.into_inner());
+        converted?;
+        Ok(request.children)
+    }
+
+    /// Handles a node notation has no conversion for, following `[content] unsupported_nodes`.
+    fn unsupported_node(&self, request: &mut AppendBlockRequest, node: &Node) -> Result<()> {
+        let name = node_name(node);
+        let position = node.position();
+        match self.options.unsupported_nodes {
+            UnsupportedNodePolicy::Ignore => {}
+            UnsupportedNodePolicy::Warn => {
+                self.warn(position, format!("{} is not supported, leaving it out", name));
+            }
+            UnsupportedNodePolicy::Error => {
+                return Err(self.error(position, format!("{} is not supported", name)));
+            }
+            UnsupportedNodePolicy::RenderAsCode => {
+                let markdown = position
+                    .zip(self.source.as_ref())
+                    .and_then(|(p, s)| s.get(p.start.offset..p.end.offset))
+                    .map(|m| m.to_string())
+                    .unwrap_or_else(|| node.to_string());
+                request.append_child(AppendBlockRequestChild::new_code_block_with_caption(
+                    build_code_rich_text(&markdown, &BTreeSet::new(), MAX_CODE_LENGTH),
+                    NotionCodeLanguage::Markdown.to_string(),
+                    None,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn build_directive(&self, code: &Code) -> Result<Vec<AppendBlockRequestChild>> {
+        let meta = code.meta.clone().unwrap_or_default();
+        let (name, args) = meta.split_once(' ').unwrap_or((meta.as_str(), ""));
+        let directive = Directive {
+            name: name.to_string(),
+            args: args.trim().to_string(),
+            body: code.value.clone(),
+            position: code.position.clone(),
+        };
+        match self.options.directives.get(name) {
+            Some(builder) => builder.build(&directive, self),
+            None => {
+                self.warn(
+                    code.position.as_ref(),
+                    format!("unknown directive :::{}, shipping its content as is", name),
+                );
+                directive.convert_body(self)
+            }
+        }
+    }
+}
+
+/// The blocks converted from one markdown file, with any warnings raised along the way.
+#[derive(Debug, Clone)]
+pub struct NotionConversion {
+    pub request: AppendBlockRequest,
+    pub warnings: Vec<Diagnostic>,
+    /// What was left out or masked following `[redact]`.
+    pub redactions: Vec<Redaction>,
+}
+
 pub fn build_paragraph(
     p: &Paragraph,
-    file_path: &String,
-    page_id: &String,
-    path_to_page_id: &HashMap<PathBuf, String>,
-    page_title: &String,
+    ctx: &ConversionContext,
 ) -> Result<Vec<AppendBlockRequestChild>> {
     let mut pblocks = Vec::new();
     let mut request_children = Vec::new();
-    let mut first_content_line = 0;
 
     for c in p.children.iter() {
         match c {
             Node::Text(t) => {
-                if t.value.starts_with("--") {
-                    if let Some(p) = &t.position {
-                        if p.start.line == 1 {
-                            continue;
-                        }
-                    }
-                }
-                let parsed_content = t.value.replace("\n", " ");
-                if first_content_line == 0 {
-                    if t.value.trim().is_empty() {
-                        continue;
-                    } else if let Some(p) = &t.position {
-                        first_content_line = p.start.line;
-                    }
-                }
-                pblocks.push(NotionBlock::new_text_block(parsed_content))
-            }
-            Node::Link(l) => {
-                let link_url = l.url.clone();
-                let use_url = if link_url.starts_with("#") {
-                    format!("https://www.notion.so/{}", page_id)
-                } else if link_url.starts_with(".") {
-                    let page_url: Vec<&str> = l.url.split("#").collect();
-                    let relative_path =
-                        PathBuf::from_str(page_url.first().unwrap_or(&l.url.as_str()))?;
-                    let base_path = PathBuf::from_str(file_path.as_str())?;
-                    let base_path = base_path.parent().unwrap_or(base_path.as_path());
-                    let full_path = base_path.join(relative_path);
-                    let full_path = reconcile_path(&full_path)?;
-                    if let Some(pid) = path_to_page_id.get(&full_path) {
-                        let formatted_pid = pid.replace("-", "");
-                        let formatted_page_title = page_title.replace(" ", "-");
-                        format!(
-                            "https://www.notion.so/{}-{}",
-                            formatted_page_title, formatted_pid
-                        )
-                    } else {
-                        return Err(anyhow!("(page={}) failed to build paragraph, detected invalid link url: {}, found no fallback alternative", file_path, l.url.clone()));
-                    }
-                } else {
-                    link_url.clone()
-                };
-
-                Url::parse(use_url.as_str()).map_err(|e| anyhow!("(page={}) failed to build paragraph, detected invalid link url: {}, err: {:?}", file_path, l.url.clone(), e))?;
-
-                let text = l.children.first();
-
-                if let Some(t) = text {
-                    if let Node::Text(t) = t {
-                        pblocks.push(NotionBlock::new_link_block(t.value.clone(), use_url))
-                    }
-                } else {
-                    pblocks.push(NotionBlock::new_link_block(l.url.clone(), use_url))
+                // Only whitespace leading a paragraph, or the text after an image, is dropped,
+                // the one between formatted words is kept.
+                let text = ctx.soft_breaks(&t.value);
+                let text = if pblocks.is_empty() { text.trim_start().to_string() } else { text };
+                if !text.is_empty() {
+                    pblocks.push(NotionBlock::new_text_block(text));
                 }
             }
             Node::Image(i) => {
-                if !pblocks.is_empty() {
-                    request_children.push(AppendBlockRequestChild::new_rich_text(
-                        BlockType::Paragraph,
-                        pblocks.clone(),
-                    ));
-                    pblocks.clear();
-                }
-                Url::parse(i.url.as_str()).map_err(|e| anyhow!("(page={}) failed to build paragraph, detected invalid image url: {}, err: {:?}", file_path, i.url.clone(), e))?;
-                request_children.push(AppendBlockRequestChild::new_external_image_block(
-                    i.url.clone(),
-                ));
+                flush_paragraph(&mut pblocks, &mut request_children);
+                request_children.push(ctx.image_block(&i.url, i.position.as_ref())?);
             }
-            Node::Strong(s) => {
-                for sc in s.children.iter() {
-                    match sc {
-                        Node::Text(t) => {
-                            let parsed_content = t.value.replace("\n", " ");
-                            pblocks.push(NotionBlock::new_text_block(parsed_content).with_annotations(TextAnnotations::bold()))
-                        }
-                        _ => {}
-                    }
+            Node::Link(l)
+                if ctx.options.linked_images == LinkedImageStrategy::Caption
+                    && l.children.iter().any(|c| matches!(c, Node::Image(_))) =>
+            {
+                // Notion images can't link anywhere, their caption links instead.
+                let url = link_url(l, ctx)?;
+                for lc in l.children.iter() {
+                    let Node::Image(i) = lc else {
+                        build_inline(lc, &TextAnnotations::new(), url.as_deref(), ctx, &mut pblocks)?;
+                        continue;
+                    };
+                    flush_paragraph(&mut pblocks, &mut request_children);
+                    let caption = if i.alt.is_empty() { l.url.clone() } else { i.alt.clone() };
+                    request_children.push(
+                        ctx.image_block(&i.url, i.position.as_ref())?
+                            .with_caption(vec![rich_text(caption, &TextAnnotations::new(), url.as_deref())]),
+                    );
                 }
             }
-            Node::InlineCode(c) => {
-                pblocks.push(NotionBlock::new_text_block(c.value.replace("\n", " ")).with_annotations(TextAnnotations::code()))
-            }
-            Node::InlineMath(m) => {
-                pblocks.push(NotionBlock::new_text_block(m.value.replace("\n", " ")).with_annotations(TextAnnotations::code()))
+            Node::Link(_) | Node::Strong(_) | Node::Emphasis(_) | Node::Delete(_) | Node::InlineCode(_) | Node::InlineMath(_) | Node::Break(_) => {
+                build_inline(c, &TextAnnotations::new(), None, ctx, &mut pblocks)?
             }
             _ => {}
         }
     }
 
+    flush_paragraph(&mut pblocks, &mut request_children);
+    Ok(request_children)
+}
+
+/// Ends the paragraph of the rich text gathered so far, if any, before a block breaking it up,
+/// like an image. The whitespace it ends with, up to that block, is dropped.
+fn flush_paragraph(pblocks: &mut Vec<NotionBlock>, request_children: &mut Vec<AppendBlockRequestChild>) {
+    while let Some(text) = pblocks.last_mut().and_then(|b| b.text.as_mut()) {
+        text.content.truncate(text.content.trim_end().len());
+        if !text.content.is_empty() {
+            break;
+        }
+        pblocks.pop();
+    }
     if !pblocks.is_empty() {
         request_children.push(AppendBlockRequestChild::new_rich_text(
             BlockType::Paragraph,
-            pblocks,
+            std::mem::take(pblocks),
         ));
     }
+}
 
-    Ok(request_children)
+/// The rich text of an inline node, its formatting composed with the `annotations` of the
+/// nodes around it, so `**`bold code`**` keeps both. Text inside a link carries `link`.
+fn build_inline(
+    node: &Node,
+    annotations: &TextAnnotations,
+    link: Option<&str>,
+    ctx: &ConversionContext,
+    blocks: &mut Vec<NotionBlock>,
+) -> Result<()> {
+    let (annotations, children) = match node {
+        Node::Text(t) => {
+            blocks.push(rich_text(ctx.soft_breaks(&t.value), annotations, link));
+            return Ok(());
+        }
+        Node::InlineCode(c) => {
+            blocks.push(rich_text(c.value.replace("\n", " "), &annotations.clone().code(), link));
+            return Ok(());
+        }
+        Node::InlineMath(m) => {
+            blocks.push(rich_text(m.value.replace("\n", " "), &annotations.clone().code(), link));
+            return Ok(());
+        }
+        // A hard break (two trailing spaces or `\`), unlike a soft one, is a line break in Notion.
+        Node::Break(_) => {
+            blocks.push(rich_text("\n".to_string(), annotations, link));
+            return Ok(());
+        }
+        // An image inside a link or formatting, which rich text can't hold, ships as its alt text.
+        Node::Image(i) => {
+            let alt = if i.alt.is_empty() { i.url.clone() } else { i.alt.clone() };
+            blocks.push(rich_text(alt, annotations, link));
+            return Ok(());
+        }
+        Node::Link(l) => {
+            // Every piece of the link text is a rich text of its own, all pointing at the link.
+            let url = link_url(l, ctx)?;
+            if l.children.is_empty() {
+                blocks.push(rich_text(l.url.clone(), annotations, url.as_deref()));
+            }
+            for c in l.children.iter() {
+                build_inline(c, annotations, url.as_deref(), ctx, blocks)?;
+            }
+            return Ok(());
+        }
+        Node::Strong(s) => (annotations.clone().bold(), &s.children),
+        Node::Emphasis(e) => (annotations.clone().italic(), &e.children),
+        Node::Delete(d) => (annotations.clone().strikethrough(), &d.children),
+        _ => return Ok(()),
+    };
+    for c in children.iter() {
+        build_inline(c, &annotations, link, ctx, blocks)?;
+    }
+    Ok(())
 }
 
-pub fn build_list(
-    list: &List,
-    file_path: &String,
-    page_id: &String,
-    path_to_page_id: &HashMap<PathBuf, String>,
-    page_title: &String,
-) -> Result<Vec<AppendBlockRequestChild>> {
+fn rich_text(content: String, annotations: &TextAnnotations, link: Option<&str>) -> NotionBlock {
+    let block = match link {
+        Some(url) => NotionBlock::new_link_block(content, url.to_string()),
+        None => NotionBlock::new_text_block(content),
+    };
+    if annotations.is_plain() {
+        block
+    } else {
+        block.with_annotations(annotations.clone())
+    }
+}
+
+/// The kind of an absolute link, by its scheme.
+#[derive(Debug, PartialEq, Eq)]
+enum LinkScheme {
+    /// `http:`, `https:` and the other schemes Notion opens, like `ftp:`.
+    Web,
+    /// `mailto:` and `tel:`, which Notion hands to the mail or phone app.
+    Contact,
+    /// `data:`, `javascript:`, `file:` and `blob:`, which Notion can't open.
+    Unsupported(String),
+}
+
+fn link_scheme(url: &Url) -> LinkScheme {
+    match url.scheme() {
+        "mailto" | "tel" => LinkScheme::Contact,
+        "data" | "javascript" | "file" | "blob" => LinkScheme::Unsupported(url.scheme().to_string()),
+        _ => LinkScheme::Web,
+    }
+}
+
+/// Where a link points in Notion: the page a relative link's file is shipped to, or the
+/// heading of a page it names. `None` for a link that ships as its text only, like one to a
+/// draft, one without a URL or one Notion can't open.
+fn link_url(l: &Link, ctx: &ConversionContext) -> Result<Option<String>> {
+    let file_path = &ctx.file_path;
+    // Links are URLs, whose separator is `/`, but one written on Windows may use `\`.
+    let link_url = l.url.replace('\\', "/");
+    let use_url = if link_url.trim().is_empty() {
+        ctx.warn(l.position.as_ref(), "a link without a URL ships as its text only".to_string());
+        return Ok(None);
+    } else if link_url == "#" {
+        format!("https://www.notion.so/{}", ctx.page_id)
+    } else if let Some(fragment) = link_url.strip_prefix("#") {
+        ctx.heading_link(
+            l.position.as_ref(),
+            Path::new(&ctx.file_path),
+            &ctx.page_id,
+            fragment,
+            format!("https://www.notion.so/{}", ctx.page_id),
+        )
+    } else if link_url.starts_with(".") {
+        let page_url: Vec<&str> = link_url.split("#").collect();
+        let relative_path =
+            PathBuf::from_str(page_url.first().unwrap_or(&link_url.as_str()))?;
+        let base_path = PathBuf::from_str(file_path.as_str())?;
+        let base_path = base_path.parent().unwrap_or(base_path.as_path());
+        let target_path = base_path.join(&relative_path);
+        let full_path = page_key(&target_path, ctx.options.symlinks)
+            .map_err(|e| ctx.error(l.position.as_ref(), format!("failed to resolve link {}: {}", l.url, e)))?;
+        if is_draft(&target_path) {
+            ctx.warn(
+                l.position.as_ref(),
+                format!("{} is a draft, shipping the link text only", l.url),
+            );
+            return Ok(None);
+        }
+        if let Some(pid) = ctx.path_to_page_id.get(&full_path) {
+            let formatted_pid = pid.replace("-", "");
+            let formatted_page_title = ctx.page_title.replace(" ", "-");
+            let url = format!(
+                "https://www.notion.so/{}-{}",
+                formatted_page_title, formatted_pid
+            );
+            match page_url.get(1) {
+                Some(fragment) => ctx.heading_link(l.position.as_ref(), &target_path, pid, fragment, url),
+                None => url,
+            }
+        } else if let Some(locale) = ctx.options.locale.as_ref().filter(|_| target_path.is_file()) {
+            ctx.warn(
+                l.position.as_ref(),
+                format!("{} is not in the {} docs, shipping the link text only", l.url, locale),
+            );
+            return Ok(None);
+        } else if let Some(target) = ctx.outside_root(&relative_path)? {
+            match ctx.outside_link(l, &target, page_url.get(1).copied())? {
+                Some(url) => url,
+                None => return Ok(None),
+            }
+        } else {
+            return Err(ctx.error(l.position.as_ref(), format!("failed to build paragraph, detected invalid link url: {}, found no fallback alternative", l.url)));
+        }
+    } else {
+        let url = Url::parse(&l.url).map_err(|e| ctx.error(l.position.as_ref(), format!("failed to build paragraph, detected invalid link url: {}, err: {:?}", l.url, e)))?;
+        match link_scheme(&url) {
+            LinkScheme::Web | LinkScheme::Contact => l.url.clone(),
+            LinkScheme::Unsupported(scheme) => {
+                ctx.warn(
+                    l.position.as_ref(),
+                    format!("{}: links can't be opened from Notion, shipping the link text only", scheme),
+                );
+                return Ok(None);
+            }
+        }
+    };
+
+    Url::parse(use_url.as_str()).map_err(|e| ctx.error(l.position.as_ref(), format!("failed to build paragraph, detected invalid link url: {}, err: {:?}", l.url, e)))?;
+    Ok(Some(use_url))
+}
+
+pub fn build_list(list: &List, ctx: &ConversionContext) -> Result<Vec<AppendBlockRequestChild>> {
     let mut children = Vec::new();
 
     for c in list.children.iter() {
-        match c {
-            Node::ListItem(li) => {
-                for cc in li.children.iter() {
-                    match cc {
-                        Node::Paragraph(p) => {
-                            let paragraph_blocks = build_paragraph(p, file_path, page_id, path_to_page_id, page_title)?;
-                            let mut lblocks = Vec::new();
-                            for p in paragraph_blocks {
-                                if let Some(rtb) = p.get_rich_text_blocks() {
-                                    lblocks.extend(rtb);
-                                }
-                            }
-                            let block_type = if list.ordered {
-                                BlockType::NumberedListItem
-                            } else {
-                                BlockType::BulletedListItem
-                            };
-                            children.push(AppendBlockRequestChild::new_rich_text(block_type, lblocks));
+        if let Node::ListItem(li) = c {
+            for cc in li.children.iter() {
+                if let Node::Paragraph(p) = cc {
+                    let paragraph_blocks = build_paragraph(p, ctx)?;
+                    let mut lblocks = Vec::new();
+                    for p in paragraph_blocks {
+                        if let Some(rtb) = p.get_rich_text_blocks() {
+                            lblocks.extend(rtb);
                         }
-                        _ => {}
                     }
+                    let block_type = if list.ordered {
+                        BlockType::NumberedListItem
+                    } else {
+                        BlockType::BulletedListItem
+                    };
+                    children.push(AppendBlockRequestChild::new_rich_text(block_type, lblocks));
                 }
             }
-            _ => {}
         }
     }
 
     Ok(children)
 }
 
+/// Notion rejects rows with fewer cells than the table is wide, so short rows, and rows
+/// around empty cells, are padded with empty cells to the widest row.
 pub fn build_table(table: &Table) -> Vec<AppendBlockRequestChild> {
-    let mut rows = Vec::new();
-    let mut table_length = 0;
-
+    let mut rows: Vec<Vec<Vec<NotionBlock>>> = Vec::new();
     for r in table.children.iter() {
-        let mut cells = Vec::new();
-        match r {
-            Node::TableRow(tr) => {
-                if tr.children.len() > table_length {
-                    table_length = tr.children.len();
-                }
-                for c in tr.children.iter() {
-                    match c {
-                        Node::TableCell(tc) => {
-                            for cc in tc.children.iter() {
-                                match cc {
-                                    Node::Text(it) => {
-                                       let parsed_content = it.value.replace("\n", " ");
-                                        cells.push(NotionBlock::new_text_block(parsed_content))
-                                    }
-                                    _ => {}
-                                }
-                            }
-                        }
-                        _ => {}
-                    }
-                }
-            }
-            _ => {}
+        if let Node::TableRow(tr) = r {
+            let cells = tr
+                .children
+                .iter()
+                .map(|c| match c {
+                    Node::TableCell(tc) => build_cell(&tc.children),
+                    _ => Vec::new(),
+                })
+                .collect();
+            rows.push(cells);
         }
-        rows.push(AppendBlockRequestChild::new_table_row_block(cells))
     }
+    let table_width = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+    let rows = rows
+        .into_iter()
+        .map(|mut cells| {
+            cells.resize(table_width, Vec::new());
+            AppendBlockRequestChild::new_table_row_block(cells)
+        })
+        .collect();
 
-    vec!(AppendBlockRequestChild::new_table_block(table_length, true, true, rows))
+    vec!(AppendBlockRequestChild::new_table_block(table_width, true, true, rows))
+}
+
+/// The rich text of a table cell. Inline code keeps its annotation, other formatting ships as
+/// plain text.
+fn build_cell(children: &[Node]) -> Vec<NotionBlock> {
+    children
+        .iter()
+        .filter_map(|c| match c {
+            Node::InlineCode(code) => Some(
+                NotionBlock::new_text_block(code.value.replace("\n", " "))
+                    .with_annotations(TextAnnotations::new().code()),
+            ),
+            _ => {
+                let text = c.to_string().replace("\n", " ");
+                (!text.is_empty()).then(|| NotionBlock::new_text_block(text))
+            }
+        })
+        .collect()
 }
 
 pub fn recurse_markdown_tree(
     request: &mut AppendBlockRequest,
     node: &Node,
     parent: &Node,
-    path: &String,
-    page_id: &String,
-    path_to_page_id: &HashMap<PathBuf, String>,
-    page_title: &String,
+    ctx: &ConversionContext,
 ) -> Result<()> {
     match node {
         Node::Heading(h) => {
             for c in h.children.iter() {
-                recurse_markdown_tree(
-                    request,
-                    c,
-                    node,
-                    path,
-                    page_id,
-                    path_to_page_id,
-                    page_title,
-                )?;
+                recurse_markdown_tree(request, c, node, ctx)?;
             }
         }
         Node::List(l) => {
-            request.extend_children(build_list(
-                l,
-                path,
-                page_id,
-                path_to_page_id,
-                page_title,
-            )?);
+            request.extend_children(build_list(l, ctx)?);
         }
         Node::ListItem(li) => {
             for c in li.children.iter() {
-                recurse_markdown_tree(
-                    request,
-                    c,
-                    parent,
-                    path,
-                    page_id,
-                    path_to_page_id,
-                    page_title,
-                )?;
+                recurse_markdown_tree(request, c, parent, ctx)?;
             }
         }
         Node::Text(t) => match parent {
             Node::Heading(h) => {
-                request.append_child(AppendBlockRequestChild::new_heading_block(
-                    t.value.clone(),
-                    h.depth,
-                ));
+                if h.depth > 3 && ctx.options.heading_strategy == HeadingStrategy::Paragraph {
+                    request.append_child(AppendBlockRequestChild::new_rich_text(
+                        BlockType::Paragraph,
+                        vec![NotionBlock::new_text_block(t.value.clone())
+                            .with_annotations(TextAnnotations::new().bold())],
+                    ));
+                } else {
+                    request.append_child(AppendBlockRequestChild::new_heading_block(
+                        t.value.clone(),
+                        h.depth,
+                    ));
+                }
             }
             Node::Root(_) => {
                 request.append_child(AppendBlockRequestChild::new_paragraph_block(
@@ -303,48 +791,348 @@ pub fn recurse_markdown_tree(
             _ => {}
         },
         Node::Paragraph(p) => {
-            request.extend_children(build_paragraph(
-                p,
-                path,
-                page_id,
-                path_to_page_id,
-                page_title,
-            )?);
+            request.extend_children(build_paragraph(p, ctx)?);
+        }
+        Node::Code(c) if c.lang.as_deref() == Some(DIRECTIVE_LANG) => {
+            request.extend_children(ctx.build_directive(c)?);
         }
         Node::Code(c) => {
-            let mut code_chunks = Vec::new();
-            for chunk in c.value.as_bytes().chunks(MAX_CODE_LENGTH) {
-                code_chunks.push(String::from(std::str::from_utf8(chunk).unwrap()));
-            }
+            let meta = CodeMeta::parse(c.meta.as_deref().unwrap_or(""));
+            let bold_lines = if ctx.options.bold_highlighted_lines {
+                meta.highlighted_lines
+            } else {
+                BTreeSet::new()
+            };
+            let rich_text = build_code_rich_text(&c.value, &bold_lines, MAX_CODE_LENGTH);
 
-            let code_language_string = c.lang.clone().unwrap_or(String::from("plain text"));
-            let parsed_code_language = NotionCodeLanguage::from_str(code_language_string.as_str())
-                .unwrap_or(NotionCodeLanguage::PlainText);
+            let parsed_code_language = match c.lang.as_deref() {
+                Some(lang) => NotionCodeLanguage::from_fence(lang).unwrap_or_else(|| {
+                    ctx.warn(
+                        c.position.as_ref(),
+                        format!("code language \"{}\" is not supported by Notion, shipping it as plain text", lang),
+                    );
+                    NotionCodeLanguage::PlainText
+                }),
+                None if ctx.options.detect_code_language => {
+                    NotionCodeLanguage::detect(&c.value).unwrap_or(NotionCodeLanguage::PlainText)
+                }
+                None => NotionCodeLanguage::PlainText,
+            };
 
-            request.append_child(AppendBlockRequestChild::new_code_block(
-                code_chunks,
+            request.append_child(AppendBlockRequestChild::new_code_block_with_caption(
+                rich_text,
                 parsed_code_language.to_string(),
+                meta.title,
             ));
         }
         Node::Root(r) => {
-            for c in r.children.iter() {
-                recurse_markdown_tree(
-                    request,
-                    c,
-                    node,
-                    path,
-                    page_id,
-                    path_to_page_id,
-                    page_title,
-                )?;
+            let mut children = r.children.iter().peekable();
+            while let Some(c) = children.next() {
+                let database = match c {
+                    Node::Html(h) => get_database_marker(&h.value),
+                    _ => None,
+                };
+                match (database, children.peek()) {
+                    (Some(title), Some(Node::Table(t))) => {
+                        request.append_child(build_database(t, title, ctx)?);
+                        children.next();
+                    }
+                    _ => recurse_markdown_tree(request, c, node, ctx)?,
+                }
             }
         }
         Node::Table(t) => {
+            if t.children.len() > MAX_CHILDREN_PER_REQUEST {
+                ctx.warn(
+                    t.position.as_ref(),
+                    format!(
+                        "table has {} rows, more than the {} Notion takes at once, the rest are appended to it in {} more request(s)",
+                        t.children.len(),
+                        MAX_CHILDREN_PER_REQUEST,
+                        (t.children.len() - 1) / MAX_CHILDREN_PER_REQUEST
+                    ),
+                );
+            }
             request.extend_children(build_table(t));
         }
-        _ => {}
+        Node::Html(h) => {
+            if let Some(include) = get_include_directive(&h.value) {
+                include_file(request, include, h.position.as_ref(), ctx)?;
+            } else if get_database_marker(&h.value).is_some() {
+                ctx.warn(
+                    h.position.as_ref(),
+                    "notation:database only applies to a table right after it, at the top level of the page".to_string(),
+                );
+            }
+        }
+        // Frontmatter and link definitions are read on their own.
+        Node::Yaml(_) | Node::Toml(_) | Node::Definition(_) => {}
+        _ => ctx.unsupported_node(request, node)?,
+    }
+
+    Ok(())
+}
+
+/// The name of a node's type, like `block_quote`.
+fn node_name(node: &Node) -> &'static str {
+    match node {
+        Node::Root(_) => "root",
+        Node::BlockQuote(_) => "block_quote",
+        Node::FootnoteDefinition(_) => "footnote_definition",
+        Node::MdxJsxFlowElement(_) => "mdx_jsx_flow_element",
+        Node::List(_) => "list",
+        Node::MdxjsEsm(_) => "mdxjs_esm",
+        Node::Toml(_) => "toml",
+        Node::Yaml(_) => "yaml",
+        Node::Break(_) => "break",
+        Node::InlineCode(_) => "inline_code",
+        Node::InlineMath(_) => "inline_math",
+        Node::Delete(_) => "delete",
+        Node::Emphasis(_) => "emphasis",
+        Node::MdxTextExpression(_) => "mdx_text_expression",
+        Node::FootnoteReference(_) => "footnote_reference",
+        Node::Html(_) => "html",
+        Node::Image(_) => "image",
+        Node::ImageReference(_) => "image_reference",
+        Node::MdxJsxTextElement(_) => "mdx_jsx_text_element",
+        Node::Link(_) => "link",
+        Node::LinkReference(_) => "link_reference",
+        Node::Strong(_) => "strong",
+        Node::Text(_) => "text",
+        Node::Code(_) => "code",
+        Node::Math(_) => "math",
+        Node::MdxFlowExpression(_) => "mdx_flow_expression",
+        Node::Heading(_) => "heading",
+        Node::Table(_) => "table",
+        Node::ThematicBreak(_) => "thematic_break",
+        Node::TableRow(_) => "table_row",
+        Node::TableCell(_) => "table_cell",
+        Node::ListItem(_) => "list_item",
+        Node::Definition(_) => "definition",
+        Node::Paragraph(_) => "paragraph",
+    }
+}
+
+/// The path of an `<!-- include: ./partials/setup.md -->` comment.
+pub fn get_include_directive(html: &str) -> Option<&str> {
+    let pattern = INCLUDE_DIRECTIVE
+        .get_or_init(|| Regex::new(r"^<!--\s*include:\s*(.+?)\s*-->$").unwrap());
+    pattern
+        .captures(html.trim())
+        .and_then(|c| c.get(1))
+        .map(|m| m.as_str())
+}
+
+/// The arguments of a `<!-- notation: --emoji 🚀 -->` comment. Any other `notation:` comment
+/// but a [`get_database_marker`] is one too, so that a mistyped one fails to parse rather
+/// than being left out.
+pub fn get_arguments_marker(html: &str) -> Option<&str> {
+    let pattern = ARGUMENTS_MARKER
+        .get_or_init(|| Regex::new(r"(?s)^<!--\s*notation:\s*(.*?)\s*-->$").unwrap());
+    if get_database_marker(html).is_some() {
+        return None;
+    }
+    pattern
+        .captures(html.trim())
+        .and_then(|c| c.get(1))
+        .map(|m| m.as_str())
+}
+
+/// The title of a `<!-- notation:database name="Releases" -->` comment, which ships the table
+/// right after it as an inline database. Empty when it has no name.
+pub fn get_database_marker(html: &str) -> Option<String> {
+    let pattern = DATABASE_MARKER
+        .get_or_init(|| Regex::new(r#"(?s)^<!--\s*notation:database(?:\s+name="([^"]*)")?\s*-->$"#).unwrap());
+    pattern
+        .captures(html.trim())
+        .map(|c| c.get(1).map_or(String::new(), |m| m.as_str().to_string()))
+}
+
+/// The inline database a table marked with a `<!-- notation:database -->` comment ships as.
+/// A header cell ending in `{type}` gives its column a type, one of `title`, `text`,
+/// `number`, `checkbox`, `date`, `select`, `multi_select` or `url`. Columns are text
+/// otherwise, and the first column is the title unless another one is.
+pub fn build_database(
+    table: &Table,
+    title: String,
+    ctx: &ConversionContext,
+) -> Result<AppendBlockRequestChild> {
+    // The text of each cell, with where the cell is to point warnings at it.
+    let mut rows = table.children.iter().map(|r| match r {
+        Node::TableRow(tr) => tr
+            .children
+            .iter()
+            .map(|c| (c.to_string(), c.position().or(table.position.as_ref())))
+            .collect::<Vec<_>>(),
+        _ => Vec::new(),
+    });
+    let annotation = COLUMN_TYPE.get_or_init(|| Regex::new(r"^(.*?)\s*\{(\w+)\}$").unwrap());
+    let mut columns: Vec<DatabaseColumn> = Vec::new();
+    for (header, position) in rows.next().unwrap_or_default() {
+        let header = header.trim();
+        let (name, column_type) = match annotation.captures(header) {
+            Some(c) => {
+                let column_type = ColumnType::from_annotation(&c[2]).ok_or_else(|| {
+                    ctx.error(
+                        position,
+                        format!("unknown column type {{{}}} in database \"{}\"", &c[2], title),
+                    )
+                })?;
+                (c[1].to_string(), column_type)
+            }
+            None => (header.to_string(), ColumnType::Text),
+        };
+        if columns.iter().any(|c| c.name == name) {
+            return Err(ctx.error(
+                position,
+                format!("database \"{}\" has two columns named \"{}\"", title, name),
+            ));
+        }
+        if column_type == ColumnType::Title && columns.iter().any(|c| c.column_type == ColumnType::Title) {
+            ctx.warn(
+                position,
+                format!("database \"{}\" can only have one title column, {} is text", title, name),
+            );
+            columns.push(DatabaseColumn { name, column_type: ColumnType::Text });
+            continue;
+        }
+        columns.push(DatabaseColumn { name, column_type });
+    }
+    if !columns.iter().any(|c| c.column_type == ColumnType::Title) {
+        if let Some(first) = columns.first_mut() {
+            first.column_type = ColumnType::Title;
+        }
+    }
+
+    let rows = rows
+        .map(|row| {
+            columns
+                .iter()
+                .enumerate()
+                .map(|(i, column)| {
+                    let (cell, position) = row.get(i).cloned().unwrap_or_default();
+                    match column.column_type.value(&cell) {
+                        Ok(_) => cell,
+                        Err(e) => {
+                            ctx.warn(
+                                position,
+                                format!("{} in column \"{}\" of database \"{}\", leaving it empty", e, column.name, title),
+                            );
+                            String::new()
+                        }
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    Ok(AppendBlockRequestChild::new_child_database_block(ChildDatabaseParent {
+        title,
+        columns,
+        rows,
+    }))
+}
+
+/// The arguments of a first paragraph made of nothing but `--` arguments that parse. Any other
+/// paragraph is content, even when it starts with `--`.
+pub fn line_arguments(node: &Node) -> Option<NotationDocArguments> {
+    match node {
+        Node::Paragraph(p) => match p.children.as_slice() {
+            [Node::Text(t)] if t.value.starts_with("--") => parse_arguments(&t.value).ok(),
+            _ => None,
+        },
+        _ => None,
     }
+}
 
+/// Whether the file at `path` is a draft, left out of ships.
+fn is_draft(path: &Path) -> bool {
+    let parser = parser_for(path).unwrap_or(&MarkdownParser);
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| parser.parse(&contents).ok())
+        .and_then(|node| NotationParseResult::new(node, path.display().to_string()).ok())
+        .and_then(|parsed| parsed.get_arguments().ok())
+        .is_some_and(|args| args.skip)
+}
+
+fn parse_arguments(arguments: &str) -> Result<NotationDocArguments> {
+    let arg_value = format!("bin {}", arguments);
+    Ok(NotationDocArguments::try_parse_from(
+        split_args(arg_value.as_str()).iter(),
+    )?)
+}
+
+/// Converts the markdown file at `include`, relative to the file being converted, in place.
+/// Links inside it are relative to the included file.
+fn include_file(
+    request: &mut AppendBlockRequest,
+    include: &str,
+    position: Option<&Position>,
+    ctx: &ConversionContext,
+) -> Result<()> {
+    let base_path = Path::new(&ctx.file_path).parent().unwrap_or(Path::new(""));
+    let path = base_path.join(include);
+    let key = reconcile_path(&path)
+        .map_err(|e| ctx.error(position, format!("failed to include {}: {}", include, e)))?;
+    // Includes stay in the shipped directory, or the page's when it is unknown, so that pushing
+    // a page can't ship any file the server can read.
+    let root = match &ctx.options.root {
+        Some(root) => root.clone(),
+        None => {
+            let page = ctx.include_stack.first().map_or(Path::new(&ctx.file_path), |p| p.as_path());
+            let dir = page.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(Path::new("."));
+            plain_path(&dir.canonicalize()?)
+        }
+    };
+    let target = path
+        .canonicalize()
+        .map_err(|e| ctx.error(position, format!("failed to include {}: {}", include, e)))?;
+    if !plain_path(&target).starts_with(&root) {
+        return Err(ctx.error(
+            position,
+            format!("failed to include {}: it is out of {}", include, root.display()),
+        ));
+    }
+    let mut include_stack = ctx.include_stack.clone();
+    if include_stack.contains(&key) {
+        let cycle: Vec<String> = include_stack
+            .iter()
+            .chain([&key])
+            .map(|p| p.display().to_string())
+            .collect();
+        return Err(ctx.error(
+            position,
+            format!("include cycle detected: {}", cycle.join(" -> ")),
+        ));
+    }
+    include_stack.push(key);
+
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|e| ctx.error(position, format!("failed to include {}: {}", include, e)))?;
+    let parser = parser_for(&path).unwrap_or(&MarkdownParser);
+    let (mut node, source) = parser
+        .parse_with_source(&contents)
+        .map_err(|e| ctx.error(position, format!("failed to parse included {}: {}", include, e)))?;
+    let redactions = Redactor::new(&ctx.options.redact)?.redact(&path.display().to_string(), &mut node);
+    let include_ctx = ConversionContext {
+        file_path: path.display().to_string(),
+        page_id: ctx.page_id.clone(),
+        path_to_page_id: ctx.path_to_page_id,
+        page_title: ctx.page_title.clone(),
+        options: ctx.options.clone(),
+        warnings: RefCell::new(Vec::new()),
+        include_stack,
+        source,
+        directive: ctx.directive.clone(),
+        redactions: RefCell::new(redactions),
+    };
+    recurse_markdown_tree(request, &node, &node, &include_ctx)?;
+    ctx.warnings
+        .borrow_mut()
+        .extend(include_ctx.warnings.into_inner());
+    ctx.redactions
+        .borrow_mut()
+        .extend(include_ctx.redactions.into_inner());
     Ok(())
 }
 
@@ -356,55 +1144,310 @@ impl NotationParseResult {
             inner: n,
             path,
             file_name,
+            source: None,
+        })
+    }
+
+    pub fn with_source(self, source: Option<String>) -> Self {
+        NotationParseResult { source, ..self }
+    }
+
+    /// The syntax tree of the file.
+    pub fn node(&self) -> &Node {
+        &self.inner
+    }
+
+    pub fn node_mut(&mut self) -> &mut Node {
+        &mut self.inner
+    }
+
+    pub fn into_node(self) -> Node {
+        self.inner
+    }
+
+    /// The path of the file, as it was parsed.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The name of the file without its extension, the title of pages without one.
+    pub fn file_name(&self) -> &str {
+        &self.file_name
+    }
+
+    /// Rewrites the syntax tree before [`NotationParseResult::to_notion`] converts it, like to
+    /// drop the sections meant for internal readers. Positions are left as they were, so
+    /// warnings still point into the file.
+    pub fn map_tree(mut self, f: impl FnOnce(Node) -> Node) -> Self {
+        self.inner = f(self.inner);
+        self
+    }
+
+    /// The page title: the `--title` argument, then what `strategy` takes it from. With
+    /// `is_pretty`, a title from the file stem is made readable with [`pretty_title`]. A title
+    /// from the first heading is taken after `redact`, like the page's blocks.
+    pub fn title(&self, strategy: TitleStrategy, is_pretty: bool, redact: &RedactSettings) -> Result<String> {
+        if let Some(title) = self.get_arguments()?.title {
+            return Ok(title);
+        }
+        Ok(match (strategy, self.first_heading(redact)?) {
+            (TitleStrategy::FirstHeading, Some(heading)) => heading,
+            _ if is_pretty => pretty_title(&self.file_name),
+            _ => self.file_name.clone(),
+        })
+    }
+
+    /// The text of the first `#` heading left after `redact`, the title it gives with
+    /// `title_strategy = "first_heading"`.
+    pub fn first_heading(&self, redact: &RedactSettings) -> Result<Option<String>> {
+        Ok(self.title_heading(redact)?.map(|(_, heading)| heading))
+    }
+
+    /// The `--slug` argument, or the `slug:` of the YAML frontmatter.
+    pub fn slug(&self) -> Result<Option<String>> {
+        Ok(self.get_arguments()?.slug)
+    }
+
+    /// The headings of the top level sections to convert, from the `sections:` of the YAML
+    /// frontmatter. Empty to convert all of them.
+    pub fn sections(&self) -> Vec<String> {
+        self.frontmatter_value("sections")
+            .map(|s| parse_section_names(&s))
+            .unwrap_or_default()
+    }
+
+    /// The tags of the page, from the `tags:` of the YAML frontmatter, either `[a, b]` or a
+    /// list of `- a` lines, then with `hashtags` the `#tags` of its text, without repeats.
+    pub fn tags(&self, hashtags: bool) -> Vec<String> {
+        let mut tags = self.frontmatter_list("tags");
+        if hashtags {
+            let hashtag = HASHTAG.get_or_init(|| Regex::new(r"(?:^|\s)#(\p{L}[\p{L}\p{N}_/-]*)").unwrap());
+            walk(&self.inner, 0, &mut |node, _| {
+                if let Node::Text(t) = node {
+                    tags.extend(
+                        hashtag
+                            .captures_iter(&t.value)
+                            .map(|c| c[1].trim_end_matches(['-', '/']).to_string()),
+                    );
+                }
+            });
+        }
+        let mut seen = BTreeSet::new();
+        tags.retain(|t| seen.insert(t.to_lowercase()));
+        tags
+    }
+
+    /// The `version:` of the YAML frontmatter, like that of a release page.
+    pub fn version(&self) -> Option<String> {
+        self.frontmatter_value("version")
+    }
+
+    /// The `status:` of the YAML frontmatter, like that of a decision record.
+    pub fn status(&self) -> Option<String> {
+        self.frontmatter_value("status")
+    }
+
+    /// The `date:` of the YAML frontmatter, as `2024-05-01`.
+    pub fn date(&self) -> Option<NaiveDate> {
+        self.frontmatter_value("date")
+            .and_then(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d").ok())
+    }
+
+    /// The text of the first paragraph, after `redact`, in at most `max_length` characters,
+    /// for the description of the page.
+    pub fn excerpt(&self, max_length: usize, redact: &RedactSettings) -> Result<Option<String>> {
+        let mut inner = self.inner.clone();
+        Redactor::new(redact)?.redact(&self.path, &mut inner);
+        let paragraph = inner
+            .children()
+            .and_then(|c| c.iter().find(|n| matches!(n, Node::Paragraph(_)) && line_arguments(n).is_none()));
+        let text = match paragraph {
+            Some(p) => p.to_string().split_whitespace().collect::<Vec<_>>().join(" "),
+            None => return Ok(None),
+        };
+        if text.chars().count() <= max_length {
+            return Ok(Some(text));
+        }
+        let truncated: String = text.chars().take(max_length.saturating_sub(1)).collect();
+        Ok(Some(format!("{}…", truncated.trim_end())))
+    }
+
+    /// A top level `key: value` of the YAML frontmatter.
+    fn frontmatter_value(&self, key: &str) -> Option<String> {
+        let frontmatter = self.top_level_nodes().iter().find_map(|n| match n {
+            Node::Yaml(y) => Some(y.value.as_str()),
+            _ => None,
+        });
+        frontmatter.and_then(|f| {
+            f.lines()
+                .filter_map(|l| l.split_once(':'))
+                .find(|(k, _)| k.trim() == key)
+                .map(|(_, v)| v.trim().trim_matches(['"', '\'']).to_string())
+                .filter(|v| !v.is_empty())
         })
     }
 
+    /// A top level list of the YAML frontmatter, `key: [a, b]` or `key:` then `- a` lines.
+    fn frontmatter_list(&self, key: &str) -> Vec<String> {
+        if let Some(value) = self.frontmatter_value(key) {
+            return parse_section_names(&value);
+        }
+        let frontmatter = self.top_level_nodes().iter().find_map(|n| match n {
+            Node::Yaml(y) => Some(y.value.as_str()),
+            _ => None,
+        });
+        let mut lines = frontmatter.into_iter().flat_map(|f| f.lines());
+        if !lines.any(|l| l.split_once(':').is_some_and(|(k, _)| k.trim() == key)) {
+            return Vec::new();
+        }
+        lines
+            .map_while(|l| l.trim().strip_prefix('-'))
+            .map(|v| v.trim().trim_matches(['"', '\'']).to_string())
+            .filter(|v| !v.is_empty())
+            .collect()
+    }
+
+    /// Where the page's arguments are given: the `<!-- notation: ... -->` comment, the first
+    /// line of `--` arguments, or else the frontmatter.
+    fn arguments_position(&self) -> Option<&Position> {
+        let nodes = self.top_level_nodes();
+        nodes
+            .iter()
+            .find(|n| matches!(n, Node::Html(h) if get_arguments_marker(&h.value).is_some()))
+            .or(nodes.first().filter(|n| line_arguments(n).is_some()))
+            .or(nodes.iter().find(|n| matches!(n, Node::Yaml(_))))
+            .and_then(|n| n.position())
+    }
+
+    /// The index, among the top level nodes, of the first `#` heading left after `redact`,
+    /// with its redacted text. A heading of a section left out is never the title.
+    fn title_heading(&self, redact: &RedactSettings) -> Result<Option<(usize, String)>> {
+        let mut inner = self.inner.clone();
+        Redactor::new(redact)?.redact(&self.path, &mut inner);
+        let heading = inner
+            .children()
+            .and_then(|c| c.iter().find(|n| matches!(n, Node::Heading(h) if h.depth == 1)));
+        Ok(heading.and_then(|h| {
+            let index = self.top_level_nodes().iter().position(|n| n.position() == h.position())?;
+            Some((index, h.to_string()))
+        }))
+    }
+
+    fn top_level_nodes(&self) -> &[Node] {
+        self.inner.children().map_or(&[], |c| c.as_slice())
+    }
+
     pub fn to_notion(
         &self,
-        page_id: &String,
+        page_id: &str,
         path_to_page_id: &HashMap<PathBuf, String>,
-    ) -> Result<AppendBlockRequest> {
+        options: &ConversionOptions,
+    ) -> Result<NotionConversion> {
         let mut request = AppendBlockRequest::new_children(vec![]);
-        recurse_markdown_tree(
-            &mut request,
-            &self.inner,
-            &self.inner,
-            &self.path,
-            page_id,
+        let ctx = ConversionContext {
+            file_path: self.path.clone(),
+            page_id: page_id.to_string(),
             path_to_page_id,
-            &self
-                .get_arguments()?
-                .title
-                .unwrap_or(self.file_name.clone()),
-        )?;
-        Ok(request)
-    }
-
-    pub fn get_arguments(&self) -> Result<NotationDocArguments> {
-        if let Some(c) = self.inner.children() {
-            let first_line = c.first();
-            if let Some(fl) = first_line {
-                if let Node::Paragraph(p) = fl {
-                    for pc in p.children.iter() {
-                        if let Node::Text(t) = pc {
-                            let arg_value = format!("bin {}", t.value.as_str());
-                            let args = NotationDocArguments::try_parse_from(
-                                split_args(arg_value.as_str()).iter(),
-                            )?;
-                            return Ok(args);
-                        }
-                    }
+            page_title: self.title(options.title_strategy, options.pretty_titles, &options.redact)?,
+            options: options.clone(),
+            warnings: RefCell::new(Vec::new()),
+            include_stack: vec![reconcile_path(Path::new(&self.path))?],
+            source: self.source.clone(),
+            directive: None,
+            redactions: RefCell::new(Vec::new()),
+        };
+        for setting in self.get_arguments()?.unsupported_page_settings() {
+            ctx.warn(
+                self.arguments_position(),
+                format!("{} is a page setting the Notion API can't change, set it on the page in Notion", setting),
+            );
+        }
+        // A heading the title came from would only repeat it on the page.
+        let is_title_from_heading = options.title_strategy == TitleStrategy::FirstHeading
+            && self.get_arguments()?.title.is_none();
+        let mut removed: Vec<usize> = match self.title_heading(&options.redact)? {
+            Some((i, _)) if is_title_from_heading => vec![i],
+            _ => Vec::new(),
+        };
+        if self.top_level_nodes().first().is_some_and(|n| line_arguments(n).is_some()) {
+            removed.insert(0, 0);
+        }
+        let sections = if options.sections.is_empty() {
+            self.sections()
+        } else {
+            options.sections.clone()
+        };
+        let mut inner = self.inner.clone();
+        if let Some(children) = inner.children_mut() {
+            for i in removed.into_iter().rev() {
+                children.remove(i);
+            }
+            if !sections.is_empty() {
+                for name in select_sections(children, &sections) {
+                    ctx.warn(None, format!("no section \"{}\", leaving it out", name));
                 }
             }
         }
-        Ok(NotationDocArguments::default())
+        let redactions = Redactor::new(&options.redact)?.redact(&self.path, &mut inner);
+        ctx.redactions.borrow_mut().extend(redactions);
+        recurse_markdown_tree(&mut request, &inner, &inner, &ctx)?;
+        Ok(NotionConversion {
+            request,
+            warnings: ctx.warnings.into_inner(),
+            redactions: ctx.redactions.into_inner(),
+        })
+    }
+
+    /// The arguments of a `<!-- notation: ... -->` comment, or else of a first paragraph of
+    /// `--` arguments, over the same keys of the YAML frontmatter, with `draft` for `skip`.
+    pub fn get_arguments(&self) -> Result<NotationDocArguments> {
+        let marker = self.top_level_nodes().iter().find_map(|n| match n {
+            Node::Html(h) => get_arguments_marker(&h.value).map(|m| (m, h.position.as_ref())),
+            _ => None,
+        });
+        let args = match marker {
+            Some((arguments, position)) => parse_arguments(arguments).map_err(|e| {
+                anyhow::Error::from(Diagnostic::error(
+                    &self.path,
+                    position,
+                    format!("invalid notation arguments: {}", e),
+                ))
+            })?,
+            None => self
+                .top_level_nodes()
+                .first()
+                .and_then(line_arguments)
+                .unwrap_or_default(),
+        };
+        Ok(NotationDocArguments {
+            emoji: args.emoji.or_else(|| self.frontmatter_value("emoji")),
+            title: args.title.or_else(|| self.frontmatter_value("title")),
+            slug: args.slug.or_else(|| self.frontmatter_value("slug")),
+            skip: args.skip || self.frontmatter_value("draft").as_deref() == Some("true"),
+            full_width: args.full_width || self.frontmatter_value("full_width").as_deref() == Some("true"),
+            small_text: args.small_text || self.frontmatter_value("small_text").as_deref() == Some("true"),
+            font: args.font.or_else(|| {
+                self.frontmatter_value("font")
+                    .and_then(|f| PageFont::from_str(&f, true).ok())
+            }),
+            cover: args.cover.or_else(|| self.frontmatter_value("cover")),
+            description: args.description.or_else(|| self.frontmatter_value("description")),
+            order: match args.order.is_empty() {
+                true => self.frontmatter_list("order"),
+                false => args.order.iter().map(|o| o.trim().to_string()).filter(|o| !o.is_empty()).collect(),
+            },
+        })
     }
 }
 
-pub fn reconcile_path(path: &PathBuf) -> Result<PathBuf> {
+/// `path` with its `..` resolved and its components percent-decoded, the key of the page of
+/// a file. Absolute paths stay absolute, with a Windows prefix as [`plain_path`] gives it.
+pub fn reconcile_path(path: &Path) -> Result<PathBuf> {
     let mut p = PathBuf::new();
     for c in path.components() {
         match c {
+            Component::Prefix(prefix) => p.push(plain_prefix(prefix.kind(), prefix.as_os_str())),
+            Component::RootDir => p.push(Component::RootDir),
             Component::Normal(n) => {
                 let component_string = n
                     .to_str()
@@ -430,27 +1473,161 @@ pub fn reconcile_path(path: &PathBuf) -> Result<PathBuf> {
     Ok(p)
 }
 
+/// The key of the page of the file at `path` in the map of shipped pages, the same for every
+/// spelling of its path: absolute, with the case of the names on disk, for the filesystems
+/// that ignore it, and through symlinks with [`SymlinkPolicy::Follow`]. A file that doesn't
+/// exist keeps its path as [`reconcile_path`] gives it.
+pub fn page_key(path: &Path, symlinks: SymlinkPolicy) -> Result<PathBuf> {
+    let reconciled = reconcile_path(path)?;
+    if !reconciled.exists() {
+        return Ok(reconciled);
+    }
+    let absolute = match symlinks {
+        SymlinkPolicy::Follow => reconciled.canonicalize()?,
+        SymlinkPolicy::Skip => std::path::absolute(&reconciled)?,
+    };
+    Ok(on_disk_case(&plain_path(&absolute)))
+}
+
+/// `path`, an existing absolute path, with each name as the directory holding it spells it.
+fn on_disk_case(path: &Path) -> PathBuf {
+    let mut cased = PathBuf::new();
+    for c in path.components() {
+        let name = match c {
+            Component::Normal(name) => name,
+            c => {
+                cased.push(c);
+                continue;
+            }
+        };
+        let on_disk = std::fs::read_dir(&cased).ok().and_then(|entries| {
+            let names: Vec<OsString> = entries.filter_map(|e| e.ok().map(|e| e.file_name())).collect();
+            if names.iter().any(|n| n == name) {
+                return None;
+            }
+            let lowercase = name.to_string_lossy().to_lowercase();
+            names.into_iter().find(|n| n.to_string_lossy().to_lowercase() == lowercase)
+        });
+        cased.push(on_disk.as_deref().unwrap_or(name));
+    }
+    cased
+}
+
+/// `path` without its `.` components, `.` itself aside, and without the `\\?\` a verbatim Windows path, like
+/// the ones `canonicalize` gives, starts with, so both spellings of a path compare equal and
+/// glob alike.
+pub fn plain_path(path: &Path) -> PathBuf {
+    let plain: PathBuf = path
+        .components()
+        .filter(|c| *c != Component::CurDir)
+        .map(|c| match c {
+            Component::Prefix(prefix) => plain_prefix(prefix.kind(), prefix.as_os_str()),
+            c => c.as_os_str().to_os_string(),
+        })
+        .collect();
+    if plain.as_os_str().is_empty() && path.components().next().is_some() {
+        return PathBuf::from(".");
+    }
+    plain
+}
+
+fn plain_prefix(prefix: Prefix, raw: &OsStr) -> OsString {
+    match prefix {
+        Prefix::Disk(d) | Prefix::VerbatimDisk(d) => format!("{}:", d.to_ascii_uppercase() as char).into(),
+        Prefix::VerbatimUNC(server, share) | Prefix::UNC(server, share) => {
+            let mut unc = OsString::from(r"\\");
+            unc.push(server);
+            unc.push(r"\");
+            unc.push(share);
+            unc
+        }
+        _ => raw.to_os_string(),
+    }
+}
+
+/// A file or directory name as a title, like `Getting Started` for `getting_started`: dashes
+/// and underscores become spaces and every word starts with a capital, the rest of it as is.
+pub fn pretty_title(stem: &str) -> String {
+    stem.split(['_', '-', ' '])
+        .filter(|w| !w.is_empty())
+        .map(|w| {
+            let mut chars = w.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Parses a documentation file with the parser for its extension, markdown when there is none.
 pub async fn parse_file(path: &Path) -> Result<NotationParseResult> {
     let contents = tokio::fs::read_to_string(path).await?;
-    let parsing_options = ParseOptions::gfm();
-    let pr = markdown::to_mdast(&contents, &parsing_options).map_err(|e| anyhow::anyhow!(e))?;
-    Ok(NotationParseResult::new(pr, format!("{path:?}"))?)
+    let parser = parser_for(path).unwrap_or(&MarkdownParser);
+    let (pr, source) = parser.parse_with_source(&contents)?;
+    Ok(NotationParseResult::new(pr, path.display().to_string())?.with_source(source))
+}
+
+/// Like [`parse_file`], for code that can't wait on it.
+pub fn parse_file_sync(path: &Path) -> Result<NotationParseResult> {
+    let contents = std::fs::read_to_string(path)?;
+    let parser = parser_for(path).unwrap_or(&MarkdownParser);
+    let (pr, source) = parser.parse_with_source(&contents)?;
+    Ok(NotationParseResult::new(pr, path.display().to_string())?.with_source(source))
 }
 
-pub fn get_md_glob_pattern(dir: String) -> String {
-    if dir.ends_with(".md") {
-        dir.clone()
+/// Matches `dir` itself when it is a documentation file, otherwise every file below it, to be
+/// filtered with [`is_source_file`]. `dir` is matched literally, with the separators of the
+/// platform.
+pub fn get_source_glob_pattern(dir: String) -> String {
+    let dir = plain_path(Path::new(&dir));
+    let escaped = PathBuf::from(Pattern::escape(&dir.to_string_lossy()));
+    if is_source_file(&dir) {
+        escaped.to_string_lossy().into_owned()
     } else {
-        format!("{}/**/*.md", dir.strip_suffix("/").unwrap_or(dir.as_str()))
+        escaped.join("**").join("*").to_string_lossy().into_owned()
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+    use std::path::Path;
+
+    use anyhow::Result;
     use clap::Parser;
+    use markdown::mdast::Node;
 
-    use crate::markdown::parse::NotationDocArguments;
+    use crate::markdown::parse::{build_table, get_source_glob_pattern, on_disk_case, page_key, parse_file, plain_path, pretty_title, reconcile_path, ConversionOptions, NotationDocArguments, NotationParseResult, NotionConversion, PageFont};
+    use crate::markdown::source::{MarkdownParser, SourceParser};
     use crate::markdown::util::split_args;
+    use crate::notion::block::{AppendBlockRequestChild, BlockType, NotionBlock, TextAnnotations};
+    use crate::notion::database::ColumnType;
+    use crate::notion::page::{multi_select_property, rich_text_property, CreatePageRequest};
+    use crate::settings::notation::{
+        LineBreakPolicy, OutsideLinkPolicy, LinkedImageStrategy, RedactSettings, SvgStrategy, SymlinkPolicy, TitleStrategy, UnsupportedNodePolicy,
+    };
+
+    /// `md` as the contents of `file_name`.
+    fn parse_as(file_name: &str, md: &str) -> NotationParseResult {
+        NotationParseResult::new(MarkdownParser.parse(md).unwrap(), file_name.to_string()).unwrap()
+    }
+
+    /// `md` as the contents of page.md.
+    fn parse(md: &str) -> NotationParseResult {
+        parse_as("page.md", md)
+    }
+
+    /// `page` converted with `options`, with no other page to link to.
+    fn convert_page(page: &NotationParseResult, options: &ConversionOptions) -> Result<NotionConversion> {
+        page.to_notion("page", &HashMap::new(), options)
+    }
+
+    /// The blocks of `md`, converted with the default options.
+    fn convert(md: &str) -> Vec<AppendBlockRequestChild> {
+        convert_page(&parse(md), &ConversionOptions::default()).unwrap().request.children
+    }
 
     #[tokio::test(flavor = "multi_thread")]
     pub async fn test_doc_arguments() {
@@ -459,9 +1636,833 @@ mod tests {
         println!("{:?}", args);
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_argument_markers() {
+        let options = ConversionOptions::default();
+
+        let text = parse("Just text first.\n\n# Page");
+        assert!(text.get_arguments().unwrap().title.is_none());
+        assert_eq!(convert_page(&text, &options).unwrap().request.children.len(), 2);
+
+        let dashes = parse("--not-an-argument is content\n\n# Page");
+        assert!(dashes.get_arguments().unwrap().title.is_none());
+        assert_eq!(convert_page(&dashes, &options).unwrap().request.children.len(), 2);
+
+        let line = parse("--emoji 🚀\n\n# Page");
+        assert_eq!(line.get_arguments().unwrap().emoji.as_deref(), Some("🚀"));
+        assert_eq!(convert_page(&line, &options).unwrap().request.children.len(), 1);
+
+        let marker = parse("<!-- notation: --emoji 🚀 --title \"Getting Started\" -->\n\n# Page");
+        let args = marker.get_arguments().unwrap();
+        assert_eq!(args.emoji.as_deref(), Some("🚀"));
+        assert_eq!(args.title.as_deref(), Some("Getting Started"));
+        assert_eq!(convert_page(&marker, &options).unwrap().request.children.len(), 1);
+
+        let frontmatter = parse("---\ntitle: \"From: Frontmatter\"\nemoji: 📚\n---\n\n<!-- notation: --title Given -->\n\n# Page");
+        let args = frontmatter.get_arguments().unwrap();
+        assert_eq!(args.title.as_deref(), Some("Given"));
+        assert_eq!(args.emoji.as_deref(), Some("📚"));
+        assert_eq!(parse("---\ntitle: \"From: Frontmatter\"\n---\n").get_arguments().unwrap().title.as_deref(), Some("From: Frontmatter"));
+
+        let invalid = parse("<!-- notation: --bogus -->\n\n# Page");
+        assert!(invalid.get_arguments().unwrap_err().to_string().contains("invalid notation arguments"));
+        let dashless = parse("<!-- notation: emoji 🚀 -->\n\n# Page");
+        assert!(dashless.get_arguments().unwrap_err().to_string().contains("invalid notation arguments"));
+        assert!(parse("<!-- notation: -->\n\n# Page").get_arguments().is_ok());
+        assert!(parse("<!-- notation:database -->\n\n# Page").get_arguments().is_ok());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_drafts() {
+        assert!(parse("--skip\n\n# Page").get_arguments().unwrap().skip);
+        assert!(parse("---\ndraft: true\n---\n\n# Page").get_arguments().unwrap().skip);
+        assert!(!parse("---\ndraft: false\n---\n\n# Page").get_arguments().unwrap().skip);
+
+        let dir = std::env::temp_dir().join(format!("notation-drafts-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("page.md"), "# Page\n\nSee [the plan](./plan.md).\n").unwrap();
+        std::fs::write(dir.join("plan.md"), "<!-- notation: --skip -->\n\n# Plan\n").unwrap();
+        let page = parse_file(&dir.join("page.md")).await.unwrap();
+        let conversion = convert_page(&page, &ConversionOptions::default()).unwrap();
+        let text = conversion.request.children[1].get_rich_text_blocks().unwrap();
+        assert!(text.iter().all(|t| t.text.as_ref().unwrap().link.is_none()));
+        assert_eq!(text[1].text.as_ref().unwrap().content, "the plan");
+        assert!(conversion.warnings[0].message.contains("is a draft"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_page_settings() {
+        let page = parse("<!-- notation: --full-width --font mono -->\n\n# Page");
+        let args = page.get_arguments().unwrap();
+        assert_eq!(args.font, Some(PageFont::Mono));
+        assert_eq!(args.unsupported_page_settings(), vec!["--full-width", "--font mono"]);
+        let conversion = convert_page(&page, &ConversionOptions::default()).unwrap();
+        assert_eq!(conversion.warnings.len(), 2);
+        assert!(conversion.warnings[0].message.starts_with("--full-width is a page setting the Notion API can't change"));
+
+        let frontmatter = parse("---\nsmall_text: true\nfont: Serif\n---\n\n# Page");
+        assert_eq!(frontmatter.get_arguments().unwrap().unsupported_page_settings(), vec!["--small-text", "--font serif"]);
+        assert!(parse("--font default\n\n# Page").get_arguments().unwrap().unsupported_page_settings().is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_excerpt() {
+        let redact = RedactSettings {
+            patterns: vec![r"sk_\w+".to_string()],
+            ..Default::default()
+        };
+        let page = parse("--emoji 🚀\n\n# Page\n\nShip the docs\nwith `sk_live` **fast**.\n\nMore");
+        assert_eq!(page.excerpt(200, &redact).unwrap().as_deref(), Some("Ship the docs with [REDACTED] fast."));
+        assert_eq!(page.excerpt(10, &redact).unwrap().as_deref(), Some("Ship the…"));
+        assert!(parse("# Page\n\n- Only a list").excerpt(200, &redact).unwrap().is_none());
+
+        let row = CreatePageRequest::new_row("db".to_string(), "Page".to_string())
+            .with_property("Summary".to_string(), rich_text_property("Ship the docs"));
+        let json = serde_json::to_value(&row).unwrap();
+        assert_eq!(json["parent"], serde_json::json!({ "database_id": "db" }));
+        assert_eq!(json["properties"]["Summary"]["rich_text"][0]["text"]["content"], "Ship the docs");
+        assert_eq!(json["properties"]["title"]["title"][0]["text"]["content"], "Page");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_tags() {
+        let inline = parse("---\ntags: [api, 'Getting Started']\n---\n\n# Page\n\nAbout the #API and #deploys, see #42.");
+        assert_eq!(inline.tags(false), vec!["api", "Getting Started"]);
+        assert_eq!(inline.tags(true), vec!["api", "Getting Started", "deploys"]);
+
+        let block = parse("---\ntitle: Page\ntags:\n  - api\n  - \"ops\"\nemoji: 📚\n---\n\n# Page\n\n`#not-a-tag`");
+        assert_eq!(block.tags(true), vec!["api", "ops"]);
+        assert!(parse("# Page\n\nText").tags(true).is_empty());
+        assert_eq!(
+            multi_select_property(&block.tags(false)),
+            serde_json::json!({ "multi_select": [{ "name": "api" }, { "name": "ops" }] })
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_database_tables() {
+        let page = parse(
+            "# Page\n\n<!-- notation:database name=\"Releases\" -->\n| Notes | Version {title} | Released {date} | Stable {checkbox} |\n| - | - | - | - |\n| First | 1.0 | 2024-05-01 | yes |\n| Second | 2.0 | soon | no |\n",
+        );
+        assert!(page.get_arguments().unwrap().title.is_none());
+        let conversion = convert_page(&page, &ConversionOptions::default()).unwrap();
+        assert_eq!(conversion.request.children.len(), 2);
+        let database = conversion.request.children[1].child_database().unwrap();
+        assert_eq!(database.title, "Releases");
+        let columns: Vec<(&str, ColumnType)> = database.columns.iter().map(|c| (c.name.as_str(), c.column_type)).collect();
+        assert_eq!(
+            columns,
+            vec![("Notes", ColumnType::Text), ("Version", ColumnType::Title), ("Released", ColumnType::Date), ("Stable", ColumnType::Checkbox)]
+        );
+        assert_eq!(database.rows[1], vec!["Second", "2.0", "", "no"]);
+        assert_eq!(conversion.warnings.len(), 1);
+        assert!(conversion.warnings[0].message.contains("\"soon\" is not a date"));
+
+        let untyped = convert("<!-- notation:database -->\n| A | B |\n| - | - |\n| 1 |\n");
+        let database = untyped[0].child_database().unwrap();
+        assert_eq!(database.columns[0].column_type, ColumnType::Title);
+        assert_eq!(database.rows[0], vec!["1", ""]);
+
+        let stray = convert_page(&parse("<!-- notation:database name=\"Nothing\" -->\n\nText\n"), &ConversionOptions::default()).unwrap();
+        assert!(stray.warnings[0].message.contains("only applies to a table right after it"));
+        let unknown = convert_page(&parse("<!-- notation:database -->\n| A {money} |\n| - |\n"), &ConversionOptions::default());
+        assert!(unknown.unwrap_err().to_string().contains("unknown column type {money}"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_ragged_tables() {
+        let table = |md: &str| {
+            let node = MarkdownParser.parse(md).unwrap();
+            match node.children().unwrap().first() {
+                Some(Node::Table(t)) => build_table(t).remove(0).table().unwrap().clone(),
+                n => panic!("not a table: {:?}", n),
+            }
+        };
+
+        let ragged = table("| A | B | C |\n| - | - | - |\n| 1 |\n| 1 | | 3 |\n| `x` **y** |  |\n");
+        assert_eq!(ragged.table_width, 3);
+        let rows: Vec<Vec<Vec<NotionBlock>>> = ragged
+            .children
+            .into_iter()
+            .map(|r| r.table_row().unwrap().cells.clone())
+            .collect();
+        assert!(rows.iter().all(|r| r.len() == 3));
+        assert!(rows[1][1].is_empty() && rows[1][2].is_empty());
+        assert!(rows[2][1].is_empty());
+        assert_eq!(rows[2][2][0].text.as_ref().unwrap().content, "3");
+        assert_eq!(rows[3][0].len(), 3);
+        assert!(rows[3][0][0].annotations.as_ref().unwrap().code);
+
+        let rows: String = (0..250).map(|i| format!("| {} |\n", i)).collect();
+        let page = parse(&format!("| N |\n| - |\n{}", rows));
+        let conversion = convert_page(&page, &ConversionOptions::default()).unwrap();
+        assert_eq!(conversion.request.children[0].table().unwrap().children.len(), 251);
+        assert!(conversion.warnings[0].message.starts_with("table has 251 rows, more than the 100 Notion takes at once, the rest are appended to it in 2 more request(s)"));
+
+        let empty = table("| |\n| - |\n");
+        assert_eq!(empty.table_width, 1);
+        assert!(empty.children[0].table_row().unwrap().cells[0].is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_repository_images() {
+        let dir = std::env::temp_dir().join(format!("notation-images-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("docs")).unwrap();
+        std::fs::create_dir_all(dir.join("assets")).unwrap();
+        git2::Repository::init(&dir).unwrap();
+        std::fs::write(dir.join("assets/logo v2.png"), "png").unwrap();
+        std::fs::write(
+            dir.join("docs/page.md"),
+            "# Page\n\n![logo](../assets/logo%20v2.png) ![hosted](https://example.com/x.png)\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("docs/missing.md"), "# Page\n\n![gone](./gone.png)\n").unwrap();
+
+        let page = parse_file(&dir.join("docs/page.md")).await.unwrap();
+        let e = convert_page(&page, &ConversionOptions::default()).unwrap_err();
+        assert!(e.to_string().contains("set [content] image_base_url"));
+
+        let options = ConversionOptions {
+            image_base_url: Some("https://raw.githubusercontent.com/org/repo/main/".to_string()),
+            ..Default::default()
+        };
+        let conversion = convert_page(&page, &options).unwrap();
+        let urls: Vec<String> = conversion
+            .request
+            .children
+            .iter()
+            .filter_map(|c| c.image().and_then(|i| i.url()).map(|u| u.to_string()))
+            .collect();
+        assert_eq!(
+            urls,
+            vec!["https://raw.githubusercontent.com/org/repo/main/assets/logo%20v2.png", "https://example.com/x.png"]
+        );
+        let missing = parse_file(&dir.join("docs/missing.md")).await.unwrap();
+        let e = convert_page(&missing, &options).unwrap_err();
+        assert!(e.to_string().contains("image ./gone.png not found"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_svg_strategies() {
+        let dir = std::env::temp_dir().join(format!("notation-svgs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        git2::Repository::init(&dir).unwrap();
+        std::fs::write(dir.join("flow.svg"), "<svg/>").unwrap();
+        std::fs::write(dir.join("page.md"), "# Page\n\n![flow](./flow.svg) ![logo](https://example.com/logo.svg)\n").unwrap();
+        let page = parse_file(&dir.join("page.md")).await.unwrap();
+        let options = |svg: SvgStrategy| ConversionOptions {
+            image_base_url: Some("https://example.com/repo/".to_string()),
+            svg,
+            ..Default::default()
+        };
+
+        let conversion = convert_page(&page, &options(SvgStrategy::PassThrough)).unwrap();
+        let urls: Vec<&str> = conversion.request.children.iter().filter_map(|c| c.image()?.url()).collect();
+        assert_eq!(urls, vec!["https://example.com/repo/flow.svg", "https://example.com/logo.svg"]);
+
+        let conversion = convert_page(&page, &options(SvgStrategy::File)).unwrap();
+        let urls: Vec<&str> = conversion
+            .request
+            .children
+            .iter()
+            .filter_map(|c| c.file().map(|f| f.external.url.as_str()))
+            .collect();
+        assert_eq!(urls, vec!["https://example.com/repo/flow.svg", "https://example.com/logo.svg"]);
+
+        let conversion = convert_page(&page, &options(SvgStrategy::Png)).unwrap();
+        let images: Vec<_> = conversion.request.children.iter().filter_map(|c| c.image()).collect();
+        assert_eq!(images.len(), 2);
+        if cfg!(feature = "svg") {
+            let local = dir.join("flow.svg").canonicalize().unwrap();
+            assert_eq!(images[0].svg_source.as_deref(), Some(local.to_str().unwrap()));
+            assert_eq!(images[1].svg_source.as_deref(), Some("https://example.com/logo.svg"));
+            assert!(conversion.warnings.is_empty());
+        } else {
+            assert!(images.iter().all(|i| i.svg_source.is_none()));
+            assert!(conversion.warnings[0].message.contains("needs notation built with the svg feature"));
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_images_in_paragraphs() {
+        let markdown = "Before ![one](https://a.com/1.png) between **bold**\n![two](https://a.com/2.png)![three](https://a.com/3.png)\nafter ![four](https://a.com/4.png)\n";
+        let blocks: Vec<String> = convert(markdown)
+            .iter()
+            .map(|c| match (c.image(), c.paragraph()) {
+                (Some(image), _) => image.url().unwrap().to_string(),
+                (_, Some(p)) => p.rich_text.iter().map(|t| t.text.as_ref().unwrap().content.as_str()).collect(),
+                _ => panic!("unexpected block {:?}", c.block_type()),
+            })
+            .collect();
+        assert_eq!(
+            blocks,
+            vec![
+                "Before",
+                "https://a.com/1.png",
+                "between bold",
+                "https://a.com/2.png",
+                "https://a.com/3.png",
+                "after",
+                "https://a.com/4.png",
+            ]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_hard_breaks() {
+        let markdown = "First line  \nsecond line\\\n**third\\\nline**\nsoft\n";
+        let blocks = convert(markdown);
+        let rich_text = &blocks[0].paragraph().unwrap().rich_text;
+        let text: String = rich_text.iter().map(|t| t.text.as_ref().unwrap().content.as_str()).collect();
+        assert_eq!(text, "First line\nsecond line\nthird\nline soft");
+        assert_eq!(rich_text.iter().filter(|t| t.text.as_ref().unwrap().content == "\n").count(), 3);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_line_break_policy() {
+        let markdown = "One sentence.\nAnother **one,\nbold**.\n";
+        let page = parse(markdown);
+        let text = |line_break: LineBreakPolicy| -> String {
+            let options = ConversionOptions {
+                line_break,
+                ..Default::default()
+            };
+            let conversion = convert_page(&page, &options).unwrap();
+            let rich_text = &conversion.request.children[0].paragraph().unwrap().rich_text;
+            rich_text.iter().map(|t| t.text.as_ref().unwrap().content.as_str()).collect()
+        };
+        assert_eq!(text(LineBreakPolicy::Reflow), "One sentence. Another one, bold.");
+        assert_eq!(text(LineBreakPolicy::Preserve), "One sentence.\nAnother one,\nbold.");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_entities_and_escapes() {
+        let markdown = "# Tom &amp; Jerry\n\n\\*not bold\\* \\_nor em\\_ &lt;tag&gt; &copy;&nbsp;&#65;&#x42; **a &amp; b** `x &amp; y`\n";
+        let blocks = convert(markdown);
+        let text = |i: usize| -> Vec<String> {
+            blocks[i]
+                .get_rich_text_blocks()
+                .unwrap()
+                .into_iter()
+                .map(|t| t.text.unwrap().content)
+                .collect()
+        };
+        assert_eq!(text(0), vec!["Tom & Jerry"]);
+        assert_eq!(text(1), vec!["*not bold* _nor em_ <tag> \u{a9}\u{a0}AB ", "a & b", " ", "x &amp; y"]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_linked_images() {
+        let markdown = "[![build](https://ci.com/badge.svg)](https://ci.com) [![](https://x.com/v.png)](https://x.com) docs\n";
+        let page = parse(markdown);
+        let options = |linked_images: LinkedImageStrategy| ConversionOptions {
+            linked_images,
+            ..Default::default()
+        };
+
+        let conversion = convert_page(&page, &options(LinkedImageStrategy::Caption)).unwrap();
+        let children = &conversion.request.children;
+        assert_eq!(children.len(), 3);
+        let captions: Vec<(String, Option<String>)> = children[..2]
+            .iter()
+            .map(|c| {
+                let caption = c.image().unwrap().caption[0].text.clone().unwrap();
+                (caption.content, caption.link.map(|l| l.url))
+            })
+            .collect();
+        assert_eq!(
+            captions,
+            vec![
+                ("build".to_string(), Some("https://ci.com".to_string())),
+                ("https://x.com".to_string(), Some("https://x.com".to_string())),
+            ]
+        );
+        let text = &children[2].paragraph().unwrap().rich_text;
+        assert_eq!(text[0].text.as_ref().unwrap().content, "docs");
+
+        let conversion = convert_page(&page, &options(LinkedImageStrategy::Text)).unwrap();
+        let children = &conversion.request.children;
+        assert_eq!(children.len(), 1);
+        let text: Vec<(String, Option<String>)> = children[0]
+            .paragraph()
+            .unwrap()
+            .rich_text
+            .iter()
+            .map(|t| {
+                let text = t.text.clone().unwrap();
+                (text.content, text.link.map(|l| l.url))
+            })
+            .collect();
+        assert_eq!(text[0], ("build".to_string(), Some("https://ci.com".to_string())));
+        assert_eq!(text[2], ("https://x.com/v.png".to_string(), Some("https://x.com".to_string())));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_unsupported_nodes() {
+        let page = parse("# Page\n\nIntro\n\n> a **quote**\n\n---\n");
+        let options = |unsupported_nodes: UnsupportedNodePolicy| ConversionOptions {
+            unsupported_nodes,
+            ..Default::default()
+        };
+
+        let conversion = convert_page(&page, &options(UnsupportedNodePolicy::Warn)).unwrap();
+        assert_eq!(conversion.request.children.len(), 2);
+        let warnings: Vec<String> = conversion.warnings.iter().map(|w| w.to_string()).collect();
+        assert_eq!(
+            warnings,
+            vec![
+                "(page=page.md:5:1) block_quote is not supported, leaving it out",
+                "(page=page.md:7:1) thematic_break is not supported, leaving it out",
+            ]
+        );
+
+        let conversion = convert_page(&page, &options(UnsupportedNodePolicy::Ignore)).unwrap();
+        assert!(conversion.warnings.is_empty());
+
+        let e = convert_page(&page, &options(UnsupportedNodePolicy::Error)).unwrap_err();
+        assert_eq!(e.to_string(), "(page=page.md:5:1) block_quote is not supported");
+
+        let conversion = convert_page(&page, &options(UnsupportedNodePolicy::RenderAsCode)).unwrap();
+        let code: Vec<String> = conversion
+            .request
+            .children
+            .iter()
+            .filter(|c| matches!(c.block_type(), BlockType::Code))
+            .map(|c| c.code().unwrap().rich_text.iter().map(|t| t.text.as_ref().unwrap().content.clone()).collect())
+            .collect();
+        // Without the file to read from, only the text of the node is left.
+        assert_eq!(code, vec!["a quote", ""]);
+        assert!(conversion.warnings.is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_unsupported_nodes_as_code() {
+        let dir = std::env::temp_dir().join(format!("notation-unsupported-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("page.md"), "# Page\n\n> a **quote**\n").unwrap();
+        let page = parse_file(&dir.join("page.md")).await.unwrap();
+        let options = ConversionOptions {
+            unsupported_nodes: UnsupportedNodePolicy::RenderAsCode,
+            ..Default::default()
+        };
+        let conversion = convert_page(&page, &options).unwrap();
+        let code = conversion.request.children.iter().find_map(|c| c.code()).unwrap();
+        assert_eq!(code.language.as_deref(), Some("markdown"));
+        assert_eq!(code.rich_text[0].text.as_ref().unwrap().content, "> a **quote**");
+
+        // Directives are rewritten before parsing, which moves what comes after them.
+        let markdown = "# Page\n\n:::warning 🔥 Careful\nThis **breaks** things.\n:::\n\n> a **quote**\n\n:::note\n> in a note\n:::\n";
+        std::fs::write(dir.join("page.md"), markdown).unwrap();
+        let page = parse_file(&dir.join("page.md")).await.unwrap();
+        std::fs::write(dir.join("page.md"), "changed since").unwrap();
+        let conversion = convert_page(&page, &options).unwrap();
+        let json = serde_json::to_string(&conversion.request).unwrap();
+        assert!(json.contains(r#""content":"> a **quote**""#));
+        assert!(json.contains(r#""content":"> in a note""#));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_warning_positions() {
+        let markdown = "<!-- notation: --full-width -->\n\n# Page\n\n:::column\nAlone\n:::\n\n::::callout\n:::column\nNested\n:::\n::::\n\n<!-- notation:database -->\n| Name | Count {number} |\n| --- | --- |\n| a | many |\n";
+        let page = parse(markdown);
+        let conversion = convert_page(&page, &ConversionOptions::default()).unwrap();
+        let warnings: Vec<String> = conversion.warnings.iter().map(|w| w.to_string()).collect();
+        assert_eq!(
+            warnings,
+            vec![
+                "(page=page.md:1:1) --full-width is a page setting the Notion API can't change, set it on the page in Notion",
+                "(page=page.md:5:1) :::column is only valid inside :::columns, shipping its content as is",
+                "(page=page.md:10:1) :::column is only valid inside :::columns, shipping its content as is",
+                "(page=page.md:18:5) \"many\" is not a number in column \"Count\" of database \"\", leaving it empty",
+            ]
+        );
+        let types: Vec<BlockType> = conversion.request.children.iter().map(|c| c.block_type()).collect();
+        assert!(matches!(
+            types.as_slice(),
+            [BlockType::Heading1, BlockType::Paragraph, BlockType::Callout, BlockType::ChildDatabase]
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_nested_formatting() {
+        let rich_text = convert("**bold `code` and _both_** ~~gone~~ *`slanted`*")[0].paragraph().unwrap().rich_text.clone();
+        let texts: Vec<(String, TextAnnotations)> = rich_text
+            .into_iter()
+            .map(|t| (t.text.unwrap().content, t.annotations.unwrap_or_default()))
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                ("bold ".to_string(), TextAnnotations::new().bold()),
+                ("code".to_string(), TextAnnotations::new().bold().code()),
+                (" and ".to_string(), TextAnnotations::new().bold()),
+                ("both".to_string(), TextAnnotations::new().bold().italic()),
+                (" ".to_string(), TextAnnotations::new()),
+                ("gone".to_string(), TextAnnotations::new().strikethrough()),
+                (" ".to_string(), TextAnnotations::new()),
+                ("slanted".to_string(), TextAnnotations::new().italic().code()),
+            ]
+        );
+        assert_eq!(TextAnnotations::new().color("red").color, "red");
+        assert!(!TextAnnotations::new().underline().is_plain());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_formatted_links() {
+        let blocks = convert("**[bold](https://a.com)** [**strong**](https://b.com) [`code`](https://c.com) _[](https://d.com)_");
+        let rich_text = blocks[0].paragraph().unwrap().rich_text.clone();
+        let links: Vec<(String, Option<String>, TextAnnotations)> = rich_text
+            .into_iter()
+            .filter_map(|t| {
+                let text = t.text?;
+                Some((text.content, text.link.map(|l| l.url), t.annotations.unwrap_or_default()))
+            })
+            .filter(|(content, _, _)| content != " ")
+            .collect();
+        assert_eq!(
+            links,
+            vec![
+                ("bold".to_string(), Some("https://a.com".to_string()), TextAnnotations::new().bold()),
+                ("strong".to_string(), Some("https://b.com".to_string()), TextAnnotations::new().bold()),
+                ("code".to_string(), Some("https://c.com".to_string()), TextAnnotations::new().code()),
+                ("https://d.com".to_string(), Some("https://d.com".to_string()), TextAnnotations::new().italic()),
+            ]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_link_text_pieces() {
+        let rich_text = convert("[see **this** section](https://a.com) after")[0].paragraph().unwrap().rich_text.clone();
+        let pieces: Vec<(String, Option<String>, bool)> = rich_text
+            .into_iter()
+            .map(|t| {
+                let text = t.text.unwrap();
+                (text.content, text.link.map(|l| l.url), t.annotations.is_some_and(|a| a.bold))
+            })
+            .collect();
+        let url = Some("https://a.com".to_string());
+        assert_eq!(
+            pieces,
+            vec![
+                ("see ".to_string(), url.clone(), false),
+                ("this".to_string(), url.clone(), true),
+                (" section".to_string(), url, false),
+                (" after".to_string(), None, false),
+            ]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_include_directive() {
+        let dir = std::env::temp_dir().join(format!("notation-include-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("partials")).unwrap();
+        std::fs::write(dir.join("page.md"), "# Page\n\n<!-- include: ./partials/setup.md -->\n\nAfter").unwrap();
+        std::fs::write(dir.join("partials/setup.md"), "Run `make setup` first.").unwrap();
+        std::fs::write(dir.join("loop.md"), "<!-- include: ./partials/loop.md -->").unwrap();
+        std::fs::write(dir.join("partials/loop.md"), "<!--include: ../loop.md-->").unwrap();
+
+        let paths = HashMap::new();
+        let options = ConversionOptions::default();
+        let page = parse_file(&dir.join("page.md")).await.unwrap();
+        let conversion = page.to_notion("page", &paths, &options).unwrap();
+        assert_eq!(conversion.request.children.len(), 3);
+
+        let looped = parse_file(&dir.join("loop.md")).await.unwrap();
+        let e = looped.to_notion("page", &paths, &options).unwrap_err();
+        assert!(e.to_string().contains("include cycle detected"));
+
+        // Nothing out of the shipped directory gets in, by absolute path or by climbing out.
+        let docs = dir.join("partials");
+        std::fs::write(dir.join("secret.env"), "TOKEN=secret").unwrap();
+        std::fs::write(docs.join("absolute.md"), "<!-- include: /etc/passwd -->").unwrap();
+        std::fs::write(docs.join("climbing.md"), "<!-- include: ../secret.env -->").unwrap();
+        let options = ConversionOptions {
+            root: Some(plain_path(&docs.canonicalize().unwrap())),
+            ..Default::default()
+        };
+        for file in ["absolute.md", "climbing.md"] {
+            let page = parse_file(&docs.join(file)).await.unwrap();
+            let e = page.to_notion("page", &paths, &options).unwrap_err();
+            assert!(e.to_string().contains("it is out of"), "{}: {}", file, e);
+        }
+        let page = parse_file(&docs.join("climbing.md")).await.unwrap();
+        let e = page.to_notion("page", &paths, &ConversionOptions::default()).unwrap_err();
+        assert!(e.to_string().contains("it is out of"), "{}", e);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_title_from_heading() {
+        let parse = |md: &str| parse_as("my_doc_v2.md", md);
+        let options = ConversionOptions {
+            title_strategy: TitleStrategy::FirstHeading,
+            ..Default::default()
+        };
+        let none = RedactSettings::default();
+        let page = parse("# My `Doc`\n\nBody");
+        assert_eq!(page.title(TitleStrategy::FileStem, false, &none).unwrap(), "my_doc_v2");
+        assert_eq!(page.title(TitleStrategy::FileStem, true, &none).unwrap(), "My Doc V2");
+        assert_eq!(page.title(TitleStrategy::FirstHeading, true, &none).unwrap(), "My Doc");
+        let conversion = convert_page(&page, &options).unwrap();
+        assert!(!conversion.request.children.iter().any(|c| matches!(c.block_type(), BlockType::Heading1)));
+
+        let no_heading = parse("## Only a subheading");
+        assert_eq!(no_heading.title(TitleStrategy::FirstHeading, false, &none).unwrap(), "my_doc_v2");
+        assert_eq!(no_heading.title(TitleStrategy::FirstHeading, true, &none).unwrap(), "My Doc V2");
+        assert_eq!(pretty_title("getting-started_with  iOS"), "Getting Started With IOS");
+
+        let titled = parse("--title Given\n\n# Heading");
+        assert_eq!(titled.title(TitleStrategy::FirstHeading, true, &none).unwrap(), "Given");
+        let conversion = convert_page(&titled, &options).unwrap();
+        assert!(conversion.request.children.iter().any(|c| matches!(c.block_type(), BlockType::Heading1)));
+
+        // The title is taken after redaction, like the blocks: masked, and never from a section
+        // left out.
+        let redact = RedactSettings {
+            patterns: vec![r"sk_\w+".to_string()],
+            headings: vec!["Internal".to_string()],
+            ..Default::default()
+        };
+        let secret = parse("# Keys like sk_live\n\nBody");
+        assert_eq!(secret.title(TitleStrategy::FirstHeading, false, &redact).unwrap(), "Keys like [REDACTED]");
+        let private = parse("# Internal\n\nHidden\n\n# Public\n\nBody");
+        assert_eq!(private.title(TitleStrategy::FirstHeading, false, &redact).unwrap(), "Public");
+        let options = ConversionOptions { redact, ..options };
+        let conversion = convert_page(&private, &options).unwrap();
+        let text = serde_json::to_string(&conversion.request).unwrap();
+        assert!(!text.contains("Hidden") && !text.contains("Public"));
+        let marked = parse("# Launch plan\n<!-- private -->\n\nHidden\n\n## Notes\n\nBody");
+        assert_eq!(marked.title(TitleStrategy::FirstHeading, false, &none).unwrap(), "my_doc_v2");
+        let conversion = convert_page(&marked, &options).unwrap();
+        assert!(!serde_json::to_string(&conversion.request).unwrap().contains("Hidden"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_link_paths() {
+        assert_eq!(reconcile_path(Path::new("docs/./api/../guide%20one.md")).unwrap(), Path::new("docs/guide one.md"));
+        assert!(reconcile_path(&std::env::temp_dir().join("docs/../guide.md")).unwrap().is_absolute());
+        assert_eq!(plain_path(Path::new("./docs/")), Path::new("docs"));
+        assert_eq!(plain_path(Path::new("./")), Path::new("."));
+        let pattern = get_source_glob_pattern("docs/[draft]".to_string());
+        assert_eq!(pattern, Path::new("docs/[[]draft[]]").join("**").join("*").to_string_lossy());
+        assert_eq!(get_source_glob_pattern("./guide.md".to_string()), "guide.md");
+
+        let dir = std::env::temp_dir().join(format!("notation-link-paths-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("api")).unwrap();
+        std::fs::write(dir.join("page.md"), "# Page\n\n[Auth](.\\api\\auth.md)\n").unwrap();
+        std::fs::write(dir.join("api/auth.md"), "# Auth\n").unwrap();
+        let paths = HashMap::from([(page_key(&dir.join("api/auth.md"), SymlinkPolicy::Follow).unwrap(), "auth".to_string())]);
+        let conversion = parse_file(&dir.join("page.md"))
+            .await
+            .unwrap()
+            .to_notion("page", &paths, &ConversionOptions::default())
+            .unwrap();
+        let text = conversion.request.children[1].get_rich_text_blocks().unwrap();
+        assert!(text[0].text.as_ref().unwrap().link.as_ref().unwrap().url.ends_with("-auth"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_link_schemes() {
+        let markdown = "[mail](mailto:docs@example.com) [call](tel:+15551234) [empty]() [top](#) \
+            [pixel](data:image/png;base64,AAAA) [site](https://example.com)";
+        let page = parse(markdown);
+        let conversion = page.to_notion("abc", &HashMap::new(), &ConversionOptions::default()).unwrap();
+        let links: Vec<Option<String>> = conversion.request.children[0]
+            .get_rich_text_blocks()
+            .unwrap()
+            .iter()
+            .map(|t| t.text.as_ref().unwrap())
+            .filter(|t| t.content.trim() != "")
+            .map(|t| t.link.as_ref().map(|l| l.url.clone()))
+            .collect();
+        assert_eq!(
+            links,
+            vec![
+                Some("mailto:docs@example.com".to_string()),
+                Some("tel:+15551234".to_string()),
+                None,
+                Some("https://www.notion.so/abc".to_string()),
+                None,
+                Some("https://example.com".to_string()),
+            ]
+        );
+        let warnings: Vec<&str> = conversion.warnings.iter().map(|w| w.message.as_str()).collect();
+        assert_eq!(
+            warnings,
+            vec![
+                "a link without a URL ships as its text only",
+                "data: links can't be opened from Notion, shipping the link text only"
+            ]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_outside_links() {
+        let dir = std::env::temp_dir().join(format!("notation-outside-links-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("docs")).unwrap();
+        git2::Repository::init(&dir).unwrap();
+        std::fs::write(dir.join("docs/page.md"), "# Page\n\n[lib](../src/lib.rs#L3) and [other](../../other/readme.md)\n").unwrap();
+        let page = parse_file(&dir.join("docs/page.md")).await.unwrap();
+        let convert = |outside_links, link_base_url: Option<&str>| {
+            let options = ConversionOptions {
+                root: Some(plain_path(&dir.join("docs").canonicalize().unwrap())),
+                outside_links,
+                link_base_url: link_base_url.map(|u| u.to_string()),
+                ..Default::default()
+            };
+            convert_page(&page, &options)
+        };
+
+        let e = convert(OutsideLinkPolicy::Error, None).unwrap_err();
+        assert!(e.to_string().contains("../src/lib.rs#L3 is out of the shipped docs"));
+        let text = convert(OutsideLinkPolicy::Text, None).unwrap();
+        assert_eq!(text.warnings.len(), 2);
+        let blocks = text.request.children[1].get_rich_text_blocks().unwrap();
+        assert!(blocks.iter().all(|t| t.text.as_ref().unwrap().link.is_none()));
+
+        assert!(convert(OutsideLinkPolicy::Rewrite, None).is_err());
+        let rewritten = convert(OutsideLinkPolicy::Rewrite, Some("https://github.com/org/repo/blob/main")).unwrap();
+        let blocks = rewritten.request.children[1].get_rich_text_blocks().unwrap();
+        let url = &blocks[0].text.as_ref().unwrap().link.as_ref().unwrap().url;
+        assert_eq!(url, "https://github.com/org/repo/blob/main/src/lib.rs#L3");
+        assert!(rewritten.warnings[1].message.contains("is out of the repository"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_page_keys() {
+        use std::path::PathBuf;
+
+        use crate::settings::directory::SourceTree;
+        use crate::settings::notation::ContentSettings;
+
+        let dir = std::env::temp_dir().join(format!("notation-page-keys-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("real")).unwrap();
+        std::fs::write(dir.join("real/guide.md"), "# Guide\n").unwrap();
+        std::os::unix::fs::symlink(dir.join("real"), dir.join("alias")).unwrap();
+
+        let key = |path: &str, symlinks| page_key(&dir.join(path), symlinks).unwrap();
+        assert_eq!(key("alias/guide.md", SymlinkPolicy::Follow), key("real/../real/guide.md", SymlinkPolicy::Follow));
+        assert_ne!(key("alias/guide.md", SymlinkPolicy::Skip), key("real/guide.md", SymlinkPolicy::Skip));
+        assert_eq!(key("missing.md", SymlinkPolicy::Follow), reconcile_path(&dir.join("missing.md")).unwrap());
+        let canonical = dir.canonicalize().unwrap();
+        assert_eq!(on_disk_case(&canonical.join("REAL/Guide.md")), canonical.join("real/guide.md"));
+
+        let files = |symlinks| {
+            let settings = ContentSettings { symlinks, ..Default::default() };
+            SourceTree::load(&dir.display().to_string(), settings).unwrap().files
+        };
+        assert_eq!(files(SymlinkPolicy::Follow).len(), 2);
+        assert_eq!(files(SymlinkPolicy::Skip), vec![PathBuf::from("real/guide.md")]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(windows)]
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_windows_link_paths() {
+        assert_eq!(plain_path(Path::new(r"\\?\C:\docs\api")), Path::new(r"C:\docs\api"));
+        assert_eq!(plain_path(Path::new(r"\\?\UNC\server\share\docs")), Path::new(r"\\server\share\docs"));
+        assert_eq!(
+            reconcile_path(Path::new(r"\\?\c:\docs\guide.md")).unwrap(),
+            reconcile_path(Path::new(r"C:\docs\api\..\guide.md")).unwrap()
+        );
+        assert_eq!(reconcile_path(Path::new(r"C:\docs\guide.md")).unwrap(), Path::new(r"C:\docs\guide.md"));
+        let pattern = get_source_glob_pattern(r"\\?\C:\docs\".to_string());
+        assert_eq!(pattern, r"C:\docs\**\*");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_slug() {
+        assert_eq!(parse("--slug getting-started\n\n# Page").slug().unwrap().as_deref(), Some("getting-started"));
+        let frontmatter = parse("---\nslug: \"auth\"\nowner: docs\n---\n\n# Page\n\nBody");
+        assert_eq!(frontmatter.slug().unwrap().as_deref(), Some("auth"));
+        let conversion = convert_page(&frontmatter, &ConversionOptions::default()).unwrap();
+        assert_eq!(conversion.request.children.len(), 2);
+        assert!(parse("# Page").slug().unwrap().is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_heading_links() {
+        let dir = std::env::temp_dir().join(format!("notation-anchors-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("page.md"),
+            "# Page\n\nSee [usage](#usage), [nothing](#missing) and [install](./other.md#install-1).\n\n## Usage\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("other.md"), "# Other\n\n## Install\n\n## Install\n").unwrap();
+
+        let other_id = "0123abcd-0123-abcd-0123-0123456789ab";
+        let paths = HashMap::from([(page_key(&dir.join("other.md"), SymlinkPolicy::Follow).unwrap(), other_id.to_string())]);
+        let page = parse_file(&dir.join("page.md")).await.unwrap();
+        let conversion = page
+            .to_notion("fedcba98-7654-3210-fedc-ba9876543210", &paths, &ConversionOptions::default())
+            .unwrap();
+        let links: Vec<String> = conversion.request.children[1]
+            .get_rich_text_blocks()
+            .unwrap()
+            .into_iter()
+            .filter_map(|t| t.text.and_then(|t| t.link))
+            .map(|l| l.url)
+            .collect();
+        assert_eq!(
+            links,
+            vec![
+                "https://www.notion.so/fedcba9876543210fedcba9876543210#usage",
+                "https://www.notion.so/fedcba98-7654-3210-fedc-ba9876543210",
+                "https://www.notion.so/0123abcd0123abcd01230123456789ab#install-1",
+            ]
+        );
+        assert_eq!(conversion.warnings.len(), 1);
+        assert!(conversion.warnings[0].message.contains("no heading #missing in"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     pub async fn test_trim() {
         let arg_string = "\n\n\n";
         assert!(arg_string.trim().is_empty());
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_map_tree() {
+        let markdown = "# Guide\n\nPublic\n\n## Internal\n\nSecret\n\n## Usage\n\nRun it";
+        let page = parse_as("guide.md", markdown);
+        assert_eq!(page.path(), "guide.md");
+        assert_eq!(page.file_name(), "guide");
+        let page = page.map_tree(|mut root| {
+            if let Some(children) = root.children_mut() {
+                let mut is_internal = false;
+                children.retain(|n| {
+                    if let Node::Heading(h) = n {
+                        if h.depth == 2 {
+                            is_internal = n.to_string() == "Internal";
+                        }
+                    }
+                    !is_internal
+                });
+            }
+            root
+        });
+        assert_eq!(page.node().children().unwrap().len(), 4);
+        let conversion = convert_page(&page, &ConversionOptions::default()).unwrap();
+        let text: Vec<String> = conversion
+            .request
+            .children
+            .iter()
+            .filter_map(|c| c.get_rich_text_blocks())
+            .map(|r| r.iter().filter_map(|t| t.text.as_ref()).map(|t| t.content.clone()).collect())
+            .collect();
+        assert_eq!(text, vec!["Guide", "Public", "Usage", "Run it"]);
+    }
 }
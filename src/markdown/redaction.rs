@@ -0,0 +1,222 @@
+use std::fmt::{Display, Formatter};
+
+use anyhow::{anyhow, Result};
+use markdown::mdast::Node;
+use regex::{NoExpand, Regex};
+use serde::{Deserialize, Serialize};
+
+use crate::settings::notation::RedactSettings;
+
+/// Something taken out of a page before it was shipped. It never holds the text itself.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Redaction {
+    pub file: String,
+    pub line: Option<usize>,
+    /// Why it was taken out: `pattern <regex>`, `heading "<name>"` or `<!-- private -->`.
+    pub rule: String,
+}
+
+impl Display for Redaction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "(page={}:{}) {}", self.file, line, self.rule),
+            None => write!(f, "(page={}) {}", self.file, self.rule),
+        }
+    }
+}
+
+/// Takes the sensitive parts out of a syntax tree, following `[redact]`.
+#[derive(Clone, Debug)]
+pub struct Redactor {
+    patterns: Vec<(String, Regex)>,
+    mask: String,
+    headings: Vec<String>,
+}
+
+const PRIVATE_RULE: &str = "<!-- private -->";
+
+impl Redactor {
+    pub fn new(settings: &RedactSettings) -> Result<Self> {
+        let patterns = settings
+            .patterns
+            .iter()
+            .map(|p| {
+                Regex::new(p)
+                    .map(|r| (p.clone(), r))
+                    .map_err(|e| anyhow!("invalid [redact] pattern {}: {}", p, e))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Redactor {
+            patterns,
+            mask: settings.mask.clone(),
+            headings: settings.headings.clone(),
+        })
+    }
+
+    /// Leaves out of `node`, the tree of `file`, the sections under the redacted headings
+    /// and the ones marked private, then masks the text matching the patterns, in code and
+    /// URLs too.
+    ///
+    /// A `<!-- private -->` right under a heading takes its whole section out, anywhere else
+    /// it takes out what follows up to a `<!-- /private -->` or the next heading.
+    pub fn redact(&self, file: &str, node: &mut Node) -> Vec<Redaction> {
+        let mut redactions = Vec::new();
+        self.redact_node(node, &mut redactions);
+        redactions
+            .into_iter()
+            .map(|(line, rule)| Redaction {
+                file: file.to_string(),
+                line,
+                rule,
+            })
+            .collect()
+    }
+
+    fn redact_node(&self, node: &mut Node, redactions: &mut Vec<(Option<usize>, String)>) {
+        let line = node.position().map(|p| p.start.line);
+        match node {
+            Node::Text(t) => self.mask(&mut t.value, line, redactions),
+            Node::InlineCode(c) => self.mask(&mut c.value, line, redactions),
+            Node::Code(c) => self.mask(&mut c.value, line, redactions),
+            Node::InlineMath(m) => self.mask(&mut m.value, line, redactions),
+            Node::Math(m) => self.mask(&mut m.value, line, redactions),
+            Node::Html(h) => self.mask(&mut h.value, line, redactions),
+            Node::Link(l) => self.mask(&mut l.url, line, redactions),
+            Node::Image(i) => {
+                self.mask(&mut i.url, line, redactions);
+                self.mask(&mut i.alt, line, redactions);
+            }
+            _ => {}
+        }
+        if let Some(children) = node.children_mut() {
+            self.remove_sections(children, redactions);
+            for child in children.iter_mut() {
+                self.redact_node(child, redactions);
+            }
+        }
+    }
+
+    fn remove_sections(&self, nodes: &mut Vec<Node>, redactions: &mut Vec<(Option<usize>, String)>) {
+        let mut i = 0;
+        while i < nodes.len() {
+            let removed = match &nodes[i] {
+                Node::Heading(h) => {
+                    let text = nodes[i].to_string();
+                    let named = self.headings.iter().find(|n| n.trim().eq_ignore_ascii_case(text.trim()));
+                    match named {
+                        Some(name) => Some((section_end(nodes, i, h.depth), format!("heading \"{}\"", name))),
+                        None if nodes.get(i + 1).is_some_and(|n| is_marker(n, "private")) => {
+                            Some((section_end(nodes, i, h.depth), PRIVATE_RULE.to_string()))
+                        }
+                        None => None,
+                    }
+                }
+                n if is_marker(n, "private") => Some((private_end(nodes, i), PRIVATE_RULE.to_string())),
+                _ => None,
+            };
+            match removed {
+                Some((end, rule)) => {
+                    redactions.push((nodes[i].position().map(|p| p.start.line), rule));
+                    nodes.drain(i..end);
+                }
+                None => i += 1,
+            }
+        }
+    }
+
+    fn mask(&self, value: &mut String, line: Option<usize>, redactions: &mut Vec<(Option<usize>, String)>) {
+        for (pattern, regex) in self.patterns.iter() {
+            let count = regex.find_iter(value).count();
+            if count > 0 {
+                *value = regex.replace_all(value, NoExpand(&self.mask)).into_owned();
+                redactions.extend((0..count).map(|_| (line, format!("pattern {}", pattern))));
+            }
+        }
+    }
+}
+
+/// Whether `node` is the HTML comment `<!-- marker -->`.
+fn is_marker(node: &Node, marker: &str) -> bool {
+    match node {
+        Node::Html(h) => h
+            .value
+            .trim()
+            .strip_prefix("<!--")
+            .and_then(|v| v.strip_suffix("-->"))
+            .is_some_and(|v| v.trim().eq_ignore_ascii_case(marker)),
+        _ => false,
+    }
+}
+
+/// The index past the section of the heading at `start`, of `depth`.
+fn section_end(nodes: &[Node], start: usize, depth: u8) -> usize {
+    nodes
+        .iter()
+        .skip(start + 1)
+        .position(|n| matches!(n, Node::Heading(h) if h.depth <= depth))
+        .map_or(nodes.len(), |p| start + 1 + p)
+}
+
+/// The index past the private block opened at `start`: past its `<!-- /private -->`, or up
+/// to the next heading.
+fn private_end(nodes: &[Node], start: usize) -> usize {
+    for (i, node) in nodes.iter().enumerate().skip(start + 1) {
+        if is_marker(node, "/private") {
+            return i + 1;
+        }
+        if matches!(node, Node::Heading(_)) {
+            return i;
+        }
+    }
+    nodes.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::markdown::redaction::Redactor;
+    use crate::markdown::source::{MarkdownParser, SourceParser};
+    use crate::settings::notation::RedactSettings;
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_redact_tree() {
+        let settings = RedactSettings {
+            patterns: vec![r"sk_live_\w+".to_string(), r"[a-z]+\.corp\.internal".to_string()],
+            headings: vec!["internal notes".to_string()],
+            ..Default::default()
+        };
+        let redactor = Redactor::new(&settings).unwrap();
+        let markdown = "# Guide\n\nUse sk_live_abc123 on [the API](https://api.corp.internal/v1).\n\n\
+            ```sh\ncurl db.corp.internal\n```\n\n\
+            <!-- private -->\n\nOn call: Alice\n\n<!-- /private -->\n\nPublic\n\n\
+            ## Deploy\n\n<!-- private -->\n\nSteps\n\n### Rollback\n\nMore steps\n\n\
+            ## Internal Notes\n\nGossip\n\n## Usage\n\nRun it";
+        let mut root = MarkdownParser.parse(markdown).unwrap();
+        let redactions = redactor.redact("guide.md", &mut root);
+        let kept: Vec<String> = root.children().unwrap().iter().map(|n| n.to_string()).collect();
+        assert_eq!(
+            kept,
+            vec!["Guide", "Use [REDACTED] on the API.", "curl [REDACTED]", "Public", "Usage", "Run it"]
+        );
+        let paragraph = format!("{:?}", root.children().unwrap()[1]);
+        assert!(paragraph.contains("https://[REDACTED]/v1"));
+        assert!(!paragraph.contains("corp.internal"));
+
+        let rules: Vec<String> = redactions.iter().map(|r| r.to_string()).collect();
+        assert_eq!(
+            rules,
+            vec![
+                "(page=guide.md:9) <!-- private -->",
+                "(page=guide.md:17) <!-- private -->",
+                "(page=guide.md:27) heading \"internal notes\"",
+                "(page=guide.md:3) pattern sk_live_\\w+",
+                "(page=guide.md:3) pattern [a-z]+\\.corp\\.internal",
+                "(page=guide.md:5) pattern [a-z]+\\.corp\\.internal",
+            ]
+        );
+        assert!(Redactor::new(&RedactSettings {
+            patterns: vec!["(".to_string()],
+            ..Default::default()
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,428 @@
+use std::sync::OnceLock;
+
+use anyhow::Result;
+use markdown::mdast::{BlockQuote, Emphasis, InlineCode, Link, Node, Strong, ThematicBreak};
+use regex::Regex;
+
+use crate::markdown::source::{
+    code, heading, image, line_position, list, paragraph, root, text, unescape, SourceParser,
+};
+
+static INLINE: OnceLock<Regex> = OnceLock::new();
+static LIST_ITEM: OnceLock<Regex> = OnceLock::new();
+static DIRECTIVE: OnceLock<Regex> = OnceLock::new();
+
+const ADMONITIONS: [&str; 10] = [
+    "admonition", "attention", "caution", "danger", "error", "hint", "important", "note", "tip",
+    "warning",
+];
+
+/// Reads reStructuredText: section titles, paragraphs, bullet and enumerated lists, literal
+/// blocks, `code-block`, `image` and admonition directives, block quotes, transitions and the
+/// common inline markup. Other directives and comments are dropped.
+pub struct RstParser;
+
+impl SourceParser for RstParser {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["rst", "rest"]
+    }
+
+    fn parse(&self, contents: &str) -> Result<Node> {
+        let lines: Vec<&str> = contents.lines().collect();
+        let mut styles = Vec::new();
+        Ok(root(parse_blocks(&lines, 1, &mut styles)))
+    }
+}
+
+/// The blocks being built, flushed whenever a blank line or another construct ends them.
+#[derive(Default)]
+struct Pending<'a> {
+    paragraph_lines: Vec<&'a str>,
+    paragraph_start: usize,
+    items: Vec<Vec<Node>>,
+    item_lines: Vec<&'a str>,
+    ordered: bool,
+    list_start: usize,
+    /// Set when the last paragraph ended with `::`, making the next indented block literal.
+    literal_next: bool,
+}
+
+impl Pending<'_> {
+    fn flush_item(&mut self) {
+        if !self.item_lines.is_empty() {
+            self.items.push(inline(&self.item_lines.join("\n"), self.list_start));
+            self.item_lines.clear();
+        }
+    }
+
+    fn flush(&mut self, blocks: &mut Vec<Node>) {
+        self.flush_item();
+        if !self.items.is_empty() {
+            blocks.push(list(std::mem::take(&mut self.items), self.ordered, self.list_start));
+        }
+        if !self.paragraph_lines.is_empty() {
+            let mut value = self.paragraph_lines.join("\n");
+            self.paragraph_lines.clear();
+            if let Some(stripped) = value.strip_suffix("::") {
+                self.literal_next = true;
+                value = if stripped.ends_with(char::is_whitespace) || stripped.is_empty() {
+                    stripped.trim_end().to_string()
+                } else {
+                    format!("{}:", stripped)
+                };
+            }
+            if !value.is_empty() {
+                blocks.push(paragraph(inline(&value, self.paragraph_start), self.paragraph_start));
+            }
+        }
+    }
+
+    fn is_open(&self) -> bool {
+        !self.paragraph_lines.is_empty() || !self.items.is_empty() || !self.item_lines.is_empty()
+    }
+}
+
+/// Parses `lines`, the first of which is line `first_line` of the file. `styles` holds the
+/// section title adornments in the order they were first seen, which sets their depth.
+fn parse_blocks(lines: &[&str], first_line: usize, styles: &mut Vec<(char, bool)>) -> Vec<Node> {
+    let list_item = LIST_ITEM.get_or_init(|| Regex::new(r"^([*+-]|\d+[.)]|#[.)])\s+(.*)$").unwrap());
+    let directive = DIRECTIVE.get_or_init(|| Regex::new(r"^\.\.\s+([\w-]+)::\s*(.*)$").unwrap());
+    let mut blocks = Vec::new();
+    let mut pending = Pending::default();
+
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i].trim_end();
+        let line_number = first_line + i;
+        let next = lines.get(i + 1).map(|l| l.trim_end());
+
+        if line.is_empty() {
+            pending.flush(&mut blocks);
+            i += 1;
+            continue;
+        }
+
+        let is_indented = line.starts_with(char::is_whitespace);
+        if is_indented && !pending.item_lines.is_empty() {
+            pending.item_lines.push(line.trim());
+            i += 1;
+            continue;
+        }
+        if is_indented && !pending.paragraph_lines.is_empty() {
+            pending.paragraph_lines.push(line.trim());
+            i += 1;
+            continue;
+        }
+        if is_indented {
+            pending.flush(&mut blocks);
+            let end = indented_block_end(lines, i);
+            let body = dedent(&lines[i..end]);
+            if std::mem::take(&mut pending.literal_next) {
+                blocks.push(code(body.join("\n"), None, None, line_number));
+            } else {
+                blocks.push(Node::BlockQuote(BlockQuote {
+                    children: parse_blocks(&body, line_number, styles),
+                    position: line_position(line_number),
+                }));
+            }
+            i = end;
+            continue;
+        }
+        pending.literal_next = false;
+
+        // A title with an overline, then one with only an underline.
+        if let (Some(adornment), Some(title), Some(under)) =
+            (adornment(line), next, lines.get(i + 2).and_then(|l| adornment(l.trim_end())))
+        {
+            if !pending.is_open() && adornment == under && !title.trim().is_empty() {
+                pending.flush(&mut blocks);
+                blocks.push(heading(&unescape(title.trim()), depth(styles, (adornment, true)), line_number + 1));
+                i += 3;
+                continue;
+            }
+        }
+        if let Some(under) = next.and_then(adornment) {
+            let is_long_enough = next.is_some_and(|n| n.chars().count() >= line.trim().chars().count());
+            if pending.paragraph_lines.is_empty() && is_long_enough {
+                pending.flush(&mut blocks);
+                blocks.push(heading(&unescape(line.trim()), depth(styles, (under, false)), line_number));
+                i += 2;
+                continue;
+            }
+        }
+        if adornment(line).is_some() && line.len() >= 4 && !pending.is_open() {
+            blocks.push(Node::ThematicBreak(ThematicBreak {
+                position: line_position(line_number),
+            }));
+            i += 1;
+            continue;
+        }
+
+        if let Some(c) = directive.captures(line) {
+            pending.flush(&mut blocks);
+            let name = c.get(1).map_or("", |m| m.as_str()).to_lowercase();
+            let argument = c.get(2).map_or("", |m| m.as_str()).trim();
+            let end = indented_block_end(lines, i + 1);
+            let body = dedent(&lines[i + 1..end]);
+            let (options, content) = split_options(&body);
+            match name.as_str() {
+                "code-block" | "code" | "sourcecode" => {
+                    let lang = Some(argument.to_string()).filter(|l| !l.is_empty());
+                    let caption = options
+                        .iter()
+                        .find_map(|o| o.strip_prefix(":caption:"))
+                        .map(|c| format!("title=\"{}\"", c.trim().replace('"', "'")));
+                    blocks.push(code(content.join("\n"), lang, caption, line_number));
+                }
+                "image" | "figure" => {
+                    let alt = options
+                        .iter()
+                        .find_map(|o| o.strip_prefix(":alt:"))
+                        .unwrap_or("")
+                        .trim();
+                    blocks.push(image(argument, alt, line_number));
+                }
+                n if ADMONITIONS.contains(&n) => {
+                    let mut children = Vec::new();
+                    if !argument.is_empty() {
+                        children.push(paragraph(inline(argument, line_number), line_number));
+                    }
+                    children.extend(parse_blocks(&content, line_number + 1, styles));
+                    blocks.push(Node::BlockQuote(BlockQuote {
+                        children,
+                        position: line_position(line_number),
+                    }));
+                }
+                _ => {}
+            }
+            i = end;
+            continue;
+        }
+        if line.starts_with("..") {
+            // A comment, a link target or a substitution definition.
+            pending.flush(&mut blocks);
+            i = indented_block_end(lines, i + 1);
+            continue;
+        }
+
+        if let Some(c) = list_item.captures(line) {
+            if !pending.paragraph_lines.is_empty() {
+                pending.flush(&mut blocks);
+            }
+            pending.flush_item();
+            if pending.items.is_empty() {
+                let marker = c.get(1).map_or("", |m| m.as_str());
+                pending.ordered = !matches!(marker, "*" | "+" | "-");
+                pending.list_start = line_number;
+            }
+            pending.item_lines.push(c.get(2).map_or("", |m| m.as_str()));
+            i += 1;
+            continue;
+        }
+
+        if !pending.items.is_empty() || !pending.item_lines.is_empty() {
+            pending.flush(&mut blocks);
+        }
+        if pending.paragraph_lines.is_empty() {
+            pending.paragraph_start = line_number;
+        }
+        pending.paragraph_lines.push(line);
+        i += 1;
+    }
+    pending.flush(&mut blocks);
+    blocks
+}
+
+/// The character of a line made of one repeated punctuation character, like `=====`.
+fn adornment(line: &str) -> Option<char> {
+    let first = line.chars().next()?;
+    if line.len() < 2 || !first.is_ascii_punctuation() || line.chars().any(|c| c != first) {
+        return None;
+    }
+    Some(first)
+}
+
+fn depth(styles: &mut Vec<(char, bool)>, style: (char, bool)) -> u8 {
+    let index = match styles.iter().position(|s| *s == style) {
+        Some(index) => index,
+        None => {
+            styles.push(style);
+            styles.len() - 1
+        }
+    };
+    (index + 1).min(6) as u8
+}
+
+/// The index of the first line after the indented block starting at `start`, trailing blank
+/// lines left out.
+fn indented_block_end(lines: &[&str], start: usize) -> usize {
+    let mut end = start;
+    for (i, l) in lines.iter().enumerate().skip(start) {
+        if l.trim().is_empty() {
+            continue;
+        }
+        if !l.starts_with(char::is_whitespace) {
+            break;
+        }
+        end = i + 1;
+    }
+    end
+}
+
+fn dedent<'a>(lines: &[&'a str]) -> Vec<&'a str> {
+    let indent = lines
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .map(|l| if l.len() >= indent { &l[indent..] } else { l.trim() })
+        .collect()
+}
+
+/// Splits a directive body into its leading `:option: value` lines and its content.
+fn split_options<'a>(body: &[&'a str]) -> (Vec<&'a str>, Vec<&'a str>) {
+    let options_end = body
+        .iter()
+        .position(|l| !l.starts_with(':'))
+        .unwrap_or(body.len());
+    let content = body[options_end..]
+        .iter()
+        .skip_while(|l| l.trim().is_empty())
+        .copied()
+        .collect();
+    (body[..options_end].to_vec(), content)
+}
+
+/// Splits a paragraph into text, `**strong**`, `*emphasis*`, ``` ``code`` ```, links written as
+/// `` `label <url>`_ `` or bare URLs, and `:doc:` references to other files.
+fn inline(value: &str, line: usize) -> Vec<Node> {
+    let pattern = INLINE.get_or_init(|| {
+        Regex::new(concat!(
+            r"\*\*(?P<strong>[^*\n]+?)\*\*",
+            r"|\*(?P<em>[^*\n]+?)\*",
+            r"|``(?P<code>[^`\n]+?)``",
+            r"|:doc:`(?:(?P<doc_label>[^`<]*?)\s*<(?P<doc_target>[^>`]+)>|(?P<doc>[^`]+))`",
+            r"|`(?P<label>[^`<]*?)\s*<(?P<url>[^>`]+)>`__?",
+            r"|(?P<bare>https?://[^\s<>`]*[^\s<>`.,;:!?)])",
+        ))
+        .unwrap()
+    });
+    let mut nodes = Vec::new();
+    let mut last = 0;
+    for c in pattern.captures_iter(value) {
+        let m = c.get(0).unwrap();
+        // Markup escaped with a backslash, like `\*`, stays text.
+        if value[..m.start()].ends_with('\\') {
+            continue;
+        }
+        if m.start() > last {
+            nodes.push(text(&unescape(&value[last..m.start()]), line));
+        }
+        last = m.end();
+        if let Some(s) = c.name("strong") {
+            nodes.push(Node::Strong(Strong {
+                children: vec![text(&unescape(s.as_str()), line)],
+                position: line_position(line),
+            }));
+        } else if let Some(e) = c.name("em") {
+            nodes.push(Node::Emphasis(Emphasis {
+                children: vec![text(&unescape(e.as_str()), line)],
+                position: line_position(line),
+            }));
+        } else if let Some(code) = c.name("code") {
+            nodes.push(Node::InlineCode(InlineCode {
+                value: code.as_str().to_string(),
+                position: line_position(line),
+            }));
+        } else if let Some(doc) = c.name("doc_target").or(c.name("doc")) {
+            let label = c.name("doc_label").map_or(doc.as_str(), |l| l.as_str());
+            nodes.push(link(&doc_url(doc.as_str().trim()), label, line));
+        } else if let Some(url) = c.name("url") {
+            let label = c.name("label").map_or("", |l| l.as_str());
+            nodes.push(link(url.as_str(), label, line));
+        } else if let Some(url) = c.name("bare") {
+            nodes.push(link(url.as_str(), "", line));
+        }
+    }
+    if last < value.len() {
+        nodes.push(text(&unescape(&value[last..]), line));
+    }
+    nodes
+}
+
+/// The relative link to the file a `:doc:` role points at, which leaves out its extension.
+fn doc_url(doc: &str) -> String {
+    let doc = if doc.ends_with(".rst") { doc.to_string() } else { format!("{}.rst", doc) };
+    if doc.starts_with('.') {
+        doc
+    } else {
+        format!("./{}", doc.trim_start_matches('/'))
+    }
+}
+
+fn link(url: &str, label: &str, line: usize) -> Node {
+    let label = if label.is_empty() { url } else { label };
+    Node::Link(Link {
+        children: vec![text(&unescape(label), line)],
+        position: line_position(line),
+        url: url.to_string(),
+        title: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use markdown::mdast::Node;
+
+    use crate::markdown::rst::RstParser;
+    use crate::markdown::source::SourceParser;
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_parse_rst() {
+        let doc = "=====\nGuide\n=====\n\nSome **bold** and ``code``, see `the site <https://example.com>`_.\n\nSetup\n-----\n\n* one\n* two\n\nRun this::\n\n    make setup\n\n.. code-block:: rust\n   :caption: main.rs\n\n   fn main() {}\n\n.. note:: Read the docs.\n\nDone\n~~~~\n";
+        let root = RstParser.parse(doc).unwrap();
+        let children = root.children().unwrap();
+        let kinds: Vec<String> = children
+            .iter()
+            .map(|c| match c {
+                Node::Heading(h) => format!("h{}", h.depth),
+                Node::Paragraph(_) => "paragraph".to_string(),
+                Node::List(_) => "list".to_string(),
+                Node::Code(_) => "code".to_string(),
+                Node::BlockQuote(_) => "quote".to_string(),
+                _ => "other".to_string(),
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec!["h1", "paragraph", "h2", "list", "paragraph", "code", "code", "quote", "h3"]
+        );
+        match (&children[4], &children[5], &children[6]) {
+            (Node::Paragraph(p), Node::Code(literal), Node::Code(c)) => {
+                assert!(matches!(&p.children[0], Node::Text(t) if t.value == "Run this:"));
+                assert_eq!(literal.value, "make setup");
+                assert_eq!(c.lang.as_deref(), Some("rust"));
+                assert_eq!(c.meta.as_deref(), Some("title=\"main.rs\""));
+                assert_eq!(c.value, "fn main() {}");
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_rst_escapes() {
+        let root = RstParser.parse("\\*not emphasis\\* and *emphasis*, AT&amp;T\n").unwrap();
+        match &root.children().unwrap()[0] {
+            Node::Paragraph(p) => {
+                assert!(matches!(&p.children[0], Node::Text(t) if t.value == "*not emphasis* and "));
+                assert!(matches!(&p.children[1], Node::Emphasis(e) if e.children[0].to_string() == "emphasis"));
+                // reStructuredText has no entities, they read as they are written.
+                assert!(matches!(&p.children[2], Node::Text(t) if t.value == ", AT&amp;T"));
+            }
+            _ => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,684 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+/// Words of a code fence in Rust docs that still make it Rust, like ```` ```no_run ````.
+const RUST_FENCE_ATTRIBUTES: [&str; 9] = [
+    "rust",
+    "ignore",
+    "no_run",
+    "should_panic",
+    "compile_fail",
+    "edition2015",
+    "edition2018",
+    "edition2021",
+    "edition2024",
+];
+
+/// A markdown file of an API reference, relative to the directory it is written to.
+#[derive(Clone, Debug)]
+pub struct ApiPage {
+    pub path: PathBuf,
+    pub markdown: String,
+}
+
+/// An API reference as a tree of markdown files. Read from the JSON of `cargo doc
+/// --output-format json`, it has a directory per module, and a page per struct, enum, trait and
+/// function of it. Only public items of the crate itself are documented.
+#[derive(Clone, Debug)]
+pub struct ApiReference {
+    /// The directory holding the pages, named after the crate or API.
+    pub name: String,
+    pub pages: Vec<ApiPage>,
+}
+
+impl ApiReference {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("failed to read rustdoc JSON {}: {}", path.display(), e))?;
+        let json: Value = serde_json::from_str(&contents)
+            .map_err(|e| anyhow!("failed to read rustdoc JSON {}: {}", path.display(), e))?;
+        ApiReference::from_json(&json)
+    }
+
+    pub fn from_json(json: &Value) -> Result<Self> {
+        let krate = Crate { json };
+        let root = krate
+            .item(&json["root"])
+            .ok_or_else(|| anyhow!("the rustdoc JSON has no root module, was it written by cargo doc?"))?;
+        let crate_name = root["name"]
+            .as_str()
+            .ok_or_else(|| anyhow!("the root module of the rustdoc JSON has no name"))?
+            .to_string();
+        let mut pages = Vec::new();
+        krate.module_pages(root, PathBuf::from(&crate_name), &crate_name, &mut pages);
+        Ok(ApiReference {
+            name: crate_name,
+            pages,
+        })
+    }
+
+    /// Writes the pages under `out`, in the directory of [`ApiReference::name`] that is emptied
+    /// first, so items gone from the API don't linger. Returns where the pages were written.
+    pub fn write(&self, out: &Path) -> Result<PathBuf> {
+        let dir = out.join(&self.name);
+        if dir.is_dir() {
+            std::fs::remove_dir_all(&dir)?;
+        }
+        for page in self.pages.iter() {
+            let path = out.join(&page.path);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(path, &page.markdown)?;
+        }
+        Ok(dir)
+    }
+}
+
+/// Items of the rustdoc JSON, by id.
+struct Crate<'a> {
+    json: &'a Value,
+}
+
+impl<'a> Crate<'a> {
+    fn item(&self, id: &Value) -> Option<&'a Value> {
+        let id = match id {
+            Value::String(s) => s.clone(),
+            Value::Number(n) => n.to_string(),
+            _ => return None,
+        };
+        self.json["index"].get(&id)
+    }
+
+    /// The public items `ids` point to, leaving out those of other crates and re-exports.
+    fn public_items(&self, ids: &Value) -> Vec<&'a Value> {
+        let crate_id = self.item(&self.json["root"]).map_or(Value::from(0), |r| r["crate_id"].clone());
+        ids.as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.item(id))
+            .filter(|i| i["crate_id"] == crate_id && i["visibility"] == "public")
+            .collect()
+    }
+
+    fn module_pages(&self, module: &Value, dir: PathBuf, path: &str, pages: &mut Vec<ApiPage>) {
+        let mut intro = frontmatter(&format!("mod {}", path), "📦");
+        intro.push_str(&docs(module));
+        let items = self.public_items(&module["inner"]["module"]["items"]);
+        let mut consts = String::new();
+        for item in items.iter() {
+            let name = item["name"].as_str().unwrap_or_default();
+            let child_path = format!("{}::{}", path, name);
+            let (kind, inner) = kind(item);
+            match kind {
+                "module" => self.module_pages(item, dir.join(name), &child_path, pages),
+                "struct" | "enum" | "trait" | "function" => pages.push(ApiPage {
+                    path: dir.join(format!("{}.{}.md", keyword(kind), name)),
+                    markdown: self.item_page(item, kind, inner, &child_path),
+                }),
+                "constant" | "static" | "type_alias" | "typedef" => {
+                    consts.push_str(&format!("\n## {}\n\n", name));
+                    consts.push_str(&code(&self.signature(item, kind, inner)));
+                    consts.push_str(&docs(item));
+                }
+                _ => {}
+            }
+        }
+        if !consts.is_empty() {
+            intro.push_str("\n# Constants and types\n");
+            intro.push_str(&consts);
+        }
+        pages.push(ApiPage {
+            path: dir.join("intro.md"),
+            markdown: intro,
+        });
+    }
+
+    fn item_page(&self, item: &Value, kind: &str, inner: &Value, path: &str) -> String {
+        let emoji = match kind {
+            "struct" => "🧱",
+            "enum" => "🔢",
+            "trait" => "🧩",
+            _ => "⚙️",
+        };
+        let mut page = frontmatter(&format!("{} {}", keyword(kind), path), emoji);
+        page.push_str(&code(&self.signature(item, kind, inner)));
+        page.push_str(&docs(item));
+        match kind {
+            "struct" => {
+                let fields = self.public_items(&inner["kind"]["plain"]["fields"]);
+                self.members(&mut page, "Fields", &fields);
+                self.impls(&mut page, &inner["impls"]);
+            }
+            "enum" => {
+                let variants = self.all_items(&inner["variants"]);
+                self.members(&mut page, "Variants", &variants);
+                self.impls(&mut page, &inner["impls"]);
+            }
+            "trait" => {
+                let methods = self.all_items(&inner["items"]);
+                self.members(&mut page, "Methods", &methods);
+            }
+            _ => {}
+        }
+        page
+    }
+
+    /// Items that carry no visibility of their own, like enum variants and trait methods.
+    fn all_items(&self, ids: &Value) -> Vec<&'a Value> {
+        ids.as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.item(id))
+            .collect()
+    }
+
+    /// A section of `title`, with the signature and docs of each of `items`.
+    fn members(&self, page: &mut String, title: &str, items: &[&Value]) {
+        if items.is_empty() {
+            return;
+        }
+        page.push_str(&format!("\n# {}\n", title));
+        for item in items {
+            let (kind, inner) = kind(item);
+            page.push_str(&format!("\n## {}\n\n", item["name"].as_str().unwrap_or_default()));
+            page.push_str(&code(&self.signature(item, kind, inner)));
+            page.push_str(&docs(item));
+        }
+    }
+
+    /// The methods of the inherent impls `ids`, then the traits of the others.
+    fn impls(&self, page: &mut String, ids: &Value) {
+        let impls: Vec<&Value> = self
+            .all_items(ids)
+            .into_iter()
+            .map(|i| &i["inner"]["impl"])
+            .filter(|i| !i["is_synthetic"].as_bool().unwrap_or(false) && !i["synthetic"].as_bool().unwrap_or(false))
+            .filter(|i| i["blanket_impl"].is_null())
+            .collect();
+        let methods: Vec<&Value> = impls
+            .iter()
+            .filter(|i| i["trait"].is_null())
+            .flat_map(|i| self.public_items(&i["items"]))
+            .filter(|m| kind(m).0 == "function")
+            .collect();
+        self.members(page, "Methods", &methods);
+        let traits: Vec<String> = impls
+            .iter()
+            .filter(|i| !i["trait"].is_null())
+            .map(|i| format!("- `{}`\n", impl_header(i)))
+            .collect();
+        if !traits.is_empty() {
+            page.push_str("\n# Trait implementations\n\n");
+            page.push_str(&traits.concat());
+        }
+    }
+
+    fn signature(&self, item: &Value, kind: &str, inner: &Value) -> String {
+        let name = item["name"].as_str().unwrap_or_default();
+        let vis = if item["visibility"] == "public" { "pub " } else { "" };
+        match kind {
+            "function" => format!("{}{}", vis, function_signature(name, inner)),
+            "struct" => {
+                let generics = generics(&inner["generics"]);
+                let body = match &inner["kind"] {
+                    Value::String(s) if s == "unit" => ";".to_string(),
+                    Value::Object(o) if o.contains_key("tuple") => {
+                        let fields: Vec<String> = o["tuple"]
+                            .as_array()
+                            .into_iter()
+                            .flatten()
+                            .map(|f| match self.item(f) {
+                                Some(f) => format!("pub {}", type_str(&f["inner"]["struct_field"])),
+                                None => "_".to_string(),
+                            })
+                            .collect();
+                        format!("({});", fields.join(", "))
+                    }
+                    plain => {
+                        let mut body = String::from(" {\n");
+                        for f in self.public_items(&plain["plain"]["fields"]) {
+                            body.push_str(&format!("    {},\n", self.signature(f, "struct_field", &f["inner"]["struct_field"])));
+                        }
+                        let stripped = plain["plain"]["has_stripped_fields"].as_bool()
+                            .or(plain["plain"]["fields_stripped"].as_bool())
+                            .unwrap_or(false);
+                        if stripped {
+                            body.push_str("    /* private fields */\n");
+                        }
+                        body.push('}');
+                        body
+                    }
+                };
+                format!("{}struct {}{}{}{}", vis, name, generics.0, generics.1, body)
+            }
+            "struct_field" => format!("{}{}: {}", vis, name, type_str(inner)),
+            "enum" => {
+                let generics = generics(&inner["generics"]);
+                let mut body = String::from(" {\n");
+                for v in self.all_items(&inner["variants"]) {
+                    body.push_str(&format!("    {},\n", self.signature(v, "variant", &v["inner"]["variant"])));
+                }
+                body.push('}');
+                format!("{}enum {}{}{}{}", vis, name, generics.0, generics.1, body)
+            }
+            "variant" => match &inner["kind"] {
+                Value::Object(o) if o.contains_key("tuple") => {
+                    let fields: Vec<String> = self
+                        .all_items(&o["tuple"])
+                        .iter()
+                        .map(|f| type_str(&f["inner"]["struct_field"]))
+                        .collect();
+                    format!("{}({})", name, fields.join(", "))
+                }
+                Value::Object(o) if o.contains_key("struct") => {
+                    let fields: Vec<String> = self
+                        .all_items(&o["struct"]["fields"])
+                        .iter()
+                        .map(|f| format!("{}: {}", f["name"].as_str().unwrap_or_default(), type_str(&f["inner"]["struct_field"])))
+                        .collect();
+                    format!("{} {{ {} }}", name, fields.join(", "))
+                }
+                _ => name.to_string(),
+            },
+            "trait" => {
+                let generics = generics(&inner["generics"]);
+                let bounds = bounds(&inner["bounds"]);
+                let bounds = if bounds.is_empty() { bounds } else { format!(": {}", bounds) };
+                let unsafety = if inner["is_unsafe"].as_bool().unwrap_or(false) { "unsafe " } else { "" };
+                format!("{}{}trait {}{}{}{}", vis, unsafety, name, generics.0, bounds, generics.1)
+            }
+            "constant" => {
+                let expr = inner["const"]["expr"].as_str().or(inner["expr"].as_str()).unwrap_or("..");
+                format!("{}const {}: {} = {};", vis, name, type_str(&inner["type"]), expr)
+            }
+            "static" => {
+                let is_mutable = inner["is_mutable"].as_bool().or(inner["mutable"].as_bool()).unwrap_or(false);
+                let mutability = if is_mutable { "mut " } else { "" };
+                format!("{}static {}{}: {};", vis, mutability, name, type_str(&inner["type"]))
+            }
+            "type_alias" | "typedef" => {
+                let generics = generics(&inner["generics"]);
+                format!("{}type {}{}{} = {};", vis, name, generics.0, generics.1, type_str(&inner["type"]))
+            }
+            _ => name.to_string(),
+        }
+    }
+}
+
+/// The kind of an item, like `struct`, and what the JSON holds about it.
+fn kind(item: &Value) -> (&str, &Value) {
+    match item["inner"].as_object().and_then(|o| o.iter().next()) {
+        Some((kind, inner)) => (kind.as_str(), inner),
+        None => ("", &Value::Null),
+    }
+}
+
+/// The keyword of a kind of item, which rustdoc also names its pages after, like `fn.main.html`.
+fn keyword(kind: &str) -> &str {
+    match kind {
+        "function" => "fn",
+        "type_alias" | "typedef" => "type",
+        kind => kind,
+    }
+}
+
+fn frontmatter(title: &str, emoji: &str) -> String {
+    format!("---\ntitle: \"{}\"\nemoji: {}\n---\n\n", title.replace('"', "'"), emoji)
+}
+
+fn code(signature: &str) -> String {
+    format!("```rust\n{}\n```\n\n", signature)
+}
+
+/// The docs of an item, with its unmarked code fences made Rust and the lines rustdoc hides
+/// from them (starting with `# `) left out.
+fn docs(item: &Value) -> String {
+    let docs = match item["docs"].as_str() {
+        Some(docs) if !docs.trim().is_empty() => docs,
+        _ => return String::new(),
+    };
+    let mut out = String::new();
+    let mut fence: Option<(String, bool)> = None;
+    for line in docs.lines() {
+        let trimmed = line.trim_start();
+        match &fence {
+            Some((marker, _)) if trimmed.starts_with(marker.as_str()) && trimmed.trim_end() == marker => {
+                fence = None;
+                out.push_str(line);
+            }
+            Some((_, true)) if trimmed == "#" || trimmed.starts_with("# ") => continue,
+            Some(_) => out.push_str(line),
+            None if trimmed.starts_with("```") || trimmed.starts_with("~~~") => {
+                let marker: String = trimmed.chars().take_while(|c| *c == '`' || *c == '~').collect();
+                let info = trimmed[marker.len()..].trim();
+                let is_rust = info
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|w| !w.is_empty())
+                    .all(|w| RUST_FENCE_ATTRIBUTES.contains(&w));
+                match is_rust {
+                    true => out.push_str(&format!("{}rust", marker)),
+                    false => out.push_str(trimmed),
+                }
+                fence = Some((marker, is_rust));
+            }
+            None => out.push_str(line),
+        }
+        out.push('\n');
+    }
+    out.push('\n');
+    out
+}
+
+fn impl_header(imp: &Value) -> String {
+    let generics = generics(&imp["generics"]);
+    let negative = if imp["is_negative"].as_bool().or(imp["negative"].as_bool()).unwrap_or(false) {
+        "!"
+    } else {
+        ""
+    };
+    format!(
+        "impl{} {}{} for {}{}",
+        generics.0,
+        negative,
+        path_str(&imp["trait"]),
+        type_str(&imp["for"]),
+        generics.1
+    )
+}
+
+fn function_signature(name: &str, inner: &Value) -> String {
+    let header = &inner["header"];
+    let flag = |new: &str, old: &str| header[new].as_bool().or(header[old].as_bool()).unwrap_or(false);
+    let mut qualifiers = String::new();
+    if flag("is_const", "const_") {
+        qualifiers.push_str("const ");
+    }
+    if flag("is_async", "async_") {
+        qualifiers.push_str("async ");
+    }
+    if flag("is_unsafe", "unsafe_") {
+        qualifiers.push_str("unsafe ");
+    }
+    let generics = generics(&inner["generics"]);
+    format!("{}fn {}{}{}{}", qualifiers, name, generics.0, fn_decl(fn_sig(inner), true), generics.1)
+}
+
+/// The inputs and output of a function, `sig` in newer formats and `decl` in older ones.
+fn fn_sig(inner: &Value) -> &Value {
+    if inner["sig"].is_null() {
+        &inner["decl"]
+    } else {
+        &inner["sig"]
+    }
+}
+
+fn fn_decl(sig: &Value, named: bool) -> String {
+    let inputs: Vec<String> = sig["inputs"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|input| {
+            let name = input[0].as_str().unwrap_or("_");
+            let ty = &input[1];
+            match (name, named) {
+                ("self", _) if ty["generic"] == "Self" => "self".to_string(),
+                ("self", _) if ty["borrowed_ref"]["type"]["generic"] == "Self" => {
+                    let r = &ty["borrowed_ref"];
+                    format!("{}self", reference(r))
+                }
+                (_, true) => format!("{}: {}", name, type_str(ty)),
+                (_, false) => type_str(ty),
+            }
+        })
+        .collect();
+    let output = match &sig["output"] {
+        Value::Null => String::new(),
+        output => format!(" -> {}", type_str(output)),
+    };
+    format!("({}){}", inputs.join(", "), output)
+}
+
+/// `&`, `&mut ` or `&'a ` of a borrowed reference.
+fn reference(r: &Value) -> String {
+    let lifetime = r["lifetime"].as_str().map(|l| format!("{} ", l)).unwrap_or_default();
+    let is_mutable = r["is_mutable"].as_bool().or(r["mutable"].as_bool()).unwrap_or(false);
+    format!("&{}{}", lifetime, if is_mutable { "mut " } else { "" })
+}
+
+/// The generic parameters of an item, like `<'a, T: Clone>`, and its where clause.
+fn generics(generics: &Value) -> (String, String) {
+    let params: Vec<String> = generics["params"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|p| {
+            let name = p["name"].as_str().unwrap_or_default();
+            let kind = &p["kind"];
+            if let Some(lifetime) = kind.get("lifetime") {
+                let outlives: Vec<&str> = lifetime["outlives"].as_array().into_iter().flatten().filter_map(|o| o.as_str()).collect();
+                return Some(if outlives.is_empty() { name.to_string() } else { format!("{}: {}", name, outlives.join(" + ")) });
+            }
+            if let Some(ty) = kind.get("type") {
+                if ty["is_synthetic"].as_bool().or(ty["synthetic"].as_bool()).unwrap_or(false) {
+                    return None;
+                }
+                let bounds = bounds(&ty["bounds"]);
+                let param = if bounds.is_empty() { name.to_string() } else { format!("{}: {}", name, bounds) };
+                return Some(match ty["default"].is_null() {
+                    true => param,
+                    false => format!("{} = {}", param, type_str(&ty["default"])),
+                });
+            }
+            kind.get("const").map(|c| format!("const {}: {}", name, type_str(&c["type"])))
+        })
+        .collect();
+    let predicates: Vec<String> = generics["where_predicates"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|p| {
+            if let Some(b) = p.get("bound_predicate") {
+                return Some(format!("{}: {}", type_str(&b["type"]), bounds(&b["bounds"])));
+            }
+            if let Some(l) = p.get("lifetime_predicate") {
+                let outlives: Vec<&str> = l["outlives"].as_array().into_iter().flatten().filter_map(|o| o.as_str()).collect();
+                return Some(format!("{}: {}", l["lifetime"].as_str().unwrap_or_default(), outlives.join(" + ")));
+            }
+            None
+        })
+        .collect();
+    let params = if params.is_empty() { String::new() } else { format!("<{}>", params.join(", ")) };
+    let predicates = if predicates.is_empty() {
+        String::new()
+    } else {
+        format!("\nwhere\n    {},", predicates.join(",\n    "))
+    };
+    (params, predicates)
+}
+
+fn bounds(bounds: &Value) -> String {
+    let bounds: Vec<String> = bounds
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|b| {
+            if let Some(t) = b.get("trait_bound") {
+                let modifier = if t["modifier"] == "maybe" { "?" } else { "" };
+                return Some(format!("{}{}", modifier, path_str(&t["trait"])));
+            }
+            b.get("outlives").and_then(|o| o.as_str()).map(|o| o.to_string())
+        })
+        .collect();
+    bounds.join(" + ")
+}
+
+/// A path to a type or trait with its generic arguments, like `Vec<String>`.
+fn path_str(path: &Value) -> String {
+    let name = path["path"].as_str().or(path["name"].as_str()).unwrap_or("_");
+    // Paths written by macros, like those of derives, start at the crate that defines them.
+    let name = match name.strip_prefix("$crate::") {
+        Some(name) => name.rsplit("::").next().unwrap_or(name),
+        None => name,
+    };
+    let args = &path["args"];
+    if let Some(angle) = args.get("angle_bracketed") {
+        let mut parts: Vec<String> = angle["args"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|a| {
+                if let Some(t) = a.get("type") {
+                    type_str(t)
+                } else if let Some(l) = a.get("lifetime").and_then(|l| l.as_str()) {
+                    l.to_string()
+                } else if let Some(c) = a.get("const") {
+                    c["expr"].as_str().unwrap_or("_").to_string()
+                } else {
+                    "_".to_string()
+                }
+            })
+            .collect();
+        let constraints = if angle["constraints"].is_null() { &angle["bindings"] } else { &angle["constraints"] };
+        parts.extend(constraints.as_array().into_iter().flatten().map(|c| {
+            let binding = &c["binding"];
+            match binding.get("equality") {
+                Some(eq) => format!("{} = {}", c["name"].as_str().unwrap_or_default(), eq.get("type").map_or_else(|| "_".to_string(), type_str)),
+                None => format!("{}: {}", c["name"].as_str().unwrap_or_default(), bounds(&binding["constraint"])),
+            }
+        }));
+        if !parts.is_empty() {
+            return format!("{}<{}>", name, parts.join(", "));
+        }
+    }
+    if let Some(p) = args.get("parenthesized") {
+        let inputs: Vec<String> = p["inputs"].as_array().into_iter().flatten().map(type_str).collect();
+        let output = match &p["output"] {
+            Value::Null => String::new(),
+            output => format!(" -> {}", type_str(output)),
+        };
+        return format!("{}({}){}", name, inputs.join(", "), output);
+    }
+    name.to_string()
+}
+
+/// A type as it would be written in Rust.
+fn type_str(ty: &Value) -> String {
+    let (kind, inner) = match ty.as_object().and_then(|o| o.iter().next()) {
+        Some((kind, inner)) => (kind.as_str(), inner),
+        None => return "_".to_string(),
+    };
+    match kind {
+        "resolved_path" => path_str(inner),
+        "generic" | "primitive" => inner.as_str().unwrap_or("_").to_string(),
+        "tuple" => {
+            let types: Vec<String> = inner.as_array().into_iter().flatten().map(type_str).collect();
+            match types.as_slice() {
+                [one] => format!("({},)", one),
+                _ => format!("({})", types.join(", ")),
+            }
+        }
+        "slice" => format!("[{}]", type_str(inner)),
+        "array" => format!("[{}; {}]", type_str(&inner["type"]), inner["len"].as_str().unwrap_or("_")),
+        "borrowed_ref" => format!("{}{}", reference(inner), type_str(&inner["type"])),
+        "raw_pointer" => {
+            let is_mutable = inner["is_mutable"].as_bool().or(inner["mutable"].as_bool()).unwrap_or(false);
+            format!("*{} {}", if is_mutable { "mut" } else { "const" }, type_str(&inner["type"]))
+        }
+        "impl_trait" => format!("impl {}", bounds(inner)),
+        "dyn_trait" => {
+            let mut traits: Vec<String> = inner["traits"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|t| path_str(&t["trait"]))
+                .collect();
+            traits.extend(inner["lifetime"].as_str().map(|l| l.to_string()));
+            format!("dyn {}", traits.join(" + "))
+        }
+        "function_pointer" => format!("fn{}", fn_decl(fn_sig(inner), false)),
+        "qualified_path" => {
+            let name = inner["name"].as_str().unwrap_or("_");
+            let self_type = type_str(&inner["self_type"]);
+            match inner["trait"].is_null() {
+                true => format!("{}::{}", self_type, name),
+                false => format!("<{} as {}>::{}", self_type, path_str(&inner["trait"]), name),
+            }
+        }
+        "pat" => type_str(&inner["type"]),
+        _ => "_".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::markdown::rustdoc::ApiReference;
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_api_reference() {
+        let string = json!({"resolved_path": {"path": "String", "id": 90, "args": null}});
+        let json = json!({
+            "root": 0,
+            "format_version": 39,
+            "index": {
+                "0": {"id": 0, "crate_id": 0, "name": "shipit", "visibility": "public", "docs": "Ships things.",
+                    "inner": {"module": {"is_crate": true, "items": [1, 2, 5, 7, 9, 10]}}},
+                "1": {"id": 1, "crate_id": 0, "name": "Ship", "visibility": "public", "docs": "A ship.\n\n```\n# use shipit::Ship;\nlet ship = Ship::new(\"Aurora\");\n```",
+                    "inner": {"struct": {"generics": {"params": [], "where_predicates": []},
+                        "kind": {"plain": {"fields": [3, 4], "has_stripped_fields": true}}, "impls": [6]}}},
+                "3": {"id": 3, "crate_id": 0, "name": "name", "visibility": "public", "docs": "What it's called.", "inner": {"struct_field": string}},
+                "4": {"id": 4, "crate_id": 0, "name": "crew", "visibility": "crate", "docs": null, "inner": {"struct_field": {"primitive": "u32"}}},
+                "6": {"id": 6, "crate_id": 0, "name": null, "visibility": "default", "docs": null,
+                    "inner": {"impl": {"generics": {"params": [], "where_predicates": []}, "trait": null, "for": {"resolved_path": {"path": "Ship", "id": 1, "args": null}},
+                        "items": [8], "is_synthetic": false, "blanket_impl": null}}},
+                "8": {"id": 8, "crate_id": 0, "name": "sail", "visibility": "public", "docs": "Sets sail.",
+                    "inner": {"function": {"sig": {"inputs": [["self", {"borrowed_ref": {"lifetime": null, "is_mutable": true, "type": {"generic": "Self"}}}],
+                        ["to", {"borrowed_ref": {"lifetime": null, "is_mutable": false, "type": {"primitive": "str"}}}]],
+                        "output": {"resolved_path": {"path": "Result", "id": 91, "args": {"angle_bracketed": {"args": [{"type": {"tuple": []}}], "constraints": []}}}}},
+                        "generics": {"params": [], "where_predicates": []}, "header": {"is_const": false, "is_unsafe": false, "is_async": true}, "has_body": true}}},
+                "2": {"id": 2, "crate_id": 0, "name": "docks", "visibility": "public", "docs": null,
+                    "inner": {"module": {"is_crate": false, "items": [11]}}},
+                "11": {"id": 11, "crate_id": 0, "name": "moor", "visibility": "public", "docs": "Ties up.",
+                    "inner": {"function": {"sig": {"inputs": [["ship", {"generic": "T"}]], "output": null},
+                        "generics": {"params": [{"name": "T", "kind": {"type": {"bounds": [{"trait_bound": {"trait": {"path": "Clone", "id": 92, "args": null}, "generic_params": [], "modifier": "none"}}], "default": null, "is_synthetic": false}}}], "where_predicates": []},
+                        "header": {"is_const": false, "is_unsafe": false, "is_async": false}, "has_body": true}}},
+                "5": {"id": 5, "crate_id": 0, "name": "MAX_CREW", "visibility": "public", "docs": null,
+                    "inner": {"constant": {"type": {"primitive": "usize"}, "const": {"expr": "12", "value": "12", "is_literal": true}}}},
+                "7": {"id": 7, "crate_id": 0, "name": "hidden", "visibility": "crate", "docs": null,
+                    "inner": {"function": {"sig": {"inputs": [], "output": null}, "generics": {"params": [], "where_predicates": []}, "header": {}, "has_body": true}}},
+                "9": {"id": 9, "crate_id": 0, "name": "Vec", "visibility": "public", "docs": null, "inner": {"use": {"source": "std::vec::Vec", "id": 93}}},
+                "10": {"id": 10, "crate_id": 0, "name": "Weather", "visibility": "public", "docs": null,
+                    "inner": {"enum": {"generics": {"params": [], "where_predicates": []}, "variants": [12, 13], "impls": []}}},
+                "12": {"id": 12, "crate_id": 0, "name": "Calm", "visibility": "default", "docs": null, "inner": {"variant": {"kind": "plain"}}},
+                "13": {"id": 13, "crate_id": 0, "name": "Storm", "visibility": "default", "docs": "Stay in.", "inner": {"variant": {"kind": {"struct": {"fields": [14], "has_stripped_fields": false}}}}},
+                "14": {"id": 14, "crate_id": 0, "name": "knots", "visibility": "default", "docs": null, "inner": {"struct_field": {"primitive": "u8"}}}
+            }
+        });
+        let reference = ApiReference::from_json(&json).unwrap();
+        assert_eq!(reference.name, "shipit");
+        let mut paths: Vec<String> = reference.pages.iter().map(|p| p.path.display().to_string()).collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            ["shipit/docks/fn.moor.md", "shipit/docks/intro.md", "shipit/enum.Weather.md", "shipit/intro.md", "shipit/struct.Ship.md"]
+        );
+        let page = |path: &str| reference.pages.iter().find(|p| p.path.ends_with(path)).unwrap().markdown.clone();
+
+        let ship = page("struct.Ship.md");
+        assert!(ship.starts_with("---\ntitle: \"struct shipit::Ship\"\nemoji: 🧱\n---"));
+        assert!(ship.contains("pub struct Ship {\n    pub name: String,\n    /* private fields */\n}"));
+        assert!(ship.contains("```rust\nlet ship = Ship::new(\"Aurora\");\n```"));
+        assert!(ship.contains("pub async fn sail(&mut self, to: &str) -> Result<()>"));
+        assert!(!ship.contains("crew"));
+
+        assert!(page("docks/fn.moor.md").contains("pub fn moor<T: Clone>(ship: T)"));
+        assert!(page("enum.Weather.md").contains("    Storm { knots: u8 },\n"));
+        let intro = page("shipit/intro.md");
+        assert!(intro.contains("Ships things."));
+        assert!(intro.contains("pub const MAX_CREW: usize = 12;"));
+    }
+}
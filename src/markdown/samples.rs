@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use markdown::mdast::Node;
+use tokio::process::Command;
+
+use crate::markdown::diagnostic::Diagnostic;
+use crate::markdown::directive::{walk, DIRECTIVE_LANG};
+use crate::markdown::parse::parse_file;
+use crate::markdown::source::line_position;
+use crate::settings::directory::SourceTree;
+use crate::settings::notation::NotationSettings;
+
+/// Where the file of a sample goes in a command.
+pub const FILE_PLACEHOLDER: &str = "{file}";
+/// Words on a code fence that keep its sample from being checked, like ```` ```rust ignore ````.
+pub const SKIP_MARKERS: [&str; 2] = ["ignore", "no_check"];
+const MAX_OUTPUT_LINES: usize = 20;
+
+/// A fenced code block of a documentation file.
+#[derive(Clone, Debug)]
+pub struct CodeSample {
+    pub lang: String,
+    pub code: String,
+    pub line: usize,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CodeCheckReport {
+    /// How many samples were run.
+    pub checked: usize,
+    pub failures: Vec<Diagnostic>,
+}
+
+/// The fenced code blocks below `node`, including those inside directives. `line_offset` is
+/// added to every line, for nodes parsed out of a larger file.
+pub fn extract_samples(node: &Node, line_offset: usize) -> Vec<CodeSample> {
+    let mut samples = Vec::new();
+    walk(node, line_offset, &mut |n, offset| {
+        let c = match n {
+            Node::Code(c) => c,
+            _ => return,
+        };
+        let lang = match c.lang.as_deref() {
+            Some(DIRECTIVE_LANG) | None => return,
+            Some(lang) => lang,
+        };
+        let is_skipped = c
+            .meta
+            .as_deref()
+            .is_some_and(|m| m.split_whitespace().any(|w| SKIP_MARKERS.contains(&w)));
+        if !is_skipped {
+            samples.push(CodeSample {
+                lang: lang.to_lowercase(),
+                code: c.value.clone(),
+                line: c.position.as_ref().map_or(0, |p| p.start.line) + offset,
+            });
+        }
+    });
+    samples
+}
+
+/// Runs the `[code_check]` command of its language on every sample under `dir`. A command
+/// failing is reported at the sample's line.
+pub async fn check_code(settings: &NotationSettings, dir: &str) -> Result<CodeCheckReport> {
+    let commands = &settings.code_check.commands;
+    if commands.is_empty() {
+        return Err(anyhow!(
+            "no commands to check code with, add some under [code_check.commands], e.g. rust = \"rustc --edition 2021 --emit=metadata --crate-type lib {}\"",
+            FILE_PLACEHOLDER
+        ));
+    }
+    let commands: HashMap<String, &String> = commands.iter().map(|(k, v)| (k.to_lowercase(), v)).collect();
+    let tree = SourceTree::load(dir, settings.content.clone())?;
+    let work_dir = std::env::temp_dir().join(format!("notation-samples-{}", std::process::id()));
+    std::fs::create_dir_all(&work_dir)?;
+
+    let mut report = CodeCheckReport::default();
+    for relative_path in tree.files.iter() {
+        let path = tree.root.join(relative_path);
+        let parsed = parse_file(&path).await?;
+        for sample in extract_samples(parsed.node(), 0) {
+            let command = match commands.get(&sample.lang) {
+                Some(c) => c,
+                None => continue,
+            };
+            report.checked += 1;
+            if let Err(e) = run_sample(command, &sample, &work_dir, report.checked).await {
+                report.failures.push(Diagnostic::error(
+                    &path.display().to_string(),
+                    line_position(sample.line).as_ref(),
+                    format!("{} sample failed: {}", sample.lang, e),
+                ));
+            }
+        }
+    }
+    let _ = std::fs::remove_dir_all(&work_dir);
+    Ok(report)
+}
+
+async fn run_sample(command: &str, sample: &CodeSample, work_dir: &Path, n: usize) -> Result<()> {
+    let file = work_dir.join(format!("sample_{}.{}", n, extension(&sample.lang)));
+    std::fs::write(&file, &sample.code)?;
+    let file = file.display().to_string();
+    let command = if command.contains(FILE_PLACEHOLDER) {
+        command.replace(FILE_PLACEHOLDER, &file)
+    } else {
+        format!("{} {}", command, file)
+    };
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(&command)
+        .current_dir(work_dir)
+        .output()
+        .await
+        .map_err(|e| anyhow!("failed to run `{}`: {}", command, e))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let out = if stderr.trim().is_empty() { stdout } else { stderr };
+    let out: Vec<&str> = out.lines().take(MAX_OUTPUT_LINES).collect();
+    Err(anyhow!("`{}` exited with {}\n{}", command, output.status, out.join("\n")))
+}
+
+/// The file extension compilers expect for a language, which is the language itself unless
+/// it is known to differ.
+fn extension(lang: &str) -> &str {
+    match lang {
+        "rust" => "rs",
+        "python" => "py",
+        "javascript" => "js",
+        "typescript" => "ts",
+        "bash" | "shell" => "sh",
+        "ruby" => "rb",
+        "kotlin" => "kt",
+        "csharp" => "cs",
+        "haskell" => "hs",
+        "yaml" => "yml",
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::markdown::directive::preprocess;
+    use crate::markdown::samples::{check_code, extract_samples};
+    use crate::markdown::source::{MarkdownParser, SourceParser};
+    use crate::settings::notation::NotationSettings;
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_extract_samples() {
+        let markdown = "# Page\n\n```rust\nfn main() {}\n```\n\n```rust ignore\nnot rust\n```\n\n```\nplain\n```\n\n:::callout\n```python\nprint(1)\n```\n:::\n";
+        let node = MarkdownParser.parse(markdown).unwrap();
+        let samples = extract_samples(&node, 0);
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0].lang, "rust");
+        assert_eq!(samples[0].line, 3);
+        assert_eq!(samples[1].lang, "python");
+        assert_eq!(samples[1].code, "print(1)");
+        assert_eq!(samples[1].line, preprocess(markdown).lines().position(|l| l == "```python").unwrap() + 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_check_code() {
+        let dir = std::env::temp_dir().join(format!("notation-check-code-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("page.md"), "# Page\n\n```rust\nfn main() {}\n```\n\n```rust\nfn helper() {}\n```\n").unwrap();
+        let settings: NotationSettings = serde_json::from_value(serde_json::json!({
+            "notion": {"secret": "secret", "parent_page": "Docs"},
+            "code_check": {"commands": {"Rust": "grep -q main"}}
+        }))
+        .unwrap();
+        let report = check_code(&settings, dir.to_str().unwrap()).await.unwrap();
+        assert_eq!(report.checked, 2);
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].line, Some(7));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
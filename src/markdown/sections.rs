@@ -0,0 +1,99 @@
+use markdown::mdast::Node;
+
+/// The depth of the headings that start the top level sections of a page: the shallowest
+/// one, unless a single heading of that depth opens the page as its title.
+fn section_depth(nodes: &[Node]) -> Option<u8> {
+    let depths: Vec<u8> = nodes
+        .iter()
+        .filter_map(|n| match n {
+            Node::Heading(h) => Some(h.depth),
+            _ => None,
+        })
+        .collect();
+    let shallowest = *depths.iter().min()?;
+    let is_title = depths[0] == shallowest && depths.iter().filter(|d| **d == shallowest).count() == 1;
+    if is_title {
+        depths.iter().copied().filter(|d| *d > shallowest).min()
+    } else {
+        Some(shallowest)
+    }
+}
+
+/// Keeps, of the top level sections of a page, those under the headings named `names`,
+/// matched without regard to case. What comes before the first section stays. Returns the
+/// names no section has.
+pub fn select_sections(nodes: &mut Vec<Node>, names: &[String]) -> Vec<String> {
+    let is_named = |heading: &Node| {
+        let text = heading.to_string();
+        names.iter().position(|n| n.trim().eq_ignore_ascii_case(text.trim()))
+    };
+    let mut found = vec![false; names.len()];
+    if let Some(depth) = section_depth(nodes) {
+        let mut is_kept = true;
+        nodes.retain(|n| {
+            match n {
+                Node::Heading(h) if h.depth == depth => {
+                    let named = is_named(n);
+                    if let Some(i) = named {
+                        found[i] = true;
+                    }
+                    is_kept = named.is_some();
+                }
+                // A shallower heading ends the section, and isn't one itself.
+                Node::Heading(h) if h.depth < depth => is_kept = true,
+                _ => {}
+            }
+            is_kept
+        });
+    }
+    names
+        .iter()
+        .zip(found)
+        .filter(|(_, is_found)| !is_found)
+        .map(|(n, _)| n.clone())
+        .collect()
+}
+
+/// The heading names of a `sections:` frontmatter value, like `[Public API, Usage]` or
+/// `Public API, Usage`.
+pub fn parse_section_names(value: &str) -> Vec<String> {
+    let value = value.trim();
+    let value = value
+        .strip_prefix('[')
+        .and_then(|v| v.strip_suffix(']'))
+        .unwrap_or(value);
+    value
+        .split(',')
+        .map(|n| n.trim().trim_matches(['"', '\'']).trim().to_string())
+        .filter(|n| !n.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::markdown::sections::{parse_section_names, select_sections};
+    use crate::markdown::source::{MarkdownParser, SourceParser};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_select_sections() {
+        let select = |markdown: &str, names: &[&str]| {
+            let mut root = MarkdownParser.parse(markdown).unwrap();
+            let names: Vec<String> = names.iter().map(|n| n.to_string()).collect();
+            let missing = select_sections(root.children_mut().unwrap(), &names);
+            let kept: Vec<String> = root.children().unwrap().iter().map(|n| n.to_string()).collect();
+            (kept, missing)
+        };
+
+        let page = "# Guide\n\nIntro\n\n## Public API\n\nCall it\n\n### Errors\n\nRetry\n\n## Internal\n\nSecret";
+        let (kept, missing) = select(page, &["public api", "Changelog"]);
+        assert_eq!(kept, vec!["Guide", "Intro", "Public API", "Call it", "Errors", "Retry"]);
+        assert_eq!(missing, vec!["Changelog"]);
+
+        // Without a title heading, the `#` headings are the sections.
+        let (kept, _) = select("Preamble\n\n# Usage\n\nRun\n\n# Internal\n\nSecret", &["Usage"]);
+        assert_eq!(kept, vec!["Preamble", "Usage", "Run"]);
+
+        assert_eq!(parse_section_names("[Public API, 'Usage']"), vec!["Public API", "Usage"]);
+        assert_eq!(parse_section_names("Usage"), vec!["Usage"]);
+    }
+}
@@ -0,0 +1,274 @@
+use std::path::Path;
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, Result};
+use markdown::mdast::{Code, Heading, Image, List, ListItem, Node, Paragraph, Root, Text};
+use markdown::unist::{Point, Position};
+use markdown::ParseOptions;
+
+use crate::markdown::directive::preprocess;
+
+/// Turns the contents of a documentation file into the markdown tree the Notion conversion
+/// works from. Markdown is parsed as is; other formats map their constructs onto it.
+pub trait SourceParser: Send + Sync {
+    /// The file extensions, without the dot, of the files this parser reads.
+    fn extensions(&self) -> &'static [&'static str];
+
+    fn parse(&self, contents: &str) -> Result<Node>;
+
+    /// Like [`SourceParser::parse`], with the text the offsets of the tree's positions point
+    /// into, when they point into one. The other formats' trees only keep lines.
+    fn parse_with_source(&self, contents: &str) -> Result<(Node, Option<String>)> {
+        Ok((self.parse(contents)?, None))
+    }
+}
+
+pub struct MarkdownParser;
+
+impl SourceParser for MarkdownParser {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["md"]
+    }
+
+    fn parse(&self, contents: &str) -> Result<Node> {
+        self.parse_with_source(contents).map(|(node, _)| node)
+    }
+
+    /// The offsets point into the contents once their directives are rewritten by [`preprocess`].
+    fn parse_with_source(&self, contents: &str) -> Result<(Node, Option<String>)> {
+        let mut options = ParseOptions::gfm();
+        options.constructs.frontmatter = true;
+        let source = preprocess(contents);
+        let node = markdown::to_mdast(&source, &options).map_err(|e| anyhow!(e))?;
+        Ok((node, Some(source)))
+    }
+}
+
+static PARSERS: OnceLock<Vec<Box<dyn SourceParser>>> = OnceLock::new();
+
+/// Markdown, then every parser enabled by a feature.
+pub fn parsers() -> &'static [Box<dyn SourceParser>] {
+    PARSERS.get_or_init(|| {
+        #[allow(unused_mut)]
+        let mut parsers: Vec<Box<dyn SourceParser>> = vec![Box::new(MarkdownParser)];
+        #[cfg(feature = "asciidoc")]
+        parsers.push(Box::new(crate::markdown::asciidoc::AsciiDocParser));
+        #[cfg(feature = "rst")]
+        parsers.push(Box::new(crate::markdown::rst::RstParser));
+        parsers
+    })
+}
+
+pub fn parser_for(path: &Path) -> Option<&'static dyn SourceParser> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    parsers()
+        .iter()
+        .find(|p| p.extensions().contains(&extension.as_str()))
+        .map(|p| p.as_ref())
+}
+
+/// Whether `path` is a documentation file one of the enabled parsers reads.
+pub fn is_source_file(path: &Path) -> bool {
+    parser_for(path).is_some()
+}
+
+/// Every extension read by the enabled parsers, markdown first.
+pub fn source_extensions() -> Vec<&'static str> {
+    parsers().iter().flat_map(|p| p.extensions().iter().copied()).collect()
+}
+
+/// The position of a block starting on `line`, so diagnostics point at the source file.
+pub fn line_position(line: usize) -> Option<Position> {
+    Some(Position {
+        start: Point::new(line, 1, 0),
+        end: Point::new(line, 1, 0),
+    })
+}
+
+pub fn text(value: &str, line: usize) -> Node {
+    Node::Text(Text {
+        value: value.to_string(),
+        position: line_position(line),
+    })
+}
+
+/// `value` without its backslash escapes, like `\*` for a `*` that doesn't start emphasis.
+/// Markdown's parser drops them itself, the other formats' parsers call this.
+pub fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match chars.peek() {
+            Some(next) if c == '\\' && next.is_ascii_punctuation() => {
+                result.push(*next);
+                chars.next();
+            }
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+/// `value` with its HTML character references, named (`&amp;`) or numeric (`&#169;`,
+/// `&#xA9;`), decoded. Unknown names are left as they are.
+pub fn decode_entities(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest[1..]
+            .find(';')
+            .filter(|end| *end <= 32)
+            .and_then(|end| Some((entity(&rest[1..end + 1])?, end + 2)));
+        match decoded {
+            Some((c, len)) => {
+                result.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+fn entity(name: &str) -> Option<char> {
+    if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    let c = match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        "hellip" => '…',
+        "mdash" => '—',
+        "ndash" => '–',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "laquo" => '«',
+        "raquo" => '»',
+        "bull" => '•',
+        "middot" => '·',
+        "deg" => '°',
+        "plusmn" => '±',
+        "times" => '×',
+        "divide" => '÷',
+        "rarr" => '→',
+        "larr" => '←',
+        "harr" => '↔',
+        "euro" => '€',
+        "pound" => '£',
+        "yen" => '¥',
+        "cent" => '¢',
+        "sect" => '§',
+        "para" => '¶',
+        _ => return None,
+    };
+    Some(c)
+}
+
+pub fn paragraph(children: Vec<Node>, line: usize) -> Node {
+    Node::Paragraph(Paragraph {
+        children,
+        position: line_position(line),
+    })
+}
+
+pub fn heading(value: &str, depth: u8, line: usize) -> Node {
+    Node::Heading(Heading {
+        children: vec![text(value, line)],
+        position: line_position(line),
+        depth,
+    })
+}
+
+pub fn code(value: String, lang: Option<String>, meta: Option<String>, line: usize) -> Node {
+    Node::Code(Code {
+        value,
+        position: line_position(line),
+        lang,
+        meta,
+    })
+}
+
+pub fn image(url: &str, alt: &str, line: usize) -> Node {
+    paragraph(
+        vec![Node::Image(Image {
+            position: line_position(line),
+            alt: alt.to_string(),
+            url: url.to_string(),
+            title: None,
+        })],
+        line,
+    )
+}
+
+/// A list whose items each hold one paragraph.
+pub fn list(items: Vec<Vec<Node>>, ordered: bool, line: usize) -> Node {
+    Node::List(List {
+        children: items
+            .into_iter()
+            .map(|children| {
+                Node::ListItem(ListItem {
+                    children: vec![paragraph(children, line)],
+                    position: line_position(line),
+                    spread: false,
+                    checked: None,
+                })
+            })
+            .collect(),
+        position: line_position(line),
+        ordered,
+        start: if ordered { Some(1) } else { None },
+        spread: false,
+    })
+}
+
+pub fn root(children: Vec<Node>) -> Node {
+    Node::Root(Root {
+        children,
+        position: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::markdown::source::{decode_entities, is_source_file, parser_for, unescape};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_parser_for() {
+        assert!(is_source_file(Path::new("docs/intro.md")));
+        assert!(is_source_file(Path::new("docs/README.MD")));
+        assert!(!is_source_file(Path::new("docs/notes.txt")));
+        assert_eq!(parser_for(Path::new("a.adoc")).is_some(), cfg!(feature = "asciidoc"));
+        assert_eq!(parser_for(Path::new("a.rst")).is_some(), cfg!(feature = "rst"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_literal_text() {
+        assert_eq!(unescape(r"\*not bold\* and \_not em\_, C:\path"), r"*not bold* and _not em_, C:\path");
+        assert_eq!(
+            decode_entities("Tom &amp; Jerry&nbsp;&copy; &#65;&#x42; &lt;tag&gt; &mdash;"),
+            "Tom & Jerry\u{a0}© AB <tag> —"
+        );
+        assert_eq!(decode_entities("AT&T & &unknown; &#xZZ; &"), "AT&T & &unknown; &#xZZ; &");
+    }
+}
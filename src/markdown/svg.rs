@@ -0,0 +1,53 @@
+use anyhow::Result;
+
+/// Whether the image at `url`, a URL or a path, is an SVG.
+pub fn is_svg(url: &str) -> bool {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    path.to_lowercase().ends_with(".svg")
+}
+
+/// Renders an SVG to a PNG at its own size, with the system's fonts for its text.
+#[cfg(feature = "svg")]
+pub fn render_png(svg: &[u8]) -> Result<Vec<u8>> {
+    use anyhow::anyhow;
+    use resvg::{tiny_skia, usvg};
+
+    let mut options = usvg::Options::default();
+    options.fontdb_mut().load_system_fonts();
+    let tree = usvg::Tree::from_data(svg, &options).map_err(|e| anyhow!("invalid svg: {}", e))?;
+    let size = tree.size().to_int_size();
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height())
+        .ok_or_else(|| anyhow!("svg has no size to render at"))?;
+    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+    pixmap.encode_png().map_err(|e| anyhow!("failed to encode png: {}", e))
+}
+
+#[cfg(not(feature = "svg"))]
+pub fn render_png(_svg: &[u8]) -> Result<Vec<u8>> {
+    Err(anyhow::anyhow!("rendering svgs needs notation built with the svg feature"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::markdown::svg::is_svg;
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_is_svg() {
+        assert!(is_svg("diagram.svg"));
+        assert!(is_svg("https://example.com/Logo.SVG?v=2"));
+        assert!(is_svg("../images/flow.svg#layer"));
+        assert!(!is_svg("photo.png"));
+        assert!(!is_svg("https://example.com/svg"));
+    }
+
+    #[cfg(feature = "svg")]
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_render_png() {
+        use crate::markdown::svg::render_png;
+
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" width="4" height="3"><rect width="4" height="3" fill="red"/></svg>"#;
+        let png = render_png(svg).unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+        assert!(render_png(b"not an svg").is_err());
+    }
+}
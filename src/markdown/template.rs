@@ -0,0 +1,187 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, Result};
+use regex::Regex;
+
+use crate::markdown::anchors::github_slug;
+use crate::settings::notation::TemplateSettings;
+
+static VARIABLE: OnceLock<Regex> = OnceLock::new();
+
+const MEETING_NOTES: &str = "---
+title: \"{{title}}\"
+emoji: 📝
+date: {{date}}
+---
+
+**Date:** {{date}}
+
+**Attendees:**
+
+# Agenda
+
+-
+
+# Notes
+
+# Decisions
+
+# Action items
+
+-
+";
+
+/// The templates notation comes with, by name.
+pub const BUILTIN_TEMPLATES: [(&str, &str); 1] = [("meeting-notes", MEETING_NOTES)];
+
+/// A markdown file with `{{variables}}` to fill in, that a new page starts from.
+#[derive(Clone, Debug)]
+pub struct Template {
+    pub name: String,
+    pub text: String,
+}
+
+impl Template {
+    /// The template `name`: `<name>.md` in the `[templates]` directory, or else the built-in
+    /// one of that name.
+    pub fn find(name: &str, settings: &TemplateSettings) -> Result<Self> {
+        if let Some(dir) = settings.dir() {
+            let path = dir.join(format!("{}.md", name));
+            if path.is_file() {
+                return Ok(Template {
+                    name: name.to_string(),
+                    text: std::fs::read_to_string(&path)?,
+                });
+            }
+        }
+        match BUILTIN_TEMPLATES.iter().find(|(n, _)| *n == name) {
+            Some((_, text)) => Ok(Template {
+                name: name.to_string(),
+                text: text.to_string(),
+            }),
+            None => Err(anyhow!(
+                "no template \"{}\", add {}.md to the [templates] directory or use one of: {}",
+                name,
+                name,
+                template_names(settings).join(", ")
+            )),
+        }
+    }
+
+    /// The names of the variables of the template, in order, without repeats.
+    pub fn variables(&self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for c in variable().captures_iter(&self.text) {
+            if !names.iter().any(|n| *n == c[1]) {
+                names.push(c[1].to_string());
+            }
+        }
+        names
+    }
+
+    /// The template with its variables replaced by `values`. Fails on variables without a
+    /// value, naming them all.
+    pub fn render(&self, values: &BTreeMap<String, String>) -> Result<String> {
+        let missing: Vec<String> = self
+            .variables()
+            .into_iter()
+            .filter(|v| !values.contains_key(v))
+            .collect();
+        if !missing.is_empty() {
+            return Err(anyhow!(
+                "template \"{}\" needs a value for {}, give it with --var {}=...",
+                self.name,
+                missing.iter().map(|m| format!("{{{{{}}}}}", m)).collect::<Vec<_>>().join(", "),
+                missing[0]
+            ));
+        }
+        Ok(variable()
+            .replace_all(&self.text, |c: &regex::Captures| values[&c[1]].clone())
+            .to_string())
+    }
+}
+
+/// The names of the templates there are, those of the `[templates]` directory first.
+pub fn template_names(settings: &TemplateSettings) -> Vec<String> {
+    let mut names: Vec<String> = settings
+        .dir()
+        .and_then(|d| std::fs::read_dir(d).ok())
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|e| e == "md"))
+        .filter_map(|p| p.file_stem().map(|s| s.to_string_lossy().to_string()))
+        .collect();
+    names.sort();
+    for (name, _) in BUILTIN_TEMPLATES {
+        if !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+/// The values a page titled `title` fills its template with: the `title`, today's `date`,
+/// the `[templates] variables`, then `vars` over all of them.
+pub fn template_values(title: &str, settings: &TemplateSettings, vars: &[(String, String)]) -> BTreeMap<String, String> {
+    let mut values = BTreeMap::new();
+    values.insert("title".to_string(), title.to_string());
+    values.insert("date".to_string(), chrono::Local::now().format("%Y-%m-%d").to_string());
+    values.extend(settings.variables.clone());
+    values.extend(vars.iter().cloned());
+    values
+}
+
+/// Reads a `--var key=value`.
+pub fn parse_variable(var: &str) -> Result<(String, String)> {
+    match var.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => Ok((key.trim().to_string(), value.to_string())),
+        _ => Err(anyhow!("template variable \"{}\" should look like name=value", var)),
+    }
+}
+
+/// Where the page titled `title` is written under `dir`, like `2024-06-01-sync.md`.
+pub fn page_path(dir: &Path, title: &str) -> PathBuf {
+    let slug = github_slug(title.trim());
+    let slug = if slug.is_empty() { "untitled".to_string() } else { slug };
+    dir.join(format!("{}.md", slug))
+}
+
+fn variable() -> &'static Regex {
+    VARIABLE.get_or_init(|| Regex::new(r"\{\{\s*([A-Za-z_][\w-]*)\s*\}\}").unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::markdown::template::{page_path, parse_variable, template_values, Template};
+    use crate::settings::notation::TemplateSettings;
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_template() {
+        let settings = TemplateSettings::default();
+        let template = Template::find("meeting-notes", &settings).unwrap();
+        assert_eq!(template.variables(), ["title", "date"]);
+        let values = template_values("2024-06-01 Sync", &settings, &[]);
+        let page = template.render(&values).unwrap();
+        assert!(page.starts_with("---\ntitle: \"2024-06-01 Sync\"\nemoji: 📝\ndate: 20"));
+        assert!(!page.contains("{{"));
+
+        let template = Template {
+            name: "retro".to_string(),
+            text: "# {{ title }}\n\nFacilitator: {{facilitator}}\nTeam: {{team}}\n".to_string(),
+        };
+        let error = template.render(&values).unwrap_err().to_string();
+        assert!(error.contains("{{facilitator}}, {{team}}"));
+        let vars = [parse_variable("facilitator=Sam").unwrap(), parse_variable("team=Docs=Team").unwrap()];
+        let page = template.render(&template_values("Retro", &settings, &vars)).unwrap();
+        assert_eq!(page, "# Retro\n\nFacilitator: Sam\nTeam: Docs=Team\n");
+
+        assert!(parse_variable("=x").is_err());
+        assert!(Template::find("standup", &settings).unwrap_err().to_string().contains("meeting-notes"));
+        assert_eq!(page_path(Path::new("docs/meetings"), "2024-06-01 Sync"), Path::new("docs/meetings/2024-06-01-sync.md"));
+    }
+}
@@ -23,7 +23,7 @@ where
 {
     let mut result = String::new();
     chars.next();
-    while let Some(c) = chars.next() {
+    for c in chars.by_ref() {
         if c == '"' {
             break;
         } else {
@@ -0,0 +1,96 @@
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+
+use crate::notion::report::{FileStatus, ShipReport};
+use crate::redact::{redact, register_secret};
+use crate::settings::notation::NotifySettings;
+
+/// What is POSTed to `[notify] webhook_url`. Chat webhooks, like Slack's, show `text` and
+/// ignore the rest; anything else gets the whole report.
+#[derive(Serialize)]
+pub struct Notification<'a> {
+    pub text: String,
+    pub report: &'a ShipReport,
+}
+
+/// One line per parent page shipped to, with what was created and updated, or why it failed.
+pub fn message(report: &ShipReport) -> String {
+    let mut lines = Vec::new();
+    for d in report.destinations.iter() {
+        let url = d.parent_page_url.as_deref().unwrap_or("-");
+        let line = match &d.error {
+            Some(e) => format!("❌ {} failed to ship to {} ({}): {}", report.src, d.name, url, e),
+            None => {
+                let count = |status: FileStatus| d.files.iter().filter(|f| f.status == status).count();
+                format!(
+                    "✅ {} shipped to {} ({}): {} created, {} updated, {} warning(s)",
+                    report.src,
+                    d.name,
+                    url,
+                    count(FileStatus::Created),
+                    count(FileStatus::Updated),
+                    d.warnings.len()
+                )
+            }
+        };
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+/// POSTs the report of a ship to `[notify] webhook_url`, if set and the outcome is one to be
+/// told about. Returns whether it did.
+pub async fn notify(settings: &NotifySettings, report: &ShipReport) -> Result<bool> {
+    let url = match &settings.webhook_url {
+        Some(url) if !url.trim().is_empty() => url,
+        _ => return Ok(false),
+    };
+    let is_wanted = if report.is_failed() { settings.on_failure } else { settings.on_success };
+    if !is_wanted {
+        return Ok(false);
+    }
+    // Webhook URLs carry their own credentials.
+    register_secret(url);
+    let notification = Notification {
+        text: message(report),
+        report,
+    };
+    let response = reqwest::Client::new()
+        .post(url)
+        .json(&notification)
+        .send()
+        .await
+        .map_err(|e| anyhow!("failed to notify the webhook: {}", redact(&e.to_string())))?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(anyhow!("webhook answered {}: {}", status, redact(&body)));
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use crate::notify::message;
+    use crate::notion::report::ShipReport;
+    use crate::notion::summary::{ShipSummary, ShippedPage};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_message() {
+        let summary = ShipSummary {
+            pages: vec![ShippedPage::new("guide.md".to_string(), "Guide".to_string(), "aaa".to_string())],
+            ..Default::default()
+        };
+        let mut report = ShipReport::new("docs", Utc::now());
+        report.add("staging", Some("https://www.notion.so/staging-1".to_string()), Ok(&summary));
+        report.add("public", None, Err("parent page not found".to_string()));
+        assert!(report.is_failed());
+        assert_eq!(
+            message(&report),
+            "✅ docs shipped to staging (https://www.notion.so/staging-1): 1 created, 0 updated, 0 warning(s)\n\
+             ❌ docs failed to ship to public (-): parent page not found"
+        );
+    }
+}
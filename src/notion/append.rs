@@ -0,0 +1,300 @@
+use anyhow::{anyhow, Result};
+
+use crate::notion::block::{AppendBlockRequest, AppendBlockRequestChild, BlockContent};
+use crate::notion::schema::append_problems;
+
+/// The most children, or table rows, Notion takes per request.
+pub const MAX_CHILDREN_PER_REQUEST: usize = 100;
+/// The most blocks per request, counting the ones nested in others.
+pub const MAX_BLOCKS_PER_REQUEST: usize = 1000;
+/// How deep children nest in one request: the blocks of the request can hold children, and
+/// those theirs, but no deeper.
+pub const MAX_NESTING: usize = 2;
+
+/// One request of blocks to append, with the blocks that didn't fit in it.
+#[derive(Clone, Debug)]
+pub struct AppendBlockBatch {
+    pub request: AppendBlockRequest,
+    /// The children, nested deeper than Notion takes or past the first 100 of a block, like the
+    /// rows of a long table, to append to a block of the request once it exists, by its path:
+    /// its index among the children of the request, then among those of each block down to it.
+    pub deferred: Vec<(Vec<usize>, AppendBlockRequest)>,
+}
+
+/// Builds the requests appending blocks takes, within Notion's limits: at most 100 children,
+/// and 1000 blocks all told, per request, at most 100 children, or rows, per block at any
+/// level, and children at most two levels deep. Those are split over several requests; what
+/// can't be, like text over Notion's limits, is left to the request schema, and blocks that
+/// don't match it fail the build rather than the request.
+///
+/// Only the first batch carries `after`, the others belong after the last block of the batch
+/// before them, whose id Notion only gives once it is appended.
+#[derive(Clone, Debug, Default)]
+pub struct AppendBlockRequestBuilder {
+    children: Vec<AppendBlockRequestChild>,
+    after: Option<String>,
+}
+
+impl From<AppendBlockRequest> for AppendBlockRequestBuilder {
+    fn from(request: AppendBlockRequest) -> Self {
+        AppendBlockRequestBuilder {
+            children: request.children,
+            after: request.after,
+        }
+    }
+}
+
+impl AppendBlockRequestBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_after(mut self, block_id: String) -> Self {
+        self.after = Some(block_id);
+        self
+    }
+
+    pub fn with_block(mut self, child: AppendBlockRequestChild) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    pub fn with_blocks(mut self, children: Vec<AppendBlockRequestChild>) -> Self {
+        self.children.extend(children);
+        self
+    }
+
+    /// The batches to send in order, or what Notion would reject, see [`append_problems`].
+    pub fn build(self) -> Result<std::vec::IntoIter<AppendBlockBatch>> {
+        let request = AppendBlockRequest {
+            children: self.children,
+            after: self.after,
+        };
+        let problems = append_problems(&request);
+        if !problems.is_empty() {
+            return Err(anyhow!("blocks Notion won't take: {}", problems.join("; ")));
+        }
+
+        let mut batches: Vec<AppendBlockBatch> = Vec::new();
+        let mut blocks = 0;
+        let mut after = request.after;
+        for mut child in request.children {
+            let mut deferred = Vec::new();
+            fit_nesting(&mut child, &mut Vec::new(), 0, &mut deferred)?;
+            let count = child.block_count();
+            let batch = match batches.last_mut() {
+                Some(b)
+                    if b.request.children.len() < MAX_CHILDREN_PER_REQUEST
+                        && blocks + count <= MAX_BLOCKS_PER_REQUEST =>
+                {
+                    blocks += count;
+                    b
+                }
+                _ => {
+                    let mut request = AppendBlockRequest::new();
+                    request.after = after.take();
+                    batches.push(AppendBlockBatch {
+                        request,
+                        deferred: Vec::new(),
+                    });
+                    blocks = count;
+                    batches.last_mut().unwrap()
+                }
+            };
+            let index = batch.request.children.len();
+            batch
+                .deferred
+                .extend(deferred.into_iter().map(|(path, r)| ([vec![index], path].concat(), r)));
+            batch.request.append_child(child);
+        }
+        Ok(batches.into_iter())
+    }
+}
+
+/// How many levels of children a block needs under it to be created at all: a table its rows,
+/// a column its blocks, and a column list its columns and theirs.
+fn required_nesting(child: &AppendBlockRequestChild) -> usize {
+    match child.content {
+        BlockContent::ColumnList { .. } => 2,
+        BlockContent::Column { .. } | BlockContent::Table { .. } => 1,
+        _ => 0,
+    }
+}
+
+/// Moves the children of `child`, `level` levels down in the request, that would nest deeper
+/// than Notion takes, or come after the first 100, to `deferred`, by their parent's `path`
+/// under `child`'s. The first child that doesn't fit goes with the ones after it, so that they
+/// are appended in order.
+fn fit_nesting(
+    child: &mut AppendBlockRequestChild,
+    path: &mut Vec<usize>,
+    level: usize,
+    deferred: &mut Vec<(Vec<usize>, AppendBlockRequest)>,
+) -> Result<()> {
+    let mut children = child.nested().to_vec();
+    if children.is_empty() {
+        return Ok(());
+    }
+    let fits = children
+        .iter()
+        .position(|c| level + 1 + required_nesting(c) > MAX_NESTING)
+        .unwrap_or(children.len())
+        .min(MAX_CHILDREN_PER_REQUEST);
+    let rest = children.split_off(fits);
+    if children.is_empty() && required_nesting(child) > 0 {
+        let block_type = serde_json::to_value(rest[0].block_type()).unwrap_or_default();
+        return Err(anyhow!(
+            "a column can't start with a {}, Notion doesn't take it that deep in a column list, put another block before it",
+            block_type.as_str().unwrap_or_default()
+        ));
+    }
+    for (i, nested) in children.iter_mut().enumerate() {
+        path.push(i);
+        fit_nesting(nested, path, level + 1, deferred)?;
+        path.pop();
+    }
+    child.set_children(children);
+    if !rest.is_empty() {
+        deferred.push((path.clone(), AppendBlockRequest::new_children(rest)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::notion::append::AppendBlockRequestBuilder;
+    use crate::notion::block::{AppendBlockRequest, AppendBlockRequestChild, NotionBlock};
+    use crate::notion::fake::FakeNotion;
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_append_batches() {
+        let paragraphs: Vec<AppendBlockRequestChild> = (0..250)
+            .map(|i| AppendBlockRequestChild::new_paragraph_block(i.to_string()))
+            .collect();
+        let batches: Vec<_> = AppendBlockRequestBuilder::new()
+            .with_after("intro".to_string())
+            .with_blocks(paragraphs)
+            .build()
+            .unwrap()
+            .collect();
+        let sizes: Vec<usize> = batches.iter().map(|b| b.request.children.len()).collect();
+        assert_eq!(sizes, vec![100, 100, 50]);
+        assert_eq!(batches[0].request.after.as_deref(), Some("intro"));
+        assert!(batches[1].request.after.is_none());
+
+        let table = |rows: usize| {
+            let rows = (0..rows)
+                .map(|i| AppendBlockRequestChild::new_table_row_block(vec![vec![NotionBlock::new_text_block(i.to_string())]]))
+                .collect();
+            AppendBlockRequestChild::new_table_block(1, true, true, rows)
+        };
+        // Each table counts for 101 blocks, its rows past the first 100 are sent separately.
+        let tables: Vec<AppendBlockRequestChild> = (0..12).map(|_| table(300)).collect();
+        let batches: Vec<_> = AppendBlockRequestBuilder::new().with_blocks(tables).build().unwrap().collect();
+        let sizes: Vec<usize> = batches.iter().map(|b| b.request.children.len()).collect();
+        assert_eq!(sizes, vec![9, 3]);
+        let (path, rows) = &batches[1].deferred[2];
+        assert_eq!(*path, vec![2]);
+        assert_eq!(rows.children.len(), 200);
+        let small = AppendBlockRequestBuilder::new().with_block(table(50)).build().unwrap().next().unwrap();
+        assert_eq!(small.request.children[0].block_count(), 51);
+        assert!(small.deferred.is_empty());
+
+        let long = AppendBlockRequestChild::new_toggle_block(
+            vec![],
+            vec![AppendBlockRequestChild::new_paragraph_block("x".repeat(2400))],
+        );
+        let error = AppendBlockRequestBuilder::new().with_block(long).build().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "blocks Notion won't take: children[0].toggle.children[0].paragraph.rich_text[0].text.content: 2400 character(s), more than 2000"
+        );
+        let pieces = (0..101).map(|i| NotionBlock::new_text_block(i.to_string())).collect();
+        let error = AppendBlockRequestBuilder::new()
+            .with_block(AppendBlockRequestChild::new_toggle_block(pieces, vec![]))
+            .build()
+            .unwrap_err();
+        assert!(error.to_string().contains("children[0].toggle.rich_text: 101 item(s), more than 100"), "{}", error);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_append_nested() {
+        let paragraph = |text: &str| AppendBlockRequestChild::new_paragraph_block(text.to_string());
+        let toggle = |children| AppendBlockRequestChild::new_toggle_block(vec![NotionBlock::new_text_block("t".to_string())], children);
+        let table = || {
+            let row = AppendBlockRequestChild::new_table_row_block(vec![vec![NotionBlock::new_text_block("cell".to_string())]]);
+            AppendBlockRequestChild::new_table_block(1, false, false, vec![row])
+        };
+        let columns = || AppendBlockRequestChild::new_column_list_block(vec![vec![paragraph("left")], vec![paragraph("right")]]);
+        let blocks = vec![
+            toggle(vec![toggle(vec![toggle(vec![paragraph("deep")])])]),
+            toggle(vec![paragraph("before"), toggle(vec![table(), paragraph("after")]), columns(), paragraph("last")]),
+        ];
+
+        let batch = AppendBlockRequestBuilder::new().with_blocks(blocks.clone()).build().unwrap().next().unwrap();
+        let paths: Vec<&Vec<usize>> = batch.deferred.iter().map(|(p, _)| p).collect();
+        assert_eq!(paths, vec![&vec![0, 0, 0], &vec![1, 1], &vec![1]]);
+        // The column list goes with the paragraph after it, so they stay in order.
+        assert_eq!(batch.deferred[2].1.children.len(), 2);
+
+        let fake = FakeNotion::start().await;
+        let docs = fake.add_page(None, "Docs");
+        let client = fake.client(&docs, json!({}));
+        client.append_block(docs.clone(), &AppendBlockRequest::new_children(blocks)).await.unwrap();
+        fn outline(fake: &FakeNotion, id: &str, depth: usize, lines: &mut Vec<String>) {
+            for child in fake.children(id) {
+                lines.push(format!("{}{}", "  ".repeat(depth), child["type"].as_str().unwrap()));
+                outline(fake, child["id"].as_str().unwrap(), depth + 1, lines);
+            }
+        }
+        let mut lines = Vec::new();
+        outline(&fake, &docs, 0, &mut lines);
+        let expected = [
+            "toggle", "  toggle", "    toggle", "      paragraph",
+            "toggle", "  paragraph", "  toggle", "    table", "      table_row", "    paragraph",
+            "  column_list", "    column", "      paragraph", "    column", "      paragraph", "  paragraph",
+        ];
+        assert_eq!(lines, expected);
+
+        let column = AppendBlockRequestChild::new_column_list_block(vec![vec![table()], vec![paragraph("right")]]);
+        let error = AppendBlockRequestBuilder::new().with_block(column).build().unwrap_err();
+        assert!(error.to_string().starts_with("a column can't start with a table"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_append_nested_tables() {
+        let table = |rows: usize| {
+            let rows = (0..rows)
+                .map(|i| AppendBlockRequestChild::new_table_row_block(vec![vec![NotionBlock::new_text_block(i.to_string())]]))
+                .collect();
+            AppendBlockRequestChild::new_table_block(1, true, false, rows)
+        };
+        let paragraph = |text: &str| AppendBlockRequestChild::new_paragraph_block(text.to_string());
+        let paragraphs = (0..150).map(|i| paragraph(&i.to_string())).collect();
+        let blocks = vec![
+            AppendBlockRequestChild::new_toggle_block(vec![], vec![table(250), paragraph("after")]),
+            AppendBlockRequestChild::new_column_list_block(vec![vec![paragraph("left"), table(120)], paragraphs]),
+        ];
+
+        let batch = AppendBlockRequestBuilder::new().with_blocks(blocks.clone()).build().unwrap().next().unwrap();
+        let deferred: Vec<(&Vec<usize>, usize)> = batch.deferred.iter().map(|(p, r)| (p, r.children.len())).collect();
+        assert_eq!(deferred, vec![(&vec![0, 0], 150), (&vec![1, 0], 1), (&vec![1, 1], 50)]);
+
+        let fake = FakeNotion::start().await;
+        let docs = fake.add_page(None, "Docs");
+        let client = fake.client(&docs, json!({}));
+        client.append_block(docs.clone(), &AppendBlockRequest::new_children(blocks)).await.unwrap();
+        let id = |block: &serde_json::Value| block["id"].as_str().unwrap().to_string();
+        let toggle = fake.children(&docs)[0].clone();
+        let in_toggle = fake.children(&id(&toggle));
+        assert_eq!(in_toggle.len(), 2);
+        assert_eq!(fake.children(&id(&in_toggle[0])).len(), 250);
+        let columns = fake.children(&id(&fake.children(&docs)[1]));
+        let left = fake.children(&id(&columns[0]));
+        assert_eq!(left[1]["type"], "table");
+        assert_eq!(fake.children(&id(&left[1])).len(), 120);
+        assert_eq!(fake.children(&id(&columns[1])).len(), 150);
+    }
+}
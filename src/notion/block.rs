@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+use crate::notion::database::ChildDatabaseParent;
+use crate::notion::page::PageEmojiIcon;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum BlockType {
     Paragraph,
@@ -16,26 +19,53 @@ pub enum BlockType {
     Image,
     Table,
     TableRow,
+    Divider,
+    Callout,
+    Toggle,
+    ColumnList,
+    Column,
+    ChildDatabase,
+    File,
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct AppendBlockRequest {
     pub children: Vec<AppendBlockRequestChild>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+}
+
+impl Default for AppendBlockRequest {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl AppendBlockRequest {
     pub fn new() -> Self {
-        AppendBlockRequest { children: vec![] }
+        AppendBlockRequest {
+            children: vec![],
+            after: None,
+        }
     }
 
     pub fn new_child(child: AppendBlockRequestChild) -> Self {
         AppendBlockRequest {
             children: vec![child],
+            after: None,
         }
     }
 
     pub fn new_children(children: Vec<AppendBlockRequestChild>) -> Self {
-        AppendBlockRequest { children }
+        AppendBlockRequest {
+            children,
+            after: None,
+        }
+    }
+
+    pub fn with_after(mut self, block_id: String) -> Self {
+        self.after = Some(block_id);
+        self
     }
 
     pub fn append_child(&mut self, child: AppendBlockRequestChild) {
@@ -51,199 +81,342 @@ impl AppendBlockRequest {
     }
 }
 
+/// A block to append. Its JSON is Notion's: `{"object": "block", "type": "paragraph",
+/// "paragraph": {...}}`, the payload under the key of its type.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct AppendBlockRequestChild {
     pub object: String,
-    #[serde(rename = "type")]
-    pub block_type: BlockType,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub heading_1: Option<RichTextParent>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub heading_2: Option<RichTextParent>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub heading_3: Option<RichTextParent>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub paragraph: Option<RichTextParent>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub code: Option<RichTextParent>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub bulleted_list_item: Option<RichTextParent>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub numbered_list_item: Option<RichTextParent>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub image: Option<ImageParent>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub table: Option<TableParent>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub table_row: Option<TableRowParent>,
+    #[serde(flatten)]
+    pub content: BlockContent,
 }
 
-pub fn get_heading_text(
-    field_depth: u8,
-    requested_depth: u8,
-    content: String,
-) -> Option<RichTextParent> {
-    if requested_depth == field_depth || (field_depth == 3 && requested_depth > 3) {
-        Some(RichTextParent::new_text(content))
-    } else {
-        None
+/// What a block holds, by its type. Serialized as the `type` of the block, with the payload
+/// under the key of that type.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BlockContent {
+    Paragraph {
+        paragraph: RichTextParent,
+    },
+    #[serde(rename = "heading_1")]
+    Heading1 {
+        heading_1: RichTextParent,
+    },
+    #[serde(rename = "heading_2")]
+    Heading2 {
+        heading_2: RichTextParent,
+    },
+    #[serde(rename = "heading_3")]
+    Heading3 {
+        heading_3: RichTextParent,
+    },
+    Code {
+        code: RichTextParent,
+    },
+    BulletedListItem {
+        bulleted_list_item: RichTextParent,
+    },
+    NumberedListItem {
+        numbered_list_item: RichTextParent,
+    },
+    Image {
+        image: ImageParent,
+    },
+    Table {
+        table: TableParent,
+    },
+    TableRow {
+        table_row: TableRowParent,
+    },
+    Divider {
+        divider: EmptyParent,
+    },
+    Callout {
+        callout: RichTextParent,
+    },
+    Toggle {
+        toggle: RichTextParent,
+    },
+    ColumnList {
+        column_list: ContainerParent,
+    },
+    Column {
+        column: ContainerParent,
+    },
+    ChildDatabase {
+        child_database: ChildDatabaseParent,
+    },
+    File {
+        file: FileParent,
+    },
+}
+
+impl BlockContent {
+    /// A block of `block_type` holding `rich_text`, or a paragraph when blocks of that type
+    /// don't hold text.
+    pub fn from_rich_text(block_type: BlockType, rich_text: RichTextParent) -> Self {
+        match block_type {
+            BlockType::Heading1 => BlockContent::Heading1 { heading_1: rich_text },
+            BlockType::Heading2 => BlockContent::Heading2 { heading_2: rich_text },
+            BlockType::Heading3 => BlockContent::Heading3 { heading_3: rich_text },
+            BlockType::Code => BlockContent::Code { code: rich_text },
+            BlockType::BulletedListItem => BlockContent::BulletedListItem { bulleted_list_item: rich_text },
+            BlockType::NumberedListItem => BlockContent::NumberedListItem { numbered_list_item: rich_text },
+            BlockType::Callout => BlockContent::Callout { callout: rich_text },
+            BlockType::Toggle => BlockContent::Toggle { toggle: rich_text },
+            _ => BlockContent::Paragraph { paragraph: rich_text },
+        }
+    }
+
+    pub fn block_type(&self) -> BlockType {
+        match self {
+            BlockContent::Paragraph { .. } => BlockType::Paragraph,
+            BlockContent::Heading1 { .. } => BlockType::Heading1,
+            BlockContent::Heading2 { .. } => BlockType::Heading2,
+            BlockContent::Heading3 { .. } => BlockType::Heading3,
+            BlockContent::Code { .. } => BlockType::Code,
+            BlockContent::BulletedListItem { .. } => BlockType::BulletedListItem,
+            BlockContent::NumberedListItem { .. } => BlockType::NumberedListItem,
+            BlockContent::Image { .. } => BlockType::Image,
+            BlockContent::Table { .. } => BlockType::Table,
+            BlockContent::TableRow { .. } => BlockType::TableRow,
+            BlockContent::Divider { .. } => BlockType::Divider,
+            BlockContent::Callout { .. } => BlockType::Callout,
+            BlockContent::Toggle { .. } => BlockType::Toggle,
+            BlockContent::ColumnList { .. } => BlockType::ColumnList,
+            BlockContent::Column { .. } => BlockType::Column,
+            BlockContent::ChildDatabase { .. } => BlockType::ChildDatabase,
+            BlockContent::File { .. } => BlockType::File,
+        }
+    }
+}
+
+impl From<BlockContent> for AppendBlockRequestChild {
+    fn from(content: BlockContent) -> Self {
+        AppendBlockRequestChild::new(content)
     }
 }
 
 impl AppendBlockRequestChild {
-    pub fn new(block_type: BlockType) -> Self {
+    pub fn new(content: BlockContent) -> Self {
         AppendBlockRequestChild {
             object: "block".to_string(),
-            block_type,
-            heading_1: None,
-            heading_2: None,
-            heading_3: None,
-            paragraph: None,
-            code: None,
-            bulleted_list_item: None,
-            numbered_list_item: None,
-            image: None,
-            table: None,
-            table_row: None,
+            content,
         }
     }
 
-    pub fn get_rich_text_blocks(&self) -> Option<Vec<NotionBlock>>
-    {
-        if let Some(h) = &self.heading_1 {
-            Some(h.get_blocks())
-        } else if let Some(h) = &self.heading_2 {
-            Some(h.get_blocks())
-        } else if let Some(h) = &self.heading_3 {
-            Some(h.get_blocks())
-        } else if let Some(p) = &self.paragraph {
-            Some(p.get_blocks())
-        } else if let Some(c) = &self.code {
-            Some(c.get_blocks())
-        } else if let Some(b) = &self.bulleted_list_item {
-            Some(b.get_blocks())
-        } else if let Some(n) = &self.numbered_list_item {
-            Some(n.get_blocks())
-        } else {
-            None
+    pub fn block_type(&self) -> BlockType {
+        self.content.block_type()
+    }
+
+    /// The text of headings, paragraphs, list items, code, callouts and toggles.
+    pub fn rich_text_parent(&self) -> Option<&RichTextParent> {
+        match &self.content {
+            BlockContent::Paragraph { paragraph: r }
+            | BlockContent::Heading1 { heading_1: r }
+            | BlockContent::Heading2 { heading_2: r }
+            | BlockContent::Heading3 { heading_3: r }
+            | BlockContent::Code { code: r }
+            | BlockContent::BulletedListItem { bulleted_list_item: r }
+            | BlockContent::NumberedListItem { numbered_list_item: r }
+            | BlockContent::Callout { callout: r }
+            | BlockContent::Toggle { toggle: r } => Some(r),
+            _ => None,
+        }
+    }
+
+    pub fn rich_text_parent_mut(&mut self) -> Option<&mut RichTextParent> {
+        match &mut self.content {
+            BlockContent::Paragraph { paragraph: r }
+            | BlockContent::Heading1 { heading_1: r }
+            | BlockContent::Heading2 { heading_2: r }
+            | BlockContent::Heading3 { heading_3: r }
+            | BlockContent::Code { code: r }
+            | BlockContent::BulletedListItem { bulleted_list_item: r }
+            | BlockContent::NumberedListItem { numbered_list_item: r }
+            | BlockContent::Callout { callout: r }
+            | BlockContent::Toggle { toggle: r } => Some(r),
+            _ => None,
+        }
+    }
+
+    pub fn get_rich_text_blocks(&self) -> Option<Vec<NotionBlock>> {
+        self.rich_text_parent().map(|r| r.get_blocks())
+    }
+
+    pub fn paragraph(&self) -> Option<&RichTextParent> {
+        match &self.content {
+            BlockContent::Paragraph { paragraph } => Some(paragraph),
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> Option<&RichTextParent> {
+        match &self.content {
+            BlockContent::Code { code } => Some(code),
+            _ => None,
+        }
+    }
+
+    pub fn callout(&self) -> Option<&RichTextParent> {
+        match &self.content {
+            BlockContent::Callout { callout } => Some(callout),
+            _ => None,
+        }
+    }
+
+    pub fn image(&self) -> Option<&ImageParent> {
+        match &self.content {
+            BlockContent::Image { image } => Some(image),
+            _ => None,
+        }
+    }
+
+    pub fn file(&self) -> Option<&FileParent> {
+        match &self.content {
+            BlockContent::File { file } => Some(file),
+            _ => None,
+        }
+    }
+
+    pub fn table(&self) -> Option<&TableParent> {
+        match &self.content {
+            BlockContent::Table { table } => Some(table),
+            _ => None,
+        }
+    }
+
+    pub fn table_mut(&mut self) -> Option<&mut TableParent> {
+        match &mut self.content {
+            BlockContent::Table { table } => Some(table),
+            _ => None,
+        }
+    }
+
+    pub fn table_row(&self) -> Option<&TableRowParent> {
+        match &self.content {
+            BlockContent::TableRow { table_row } => Some(table_row),
+            _ => None,
+        }
+    }
+
+    pub fn column_list(&self) -> Option<&ContainerParent> {
+        match &self.content {
+            BlockContent::ColumnList { column_list } => Some(column_list),
+            _ => None,
+        }
+    }
+
+    pub fn column(&self) -> Option<&ContainerParent> {
+        match &self.content {
+            BlockContent::Column { column } => Some(column),
+            _ => None,
+        }
+    }
+
+    pub fn child_database(&self) -> Option<&ChildDatabaseParent> {
+        match &self.content {
+            BlockContent::ChildDatabase { child_database } => Some(child_database),
+            _ => None,
         }
     }
 
     pub fn new_rich_text(block_type: BlockType, rich_text: Vec<NotionBlock>) -> Self {
-        AppendBlockRequestChild::new(block_type).with_rich_text(rich_text)
+        BlockContent::from_rich_text(block_type, RichTextParent::new(rich_text)).into()
     }
 
     pub fn new_paragraph_block(content: String) -> Self {
         let formatted_content = content.replace("\n", " ");
-        AppendBlockRequestChild {
-            object: "block".to_string(),
-            block_type: BlockType::Paragraph,
-            heading_1: None,
-            heading_2: None,
-            heading_3: None,
-            paragraph: Some(RichTextParent::new_text(formatted_content)),
-            code: None,
-            bulleted_list_item: None,
-            numbered_list_item: None,
-            image: None,
-            table: None,
-            table_row: None,
+        BlockContent::Paragraph {
+            paragraph: RichTextParent::new_text(formatted_content),
         }
+        .into()
     }
 
+    /// A heading of `depth`, those past 3 becoming level 3 headings.
     pub fn new_heading_block(content: String, depth: u8) -> Self {
         let block_type = match depth {
             1 => BlockType::Heading1,
             2 => BlockType::Heading2,
-            3 => BlockType::Heading3,
             _ => BlockType::Heading3,
         };
-
-        AppendBlockRequestChild {
-            object: "block".to_string(),
-            block_type,
-            heading_1: get_heading_text(1, depth, content.clone()),
-            heading_2: get_heading_text(2, depth, content.clone()),
-            heading_3: get_heading_text(3, depth, content),
-            paragraph: None,
-            code: None,
-            bulleted_list_item: None,
-            numbered_list_item: None,
-            image: None,
-            table: None,
-            table_row: None,
-        }
+        BlockContent::from_rich_text(block_type, RichTextParent::new_text(content)).into()
     }
 
     pub fn new_code_block(content: Vec<String>, language: String) -> Self {
-        AppendBlockRequestChild {
-            object: "block".to_string(),
-            block_type: BlockType::Code,
-            heading_1: None,
-            heading_2: None,
-            heading_3: None,
-            paragraph: None,
-            code: Some(RichTextParent::new_code(content, language)),
-            bulleted_list_item: None,
-            numbered_list_item: None,
-            image: None,
-            table: None,
-            table_row: None,
+        let rich_text = content.into_iter().map(NotionBlock::new_code_block).collect();
+        AppendBlockRequestChild::new_code_block_with_caption(rich_text, language, None)
+    }
+
+    /// A code block from already built rich text, with an optional caption below it.
+    pub fn new_code_block_with_caption(
+        rich_text: Vec<NotionBlock>,
+        language: String,
+        caption: Option<String>,
+    ) -> Self {
+        let mut code = RichTextParent::new_code(rich_text, language);
+        if let Some(caption) = caption {
+            code = code.with_caption(caption);
         }
+        BlockContent::Code { code }.into()
     }
 
     pub fn new_bulleted_list_item_block(content: String) -> Self {
-        AppendBlockRequestChild {
-            object: "block".to_string(),
-            block_type: BlockType::BulletedListItem,
-            heading_1: None,
-            heading_2: None,
-            heading_3: None,
-            paragraph: None,
-            code: None,
-            bulleted_list_item: Some(RichTextParent::new_text(content)),
-            numbered_list_item: None,
-            image: None,
-            table: None,
-            table_row: None,
+        BlockContent::BulletedListItem {
+            bulleted_list_item: RichTextParent::new_text(content),
         }
+        .into()
     }
 
     pub fn new_numbered_list_item_block(content: String) -> Self {
-        AppendBlockRequestChild {
-            object: "block".to_string(),
-            block_type: BlockType::NumberedListItem,
-            heading_1: None,
-            heading_2: None,
-            heading_3: None,
-            paragraph: None,
-            code: None,
-            bulleted_list_item: None,
-            numbered_list_item: Some(RichTextParent::new_text(content)),
-            image: None,
-            table: None,
-            table_row: None,
+        BlockContent::NumberedListItem {
+            numbered_list_item: RichTextParent::new_text(content),
         }
+        .into()
     }
 
     pub fn new_external_image_block(url: String) -> Self {
-        AppendBlockRequestChild {
-            object: "block".to_string(),
-            block_type: BlockType::Image,
-            heading_1: None,
-            heading_2: None,
-            heading_3: None,
-            paragraph: None,
-            code: None,
-            bulleted_list_item: None,
-            numbered_list_item: None,
-            image: Some(ImageParent {
-                image_type: "external".to_string(),
-                external: ExternalImageInner { url },
-            }),
-            table: None,
-            table_row: None,
+        let image = ImageParent {
+            image_type: "external".to_string(),
+            external: Some(ExternalImageInner { url }),
+            file_upload: None,
+            svg_source: None,
+            caption: Vec::new(),
+        };
+        BlockContent::Image { image }.into()
+    }
+
+    /// An image rendered to PNG from the SVG at `source`, a URL or a local path, and uploaded
+    /// to Notion when the block is written.
+    pub fn new_svg_image_block(source: String) -> Self {
+        let image = ImageParent {
+            image_type: "file_upload".to_string(),
+            external: None,
+            file_upload: None,
+            svg_source: Some(source),
+            caption: Vec::new(),
+        };
+        BlockContent::Image { image }.into()
+    }
+
+    /// Captions an image or file block, leaving other blocks as they are.
+    pub fn with_caption(mut self, caption: Vec<NotionBlock>) -> Self {
+        match &mut self.content {
+            BlockContent::Image { image } => image.caption = caption,
+            BlockContent::File { file } => file.caption = caption,
+            _ => {}
         }
+        self
+    }
+
+    /// A file block downloading the file at `url`.
+    pub fn new_external_file_block(url: String) -> Self {
+        let file = FileParent {
+            file_type: "external".to_string(),
+            external: ExternalImageInner { url },
+            caption: Vec::new(),
+        };
+        BlockContent::File { file }.into()
     }
 
     pub fn new_table_block(
@@ -252,74 +425,137 @@ impl AppendBlockRequestChild {
         has_row_header: bool,
         rows: Vec<AppendBlockRequestChild>,
     ) -> Self {
-        AppendBlockRequestChild {
-            object: "block".to_string(),
-            block_type: BlockType::Table,
-            heading_1: None,
-            heading_2: None,
-            heading_3: None,
-            paragraph: None,
-            code: None,
-            bulleted_list_item: None,
-            numbered_list_item: None,
-            image: None,
-            table: Some(TableParent {
-                table_width,
-                has_column_header,
-                has_row_header,
-                children: rows,
-            }),
-            table_row: None,
+        let table = TableParent {
+            table_width,
+            has_column_header,
+            has_row_header,
+            children: rows,
+        };
+        BlockContent::Table { table }.into()
+    }
+
+    /// A row of `cells`, each the rich text of one cell, empty for an empty cell.
+    pub fn new_table_row_block(cells: Vec<Vec<NotionBlock>>) -> Self {
+        BlockContent::TableRow {
+            table_row: TableRowParent { cells },
         }
+        .into()
+    }
+
+    pub fn new_divider_block() -> Self {
+        BlockContent::Divider { divider: EmptyParent {} }.into()
+    }
+
+    /// A callout with an emoji icon, holding `children` below its text.
+    pub fn new_callout_block(
+        rich_text: Vec<NotionBlock>,
+        emoji: String,
+        color: String,
+        children: Vec<AppendBlockRequestChild>,
+    ) -> Self {
+        let callout = RichTextParent::new(rich_text)
+            .with_icon(emoji)
+            .with_color(color)
+            .with_children(children);
+        BlockContent::Callout { callout }.into()
     }
 
-    pub fn new_table_row_block(cells: Vec<NotionBlock>) -> Self {
-        let mut formatted_cells = Vec::new();
-        for c in cells {
-            formatted_cells.push(vec![c]);
+    pub fn new_toggle_block(rich_text: Vec<NotionBlock>, children: Vec<AppendBlockRequestChild>) -> Self {
+        BlockContent::Toggle {
+            toggle: RichTextParent::new(rich_text).with_children(children),
         }
-        AppendBlockRequestChild {
-            object: "block".to_string(),
-            block_type: BlockType::TableRow,
-            heading_1: None,
-            heading_2: None,
-            heading_3: None,
-            paragraph: None,
-            code: None,
-            bulleted_list_item: None,
-            numbered_list_item: None,
-            image: None,
-            table: None,
-            table_row: Some(TableRowParent { cells: formatted_cells }),
+        .into()
+    }
+
+    /// Side by side columns, each holding its blocks.
+    pub fn new_column_list_block(columns: Vec<Vec<AppendBlockRequestChild>>) -> Self {
+        let columns = columns
+            .into_iter()
+            .map(AppendBlockRequestChild::new_column_block)
+            .collect();
+        BlockContent::ColumnList {
+            column_list: ContainerParent { children: columns },
         }
+        .into()
     }
 
-    pub fn with_rich_text(mut self, rich_text: Vec<NotionBlock>) -> Self {
-        match self.block_type {
-            BlockType::NumberedListItem => {
-                self.numbered_list_item = Some(RichTextParent::new(rich_text));
-            }
-            BlockType::BulletedListItem => {
-                self.bulleted_list_item = Some(RichTextParent::new(rich_text));
-            }
-            BlockType::Paragraph => {
-                self.paragraph = Some(RichTextParent::new(rich_text));
-            }
-            BlockType::Heading1 => {
-                self.heading_1 = Some(RichTextParent::new(rich_text));
-            }
-            BlockType::Heading2 => {
-                self.heading_2 = Some(RichTextParent::new(rich_text));
+    /// One column, only valid inside a column list.
+    pub fn new_column_block(children: Vec<AppendBlockRequestChild>) -> Self {
+        BlockContent::Column {
+            column: ContainerParent { children },
+        }
+        .into()
+    }
+
+    /// The blocks nested in this one.
+    pub fn nested(&self) -> &[AppendBlockRequestChild] {
+        match &self.content {
+            BlockContent::Table { table } => &table.children,
+            BlockContent::ColumnList { column_list: c } | BlockContent::Column { column: c } => &c.children,
+            _ => self
+                .rich_text_parent()
+                .and_then(|r| r.children.as_deref())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// How many blocks this one counts for in a request: itself and the blocks nested in it.
+    pub fn block_count(&self) -> usize {
+        1 + self.nested().iter().map(|c| c.block_count()).sum::<usize>()
+    }
+
+    /// Nests `children` in this block, for the blocks that hold some. Returns whether it could.
+    pub fn set_children(&mut self, children: Vec<AppendBlockRequestChild>) -> bool {
+        match &mut self.content {
+            BlockContent::Table { table } => table.children = children,
+            BlockContent::ColumnList { column_list: c } | BlockContent::Column { column: c } => {
+                c.children = children
             }
-            BlockType::Heading3 => {
-                self.heading_3 = Some(RichTextParent::new(rich_text));
+            _ => return self.rich_text_parent_mut().map(|r| r.children = Some(children)).is_some(),
+        }
+        true
+    }
+
+    /// The images in this block, or nested in it, whose SVG is still to be uploaded.
+    pub fn pending_svgs(&mut self) -> Vec<&mut ImageParent> {
+        let nested = match &mut self.content {
+            BlockContent::Image { image } => {
+                return image.svg_source.is_some().then_some(image).into_iter().collect();
             }
-            BlockType::Code => {
-                self.code = Some(RichTextParent::new(rich_text));
+            BlockContent::Callout { callout: r } | BlockContent::Toggle { toggle: r } => r.children.as_mut(),
+            BlockContent::ColumnList { column_list: c } | BlockContent::Column { column: c } => {
+                Some(&mut c.children)
             }
-            _ => {}
+            _ => None,
+        };
+        nested
+            .into_iter()
+            .flatten()
+            .flat_map(|c| c.pending_svgs())
+            .collect()
+    }
+
+    /// Takes the rows of a table past the first `max`, which have to be appended to the table
+    /// once it exists.
+    pub fn split_off_rows(&mut self, max: usize) -> Vec<AppendBlockRequestChild> {
+        match self.table_mut() {
+            Some(t) if t.children.len() > max => t.children.split_off(max),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn new_child_database_block(database: ChildDatabaseParent) -> Self {
+        BlockContent::ChildDatabase {
+            child_database: database,
         }
+        .into()
+    }
 
+    /// Replaces the text of a block holding some, leaving other blocks as they are.
+    pub fn with_rich_text(mut self, rich_text: Vec<NotionBlock>) -> Self {
+        if let Some(r) = self.rich_text_parent_mut() {
+            r.rich_text = rich_text;
+        }
         self
     }
 }
@@ -329,13 +565,57 @@ pub struct RichTextParent {
     pub rich_text: Vec<NotionBlock>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub language: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub caption: Option<Vec<NotionBlock>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon: Option<PageEmojiIcon>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// Nested blocks, for the blocks that hold some, like callouts and toggles.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub children: Option<Vec<AppendBlockRequestChild>>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ImageParent {
     #[serde(rename = "type")]
     pub image_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external: Option<ExternalImageInner>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_upload: Option<FileUploadInner>,
+    /// The SVG, a URL or a local path, still to be rendered and uploaded in place of this
+    /// image, see [`AppendBlockRequestChild::new_svg_image_block`]. Written to exported
+    /// requests, so that importing one uploads it, and gone once it is uploaded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub svg_source: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub caption: Vec<NotionBlock>,
+}
+
+impl ImageParent {
+    /// Where the image is shipped from, the SVG for one still to be uploaded.
+    pub fn url(&self) -> Option<&str> {
+        self.external
+            .as_ref()
+            .map(|e| e.url.as_str())
+            .or(self.svg_source.as_deref())
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FileParent {
+    #[serde(rename = "type")]
+    pub file_type: String,
     pub external: ExternalImageInner,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub caption: Vec<NotionBlock>,
+}
+
+/// A file uploaded to Notion, by the id of its upload.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FileUploadInner {
+    pub id: String,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -343,6 +623,8 @@ pub struct TableParent {
     pub table_width: usize,
     pub has_column_header: bool,
     pub has_row_header: bool,
+    /// Only set when appending, Notion lists a table's rows as its children.
+    #[serde(default)]
     pub children: Vec<AppendBlockRequestChild>,
 }
 
@@ -351,11 +633,27 @@ pub struct TableRowParent {
     pub cells: Vec<Vec<NotionBlock>>,
 }
 
+/// The payload of blocks that only hold other blocks, like columns.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ContainerParent {
+    /// Only set when appending, Notion lists them as the block's children.
+    #[serde(default)]
+    pub children: Vec<AppendBlockRequestChild>,
+}
+
+/// The payload of blocks that carry no content, like dividers, which Notion expects as `{}`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EmptyParent {}
+
 impl RichTextParent {
     pub fn new(rich_text: Vec<NotionBlock>) -> Self {
         RichTextParent {
             rich_text,
             language: None,
+            caption: None,
+            icon: None,
+            color: None,
+            children: None,
         }
     }
 
@@ -363,21 +661,44 @@ impl RichTextParent {
         RichTextParent {
             rich_text: vec![NotionBlock::new_text_block(content)],
             language: None,
+            caption: None,
+            icon: None,
+            color: None,
+            children: None,
         }
     }
 
-    pub fn new_code(content: Vec<String>, language: String) -> Self {
-        let mut rich_text = Vec::new();
-        for c in content {
-            rich_text.push(NotionBlock::new_code_block(c))
-        }
-
+    pub fn new_code(rich_text: Vec<NotionBlock>, language: String) -> Self {
         RichTextParent {
             rich_text,
             language: Some(language),
+            caption: None,
+            icon: None,
+            color: None,
+            children: None,
         }
     }
 
+    pub fn with_caption(mut self, caption: String) -> Self {
+        self.caption = Some(vec![NotionBlock::new_text_block(caption)]);
+        self
+    }
+
+    pub fn with_icon(mut self, emoji: String) -> Self {
+        self.icon = Some(PageEmojiIcon::new(emoji));
+        self
+    }
+
+    pub fn with_color(mut self, color: String) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    pub fn with_children(mut self, children: Vec<AppendBlockRequestChild>) -> Self {
+        self.children = Some(children);
+        self
+    }
+
     pub fn get_blocks(&self) -> Vec<NotionBlock>
     {
         self.rich_text.clone()
@@ -411,7 +732,7 @@ pub struct TextLink {
     pub url: String,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TextAnnotations {
     pub bold: bool,
     pub italic: bool,
@@ -421,10 +742,18 @@ pub struct TextAnnotations {
     pub color: String,
 }
 
+impl Default for TextAnnotations {
+    fn default() -> Self {
+        TextAnnotations::new()
+    }
+}
+
+/// Annotations are built up one at a time, so nested formatting composes, like
+/// `TextAnnotations::new().bold().code()` for bold inline code.
 impl TextAnnotations {
-    pub fn bold() -> Self {
+    pub fn new() -> Self {
         TextAnnotations {
-            bold: true,
+            bold: false,
             italic: false,
             strikethrough: false,
             underline: false,
@@ -433,15 +762,39 @@ impl TextAnnotations {
         }
     }
 
-    pub fn code() -> Self {
-        TextAnnotations {
-            bold: false,
-            italic: false,
-            strikethrough: false,
-            underline: false,
-            code: true,
-            color: "default".to_string(),
-        }
+    pub fn bold(mut self) -> Self {
+        self.bold = true;
+        self
+    }
+
+    pub fn italic(mut self) -> Self {
+        self.italic = true;
+        self
+    }
+
+    pub fn strikethrough(mut self) -> Self {
+        self.strikethrough = true;
+        self
+    }
+
+    pub fn underline(mut self) -> Self {
+        self.underline = true;
+        self
+    }
+
+    pub fn code(mut self) -> Self {
+        self.code = true;
+        self
+    }
+
+    pub fn color(mut self, color: &str) -> Self {
+        self.color = color.to_string();
+        self
+    }
+
+    /// Whether these are Notion's defaults, which text doesn't need to carry.
+    pub fn is_plain(&self) -> bool {
+        *self == TextAnnotations::new()
     }
 }
 
@@ -484,3 +837,66 @@ impl NotionBlock {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::notion::block::{AppendBlockRequestChild, BlockType, NotionBlock};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_large_tables() {
+        let rows = (0..250)
+            .map(|i| AppendBlockRequestChild::new_table_row_block(vec![vec![NotionBlock::new_text_block(i.to_string())]]))
+            .collect();
+        let mut table = AppendBlockRequestChild::new_table_block(1, true, true, rows);
+        assert_eq!(table.block_count(), 251);
+        let rest = table.split_off_rows(100);
+        assert_eq!(rest.len(), 150);
+        assert_eq!(table.block_count(), 101);
+        assert!(table.split_off_rows(100).is_empty());
+
+        let toggle = AppendBlockRequestChild::new_toggle_block(vec![], vec![table]);
+        assert_eq!(toggle.block_count(), 102);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_block_json() {
+        let heading = AppendBlockRequestChild::new_heading_block("Setup".to_string(), 5);
+        assert_eq!(heading.block_type(), BlockType::Heading3);
+        let callout = AppendBlockRequestChild::new_callout_block(
+            vec![NotionBlock::new_text_block("Careful".to_string())],
+            "⚠️".to_string(),
+            "yellow_background".to_string(),
+            vec![AppendBlockRequestChild::new_divider_block()],
+        );
+        let text = serde_json::to_string(&callout).unwrap();
+        assert!(text.starts_with(r#"{"object":"block","type":"callout","callout":"#));
+        let json = serde_json::to_value(vec![heading, callout]).unwrap();
+        assert_eq!(
+            json,
+            json!([
+                {
+                    "object": "block",
+                    "type": "heading_3",
+                    "heading_3": { "rich_text": [{ "type": "text", "text": { "content": "Setup" } }] },
+                },
+                {
+                    "object": "block",
+                    "type": "callout",
+                    "callout": {
+                        "rich_text": [{ "type": "text", "text": { "content": "Careful" } }],
+                        "icon": { "type": "emoji", "emoji": "⚠️" },
+                        "color": "yellow_background",
+                        "children": [{ "object": "block", "type": "divider", "divider": {} }],
+                    },
+                },
+            ])
+        );
+
+        let blocks: Vec<AppendBlockRequestChild> = serde_json::from_value(json).unwrap();
+        assert_eq!(blocks[1].callout().unwrap().children.as_ref().unwrap().len(), 1);
+        let mismatched = json!({ "object": "block", "type": "paragraph", "heading_1": { "rich_text": [] } });
+        assert!(serde_json::from_value::<AppendBlockRequestChild>(mismatched).is_err());
+    }
+}
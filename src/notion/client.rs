@@ -1,59 +1,295 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use anyhow::anyhow;
 use anyhow::Result;
-use glob::glob;
-use reqwest::{Client, ClientBuilder, header, StatusCode};
+use chrono::{DateTime, DurationRound, SecondsFormat, TimeDelta, Utc};
+use reqwest::{Client, ClientBuilder, header, RequestBuilder, Response, StatusCode};
 use reqwest::header::{HeaderMap, HeaderValue};
-use serde_json::{json, to_string, Value};
+use reqwest::multipart::{Form, Part};
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::watch;
 
-use crate::generate_random_string;
-use crate::markdown::parse::{get_md_glob_pattern, NotationDocArguments, parse_file};
-use crate::notion::block::AppendBlockRequest;
+use crate::redact::{register_secret, RedactResult};
+use crate::markdown::anchors::{links_to_headings, parse_anchor_url, Slugger};
+use crate::markdown::directive::{DirectiveBuilder, DirectiveRegistry};
+use crate::markdown::source::source_extensions;
+use crate::markdown::svg::render_png;
+use crate::markdown::diagnostic::Diagnostic;
+use crate::markdown::parse::{ConversionOptions, NotationParseResult, page_key, parse_file, plain_path, pretty_title};
+use crate::notion::export::ExportPlan;
+use crate::notion::clock::{ShipClock, SystemClock};
+use crate::notion::footer::Footer;
+use crate::notion::append::AppendBlockRequestBuilder;
+use crate::notion::block::{
+    AppendBlockRequest, AppendBlockRequestChild, BlockContent, BlockType, FileUploadInner, NotionBlock,
+};
+use crate::notion::database::ChildDatabaseParent;
+use crate::notion::error::ApiError;
+use crate::notion::index::{IndexedPage, PageIndex};
+use crate::notion::parallel::for_each_page;
+use crate::notion::parent::{is_under, object_title, parent_path, pick_parent, AmbiguousParentError, ParentCandidate};
 use crate::notion::page::{
-    CreatePageRequest, CreatePageResponse, GetPageContentResponse, PageContentType,
+    BlockChildrenPage, CreatePageRequest, CreatePageResponse, GetPageContentResponse, PageContentResult,
+    PageContentType, PageCover, PageResponse, date_property, day_property, multi_select_property, rich_text_property, select_property, UpdatePageRequest,
 };
-use crate::notion::search::{SearchRequest, SearchResult, SearchResultItem};
-use crate::settings::notation::{NotationSettings};
+use crate::notion::schema::{append_problems, create_page_problems, request_warnings};
+use crate::notion::search::{SearchIter, SearchRequest, SearchResult, SearchResultItem};
+use crate::notion::summary::{PageStatus, ShipProgress, ShipSummary, ShippedPage};
+use crate::notion::transform::{BlockTransform, DocContext, TransformPipeline};
+use crate::notion::tree::ClearPlan;
+use crate::notion::usage::{ApiCounters, ApiUsage};
+use crate::notion::user::UserResponse;
+use crate::settings::directory::{DirectorySettingsTree, SourceTree};
+use crate::settings::notation::{Destination, Locale, NotationSettings, TitleStrategy, UpdatedFrom};
+use crate::sync::git::{changed_since, head_commit, last_commit_time};
+use crate::sync::state::{content_hash, SyncState, TrackedPage};
+use crate::sync::status::{is_edited_after, ConflictError, ConflictPolicy};
 
 #[derive(Clone)]
 pub struct NotionClient {
     client: Client,
     base_endpoint: String,
     parent_page_name: String,
+    settings: NotationSettings,
+    transforms: TransformPipeline,
+    directives: DirectiveRegistry,
+    api: Arc<ApiCounters>,
+    stop: Option<watch::Receiver<bool>>,
+    sections: Vec<String>,
+    locale: Option<String>,
+    clock: Arc<dyn ShipClock>,
 }
 
 const NOTION_VERSION: &str = "2022-06-28";
 const NOTION_BASE_URL: &str = "https://api.notion.com/v1";
 const INTRO_FILENAME: &str = "intro";
+const MAX_ATTEMPTS: u32 = 4;
+/// How many pages above a candidate parent page are fetched to tell it apart from the others.
+const MAX_BREADCRUMB_DEPTH: usize = 8;
 
 impl NotionClient {
     pub fn new() -> Result<Self> {
-        let settings = NotationSettings::new()?;
+        Self::from_settings(NotationSettings::new()?)
+    }
+
+    pub fn from_settings(settings: NotationSettings) -> Result<Self> {
+        Ok(NotionClient {
+            client: Self::http_client(&settings.notion.secret)?,
+            base_endpoint: NOTION_BASE_URL.to_string(),
+            parent_page_name: settings.notion.parent_page.clone(),
+            settings,
+            transforms: TransformPipeline::default(),
+            directives: DirectiveRegistry::default(),
+            api: Arc::new(ApiCounters::default()),
+            stop: None,
+            sections: Vec::new(),
+            locale: None,
+            clock: Arc::new(SystemClock),
+        })
+    }
+
+    /// A client shipping to `destination`, with the same transforms and directives.
+    pub fn for_destination(&self, destination: &Destination) -> Result<Self> {
+        let settings = self.settings.for_destination(destination);
+        Ok(NotionClient {
+            client: Self::http_client(&settings.notion.secret)?,
+            parent_page_name: settings.notion.parent_page.clone(),
+            settings,
+            ..self.clone()
+        })
+    }
+
+    /// A client shipping the docs of `locale`, whose links stay within the locale.
+    pub fn for_locale(&self, locale: &Locale) -> Result<Self> {
+        Ok(NotionClient {
+            locale: Some(locale.name.clone()),
+            ..self.for_destination(&locale.destination())?
+        })
+    }
+
+    fn http_client(secret: &str) -> Result<Client> {
+        register_secret(secret);
         let mut headers = HeaderMap::new();
         headers.insert("Notion-Version", HeaderValue::from_static(NOTION_VERSION));
         let mut auth_value =
-            HeaderValue::from_str(&format!("Bearer {}", settings.notion.secret.clone()))
-                .map_err(|e| anyhow!(e))?;
+            HeaderValue::from_str(&format!("Bearer {}", secret)).map_err(|e| anyhow!(e))?;
         auth_value.set_sensitive(true);
         headers.insert(header::AUTHORIZATION, auth_value);
-        let client = ClientBuilder::new()
+        ClientBuilder::new()
             .default_headers(headers)
             .build()
-            .map_err(|e| anyhow!(e))?;
+            .map_err(|e| anyhow!(e))
+    }
 
-        Ok(NotionClient {
-            client,
-            base_endpoint: NOTION_BASE_URL.to_string(),
-            parent_page_name: settings.notion.parent_page.clone(),
-        })
+    /// Registers a transform run on the blocks of every shipped file, after conversion and
+    /// before the footer is added and the blocks are sent.
+    pub fn with_transform(mut self, transform: impl BlockTransform + 'static) -> Self {
+        self.transforms.push(transform);
+        self
+    }
+
+    /// Registers a builder for `:::name` blocks, on top of the built-in directives.
+    pub fn with_directive(mut self, name: &str, builder: impl DirectiveBuilder + 'static) -> Self {
+        self.directives.register(name, builder);
+        self
+    }
+
+    /// Stops the ships of this client once `stop` turns true: the requests under way finish,
+    /// no new ones are sent, and what was shipped so far is kept in the sync state. The files
+    /// left are in [`ShipSummary::interrupted`].
+    pub fn with_stop(mut self, stop: watch::Receiver<bool>) -> Self {
+        self.stop = Some(stop);
+        self
+    }
+
+    /// Ships only the top level sections of every file under these headings, and what
+    /// comes before the first section, over the `sections:` of the frontmatter.
+    pub fn with_sections(mut self, sections: Vec<String>) -> Self {
+        self.sections = sections;
+        self
+    }
+
+    /// Sends the requests to `base_endpoint` instead of the Notion API, like a proxy in front
+    /// of it.
+    pub fn with_base_endpoint(mut self, base_endpoint: String) -> Self {
+        self.base_endpoint = base_endpoint;
+        self
+    }
+
+    /// Takes the time stamped on the shipped pages, and the ids of the pages of simulated
+    /// ships, from `clock`, to pin them in tests.
+    pub fn with_clock(mut self, clock: impl ShipClock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// The request creating a page of a ship, with the `cover` URL, or else the cover `[cover]`
+    /// gives it. With `row`, the page is a row of the `[database]` with those properties
+    /// instead.
+    fn ship_page_request(
+        &self,
+        parent_id: String,
+        title: String,
+        emoji: Option<String>,
+        cover: Option<String>,
+        row: Option<Vec<(String, Value)>>,
+    ) -> CreatePageRequest {
+        let mut request = match (row, &self.settings.database.id) {
+            (Some(properties), Some(database_id)) => properties
+                .into_iter()
+                .fold(CreatePageRequest::new_row(database_id.clone(), title.clone()), |r, (name, value)| {
+                    r.with_property(name, value)
+                }),
+            _ => CreatePageRequest::new(parent_id, title.clone()),
+        };
+        if let Some(emoji) = emoji {
+            request = request.with_icon(emoji);
+        }
+        match cover.or_else(|| self.settings.cover.url_for(&title)) {
+            Some(url) => request.with_cover(PageCover::new(url)),
+            None => request,
+        }
+    }
+
+    /// The request updating a page of an earlier ship, like [`Self::ship_page_request`].
+    fn ship_update_request(
+        &self,
+        title: String,
+        emoji: Option<String>,
+        cover: Option<String>,
+        row: Option<Vec<(String, Value)>>,
+    ) -> UpdatePageRequest {
+        let mut request = UpdatePageRequest::new(title.clone());
+        for (name, value) in row.unwrap_or_default() {
+            request = request.with_property(name, value);
+        }
+        if let Some(emoji) = emoji {
+            request = request.with_icon(emoji);
+        }
+        match cover.or_else(|| self.settings.cover.url_for(&title)) {
+            Some(url) => request.with_cover(PageCover::new(url)),
+            None => request,
+        }
+    }
+
+    /// The properties of the page of a ship created right under `root_page_id` from `parsed`,
+    /// last updated at `updated`, as a row of the `[database]`, or none when there is no
+    /// database or it isn't created there. The `description` given wins over the excerpt.
+    fn row_properties(
+        &self,
+        parent_id: &str,
+        root_page_id: &str,
+        description: Option<&str>,
+        parsed: Option<&NotationParseResult>,
+        updated: Option<DateTime<Utc>>,
+    ) -> Result<Option<Vec<(String, Value)>>> {
+        let database = &self.settings.database;
+        if database.id.is_none() || parent_id != root_page_id {
+            return Ok(None);
+        }
+        let mut properties = Vec::new();
+        if let Some(property) = &database.description_property {
+            let description = match (description, parsed) {
+                (Some(d), _) => Some(d.to_string()),
+                (None, Some(parsed)) => parsed.excerpt(database.description_length, &self.settings.redact)?,
+                (None, None) => None,
+            };
+            if let Some(description) = description {
+                properties.push((property.clone(), rich_text_property(&description)));
+            }
+        }
+        if let (Some(property), Some(parsed)) = (&database.tags_property, parsed) {
+            properties.push((property.clone(), multi_select_property(&parsed.tags(database.hashtags))));
+        }
+        if let (Some(property), Some(updated)) = (&database.updated_property, updated) {
+            properties.push((property.clone(), date_property(updated)));
+        }
+        if let (Some(property), Some(version)) = (&database.version_property, parsed.and_then(|p| p.version())) {
+            properties.push((property.clone(), rich_text_property(&version)));
+        }
+        if let (Some(property), Some(date)) = (&database.date_property, parsed.and_then(|p| p.date())) {
+            properties.push((property.clone(), day_property(date)));
+        }
+        if let (Some(property), Some(status)) = (&database.status_property, parsed.and_then(|p| p.status())) {
+            properties.push((property.clone(), select_property(&status)));
+        }
+        Ok(Some(properties))
+    }
+
+    fn is_stopping(&self) -> bool {
+        self.stop.as_ref().is_some_and(|s| *s.borrow())
     }
 
     pub fn parent_page_name(&self) -> String {
         self.parent_page_name.clone()
     }
 
+    pub fn settings(&self) -> &NotationSettings {
+        &self.settings
+    }
+
+    /// The requests sent so far by this client, and by the clients it was cloned from or to.
+    pub fn api_usage(&self) -> ApiUsage {
+        self.api.usage()
+    }
+
+    pub async fn get_current_user(&self) -> Result<UserResponse> {
+        let url = format!("{}/users/me", self.base_endpoint);
+        let r = self.send(self.client.get(&url)).await?;
+        let status = r.status();
+        if status != StatusCode::OK {
+            return Err(ApiError::read(r, "failed to get the current user").await.into());
+        }
+        let response: UserResponse = r.json().await.redacted()?;
+        Ok(response)
+    }
+
     pub async fn create_page_by_parent_name(
         &self,
         parent_name: String,
@@ -71,39 +307,157 @@ impl NotionClient {
         page_name: String,
         emoji: Option<String>,
     ) -> Result<String> {
-        let url = format!("{}/pages", self.base_endpoint);
         let mut create_page_request = CreatePageRequest::new(parent_id, page_name);
         if let Some(emoji) = emoji {
             create_page_request = create_page_request.with_icon(emoji);
         }
+        self.create_page(&create_page_request).await
+    }
 
-        let response = self
-            .client
-            .post(&url)
-            .json(&create_page_request)
-            .send()
-            .await?;
-        let parsed_response: CreatePageResponse = response.json().await?;
+    /// Creates the page of `create_page_request`, returning its id.
+    pub async fn create_page(&self, create_page_request: &CreatePageRequest) -> Result<String> {
+        create_page_request.validate()?;
+        let url = format!("{}/pages", self.base_endpoint);
+        let parent_id = create_page_request.parent_id();
+        let page_name = create_page_request.title();
+
+        // Sent again only once it is sure the page wasn't created, so that a retry doesn't
+        // create it twice.
+        let builder = self.client.post(&url).json(create_page_request);
+        let started = Utc::now();
+        let mut delay = Duration::from_millis(500);
+        let mut attempt = 0;
+        let response = loop {
+            attempt += 1;
+            match self.attempt(&builder, attempt == MAX_ATTEMPTS).await? {
+                Attempt::Done(r) => break r,
+                Attempt::Retry { after, may_have_landed } => {
+                    self.back_off(after.unwrap_or(delay)).await;
+                    if may_have_landed && !create_page_request.is_row() {
+                        if let Some(id) = self.find_created_page(parent_id, &page_name, started).await? {
+                            return Ok(id);
+                        }
+                    }
+                }
+            }
+            delay *= 2;
+        };
+        if response.status() != StatusCode::OK {
+            return Err(ApiError::read(response, "failed to create page").await.into());
+        }
+        let parsed_response: CreatePageResponse = response.json().await.redacted()?;
 
         Ok(parsed_response.id.clone())
     }
 
+    /// The id of the page titled `parent_name`, or found by its path like `Engineering/Docs`.
+    /// Among pages sharing the title, the one under the pages of the path is picked, then a
+    /// single one at the top of the workspace. Fails with an [`AmbiguousParentError`] when
+    /// that leaves more than one.
     pub async fn get_parent_id_by_name(&self, parent_name: String) -> Result<String> {
-        let search_result = self.find_page_by_name(parent_name).await?;
-        if search_result.len() != 1 {
-            let result_urls = search_result
-                .iter()
-                .map(|r| r.url.clone())
-                .collect::<Vec<String>>()
-                .join(", ");
+        let mut search_result = self.find_page_by_name(parent_name.clone()).await?;
+        let mut ancestors = Vec::new();
+        if search_result.is_empty() && parent_name.contains('/') {
+            let (title, path) = parent_path(&parent_name);
+            search_result = self.find_page_by_name(title).await?;
+            ancestors = path;
+        }
+        if search_result.is_empty() {
+            return Err(anyhow!("need to match exactly one parent page, found 0 results"));
+        }
+        if search_result.len() == 1 && ancestors.is_empty() {
+            return Ok(search_result[0].id.clone());
+        }
+        let mut candidates = Vec::new();
+        for r in search_result.iter() {
+            let parent = serde_json::to_value(&r.parent)?;
+            candidates.push(ParentCandidate {
+                id: r.id.clone(),
+                url: r.url.clone(),
+                title: r.properties.title.title.iter().map(|t| t.plain_text.as_str()).collect(),
+                is_top_level: r.parent.parent_type.as_deref() == Some("workspace"),
+                ancestors: self.ancestor_titles(&parent, ancestors.len()).await?,
+            });
+        }
+        if let Some(c) = pick_parent(&candidates, &ancestors) {
+            return Ok(c.id.clone());
+        }
+        // Which page is which only shows with all the pages above them.
+        let mut ambiguous = Vec::new();
+        for (r, mut c) in search_result.iter().zip(candidates) {
+            if is_under(&c, &ancestors) {
+                c.ancestors = self
+                    .ancestor_titles(&serde_json::to_value(&r.parent)?, MAX_BREADCRUMB_DEPTH)
+                    .await?;
+                ambiguous.push(c);
+            }
+        }
+        if ambiguous.is_empty() {
             return Err(anyhow!(
-                "need to match exactly one parent page, found {} results ({})",
-                search_result.len(),
-                result_urls
+                "need to match exactly one parent page, found no page at the path \"{}\"",
+                parent_name
             ));
         }
-        let parent_id = search_result[0].id.clone();
-        Ok(parent_id)
+        Err(AmbiguousParentError {
+            name: parent_name,
+            candidates: ambiguous,
+        }
+        .into())
+    }
+
+    /// The id of the parent page: `[notion] parent_page_id`, or the page found by the name of
+    /// `[notion] parent_page`.
+    pub async fn parent_page_id(&self) -> Result<String> {
+        match &self.settings.notion.parent_page_id {
+            Some(id) => Ok(id.clone()),
+            None => self.get_parent_id_by_name(self.parent_page_name.clone()).await,
+        }
+    }
+
+    /// Ships under the page `parent_page_id` instead of looking up `[notion] parent_page`.
+    pub fn with_parent_page_id(mut self, parent_page_id: String) -> Self {
+        self.settings.notion.parent_page_id = Some(parent_page_id);
+        self
+    }
+
+    /// The titles of the pages and databases above `parent`, a `parent` object of the API,
+    /// closest first and at most `depth` of them. They stop at the first page the integration
+    /// can't see.
+    async fn ancestor_titles(&self, parent: &Value, depth: usize) -> Result<Vec<String>> {
+        let index = self.page_index()?;
+        let mut titles = Vec::new();
+        let mut parent = parent.clone();
+        while titles.len() < depth {
+            let kind = match parent["type"].as_str() {
+                Some("page_id") => "pages",
+                Some("database_id") => "databases",
+                Some("block_id") => "blocks",
+                _ => break,
+            };
+            let id = match parent[parent["type"].as_str().unwrap_or_default()].as_str() {
+                Some(id) => id.to_string(),
+                None => break,
+            };
+            if let Some(page) = index.as_ref().and_then(|i| i.get(&id)) {
+                titles.push(page.title.clone());
+                parent = serde_json::to_value(&page.parent)?;
+                continue;
+            }
+            let url = format!("{}/{}/{}", self.base_endpoint, kind, id);
+            let r = self.send(self.client.get(&url)).await?;
+            if matches!(r.status(), StatusCode::FORBIDDEN | StatusCode::NOT_FOUND) {
+                break;
+            }
+            if r.status() != StatusCode::OK {
+                return Err(ApiError::read(r, "failed to get the pages above the parent page").await.into());
+            }
+            let object: Value = r.json().await.redacted()?;
+            if let Some(title) = object_title(&object) {
+                titles.push(title);
+            }
+            parent = object["parent"].clone();
+        }
+        Ok(titles)
     }
 
     pub async fn delete(&self, resource_id: String, resource_type: &PageContentType) -> Result<()> {
@@ -114,7 +468,7 @@ impl NotionClient {
         let archive_body = json!({
             "in_trash": true,
         });
-        self.client.patch(&url).json(&archive_body).send().await?;
+        self.send(self.client.patch(&url).json(&archive_body)).await?;
         Ok(())
     }
 
@@ -122,25 +476,337 @@ impl NotionClient {
         &self,
         page_or_block_id: String,
         request: &AppendBlockRequest,
-    ) -> Result<()> {
+    ) -> Result<GetPageContentResponse> {
         let url = format!(
             "{}/blocks/{}/children",
             self.base_endpoint, page_or_block_id
         );
-        let r = self.client.patch(&url).json(request).send().await?;
+        // Longer requests are sent in batches, each placed after the last block of the one
+        // before. Children that didn't fit, nested too deep or past the first 100 rows of a
+        // table, are appended to their block afterwards.
+        let mut combined: Option<GetPageContentResponse> = None;
+        let mut after = request.after.clone();
+        for mut batch in AppendBlockRequestBuilder::from(request.clone()).build()? {
+            batch.request.after = after.clone();
+            let response = self.send_append(&url, &batch.request).await?;
+            for (path, deferred) in batch.deferred {
+                let block_id = self.nested_block_id(&response, &path).await?;
+                Box::pin(self.append_block(block_id, &deferred)).await?;
+            }
+            if after.is_some() {
+                after = response.results.get(batch.request.children.len() - 1).map(|b| b.id.clone());
+            }
+            match combined.as_mut() {
+                Some(c) => c.results.extend(response.results),
+                None => combined = Some(response),
+            }
+        }
+        Ok(combined.unwrap_or(GetPageContentResponse {
+            results: vec![],
+            has_more: false,
+            next_cursor: None,
+        }))
+    }
+
+    /// The id of the block appended by `response` at `path`, its index among the appended
+    /// blocks, then among the children of each block down to it.
+    async fn nested_block_id(&self, response: &GetPageContentResponse, path: &[usize]) -> Result<String> {
+        let missing = || anyhow!("failed to append nested blocks, the block to hold them was not created");
+        let mut block_id = response.results.get(path[0]).map(|b| b.id.clone()).ok_or_else(missing)?;
+        for i in path[1..].iter() {
+            let children = self.get_all_block_children::<Value>(&block_id).await?;
+            block_id = children
+                .get(*i)
+                .and_then(|c| c["id"].as_str())
+                .map(|id| id.to_string())
+                .ok_or_else(missing)?;
+        }
+        Ok(block_id)
+    }
+
+    async fn send_append(&self, url: &str, batch: &AppendBlockRequest) -> Result<GetPageContentResponse> {
+        let r = self.send(self.client.patch(url).json(batch)).await?;
         let status = r.status();
         if status != StatusCode::OK {
-            let response: Value = r.json().await?;
-            return Err(anyhow!(
-                "(request_status={}) failed to append block: {}",
-                status,
-                to_string(&response)?
-            ));
+            return Err(ApiError::read(r, "failed to append block").await.into());
+        }
+        let response: GetPageContentResponse = r.json().await.redacted()?;
+        Ok(response)
+    }
+
+    /// Appends blocks like [`NotionClient::append_block`], creating the inline databases among
+    /// them on their own. Notion puts a new database at the end of the page whatever `after`
+    /// says, so the blocks after one follow it there, and only keep their place when nothing
+    /// else is below `after`, see [`databases_below_subpages`].
+    pub async fn append_content(&self, page_id: &str, mut request: AppendBlockRequest) -> Result<()> {
+        self.upload_svgs(&mut request.children).await?;
+        let mut after = request.after;
+        let mut blocks = Vec::new();
+        for child in request.children {
+            let database = match child.content {
+                BlockContent::ChildDatabase { child_database } => child_database,
+                content => {
+                    blocks.push(AppendBlockRequestChild { content, ..child });
+                    continue;
+                }
+            };
+            if !blocks.is_empty() {
+                let batch = AppendBlockRequest {
+                    children: std::mem::take(&mut blocks),
+                    after: after.clone(),
+                };
+                self.append_block(page_id.to_string(), &batch).await?;
+            }
+            let database_id = self.create_database(page_id, &database).await?;
+            if after.is_some() {
+                after = Some(database_id);
+            }
+        }
+        if !blocks.is_empty() {
+            let batch = AppendBlockRequest { children: blocks, after };
+            self.append_block(page_id.to_string(), &batch).await?;
         }
         Ok(())
     }
 
+    /// Renders the SVGs of the images shipped with `svg = "png"` and uploads them, pointing the
+    /// images at their uploads.
+    async fn upload_svgs(&self, children: &mut [AppendBlockRequestChild]) -> Result<()> {
+        for image in children.iter_mut().flat_map(|c| c.pending_svgs()) {
+            let source = image.svg_source.take().unwrap_or_default();
+            let remote = Url::parse(&source)
+                .ok()
+                .filter(|u| matches!(u.scheme(), "http" | "https"));
+            let svg = match remote {
+                // Not through the Notion client, which would send the integration secret along.
+                Some(url) => reqwest::get(url).await?.error_for_status()?.bytes().await?.to_vec(),
+                None => std::fs::read(&source).map_err(|e| anyhow!("failed to read {}: {}", source, e))?,
+            };
+            let png = render_png(&svg).map_err(|e| anyhow!("failed to render {}: {}", source, e))?;
+            let stem = Path::new(source.split(['?', '#']).next().unwrap_or(&source))
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or("image".to_string());
+            let id = self.upload_file(&format!("{}.png", stem), "image/png", png).await?;
+            image.file_upload = Some(FileUploadInner { id });
+        }
+        Ok(())
+    }
+
+    /// Uploads a file to Notion, returning the id of the upload for blocks to point at.
+    pub async fn upload_file(&self, name: &str, content_type: &str, contents: Vec<u8>) -> Result<String> {
+        let url = format!("{}/file_uploads", self.base_endpoint);
+        let body = json!({ "filename": name, "content_type": content_type });
+        let r = self.send(self.client.post(&url).json(&body)).await?;
+        let status = r.status();
+        if status != StatusCode::OK {
+            return Err(ApiError::read(r, format!("failed to start uploading {}", name)).await.into());
+        }
+        let upload: Value = r.json().await.redacted()?;
+        let id = upload["id"]
+            .as_str()
+            .ok_or(anyhow!("failed to upload {}, notion returned no upload id", name))?
+            .to_string();
+
+        // Multipart bodies can't be cloned to retry, so this one is sent once.
+        let part = Part::bytes(contents)
+            .file_name(name.to_string())
+            .mime_str(content_type)?;
+        let r = self
+            .client
+            .post(format!("{}/file_uploads/{}/send", self.base_endpoint, id))
+            .multipart(Form::new().part("file", part))
+            .send()
+            .await
+            .redacted()?;
+        let status = r.status();
+        if status != StatusCode::OK {
+            return Err(ApiError::read(r, format!("failed to upload {}", name)).await.into());
+        }
+        Ok(id)
+    }
+
+    /// Creates an inline database, and its rows, at the end of the page `parent_id`.
+    pub async fn create_database(
+        &self,
+        parent_id: &str,
+        database: &ChildDatabaseParent,
+    ) -> Result<String> {
+        let url = format!("{}/databases", self.base_endpoint);
+        let r = self
+            .send(self.client.post(&url).json(&database.create_request(parent_id)))
+            .await?;
+        let status = r.status();
+        if status != StatusCode::OK {
+            return Err(ApiError::read(r, format!("failed to create database \"{}\"", database.title)).await.into());
+        }
+        let created: CreatePageResponse = r.json().await.redacted()?;
+        let url = format!("{}/pages", self.base_endpoint);
+        for row in database.row_requests(&created.id) {
+            let r = self.send(self.client.post(&url).json(&row)).await?;
+            let status = r.status();
+            if status != StatusCode::OK {
+                return Err(ApiError::read(r, format!("failed to add a row to database \"{}\"", database.title)).await.into());
+            }
+        }
+        Ok(created.id)
+    }
+
+    /// When the page of `relative_path`, under `root`, was last updated for the `[database]`
+    /// updated property, `None` without one.
+    fn updated_at(&self, root: &Path, relative_path: &Path, shipped_at: DateTime<Utc>) -> Result<Option<DateTime<Utc>>> {
+        let database = &self.settings.database;
+        if database.id.is_none() || database.updated_property.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(match database.updated_from {
+            UpdatedFrom::Ship => shipped_at,
+            UpdatedFrom::Commit => last_commit_time(root, relative_path)?.unwrap_or(shipped_at),
+        }))
+    }
+
+    /// Adds to the multi-select `property` of the `[database]` the options among `names` it
+    /// doesn't have yet. `known` keeps the options it has between the pages of a ship, read
+    /// from Notion the first time.
+    async fn add_select_options(&self, known: &mut Option<Vec<String>>, property: &str, names: &[String]) -> Result<()> {
+        let database_id = match &self.settings.database.id {
+            Some(id) => id,
+            None => return Ok(()),
+        };
+        if known.is_none() {
+            *known = Some(self.get_select_options(database_id, property).await?);
+        }
+        let known = known.get_or_insert_with(Vec::new);
+        let missing: Vec<&String> = names.iter().filter(|n| !known.contains(n)).collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        known.extend(missing.into_iter().cloned());
+        let options: Vec<Value> = known.iter().map(|n| json!({ "name": n })).collect();
+        let url = format!("{}/databases/{}", self.base_endpoint, database_id);
+        let request = json!({ "properties": { property: { "multi_select": { "options": options } } } });
+        let r = self.send(self.client.patch(&url).json(&request)).await?;
+        let status = r.status();
+        if status != StatusCode::OK {
+            return Err(ApiError::read(r, format!("failed to add options to \"{}\"", property)).await.into());
+        }
+        Ok(())
+    }
+
+    /// The options of the multi-select `property` of the database `database_id`.
+    async fn get_select_options(&self, database_id: &str, property: &str) -> Result<Vec<String>> {
+        let url = format!("{}/databases/{}", self.base_endpoint, database_id);
+        let r = self.send(self.client.get(&url)).await?;
+        let status = r.status();
+        if status != StatusCode::OK {
+            return Err(ApiError::read(r, "failed to get database").await.into());
+        }
+        let database: Value = r.json().await.redacted()?;
+        let options = database["properties"][property]["multi_select"]["options"]
+            .as_array()
+            .ok_or(anyhow!("[database] tags_property \"{}\" is not a multi-select property of the database", property))?;
+        Ok(options
+            .iter()
+            .filter_map(|o| o["name"].as_str())
+            .map(|n| n.to_string())
+            .collect())
+    }
+
+    /// Sends a request, retrying when Notion rate limits it or fails on its side. Waits as long
+    /// as a `Retry-After` header asks, doubling the wait otherwise.
+    async fn send(&self, builder: RequestBuilder) -> Result<Response> {
+        let mut delay = Duration::from_millis(500);
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            match self.attempt(&builder, attempt == MAX_ATTEMPTS).await? {
+                Attempt::Done(r) => return Ok(r),
+                Attempt::Retry { after, .. } => self.back_off(after.unwrap_or(delay)).await,
+            }
+            delay *= 2;
+        }
+    }
+
+    /// Sends a request once, telling whether it is worth sending again.
+    async fn attempt(&self, builder: &RequestBuilder, is_last: bool) -> Result<Attempt> {
+        let request = builder
+            .try_clone()
+            .ok_or(anyhow!("failed to retry request, its body can't be cloned"))?;
+        self.api.call();
+        match request.send().await {
+            Ok(r) if is_retryable(r.status()) && !is_last => {
+                let is_rate_limited = r.status() == StatusCode::TOO_MANY_REQUESTS;
+                if is_rate_limited {
+                    self.api.rate_limited();
+                }
+                let after = r
+                    .headers()
+                    .get(header::RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.parse::<u64>().ok())
+                    .map(Duration::from_secs);
+                Ok(Attempt::Retry {
+                    after,
+                    may_have_landed: !is_rate_limited,
+                })
+            }
+            Ok(r) => {
+                if r.status() == StatusCode::TOO_MANY_REQUESTS {
+                    self.api.rate_limited();
+                }
+                Ok(Attempt::Done(r))
+            }
+            Err(e) if !is_last && (e.is_connect() || e.is_timeout()) => Ok(Attempt::Retry {
+                after: None,
+                may_have_landed: !e.is_connect(),
+            }),
+            Err(e) => Err(e).redacted(),
+        }
+    }
+
+    async fn back_off(&self, backoff: Duration) {
+        self.api.retry(backoff);
+        tokio::time::sleep(backoff).await;
+    }
+
+    /// The page a create request that timed out, or failed on Notion's side, may have created
+    /// all the same: an empty child page of `parent_id` titled `title`, created by this
+    /// integration since `since`.
+    async fn find_created_page(&self, parent_id: &str, title: &str, since: DateTime<Utc>) -> Result<Option<String>> {
+        let bot_id = self.get_current_user().await?.id;
+        Ok(self
+            .get_all_block_children::<Value>(parent_id)
+            .await?
+            .iter()
+            .find(|b| is_created_page(b, title, since, &bot_id))
+            .and_then(|b| b["id"].as_str())
+            .map(|id| id.to_string()))
+    }
+
+    /// Appends an empty paragraph that marks where an `intro.md` body should be inserted, so
+    /// the intro content lands above the links to child pages created afterward.
+    pub async fn append_intro_anchor(&self, page_id: String) -> Result<String> {
+        let request = AppendBlockRequest::new_child(AppendBlockRequestChild::new_paragraph_block(
+            String::new(),
+        ));
+        let response = self.append_block(page_id, &request).await?;
+        let anchor = response
+            .results
+            .first()
+            .ok_or(anyhow!("failed to append intro anchor, no block was created"))?;
+        Ok(anchor.id.clone())
+    }
+
+    /// The pages titled `page_name`, from the page index when it has any, otherwise searched
+    /// for in Notion.
     pub async fn find_page_by_name(&self, page_name: String) -> Result<Vec<SearchResultItem>> {
+        if let Some(index) = self.page_index()? {
+            let indexed: Vec<SearchResultItem> =
+                index.find_by_title(&page_name).iter().map(|p| p.search_result()).collect();
+            if !indexed.is_empty() {
+                return Ok(indexed);
+            }
+        }
         let lower_name = page_name.to_lowercase();
         let all_related_pages = self.find_all_pages_related_to_name(page_name).await?;
         let filtered_response: Vec<SearchResultItem> = all_related_pages
@@ -152,125 +818,1558 @@ impl NotionClient {
         Ok(filtered_response)
     }
 
+    /// The first page of the pages Notion finds for `page_name`, the best matches first.
     pub async fn find_all_pages_related_to_name(&self, page_name: String) -> Result<SearchResult> {
-        let search_request = SearchRequest::new(page_name);
+        let search_request = SearchRequest::new(page_name).with_page_size(100);
+        let response = self.search_page(&search_request).await?;
+        Ok(SearchResult {
+            results: response
+                .results
+                .into_iter()
+                .filter_map(|r| serde_json::from_value(r).ok())
+                .collect(),
+            has_more: response.has_more,
+            next_cursor: response.next_cursor,
+        })
+    }
+
+    /// One page of the results of `search_request`.
+    pub async fn search_page(&self, search_request: &SearchRequest) -> Result<BlockChildrenPage<Value>> {
         let endpoint = format!("{}/search", self.base_endpoint);
-        let r = self
-            .client
-            .post(&endpoint)
-            .json(&search_request)
-            .send()
-            .await?;
-        let response: Value = r.json().await?;
-        let response: SearchResult = serde_json::from_value(response)?;
-        Ok(response)
+        let r = self.send(self.client.post(&endpoint).json(search_request)).await?;
+        if r.status() != StatusCode::OK {
+            return Err(ApiError::read(r, "failed to search").await.into());
+        }
+        r.json().await.redacted()
+    }
+
+    /// Every result of `search_request`, following the cursors as they are read.
+    pub fn search_iter(&self, search_request: SearchRequest) -> SearchIter<'_> {
+        SearchIter::new(self, search_request)
+    }
+
+    /// The local index of the pages of the workspace, when `[index] enabled` and it was
+    /// refreshed with the secret of this client.
+    pub fn page_index(&self) -> Result<Option<PageIndex>> {
+        if !self.settings.index.enabled {
+            return Ok(None);
+        }
+        match PageIndex::path(&self.settings) {
+            Some(path) => PageIndex::load(&path, &self.settings.notion.secret),
+            None => Ok(None),
+        }
+    }
+
+    /// Searches every page the integration can see and writes them to the page index.
+    pub async fn refresh_index(&self) -> Result<(PathBuf, PageIndex)> {
+        let path = PageIndex::path(&self.settings)
+            .ok_or(anyhow!("could not find a home directory to keep the page index in, set [index] path"))?;
+        let mut pages = Vec::new();
+        let mut results = self.search_iter(SearchRequest::new(String::new()).with_page_size(100));
+        while let Some(r) = results.next().await? {
+            pages.extend(IndexedPage::from_search(&r));
+        }
+        let refreshed_at = self.clock.now().to_rfc3339_opts(SecondsFormat::Secs, true);
+        let index = PageIndex::new(&self.settings.notion.secret, refreshed_at, pages);
+        index.save(&path)?;
+        Ok((path, index))
     }
 
     pub async fn get_page_content_by_id(&self, page_id: String) -> Result<GetPageContentResponse> {
         let url = format!("{}/blocks/{}/children", self.base_endpoint, page_id);
-        let response = self.client.get(&url).send().await?;
-        let response: GetPageContentResponse = response.json().await?;
+        let response = self.send(self.client.get(&url)).await?;
+        if response.status() != StatusCode::OK {
+            return Err(ApiError::read(response, "failed to get page content").await.into());
+        }
+        let response: GetPageContentResponse = response.json().await.redacted()?;
         Ok(response)
     }
 
-    pub async fn clear(&self) -> Result<()> {
-        let parent_id = self
-            .get_parent_id_by_name(self.parent_page_name.clone())
-            .await?;
-        let page_details = self.get_page_content_by_id(parent_id.clone()).await?;
-        for rid in page_details.results.iter() {
-            self.delete(rid.id.clone(), &rid.content_type).await?;
+    /// Every block on a page, following pagination.
+    pub async fn get_all_page_content_by_id(&self, page_id: String) -> Result<Vec<PageContentResult>> {
+        self.get_all_block_children(&page_id).await
+    }
+
+    /// The blocks on a page, read back into the block model they were shipped with. Blocks the
+    /// model doesn't cover, like child pages, are left out.
+    pub async fn get_page_blocks(&self, page_id: &str) -> Result<Vec<AppendBlockRequestChild>> {
+        let mut blocks = Vec::new();
+        for value in self.get_all_block_children::<Value>(page_id).await? {
+            let block: AppendBlockRequestChild = match serde_json::from_value(value.clone()) {
+                Ok(b) => b,
+                Err(_) => continue,
+            };
+            let has_children = value["has_children"].as_bool().unwrap_or(false);
+            let mut block = block;
+            if let (Some(table), Some(id)) = (block.table_mut(), value["id"].as_str()) {
+                if has_children {
+                    table.children = self
+                        .get_all_block_children::<Value>(id)
+                        .await?
+                        .into_iter()
+                        .filter_map(|v| serde_json::from_value(v).ok())
+                        .collect();
+                }
+            }
+            blocks.push(block);
+        }
+        Ok(blocks)
+    }
+
+    /// Every child of a block or page, following pagination, read into `T`.
+    pub async fn get_all_block_children<T: DeserializeOwned>(&self, block_id: &str) -> Result<Vec<T>> {
+        let mut results = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let mut url = format!(
+                "{}/blocks/{}/children?page_size=100",
+                self.base_endpoint, block_id
+            );
+            if let Some(c) = &cursor {
+                url = format!("{}&start_cursor={}", url, c);
+            }
+            let response = self.send(self.client.get(&url)).await?;
+            if response.status() != StatusCode::OK {
+                return Err(ApiError::read(response, "failed to get block children").await.into());
+            }
+            let response: BlockChildrenPage<T> = response.json().await.redacted()?;
+            results.extend(response.results);
+            match response.next_cursor {
+                Some(c) if response.has_more => cursor = Some(c),
+                _ => break,
+            }
+        }
+        Ok(results)
+    }
+
+    pub async fn get_page(&self, page_id: &str) -> Result<PageResponse> {
+        let url = format!("{}/pages/{}", self.base_endpoint, page_id);
+        let r = self.send(self.client.get(&url)).await?;
+        let status = r.status();
+        if status != StatusCode::OK {
+            return Err(ApiError::read(r, "failed to get page").await.into());
         }
+        let response: PageResponse = r.json().await.redacted()?;
+        Ok(response)
+    }
+
+    /// Like [`NotionClient::get_page`], `None` when there is no such page, or it was deleted
+    /// for good.
+    pub async fn find_page(&self, page_id: &str) -> Result<Option<PageResponse>> {
+        let url = format!("{}/pages/{}", self.base_endpoint, page_id);
+        let r = self.send(self.client.get(&url)).await?;
+        let status = r.status();
+        if status == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if status != StatusCode::OK {
+            return Err(ApiError::read(r, "failed to get page").await.into());
+        }
+        Ok(Some(r.json().await.redacted()?))
+    }
 
+    /// Takes an archived page out of the trash.
+    pub async fn restore_page(&self, page_id: &str) -> Result<()> {
+        let url = format!("{}/pages/{}", self.base_endpoint, page_id);
+        let r = self.send(self.client.patch(&url).json(&json!({ "in_trash": false }))).await?;
+        let status = r.status();
+        if status != StatusCode::OK {
+            return Err(ApiError::read(r, "failed to restore page").await.into());
+        }
         Ok(())
     }
 
-    pub async fn create_pages(&self, dir: String, is_simulate: bool) -> Result<()> {
-        let pattern = get_md_glob_pattern(dir.clone());
-        let root_page_id = self
-            .get_parent_id_by_name(self.parent_page_name.clone())
-            .await?;
+    pub async fn update_page(
+        &self,
+        page_id: String,
+        title: String,
+        emoji: Option<String>,
+    ) -> Result<()> {
+        let mut update_page_request = UpdatePageRequest::new(title);
+        if let Some(emoji) = emoji {
+            update_page_request = update_page_request.with_icon(emoji);
+        }
+        self.update_page_with(&page_id, &update_page_request).await
+    }
+
+    pub async fn update_page_with(&self, page_id: &str, update_page_request: &UpdatePageRequest) -> Result<()> {
+        update_page_request.validate()?;
+        let url = format!("{}/pages/{}", self.base_endpoint, page_id);
+        let r = self.send(self.client.patch(&url).json(update_page_request)).await?;
+        let status = r.status();
+        if status != StatusCode::OK {
+            return Err(ApiError::read(r, "failed to update page").await.into());
+        }
+        Ok(())
+    }
+
+    /// Replaces the blocks on a page with `request`, keeping its child pages. The new blocks
+    /// take the place of the old ones, so content that sat above the child pages stays there.
+    pub async fn replace_page_content(
+        &self,
+        page_id: String,
+        request: AppendBlockRequest,
+    ) -> Result<()> {
+        let existing = self.get_all_page_content_by_id(page_id.clone()).await?;
+        let mut request = request;
+        if let Some(first) = existing.first() {
+            if !matches!(first.content_type, PageContentType::ChildPage) {
+                request = request.with_after(first.id.clone());
+            }
+        }
+        if !request.children.is_empty() {
+            self.append_content(&page_id, request).await?;
+        }
+        for b in existing
+            .iter()
+            .filter(|b| !matches!(b.content_type, PageContentType::ChildPage))
+        {
+            self.delete(b.id.clone(), &b.content_type).await?;
+        }
+        Ok(())
+    }
+
+    /// Archives everything under the parent page, see [`ClearPlan`].
+    pub async fn clear(&self) -> Result<()> {
+        let parent_id = self.parent_page_id().await?;
+        ClearPlan::read(self, &parent_id).await?.archive(self).await?;
+        Ok(())
+    }
+
+    pub async fn create_pages(&self, dir: String, is_simulate: bool) -> Result<ShipSummary> {
+        let options = ShipOptions {
+            simulate: is_simulate,
+            ..Default::default()
+        };
+        self.ship(dir, &options).await
+    }
+
+    /// Ships the markdown under `dir` and records the pages it created in the sync state.
+    /// With [`ShipOptions::since`], only files changed since that git ref (or not shipped yet)
+    /// are shipped: their tracked pages are updated in place, and pages of files that are
+    /// gone are archived. [`ShipOptions::only`] ships the files it lists the same way.
+    pub async fn ship(&self, dir: String, options: &ShipOptions) -> Result<ShipSummary> {
+        let before = self.api_usage();
+        let (mut summary, _) = self.run_ship(dir, options, options.export_dir.is_some()).await?;
+        summary.api = self.api_usage() - before;
+        Ok(summary)
+    }
+
+    /// Converts the markdown under `dir` like an export, without calling Notion or touching
+    /// the sync state, and keeps the requests every page would get in memory.
+    pub async fn plan(&self, dir: String, parent_page_id: &str) -> Result<(ShipSummary, ExportPlan)> {
+        let options = ShipOptions {
+            parent_page_id: Some(parent_page_id.to_string()),
+            ephemeral: true,
+            ..Default::default()
+        };
+        let (summary, export) = self.run_ship(dir, &options, true).await?;
+        Ok((summary, export.unwrap_or_default()))
+    }
+
+    async fn run_ship(
+        &self,
+        dir: String,
+        options: &ShipOptions,
+        is_export: bool,
+    ) -> Result<(ShipSummary, Option<ExportPlan>)> {
+        let is_simulate = options.simulate || is_export;
+        let is_stopping = || !is_simulate && self.is_stopping();
+        // Requests are checked against the schema of what Notion accepts on dry runs, and in
+        // debug builds, to catch what the conversion got wrong before Notion rejects it.
+        let is_validated = is_simulate || cfg!(debug_assertions);
+        let mut export = is_export.then(ExportPlan::default);
+        let root_page_id = match &options.parent_page_id {
+            Some(id) => id.clone(),
+            None => self.parent_page_id().await?,
+        };
 
         let mut paths_to_ids = HashMap::new();
+        let mut ordered_pages = Vec::new();
+        let shipped_at = self.clock.now();
+        // The options of the `[database]` tags property, once read.
+        let mut tag_options: Option<Vec<String>> = None;
         let mut subdir_path_to_parent_id: HashMap<PathBuf, String> = HashMap::new();
+        let mut intro_anchors: HashMap<String, String> = HashMap::new();
+        let mut summary = ShipSummary::default();
+
+        let SourceTree {
+            root,
+            files: mut shipped_paths,
+            settings: directory_settings,
+        } = SourceTree::load_redacted(&dir, self.settings.content.clone(), &self.settings.redact)?;
+        // The root of a file shipped from the current directory is empty.
+        let canonical_root = plain_path(&Path::new(".").join(&root).canonicalize()?);
+        let conversion_options = |relative_path: &Path| ConversionOptions {
+            heading_strategy: directory_settings.heading_strategy(relative_path),
+            title_strategy: directory_settings.title_strategy(relative_path),
+            pretty_titles: self.settings.content.pretty_titles,
+            bold_highlighted_lines: self.settings.content.bold_highlighted_lines,
+            detect_code_language: self.settings.content.detect_code_language,
+            image_base_url: self.settings.content.image_base_url.clone(),
+            svg: self.settings.content.svg,
+            unsupported_nodes: self.settings.content.unsupported_nodes,
+            linked_images: self.settings.content.linked_images,
+            line_break: self.settings.content.line_break,
+            symlinks: self.settings.content.symlinks,
+            directives: self.directives.clone(),
+            sections: self.sections.clone(),
+            redact: self.settings.redact.clone(),
+            locale: self.locale.clone(),
+            root: Some(canonical_root.clone()),
+            outside_links: self.settings.content.outside_links,
+            link_base_url: self.settings.content.link_base_url.clone(),
+        };
+
+        // Drafts are converted, to catch what would keep them from shipping, but not shipped.
+        let mut drafts = Vec::new();
+        for p in shipped_paths.iter() {
+            if parse_file(&root.join(p)).await?.get_arguments()?.skip {
+                drafts.push(p.clone());
+            }
+        }
+        shipped_paths.retain(|p| !drafts.contains(p));
+        let has_intro = |sub_dir: &Path| {
+            let intro_path = get_intro_path(&root, sub_dir);
+            intro_path.exists() && !drafts.iter().any(|d| root.join(d) == intro_path)
+        };
+
+        let state_path = SyncState::path(&root, self.settings.sync.state_file.as_deref());
+        let (mut state, changed) = match (&options.only, &options.since) {
+            (None, None) => (SyncState::new(root_page_id.clone()), None),
+            _ if options.ephemeral => {
+                return Err(anyhow!("--since needs the sync state, it can't ship ephemerally"));
+            }
+            (only, since) => {
+                let state = SyncState::load(&state_path)?.ok_or(anyhow!(
+                    "--since needs the sync state of a previous ship at {}, ship without --since first",
+                    state_path.display()
+                ))?;
+                if state.parent_page_id != root_page_id {
+                    return Err(anyhow!(
+                        "{} was shipped to another parent page, ship without --since to start over",
+                        state_path.display()
+                    ));
+                }
+                let changed = match (only, since) {
+                    (Some(only), _) => only.clone(),
+                    (None, Some(since)) => changed_since(&root, since)?,
+                    (None, None) => HashSet::new(),
+                };
+                (state, Some(changed))
+            }
+        };
+        let is_incremental = changed.is_some();
+        let commit = head_commit(&root);
+        let footer = self.settings.footer.enabled.then(|| {
+            Footer::new(&self.settings.footer.template, commit.as_deref(), shipped_at)
+        });
+
+        let merged_directories: HashMap<PathBuf, PathBuf> = shipped_paths
+            .iter()
+            .filter_map(|p| directory_settings.merged_directory(p).map(|d| (p.clone(), d)))
+            .collect();
+        // A merged page is shipped whole, so any change to one of its files, or to the set of
+        // its files, ships all of them.
+        let mut changed_merged_directories = HashSet::new();
+        if let Some(changed) = &changed {
+            for (p, d) in merged_directories.iter() {
+                if changed.contains(p)
+                    || state.merged_pages.get(&SyncState::key(p)) != Some(&SyncState::key(d))
+                {
+                    changed_merged_directories.insert(d.clone());
+                }
+            }
+            for (key, d) in state.merged_pages.iter() {
+                let is_still_merged = merged_directories
+                    .iter()
+                    .any(|(p, md)| SyncState::key(p) == *key && SyncState::key(md) == *d);
+                if !is_still_merged {
+                    changed_merged_directories.insert(PathBuf::from(d));
+                }
+            }
+        }
+
+        // Files renamed since the last ship keep the page of their slug.
+        let mut renamed = HashSet::new();
+        if is_incremental {
+            for p in shipped_paths.iter().filter(|p| !merged_directories.contains_key(*p)) {
+                let key = SyncState::key(p);
+                let is_intro = p
+                    .file_stem()
+                    .is_some_and(|s| s.to_string_lossy().to_lowercase() == INTRO_FILENAME);
+                if is_intro || state.pages.contains_key(&key) {
+                    continue;
+                }
+                let slug = match parse_file(&root.join(p)).await?.slug()? {
+                    Some(slug) => slug,
+                    None => continue,
+                };
+                let old_key = match state.find_slug(&slug, &key) {
+                    Some(k) if !shipped_paths.iter().any(|s| SyncState::key(s) == k) => k,
+                    _ => continue,
+                };
+                if state.rename_page(&old_key, &key) {
+                    renamed.insert(p.clone());
+                } else {
+                    summary.warnings.push(Diagnostic::warning(
+                        &root.join(p).display().to_string(),
+                        None,
+                        format!(
+                            "slug \"{}\" was shipped from {} in another directory, Notion can't move pages so a new one is created",
+                            slug, old_key
+                        ),
+                    ));
+                }
+            }
+        }
+
+        // Pages edited in Notion since the last ship are only overwritten when asked to.
+        let mut skipped = HashSet::new();
+        if let (Some(changed), false) = (&changed, is_simulate || options.on_conflict == ConflictPolicy::Overwrite) {
+            let mut conflicts: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+            let mut checked = HashSet::new();
+            for p in shipped_paths.iter() {
+                let is_intro = p
+                    .file_stem()
+                    .is_some_and(|s| s.to_string_lossy().to_lowercase() == INTRO_FILENAME);
+                let key = match merged_directories.get(p) {
+                    Some(d) if changed_merged_directories.contains(d) => SyncState::key(d),
+                    Some(_) => continue,
+                    None if !changed.contains(p) && !renamed.contains(p) => continue,
+                    None if is_intro => SyncState::key(p.parent().unwrap_or(Path::new(""))),
+                    None => SyncState::key(p),
+                };
+                let tracked = if merged_directories.contains_key(p) || is_intro {
+                    state.directories.get(&key)
+                } else {
+                    state.pages.get(&key)
+                };
+                let (page_id, shipped_time) = match tracked {
+                    Some(TrackedPage {
+                        page_id,
+                        last_edited_time: Some(t),
+                        ..
+                    }) => (page_id, t),
+                    _ => continue,
+                };
+                if !checked.insert(key.clone()) {
+                    if let Some(files) = conflicts.get_mut(&key) {
+                        files.push(p.clone());
+                    }
+                    continue;
+                }
+                let page = self.get_page(page_id).await?;
+                if !page.in_trash && is_edited_after(shipped_time, &page.last_edited_time) {
+                    conflicts.insert(key, vec![p.clone()]);
+                }
+            }
+            if !conflicts.is_empty() && options.on_conflict == ConflictPolicy::Fail {
+                return Err(ConflictError {
+                    pages: conflicts.into_keys().collect(),
+                }
+                .into());
+            }
+            for (key, files) in conflicts {
+                summary.warnings.push(Diagnostic::warning(
+                    &root.join(&key).display().to_string(),
+                    None,
+                    "edited in Notion since the last ship, left as it is".to_string(),
+                ));
+                changed_merged_directories.remove(&PathBuf::from(&key));
+                summary.skipped.extend(files.iter().map(|f| SyncState::key(f)));
+                skipped.extend(files);
+            }
+        }
+
+        if !is_simulate {
+            let mut page_keys = HashSet::new();
+            let mut directory_keys = HashSet::new();
+            for p in shipped_paths.iter() {
+                match merged_directories.get(p) {
+                    Some(d) => directory_keys.extend(d.ancestors().map(SyncState::key)),
+                    None => {
+                        page_keys.insert(SyncState::key(p));
+                        directory_keys.extend(p.ancestors().skip(1).map(SyncState::key));
+                    }
+                }
+            }
+            self.archive_removed_pages(&mut state, &page_keys, &directory_keys)
+                .await?;
+        }
+        state.merged_pages = merged_directories
+            .iter()
+            .map(|(p, d)| (SyncState::key(p), SyncState::key(d)))
+            .collect();
+
+        if !is_simulate && !is_incremental && has_intro(Path::new("")) {
+            let anchor_id = self.append_intro_anchor(root_page_id.clone()).await?;
+            intro_anchors.insert(root_page_id.clone(), anchor_id);
+        }
+
+        for relative_path in shipped_paths.iter() {
+            let path = root.join(relative_path);
+            let file_name = path.file_stem().unwrap().to_str().unwrap().to_string();
+            let is_intro = file_name.to_lowercase().as_str() == INTRO_FILENAME;
+            let sub_dir_path = relative_path.parent().unwrap_or(Path::new("")).to_path_buf();
+            let merged_into = merged_directories.get(relative_path);
+            // The directory whose page this file's content goes on, for intros and merged files.
+            let page_dir = match merged_into {
+                Some(d) => Some(d.clone()),
+                None if is_intro => Some(sub_dir_path.clone()),
+                None => None,
+            };
+            let is_section_intro = is_intro && page_dir.as_ref() == Some(&sub_dir_path);
+            let tracked_id = match &page_dir {
+                Some(d) if d.as_os_str().is_empty() => Some(root_page_id.clone()),
+                Some(d) => state
+                    .directories
+                    .get(&SyncState::key(d))
+                    .map(|d| d.page_id.clone()),
+                None => state
+                    .pages
+                    .get(&SyncState::key(relative_path))
+                    .map(|p| p.page_id.clone()),
+            };
+            let is_changed = match merged_into {
+                Some(d) => changed.is_none() || changed_merged_directories.contains(d),
+                None => {
+                    (changed.as_ref().is_none_or(|c| c.contains(relative_path))
+                        || renamed.contains(relative_path))
+                        && !skipped.contains(relative_path)
+                }
+            };
+            if let (Some(tracked_id), false) = (&tracked_id, is_changed) {
+                if !skipped.contains(relative_path) {
+                    let tracked = match &page_dir {
+                        Some(d) => state.directories.get(&SyncState::key(d)),
+                        None => state.pages.get(&SyncState::key(relative_path)),
+                    };
+                    summary.unchanged.push(
+                        ShippedPage::new(
+                            relative_path.display().to_string(),
+                            tracked.map(|t| t.title.clone()).unwrap_or_default(),
+                            tracked_id.clone(),
+                        )
+                        .with_status(PageStatus::Unchanged),
+                    );
+                }
+                paths_to_ids.insert(page_key(&path, self.settings.content.symlinks)?, tracked_id.clone());
+                continue;
+            }
+            if is_stopping() {
+                summary.interrupted.push(SyncState::key(merged_into.unwrap_or(relative_path)));
+                continue;
+            }
+
+            let components: Vec<_> = relative_path.components().collect();
+            let mut accumulated_components = Vec::new();
+            let directory_depth = match merged_into {
+                Some(d) => d.components().count(),
+                None => components.len() - 1,
+            };
 
-        for entry in glob(&pattern)? {
-            let path = entry?;
-
-            if path.is_file() {
-                let relative_path = path.strip_prefix(dir.clone()).unwrap();
-                let components: Vec<_> = relative_path.components().collect();
-
-                let mut accumulated_components = Vec::new();
-
-                if components.len() > 1 {
-                    for component in components.iter().take(components.len() - 1) {
-                        if let Some(dir_name) = component.as_os_str().to_str() {
-                            let base_path = PathBuf::new().join(accumulated_components.join("/"));
-                            let new_subdir_path = base_path.join(dir_name);
-                            if subdir_path_to_parent_id.get(&new_subdir_path).is_none() {
-                                let parent_dir_id = subdir_path_to_parent_id
-                                    .get(&base_path)
-                                    .unwrap_or(&root_page_id);
-                                let new_dir_id = if is_simulate {
-                                    generate_random_string(30)
-                                } else {
-                                    let parent_path = path.parent().unwrap_or(Path::new("/"));
-                                    let intro_path = parent_path.join(format!("{}.md", INTRO_FILENAME));
-                                    let page_args = if intro_path.exists() {
-                                        let parsed_content = parse_file(&intro_path).await?;
-                                        let arguments = parsed_content.get_arguments()?;
-                                        arguments
-                                    } else {
-                                        NotationDocArguments::default()
-                                    };
-                                    self.create_page_by_parent_id(
-                                        parent_dir_id.clone(),
-                                        page_args.title.unwrap_or(dir_name.to_string()),
-                                        page_args.emoji,
-                                    )
-                                    .await?
-                                };
-                                subdir_path_to_parent_id
-                                    .insert(new_subdir_path.clone(), new_dir_id.clone());
-                            }
-                            accumulated_components.push(dir_name.to_string());
+            if directory_depth > 0 {
+                for component in components.iter().take(directory_depth) {
+                    if let Some(dir_name) = component.as_os_str().to_str() {
+                        let base_path = PathBuf::new().join(accumulated_components.join("/"));
+                        let new_subdir_path = base_path.join(dir_name);
+                        if let Some(tracked) = state.directories.get(&SyncState::key(&new_subdir_path)) {
+                            subdir_path_to_parent_id
+                                .entry(new_subdir_path.clone())
+                                .or_insert(tracked.page_id.clone());
                         }
+                        if !subdir_path_to_parent_id.contains_key(&new_subdir_path) {
+                            let parent_dir_id = subdir_path_to_parent_id
+                                .get(&base_path)
+                                .unwrap_or(&root_page_id);
+                            let dir_page =
+                                get_directory_page_arguments(&new_subdir_path, &directory_settings, &self.settings);
+                            let dir_title = dir_page.title.clone();
+                            let row = self.row_properties(
+                                parent_dir_id,
+                                &root_page_id,
+                                dir_page.description.as_deref(),
+                                None,
+                                Some(shipped_at),
+                            )?;
+                            let request = self.ship_page_request(
+                                parent_dir_id.clone(),
+                                dir_title.clone(),
+                                dir_page.emoji,
+                                dir_page.cover,
+                                row,
+                            );
+                            request
+                                .validate()
+                                .map_err(|e| anyhow!("{}: {}", root.join(&new_subdir_path).display(), e))?;
+                            let new_dir_id = if is_simulate {
+                                let new_dir_id = self.clock.placeholder_id(&SyncState::key(&new_subdir_path));
+                                if let Some(export) = export.as_mut() {
+                                    export.add_page(
+                                        new_subdir_path.display().to_string(),
+                                        new_dir_id.clone(),
+                                        request,
+                                    );
+                                }
+                                new_dir_id
+                            } else {
+                                let new_dir_id = self.create_page(&request).await?;
+                                if has_intro(&new_subdir_path) {
+                                    let anchor_id = self.append_intro_anchor(new_dir_id.clone()).await?;
+                                    intro_anchors.insert(new_dir_id.clone(), anchor_id);
+                                }
+                                new_dir_id
+                            };
+                            let shipped = ShippedPage::new(
+                                new_subdir_path.display().to_string(),
+                                dir_title.clone(),
+                                new_dir_id.clone(),
+                            );
+                            options.progress(ShipProgress::Page(shipped.clone()));
+                            summary.pages.push(shipped);
+                            state.directories.insert(
+                                SyncState::key(&new_subdir_path),
+                                TrackedPage {
+                                    page_id: new_dir_id.clone(),
+                                    title: dir_title,
+                                    ..Default::default()
+                                },
+                            );
+                            subdir_path_to_parent_id
+                                .insert(new_subdir_path.clone(), new_dir_id.clone());
+                        }
+                        accumulated_components.push(dir_name.to_string());
                     }
                 }
+            }
 
-                let sub_dir_path = PathBuf::new().join(accumulated_components.join("/"));
-                let parent_id = subdir_path_to_parent_id
-                    .get(&sub_dir_path)
-                    .unwrap_or(&root_page_id);
-                let parsed_content = parse_file(&path).await?;
-                let arguments = parsed_content.get_arguments()?;
-                let file_name = path.file_stem().unwrap().to_str().unwrap().to_string();
-                let page_title = arguments.title.unwrap_or(file_name.clone());
-
-                let cr = if is_simulate {
-                    generate_random_string(30)
-                } else {
-                    if file_name.to_lowercase().as_str() == INTRO_FILENAME {
-                        parent_id.clone()
-                    } else {
-                        self.create_page_by_parent_id(parent_id.clone(), page_title, arguments.emoji)
-                            .await?
+            let parent_id = subdir_path_to_parent_id
+                .get(merged_into.unwrap_or(&sub_dir_path))
+                .unwrap_or(&root_page_id);
+            let parsed_content = parse_file(&path).await?;
+            let arguments = parsed_content.get_arguments()?;
+            let page_title = parsed_content.title(
+                directory_settings.title_strategy(relative_path),
+                self.settings.content.pretty_titles,
+                &self.settings.redact,
+            )?;
+            let emoji = arguments
+                .emoji
+                .clone()
+                .or_else(|| self.settings.emoji.emoji_for(relative_path, &page_title));
+            // Merged files keep their own title, for their section of the directory's page.
+            let shipped_title = self.settings.content.page_title(&page_title);
+
+            // Tracked pages, and directory pages that existed before this ship, get their
+            // content replaced; pages created by this ship get it appended.
+            let is_replaced = is_incremental && tracked_id.is_some();
+            let updated = if parent_id == &root_page_id {
+                self.updated_at(&root, relative_path, shipped_at)?
+            } else {
+                None
+            };
+            let row = self.row_properties(
+                parent_id,
+                &root_page_id,
+                arguments.description.as_deref(),
+                Some(&parsed_content),
+                updated,
+            )?;
+            let request = self.ship_page_request(
+                parent_id.clone(),
+                shipped_title.clone(),
+                emoji.clone(),
+                arguments.cover.clone(),
+                row.clone(),
+            );
+            request.validate().map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+            if is_validated && page_dir.is_none() && !is_section_intro && merged_into.is_none() {
+                summary
+                    .warnings
+                    .extend(request_warnings(&path.display().to_string(), create_page_problems(&request)));
+            }
+            let is_row_shipped = row.is_some() && page_dir.is_none() && !is_simulate;
+            if let (Some(property), true) = (&self.settings.database.tags_property, is_row_shipped) {
+                let tags = parsed_content.tags(self.settings.database.hashtags);
+                self.add_select_options(&mut tag_options, property, &tags).await?;
+            }
+            let cr = if is_simulate && page_dir.is_some() {
+                parent_id.clone()
+            } else if is_simulate {
+                let page_id = tracked_id
+                    .clone()
+                    .unwrap_or_else(|| self.clock.placeholder_id(&SyncState::key(relative_path)));
+                if let Some(export) = export.as_mut() {
+                    export.add_page(relative_path.display().to_string(), page_id.clone(), request);
+                }
+                page_id
+            } else if is_section_intro {
+                if let Some(tracked_id) = tracked_id.clone().filter(|_| is_incremental) {
+                    if !sub_dir_path.as_os_str().is_empty() {
+                        let dir_page = get_directory_page_arguments(&sub_dir_path, &directory_settings, &self.settings);
+                        let request =
+                            self.ship_update_request(dir_page.title, dir_page.emoji, dir_page.cover, None);
+                        self.update_page_with(&tracked_id, &request).await?;
                     }
+                }
+                parent_id.clone()
+            } else if merged_into.is_some() {
+                parent_id.clone()
+            } else if let Some(tracked_id) = tracked_id.clone() {
+                let request = self.ship_update_request(shipped_title.clone(), emoji, arguments.cover.clone(), row);
+                self.update_page_with(&tracked_id, &request).await?;
+                tracked_id
+            } else {
+                self.create_page(&request).await?
+            };
+            if page_dir.is_none() {
+                let status = if tracked_id.is_some() { PageStatus::Updated } else { PageStatus::Created };
+                let shipped = ShippedPage::new(relative_path.display().to_string(), shipped_title.clone(), cr.clone())
+                    .with_status(status);
+                options.progress(ShipProgress::Page(shipped.clone()));
+                summary.pages.push(shipped);
+                state.pages.insert(
+                    SyncState::key(relative_path),
+                    TrackedPage {
+                        page_id: cr.clone(),
+                        title: shipped_title.clone(),
+                        slug: parsed_content.slug()?,
+                        source_hash: Some(content_hash(&std::fs::read(&path)?)),
+                        last_edited_time: None,
+                    },
+                );
+            }
+
+            paths_to_ids.insert(page_key(&path, self.settings.content.symlinks)?, cr.clone());
+            ordered_pages.push(OrderedPage {
+                path: path.clone(),
+                relative_path: relative_path.clone(),
+                page_id: cr,
+                title: page_title,
+                is_replaced,
+                merged_into: merged_into.cloned(),
+                is_section_intro,
+            });
+        }
+
+        for relative_path in drafts.iter() {
+            let parsed_content = parse_file(&root.join(relative_path)).await?;
+            let options = conversion_options(relative_path);
+            let conversion = parsed_content.to_notion("", &paths_to_ids, &options)?;
+            summary.warnings.extend(conversion.warnings);
+            summary.drafts.push(SyncState::key(relative_path));
+        }
+
+        let mut merged_pages: Vec<MergedPage> = Vec::new();
+        let mut writes: Vec<(String, PageWrite)> = Vec::new();
+        let mut linking_headings: HashSet<String> = HashSet::new();
+        for page in ordered_pages.iter() {
+            let parsed_content = parse_file(&page.path).await?;
+            let mut page_options = conversion_options(&page.relative_path);
+            // The heading of an intro stays on the page unless it titles the directory's page.
+            let dir = page.relative_path.parent().unwrap_or(Path::new(""));
+            if page.is_section_intro && !directory_settings.is_titled_by_intro(dir) {
+                page_options.title_strategy = TitleStrategy::FileStem;
+            }
+            let conversion = parsed_content.to_notion(&page.page_id, &paths_to_ids, &page_options)?;
+            summary.warnings.extend(conversion.warnings);
+            summary.redactions.extend(conversion.redactions);
+            let mut notion_request = conversion.request;
+            let doc_context = DocContext {
+                relative_path: page.relative_path.clone(),
+                path: page.path.clone(),
+                page_id: page.page_id.clone(),
+                title: page.title.clone(),
+                merged_into: page.merged_into.clone(),
+            };
+            self.transforms.apply(&mut notion_request, &doc_context)?;
+            if page.is_section_intro && page.merged_into.is_none() {
+                summary.warnings.extend(databases_below_subpages(&page.path, &notion_request));
+            }
+            if links_to_headings(&notion_request.children) {
+                linking_headings.insert(page.page_id.clone());
+            }
+            if let Some(merged_into) = &page.merged_into {
+                let section = MergedSection {
+                    title: page.title.clone(),
+                    is_intro: page.is_section_intro,
+                    children: notion_request.children,
                 };
+                match merged_pages.iter_mut().find(|m| m.directory == *merged_into) {
+                    Some(m) => m.sections.push(section),
+                    None => merged_pages.push(MergedPage {
+                        directory: merged_into.clone(),
+                        page_id: page.page_id.clone(),
+                        is_replaced: page.is_replaced,
+                        sections: vec![section],
+                    }),
+                }
+                continue;
+            }
+            if let Some(footer) = &footer {
+                notion_request.extend_children(footer.blocks(&page.path.display().to_string()));
+            }
+            if is_validated {
+                summary
+                    .warnings
+                    .extend(request_warnings(&page.path.display().to_string(), append_problems(&notion_request)));
+            }
+            if let Some(export) = export.as_mut() {
+                export.append_blocks(&page.page_id, notion_request);
+            } else if !is_simulate {
+                writes.push((
+                    page.page_id.clone(),
+                    PageWrite {
+                        page_id: page.page_id.clone(),
+                        path: page.relative_path.clone(),
+                        request: notion_request,
+                        is_replaced: page.is_replaced,
+                        anchor_id: intro_anchors.remove(&page.page_id),
+                    },
+                ));
+            }
+        }
 
-                paths_to_ids.insert(path.clone(), cr.clone());
+        for merged_page in merged_pages {
+            let page_id = merged_page.page_id.clone();
+            let is_replaced = merged_page.is_replaced;
+            let directory_path = merged_page.directory.clone();
+            let directory = root.join(&merged_page.directory);
+            let mut notion_request = merged_page.into_request();
+            if let Some(footer) = &footer {
+                notion_request.extend_children(footer.blocks(&directory.display().to_string()));
+            }
+            if is_validated {
+                summary
+                    .warnings
+                    .extend(request_warnings(&directory.display().to_string(), append_problems(&notion_request)));
+            }
+            if let Some(export) = export.as_mut() {
+                export.append_blocks(&page_id, notion_request);
+            } else if !is_simulate {
+                writes.push((
+                    page_id.clone(),
+                    PageWrite {
+                        anchor_id: intro_anchors.remove(&page_id),
+                        page_id,
+                        path: directory_path,
+                        request: notion_request,
+                        is_replaced,
+                    },
+                ));
             }
         }
 
-        for (path, page_id) in paths_to_ids.iter() {
-            let parsed_content = parse_file(&path).await?;
-            let notion_request = parsed_content.to_notion(&page_id, &paths_to_ids)?;
-            if !is_simulate {
-                self.append_block(page_id.clone(), &notion_request).await?;
+        // Pages are written `[sync] concurrency` at a time, the content of each in order. Once
+        // asked to stop, the pages under way are finished and the others left.
+        let finished: Arc<Mutex<(HashSet<String>, Vec<String>)>> = Arc::default();
+        let (client, progress, shared) = (self.clone(), options.progress.clone(), finished.clone());
+        for_each_page(writes, self.settings.sync.concurrency, move |write: PageWrite| {
+            let (client, progress, finished) = (client.clone(), progress.clone(), shared.clone());
+            async move {
+                if client.is_stopping() {
+                    finished.lock().unwrap().1.push(SyncState::key(&write.path));
+                    return Ok(());
+                }
+                client
+                    .write_page_content(&write.page_id, write.request, write.is_replaced, write.anchor_id)
+                    .await?;
+                if let Some(progress) = progress {
+                    // Nobody listening anymore doesn't stop the ship.
+                    let _ = progress.send(ShipProgress::Written {
+                        path: write.path.display().to_string(),
+                    });
+                }
+                finished.lock().unwrap().0.insert(write.page_id);
+                Ok(())
+            }
+        })
+        .await?;
+        let (written, interrupted) = std::mem::take(&mut *finished.lock().unwrap());
+        summary.interrupted.extend(interrupted);
+
+        if let (Some(export), Some(out)) = (&export, &options.export_dir) {
+            export.write(out)?;
+        }
+        summary.interrupted.sort();
+        summary.interrupted.dedup();
+        if export.is_none() && !is_simulate && !summary.is_interrupted() {
+            self.link_headings(&linking_headings).await?;
+        }
+        if !is_simulate && !options.ephemeral && summary.is_interrupted() {
+            // The pages of this ship forget their hash and edit time, so that the next ship
+            // neither takes them for unchanged nor for edited in Notion, and the commit stays
+            // the one of the last whole ship so that `--since` it ships the rest.
+            let shipped: HashSet<&str> = summary.pages.iter().map(|p| p.page_id.as_str()).collect();
+            for tracked in state.pages.values_mut().chain(state.directories.values_mut()) {
+                if shipped.contains(tracked.page_id.as_str()) || written.contains(&tracked.page_id) {
+                    if !written.contains(&tracked.page_id) {
+                        tracked.source_hash = None;
+                    }
+                    tracked.last_edited_time = None;
+                }
+            }
+            state.save(&state_path)?;
+        } else if !is_simulate && !options.ephemeral {
+            // Recorded once everything is written, so edits made in Notion after this ship can
+            // be told apart from the ship's own.
+            for tracked in state.pages.values_mut().chain(state.directories.values_mut()) {
+                if written.contains(&tracked.page_id) {
+                    tracked.last_edited_time = Some(self.get_page(&tracked.page_id).await?.last_edited_time);
+                }
+            }
+            state.commit = commit;
+            state.save(&state_path)?;
+        }
+
+        Ok((summary, export))
+    }
+
+    /// Puts converted content on a page: replacing what a page from an earlier ship holds, or
+    /// appending to a new page, after its intro anchor if it has one.
+    async fn write_page_content(
+        &self,
+        page_id: &str,
+        mut notion_request: AppendBlockRequest,
+        is_replaced: bool,
+        anchor_id: Option<String>,
+    ) -> Result<()> {
+        if is_replaced {
+            return self
+                .replace_page_content(page_id.to_string(), notion_request)
+                .await;
+        }
+        if let Some(anchor_id) = anchor_id.clone() {
+            notion_request = notion_request.with_after(anchor_id);
+        }
+        if !notion_request.children.is_empty() {
+            self.append_content(page_id, notion_request).await?;
+        }
+        if let Some(anchor_id) = anchor_id {
+            self.delete(anchor_id, &PageContentType::Paragraph).await?;
+        }
+        Ok(())
+    }
+
+    /// Points the links to headings on `page_ids`, which only name a heading by its anchor, at
+    /// the heading blocks, which only have ids once the pages they are on are written. Links to
+    /// headings that can't be found keep linking to the page.
+    async fn link_headings(&self, page_ids: &HashSet<String>) -> Result<()> {
+        let mut headings: HashMap<String, HashMap<String, String>> = HashMap::new();
+        for page_id in page_ids.iter() {
+            for block in self.page_blocks(page_id).await? {
+                let block_id = block["id"].as_str().unwrap_or_default().to_string();
+                let child: AppendBlockRequestChild = match serde_json::from_value(block) {
+                    Ok(c) => c,
+                    Err(_) => continue,
+                };
+                let mut rich_text = match child.get_rich_text_blocks() {
+                    Some(r) => r,
+                    None => continue,
+                };
+                let mut is_changed = false;
+                for link in rich_text
+                    .iter_mut()
+                    .filter_map(|t| t.text.as_mut())
+                    .filter_map(|t| t.link.as_mut())
+                {
+                    let (target, slug) = match parse_anchor_url(&link.url) {
+                        Some(a) => a,
+                        None => continue,
+                    };
+                    if !headings.contains_key(&target) {
+                        headings.insert(target.clone(), self.heading_blocks(&target).await.unwrap_or_default());
+                    }
+                    if let Some(heading_id) = headings[&target].get(&slug) {
+                        link.url = format!("https://www.notion.so/{}#{}", target, heading_id);
+                        is_changed = true;
+                    }
+                }
+                if is_changed {
+                    self.update_rich_text(&block_id, &child.block_type(), rich_text)
+                        .await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The blocks on `page_id` in document order, those nested in toggles, callouts, columns
+    /// and the like right after the block they are in. Subpages, databases and tables are not
+    /// gone into.
+    async fn page_blocks(&self, page_id: &str) -> Result<Vec<Value>> {
+        let mut blocks = Vec::new();
+        let mut levels = vec![self.get_all_block_children::<Value>(page_id).await?.into_iter()];
+        while let Some(level) = levels.last_mut() {
+            let block = match level.next() {
+                Some(b) => b,
+                None => {
+                    levels.pop();
+                    continue;
+                }
+            };
+            let is_nesting = block["has_children"] == true
+                && !matches!(block["type"].as_str(), Some("child_page" | "child_database" | "table"));
+            let id = block["id"].as_str().unwrap_or_default().to_string();
+            blocks.push(block);
+            if is_nesting {
+                levels.push(self.get_all_block_children::<Value>(&id).await?.into_iter());
+            }
+        }
+        Ok(blocks)
+    }
+
+    /// The ids, without dashes, of the headings on a page by their anchors, nested ones too.
+    async fn heading_blocks(&self, page_id: &str) -> Result<HashMap<String, String>> {
+        let mut slugger = Slugger::default();
+        let mut headings = HashMap::new();
+        for block in self.page_blocks(page_id).await? {
+            let block_id = block["id"].as_str().unwrap_or_default().replace("-", "");
+            let child: AppendBlockRequestChild = match serde_json::from_value(block) {
+                Ok(c) => c,
+                Err(_) => continue,
+            };
+            if !matches!(
+                child.block_type(),
+                BlockType::Heading1 | BlockType::Heading2 | BlockType::Heading3
+            ) {
+                continue;
+            }
+            let text: String = child
+                .get_rich_text_blocks()
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|t| t.text.as_ref())
+                .map(|t| t.content.as_str())
+                .collect();
+            headings.insert(slugger.slug(&text), block_id);
+        }
+        Ok(headings)
+    }
+
+    async fn update_rich_text(
+        &self,
+        block_id: &str,
+        block_type: &BlockType,
+        rich_text: Vec<NotionBlock>,
+    ) -> Result<()> {
+        let url = format!("{}/blocks/{}", self.base_endpoint, block_id);
+        let body = json!({
+            serde_json::to_value(block_type)?.as_str().unwrap_or_default(): {
+                "rich_text": rich_text,
             }
+        });
+        let r = self.send(self.client.patch(&url).json(&body)).await?;
+        let status = r.status();
+        if status != StatusCode::OK {
+            return Err(ApiError::read(r, "failed to update block").await.into());
         }
+        Ok(())
+    }
+
+    /// Archives the tracked pages, and directory pages, that are no longer in `shipped_pages`
+    /// and `shipped_directories`. Pages inside an archived directory page go with it.
+    async fn archive_removed_pages(
+        &self,
+        state: &mut SyncState,
+        shipped_pages: &HashSet<String>,
+        shipped_directories: &HashSet<String>,
+    ) -> Result<()> {
+        let removed_directories: Vec<String> = state
+            .directories
+            .keys()
+            .filter(|k| !shipped_directories.contains(*k))
+            .cloned()
+            .collect();
+        let is_in_removed_directory = |key: &str| {
+            Path::new(key)
+                .ancestors()
+                .skip(1)
+                .any(|a| removed_directories.contains(&SyncState::key(a)))
+        };
 
+        let removed_pages: Vec<String> = state
+            .pages
+            .keys()
+            .filter(|k| !shipped_pages.contains(*k))
+            .cloned()
+            .collect();
+        for key in removed_pages {
+            if let Some(page) = state.pages.remove(&key) {
+                if !is_in_removed_directory(&key) {
+                    self.delete(page.page_id.clone(), &PageContentType::ChildPage).await?;
+                }
+                state.record_archived(&key, false, page);
+            }
+        }
+        for key in removed_directories.iter() {
+            if let Some(page) = state.directories.remove(key) {
+                if !is_in_removed_directory(key) {
+                    self.delete(page.page_id.clone(), &PageContentType::ChildPage).await?;
+                }
+                state.record_archived(key, true, page);
+            }
+        }
         Ok(())
     }
 }
+
+/// A markdown file with its page, between creating pages and converting their content.
+struct OrderedPage {
+    path: PathBuf,
+    relative_path: PathBuf,
+    page_id: String,
+    title: String,
+    is_replaced: bool,
+    merged_into: Option<PathBuf>,
+    is_section_intro: bool,
+}
+
+/// The page of a directory with `merge = true`, built from the sections of its files.
+struct MergedPage {
+    directory: PathBuf,
+    page_id: String,
+    is_replaced: bool,
+    sections: Vec<MergedSection>,
+}
+
+struct MergedSection {
+    title: String,
+    is_intro: bool,
+    children: Vec<AppendBlockRequestChild>,
+}
+
+impl MergedPage {
+    /// The directory's intro first, then every other file after a divider, under a heading
+    /// with its title unless it starts with a heading of its own.
+    fn into_request(mut self) -> AppendBlockRequest {
+        self.sections.sort_by_key(|s| !s.is_intro);
+        let mut request = AppendBlockRequest::new();
+        for section in self.sections {
+            if !section.is_intro {
+                if !request.children.is_empty() {
+                    request.append_child(AppendBlockRequestChild::new_divider_block());
+                }
+                let starts_with_heading = section.children.first().is_some_and(|c| {
+                    matches!(
+                        c.block_type(),
+                        BlockType::Heading1 | BlockType::Heading2 | BlockType::Heading3
+                    )
+                });
+                if !starts_with_heading {
+                    request.append_child(AppendBlockRequestChild::new_heading_block(section.title, 1));
+                }
+            }
+            request.extend_children(section.children);
+        }
+        request
+    }
+}
+
+/// Options of a single [`NotionClient::ship`].
+#[derive(Clone, Debug, Default)]
+pub struct ShipOptions {
+    /// Convert everything without calling Notion or writing the sync state.
+    pub simulate: bool,
+    /// A git ref: only markdown changed since then is shipped.
+    pub since: Option<String>,
+    /// Only these files, relative to the shipped root, are shipped, like those changed since
+    /// [`ShipOptions::since`]. Takes precedence over it.
+    pub only: Option<HashSet<PathBuf>>,
+    /// Ship under this page instead of the configured parent page.
+    pub parent_page_id: Option<String>,
+    /// Neither read nor write the sync state, for throwaway ships.
+    pub ephemeral: bool,
+    /// Write the requests of every page as JSON under this directory instead of sending them.
+    pub export_dir: Option<PathBuf>,
+    /// What a ship with `since` does with pages edited in Notion since the last ship.
+    pub on_conflict: ConflictPolicy,
+    /// Where to send what the ship is up to, as it goes.
+    pub progress: Option<UnboundedSender<ShipProgress>>,
+}
+
+impl ShipOptions {
+    fn progress(&self, progress: ShipProgress) {
+        if let Some(sender) = &self.progress {
+            // Nobody listening anymore doesn't stop the ship.
+            let _ = sender.send(progress);
+        }
+    }
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Warnings for the inline databases on the page of a directory, which Notion adds at the end of
+/// the page, below its subpages, with the blocks after them, wherever the intro puts them.
+fn databases_below_subpages(path: &Path, request: &AppendBlockRequest) -> Vec<Diagnostic> {
+    request
+        .children
+        .iter()
+        .filter_map(|c| match &c.content {
+            BlockContent::ChildDatabase { child_database } => Some(child_database),
+            _ => None,
+        })
+        .map(|d| {
+            let message = format!(
+                "Notion adds databases at the end of a page, so \"{}\" and what follows it go below the subpages",
+                d.title
+            );
+            Diagnostic::warning(&path.display().to_string(), None, message)
+        })
+        .collect()
+}
+
+/// The content of a page a ship writes, once its pages are all created.
+struct PageWrite {
+    page_id: String,
+    /// The file, or merged directory, relative to the shipped root.
+    path: PathBuf,
+    request: AppendBlockRequest,
+    is_replaced: bool,
+    anchor_id: Option<String>,
+}
+
+/// How one attempt at a request went.
+enum Attempt {
+    Done(Response),
+    /// Worth sending again, after `after` when Notion asked for a wait. `may_have_landed` when
+    /// Notion may have carried the request out anyway, after a timeout or an error on its side.
+    Retry {
+        after: Option<Duration>,
+        may_have_landed: bool,
+    },
+}
+
+/// Whether `block` is a child page titled `title`, still empty, created by the bot user
+/// `bot_id` since `since`. Notion rounds creation times down to the minute, so a page someone
+/// made within the same minute is only told apart by who created it.
+pub fn is_created_page(block: &Value, title: &str, since: DateTime<Utc>, bot_id: &str) -> bool {
+    let created = block["created_time"]
+        .as_str()
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| t.with_timezone(&Utc));
+    let since = since.duration_trunc(TimeDelta::minutes(1)).unwrap_or(since);
+    block["type"] == "child_page"
+        && block["child_page"]["title"] == title
+        && !block["has_children"].as_bool().unwrap_or(true)
+        && block["created_by"]["id"] == bot_id
+        && created.is_some_and(|c| c >= since)
+}
+
+/// What the page for a directory is shipped with.
+struct DirectoryPage {
+    title: String,
+    emoji: Option<String>,
+    cover: Option<String>,
+    description: Option<String>,
+}
+
+/// The page for `sub_dir`, from its `intro.md` arguments first, then its `.notation.toml`,
+/// then the first heading of its intro with `title_strategy = "first_heading"`, then the
+/// directory name, with the title through `[content] title_template` and the emoji
+/// from `[emoji]` when none is set.
+fn get_directory_page_arguments(
+    sub_dir: &Path,
+    directory_settings: &DirectorySettingsTree,
+    settings: &NotationSettings,
+) -> DirectoryPage {
+    let dir_settings = directory_settings.get(sub_dir).cloned().unwrap_or_default();
+    let dir_name = sub_dir
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let dir_name = if settings.content.pretty_titles {
+        pretty_title(&dir_name)
+    } else {
+        dir_name
+    };
+    let title = match (dir_settings.title, dir_settings.heading) {
+        (Some(title), _) => title,
+        (None, Some(heading)) if directory_settings.is_titled_by_intro(sub_dir) => heading,
+        _ => dir_name,
+    };
+    let emoji = dir_settings
+        .emoji
+        .or_else(|| settings.emoji.emoji_for(sub_dir, &title));
+    DirectoryPage {
+        title: settings.content.page_title(&title),
+        emoji,
+        cover: dir_settings.cover,
+        description: dir_settings.description,
+    }
+}
+
+/// The `intro.md` whose content and arguments belong to the page for `sub_dir`, relative to
+/// the shipped `root`. An intro in another enabled format, like `intro.adoc`, is used when
+/// there is no `intro.md`.
+pub fn get_intro_path(root: &Path, sub_dir: &Path) -> PathBuf {
+    let dir = root.join(sub_dir);
+    source_extensions()
+        .into_iter()
+        .map(|e| dir.join(format!("{}.{}", INTRO_FILENAME, e)))
+        .find(|p| p.is_file())
+        .unwrap_or(dir.join(format!("{}.md", INTRO_FILENAME)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::path::PathBuf;
+
+    use chrono::{TimeZone, Utc};
+    use serde_json::json;
+
+    use crate::notion::client::{is_created_page, NotionClient, ShipOptions};
+    use crate::notion::clock::{placeholder_id, FixedClock};
+    use crate::notion::fake::FakeNotion;
+    use crate::notion::parent::AmbiguousParentError;
+    use crate::settings::notation::NotationSettings;
+    use crate::sync::state::SyncState;
+    use crate::sync::status::{ConflictError, ConflictPolicy};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_is_created_page() {
+        let since = Utc.with_ymd_and_hms(2026, 3, 4, 10, 15, 42).unwrap();
+        let block = |title: &str, created: &str, has_children: bool| {
+            json!({
+                "id": "aaa",
+                "type": "child_page",
+                "child_page": { "title": title },
+                "created_time": created,
+                "created_by": { "object": "user", "id": "bot" },
+                "has_children": has_children,
+            })
+        };
+        assert!(is_created_page(&block("Guide", "2026-03-04T10:15:00.000Z", false), "Guide", since, "bot"));
+        assert!(is_created_page(&block("Guide", "2026-03-04T10:16:00.000Z", false), "Guide", since, "bot"));
+        assert!(!is_created_page(&block("Guide", "2026-03-04T10:14:00.000Z", false), "Guide", since, "bot"));
+        assert!(!is_created_page(&block("Guide", "2026-03-04T10:15:00.000Z", true), "Guide", since, "bot"));
+        assert!(!is_created_page(&block("FAQ", "2026-03-04T10:15:00.000Z", false), "Guide", since, "bot"));
+        // A page someone made in the same minute is theirs, not the one the request created.
+        assert!(!is_created_page(&block("Guide", "2026-03-04T10:15:00.000Z", false), "Guide", since, "other bot"));
+        let paragraph = json!({ "type": "paragraph", "created_time": "2026-03-04T10:15:00.000Z", "has_children": false });
+        assert!(!is_created_page(&paragraph, "Guide", since, "bot"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_reproducible_plans() {
+        let dir = std::env::temp_dir().join(format!("notation-plans-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("guides")).unwrap();
+        std::fs::write(dir.join("guides/setup.md"), "# Setup\n\nSet it up.\n").unwrap();
+        std::fs::write(dir.join("faq.md"), "# FAQ\n\nAsk.\n").unwrap();
+        let settings: NotationSettings = serde_json::from_value(json!({
+            "notion": {"secret": "ntn_reproducible_plans", "parent_page": "Docs"},
+            "footer": {"enabled": true, "template": "Shipped at {timestamp}"}
+        }))
+        .unwrap();
+        let plan = || async {
+            let clock = FixedClock(Utc.with_ymd_and_hms(2026, 3, 4, 10, 15, 42).unwrap());
+            let client = NotionClient::from_settings(settings.clone()).unwrap().with_clock(clock);
+            let (_, plan) = client.plan(dir.display().to_string(), "parent").await.unwrap();
+            serde_json::to_value(&plan.pages).unwrap()
+        };
+        let pages = plan().await;
+        assert_eq!(pages, plan().await);
+        let ids: Vec<&str> = pages.as_array().unwrap().iter().map(|p| p["page_id"].as_str().unwrap()).collect();
+        assert!(ids.contains(&placeholder_id("guides").as_str()));
+        assert!(ids.contains(&placeholder_id("guides/setup.md").as_str()));
+        assert!(pages.to_string().contains("2026-03-04"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_directory_titled_by_intro() {
+        let dir = std::env::temp_dir().join(format!("notation-intro-titles-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("guides")).unwrap();
+        std::fs::create_dir_all(dir.join("api")).unwrap();
+        std::fs::write(dir.join("guides/intro.md"), "# The Guides\n\nRead on.\n").unwrap();
+        std::fs::write(dir.join("api/intro.md"), "---\ntitle: API\n---\n# The API\n\nCall it.\n").unwrap();
+        let settings: NotationSettings = serde_json::from_value(json!({
+            "notion": {"secret": "ntn_intro_titles", "parent_page": "Docs"},
+            "content": {"title_strategy": "first_heading"}
+        }))
+        .unwrap();
+        let client = NotionClient::from_settings(settings).unwrap();
+        let (_, plan) = client.plan(dir.display().to_string(), "parent").await.unwrap();
+        let pages = serde_json::to_value(&plan.pages).unwrap();
+        let page = |id: String| {
+            let pages = pages.as_array().unwrap();
+            let found = pages.iter().filter(|p| p["page_id"] == id.as_str());
+            found.map(|p| p.to_string()).collect::<Vec<_>>().join("\n")
+        };
+
+        let guides = page(placeholder_id("guides"));
+        assert!(guides.contains("\"The Guides\""));
+        assert!(!guides.contains("heading_1"));
+        // An explicit title wins, and the heading stays on the page.
+        let api = page(placeholder_id("api"));
+        assert!(api.contains("\"API\""));
+        assert!(api.contains("heading_1"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_ambiguous_parent_under_hidden_page() {
+        let fake = FakeNotion::start().await;
+        let team = fake.add_page(None, "Team");
+        let engineering = fake.add_page(Some(&team), "Engineering");
+        let handbook = fake.add_page(Some(&engineering), "Handbook");
+        let marketing = fake.add_page(None, "Marketing");
+        let other = fake.add_page(Some(&marketing), "Handbook");
+        fake.hide(&team);
+        let client = fake.client(&marketing, json!({}));
+
+        // The breadcrumb stops at the page the integration can't see.
+        let error = client.get_parent_id_by_name("Handbook".to_string()).await.unwrap_err();
+        let ambiguous = error.downcast_ref::<AmbiguousParentError>().unwrap();
+        let mut breadcrumbs: Vec<String> = ambiguous.candidates.iter().map(|c| c.breadcrumb()).collect();
+        breadcrumbs.sort();
+        assert_eq!(breadcrumbs, vec!["Engineering / Handbook", "Marketing / Handbook"]);
+
+        assert_eq!(client.get_parent_id_by_name("Engineering/Handbook".to_string()).await.unwrap(), handbook);
+        assert_eq!(client.get_parent_id_by_name("Marketing/Handbook".to_string()).await.unwrap(), other);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_database_below_subpages() {
+        let dir = std::env::temp_dir().join(format!("notation-database-subpages-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("releases")).unwrap();
+        let table = "<!-- notation:database name=\"Versions\" -->\n| Version |\n| - |\n| 1.0 |\n";
+        std::fs::write(dir.join("releases/intro.md"), format!("# Releases\n\n{}", table)).unwrap();
+        std::fs::write(dir.join("releases/v1.md"), format!("# 1.0\n\n{}", table)).unwrap();
+        let settings: NotationSettings = serde_json::from_value(json!({
+            "notion": {"secret": "ntn_database_subpages", "parent_page": "Docs"}
+        }))
+        .unwrap();
+        let client = NotionClient::from_settings(settings).unwrap();
+        let (summary, _) = client.plan(dir.display().to_string(), "parent").await.unwrap();
+        let warnings: Vec<&str> = summary
+            .warnings
+            .iter()
+            .filter(|w| w.message.contains("below the subpages"))
+            .map(|w| w.file.as_str())
+            .collect();
+        assert_eq!(warnings, vec![dir.join("releases/intro.md").display().to_string()]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_ship_conflicts() {
+        let dir = std::env::temp_dir().join(format!("notation-ship-conflicts-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.md"), "# A\n\nFirst\n").unwrap();
+        std::fs::write(dir.join("b.md"), "# B\n\nFirst\n").unwrap();
+        let fake = FakeNotion::start().await;
+        let docs = fake.add_page(None, "Docs");
+        let client = fake.client(&docs, json!({}));
+        client.ship(dir.display().to_string(), &ShipOptions::default()).await.unwrap();
+        let state = SyncState::load(&SyncState::path(&dir, None)).unwrap().unwrap();
+        let (a, b) = (state.pages["a.md"].page_id.clone(), state.pages["b.md"].page_id.clone());
+        let text = |id: &str| {
+            let paragraph = fake.children(id).into_iter().find(|c| c["type"] == "paragraph").unwrap();
+            paragraph["paragraph"]["rich_text"][0]["text"]["content"].as_str().unwrap().to_string()
+        };
+
+        std::fs::write(dir.join("a.md"), "# A\n\nSecond\n").unwrap();
+        std::fs::write(dir.join("b.md"), "# B\n\nSecond\n").unwrap();
+        fake.edit(&a, "2099-01-01T00:00:00.000Z");
+        let ship = |on_conflict: ConflictPolicy| ShipOptions {
+            only: Some(HashSet::from([PathBuf::from("a.md"), PathBuf::from("b.md")])),
+            on_conflict,
+            ..Default::default()
+        };
+
+        let error = client.ship(dir.display().to_string(), &ship(ConflictPolicy::Fail)).await.unwrap_err();
+        assert_eq!(error.downcast_ref::<ConflictError>().unwrap().pages, vec!["a.md"]);
+        assert_eq!((text(&a), text(&b)), ("First".to_string(), "First".to_string()));
+
+        let summary = client.ship(dir.display().to_string(), &ship(ConflictPolicy::Skip)).await.unwrap();
+        assert_eq!(summary.skipped, vec!["a.md"]);
+        assert_eq!((text(&a), text(&b)), ("First".to_string(), "Second".to_string()));
+
+        client.ship(dir.display().to_string(), &ship(ConflictPolicy::Overwrite)).await.unwrap();
+        assert_eq!(text(&a), "Second");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_ship_stops() {
+        let dir = std::env::temp_dir().join(format!("notation-ship-stops-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["a", "b", "c"] {
+            std::fs::write(dir.join(format!("{}.md", name)), format!("# {}\n\nText of {}\n", name, name)).unwrap();
+        }
+        let fake = FakeNotion::start().await;
+        let docs = fake.add_page(None, "Docs");
+        let settings = json!({ "sync": { "concurrency": 1 } });
+        let client = fake.client(&docs, settings.clone()).with_stop(fake.stop_after(1));
+        let summary = client.ship(dir.display().to_string(), &ShipOptions::default()).await.unwrap();
+        assert_eq!(summary.interrupted, vec!["b.md", "c.md"]);
+
+        // Every page was created, only the first written: the others are kept without a hash
+        // or an edit time, so that the next ship neither takes them for unchanged nor for
+        // edited in Notion.
+        let state = SyncState::load(&SyncState::path(&dir, None)).unwrap().unwrap();
+        assert_eq!(state.pages.keys().collect::<Vec<_>>(), vec!["a.md", "b.md", "c.md"]);
+        assert!(state.pages["a.md"].source_hash.is_some());
+        assert!(state.pages["b.md"].source_hash.is_none() && state.pages["c.md"].source_hash.is_none());
+        assert!(state.pages.values().all(|p| p.last_edited_time.is_none()));
+        assert_eq!(fake.children(&state.pages["a.md"].page_id).len(), 2);
+        assert!(fake.children(&state.pages["b.md"].page_id).is_empty());
+
+        // Shipping again what changed since the last whole ship, all three files here, fills in
+        // the pages already created.
+        let client = fake.client(&docs, settings);
+        let only = HashSet::from(["a.md", "b.md", "c.md"].map(PathBuf::from));
+        let options = ShipOptions {
+            only: Some(only),
+            ..Default::default()
+        };
+        let summary = client.ship(dir.display().to_string(), &options).await.unwrap();
+        assert!(summary.interrupted.is_empty());
+        assert_eq!(fake.page_titles(&docs), vec!["a", "b", "c"]);
+        let resumed = SyncState::load(&SyncState::path(&dir, None)).unwrap().unwrap();
+        for (key, page) in resumed.pages.iter() {
+            assert_eq!(page.page_id, state.pages[key].page_id);
+            assert!(page.source_hash.is_some() && page.last_edited_time.is_some(), "{}", key);
+            assert_eq!(fake.children(&page.page_id).len(), 2, "{}", key);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_nested_heading_links() {
+        let dir = std::env::temp_dir().join(format!("notation-nested-heading-links-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let a = "# A\n\n:::toggle Details\nSee [setup](./b.md#setup).\n:::\n";
+        let b = "# B\n\n::::columns\n:::column\n## Intro\n:::\n:::column\n## Setup\n:::\n::::\n";
+        std::fs::write(dir.join("a.md"), a).unwrap();
+        std::fs::write(dir.join("b.md"), b).unwrap();
+        let fake = FakeNotion::start().await;
+        let docs = fake.add_page(None, "Docs");
+        let client = fake.client(&docs, json!({}));
+        let summary = client.ship(dir.display().to_string(), &ShipOptions::default()).await.unwrap();
+        assert!(summary.warnings.is_empty(), "{:?}", summary.warnings);
+
+        let state = SyncState::load(&SyncState::path(&dir, None)).unwrap().unwrap();
+        let find = |id: &str, block_type: &str| {
+            fn walk(fake: &FakeNotion, id: &str, block_type: &str) -> Option<serde_json::Value> {
+                fake.children(id).into_iter().find_map(|c| match c["type"] == block_type {
+                    true => Some(c),
+                    false => walk(fake, c["id"].as_str().unwrap(), block_type),
+                })
+            }
+            walk(&fake, id, block_type).unwrap()
+        };
+        let paragraph = find(&state.pages["a.md"].page_id, "paragraph");
+        let url = paragraph["paragraph"]["rich_text"][1]["text"]["link"]["url"].as_str().unwrap().to_string();
+        let columns = fake.children(find(&state.pages["b.md"].page_id, "column_list")["id"].as_str().unwrap());
+        let heading = &fake.children(columns[1]["id"].as_str().unwrap())[0];
+        assert_eq!(heading["heading_2"]["rich_text"][0]["text"]["content"], "Setup");
+        assert!(url.ends_with(&format!("#{}", heading["id"].as_str().unwrap().replace('-', ""))), "{}", url);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
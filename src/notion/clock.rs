@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+
+use crate::sync::state::content_hash;
+
+/// Where a ship takes the time it stamps on pages, and the ids of the pages a simulated ship
+/// pretends to create, so tests and snapshots of dry runs can pin both. The waits between
+/// retries, and the checks of what a failed request created, keep to the system clock.
+pub trait ShipClock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+
+    /// The id a simulated ship gives the page of `key`, a path of the shipped tree.
+    fn placeholder_id(&self, key: &str) -> String {
+        placeholder_id(key)
+    }
+}
+
+/// The system clock, the one of real ships.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl ShipClock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock stopped at a time.
+#[derive(Clone, Copy, Debug)]
+pub struct FixedClock(pub DateTime<Utc>);
+
+impl ShipClock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
+
+/// A 32 hex digit id, like Notion's, from the hash of `key`, the same on every run.
+pub fn placeholder_id(key: &str) -> String {
+    content_hash(key.as_bytes())[..32].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+
+    use crate::notion::clock::{placeholder_id, FixedClock, ShipClock};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_placeholder_ids() {
+        let id = placeholder_id("guides/setup.md");
+        assert_eq!(id.len(), 32);
+        assert!(id.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(id, placeholder_id("guides/setup.md"));
+        assert_ne!(id, placeholder_id("guides"));
+
+        let clock = FixedClock(DateTime::from_timestamp(1_700_000_000, 0).unwrap());
+        assert_eq!(clock.now().timestamp(), 1_700_000_000);
+        assert_eq!(clock.placeholder_id("guides"), placeholder_id("guides"));
+    }
+}
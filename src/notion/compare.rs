@@ -0,0 +1,251 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use glob::glob;
+use serde_json::Value;
+use similar::{capture_diff_slices, Algorithm, DiffOp, TextDiff};
+
+use crate::notion::export::ExportPlan;
+
+/// How a page of the current conversion differs from the same page of an earlier export.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Difference {
+    /// A page the earlier export doesn't have.
+    AddedPage(PathBuf),
+    /// A page of the earlier export that isn't shipped anymore.
+    RemovedPage(PathBuf),
+    /// The request creating the page changed, like its title or emoji.
+    ChangedPage { file: PathBuf, diff: String },
+    /// A block that wasn't there, at its index in the current conversion.
+    AddedBlock { file: PathBuf, index: usize, block: String },
+    /// A block that isn't there anymore, at its index in the earlier export.
+    RemovedBlock { file: PathBuf, index: usize, block: String },
+    /// A block that changed, at its index in the current conversion.
+    ChangedBlock { file: PathBuf, index: usize, diff: String },
+}
+
+impl Difference {
+    pub fn file(&self) -> &Path {
+        match self {
+            Difference::AddedPage(file) | Difference::RemovedPage(file) => file,
+            Difference::ChangedPage { file, .. }
+            | Difference::AddedBlock { file, .. }
+            | Difference::RemovedBlock { file, .. }
+            | Difference::ChangedBlock { file, .. } => file,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CompareReport {
+    /// How many pages were compared, across both sides.
+    pub pages: usize,
+    pub differences: Vec<Difference>,
+}
+
+impl CompareReport {
+    pub fn is_same(&self) -> bool {
+        self.differences.is_empty()
+    }
+}
+
+/// The pages of an export written to `dir`, by their file name in it.
+pub fn read_export(dir: &Path) -> Result<BTreeMap<PathBuf, Value>> {
+    if !dir.is_dir() {
+        return Err(anyhow!("{} is not a directory written by export", dir.display()));
+    }
+    let pattern = format!("{}/**/*.json", dir.display().to_string().trim_end_matches('/'));
+    let mut pages = BTreeMap::new();
+    for entry in glob(&pattern)? {
+        let path = entry?;
+        let contents = std::fs::read_to_string(&path)?;
+        let page: Value = serde_json::from_str(&contents)
+            .map_err(|e| anyhow!("failed to read exported page {}: {}", path.display(), e))?;
+        pages.insert(path.strip_prefix(dir)?.to_path_buf(), page);
+    }
+    Ok(pages)
+}
+
+/// The pages of `plan`, as export would write them, by their file name.
+pub fn plan_pages(plan: &ExportPlan) -> Result<BTreeMap<PathBuf, Value>> {
+    plan.pages
+        .iter()
+        .map(|p| Ok((p.file_name(), serde_json::to_value(p)?)))
+        .collect()
+}
+
+/// Compares the pages of the current conversion with those of an earlier export, block by
+/// block. Page ids are made up anew on every export, so they are swapped for the paths of
+/// their pages on both sides first.
+pub fn compare(
+    previous: BTreeMap<PathBuf, Value>,
+    current: BTreeMap<PathBuf, Value>,
+) -> CompareReport {
+    let previous = without_page_ids(previous);
+    let current = without_page_ids(current);
+    let mut report = CompareReport::default();
+    let mut files: Vec<&PathBuf> = previous.keys().chain(current.keys()).collect();
+    files.sort();
+    files.dedup();
+    report.pages = files.len();
+    for file in files {
+        match (previous.get(file), current.get(file)) {
+            (Some(before), Some(after)) => compare_page(file, before, after, &mut report.differences),
+            (None, Some(_)) => report.differences.push(Difference::AddedPage(file.clone())),
+            (Some(_), None) => report.differences.push(Difference::RemovedPage(file.clone())),
+            (None, None) => {}
+        }
+    }
+    report
+}
+
+fn compare_page(file: &Path, before: &Value, after: &Value, differences: &mut Vec<Difference>) {
+    if before["create_page"] != after["create_page"] {
+        differences.push(Difference::ChangedPage {
+            file: file.to_path_buf(),
+            diff: json_diff(&before["create_page"], &after["create_page"]),
+        });
+    }
+    let before = blocks(before);
+    let after = blocks(after);
+    let before_keys: Vec<String> = before.iter().map(|b| b.to_string()).collect();
+    let after_keys: Vec<String> = after.iter().map(|b| b.to_string()).collect();
+    for op in capture_diff_slices(Algorithm::Myers, &before_keys, &after_keys) {
+        if let DiffOp::Equal { .. } = op {
+            continue;
+        }
+        // Blocks replaced one for one are changes, the rest were added or removed.
+        let (old, new) = (op.old_range(), op.new_range());
+        let paired = old.len().min(new.len());
+        for (i, j) in old.clone().zip(new.clone()) {
+            differences.push(Difference::ChangedBlock {
+                file: file.to_path_buf(),
+                index: j,
+                diff: json_diff(before[i], after[j]),
+            });
+        }
+        for (i, block) in before.iter().enumerate().take(old.end).skip(old.start + paired) {
+            differences.push(Difference::RemovedBlock {
+                file: file.to_path_buf(),
+                index: i,
+                block: pretty(block),
+            });
+        }
+        for (j, block) in after.iter().enumerate().take(new.end).skip(new.start + paired) {
+            differences.push(Difference::AddedBlock {
+                file: file.to_path_buf(),
+                index: j,
+                block: pretty(block),
+            });
+        }
+    }
+}
+
+fn blocks(page: &Value) -> Vec<&Value> {
+    page["append_blocks"]["children"]
+        .as_array()
+        .map(|c| c.iter().collect())
+        .unwrap_or_default()
+}
+
+fn pretty(value: &Value) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_default()
+}
+
+fn json_diff(before: &Value, after: &Value) -> String {
+    TextDiff::from_lines(&pretty(before), &pretty(after))
+        .unified_diff()
+        .header("previous", "current")
+        .to_string()
+}
+
+/// Swaps the id of every page for `<path>`, wherever it appears, like in links between pages.
+fn without_page_ids(pages: BTreeMap<PathBuf, Value>) -> BTreeMap<PathBuf, Value> {
+    let ids: HashMap<String, String> = pages
+        .values()
+        .filter(|p| p["create_page"].is_object())
+        .filter_map(|p| Some((p["page_id"].as_str()?.to_string(), format!("<{}>", p["path"].as_str()?))))
+        .collect();
+    pages
+        .into_iter()
+        .map(|(file, mut page)| {
+            replace_ids(&mut page, &ids);
+            (file, page)
+        })
+        .collect()
+}
+
+fn replace_ids(value: &mut Value, ids: &HashMap<String, String>) {
+    match value {
+        Value::String(s) => {
+            for (id, path) in ids.iter() {
+                if s.contains(id.as_str()) {
+                    *s = s.replace(id.as_str(), path);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(|v| replace_ids(v, ids)),
+        Value::Object(map) => map.values_mut().for_each(|v| replace_ids(v, ids)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    use serde_json::json;
+
+    use crate::notion::compare::{compare, Difference};
+
+    fn page(id: &str, path: &str, title: &str, blocks: Vec<&str>) -> serde_json::Value {
+        let children: Vec<_> = blocks
+            .into_iter()
+            .map(|t| json!({ "type": "paragraph", "paragraph": { "rich_text": [{ "text": { "content": t } }] } }))
+            .collect();
+        json!({
+            "path": path,
+            "page_id": id,
+            "create_page": { "parent": { "page_id": "PARENT_PAGE_ID" }, "title": title },
+            "append_blocks": { "children": children },
+        })
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_compare_exports() {
+        let previous = BTreeMap::from([
+            (PathBuf::from("guide.json"), page("aaa", "guide.md", "Guide", vec!["intro", "see https://www.notion.so/bbb", "old"])),
+            (PathBuf::from("api.json"), page("bbb", "api.md", "API", vec!["auth"])),
+            (PathBuf::from("gone.json"), page("ccc", "gone.md", "Gone", vec![])),
+        ]);
+        let current = BTreeMap::from([
+            (PathBuf::from("guide.json"), page("xxx", "guide.md", "Guide", vec!["intro", "see https://www.notion.so/yyy", "new", "more"])),
+            (PathBuf::from("api.json"), page("yyy", "api.md", "The API", vec!["auth"])),
+            (PathBuf::from("new.json"), page("zzz", "new.md", "New", vec![])),
+        ]);
+        let report = compare(previous, current);
+        assert_eq!(report.pages, 4);
+        let kinds: Vec<String> = report
+            .differences
+            .iter()
+            .map(|d| match d {
+                Difference::AddedPage(f) => format!("+ {}", f.display()),
+                Difference::RemovedPage(f) => format!("- {}", f.display()),
+                Difference::ChangedPage { file, .. } => format!("~ {}", file.display()),
+                Difference::AddedBlock { file, index, .. } => format!("+ {} #{}", file.display(), index),
+                Difference::RemovedBlock { file, index, .. } => format!("- {} #{}", file.display(), index),
+                Difference::ChangedBlock { file, index, .. } => format!("~ {} #{}", file.display(), index),
+            })
+            .collect();
+        assert_eq!(kinds, vec!["~ api.json", "- gone.json", "~ guide.json #2", "+ guide.json #3", "+ new.json"]);
+        match &report.differences[2] {
+            Difference::ChangedBlock { diff, .. } => {
+                assert!(diff.contains("-          \"content\": \"old\""));
+                assert!(diff.contains("+          \"content\": \"new\""));
+            }
+            d => panic!("expected a changed block, got {:?}", d),
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveDate};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+/// The type of a database column, from a `{type}` at the end of its header cell.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnType {
+    Title,
+    #[default]
+    Text,
+    Number,
+    Checkbox,
+    Date,
+    Select,
+    MultiSelect,
+    Url,
+}
+
+impl ColumnType {
+    pub fn from_annotation(annotation: &str) -> Option<Self> {
+        match annotation.trim().to_lowercase().as_str() {
+            "title" => Some(ColumnType::Title),
+            "text" => Some(ColumnType::Text),
+            "number" => Some(ColumnType::Number),
+            "checkbox" => Some(ColumnType::Checkbox),
+            "date" => Some(ColumnType::Date),
+            "select" => Some(ColumnType::Select),
+            "multi_select" => Some(ColumnType::MultiSelect),
+            "url" => Some(ColumnType::Url),
+            _ => None,
+        }
+    }
+
+    /// The key Notion names the property type with.
+    fn property_type(&self) -> &'static str {
+        match self {
+            ColumnType::Title => "title",
+            ColumnType::Text => "rich_text",
+            ColumnType::Number => "number",
+            ColumnType::Checkbox => "checkbox",
+            ColumnType::Date => "date",
+            ColumnType::Select => "select",
+            ColumnType::MultiSelect => "multi_select",
+            ColumnType::Url => "url",
+        }
+    }
+
+    /// The property value of a cell, `None` for an empty one.
+    pub fn value(&self, cell: &str) -> Result<Option<Value>> {
+        let cell = cell.trim();
+        if cell.is_empty() {
+            return Ok(None);
+        }
+        let value = match self {
+            ColumnType::Title | ColumnType::Text => {
+                json!([{ "type": "text", "text": { "content": cell } }])
+            }
+            ColumnType::Number => json!(cell
+                .parse::<f64>()
+                .map_err(|_| anyhow!("\"{}\" is not a number", cell))?),
+            ColumnType::Checkbox => match cell.to_lowercase().as_str() {
+                "true" | "yes" | "x" | "✓" | "✅" => json!(true),
+                "false" | "no" | "-" => json!(false),
+                _ => return Err(anyhow!("\"{}\" is not a checkbox, use yes or no", cell)),
+            },
+            ColumnType::Date => {
+                if NaiveDate::parse_from_str(cell, "%Y-%m-%d").is_err()
+                    && DateTime::parse_from_rfc3339(cell).is_err()
+                {
+                    return Err(anyhow!("\"{}\" is not a date like 2024-05-01", cell));
+                }
+                json!({ "start": cell })
+            }
+            ColumnType::Select => {
+                if cell.contains(',') {
+                    return Err(anyhow!("\"{}\" can't be a select option, options can't hold commas", cell));
+                }
+                json!({ "name": cell })
+            }
+            ColumnType::MultiSelect => Value::Array(
+                cell.split(',')
+                    .map(|o| o.trim())
+                    .filter(|o| !o.is_empty())
+                    .map(|o| json!({ "name": o }))
+                    .collect(),
+            ),
+            ColumnType::Url => {
+                Url::parse(cell).map_err(|_| anyhow!("\"{}\" is not a url", cell))?;
+                json!(cell)
+            }
+        };
+        Ok(Some(value))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DatabaseColumn {
+    pub name: String,
+    pub column_type: ColumnType,
+}
+
+/// A table shipped as an inline database. Notion doesn't take databases as blocks, so this one
+/// is created on its own where the block would go. Notion lists it back as a `child_database`
+/// block holding only its title.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChildDatabaseParent {
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub columns: Vec<DatabaseColumn>,
+    /// The cells of each row, one per column.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rows: Vec<Vec<String>>,
+}
+
+impl ChildDatabaseParent {
+    /// The body creating the database on the page `parent_id`.
+    pub fn create_request(&self, parent_id: &str) -> Value {
+        let properties: Map<String, Value> = self
+            .columns
+            .iter()
+            .map(|c| (c.name.clone(), json!({ c.column_type.property_type(): {} })))
+            .collect();
+        json!({
+            "parent": { "type": "page_id", "page_id": parent_id },
+            "is_inline": true,
+            "title": [{ "type": "text", "text": { "content": self.title } }],
+            "properties": properties,
+        })
+    }
+
+    /// The bodies creating the rows in the database `database_id`. Cells that don't fit their
+    /// column are left empty.
+    pub fn row_requests(&self, database_id: &str) -> Vec<Value> {
+        self.rows
+            .iter()
+            .map(|row| {
+                let properties: Map<String, Value> = self
+                    .columns
+                    .iter()
+                    .zip(row.iter())
+                    .filter_map(|(c, cell)| {
+                        let value = c.column_type.value(cell).ok()??;
+                        Some((c.name.clone(), json!({ c.column_type.property_type(): value })))
+                    })
+                    .collect();
+                json!({
+                    "parent": { "database_id": database_id },
+                    "properties": properties,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::notion::database::{ChildDatabaseParent, ColumnType, DatabaseColumn};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_database_requests() {
+        assert_eq!(ColumnType::from_annotation("Multi_Select"), Some(ColumnType::MultiSelect));
+        assert_eq!(ColumnType::from_annotation("money"), None);
+        assert_eq!(ColumnType::Number.value("1.5").unwrap(), Some(json!(1.5)));
+        assert!(ColumnType::Number.value("many").is_err());
+        assert_eq!(ColumnType::Checkbox.value("Yes").unwrap(), Some(json!(true)));
+        assert!(ColumnType::Date.value("2024-05-01").is_ok());
+        assert!(ColumnType::Date.value("May 1st").is_err());
+        assert!(ColumnType::Select.value("a, b").is_err());
+        assert_eq!(ColumnType::MultiSelect.value("a, b").unwrap(), Some(json!([{ "name": "a" }, { "name": "b" }])));
+        assert_eq!(ColumnType::Url.value(" ").unwrap(), None);
+
+        let database = ChildDatabaseParent {
+            title: "Releases".to_string(),
+            columns: vec![
+                DatabaseColumn { name: "Version".to_string(), column_type: ColumnType::Title },
+                DatabaseColumn { name: "Released".to_string(), column_type: ColumnType::Date },
+            ],
+            rows: vec![vec!["1.0".to_string(), "2024-05-01".to_string()], vec!["2.0".to_string(), String::new()]],
+        };
+        let create = database.create_request("page");
+        assert_eq!(create["is_inline"], json!(true));
+        assert_eq!(create["properties"]["Released"], json!({ "date": {} }));
+        let rows = database.row_requests("db");
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["properties"]["Released"], json!({ "date": { "start": "2024-05-01" } }));
+        assert!(rows[1]["properties"].get("Released").is_none());
+    }
+}
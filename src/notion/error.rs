@@ -0,0 +1,92 @@
+use std::fmt::{Display, Formatter};
+
+use reqwest::{Response, StatusCode};
+use serde_json::Value;
+
+use crate::redact::redact;
+
+/// The headers Notion answers requests with the id of, for its support to look them up.
+pub const REQUEST_ID_HEADERS: [&str; 2] = ["x-notion-request-id", "x-request-id"];
+
+/// A request Notion turned down.
+#[derive(Clone, Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    /// The id Notion gave the request, to quote in support tickets.
+    pub request_id: Option<String>,
+    /// What was being done, like `failed to append block`.
+    pub context: String,
+    /// The response body, redacted.
+    pub body: String,
+}
+
+impl ApiError {
+    /// Reads the error out of `response`. The request id is taken from the header, or the
+    /// body when there is none.
+    pub async fn read(response: Response, context: impl Into<String>) -> Self {
+        let status = response.status();
+        let header_id = REQUEST_ID_HEADERS
+            .iter()
+            .find_map(|h| response.headers().get(*h))
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string());
+        let text = response.text().await.unwrap_or_default();
+        let (body, body_id) = match serde_json::from_str::<Value>(&text) {
+            Ok(json) => (json.to_string(), json["request_id"].as_str().map(|s| s.to_string())),
+            Err(_) => (text, None),
+        };
+        ApiError {
+            status,
+            request_id: header_id.or(body_id),
+            context: context.into(),
+            body: redact(&body),
+        }
+    }
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.request_id {
+            Some(id) => write!(f, "(request_status={}, request_id={}) {}: {}", self.status, id, self.context, self.body),
+            None => write!(f, "(request_status={}) {}: {}", self.status, self.context, self.body),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// The Notion request id quoted in an error message, for errors that were turned into text
+/// on their way up.
+pub fn request_id_in(message: &str) -> Option<String> {
+    let start = message.find("request_id=")? + "request_id=".len();
+    let id: String = message[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '-')
+        .collect();
+    Some(id).filter(|id| !id.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+
+    use crate::notion::error::{request_id_in, ApiError};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_api_error() {
+        let mut error = ApiError {
+            status: StatusCode::BAD_REQUEST,
+            request_id: Some("2b5c6a1e-8d0f-4c3a-9b7e-1f2d3c4b5a69".to_string()),
+            context: "failed to append block".to_string(),
+            body: r#"{"code":"validation_error"}"#.to_string(),
+        };
+        let message = format!("failed to ship guide.md: {}", error);
+        assert_eq!(
+            message,
+            r#"failed to ship guide.md: (request_status=400 Bad Request, request_id=2b5c6a1e-8d0f-4c3a-9b7e-1f2d3c4b5a69) failed to append block: {"code":"validation_error"}"#
+        );
+        assert_eq!(request_id_in(&message).as_deref(), Some("2b5c6a1e-8d0f-4c3a-9b7e-1f2d3c4b5a69"));
+        error.request_id = None;
+        assert_eq!(request_id_in(&error.to_string()), None);
+    }
+}
@@ -0,0 +1,211 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::markdown::source::is_source_file;
+use crate::notion::block::AppendBlockRequest;
+use crate::notion::page::CreatePageRequest;
+
+/// The requests a ship would send for one Notion page.
+#[derive(Clone, Serialize)]
+pub struct ExportedPage {
+    /// The markdown file or directory of the page, relative to the shipped root. Empty for the
+    /// parent page.
+    pub path: String,
+    pub page_id: String,
+    /// `None` for the parent page, which already exists.
+    pub create_page: Option<CreatePageRequest>,
+    pub append_blocks: Option<AppendBlockRequest>,
+}
+
+impl ExportedPage {
+    /// `api/auth.md` is written to `api/auth.json`, the page of the `api` directory to
+    /// `api/_directory.json` and the parent page to `_parent.json`.
+    pub fn file_name(&self) -> PathBuf {
+        let path = Path::new(&self.path);
+        if self.path.is_empty() {
+            PathBuf::from("_parent.json")
+        } else if is_source_file(path) {
+            path.with_extension("json")
+        } else {
+            path.join("_directory.json")
+        }
+    }
+}
+
+/// The pages of a ship that is written to disk instead of sent to Notion.
+#[derive(Clone, Default)]
+pub struct ExportPlan {
+    pub pages: Vec<ExportedPage>,
+}
+
+impl ExportPlan {
+    pub fn create_page(
+        &mut self,
+        path: String,
+        page_id: String,
+        parent_id: String,
+        title: String,
+        emoji: Option<String>,
+    ) {
+        let mut create_page_request = CreatePageRequest::new(parent_id, title);
+        if let Some(emoji) = emoji {
+            create_page_request = create_page_request.with_icon(emoji);
+        }
+        self.add_page(path, page_id, create_page_request);
+    }
+
+    /// Records the page `create_page_request` would create for `path`, as `page_id`.
+    pub fn add_page(&mut self, path: String, page_id: String, create_page_request: CreatePageRequest) {
+        self.pages.push(ExportedPage {
+            path,
+            page_id,
+            create_page: Some(create_page_request),
+            append_blocks: None,
+        });
+    }
+
+    /// Records the blocks appended to `page_id`, which is the parent page when no page was
+    /// created for it.
+    pub fn append_blocks(&mut self, page_id: &str, request: AppendBlockRequest) {
+        match self.pages.iter_mut().find(|p| p.page_id == page_id) {
+            Some(p) => p.append_blocks = Some(request),
+            None => self.pages.push(ExportedPage {
+                path: String::new(),
+                page_id: page_id.to_string(),
+                create_page: None,
+                append_blocks: Some(request),
+            }),
+        }
+    }
+
+    /// The pages created right under `page_id`, in shipping order.
+    pub fn children<'a>(&'a self, page_id: &'a str) -> impl Iterator<Item = &'a ExportedPage> {
+        self.pages
+            .iter()
+            .filter(move |p| p.create_page.as_ref().is_some_and(|c| c.parent_id() == page_id))
+    }
+
+    /// An ASCII tree of the pages below `parent_page_id`, with their emojis and how many
+    /// blocks each gets, like
+    ///
+    /// ```text
+    /// 📄 Engineering Docs (2 blocks)
+    /// ├── 📁 api
+    /// │   └── 📄 auth (4 blocks)
+    /// └── 📘 Guide (12 blocks)
+    /// ```
+    pub fn tree(&self, parent_page_id: &str, parent_title: &str) -> String {
+        let mut out = format!("📄 {}{}\n", parent_title, self.block_count(parent_page_id));
+        self.write_tree(&mut out, parent_page_id, "");
+        // The rows of a `[database]` hang off the database rather than the parent page.
+        let mut database_ids: Vec<&str> = self
+            .pages
+            .iter()
+            .filter_map(|p| p.create_page.as_ref().filter(|c| c.is_row()).map(|c| c.parent_id()))
+            .collect();
+        database_ids.sort();
+        database_ids.dedup();
+        for database_id in database_ids {
+            out.push_str(&format!("🗃️ {}\n", database_id));
+            self.write_tree(&mut out, database_id, "");
+        }
+        out
+    }
+
+    fn write_tree(&self, out: &mut String, page_id: &str, prefix: &str) {
+        let children: Vec<&ExportedPage> = self.children(page_id).collect();
+        for (i, child) in children.iter().enumerate() {
+            let is_last = i + 1 == children.len();
+            let create = match &child.create_page {
+                Some(c) => c,
+                None => continue,
+            };
+            let is_directory = !is_source_file(Path::new(&child.path));
+            let emoji = create.emoji().unwrap_or(if is_directory { "📁" } else { "📄" });
+            out.push_str(&format!(
+                "{}{} {} {}{}\n",
+                prefix,
+                if is_last { "└──" } else { "├──" },
+                emoji,
+                create.title(),
+                self.block_count(&child.page_id)
+            ));
+            let prefix = format!("{}{}", prefix, if is_last { "    " } else { "│   " });
+            self.write_tree(out, &child.page_id, &prefix);
+        }
+    }
+
+    fn block_count(&self, page_id: &str) -> String {
+        let count = self
+            .pages
+            .iter()
+            .find(|p| p.page_id == page_id)
+            .and_then(|p| p.append_blocks.as_ref())
+            .map_or(0, |b| b.children.len());
+        match count {
+            0 => String::new(),
+            1 => " (1 block)".to_string(),
+            n => format!(" ({} blocks)", n),
+        }
+    }
+
+    /// Writes one pretty-printed JSON file per page under `out`.
+    pub fn write(&self, out: &Path) -> Result<()> {
+        for page in self.pages.iter() {
+            let path = out.join(page.file_name());
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&path, serde_json::to_string_pretty(page)?)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::notion::block::{AppendBlockRequest, AppendBlockRequestChild};
+    use crate::notion::export::ExportPlan;
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_export_plan() {
+        let mut plan = ExportPlan::default();
+        plan.create_page("api".to_string(), "dir".to_string(), "parent".to_string(), "API".to_string(), None);
+        plan.create_page("api/auth.md".to_string(), "auth".to_string(), "dir".to_string(), "auth".to_string(), None);
+        plan.append_blocks("auth", AppendBlockRequest::new());
+        plan.append_blocks("parent", AppendBlockRequest::new());
+
+        let file_names: Vec<PathBuf> = plan.pages.iter().map(|p| p.file_name()).collect();
+        assert_eq!(
+            file_names,
+            vec![
+                PathBuf::from("api/_directory.json"),
+                PathBuf::from("api/auth.json"),
+                PathBuf::from("_parent.json"),
+            ]
+        );
+        assert!(plan.pages[1].append_blocks.is_some());
+        assert!(plan.pages[2].create_page.is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_tree() {
+        let mut plan = ExportPlan::default();
+        plan.create_page("api".to_string(), "dir".to_string(), "parent".to_string(), "api".to_string(), None);
+        plan.create_page("api/auth.md".to_string(), "auth".to_string(), "dir".to_string(), "auth".to_string(), None);
+        plan.create_page("guide.md".to_string(), "guide".to_string(), "parent".to_string(), "Guide".to_string(), Some("📘".to_string()));
+        plan.append_blocks(
+            "auth",
+            AppendBlockRequest::new_children(vec![AppendBlockRequestChild::new_divider_block()]),
+        );
+        plan.append_blocks("parent", AppendBlockRequest::new());
+        assert_eq!(
+            plan.tree("parent", "Docs"),
+            "📄 Docs\n├── 📁 api\n│   └── 📄 auth (1 block)\n└── 📘 Guide\n"
+        );
+    }
+}
@@ -0,0 +1,398 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use serde_json::{json, Value};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+
+use crate::notion::client::NotionClient;
+use crate::settings::notation::NotationSettings;
+
+/// The edit time of everything the fake creates or changes, unless a test sets another.
+pub const FAKE_EDITED_TIME: &str = "2026-03-04T10:15:00.000Z";
+/// How deep children nest in one append request, Notion turns down deeper ones.
+const MAX_NESTING: usize = 2;
+const MAX_CHILDREN: usize = 100;
+
+/// A stand-in for the Notion API on a local port, keeping pages and blocks in memory, for
+/// tests of what the client does. It turns down append requests like Notion does: nested
+/// more than two levels deep, with more than 100 children to a block, or with an empty
+/// column.
+#[derive(Clone)]
+pub struct FakeNotion {
+    pub base_endpoint: String,
+    state: Arc<Mutex<FakeState>>,
+}
+
+#[derive(Default)]
+struct FakeState {
+    blocks: HashMap<String, FakeBlock>,
+    /// The ids answered with a 404, like pages not shared with the integration.
+    hidden: HashSet<String>,
+    /// Every request, as its method and path.
+    requests: Vec<String>,
+    /// How many more appends to answer before turning the stop true, see [`FakeNotion::stop_after`].
+    stop: Option<(usize, watch::Sender<bool>)>,
+    next_id: usize,
+}
+
+struct FakeBlock {
+    /// The block as Notion lists it, without its children.
+    value: Value,
+    parent: Option<String>,
+    children: Vec<String>,
+    in_trash: bool,
+    last_edited_time: String,
+}
+
+impl FakeNotion {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let fake = FakeNotion {
+            base_endpoint: format!("http://{}/v1", listener.local_addr().unwrap()),
+            state: Arc::default(),
+        };
+        let state = fake.state.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, state.clone()));
+            }
+        });
+        fake
+    }
+
+    /// A client of the fake shipping under `parent_page_id`, with `settings` over the
+    /// defaults.
+    pub fn client(&self, parent_page_id: &str, settings: Value) -> NotionClient {
+        let mut base = json!({ "notion": { "secret": "ntn_fake", "parent_page": "Docs" } });
+        merge(&mut base, settings);
+        let settings: NotationSettings = serde_json::from_value(base).unwrap();
+        NotionClient::from_settings(settings)
+            .unwrap()
+            .with_base_endpoint(self.base_endpoint.clone())
+            .with_parent_page_id(parent_page_id.to_string())
+    }
+
+    /// Adds a page titled `title`, under `parent` or at the top of the workspace.
+    pub fn add_page(&self, parent: Option<&str>, title: &str) -> String {
+        let mut state = self.state.lock().unwrap();
+        let value = json!({ "object": "block", "type": "child_page", "child_page": { "title": title } });
+        state.insert(parent, value)
+    }
+
+    /// Adds a paragraph to the page or block `parent`.
+    pub fn add_paragraph(&self, parent: &str, text: &str) -> String {
+        let mut state = self.state.lock().unwrap();
+        let value = json!({ "object": "block", "type": "paragraph", "paragraph": { "rich_text": rich_text(text) } });
+        state.insert(Some(parent), value)
+    }
+
+    /// Answers every request about `id` with a 404.
+    pub fn hide(&self, id: &str) {
+        self.state.lock().unwrap().hidden.insert(id.to_string());
+    }
+
+    /// Makes it look like `id` was edited in Notion at `time`.
+    pub fn edit(&self, id: &str, time: &str) {
+        if let Some(b) = self.state.lock().unwrap().blocks.get_mut(id) {
+            b.last_edited_time = time.to_string();
+        }
+    }
+
+    /// The children of `id` not in the trash, as Notion lists them.
+    pub fn children(&self, id: &str) -> Vec<Value> {
+        let state = self.state.lock().unwrap();
+        state.children(id)
+    }
+
+    /// The titles of the pages under `id` not in the trash, all the way down, in order.
+    pub fn page_titles(&self, id: &str) -> Vec<String> {
+        let mut titles = Vec::new();
+        for child in self.children(id) {
+            if let Some(title) = child["child_page"]["title"].as_str() {
+                titles.push(title.to_string());
+                titles.extend(self.page_titles(child["id"].as_str().unwrap()));
+            }
+        }
+        titles
+    }
+
+    pub fn is_trashed(&self, id: &str) -> bool {
+        self.state.lock().unwrap().is_trashed(id)
+    }
+
+    /// A stop, for [`NotionClient::with_stop`], that turns true as the `appends`th append
+    /// request is answered, so that a ship stops at the same point every time.
+    pub fn stop_after(&self, appends: usize) -> watch::Receiver<bool> {
+        let (sender, receiver) = watch::channel(false);
+        self.state.lock().unwrap().stop = Some((appends, sender));
+        receiver
+    }
+
+    /// The requests sent so far, like `PATCH /v1/pages/<id>`.
+    pub fn requests(&self) -> Vec<String> {
+        self.state.lock().unwrap().requests.clone()
+    }
+}
+
+impl FakeState {
+    fn insert(&mut self, parent: Option<&str>, mut value: Value) -> String {
+        self.next_id += 1;
+        let id = format!("{:08x}-0000-4000-8000-{:012x}", self.next_id, self.next_id);
+        value["id"] = json!(id);
+        value["created_time"] = json!(FAKE_EDITED_TIME);
+        if let Some(p) = parent.and_then(|p| self.blocks.get_mut(p)) {
+            p.children.push(id.clone());
+        }
+        self.blocks.insert(
+            id.clone(),
+            FakeBlock {
+                value,
+                parent: parent.map(|p| p.to_string()),
+                children: Vec::new(),
+                in_trash: false,
+                last_edited_time: FAKE_EDITED_TIME.to_string(),
+            },
+        );
+        id
+    }
+
+    /// Adds `children`, as sent to append, to `parent` after `after`, with theirs. Returns
+    /// the ids of the top ones.
+    fn append(&mut self, parent: &str, children: &[Value], after: Option<&str>) -> Vec<String> {
+        let mut ids = Vec::new();
+        for child in children {
+            let mut value = child.clone();
+            let block_type = value["type"].as_str().unwrap_or_default().to_string();
+            let nested = value[&block_type]
+                .as_object_mut()
+                .and_then(|o| o.remove("children"))
+                .unwrap_or(json!([]));
+            let id = self.insert(Some(parent), value);
+            self.append(&id, nested.as_array().unwrap(), None);
+            ids.push(id);
+        }
+        if let Some(after) = after {
+            let siblings = &mut self.blocks.get_mut(parent).unwrap().children;
+            siblings.retain(|c| !ids.contains(c));
+            let at = siblings.iter().position(|c| c == after).map_or(siblings.len(), |p| p + 1);
+            siblings.splice(at..at, ids.iter().cloned());
+        }
+        ids
+    }
+
+    fn is_trashed(&self, id: &str) -> bool {
+        match self.blocks.get(id) {
+            Some(b) => b.in_trash || b.parent.as_deref().is_some_and(|p| self.is_trashed(p)),
+            None => true,
+        }
+    }
+
+    fn children(&self, id: &str) -> Vec<Value> {
+        let block = match self.blocks.get(id) {
+            Some(b) => b,
+            None => return Vec::new(),
+        };
+        block
+            .children
+            .iter()
+            .filter(|c| !self.blocks[*c].in_trash)
+            .map(|c| self.block(c))
+            .collect()
+    }
+
+    fn block(&self, id: &str) -> Value {
+        let block = &self.blocks[id];
+        let mut value = block.value.clone();
+        value["has_children"] = json!(block.children.iter().any(|c| !self.blocks[c].in_trash));
+        value["in_trash"] = json!(self.is_trashed(id));
+        value["last_edited_time"] = json!(block.last_edited_time);
+        value
+    }
+
+    fn page(&self, id: &str) -> Value {
+        let block = &self.blocks[id];
+        let parent = match &block.parent {
+            Some(p) => json!({ "type": "page_id", "page_id": p }),
+            None => json!({ "type": "workspace", "workspace": true }),
+        };
+        let title = block.value["child_page"]["title"].as_str().unwrap_or_default();
+        json!({
+            "object": "page",
+            "id": id,
+            "url": format!("https://www.notion.so/{}", id.replace('-', "")),
+            "parent": parent,
+            "in_trash": self.is_trashed(id),
+            "last_edited_time": block.last_edited_time,
+            "properties": { "title": { "type": "title", "title": [{ "plain_text": title }] } },
+        })
+    }
+
+    fn handle(&mut self, method: &str, path: &str, body: Value) -> (u16, Value) {
+        self.requests.push(format!("{} {}", method, path));
+        let path = path.split('?').next().unwrap_or_default();
+        // Notion takes ids with or without their dashes.
+        let segments: Vec<String> = path.trim_start_matches("/v1/").split('/').map(dashed).collect();
+        let segments: Vec<&str> = segments.iter().map(|s| s.as_str()).collect();
+        if let Some(id) = segments.get(1) {
+            if self.hidden.contains(*id) || (segments.len() > 1 && !self.blocks.contains_key(*id)) {
+                return not_found(id);
+            }
+        }
+        match (method, segments.as_slice()) {
+            ("GET", ["pages", id]) | ("GET", ["blocks", id]) => (200, self.page(id)),
+            ("GET", ["blocks", id, "children"]) => (200, json!({ "results": self.children(id), "has_more": false, "next_cursor": null })),
+            ("PATCH", ["blocks", id, "children"]) => {
+                let children = body["children"].as_array().cloned().unwrap_or_default();
+                if let Some(problem) = append_problem(&children, 0) {
+                    return (400, json!({ "object": "error", "status": 400, "code": "validation_error", "message": problem }));
+                }
+                let ids = self.append(id, &children, body["after"].as_str());
+                if let Some((appends, stop)) = &mut self.stop {
+                    *appends = appends.saturating_sub(1);
+                    if *appends == 0 {
+                        let _ = stop.send(true);
+                    }
+                }
+                let results: Vec<Value> = ids.iter().map(|i| self.block(i)).collect();
+                (200, json!({ "results": results, "has_more": false, "next_cursor": null }))
+            }
+            ("PATCH", ["pages", id]) | ("PATCH", ["blocks", id]) => {
+                let block = self.blocks.get_mut(*id).unwrap();
+                if let Some(in_trash) = body["in_trash"].as_bool() {
+                    block.in_trash = in_trash;
+                }
+                if let Some(title) = body["properties"]["title"]["title"][0]["text"]["content"].as_str() {
+                    block.value["child_page"]["title"] = json!(title);
+                }
+                let block_type = block.value["type"].as_str().unwrap_or_default().to_string();
+                if let Some(text) = body.get(&block_type).and_then(|b| b.get("rich_text")) {
+                    block.value[&block_type]["rich_text"] = text.clone();
+                }
+                block.last_edited_time = FAKE_EDITED_TIME.to_string();
+                (200, self.page(id))
+            }
+            ("POST", ["pages"]) => {
+                let parent = body["parent"]["page_id"].as_str().or(body["parent"]["database_id"].as_str());
+                let parent = parent.unwrap_or_default().to_string();
+                if !self.blocks.contains_key(&parent) {
+                    return not_found(&parent);
+                }
+                let title = body["properties"]["title"]["title"][0]["text"]["content"].as_str().unwrap_or_default();
+                let id = self.insert(Some(&parent), json!({ "object": "block", "type": "child_page", "child_page": { "title": title } }));
+                (200, json!({ "object": "page", "id": id }))
+            }
+            ("POST", ["databases"]) => {
+                let parent = body["parent"]["page_id"].as_str().unwrap_or_default().to_string();
+                if !self.blocks.contains_key(&parent) {
+                    return not_found(&parent);
+                }
+                let title = body["title"][0]["text"]["content"].as_str().unwrap_or_default();
+                let id = self.insert(Some(&parent), json!({ "object": "block", "type": "child_database", "child_database": { "title": title } }));
+                (200, json!({ "object": "database", "id": id }))
+            }
+            ("POST", ["search"]) => {
+                let query = body["query"].as_str().unwrap_or_default().to_lowercase();
+                let results: Vec<Value> = self
+                    .blocks
+                    .iter()
+                    .filter(|(id, b)| b.value["type"] == "child_page" && !self.is_trashed(id) && !self.hidden.contains(*id))
+                    .filter(|(_, b)| b.value["child_page"]["title"].as_str().unwrap_or_default().to_lowercase().contains(&query))
+                    .map(|(id, _)| self.page(id))
+                    .collect();
+                (200, json!({ "results": results, "has_more": false, "next_cursor": null }))
+            }
+            _ => (400, json!({ "object": "error", "status": 400, "code": "invalid_request_url", "message": path })),
+        }
+    }
+}
+
+/// Why Notion would turn down appending `children`, `depth` levels down in the request.
+fn append_problem(children: &[Value], depth: usize) -> Option<String> {
+    if children.len() > MAX_CHILDREN {
+        return Some(format!("body.children.length should be ≤ {}, instead was {}", MAX_CHILDREN, children.len()));
+    }
+    for child in children {
+        let block_type = child["type"].as_str().unwrap_or_default();
+        let nested = child[block_type]["children"].as_array().cloned().unwrap_or_default();
+        if block_type == "column" && nested.is_empty() {
+            return Some("column should have at least one child".to_string());
+        }
+        if nested.is_empty() {
+            continue;
+        }
+        if depth == MAX_NESTING {
+            return Some(format!("body.children should be nested at most {} levels deep", MAX_NESTING));
+        }
+        if let Some(problem) = append_problem(&nested, depth + 1) {
+            return Some(problem);
+        }
+    }
+    None
+}
+
+fn dashed(segment: &str) -> String {
+    match segment.len() == 32 && segment.chars().all(|c| c.is_ascii_hexdigit()) {
+        true => [&segment[..8], &segment[8..12], &segment[12..16], &segment[16..20], &segment[20..]].join("-"),
+        false => segment.to_string(),
+    }
+}
+
+fn not_found(id: &str) -> (u16, Value) {
+    let message = format!("Could not find block with ID: {}. Make sure the relevant pages and databases are shared with your integration.", id);
+    (404, json!({ "object": "error", "status": 404, "code": "object_not_found", "message": message }))
+}
+
+fn rich_text(text: &str) -> Value {
+    json!([{ "type": "text", "text": { "content": text }, "plain_text": text }])
+}
+
+fn merge(base: &mut Value, over: Value) {
+    match (base, over) {
+        (Value::Object(base), Value::Object(over)) => {
+            for (key, value) in over {
+                merge(base.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (base, over) => *base = over,
+    }
+}
+
+async fn serve(mut stream: TcpStream, state: Arc<Mutex<FakeState>>) {
+    let mut buffer = Vec::new();
+    let mut chunk = [0; 8192];
+    let header_end = loop {
+        if let Some(end) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break end + 4;
+        }
+        match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => return,
+            Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+        }
+    };
+    let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+    let length = head
+        .lines()
+        .find_map(|l| l.to_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap_or(0)))
+        .unwrap_or(0);
+    while buffer.len() < header_end + length {
+        match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => return,
+            Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+        }
+    }
+    let mut request_line = head.lines().next().unwrap_or_default().split_whitespace();
+    let (method, path) = (request_line.next().unwrap_or_default(), request_line.next().unwrap_or_default());
+    let body = serde_json::from_slice(&buffer[header_end..header_end + length]).unwrap_or(Value::Null);
+    let (status, body) = state.lock().unwrap().handle(method, path, body);
+    let body = body.to_string();
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        if status == 200 { "OK" } else { "Error" },
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+}
@@ -0,0 +1,70 @@
+use chrono::{DateTime, Utc};
+
+use crate::notion::block::{AppendBlockRequestChild, BlockType, NotionBlock, TextAnnotations};
+
+const SHORT_COMMIT_LEN: usize = 7;
+
+/// The footer of the pages of one ship, tracing their content back to its source.
+#[derive(Clone, Debug)]
+pub struct Footer {
+    template: String,
+    commit: String,
+    timestamp: String,
+}
+
+impl Footer {
+    pub fn new(template: &str, commit: Option<&str>, shipped_at: DateTime<Utc>) -> Self {
+        let commit = match commit {
+            Some(c) => c.chars().take(SHORT_COMMIT_LEN).collect(),
+            None => "unknown".to_string(),
+        };
+        Footer {
+            template: template.to_string(),
+            commit,
+            timestamp: shipped_at.format("%Y-%m-%d %H:%M UTC").to_string(),
+        }
+    }
+
+    pub fn render(&self, path: &str) -> String {
+        self.template
+            .replace("{path}", path)
+            .replace("{commit}", &self.commit)
+            .replace("{timestamp}", &self.timestamp)
+    }
+
+    /// A divider followed by the footer line in gray italics.
+    pub fn blocks(&self, path: &str) -> Vec<AppendBlockRequestChild> {
+        let text = NotionBlock::new_text_block(self.render(path))
+            .with_annotations(TextAnnotations::new().italic().color("gray"));
+        vec![
+            AppendBlockRequestChild::new_divider_block(),
+            AppendBlockRequestChild::new_rich_text(BlockType::Paragraph, vec![text]),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use crate::notion::footer::Footer;
+    use crate::settings::notation::DEFAULT_FOOTER_TEMPLATE;
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_render_footer() {
+        let shipped_at = Utc.with_ymd_and_hms(2024, 7, 1, 9, 30, 0).unwrap();
+        let footer = Footer::new(
+            DEFAULT_FOOTER_TEMPLATE,
+            Some("0123456789abcdef0123456789abcdef01234567"),
+            shipped_at,
+        );
+        assert_eq!(
+            footer.render("docs/api/auth.md"),
+            "Source: docs/api/auth.md · commit 0123456 · shipped 2024-07-01 09:30 UTC"
+        );
+        assert_eq!(footer.blocks("docs/api/auth.md").len(), 2);
+
+        let footer = Footer::new("{commit}", None, shipped_at);
+        assert_eq!(footer.render("a.md"), "unknown");
+    }
+}
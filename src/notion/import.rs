@@ -0,0 +1,111 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+
+use crate::notion::block::{AppendBlockRequest, AppendBlockRequestChild};
+
+/// The shapes an import file may take: an append request, a bare list of blocks, or a page
+/// written by `notation export`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ImportFile {
+    Request(AppendBlockRequest),
+    Children(Vec<AppendBlockRequestChild>),
+    Exported { append_blocks: Option<AppendBlockRequest> },
+}
+
+/// Reads the blocks to append from a JSON file.
+pub fn read_blocks(path: &Path) -> Result<AppendBlockRequest> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("failed to read {}: {}", path.display(), e))?;
+    parse_blocks(&content).map_err(|e| anyhow!("(file={}) {}", path.display(), e))
+}
+
+/// Parses raw Notion block JSON into an append request, checking every block against the
+/// block model, which takes a block only with the payload of its type.
+pub fn parse_blocks(content: &str) -> Result<AppendBlockRequest> {
+    // Deserializing into the enum first would only say that no variant matched, so the
+    // shape is picked from the raw value and serde gets to point at the offending field.
+    let value: serde_json::Value = serde_json::from_str(content)
+        .map_err(|e| anyhow!("invalid JSON at line {} column {}: {}", e.line(), e.column(), e))?;
+    let parsed = if value.is_array() {
+        serde_json::from_value::<Vec<AppendBlockRequestChild>>(value).map(ImportFile::Children)
+    } else if value.get("children").is_some() {
+        serde_json::from_value::<AppendBlockRequest>(value).map(ImportFile::Request)
+    } else {
+        serde_json::from_value::<ImportFile>(value)
+    };
+    let request = match parsed.map_err(|e| anyhow!("blocks don't match the block model: {}", e))? {
+        ImportFile::Request(r) => r,
+        ImportFile::Children(c) => AppendBlockRequest::new_children(c),
+        ImportFile::Exported { append_blocks } => {
+            append_blocks.ok_or(anyhow!("exported page has no blocks to append"))?
+        }
+    };
+    Ok(request)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::commands::import;
+    use crate::notion::block::{AppendBlockRequest, AppendBlockRequestChild};
+    use crate::notion::fake::FakeNotion;
+    use crate::notion::import::parse_blocks;
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_parse_blocks() {
+        let request = AppendBlockRequest::new_children(vec![
+            AppendBlockRequestChild::new_paragraph_block("hello".to_string()),
+            AppendBlockRequestChild::new_divider_block(),
+        ]);
+        let json = serde_json::to_string(&request).unwrap();
+        assert_eq!(parse_blocks(&json).unwrap().children.len(), 2);
+
+        let children = serde_json::to_string(&request.children).unwrap();
+        assert_eq!(parse_blocks(&children).unwrap().children.len(), 2);
+
+        let exported = format!(r#"{{"path": "a.md", "page_id": "x", "append_blocks": {}}}"#, json);
+        assert_eq!(parse_blocks(&exported).unwrap().children.len(), 2);
+
+        let missing_body = r#"[{"object": "block", "type": "paragraph"}]"#;
+        assert!(parse_blocks(missing_body).is_err());
+        let unknown_type = r#"[{"object": "block", "type": "synced_block", "synced_block": {}}]"#;
+        assert!(parse_blocks(unknown_type).is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_import_exported_page() {
+        let svg = AppendBlockRequestChild::new_svg_image_block("diagrams/flow.svg".to_string());
+        let json = serde_json::to_string(&vec![svg]).unwrap();
+        let image = parse_blocks(&json).unwrap().children[0].image().cloned().unwrap();
+        assert_eq!(image.svg_source.as_deref(), Some("diagrams/flow.svg"));
+
+        let fake = FakeNotion::start().await;
+        let docs = fake.add_page(None, "Docs");
+        let blocks = json!([
+            { "object": "block", "type": "paragraph", "paragraph": { "rich_text": [{ "type": "text", "text": { "content": "Before" } }] } },
+            {
+                "object": "block",
+                "type": "child_database",
+                "child_database": {
+                    "title": "Releases",
+                    "columns": [{ "name": "Version", "column_type": "title" }],
+                    "rows": [["1.0"], ["2.0"]]
+                }
+            },
+            { "object": "block", "type": "paragraph", "paragraph": { "rich_text": [{ "type": "text", "text": { "content": "After" } }] } }
+        ]);
+        let path = std::env::temp_dir().join(format!("notation-import-{}.json", std::process::id()));
+        std::fs::write(&path, blocks.to_string()).unwrap();
+        let client = fake.client(&docs, json!({}));
+        assert_eq!(import(&client, docs.clone(), &path).await.unwrap().appended, 3);
+        let types: Vec<String> = fake.children(&docs).iter().map(|c| c["type"].as_str().unwrap().to_string()).collect();
+        assert_eq!(types, vec!["paragraph", "child_database", "paragraph"]);
+        let database = fake.children(&docs)[1]["id"].as_str().unwrap().to_string();
+        assert_eq!(fake.children(&database).len(), 2);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
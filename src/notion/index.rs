@@ -0,0 +1,160 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::notion::parent::object_title;
+use crate::notion::search::{SearchResultItem, SearchResultItemParent, SearchResultItemProperty, TitleArrayElement, TitleInner};
+use crate::settings::notation::NotationSettings;
+use crate::sync::state::content_hash;
+
+/// A page the integration can see, as it was when the index was refreshed.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexedPage {
+    pub id: String,
+    pub title: String,
+    pub url: String,
+    pub parent: SearchResultItemParent,
+    pub last_edited_time: String,
+}
+
+impl IndexedPage {
+    /// The page, read from a result of the search API. `None` for anything but a page.
+    pub fn from_search(result: &Value) -> Option<Self> {
+        if result["object"] != "page" {
+            return None;
+        }
+        Some(IndexedPage {
+            id: result["id"].as_str()?.to_string(),
+            title: object_title(result).unwrap_or_default(),
+            url: result["url"].as_str().unwrap_or_default().to_string(),
+            parent: serde_json::from_value(result["parent"].clone()).ok()?,
+            last_edited_time: result["last_edited_time"].as_str().unwrap_or_default().to_string(),
+        })
+    }
+
+    /// The page as the search API would have found it.
+    pub fn search_result(&self) -> SearchResultItem {
+        SearchResultItem {
+            object: "page".to_string(),
+            id: self.id.clone(),
+            url: self.url.clone(),
+            parent: self.parent.clone(),
+            properties: SearchResultItemProperty {
+                title: TitleInner {
+                    title: vec![TitleArrayElement {
+                        plain_text: self.title.clone(),
+                    }],
+                },
+            },
+        }
+    }
+}
+
+/// The pages of the workspace of an integration secret, so that finding one by its title
+/// doesn't search Notion on every run. It is only as fresh as its last refresh.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PageIndex {
+    /// The [`content_hash`] of the secret the index was read with, told apart without
+    /// keeping the secret.
+    pub workspace: String,
+    pub refreshed_at: String,
+    /// By page id, without dashes.
+    pub pages: BTreeMap<String, IndexedPage>,
+}
+
+impl PageIndex {
+    pub fn new(secret: &str, refreshed_at: String, pages: Vec<IndexedPage>) -> Self {
+        PageIndex {
+            workspace: workspace_key(secret),
+            refreshed_at,
+            pages: pages.into_iter().map(|p| (page_key(&p.id), p)).collect(),
+        }
+    }
+
+    /// Where the index of `settings` is kept, `None` when there is no home directory to keep
+    /// it in.
+    pub fn path(settings: &NotationSettings) -> Option<PathBuf> {
+        match &settings.index.path {
+            Some(path) => Some(PathBuf::from(path)),
+            None => dirs::home_dir().map(|h| {
+                h.join(".notation")
+                    .join(format!("index-{}.json", &workspace_key(&settings.notion.secret)[..12]))
+            }),
+        }
+    }
+
+    /// The index at `path`, `None` when there is none yet or it was refreshed with another
+    /// secret.
+    pub fn load(path: &Path, secret: &str) -> Result<Option<Self>> {
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let contents = std::fs::read_to_string(path)?;
+        let index: PageIndex = serde_json::from_str(&contents)
+            .map_err(|e| anyhow!("failed to read page index {}: {}", path.display(), e))?;
+        Ok(Some(index).filter(|i| i.workspace == workspace_key(secret)))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn get(&self, page_id: &str) -> Option<&IndexedPage> {
+        self.pages.get(&page_key(page_id))
+    }
+
+    /// The pages titled `title`, without regard to case.
+    pub fn find_by_title(&self, title: &str) -> Vec<&IndexedPage> {
+        let title = title.to_lowercase();
+        self.pages.values().filter(|p| p.title.to_lowercase() == title).collect()
+    }
+}
+
+fn workspace_key(secret: &str) -> String {
+    content_hash(secret.as_bytes())
+}
+
+/// Page ids come with and without dashes.
+fn page_key(page_id: &str) -> String {
+    page_id.replace('-', "")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::notion::index::{IndexedPage, PageIndex};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_page_index() {
+        let results = [
+            json!({"object": "page", "id": "1111-aaaa", "url": "https://www.notion.so/Docs-1111aaaa",
+                "parent": {"type": "workspace", "workspace": true}, "last_edited_time": "2024-05-01T12:34:00.000Z",
+                "properties": {"title": {"type": "title", "title": [{"plain_text": "Docs"}]}}}),
+            json!({"object": "page", "id": "2222-bbbb", "url": "https://www.notion.so/Docs-2222bbbb",
+                "parent": {"type": "page_id", "page_id": "3333"}, "last_edited_time": "2024-05-02T08:00:00.000Z",
+                "properties": {"Name": {"type": "title", "title": [{"plain_text": "do"}, {"plain_text": "cs"}]}}}),
+            json!({"object": "database", "id": "4444"}),
+        ];
+        let pages: Vec<IndexedPage> = results.iter().filter_map(IndexedPage::from_search).collect();
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[1].parent.page_id.as_deref(), Some("3333"));
+
+        let index = PageIndex::new("ntn_page_index", "2024-05-03T00:00:00Z".to_string(), pages);
+        assert_eq!(index.find_by_title("DOCS").len(), 2);
+        assert_eq!(index.get("2222bbbb").unwrap().search_result().properties.title.title[0].plain_text, "docs");
+
+        let path = std::env::temp_dir().join(format!("notation-index-{}.json", std::process::id()));
+        index.save(&path).unwrap();
+        assert_eq!(PageIndex::load(&path, "ntn_page_index").unwrap().unwrap().pages, index.pages);
+        assert!(PageIndex::load(&path, "ntn_other_workspace").unwrap().is_none());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
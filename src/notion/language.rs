@@ -1,14 +1,20 @@
 use std::fmt;
 use std::str::FromStr;
+use std::sync::OnceLock;
 
 use anyhow::anyhow;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+static FILE_NAME_COMMENT: OnceLock<Regex> = OnceLock::new();
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub enum NotionCodeLanguage {
     Abap,
     Agda,
     Arduino,
+    AsciiArt,
     Assembly,
     Bash,
     Basic,
@@ -37,6 +43,7 @@ pub enum NotionCodeLanguage {
     Graphql,
     Groovy,
     Haskell,
+    Hcl,
     Html,
     Idris,
     Java,
@@ -79,6 +86,7 @@ pub enum NotionCodeLanguage {
     Scheme,
     Scss,
     Shell,
+    Smalltalk,
     Solidity,
     Sql,
     Swift,
@@ -100,6 +108,7 @@ impl fmt::Display for NotionCodeLanguage {
             NotionCodeLanguage::Abap => "abap",
             NotionCodeLanguage::Agda => "agda",
             NotionCodeLanguage::Arduino => "arduino",
+            NotionCodeLanguage::AsciiArt => "ascii art",
             NotionCodeLanguage::Assembly => "assembly",
             NotionCodeLanguage::Bash => "bash",
             NotionCodeLanguage::Basic => "basic",
@@ -128,6 +137,7 @@ impl fmt::Display for NotionCodeLanguage {
             NotionCodeLanguage::Graphql => "graphql",
             NotionCodeLanguage::Groovy => "groovy",
             NotionCodeLanguage::Haskell => "haskell",
+            NotionCodeLanguage::Hcl => "hcl",
             NotionCodeLanguage::Html => "html",
             NotionCodeLanguage::Idris => "idris",
             NotionCodeLanguage::Java => "java",
@@ -170,6 +180,7 @@ impl fmt::Display for NotionCodeLanguage {
             NotionCodeLanguage::Scheme => "scheme",
             NotionCodeLanguage::Scss => "scss",
             NotionCodeLanguage::Shell => "shell",
+            NotionCodeLanguage::Smalltalk => "smalltalk",
             NotionCodeLanguage::Solidity => "solidity",
             NotionCodeLanguage::Sql => "sql",
             NotionCodeLanguage::Swift => "swift",
@@ -196,6 +207,7 @@ impl FromStr for NotionCodeLanguage {
             "abap" => Ok(NotionCodeLanguage::Abap),
             "agda" => Ok(NotionCodeLanguage::Agda),
             "arduino" => Ok(NotionCodeLanguage::Arduino),
+            "ascii art" => Ok(NotionCodeLanguage::AsciiArt),
             "assembly" => Ok(NotionCodeLanguage::Assembly),
             "bash" => Ok(NotionCodeLanguage::Bash),
             "basic" => Ok(NotionCodeLanguage::Basic),
@@ -224,6 +236,7 @@ impl FromStr for NotionCodeLanguage {
             "graphql" => Ok(NotionCodeLanguage::Graphql),
             "groovy" => Ok(NotionCodeLanguage::Groovy),
             "haskell" => Ok(NotionCodeLanguage::Haskell),
+            "hcl" => Ok(NotionCodeLanguage::Hcl),
             "html" => Ok(NotionCodeLanguage::Html),
             "idris" => Ok(NotionCodeLanguage::Idris),
             "java" => Ok(NotionCodeLanguage::Java),
@@ -266,6 +279,7 @@ impl FromStr for NotionCodeLanguage {
             "scheme" => Ok(NotionCodeLanguage::Scheme),
             "scss" => Ok(NotionCodeLanguage::Scss),
             "shell" => Ok(NotionCodeLanguage::Shell),
+            "smalltalk" => Ok(NotionCodeLanguage::Smalltalk),
             "solidity" => Ok(NotionCodeLanguage::Solidity),
             "sql" => Ok(NotionCodeLanguage::Sql),
             "swift" => Ok(NotionCodeLanguage::Swift),
@@ -283,3 +297,218 @@ impl FromStr for NotionCodeLanguage {
         }
     }
 }
+
+impl NotionCodeLanguage {
+    /// Maps the language of a code fence to Notion's, accepting Notion's names in any case,
+    /// common aliases and file extensions (`yml`, `ts`, `main.rs`), and fence info like
+    /// `rust,ignore` or `language-python`. `None` when nothing matches.
+    pub fn from_fence(lang: &str) -> Option<Self> {
+        let lang = lang.trim().to_lowercase();
+        let lang = lang
+            .strip_prefix("language-")
+            .or(lang.strip_prefix("lang-"))
+            .unwrap_or(&lang);
+        if let Some(l) = Self::from_name(lang) {
+            return Some(l);
+        }
+        let first_token = lang
+            .split([',', ' ', '{', ':'])
+            .find(|t| !t.is_empty())
+            .unwrap_or("");
+        if let Some(l) = Self::from_name(first_token) {
+            return Some(l);
+        }
+        if let Some((_, extension)) = first_token.rsplit_once('.') {
+            if let Some(l) = Self::from_name(extension) {
+                return Some(l);
+            }
+        }
+        if first_token.ends_with("sh") {
+            return Some(NotionCodeLanguage::Shell);
+        }
+        None
+    }
+
+    /// Guesses the language of a code block that has none: from its shebang, from a first line
+    /// comment naming a file like `# config.yaml`, or from content that can't be anything
+    /// else, like JSON, `<?xml`, a diff or `$ ` prompts. `None` when nothing is certain enough.
+    pub fn detect(code: &str) -> Option<Self> {
+        let mut lines = code.lines().map(str::trim).filter(|l| !l.is_empty());
+        let first = lines.next()?;
+        if let Some(shebang) = first.strip_prefix("#!") {
+            // `#!/bin/bash -e`, `#!/usr/bin/env python3`
+            let mut words = shebang.split_whitespace();
+            let program = words.next()?.rsplit('/').next()?;
+            let program = match program {
+                "env" => words.find(|w| !w.starts_with('-'))?,
+                p => p,
+            };
+            let program = program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+            return Self::from_fence(program).filter(|l| *l != NotionCodeLanguage::PlainText);
+        }
+        let file_name = FILE_NAME_COMMENT
+            .get_or_init(|| Regex::new(r"^(?://|#|--|;|/\*|<!--)\s*([\w./-]+\.\w+)\s*(?:\*/|-->)?$").unwrap())
+            .captures(first);
+        if let Some(l) = file_name.and_then(|c| Self::from_fence(&c[1])) {
+            return Some(l).filter(|l| *l != NotionCodeLanguage::PlainText);
+        }
+        let lower = first.to_lowercase();
+        if (first.starts_with('{') || first.starts_with('[')) && serde_json::from_str::<Value>(code).is_ok() {
+            Some(NotionCodeLanguage::Json)
+        } else if first.starts_with("<?xml") {
+            Some(NotionCodeLanguage::Xml)
+        } else if lower.starts_with("<!doctype html") || lower.starts_with("<html") {
+            Some(NotionCodeLanguage::Html)
+        } else if first.starts_with("diff --git") || (first.starts_with("--- ") && lines.next()?.starts_with("+++ ")) {
+            Some(NotionCodeLanguage::Diff)
+        } else if first.starts_with("$ ") {
+            Some(NotionCodeLanguage::Shell)
+        } else {
+            None
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        NotionCodeLanguage::from_str(name)
+            .ok()
+            .or_else(|| Self::from_alias(name))
+    }
+
+    fn from_alias(alias: &str) -> Option<Self> {
+        let l = match alias {
+            "" | "text" | "txt" | "plain" | "plaintext" | "none" | "nohighlight" => {
+                NotionCodeLanguage::PlainText
+            }
+            "sh" | "zsh" | "ksh" | "fish" | "console" | "terminal" | "shell-session"
+            | "shellsession" | "sh-session" => NotionCodeLanguage::Shell,
+            "ps" | "ps1" | "psm1" | "pwsh" | "posh" => NotionCodeLanguage::Powershell,
+            "bat" | "cmd" | "batch" => NotionCodeLanguage::Shell,
+            "yml" => NotionCodeLanguage::Yaml,
+            "ts" | "tsx" | "mts" | "cts" => NotionCodeLanguage::Typescript,
+            "js" | "jsx" | "mjs" | "cjs" | "node" | "es6" | "ecmascript" => {
+                NotionCodeLanguage::Javascript
+            }
+            "json5" | "jsonc" | "jsonl" | "ndjson" | "geojson" => NotionCodeLanguage::Json,
+            "proto" | "proto3" => NotionCodeLanguage::Protobuf,
+            "dockerfile" | "containerfile" => NotionCodeLanguage::Docker,
+            "py" | "py3" | "python3" | "pyi" => NotionCodeLanguage::Python,
+            "rb" | "gemfile" | "rake" => NotionCodeLanguage::Ruby,
+            "rs" => NotionCodeLanguage::Rust,
+            "golang" => NotionCodeLanguage::Go,
+            "kt" | "kts" => NotionCodeLanguage::Kotlin,
+            "cs" | "csharp" | "c-sharp" => NotionCodeLanguage::CSharp,
+            "cpp" | "cxx" | "cc" | "hpp" | "hxx" | "hh" | "h++" => NotionCodeLanguage::CPlusPlus,
+            "h" => NotionCodeLanguage::C,
+            "objc" | "objective_c" | "objectivec" | "obj-c" => NotionCodeLanguage::ObjectiveC,
+            "fs" | "fsharp" | "fsx" | "fsi" => NotionCodeLanguage::FSharp,
+            "md" | "mdx" | "mkd" => NotionCodeLanguage::Markdown,
+            "tex" => NotionCodeLanguage::Latex,
+            "make" | "mk" | "mak" => NotionCodeLanguage::Makefile,
+            "gql" => NotionCodeLanguage::Graphql,
+            "ex" | "exs" | "heex" => NotionCodeLanguage::Elixir,
+            "erl" | "hrl" => NotionCodeLanguage::Erlang,
+            "hs" | "lhs" => NotionCodeLanguage::Haskell,
+            "clj" | "cljs" | "cljc" | "edn" => NotionCodeLanguage::Clojure,
+            "coffee" => NotionCodeLanguage::Coffeescript,
+            "sol" => NotionCodeLanguage::Solidity,
+            "vb" | "vba" | "vbs" => NotionCodeLanguage::VisualBasic,
+            "vbnet" => NotionCodeLanguage::VbNet,
+            "wasm" | "wat" | "wast" => NotionCodeLanguage::Webassembly,
+            "htm" | "xhtml" | "vue" | "svelte" | "handlebars" | "hbs" | "jinja" | "erb" => {
+                NotionCodeLanguage::Html
+            }
+            "svg" | "xsd" | "xsl" | "xslt" | "plist" | "csproj" | "wsdl" => NotionCodeLanguage::Xml,
+            "ml" | "mli" => NotionCodeLanguage::Ocaml,
+            "pl" | "pm" => NotionCodeLanguage::Perl,
+            "jl" => NotionCodeLanguage::Julia,
+            "sv" | "svh" | "systemverilog" => NotionCodeLanguage::Verilog,
+            "vhd" => NotionCodeLanguage::Vhdl,
+            "scm" | "ss" => NotionCodeLanguage::Scheme,
+            "rkt" => NotionCodeLanguage::Racket,
+            "el" | "elisp" | "emacs-lisp" | "cl" | "common-lisp" => NotionCodeLanguage::Lisp,
+            "asm" | "nasm" | "x86asm" => NotionCodeLanguage::Assembly,
+            "patch" | "udiff" => NotionCodeLanguage::Diff,
+            "gradle" => NotionCodeLanguage::Groovy,
+            "ino" => NotionCodeLanguage::Arduino,
+            "re" | "rei" | "reasonml" => NotionCodeLanguage::Reason,
+            "feature" | "cucumber" => NotionCodeLanguage::Gherkin,
+            "frag" | "vert" | "hlsl" => NotionCodeLanguage::Glsl,
+            "postgres" | "postgresql" | "psql" | "pgsql" | "mysql" | "sqlite" | "plsql"
+            | "tsql" | "mssql" | "sparql" => NotionCodeLanguage::Sql,
+            "ll" | "llvm" => NotionCodeLanguage::LlvmIr,
+            "purs" => NotionCodeLanguage::Purescript,
+            "gallina" => NotionCodeLanguage::Coq,
+            "idr" => NotionCodeLanguage::Idris,
+            "wl" | "wolfram" => NotionCodeLanguage::Mathematica,
+            "mmd" => NotionCodeLanguage::Mermaid,
+            "styl" | "stylus" => NotionCodeLanguage::Css,
+            "java/c/c++/c#" | "clike" | "c-like" => NotionCodeLanguage::JavaCCPlusPlusCSharp,
+            "pas" | "delphi" | "objectpascal" => NotionCodeLanguage::Pascal,
+            "f90" | "f95" | "f03" => NotionCodeLanguage::Fortran,
+            "rscript" => NotionCodeLanguage::R,
+            "swipl" => NotionCodeLanguage::Prolog,
+            "sbt" => NotionCodeLanguage::Scala,
+            "octave" => NotionCodeLanguage::Matlab,
+            "htmlbars" | "mustache" => NotionCodeLanguage::Markup,
+            "tf" | "tfvars" | "terraform" | "hcl2" | "nomad" => NotionCodeLanguage::Hcl,
+            "st" | "squeak" | "pharo" => NotionCodeLanguage::Smalltalk,
+            "ascii" | "ascii-art" | "asciiart" | "ascii_art" => NotionCodeLanguage::AsciiArt,
+            _ => return None,
+        };
+        Some(l)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::notion::language::NotionCodeLanguage;
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_from_fence() {
+        for (fence, expected) in [
+            ("Rust", NotionCodeLanguage::Rust),
+            ("sh", NotionCodeLanguage::Shell),
+            ("yml", NotionCodeLanguage::Yaml),
+            ("ts", NotionCodeLanguage::Typescript),
+            ("jsx", NotionCodeLanguage::Javascript),
+            ("proto", NotionCodeLanguage::Protobuf),
+            ("Dockerfile", NotionCodeLanguage::Docker),
+            ("rust,ignore", NotionCodeLanguage::Rust),
+            ("language-python", NotionCodeLanguage::Python),
+            ("main.rs", NotionCodeLanguage::Rust),
+            ("tcsh", NotionCodeLanguage::Shell),
+            ("text", NotionCodeLanguage::PlainText),
+            ("hcl", NotionCodeLanguage::Hcl),
+            ("tf", NotionCodeLanguage::Hcl),
+            ("Terraform", NotionCodeLanguage::Hcl),
+            ("main.tf", NotionCodeLanguage::Hcl),
+            ("Smalltalk", NotionCodeLanguage::Smalltalk),
+            ("ascii art", NotionCodeLanguage::AsciiArt),
+            ("ascii-art", NotionCodeLanguage::AsciiArt),
+        ] {
+            assert_eq!(NotionCodeLanguage::from_fence(fence), Some(expected), "{}", fence);
+        }
+        assert_eq!(NotionCodeLanguage::from_fence("cobol"), None);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_detect() {
+        for (code, expected) in [
+            ("#!/usr/bin/env python3\nprint('hi')", Some(NotionCodeLanguage::Python)),
+            ("#!/bin/bash -e\necho hi", Some(NotionCodeLanguage::Bash)),
+            ("#!/usr/bin/env -S node --no-warnings\n", Some(NotionCodeLanguage::Javascript)),
+            ("# config.yaml\nport: 8080", Some(NotionCodeLanguage::Yaml)),
+            ("// src/main.rs\nfn main() {}", Some(NotionCodeLanguage::Rust)),
+            ("<!-- index.html -->\n<p>Hi</p>", Some(NotionCodeLanguage::Html)),
+            ("{\"name\": \"notation\"}", Some(NotionCodeLanguage::Json)),
+            ("<?xml version=\"1.0\"?>\n<a/>", Some(NotionCodeLanguage::Xml)),
+            ("--- a/x\n+++ b/x\n", Some(NotionCodeLanguage::Diff)),
+            ("$ cargo build\n", Some(NotionCodeLanguage::Shell)),
+            ("{ not json", None),
+            ("# Install the tool\nmake install", None),
+            ("hello world", None),
+        ] {
+            assert_eq!(NotionCodeLanguage::detect(code), expected, "{}", code);
+        }
+    }
+}
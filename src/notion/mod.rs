@@ -1,5 +1,26 @@
+pub mod append;
 pub mod block;
 pub mod client;
+pub mod clock;
+pub mod compare;
+pub mod database;
+pub mod error;
+pub mod export;
+#[cfg(test)]
+pub mod fake;
+pub mod footer;
+pub mod import;
+pub mod index;
 pub mod language;
 pub mod page;
+pub mod parallel;
+pub mod parent;
+pub mod report;
+pub mod schema;
 pub mod search;
+pub mod snapshot;
+pub mod summary;
+pub mod transform;
+pub mod tree;
+pub mod usage;
+pub mod user;
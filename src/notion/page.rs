@@ -1,6 +1,14 @@
+use std::collections::BTreeMap;
 use std::fmt::Display;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The most characters Notion takes in a page title, counted in UTF-16 code units as Notion
+/// does.
+pub const MAX_TITLE_LENGTH: usize = 2000;
 
 #[derive(Clone, Serialize, Deserialize, Debug, Default)]
 #[serde(rename_all = "snake_case")]
@@ -25,6 +33,18 @@ impl Display for PageContentType {
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct GetPageContentResponse {
     pub results: Vec<PageContentResult>,
+    #[serde(default)]
+    pub has_more: bool,
+    pub next_cursor: Option<String>,
+}
+
+/// One page of a paginated list of block children, whatever they are read into.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct BlockChildrenPage<T> {
+    pub results: Vec<T>,
+    #[serde(default)]
+    pub has_more: bool,
+    pub next_cursor: Option<String>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -40,14 +60,40 @@ pub struct CreatePageResponse {
     pub id: String,
 }
 
+/// A page as retrieved on its own, without its blocks.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PageResponse {
+    pub id: String,
+    /// Like `2024-05-01T12:34:00.000Z`. Notion rounds it down to the minute.
+    pub last_edited_time: String,
+    #[serde(default)]
+    pub in_trash: bool,
+    #[serde(default)]
+    pub icon: Option<Value>,
+}
+
+impl PageResponse {
+    /// The emoji of the page's icon, if it is one.
+    pub fn emoji(&self) -> Option<String> {
+        self.icon.as_ref()?.get("emoji")?.as_str().map(|e| e.to_string())
+    }
+}
+
+/// Where a page is created: under a page, or as a row of a database.
 #[derive(Clone, Serialize, Deserialize)]
-pub struct PageParent {
-    page_id: String,
+#[serde(untagged)]
+pub enum PageParent {
+    Page { page_id: String },
+    Database { database_id: String },
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct PageProperties {
     title: TitleSubProperties,
+    /// The other properties of a database row, by name, like
+    /// `{"rich_text": [{"type": "text", "text": {"content": "..."}}]}`.
+    #[serde(flatten)]
+    others: BTreeMap<String, Value>,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -83,25 +129,26 @@ impl PageProperties {
                 property_type: "title".to_string(),
                 id: "title".to_string(),
             },
+            others: BTreeMap::new(),
         }
     }
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PageEmojiIcon {
     #[serde(rename = "type")]
     icon_type: String,
     emoji: String,
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PageCover {
     #[serde(rename = "type")]
     cover_type: String,
     external: PageCoverExternalUrl,
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PageCoverExternalUrl {
     url: String,
 }
@@ -113,6 +160,10 @@ impl PageCover {
             external: PageCoverExternalUrl { url: external_url },
         }
     }
+
+    pub fn url(&self) -> &str {
+        &self.external.url
+    }
 }
 
 impl PageEmojiIcon {
@@ -122,6 +173,10 @@ impl PageEmojiIcon {
             emoji,
         }
     }
+
+    pub fn emoji(&self) -> &str {
+        &self.emoji
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -136,7 +191,7 @@ pub struct CreatePageRequest {
 impl CreatePageRequest {
     pub fn new(parent_id: String, title: String) -> Self {
         CreatePageRequest {
-            parent: PageParent { page_id: parent_id },
+            parent: PageParent::Page { page_id: parent_id },
             properties: PageProperties::new(title),
             children: Value::Array(vec![]),
             icon: None,
@@ -144,6 +199,28 @@ impl CreatePageRequest {
         }
     }
 
+    /// A request creating a row of the database `database_id`.
+    pub fn new_row(database_id: String, title: String) -> Self {
+        CreatePageRequest {
+            parent: PageParent::Database { database_id },
+            ..Self::new(String::new(), title)
+        }
+    }
+
+    /// Sets the property `name` of a database row.
+    pub fn with_property(mut self, name: String, value: Value) -> Self {
+        self.properties.others.insert(name, value);
+        self
+    }
+
+    pub fn property(&self, name: &str) -> Option<&Value> {
+        self.properties.others.get(name)
+    }
+
+    pub fn is_row(&self) -> bool {
+        matches!(self.parent, PageParent::Database { .. })
+    }
+
     pub fn with_icon(mut self, icon: String) -> Self {
         self.icon = Some(PageEmojiIcon::new(icon));
         self
@@ -158,4 +235,212 @@ impl CreatePageRequest {
         self.children = children;
         self
     }
+
+    /// The id of the page, or database, the page is created in.
+    pub fn parent_id(&self) -> &str {
+        match &self.parent {
+            PageParent::Page { page_id } => page_id,
+            PageParent::Database { database_id } => database_id,
+        }
+    }
+
+    pub fn title(&self) -> String {
+        self.properties
+            .title
+            .title
+            .iter()
+            .map(|t| t.text.content.as_str())
+            .collect()
+    }
+
+    pub fn emoji(&self) -> Option<&str> {
+        self.icon.as_ref().map(|i| i.emoji())
+    }
+
+    pub fn cover(&self) -> Option<&PageCover> {
+        self.cover.as_ref()
+    }
+
+    /// Checks the title and the icon before the request is sent, since Notion only answers
+    /// a bad one with a bare 400.
+    pub fn validate(&self) -> Result<()> {
+        validate_page(&self.title(), self.emoji())
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct UpdatePageRequest {
+    properties: PageProperties,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    icon: Option<PageEmojiIcon>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cover: Option<PageCover>,
+}
+
+impl UpdatePageRequest {
+    pub fn new(title: String) -> Self {
+        UpdatePageRequest {
+            properties: PageProperties::new(title),
+            icon: None,
+            cover: None,
+        }
+    }
+
+    pub fn with_icon(mut self, icon: String) -> Self {
+        self.icon = Some(PageEmojiIcon::new(icon));
+        self
+    }
+
+    pub fn with_cover(mut self, cover: PageCover) -> Self {
+        self.cover = Some(cover);
+        self
+    }
+
+    /// Sets the property `name`, for a page that is a database row.
+    pub fn with_property(mut self, name: String, value: Value) -> Self {
+        self.properties.others.insert(name, value);
+        self
+    }
+
+    /// Checks the title and the icon, like [`CreatePageRequest::validate`].
+    pub fn validate(&self) -> Result<()> {
+        let title: String = self.properties.title.title.iter().map(|t| t.text.content.as_str()).collect();
+        validate_page(&title, self.icon.as_ref().map(|i| i.emoji()))
+    }
+}
+
+fn validate_page(title: &str, emoji: Option<&str>) -> Result<()> {
+    validate_title(title)?;
+    match emoji {
+        Some(emoji) => validate_emoji(emoji).map_err(|e| anyhow!("page \"{}\": {}", title, e)),
+        None => Ok(()),
+    }
+}
+
+/// Checks that `title` is within [`MAX_TITLE_LENGTH`].
+pub fn validate_title(title: &str) -> Result<()> {
+    let length = title.encode_utf16().count();
+    if length > MAX_TITLE_LENGTH {
+        let start: String = title.graphemes(true).take(40).collect();
+        return Err(anyhow!(
+            "the title \"{}…\" is {} characters long, Notion takes at most {}",
+            start,
+            length,
+            MAX_TITLE_LENGTH
+        ));
+    }
+    Ok(())
+}
+
+/// Checks that `emoji` is a single emoji, which can take several code points, like the ZWJ
+/// sequence 😮‍💨, a flag or a keycap.
+pub fn validate_emoji(emoji: &str) -> Result<()> {
+    let graphemes: Vec<&str> = emoji.graphemes(true).collect();
+    let is_emoji = match graphemes.as_slice() {
+        [g] => {
+            // A keycap, like 1️⃣, starts with its ASCII digit.
+            g.contains('\u{20E3}') || g.chars().next().is_some_and(is_emoji_char)
+        }
+        _ => false,
+    };
+    if is_emoji {
+        return Ok(());
+    }
+    match graphemes.len() {
+        0 => Err(anyhow!("the icon is empty, give an emoji or leave it out")),
+        1 => Err(anyhow!("the icon \"{}\" is not an emoji", emoji)),
+        n => Err(anyhow!("the icon \"{}\" is {} characters, Notion takes a single emoji", emoji, n)),
+    }
+}
+
+/// Whether `c` is one of the code points emoji start with, those of the pictograph blocks and
+/// the older symbols that have an emoji form, like ☕ or ⭐. Typographic punctuation, like `—`
+/// or `“`, is not.
+pub fn is_emoji_char(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x00A9
+            | 0x00AE
+            | 0x203C
+            | 0x2049
+            | 0x2122
+            | 0x2139
+            | 0x2194..=0x2199
+            | 0x21A9..=0x21AA
+            | 0x231A..=0x231B
+            | 0x2328
+            | 0x23CF
+            | 0x23E9..=0x23F3
+            | 0x23F8..=0x23FA
+            | 0x24C2
+            | 0x25AA..=0x25AB
+            | 0x25B6
+            | 0x25C0
+            | 0x25FB..=0x25FE
+            | 0x2600..=0x27BF
+            | 0x2934..=0x2935
+            | 0x2B05..=0x2B07
+            | 0x2B1B..=0x2B1C
+            | 0x2B50
+            | 0x2B55
+            | 0x3030
+            | 0x303D
+            | 0x3297
+            | 0x3299
+            | 0x1F000..=0x1FAFF
+    )
+}
+
+/// The value of a multi-select property of a database row with the options `names`.
+pub fn multi_select_property(names: &[String]) -> Value {
+    let options: Vec<Value> = names.iter().map(|n| serde_json::json!({ "name": n })).collect();
+    serde_json::json!({ "multi_select": options })
+}
+
+/// The value of a select property of a database row set to the option `name`, which Notion
+/// adds to the property when it doesn't have it yet.
+pub fn select_property(name: &str) -> Value {
+    serde_json::json!({ "select": { "name": name } })
+}
+
+/// The value of a date property of a database row set to `date`.
+pub fn date_property(date: DateTime<Utc>) -> Value {
+    serde_json::json!({ "date": { "start": date.to_rfc3339_opts(SecondsFormat::Secs, true) } })
+}
+
+/// The value of a date property of a database row set to the day `date`, without a time.
+pub fn day_property(date: NaiveDate) -> Value {
+    serde_json::json!({ "date": { "start": date.format("%Y-%m-%d").to_string() } })
+}
+
+/// The value of a rich text property of a database row holding `text`.
+pub fn rich_text_property(text: &str) -> Value {
+    serde_json::json!({ "rich_text": [{ "type": "text", "text": { "content": text } }] })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::notion::page::{validate_emoji, validate_title, CreatePageRequest, UpdatePageRequest, MAX_TITLE_LENGTH};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_validate_page() {
+        for emoji in ["🚀", "😮‍💨", "👩‍👩‍👧", "🇯🇵", "1️⃣", "❤️", "👍🏽", "☕", "⭐"] {
+            assert!(validate_emoji(emoji).is_ok(), "{}", emoji);
+        }
+        for punctuation in ["—", "“", "…", "→", "é"] {
+            assert!(validate_emoji(punctuation).is_err(), "{}", punctuation);
+        }
+        assert!(validate_emoji("").unwrap_err().to_string().contains("empty"));
+        assert!(validate_emoji("a").unwrap_err().to_string().contains("not an emoji"));
+        assert!(validate_emoji("🚀🚀").unwrap_err().to_string().contains("2 characters"));
+
+        assert!(validate_title("Getting Started 👩‍👩‍👧").is_ok());
+        assert!(validate_title(&"👩".repeat(MAX_TITLE_LENGTH / 2)).is_ok());
+        let e = validate_title(&"👩".repeat(MAX_TITLE_LENGTH / 2 + 1)).unwrap_err();
+        assert!(e.to_string().contains("2002 characters long"));
+
+        let request = CreatePageRequest::new("parent".to_string(), "Guide".to_string()).with_icon("🚀🚀".to_string());
+        assert!(request.validate().unwrap_err().to_string().starts_with("page \"Guide\": the icon"));
+        assert!(UpdatePageRequest::new("Guide".to_string()).with_icon("😮‍💨".to_string()).validate().is_ok());
+    }
 }
@@ -0,0 +1,120 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+/// Runs `write` over `jobs`, each keyed by the id of the page it writes to, at most
+/// `concurrency` pages at a time. The jobs of one page run one after another in the order
+/// given, so its blocks land in document order, while different pages are written in
+/// parallel. Pages start in the order of their first job.
+///
+/// Once a job fails, no other page is started and the pages under way are finished. The first
+/// error, in the order of the jobs, is returned.
+pub async fn for_each_page<T, F, Fut>(jobs: Vec<(String, T)>, concurrency: usize, write: F) -> Result<()>
+where
+    T: Send + 'static,
+    F: Fn(T) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<()>> + Send,
+{
+    let mut pages: Vec<(String, Vec<T>)> = Vec::new();
+    for (page_id, job) in jobs {
+        match pages.iter_mut().find(|(id, _)| *id == page_id) {
+            Some((_, page_jobs)) => page_jobs.push(job),
+            None => pages.push((page_id, vec![job])),
+        }
+    }
+
+    let write = Arc::new(write);
+    let permits = Arc::new(Semaphore::new(concurrency.max(1)));
+    let is_failed = Arc::new(AtomicBool::new(false));
+    let mut writes = JoinSet::new();
+    for (index, (_, page_jobs)) in pages.into_iter().enumerate() {
+        // Taken before spawning, so pages start in order.
+        let permit = permits.clone().acquire_owned().await?;
+        if is_failed.load(Ordering::SeqCst) {
+            break;
+        }
+        let write = write.clone();
+        let is_failed = is_failed.clone();
+        writes.spawn(async move {
+            let _permit = permit;
+            for job in page_jobs {
+                if let Err(e) = write(job).await {
+                    is_failed.store(true, Ordering::SeqCst);
+                    return Err((index, e));
+                }
+            }
+            Ok(())
+        });
+    }
+
+    let mut first_error: Option<(usize, anyhow::Error)> = None;
+    while let Some(joined) = writes.join_next().await {
+        match joined.map_err(|e| anyhow!("failed to write a page: {}", e))? {
+            Err((index, e)) if first_error.as_ref().is_none_or(|(i, _)| index < *i) => first_error = Some((index, e)),
+            _ => {}
+        }
+    }
+    match first_error {
+        Some((_, e)) => Err(e),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use anyhow::anyhow;
+
+    use crate::notion::parallel::for_each_page;
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_for_each_page() {
+        // Earlier batches of a page take longer, so they'd land last if a page's batches ran
+        // at the same time.
+        let jobs: Vec<(String, (String, u64))> = (0..4)
+            .flat_map(|batch| {
+                ["a", "b", "c"].map(|page| (page.to_string(), (format!("{}{}", page, batch), 40 - batch * 10)))
+            })
+            .collect();
+        let landed = Arc::new(Mutex::new(Vec::new()));
+        let written = landed.clone();
+        for_each_page(jobs, 3, move |(batch, delay): (String, u64)| {
+            let written = written.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(delay)).await;
+                written.lock().unwrap().push(batch);
+                Ok(())
+            }
+        })
+        .await
+        .unwrap();
+        let landed = landed.lock().unwrap().clone();
+        assert_eq!(landed.len(), 12);
+        for page in ["a", "b", "c"] {
+            let batches: Vec<&String> = landed.iter().filter(|b| b.starts_with(page)).collect();
+            assert_eq!(batches, ["0", "1", "2", "3"].map(|i| format!("{}{}", page, i)).iter().collect::<Vec<_>>());
+        }
+        // The pages were written at the same time: the first batch of every page took
+        // longest, and the last ones landed together.
+        assert!(landed[..3].iter().all(|b| b.ends_with('0')));
+
+        let result = for_each_page(
+            vec![("a".to_string(), 1), ("a".to_string(), 2), ("b".to_string(), 3)],
+            1,
+            |n: i32| async move {
+                match n {
+                    1 => Err(anyhow!("page a failed")),
+                    _ => panic!("nothing is written after a failed page"),
+                }
+            },
+        )
+        .await;
+        assert_eq!(result.unwrap_err().to_string(), "page a failed");
+    }
+}
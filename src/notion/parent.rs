@@ -0,0 +1,141 @@
+use std::fmt::{Display, Formatter};
+
+use serde_json::Value;
+
+/// A page titled like the configured parent page, one of those the parent page is picked from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParentCandidate {
+    pub id: String,
+    pub url: String,
+    pub title: String,
+    /// Whether the page is at the top of the workspace rather than under another page.
+    pub is_top_level: bool,
+    /// The titles of the pages it is under, its own parent first, as far up as was asked.
+    pub ancestors: Vec<String>,
+}
+
+/// More than one page could be the parent page, and none of them is a better match.
+#[derive(Clone, Debug)]
+pub struct AmbiguousParentError {
+    pub name: String,
+    pub candidates: Vec<ParentCandidate>,
+}
+
+impl ParentCandidate {
+    /// The titles from the top of the workspace down to the page, like
+    /// `Team / Engineering / Docs`.
+    pub fn breadcrumb(&self) -> String {
+        let mut titles: Vec<&str> = self
+            .ancestors
+            .iter()
+            .rev()
+            .map(|t| if t.trim().is_empty() { "Untitled" } else { t.as_str() })
+            .collect();
+        titles.push(&self.title);
+        titles.join(" / ")
+    }
+}
+
+impl Display for AmbiguousParentError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let pages: Vec<String> = self
+            .candidates
+            .iter()
+            .map(|c| format!("{} at {}", c.breadcrumb(), c.url))
+            .collect();
+        write!(
+            f,
+            "{} pages match the parent page \"{}\" ({}), pick one with --parent-id or [notion] parent_page_id, or name it by its path like \"Team/{}\"",
+            self.candidates.len(),
+            self.name,
+            pages.join(", "),
+            self.name
+        )
+    }
+}
+
+impl std::error::Error for AmbiguousParentError {}
+
+/// The title of a page or a database object of the API.
+pub fn object_title(object: &Value) -> Option<String> {
+    let title = match object["object"].as_str() {
+        Some("database") => object["title"].as_array()?,
+        _ => object["properties"]
+            .as_object()?
+            .values()
+            .find(|p| p["type"] == "title")?["title"]
+            .as_array()?,
+    };
+    Some(title.iter().filter_map(|t| t["plain_text"].as_str()).collect())
+}
+
+/// The title and the titles of the pages above it, closest first, of a `parent_page` like
+/// `Engineering/Docs`.
+pub fn parent_path(name: &str) -> (String, Vec<String>) {
+    let mut segments: Vec<String> = name
+        .split('/')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect();
+    let title = segments.pop().unwrap_or_default();
+    segments.reverse();
+    (title, segments)
+}
+
+/// Whether the pages above `candidate` are titled `ancestors`, closest first.
+pub fn is_under(candidate: &ParentCandidate, ancestors: &[String]) -> bool {
+    ancestors.len() <= candidate.ancestors.len()
+        && ancestors
+            .iter()
+            .zip(candidate.ancestors.iter())
+            .all(|(a, b)| a.trim().eq_ignore_ascii_case(b.trim()))
+}
+
+/// The candidate under `ancestors` that is the parent page. When several are, a single one at
+/// the top of the workspace wins over those nested under other pages.
+pub fn pick_parent<'a>(candidates: &'a [ParentCandidate], ancestors: &[String]) -> Option<&'a ParentCandidate> {
+    let under: Vec<&ParentCandidate> = candidates.iter().filter(|c| is_under(c, ancestors)).collect();
+    if under.len() == 1 {
+        return Some(under[0]);
+    }
+    let top_level: Vec<&ParentCandidate> = under.iter().copied().filter(|c| c.is_top_level).collect();
+    match top_level.as_slice() {
+        [c] => Some(c),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::notion::parent::{parent_path, pick_parent, ParentCandidate};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_pick_parent() {
+        let candidate = |id: &str, is_top_level: bool, ancestors: &[&str]| ParentCandidate {
+            id: id.to_string(),
+            url: format!("https://www.notion.so/{}", id),
+            title: "Docs".to_string(),
+            is_top_level,
+            ancestors: ancestors.iter().map(|a| a.to_string()).collect(),
+        };
+        let candidates = vec![
+            candidate("a", false, &["Engineering", "Team"]),
+            candidate("b", false, &["Archive"]),
+            candidate("c", true, &[]),
+        ];
+        let pick = |name: &str| {
+            let (_, ancestors) = parent_path(name);
+            pick_parent(&candidates, &ancestors).map(|c| c.id.clone())
+        };
+
+        assert_eq!(parent_path(" Team / Engineering/Docs"), ("Docs".to_string(), vec!["Engineering".to_string(), "Team".to_string()]));
+        assert_eq!(pick("Docs"), Some("c".to_string()));
+        assert_eq!(pick("engineering/Docs"), Some("a".to_string()));
+        assert_eq!(pick("Team/Engineering/Docs"), Some("a".to_string()));
+        assert_eq!(pick("Sales/Docs"), None);
+        assert_eq!(pick_parent(&candidates[..2], &[]), None);
+
+        assert_eq!(candidates[0].breadcrumb(), "Team / Engineering / Docs");
+        assert_eq!(candidates[2].breadcrumb(), "Docs");
+    }
+}
@@ -0,0 +1,285 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::markdown::diagnostic::Diagnostic;
+use crate::markdown::redaction::Redaction;
+use crate::notion::error::request_id_in;
+use crate::notion::summary::{PageStatus, ShipSummary};
+use crate::notion::usage::ApiUsage;
+
+/// What a ship did with a file, or with the page of a directory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileStatus {
+    Created,
+    Updated,
+    /// Left as it is by an incremental ship, unchanged since the last one.
+    Unchanged,
+    /// Left as it is since its page was edited in Notion.
+    Skipped,
+    Draft,
+    /// Not written, the ship was stopped first.
+    Interrupted,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FileReport {
+    pub path: String,
+    pub status: FileStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+impl FileReport {
+    fn unshipped(path: &str, status: FileStatus) -> Self {
+        FileReport {
+            path: path.to_string(),
+            status,
+            title: None,
+            page_id: None,
+            url: None,
+        }
+    }
+}
+
+/// How the ship to one parent page went: `[notion] parent_page`, or one of the
+/// `[[destinations]]`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DestinationReport {
+    pub name: String,
+    pub parent_page_url: Option<String>,
+    /// Why the ship failed, if it did.
+    pub error: Option<String>,
+    /// The id Notion gave the request that failed the ship, to quote in support tickets.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    pub files: Vec<FileReport>,
+    pub warnings: Vec<Diagnostic>,
+    /// The requests the ship sent to Notion, not counted when it failed.
+    #[serde(default)]
+    pub api: ApiUsage,
+    /// What was left out of the pages, or masked, following `[redact]`.
+    #[serde(default)]
+    pub redactions: Vec<Redaction>,
+}
+
+/// The report of a `ship`, written as JSON so publishing runs can be archived.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ShipReport {
+    pub src: String,
+    pub started_at: String,
+    pub finished_at: String,
+    pub duration_ms: i64,
+    pub destinations: Vec<DestinationReport>,
+    /// The requests sent to Notion by every ship, failed ones included.
+    #[serde(default)]
+    pub api: ApiUsage,
+    #[serde(skip)]
+    started: Option<DateTime<Utc>>,
+}
+
+impl ShipReport {
+    pub fn new(src: &str, started: DateTime<Utc>) -> Self {
+        ShipReport {
+            src: src.to_string(),
+            started_at: started.to_rfc3339_opts(SecondsFormat::Millis, true),
+            finished_at: String::new(),
+            duration_ms: 0,
+            destinations: Vec::new(),
+            api: ApiUsage::default(),
+            started: Some(started),
+        }
+    }
+
+    /// Adds how the ship to `name` went, its summary or why it failed.
+    pub fn add(
+        &mut self,
+        name: &str,
+        parent_page_url: Option<String>,
+        outcome: std::result::Result<&ShipSummary, String>,
+    ) {
+        let redactions = outcome.as_ref().map(|s| s.redactions.clone()).unwrap_or_default();
+        let (files, warnings, api, error) = match outcome {
+            Ok(summary) if summary.is_interrupted() => (
+                files(summary),
+                summary.warnings.clone(),
+                summary.api,
+                Some(format!("stopped with {} file(s) left to ship", summary.interrupted.len())),
+            ),
+            Ok(summary) => (files(summary), summary.warnings.clone(), summary.api, None),
+            Err(e) => (Vec::new(), Vec::new(), ApiUsage::default(), Some(e)),
+        };
+        self.destinations.push(DestinationReport {
+            name: name.to_string(),
+            parent_page_url,
+            request_id: error.as_deref().and_then(request_id_in),
+            error,
+            files,
+            warnings,
+            api,
+            redactions,
+        });
+    }
+
+    pub fn with_api_usage(mut self, api: ApiUsage) -> Self {
+        self.api = api;
+        self
+    }
+
+    /// Records when the ship finished, and so how long it took.
+    pub fn finish(mut self, finished: DateTime<Utc>) -> Self {
+        self.finished_at = finished.to_rfc3339_opts(SecondsFormat::Millis, true);
+        if let Some(started) = self.started {
+            self.duration_ms = (finished - started).num_milliseconds();
+        }
+        self
+    }
+
+    /// Whether the ship to any parent page failed.
+    pub fn is_failed(&self) -> bool {
+        self.destinations.iter().any(|d| d.error.is_some())
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .map_err(|e| anyhow!("failed to write the ship report to {}: {}", path.display(), e))
+    }
+}
+
+fn files(summary: &ShipSummary) -> Vec<FileReport> {
+    let shipped = summary.pages.iter().chain(summary.unchanged.iter());
+    let shipped = shipped.filter(|p| !summary.interrupted.contains(&p.path)).map(|p| FileReport {
+        path: p.path.clone(),
+        status: match p.status {
+            PageStatus::Created => FileStatus::Created,
+            PageStatus::Updated => FileStatus::Updated,
+            PageStatus::Unchanged => FileStatus::Unchanged,
+        },
+        title: Some(p.title.clone()),
+        page_id: Some(p.page_id.clone()),
+        url: Some(p.url.clone()),
+    });
+    let skipped = summary.skipped.iter().map(|p| FileReport::unshipped(p, FileStatus::Skipped));
+    let drafts = summary.drafts.iter().map(|p| FileReport::unshipped(p, FileStatus::Draft));
+    let interrupted = summary.interrupted.iter().map(|p| FileReport::unshipped(p, FileStatus::Interrupted));
+    shipped.chain(skipped).chain(drafts).chain(interrupted).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use crate::markdown::diagnostic::Diagnostic;
+    use crate::markdown::redaction::Redaction;
+    use crate::notion::report::{FileStatus, ShipReport};
+    use crate::notion::summary::{PageStatus, ShipSummary, ShippedPage};
+    use crate::notion::usage::ApiUsage;
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_ship_report() {
+        let summary = ShipSummary {
+            pages: vec![
+                ShippedPage::new("guide.md".to_string(), "Guide".to_string(), "aaa".to_string()),
+                ShippedPage::new("api.md".to_string(), "API".to_string(), "bbb".to_string())
+                    .with_status(PageStatus::Updated),
+            ],
+            warnings: vec![Diagnostic::warning("docs/guide.md", None, "no heading #setup".to_string())],
+            drafts: vec!["wip.md".to_string()],
+            unchanged: vec![ShippedPage::new("faq.md".to_string(), "FAQ".to_string(), "ccc".to_string())
+                .with_status(PageStatus::Unchanged)],
+            skipped: vec!["edited.md".to_string()],
+            api: ApiUsage {
+                calls: 12,
+                retries: 1,
+                rate_limited: 1,
+                backoff_ms: 1000,
+            },
+            interrupted: vec![],
+            redactions: vec![Redaction {
+                file: "docs/guide.md".to_string(),
+                line: Some(3),
+                rule: "<!-- private -->".to_string(),
+            }],
+        };
+        let started = Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap();
+        let mut report = ShipReport::new("docs", started);
+        report.add("docs", Some("https://www.notion.so/docs-123".to_string()), Ok(&summary));
+        report.add("staging", None, Err("parent page not found".to_string()));
+        report.add(
+            "archive",
+            None,
+            Err("(request_status=502 Bad Gateway, request_id=6f1d2c3b-aaaa) failed to append block: {}".to_string()),
+        );
+        let report = report.finish(started + Duration::milliseconds(1500));
+
+        assert_eq!(report.started_at, "2026-01-02T03:04:05.000Z");
+        assert_eq!(report.duration_ms, 1500);
+        let files: Vec<(&str, FileStatus)> = report.destinations[0]
+            .files
+            .iter()
+            .map(|f| (f.path.as_str(), f.status))
+            .collect();
+        assert_eq!(
+            files,
+            vec![
+                ("guide.md", FileStatus::Created),
+                ("api.md", FileStatus::Updated),
+                ("faq.md", FileStatus::Unchanged),
+                ("edited.md", FileStatus::Skipped),
+                ("wip.md", FileStatus::Draft),
+            ]
+        );
+        assert_eq!(report.destinations[0].files[1].url.as_deref(), Some("https://www.notion.so/bbb"));
+        assert_eq!(report.destinations[1].error.as_deref(), Some("parent page not found"));
+        assert_eq!(report.destinations[1].request_id, None);
+        assert_eq!(report.destinations[2].request_id.as_deref(), Some("6f1d2c3b-aaaa"));
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["destinations"][0]["warnings"][0]["file"], "docs/guide.md");
+        assert_eq!(json["destinations"][0]["files"][2]["status"], "unchanged");
+        assert_eq!(json["destinations"][0]["files"][2]["url"], "https://www.notion.so/ccc");
+        assert_eq!(json["destinations"][0]["api"]["rate_limited"], 1);
+        assert_eq!(json["destinations"][0]["redactions"][0]["line"], 3);
+        assert_eq!(json["destinations"][1]["api"]["calls"], 0);
+        assert!(json["destinations"][0]["files"][3].get("url").is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_interrupted_report() {
+        let summary = ShipSummary {
+            pages: vec![
+                ShippedPage::new("guide.md".to_string(), "Guide".to_string(), "aaa".to_string()),
+                ShippedPage::new("api.md".to_string(), "API".to_string(), "bbb".to_string()),
+            ],
+            interrupted: vec!["api.md".to_string(), "faq.md".to_string()],
+            ..Default::default()
+        };
+        let mut report = ShipReport::new("docs", Utc::now());
+        report.add("docs", None, Ok(&summary));
+        assert!(report.is_failed());
+        assert_eq!(report.destinations[0].error.as_deref(), Some("stopped with 2 file(s) left to ship"));
+        let files: Vec<(&str, FileStatus)> = report.destinations[0]
+            .files
+            .iter()
+            .map(|f| (f.path.as_str(), f.status))
+            .collect();
+        assert_eq!(
+            files,
+            vec![
+                ("guide.md", FileStatus::Created),
+                ("api.md", FileStatus::Interrupted),
+                ("faq.md", FileStatus::Interrupted),
+            ]
+        );
+    }
+}
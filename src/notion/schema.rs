@@ -0,0 +1,189 @@
+use std::sync::OnceLock;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::markdown::diagnostic::Diagnostic;
+use crate::notion::block::AppendBlockRequest;
+use crate::notion::page::CreatePageRequest;
+
+/// The shapes of the requests notation sends Notion, as a JSON Schema.
+const SCHEMA: &str = include_str!("../../schema/notion-requests.schema.json");
+
+static PARSED_SCHEMA: OnceLock<Value> = OnceLock::new();
+
+fn request_schema() -> &'static Value {
+    PARSED_SCHEMA.get_or_init(|| serde_json::from_str(SCHEMA).expect("the embedded request schema is valid JSON"))
+}
+
+/// Where a block request doesn't match the schema, like
+/// `children[2].table.table_width: below the minimum of 1`. Empty when it does.
+pub fn append_problems(request: &AppendBlockRequest) -> Vec<String> {
+    problems("append_block_request", request)
+}
+
+pub fn create_page_problems(request: &CreatePageRequest) -> Vec<String> {
+    problems("create_page_request", request)
+}
+
+/// Warnings for a request of `file` that Notion would reject, see [`append_problems`].
+pub fn request_warnings(file: &str, problems: Vec<String>) -> Vec<Diagnostic> {
+    problems
+        .into_iter()
+        .map(|p| Diagnostic::warning(file, None, format!("the Notion request doesn't match its schema, {}", p)))
+        .collect()
+}
+
+fn problems(definition: &str, request: &impl Serialize) -> Vec<String> {
+    let value = match serde_json::to_value(request) {
+        Ok(v) => v,
+        Err(e) => return vec![format!("failed to serialize: {}", e)],
+    };
+    let mut problems = Vec::new();
+    validate(&request_schema()["$defs"][definition], &value, "", &mut problems);
+    problems
+}
+
+/// Checks `value` against `schema`, for the keywords the embedded schema uses: `$ref`, `type`,
+/// `const`, `required`, `properties`, `items`, `minItems`, `maxItems`, `minLength`,
+/// `maxLength`, `minimum` and `oneOf`.
+pub fn validate(schema: &Value, value: &Value, path: &str, problems: &mut Vec<String>) {
+    let at = |message: String| if path.is_empty() { message } else { format!("{}: {}", path, message) };
+    if let Some(reference) = schema["$ref"].as_str() {
+        match reference.strip_prefix("#/$defs/").map(|name| &request_schema()["$defs"][name]) {
+            Some(definition) if !definition.is_null() => validate(definition, value, path, problems),
+            _ => problems.push(at(format!("unknown schema reference {}", reference))),
+        }
+    }
+    if let Some(types) = schema.get("type") {
+        let types: Vec<&str> = match types {
+            Value::Array(t) => t.iter().filter_map(|t| t.as_str()).collect(),
+            t => t.as_str().into_iter().collect(),
+        };
+        if !types.iter().any(|t| is_type(value, t)) {
+            problems.push(at(format!("expected {}, found {}", types.join(" or "), type_name(value))));
+            return;
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if value != expected {
+            problems.push(at(format!("expected {}, found {}", expected, value)));
+        }
+    }
+    if let Value::Object(object) = value {
+        for key in schema["required"].as_array().into_iter().flatten().filter_map(|k| k.as_str()) {
+            if !object.contains_key(key) {
+                problems.push(at(format!("missing {}", key)));
+            }
+        }
+        for (key, property) in schema["properties"].as_object().into_iter().flatten() {
+            if let Some(v) = object.get(key) {
+                let path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                validate(property, v, &path, problems);
+            }
+        }
+    }
+    if let Value::Array(items) = value {
+        if let Some(min) = schema["minItems"].as_u64().filter(|m| (items.len() as u64) < *m) {
+            problems.push(at(format!("{} item(s), fewer than {}", items.len(), min)));
+        }
+        if let Some(max) = schema["maxItems"].as_u64().filter(|m| (items.len() as u64) > *m) {
+            problems.push(at(format!("{} item(s), more than {}", items.len(), max)));
+        }
+        if schema.get("items").is_some() {
+            for (i, item) in items.iter().enumerate() {
+                validate(&schema["items"], item, &format!("{}[{}]", path, i), problems);
+            }
+        }
+    }
+    if let Value::String(s) = value {
+        let length = s.chars().count() as u64;
+        if let Some(min) = schema["minLength"].as_u64().filter(|m| length < *m) {
+            problems.push(at(format!("{} character(s), fewer than {}", length, min)));
+        }
+        if let Some(max) = schema["maxLength"].as_u64().filter(|m| length > *m) {
+            problems.push(at(format!("{} character(s), more than {}", length, max)));
+        }
+    }
+    if let (Some(number), Some(minimum)) = (value.as_f64(), schema["minimum"].as_f64()) {
+        if number < minimum {
+            problems.push(at(format!("{} is below the minimum of {}", number, minimum)));
+        }
+    }
+    if let Some(branches) = schema["oneOf"].as_array() {
+        let outcomes: Vec<Vec<String>> = branches
+            .iter()
+            .map(|b| {
+                let mut branch_problems = Vec::new();
+                validate(b, value, path, &mut branch_problems);
+                branch_problems
+            })
+            .collect();
+        match outcomes.iter().filter(|p| p.is_empty()).count() {
+            1 => {}
+            0 => {
+                // The branch of the value's own `type`, when the branches are told apart by it,
+                // says best what is wrong.
+                let own = branches.iter().position(|b| b["properties"]["type"]["const"] == value["type"]);
+                match own {
+                    Some(i) => problems.extend(outcomes[i].clone()),
+                    None => problems.push(at(format!("unexpected type {}", value["type"]))),
+                }
+            }
+            n => problems.push(at(format!("matches {} alternatives instead of one", n))),
+        }
+    }
+}
+
+fn is_type(value: &Value, name: &str) -> bool {
+    match name {
+        "integer" => value.is_u64() || value.is_i64(),
+        name => type_name(value) == name || (name == "number" && value.is_number()),
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::notion::block::{AppendBlockRequest, AppendBlockRequestChild};
+    use crate::notion::page::CreatePageRequest;
+    use crate::notion::schema::{append_problems, create_page_problems, request_schema, validate};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_schema() {
+        let request = AppendBlockRequest::new_children(vec![
+            AppendBlockRequestChild::new_paragraph_block("Hello".to_string()),
+            AppendBlockRequestChild::new_paragraph_block("x".repeat(2001)),
+        ]);
+        assert_eq!(append_problems(&request), vec!["children[1].paragraph.rich_text[0].text.content: 2001 character(s), more than 2000"]);
+        let page = CreatePageRequest::new("parent".to_string(), "Guide".to_string()).with_icon("📘".to_string());
+        assert!(create_page_problems(&page).is_empty());
+
+        let mut problems = Vec::new();
+        let table = json!({
+            "object": "block",
+            "type": "table",
+            "table": { "has_column_header": true, "has_row_header": false, "children": [] },
+        });
+        validate(&request_schema()["$defs"]["block"], &table, "children[0]", &mut problems);
+        assert_eq!(
+            problems,
+            vec!["children[0].table: missing table_width", "children[0].table.children: 0 item(s), fewer than 1"]
+        );
+        let mut problems = Vec::new();
+        validate(&request_schema()["$defs"]["block"], &json!({ "object": "block", "type": "quote", "quote": {} }), "", &mut problems);
+        assert_eq!(problems, vec!["unexpected type \"quote\""]);
+    }
+}
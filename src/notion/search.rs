@@ -1,26 +1,70 @@
+use std::collections::VecDeque;
+
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::notion::client::NotionClient;
 
+/// A request to the search API. Notion returns at most 100 results at a time, see
+/// [`NotionClient::search_iter`] for all of them.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SearchRequest {
     query: String,
-    filter: SearchFilter,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filter: Option<SearchFilter>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sort: Option<SearchSort>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start_cursor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page_size: Option<u32>,
+}
+
+/// What a search is limited to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchObject {
+    #[default]
+    Page,
+    Database,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct SearchFilter {
-    value: String,
+    value: SearchObject,
     property: String,
 }
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    Ascending,
+    #[default]
+    Descending,
+}
+
+/// The time results are sorted by. Notion only sorts searches by the last edit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortTimestamp {
+    #[default]
+    LastEditedTime,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct SearchResult {
-    pub results: Vec<SearchResultItem>,
+struct SearchSort {
+    direction: SortDirection,
+    timestamp: SortTimestamp,
 }
 
-impl Default for SearchResult {
-    fn default() -> Self {
-        SearchResult { results: vec![] }
-    }
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SearchResult {
+    pub results: Vec<SearchResultItem>,
+    #[serde(default)]
+    pub has_more: bool,
+    #[serde(default)]
+    pub next_cursor: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -32,9 +76,16 @@ pub struct SearchResultItem {
     pub properties: SearchResultItemProperty,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct SearchResultItemParent {
+    /// `page_id`, `block_id`, `database_id` or `workspace`.
+    #[serde(rename = "type", default)]
+    pub parent_type: Option<String>,
     pub page_id: Option<String>,
+    #[serde(default)]
+    pub block_id: Option<String>,
+    #[serde(default)]
+    pub database_id: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -53,13 +104,117 @@ pub struct TitleArrayElement {
 }
 
 impl SearchRequest {
+    /// A search of the pages matching `query`, an empty one matching them all.
     pub fn new(query: String) -> Self {
         SearchRequest {
             query,
-            filter: SearchFilter {
-                value: "page".to_string(),
-                property: "object".to_string(),
-            },
+            filter: None,
+            sort: None,
+            start_cursor: None,
+            page_size: None,
         }
+        .with_object(Some(SearchObject::Page))
+    }
+
+    /// Limits the search to pages or to databases, or searches both with `None`.
+    pub fn with_object(mut self, object: Option<SearchObject>) -> Self {
+        self.filter = object.map(|value| SearchFilter {
+            value,
+            property: "object".to_string(),
+        });
+        self
+    }
+
+    /// Sorts the results by `timestamp` instead of by relevance.
+    pub fn with_sort(mut self, direction: SortDirection, timestamp: SortTimestamp) -> Self {
+        self.sort = Some(SearchSort { direction, timestamp });
+        self
+    }
+
+    /// How many results are returned at a time, at most 100.
+    pub fn with_page_size(mut self, page_size: u32) -> Self {
+        self.page_size = Some(page_size.clamp(1, MAX_PAGE_SIZE));
+        self
+    }
+
+    /// The next page of results, after those ending at `cursor`.
+    pub fn with_start_cursor(mut self, cursor: Option<String>) -> Self {
+        self.start_cursor = cursor;
+        self
+    }
+}
+
+const MAX_PAGE_SIZE: u32 = 100;
+
+/// The results of a search, one at a time, fetched a page of results at a time as they are
+/// read. See [`NotionClient::search_iter`].
+pub struct SearchIter<'a> {
+    client: &'a NotionClient,
+    request: SearchRequest,
+    results: VecDeque<Value>,
+    is_done: bool,
+}
+
+impl<'a> SearchIter<'a> {
+    pub fn new(client: &'a NotionClient, request: SearchRequest) -> Self {
+        SearchIter {
+            client,
+            request,
+            results: VecDeque::new(),
+            is_done: false,
+        }
+    }
+
+    /// The next result, a page or a database object of the API, `None` after the last one.
+    pub async fn next(&mut self) -> Result<Option<Value>> {
+        while self.results.is_empty() && !self.is_done {
+            let page = self.client.search_page(&self.request).await?;
+            self.results.extend(page.results);
+            match page.next_cursor {
+                Some(c) if page.has_more => self.request.start_cursor = Some(c),
+                _ => self.is_done = true,
+            }
+        }
+        Ok(self.results.pop_front())
+    }
+
+    /// All the remaining results.
+    pub async fn collect(mut self) -> Result<Vec<Value>> {
+        let mut results = Vec::new();
+        while let Some(r) = self.next().await? {
+            results.push(r);
+        }
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::notion::search::{SearchObject, SearchRequest, SortDirection, SortTimestamp};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_search_request() {
+        let request = serde_json::to_value(SearchRequest::new("Docs".to_string())).unwrap();
+        assert_eq!(request, json!({"query": "Docs", "filter": {"value": "page", "property": "object"}}));
+
+        let request = SearchRequest::new(String::new())
+            .with_object(Some(SearchObject::Database))
+            .with_sort(SortDirection::Ascending, SortTimestamp::LastEditedTime)
+            .with_page_size(500)
+            .with_start_cursor(Some("abc".to_string()));
+        assert_eq!(
+            serde_json::to_value(request).unwrap(),
+            json!({
+                "query": "",
+                "filter": {"value": "database", "property": "object"},
+                "sort": {"direction": "ascending", "timestamp": "last_edited_time"},
+                "start_cursor": "abc",
+                "page_size": 100,
+            })
+        );
+        let request = SearchRequest::new(String::new()).with_object(None);
+        assert_eq!(serde_json::to_value(request).unwrap(), json!({"query": ""}));
     }
 }
@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use async_recursion::async_recursion;
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::notion::block::{AppendBlockRequest, AppendBlockRequestChild, BlockContent};
+use crate::notion::client::NotionClient;
+
+/// A page of a [`Snapshot`], with the pages under it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SnapshotPage {
+    pub page_id: String,
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emoji: Option<String>,
+    pub blocks: Vec<AppendBlockRequestChild>,
+    #[serde(default)]
+    pub pages: Vec<SnapshotPage>,
+}
+
+impl SnapshotPage {
+    fn page_count(&self) -> usize {
+        1 + self.pages.iter().map(|p| p.page_count()).sum::<usize>()
+    }
+
+    fn block_count(&self) -> usize {
+        self.blocks.iter().map(|b| b.block_count()).sum::<usize>()
+            + self.pages.iter().map(|p| p.block_count()).sum::<usize>()
+    }
+}
+
+/// The blocks and pages under the parent page, as they were when the snapshot was taken.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub taken_at: String,
+    /// The parent page, whose title and icon are left as they are.
+    pub root: SnapshotPage,
+    /// What the snapshot couldn't keep, by page title and block type, like `Guide: synced_block`.
+    #[serde(default)]
+    pub left_out: Vec<String>,
+}
+
+impl Snapshot {
+    /// How many pages there are under the parent page.
+    pub fn page_count(&self) -> usize {
+        self.root.page_count() - 1
+    }
+
+    /// How many blocks there are on the parent page and the pages under it, nested ones included.
+    pub fn block_count(&self) -> usize {
+        self.root.block_count()
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("failed to read {}: {}", path.display(), e))?;
+        serde_json::from_str(&contents).map_err(|e| anyhow!("(file={}) not a snapshot: {}", path.display(), e))
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .map_err(|e| anyhow!("failed to write the snapshot to {}: {}", path.display(), e))
+    }
+}
+
+/// How a [`restore`] went.
+#[derive(Clone, Debug, Default)]
+pub struct RestoreReport {
+    pub pages: usize,
+    pub blocks: usize,
+    /// The ids of the recreated pages by the ids of the pages they were recreated from.
+    pub page_ids: HashMap<String, String>,
+}
+
+/// Reads the blocks on the page `page_id`, and those nested in them, and the pages under it.
+pub async fn take(client: &NotionClient, page_id: &str, title: &str) -> Result<Snapshot> {
+    let mut left_out = Vec::new();
+    let root = read_page(client, page_id.to_string(), title.to_string(), None, &mut left_out).await?;
+    Ok(Snapshot {
+        taken_at: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+        root,
+        left_out,
+    })
+}
+
+#[async_recursion]
+async fn read_page(
+    client: &NotionClient,
+    page_id: String,
+    title: String,
+    emoji: Option<String>,
+    left_out: &mut Vec<String>,
+) -> Result<SnapshotPage> {
+    let mut page = SnapshotPage {
+        page_id,
+        title,
+        emoji,
+        blocks: Vec::new(),
+        pages: Vec::new(),
+    };
+    let mut blocks = Vec::new();
+    read_blocks(client, &page.page_id, &page.title, &mut blocks, &mut page.pages, left_out).await?;
+    page.blocks = blocks;
+    Ok(page)
+}
+
+/// Reads the children of `block_id` into `blocks`, and the child pages among them, or nested in
+/// them, into `pages`.
+#[async_recursion]
+async fn read_blocks(
+    client: &NotionClient,
+    block_id: &str,
+    title: &str,
+    blocks: &mut Vec<AppendBlockRequestChild>,
+    pages: &mut Vec<SnapshotPage>,
+    left_out: &mut Vec<String>,
+) -> Result<()> {
+    for value in client.get_all_block_children::<Value>(block_id).await? {
+        let kind = value["type"].as_str().unwrap_or("unknown").to_string();
+        let id = value["id"].as_str().unwrap_or_default().to_string();
+        if kind == "child_page" {
+            let child_title = value["child_page"]["title"].as_str().unwrap_or_default().to_string();
+            let emoji = client.get_page(&id).await?.emoji();
+            pages.push(read_page(client, id, child_title, emoji, left_out).await?);
+            continue;
+        }
+        let mut block = match restorable_block(&value) {
+            Some(block) => block,
+            None => {
+                left_out.push(format!("{}: {}", title, kind));
+                continue;
+            }
+        };
+        if value["has_children"].as_bool().unwrap_or(false) {
+            let mut children = Vec::new();
+            read_blocks(client, &id, title, &mut children, pages, left_out).await?;
+            if !block.set_children(children) {
+                left_out.push(format!("{}: blocks nested in a {}", title, kind));
+            }
+        }
+        blocks.push(block);
+    }
+    Ok(())
+}
+
+/// A block as read from Notion, if it can be appended again. Inline databases, and files
+/// uploaded to Notion, whose URLs expire, can't.
+pub fn restorable_block(value: &Value) -> Option<AppendBlockRequestChild> {
+    let block: AppendBlockRequestChild = serde_json::from_value(value.clone()).ok()?;
+    let is_restorable = match &block.content {
+        BlockContent::ChildDatabase { .. } => false,
+        BlockContent::Image { image } => image.external.is_some() || image.file_upload.is_some(),
+        _ => true,
+    };
+    is_restorable.then_some(block)
+}
+
+/// Recreates the blocks and pages of `snapshot` under the page `page_id`, after what is
+/// already there.
+pub async fn restore(client: &NotionClient, snapshot: &Snapshot, page_id: &str) -> Result<RestoreReport> {
+    let mut report = RestoreReport::default();
+    restore_page(client, page_id, &snapshot.root, &mut report).await?;
+    Ok(report)
+}
+
+#[async_recursion]
+async fn restore_page(client: &NotionClient, page_id: &str, page: &SnapshotPage, report: &mut RestoreReport) -> Result<()> {
+    // The blocks go first, so they end up above the pages, as they were shipped.
+    if !page.blocks.is_empty() {
+        client
+            .append_block(page_id.to_string(), &AppendBlockRequest::new_children(page.blocks.clone()))
+            .await?;
+        report.blocks += page.blocks.iter().map(|b| b.block_count()).sum::<usize>();
+    }
+    for child in page.pages.iter() {
+        let id = client
+            .create_page_by_parent_id(page_id.to_string(), child.title.clone(), child.emoji.clone())
+            .await?;
+        report.pages += 1;
+        report.page_ids.insert(child.page_id.clone(), id.clone());
+        restore_page(client, &id, child, report).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::notion::block::{AppendBlockRequestChild, BlockType};
+    use crate::notion::snapshot::{restorable_block, Snapshot, SnapshotPage};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_snapshot() {
+        let paragraph = json!({
+            "object": "block",
+            "id": "b1",
+            "type": "paragraph",
+            "has_children": true,
+            "paragraph": { "rich_text": [{ "type": "text", "text": { "content": "Hello", "link": null }, "plain_text": "Hello" }], "color": "default" }
+        });
+        let mut block = restorable_block(&paragraph).unwrap();
+        assert!(block.set_children(vec![AppendBlockRequestChild::new_paragraph_block("nested".to_string())]));
+        assert_eq!(block.block_count(), 2);
+        assert!(!AppendBlockRequestChild::new_divider_block().set_children(vec![]));
+
+        let uploaded = json!({
+            "object": "block",
+            "type": "image",
+            "image": { "type": "file", "file": { "url": "https://s3.example.com/a.png", "expiry_time": "2026-01-01T00:00:00.000Z" } }
+        });
+        assert!(restorable_block(&uploaded).is_none());
+        let external = json!({
+            "object": "block",
+            "type": "image",
+            "image": { "type": "external", "external": { "url": "https://example.com/a.png" } }
+        });
+        assert!(matches!(restorable_block(&external).unwrap().block_type(), BlockType::Image));
+        assert!(restorable_block(&json!({ "object": "block", "type": "synced_block", "synced_block": {} })).is_none());
+
+        let page = |title: &str, pages: Vec<SnapshotPage>| SnapshotPage {
+            page_id: format!("{}-id", title),
+            title: title.to_string(),
+            emoji: None,
+            blocks: vec![block.clone()],
+            pages,
+        };
+        let snapshot = Snapshot {
+            taken_at: "2026-01-02T03:04:05.000Z".to_string(),
+            root: page("Docs", vec![page("Guide", vec![page("Setup", vec![])]), page("API", vec![])]),
+            left_out: vec!["Guide: synced_block".to_string()],
+        };
+        assert_eq!(snapshot.page_count(), 3);
+        assert_eq!(snapshot.block_count(), 8);
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let read: Snapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.root.pages[0].pages[0].title, "Setup");
+        assert_eq!(read.root.blocks[0].block_count(), 2);
+    }
+}
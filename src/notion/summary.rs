@@ -0,0 +1,168 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::markdown::diagnostic::Diagnostic;
+use crate::markdown::redaction::Redaction;
+use crate::notion::usage::ApiUsage;
+
+pub fn page_url(page_id: &str) -> String {
+    format!("https://www.notion.so/{}", page_id.replace("-", ""))
+}
+
+/// Whether a ship created a page, wrote over the one of an earlier ship or left it as it is.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PageStatus {
+    #[default]
+    Created,
+    Updated,
+    Unchanged,
+}
+
+/// A page created or updated by a ship, for a markdown file or for a directory.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ShippedPage {
+    pub path: String,
+    pub title: String,
+    pub page_id: String,
+    pub url: String,
+    #[serde(default)]
+    pub status: PageStatus,
+}
+
+impl ShippedPage {
+    pub fn new(path: String, title: String, page_id: String) -> Self {
+        ShippedPage {
+            path,
+            title,
+            url: page_url(&page_id),
+            page_id,
+            status: PageStatus::Created,
+        }
+    }
+
+    pub fn with_status(mut self, status: PageStatus) -> Self {
+        self.status = status;
+        self
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ShipSummary {
+    pub pages: Vec<ShippedPage>,
+    pub warnings: Vec<Diagnostic>,
+    /// The files left out as drafts, relative to the shipped root.
+    #[serde(default)]
+    pub drafts: Vec<String>,
+    /// The pages of the files an incremental ship left as they are, unchanged since the last
+    /// ship.
+    #[serde(default)]
+    pub unchanged: Vec<ShippedPage>,
+    /// The files whose pages were edited in Notion since the last ship, left as they are.
+    #[serde(default)]
+    pub skipped: Vec<String>,
+    /// The requests the ship sent to Notion.
+    #[serde(default)]
+    pub api: ApiUsage,
+    /// The files, or merged directories, a ship that was stopped didn't write, relative to
+    /// the shipped root.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub interrupted: Vec<String>,
+    /// What was left out of the pages, or masked, following `[redact]`.
+    #[serde(default)]
+    pub redactions: Vec<Redaction>,
+}
+
+/// What a ship is up to, sent as it goes to [`crate::notion::client::ShipOptions::progress`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ShipProgress {
+    /// The page of a file or directory was created, or will be written over.
+    Page(ShippedPage),
+    /// The content of the file, or merged directory, at `path` was written to its page.
+    Written { path: String },
+}
+
+impl ShipSummary {
+    /// Whether the ship was stopped before writing everything, see
+    /// [`crate::notion::client::NotionClient::with_stop`].
+    pub fn is_interrupted(&self) -> bool {
+        !self.interrupted.is_empty()
+    }
+
+    /// Every page of the ship, created, updated or unchanged, by the path of its file or
+    /// directory relative to the shipped root.
+    pub fn manifest_pages(&self) -> BTreeMap<String, ManifestPage> {
+        self.pages
+            .iter()
+            .chain(self.unchanged.iter())
+            .map(|p| {
+                let page = ManifestPage {
+                    page_id: p.page_id.clone(),
+                    url: p.url.clone(),
+                    title: p.title.clone(),
+                };
+                (p.path.replace('\\', "/"), page)
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ManifestPage {
+    pub page_id: String,
+    pub url: String,
+    pub title: String,
+}
+
+/// Where the files of a ship ended up in Notion, for other tools to link to the pages.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub parent_page_url: Option<String>,
+    pub pages: BTreeMap<String, ManifestPage>,
+}
+
+impl Manifest {
+    pub fn new(parent_page_url: Option<String>, summary: &ShipSummary) -> Self {
+        Manifest {
+            parent_page_url,
+            pages: summary.manifest_pages(),
+        }
+    }
+}
+
+/// Writes a manifest, or manifests by destination, as JSON to `path`.
+pub fn write_manifest(path: &Path, manifest: &impl Serialize) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(manifest)?)
+        .map_err(|e| anyhow!("failed to write the manifest to {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::notion::summary::{Manifest, PageStatus, ShipSummary, ShippedPage};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_manifest() {
+        let summary = ShipSummary {
+            pages: vec![
+                ShippedPage::new("api".to_string(), "API".to_string(), "aaa-111".to_string()),
+                ShippedPage::new("api/auth.md".to_string(), "Auth".to_string(), "bbb".to_string()),
+            ],
+            unchanged: vec![ShippedPage::new("faq.md".to_string(), "FAQ".to_string(), "ccc".to_string())
+                .with_status(PageStatus::Unchanged)],
+            drafts: vec!["wip.md".to_string()],
+            ..Default::default()
+        };
+        let manifest = Manifest::new(Some("https://www.notion.so/docs-123".to_string()), &summary);
+        let paths: Vec<&str> = manifest.pages.keys().map(String::as_str).collect();
+        assert_eq!(paths, vec!["api", "api/auth.md", "faq.md"]);
+        assert_eq!(manifest.pages["api"].url, "https://www.notion.so/aaa111");
+        assert_eq!(manifest.pages["faq.md"].title, "FAQ");
+    }
+}
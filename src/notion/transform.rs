@@ -0,0 +1,99 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use crate::notion::block::AppendBlockRequest;
+
+/// What a transform knows about the file whose blocks it is given.
+#[derive(Clone, Debug)]
+pub struct DocContext {
+    /// The file, relative to the shipped root.
+    pub relative_path: PathBuf,
+    pub path: PathBuf,
+    pub page_id: String,
+    pub title: String,
+    /// The merged directory whose page the file becomes a section of, if any.
+    pub merged_into: Option<PathBuf>,
+}
+
+/// Rewrites the blocks converted from a file before they are shipped, e.g. to expand custom
+/// directives or enforce a link policy. Plain functions and closures taking
+/// `(&mut AppendBlockRequest, &DocContext)` are transforms too.
+pub trait BlockTransform: Send + Sync {
+    fn transform(&self, request: &mut AppendBlockRequest, ctx: &DocContext) -> Result<()>;
+}
+
+impl<F> BlockTransform for F
+where
+    F: Fn(&mut AppendBlockRequest, &DocContext) + Send + Sync,
+{
+    fn transform(&self, request: &mut AppendBlockRequest, ctx: &DocContext) -> Result<()> {
+        self(request, ctx);
+        Ok(())
+    }
+}
+
+/// The transforms registered on a client, run in the order they were added.
+#[derive(Clone, Default)]
+pub struct TransformPipeline {
+    transforms: Vec<Arc<dyn BlockTransform>>,
+}
+
+impl TransformPipeline {
+    pub fn push(&mut self, transform: impl BlockTransform + 'static) {
+        self.transforms.push(Arc::new(transform));
+    }
+
+    pub fn apply(&self, request: &mut AppendBlockRequest, ctx: &DocContext) -> Result<()> {
+        for t in self.transforms.iter() {
+            t.transform(request, ctx)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use anyhow::{anyhow, Result};
+
+    use crate::notion::block::{AppendBlockRequest, AppendBlockRequestChild};
+    use crate::notion::transform::{BlockTransform, DocContext, TransformPipeline};
+
+    struct NoEmptyPages;
+
+    impl BlockTransform for NoEmptyPages {
+        fn transform(&self, request: &mut AppendBlockRequest, ctx: &DocContext) -> Result<()> {
+            if request.children.is_empty() {
+                return Err(anyhow!("{} is empty", ctx.relative_path.display()));
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_transform_pipeline() {
+        let ctx = DocContext {
+            relative_path: PathBuf::from("a.md"),
+            path: PathBuf::from("docs/a.md"),
+            page_id: "page".to_string(),
+            title: "a".to_string(),
+            merged_into: None,
+        };
+        let mut pipeline = TransformPipeline::default();
+        pipeline.push(|r: &mut AppendBlockRequest, ctx: &DocContext| {
+            r.append_child(AppendBlockRequestChild::new_paragraph_block(ctx.title.clone()));
+        });
+        pipeline.push(NoEmptyPages);
+
+        let mut request = AppendBlockRequest::new();
+        pipeline.apply(&mut request, &ctx).unwrap();
+        assert_eq!(request.children.len(), 1);
+
+        let mut strict = TransformPipeline::default();
+        strict.push(NoEmptyPages);
+        assert!(strict.apply(&mut AppendBlockRequest::new(), &ctx).is_err());
+    }
+}
@@ -0,0 +1,237 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use anyhow::Result;
+use serde_json::Value;
+
+use crate::markdown::source::source_extensions;
+use crate::notion::client::NotionClient;
+use crate::notion::page::PageContentType;
+
+/// Above this many pages, clearing takes typing the name of the parent page rather than
+/// pressing ENTER.
+pub const ENTER_CONFIRMATION_MAX_PAGES: usize = 10;
+
+/// A block or page under a page, `depth` levels down: 1 is on the page itself.
+#[derive(Clone, Debug)]
+pub struct PageTreeItem {
+    pub id: String,
+    pub content_type: PageContentType,
+    pub depth: usize,
+    /// The title of a child page.
+    pub title: Option<String>,
+}
+
+/// How many pages and blocks there are at one depth of a [`ClearPlan`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DepthCount {
+    pub pages: usize,
+    pub blocks: usize,
+}
+
+/// What it takes to go ahead with a clear.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Confirmation {
+    /// Nothing to clear.
+    None,
+    Enter,
+    /// Typing the name of the parent page.
+    PageName,
+}
+
+/// Everything a clear archives: the blocks and pages on the parent page, and those on the
+/// pages under it, all the way down.
+#[derive(Clone, Debug, Default)]
+pub struct ClearPlan {
+    pub items: Vec<PageTreeItem>,
+}
+
+impl ClearPlan {
+    /// Walks the pages under `page_id`, following pagination.
+    pub async fn read(client: &NotionClient, page_id: &str) -> Result<Self> {
+        ClearPlan::walk(client, Vec::new(), VecDeque::from([(page_id.to_string(), 1)])).await
+    }
+
+    /// Like [`ClearPlan::read`], for the pages themselves and what is under them, by id and title.
+    pub async fn read_pages(client: &NotionClient, pages: &[(String, String)]) -> Result<Self> {
+        let items = pages
+            .iter()
+            .map(|(id, title)| PageTreeItem {
+                id: id.clone(),
+                content_type: PageContentType::ChildPage,
+                depth: 1,
+                title: Some(title.clone()),
+            })
+            .collect();
+        let pages = pages.iter().map(|(id, _)| (id.clone(), 2)).collect();
+        ClearPlan::walk(client, items, pages).await
+    }
+
+    async fn walk(
+        client: &NotionClient,
+        mut items: Vec<PageTreeItem>,
+        mut pages: VecDeque<(String, usize)>,
+    ) -> Result<Self> {
+        while let Some((page_id, depth)) = pages.pop_front() {
+            for value in client.get_all_block_children::<Value>(&page_id).await? {
+                let content_type: PageContentType =
+                    serde_json::from_value(value["type"].clone()).unwrap_or_default();
+                let id = value["id"].as_str().unwrap_or_default().to_string();
+                let title = value["child_page"]["title"].as_str().map(|t| t.to_string());
+                if matches!(content_type, PageContentType::ChildPage) {
+                    pages.push_back((id.clone(), depth + 1));
+                }
+                items.push(PageTreeItem {
+                    id,
+                    content_type,
+                    depth,
+                    title,
+                });
+            }
+        }
+        Ok(ClearPlan { items })
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.items
+            .iter()
+            .filter(|i| matches!(i.content_type, PageContentType::ChildPage))
+            .count()
+    }
+
+    pub fn by_depth(&self) -> BTreeMap<usize, DepthCount> {
+        let mut counts: BTreeMap<usize, DepthCount> = BTreeMap::new();
+        for item in self.items.iter() {
+            let count = counts.entry(item.depth).or_default();
+            match item.content_type {
+                PageContentType::ChildPage => count.pages += 1,
+                _ => count.blocks += 1,
+            }
+        }
+        counts
+    }
+
+    pub fn confirmation(&self) -> Confirmation {
+        if self.items.is_empty() {
+            Confirmation::None
+        } else if self.page_count() <= ENTER_CONFIRMATION_MAX_PAGES {
+            Confirmation::Enter
+        } else {
+            Confirmation::PageName
+        }
+    }
+
+    /// Archives the blocks and pages at the top of the plan. Notion trashes what is under a
+    /// page with it, and takes it out of the trash with the page, so a restored page comes back
+    /// whole. Returns how many blocks and pages went, those under the top ones included.
+    pub async fn archive(&self, client: &NotionClient) -> Result<usize> {
+        for item in self.items.iter().filter(|i| i.depth == 1) {
+            client.delete(item.id.clone(), &item.content_type).await?;
+        }
+        Ok(self.items.len())
+    }
+}
+
+/// The page found by following `titles` down from the page `page_id`, one child page per
+/// title, by id and title. Titles match file and directory names loosely: `getting-started.md`
+/// finds "Getting started".
+pub async fn find_by_titles(client: &NotionClient, page_id: &str, titles: &[String]) -> Result<Option<(String, String)>> {
+    let mut found = None;
+    let mut page_id = page_id.to_string();
+    for title in titles.iter() {
+        let child = client
+            .get_all_block_children::<Value>(&page_id)
+            .await?
+            .into_iter()
+            .filter_map(|v| Some((v["id"].as_str()?.to_string(), v["child_page"]["title"].as_str()?.to_string())))
+            .find(|(_, t)| loose_title(t) == loose_title(title));
+        match child {
+            Some((id, t)) => {
+                page_id = id.clone();
+                found = Some((id, t));
+            }
+            None => return Ok(None),
+        }
+    }
+    Ok(found)
+}
+
+/// A title, or a file name, lowercased and without its extension, `-` or `_`.
+pub fn loose_title(title: &str) -> String {
+    let title = title.to_lowercase();
+    let title = source_extensions()
+        .iter()
+        .find_map(|e| title.strip_suffix(&format!(".{}", e)))
+        .unwrap_or(&title);
+    title
+        .replace(['-', '_'], " ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::notion::fake::FakeNotion;
+    use crate::notion::page::PageContentType;
+    use crate::notion::tree::{loose_title, ClearPlan, Confirmation, DepthCount, PageTreeItem};
+    use crate::sync::trash;
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_clear_plan() {
+        let item = |depth: usize, content_type: PageContentType| PageTreeItem {
+            id: format!("{}", depth),
+            content_type,
+            depth,
+            title: None,
+        };
+        let mut plan = ClearPlan::default();
+        assert_eq!(plan.confirmation(), Confirmation::None);
+
+        plan.items = vec![
+            item(1, PageContentType::Paragraph),
+            item(1, PageContentType::ChildPage),
+            item(2, PageContentType::ChildPage),
+            item(2, PageContentType::Unknown),
+            item(3, PageContentType::Paragraph),
+        ];
+        assert_eq!(plan.page_count(), 2);
+        let by_depth = plan.by_depth();
+        assert_eq!(by_depth[&1], DepthCount { pages: 1, blocks: 1 });
+        assert_eq!(by_depth[&2], DepthCount { pages: 1, blocks: 1 });
+        assert_eq!(by_depth[&3], DepthCount { pages: 0, blocks: 1 });
+        assert_eq!(plan.confirmation(), Confirmation::Enter);
+
+        plan.items.extend((0..10).map(|_| item(2, PageContentType::ChildPage)));
+        assert_eq!(plan.confirmation(), Confirmation::PageName);
+
+        assert_eq!(loose_title("getting-started.md"), loose_title("Getting  Started"));
+        assert_eq!(loose_title("api_reference"), "api reference");
+        assert_ne!(loose_title("guides"), loose_title("Guide"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_clear_and_restore() {
+        let fake = FakeNotion::start().await;
+        let docs = fake.add_page(None, "Docs");
+        fake.add_paragraph(&docs, "Welcome");
+        let guides = fake.add_page(Some(&docs), "Guides");
+        fake.add_paragraph(&guides, "All the guides");
+        let setup = fake.add_page(Some(&guides), "Setup");
+        fake.add_paragraph(&setup, "Set it up");
+        let client = fake.client(&docs, json!({}));
+
+        let plan = ClearPlan::read(&client, &docs).await.unwrap();
+        assert_eq!(plan.page_count(), 2);
+        assert_eq!(plan.archive(&client).await.unwrap(), 5);
+        let archived = fake.requests().iter().filter(|r| r.starts_with("PATCH")).count();
+        assert_eq!(archived, 2);
+        assert!(fake.children(&docs).is_empty());
+        assert!(fake.is_trashed(&setup));
+
+        trash::restore(&client, None, &guides).await.unwrap();
+        assert_eq!(fake.page_titles(&docs), vec!["Guides", "Setup"]);
+        assert_eq!(fake.children(&setup)[0]["paragraph"]["rich_text"][0]["plain_text"], "Set it up");
+    }
+}
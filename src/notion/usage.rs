@@ -0,0 +1,91 @@
+use std::ops::Sub;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// How much of the Notion API a ship used, to tell how close it came to the rate limits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiUsage {
+    /// Requests sent, retries included.
+    pub calls: u64,
+    pub retries: u64,
+    /// Responses with status 429, Notion asking to slow down.
+    pub rate_limited: u64,
+    /// Time spent waiting before retries.
+    pub backoff_ms: u64,
+}
+
+impl Sub for ApiUsage {
+    type Output = ApiUsage;
+
+    fn sub(self, earlier: ApiUsage) -> ApiUsage {
+        ApiUsage {
+            calls: self.calls.saturating_sub(earlier.calls),
+            retries: self.retries.saturating_sub(earlier.retries),
+            rate_limited: self.rate_limited.saturating_sub(earlier.rate_limited),
+            backoff_ms: self.backoff_ms.saturating_sub(earlier.backoff_ms),
+        }
+    }
+}
+
+/// Counts the requests of a client, and of the clients cloned from it.
+#[derive(Debug, Default)]
+pub struct ApiCounters {
+    calls: AtomicU64,
+    retries: AtomicU64,
+    rate_limited: AtomicU64,
+    backoff_ms: AtomicU64,
+}
+
+impl ApiCounters {
+    pub fn call(&self) {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn rate_limited(&self) {
+        self.rate_limited.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn retry(&self, backoff: Duration) {
+        self.retries.fetch_add(1, Ordering::Relaxed);
+        self.backoff_ms.fetch_add(backoff.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    pub fn usage(&self) -> ApiUsage {
+        ApiUsage {
+            calls: self.calls.load(Ordering::Relaxed),
+            retries: self.retries.load(Ordering::Relaxed),
+            rate_limited: self.rate_limited.load(Ordering::Relaxed),
+            backoff_ms: self.backoff_ms.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::notion::usage::{ApiCounters, ApiUsage};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_api_usage() {
+        let counters = ApiCounters::default();
+        counters.call();
+        let before = counters.usage();
+        counters.call();
+        counters.rate_limited();
+        counters.retry(Duration::from_millis(1500));
+        counters.call();
+        assert_eq!(
+            counters.usage() - before,
+            ApiUsage {
+                calls: 2,
+                retries: 1,
+                rate_limited: 1,
+                backoff_ms: 1500,
+            }
+        );
+        assert_eq!(before - counters.usage(), ApiUsage::default());
+    }
+}
@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UserResponse {
+    pub id: String,
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub user_type: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub code: Option<String>,
+    pub message: Option<String>,
+}
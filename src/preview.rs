@@ -0,0 +1,355 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+use anyhow::Result;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, NON_ALPHANUMERIC};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::notion::block::{AppendBlockRequestChild, BlockContent, BlockType, NotionBlock};
+use crate::notion::client::NotionClient;
+use crate::notion::export::ExportPlan;
+use crate::notion::summary::ShipSummary;
+
+/// Stands in for the id of the parent page while previewing.
+pub const PREVIEW_PARENT_PAGE_ID: &str = "preview";
+
+const STYLE: &str = r#"
+body { font-family: ui-sans-serif, -apple-system, "Segoe UI", Helvetica, sans-serif; color: #37352f; max-width: 900px; margin: 40px auto; padding: 0 24px; line-height: 1.5; }
+a { color: inherit; }
+nav { font-size: 14px; color: #787774; margin-bottom: 24px; }
+.icon { font-size: 56px; }
+pre { background: #f7f6f3; padding: 16px; border-radius: 4px; overflow-x: auto; }
+pre .language { display: block; font-size: 12px; color: #787774; margin-bottom: 8px; }
+code { background: #f1f1ef; color: #eb5757; border-radius: 3px; padding: 0 4px; }
+pre code { background: none; color: inherit; padding: 0; }
+figcaption { font-size: 14px; color: #787774; }
+.callout { display: flex; gap: 12px; padding: 16px; border-radius: 4px; background: #f1f1ef; margin: 8px 0; }
+.gray_background { background: #f1f1ef; }
+.yellow_background { background: #fbf3db; }
+.columns { display: flex; gap: 24px; }
+.columns > div { flex: 1; }
+table { border-collapse: collapse; }
+td { border: 1px solid #e9e9e7; padding: 6px 8px; }
+tr:first-child td { font-weight: 600; background: #f7f6f3; }
+.subpage { display: block; padding: 4px 0; }
+.warning { color: #d9730d; }
+"#;
+
+/// Serves the converted pages of `src` as HTML on `addr`. Every request converts the files
+/// again, so edits show up on reload.
+pub async fn serve(client: NotionClient, src: String, addr: SocketAddr) -> Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let client = client.clone();
+        let src = src.clone();
+        tokio::spawn(async move {
+            let _ = handle(stream, &client, src).await;
+        });
+    }
+}
+
+async fn handle(mut stream: TcpStream, client: &NotionClient, src: String) -> Result<()> {
+    let mut buffer = vec![0; 8192];
+    let read = stream.read(&mut buffer).await?;
+    let request = String::from_utf8_lossy(&buffer[..read]);
+    let target = request.split_whitespace().nth(1).unwrap_or("/");
+
+    let (status, body) = if target == "/favicon.ico" {
+        ("404 Not Found", String::new())
+    } else {
+        match client.plan(src, PREVIEW_PARENT_PAGE_ID).await {
+            Ok((summary, plan)) => {
+                let path = target
+                    .strip_prefix("/page/")
+                    .map(|p| percent_decode_str(p).decode_utf8_lossy().into_owned())
+                    .unwrap_or_default();
+                match render_page(&summary, &plan, &path) {
+                    Some(html) => ("200 OK", html),
+                    None => ("404 Not Found", layout("Not found", &format!("<p>No page for {}</p>", escape(&path)))),
+                }
+            }
+            Err(e) => (
+                "500 Internal Server Error",
+                layout("Conversion failed", &format!("<pre>{}</pre>", escape(&format!("{:#}", e)))),
+            ),
+        }
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    Ok(())
+}
+
+fn page_link(path: &str) -> String {
+    if path.is_empty() {
+        "/".to_string()
+    } else {
+        format!("/page/{}", utf8_percent_encode(path, NON_ALPHANUMERIC))
+    }
+}
+
+/// The page of the file or directory at `path`, relative to the previewed root, or the
+/// parent page when `path` is empty.
+pub fn render_page(summary: &ShipSummary, plan: &ExportPlan, path: &str) -> Option<String> {
+    let page = plan.pages.iter().find(|p| p.path == path);
+    let (page_id, title, emoji) = match page.and_then(|p| p.create_page.as_ref()) {
+        Some(create) => (page.map(|p| p.page_id.as_str())?, create.title(), create.emoji()),
+        None if path.is_empty() => (PREVIEW_PARENT_PAGE_ID, "Preview".to_string(), None),
+        None => return None,
+    };
+    let links: HashMap<String, String> = plan
+        .pages
+        .iter()
+        .map(|p| (p.page_id.replace("-", ""), page_link(&p.path)))
+        .collect();
+
+    let mut html = String::new();
+    if !path.is_empty() {
+        html.push_str(&format!("<nav><a href=\"/\">Preview</a> / {}</nav>", escape(path)));
+    }
+    if let Some(emoji) = emoji {
+        html.push_str(&format!("<div class=\"icon\">{}</div>", escape(emoji)));
+    }
+    html.push_str(&format!("<h1>{}</h1>", escape(&title)));
+    if let Some(blocks) = page.and_then(|p| p.append_blocks.as_ref()) {
+        render_blocks(&mut html, &blocks.children, &links);
+    }
+    for child in plan.children(page_id) {
+        let create = child.create_page.as_ref()?;
+        html.push_str(&format!(
+            "<a class=\"subpage\" href=\"{}\">{} {}</a>",
+            page_link(&child.path),
+            escape(create.emoji().unwrap_or("📄")),
+            escape(&create.title())
+        ));
+    }
+    if path.is_empty() {
+        for w in summary.warnings.iter() {
+            html.push_str(&format!("<p class=\"warning\">⚠️ {}</p>", escape(&w.to_string())));
+        }
+    }
+    Some(layout(&title, &html))
+}
+
+fn layout(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>{}</title><style>{}</style></head><body>{}</body></html>",
+        escape(title),
+        STYLE,
+        body
+    )
+}
+
+/// Renders blocks as HTML, grouping list items into lists. Links to other converted pages
+/// point at their preview.
+pub fn render_blocks(html: &mut String, blocks: &[AppendBlockRequestChild], links: &HashMap<String, String>) {
+    let mut open_list: Option<&str> = None;
+    for b in blocks {
+        let list = match b.block_type() {
+            BlockType::BulletedListItem => Some("ul"),
+            BlockType::NumberedListItem => Some("ol"),
+            _ => None,
+        };
+        if open_list != list {
+            if let Some(tag) = open_list {
+                html.push_str(&format!("</{}>", tag));
+            }
+            if let Some(tag) = list {
+                html.push_str(&format!("<{}>", tag));
+            }
+            open_list = list;
+        }
+        render_block(html, b, links);
+    }
+    if let Some(tag) = open_list {
+        html.push_str(&format!("</{}>", tag));
+    }
+}
+
+fn render_block(html: &mut String, b: &AppendBlockRequestChild, links: &HashMap<String, String>) {
+    let text = rich_text(&b.get_rich_text_blocks().unwrap_or_default(), links);
+    match &b.content {
+        BlockContent::Heading1 { .. } => html.push_str(&format!("<h2>{}</h2>", text)),
+        BlockContent::Heading2 { .. } => html.push_str(&format!("<h3>{}</h3>", text)),
+        BlockContent::Heading3 { .. } => html.push_str(&format!("<h4>{}</h4>", text)),
+        BlockContent::Paragraph { .. } => html.push_str(&format!("<p>{}</p>", text)),
+        BlockContent::BulletedListItem { .. } | BlockContent::NumberedListItem { .. } => {
+            html.push_str(&format!("<li>{}</li>", text))
+        }
+        BlockContent::Code { code } => {
+            let language = code.language.as_deref().unwrap_or("plain text");
+            html.push_str(&format!(
+                "<figure><pre><span class=\"language\">{}</span><code>{}</code></pre>",
+                escape(language),
+                text
+            ));
+            if let Some(caption) = code.caption.as_ref() {
+                html.push_str(&format!("<figcaption>{}</figcaption>", rich_text(caption, links)));
+            }
+            html.push_str("</figure>");
+        }
+        BlockContent::Image { image } => {
+            if let Some(url) = image.url() {
+                html.push_str(&format!("<p><img src=\"{}\" style=\"max-width: 100%\"></p>", escape(url)));
+            }
+        }
+        BlockContent::Table { table } => {
+            html.push_str("<table>");
+            for row in table.children.iter() {
+                html.push_str("<tr>");
+                for cell in row.table_row().iter().flat_map(|r| r.cells.iter()) {
+                    html.push_str(&format!("<td>{}</td>", rich_text(cell, links)));
+                }
+                html.push_str("</tr>");
+            }
+            html.push_str("</table>");
+        }
+        BlockContent::TableRow { .. } => {}
+        BlockContent::ChildDatabase { child_database: d } => {
+            html.push_str(&format!("<table><caption>{}</caption><tr>", escape(&d.title)));
+            for column in d.columns.iter() {
+                html.push_str(&format!("<th>{}</th>", escape(&column.name)));
+            }
+            html.push_str("</tr>");
+            for row in d.rows.iter() {
+                html.push_str("<tr>");
+                for cell in row.iter() {
+                    html.push_str(&format!("<td>{}</td>", escape(cell)));
+                }
+                html.push_str("</tr>");
+            }
+            html.push_str("</table>");
+        }
+        BlockContent::File { file } => {
+            let url = escape(&file.external.url);
+            html.push_str(&format!("<p>📎 <a href=\"{}\">{}</a></p>", url, url));
+        }
+        BlockContent::Divider { .. } => html.push_str("<hr>"),
+        BlockContent::Callout { callout } => {
+            html.push_str(&format!(
+                "<div class=\"callout {}\"><div>{}</div><div>{}",
+                escape(callout.color.as_deref().unwrap_or("")),
+                escape(callout.icon.as_ref().map_or("💡", |i| i.emoji())),
+                text
+            ));
+            if let Some(children) = callout.children.as_ref() {
+                render_blocks(html, children, links);
+            }
+            html.push_str("</div></div>");
+        }
+        BlockContent::Toggle { toggle } => {
+            html.push_str(&format!("<details><summary>{}</summary>", text));
+            if let Some(children) = toggle.children.as_ref() {
+                render_blocks(html, children, links);
+            }
+            html.push_str("</details>");
+        }
+        BlockContent::ColumnList { column_list } => {
+            html.push_str("<div class=\"columns\">");
+            for column in column_list.children.iter() {
+                html.push_str("<div>");
+                if let Some(c) = column.column() {
+                    render_blocks(html, &c.children, links);
+                }
+                html.push_str("</div>");
+            }
+            html.push_str("</div>");
+        }
+        BlockContent::Column { column } => render_blocks(html, &column.children, links),
+    }
+}
+
+fn rich_text(blocks: &[NotionBlock], links: &HashMap<String, String>) -> String {
+    let mut html = String::new();
+    for block in blocks {
+        let text = match &block.text {
+            Some(t) => t,
+            None => continue,
+        };
+        let mut span = escape(&text.content);
+        if let Some(a) = &block.annotations {
+            if a.code {
+                span = format!("<code>{}</code>", span);
+            }
+            if a.bold {
+                span = format!("<strong>{}</strong>", span);
+            }
+            if a.italic {
+                span = format!("<em>{}</em>", span);
+            }
+            if a.strikethrough {
+                span = format!("<s>{}</s>", span);
+            }
+            if a.underline {
+                span = format!("<u>{}</u>", span);
+            }
+        }
+        if let Some(link) = &text.link {
+            let href = links
+                .iter()
+                .find(|(id, _)| !id.is_empty() && link.url.ends_with(id.as_str()))
+                .map_or(link.url.clone(), |(_, href)| href.clone());
+            span = format!("<a href=\"{}\">{}</a>", escape(&href), span);
+        }
+        html.push_str(&span);
+    }
+    html
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::notion::block::{AppendBlockRequest, AppendBlockRequestChild, NotionBlock, TextAnnotations};
+    use crate::notion::export::ExportPlan;
+    use crate::notion::summary::ShipSummary;
+    use crate::preview::{render_blocks, render_page, PREVIEW_PARENT_PAGE_ID};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_render_page() {
+        let mut plan = ExportPlan::default();
+        plan.create_page(
+            "guide.md".to_string(),
+            "guide-id".to_string(),
+            PREVIEW_PARENT_PAGE_ID.to_string(),
+            "Guide".to_string(),
+            Some("📘".to_string()),
+        );
+        plan.append_blocks(
+            "guide-id",
+            AppendBlockRequest::new_children(vec![
+                AppendBlockRequestChild::new_bulleted_list_item_block("one".to_string()),
+                AppendBlockRequestChild::new_bulleted_list_item_block("two".to_string()),
+                AppendBlockRequestChild::new_rich_text(
+                    crate::notion::block::BlockType::Paragraph,
+                    vec![NotionBlock::new_text_block("<b>".to_string()).with_annotations(TextAnnotations::new().bold())],
+                ),
+            ]),
+        );
+        let summary = ShipSummary::default();
+
+        let root = render_page(&summary, &plan, "").unwrap();
+        assert!(root.contains("href=\"/page/guide%2Emd\""));
+        let guide = render_page(&summary, &plan, "guide.md").unwrap();
+        assert!(guide.contains("<ul><li>one</li><li>two</li></ul>"));
+        assert!(guide.contains("<strong>&lt;b&gt;</strong>"));
+        assert!(render_page(&summary, &plan, "missing.md").is_none());
+
+        let mut html = String::new();
+        render_blocks(&mut html, &[AppendBlockRequestChild::new_divider_block()], &HashMap::new());
+        assert_eq!(html, "<hr>");
+    }
+}
@@ -0,0 +1,73 @@
+use std::sync::{OnceLock, RwLock};
+
+use anyhow::anyhow;
+use regex::Regex;
+
+pub const REDACTED: &str = "[REDACTED]";
+
+static SECRETS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+static TOKEN_PATTERN: OnceLock<Regex> = OnceLock::new();
+
+/// Registers a secret so that [`redact`] scrubs it from every string, wherever it appears.
+pub fn register_secret(secret: &str) {
+    let secret = secret.trim();
+    if secret.is_empty() {
+        return;
+    }
+    let mut secrets = SECRETS.write().unwrap_or_else(|e| e.into_inner());
+    if !secrets.iter().any(|s| s == secret) {
+        secrets.push(secret.to_string());
+    }
+}
+
+/// Replaces registered secrets, bearer tokens and anything shaped like a Notion integration
+/// secret with [`REDACTED`].
+pub fn redact(s: &str) -> String {
+    let mut result = s.to_string();
+    for secret in SECRETS.read().unwrap_or_else(|e| e.into_inner()).iter() {
+        result = result.replace(secret.as_str(), REDACTED);
+    }
+    let pattern = TOKEN_PATTERN.get_or_init(|| {
+        Regex::new(r"(?i:bearer\s+)[^\s,;\x22']+|\b(?:secret|ntn)_[A-Za-z0-9]{16,}").unwrap()
+    });
+    pattern.replace_all(&result, REDACTED).into_owned()
+}
+
+/// Rebuilds an error, with its whole context chain, from its redacted rendering.
+pub fn redact_error(e: impl Into<anyhow::Error>) -> anyhow::Error {
+    let e = e.into();
+    anyhow!(redact(&format!("{:#}", e)))
+}
+
+pub trait RedactResult<T> {
+    fn redacted(self) -> anyhow::Result<T>;
+}
+
+impl<T, E: Into<anyhow::Error>> RedactResult<T> for Result<T, E> {
+    fn redacted(self) -> anyhow::Result<T> {
+        self.map_err(redact_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::redact::{redact, register_secret, REDACTED};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_redact() {
+        register_secret("my-very-own-secret");
+        assert_eq!(
+            redact("failed with key my-very-own-secret!"),
+            format!("failed with key {}!", REDACTED)
+        );
+        assert_eq!(
+            redact("Authorization: Bearer abc.def"),
+            format!("Authorization: {}", REDACTED)
+        );
+        assert_eq!(
+            redact("token secret_0123456789abcdefghij leaked"),
+            format!("token {} leaked", REDACTED)
+        );
+        assert_eq!(redact("nothing to see here"), "nothing to see here");
+    }
+}
@@ -0,0 +1,517 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use markdown::mdast::Node;
+use markdown::ParseOptions;
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+
+use crate::markdown::parse::line_arguments;
+use crate::notion::block::{AppendBlockRequestChild, BlockContent, NotionBlock};
+use crate::notion::client::{get_intro_path, NotionClient, ShipOptions};
+use crate::notion::page::PageContentType;
+use crate::notion::summary::page_url;
+
+/// A block-level element, reduced to what both markdown and Notion can express.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Element {
+    pub kind: &'static str,
+    pub text: String,
+}
+
+impl Element {
+    fn new(kind: &'static str, text: String) -> Self {
+        Element { kind, text }
+    }
+
+    pub fn to_markdown(&self) -> String {
+        match self.kind {
+            "heading" => format!("# {}", self.text),
+            "bulleted_list_item" => format!("- {}", self.text),
+            "numbered_list_item" => format!("1. {}", self.text),
+            "code" => format!("```\n{}\n```", self.text),
+            "quote" => format!("> {}", self.text),
+            "image" => format!("![]({})", self.text),
+            "divider" => "---".to_string(),
+            "equation" => format!("$$\n{}\n$$", self.text),
+            _ => self.text.clone(),
+        }
+    }
+}
+
+/// The elements of a page, from its markdown or from its Notion blocks.
+#[derive(Clone, Debug, Default)]
+pub struct Elements {
+    pub blocks: Vec<Element>,
+    /// How many times each inline style (bold, italic, inline code, strikethrough, link) is used.
+    pub inline: BTreeMap<&'static str, usize>,
+}
+
+impl Elements {
+    /// The elements as normalized markdown, one block per paragraph.
+    pub fn to_markdown(&self) -> String {
+        let blocks: Vec<String> = self.blocks.iter().map(|e| e.to_markdown()).collect();
+        format!("{}\n", blocks.join("\n\n"))
+    }
+}
+
+/// How many elements of one kind made it through a round trip.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ElementFidelity {
+    pub kind: String,
+    pub expected: usize,
+    pub matched: usize,
+}
+
+impl ElementFidelity {
+    pub fn ratio(&self) -> f64 {
+        if self.expected == 0 {
+            1.0
+        } else {
+            self.matched as f64 / self.expected as f64
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FileRoundtrip {
+    pub path: String,
+    pub fidelity: Vec<ElementFidelity>,
+    /// A unified diff from the normalized markdown to what came back from Notion, empty when
+    /// they are the same.
+    pub diff: String,
+}
+
+impl FileRoundtrip {
+    pub fn is_lossless(&self) -> bool {
+        self.diff.is_empty() && self.fidelity.iter().all(|f| f.matched == f.expected)
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RoundtripReport {
+    pub files: Vec<FileRoundtrip>,
+    /// The scratch page the files were shipped to, when it was kept.
+    pub scratch_url: Option<String>,
+}
+
+impl RoundtripReport {
+    /// Fidelity per element kind across all files.
+    pub fn totals(&self) -> Vec<ElementFidelity> {
+        let mut totals: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+        for f in self.files.iter().flat_map(|f| f.fidelity.iter()) {
+            let t = totals.entry(f.kind.clone()).or_default();
+            t.0 += f.expected;
+            t.1 += f.matched;
+        }
+        totals
+            .into_iter()
+            .map(|(kind, (expected, matched))| ElementFidelity {
+                kind,
+                expected,
+                matched,
+            })
+            .collect()
+    }
+}
+
+/// Ships `src` to a scratch page under the parent page, reads every page back and compares it
+/// with its markdown. The scratch page is archived afterwards unless `keep` is set. Files of
+/// merged directories are not compared.
+pub async fn roundtrip(nc: &NotionClient, src: String, keep: bool) -> Result<RoundtripReport> {
+    let mut settings = nc.settings().clone();
+    settings.footer.enabled = false;
+    let nc = NotionClient::from_settings(settings)?;
+
+    let parent_id = nc.parent_page_id().await?;
+    let scratch_id = nc
+        .create_page_by_parent_id(
+            parent_id,
+            format!("Notation roundtrip {}", Utc::now().format("%Y-%m-%d %H:%M:%S UTC")),
+            Some("🔁".to_string()),
+        )
+        .await?;
+    let result = compare_shipped(&nc, &src, &scratch_id).await;
+    if keep {
+        return result.map(|mut r| {
+            r.scratch_url = Some(page_url(&scratch_id));
+            r
+        });
+    }
+    nc.delete(scratch_id, &PageContentType::ChildPage).await?;
+    result
+}
+
+async fn compare_shipped(nc: &NotionClient, src: &str, scratch_id: &str) -> Result<RoundtripReport> {
+    let options = ShipOptions {
+        parent_page_id: Some(scratch_id.to_string()),
+        ephemeral: true,
+        ..Default::default()
+    };
+    let summary = nc.ship(src.to_string(), &options).await?;
+    let root = if Path::new(src).is_file() {
+        Path::new(src).parent().unwrap_or(Path::new("")).to_path_buf()
+    } else {
+        PathBuf::from(src)
+    };
+
+    let mut file_pages: Vec<(PathBuf, String)> = Vec::new();
+    let root_intro = get_intro_path(&root, Path::new(""));
+    if root_intro.is_file() {
+        file_pages.push((root_intro, scratch_id.to_string()));
+    }
+    for p in summary.pages.iter() {
+        let path = root.join(&p.path);
+        if path.extension().and_then(|e| e.to_str()) == Some("md") {
+            file_pages.push((path, p.page_id.clone()));
+        } else if get_intro_path(&root, Path::new(&p.path)).is_file() {
+            file_pages.push((get_intro_path(&root, Path::new(&p.path)), p.page_id.clone()));
+        }
+    }
+
+    let mut report = RoundtripReport::default();
+    for (path, page_id) in file_pages {
+        let contents = std::fs::read_to_string(&path)?;
+        let expected = markdown_elements(&contents)?;
+        let actual = block_elements(&nc.get_page_blocks(&page_id).await?);
+        report.files.push(compare(&path.display().to_string(), &expected, &actual));
+    }
+    Ok(report)
+}
+
+pub fn compare(path: &str, expected: &Elements, actual: &Elements) -> FileRoundtrip {
+    let mut fidelity: BTreeMap<&'static str, (usize, usize)> = BTreeMap::new();
+    let mut remaining: HashMap<&Element, usize> = HashMap::new();
+    for e in actual.blocks.iter() {
+        *remaining.entry(e).or_default() += 1;
+    }
+    for e in expected.blocks.iter() {
+        let f = fidelity.entry(e.kind).or_default();
+        f.0 += 1;
+        if let Some(count) = remaining.get_mut(e).filter(|c| **c > 0) {
+            *count -= 1;
+            f.1 += 1;
+        }
+    }
+    for (kind, count) in expected.inline.iter() {
+        let f = fidelity.entry(kind).or_default();
+        f.0 += count;
+        f.1 += (*count).min(actual.inline.get(kind).copied().unwrap_or(0));
+    }
+
+    let expected_markdown = expected.to_markdown();
+    let actual_markdown = actual.to_markdown();
+    let diff = if expected_markdown == actual_markdown {
+        String::new()
+    } else {
+        TextDiff::from_lines(&expected_markdown, &actual_markdown)
+            .unified_diff()
+            .header("markdown", "notion")
+            .to_string()
+    };
+    FileRoundtrip {
+        path: path.to_string(),
+        fidelity: fidelity
+            .into_iter()
+            .map(|(kind, (expected, matched))| ElementFidelity {
+                kind: kind.to_string(),
+                expected,
+                matched,
+            })
+            .collect(),
+        diff,
+    }
+}
+
+/// The elements of a markdown document, leaving out its arguments line and HTML comments.
+pub fn markdown_elements(contents: &str) -> Result<Elements> {
+    let root = markdown::to_mdast(contents, &ParseOptions::gfm()).map_err(|e| anyhow!(e))?;
+    let mut elements = Elements::default();
+    if let Node::Root(r) = &root {
+        for (i, c) in r.children.iter().enumerate() {
+            if i == 0 && is_arguments_paragraph(c) {
+                continue;
+            }
+            collect_markdown(c, &mut elements);
+        }
+    }
+    Ok(elements)
+}
+
+/// The elements of a single markdown node.
+pub fn node_elements(node: &Node) -> Elements {
+    let mut elements = Elements::default();
+    collect_markdown(node, &mut elements);
+    elements
+}
+
+/// Whether `node` is a paragraph of `--` arguments, which only count as the first node.
+pub fn is_arguments_paragraph(node: &Node) -> bool {
+    line_arguments(node).is_some()
+}
+
+fn collect_markdown(node: &Node, elements: &mut Elements) {
+    match node {
+        Node::Heading(h) => {
+            count_inline(&h.children, &mut elements.inline);
+            elements.blocks.push(Element::new("heading", inline_text(&h.children)));
+        }
+        Node::Paragraph(p) => {
+            count_inline(&p.children, &mut elements.inline);
+            let text = inline_text(&p.children);
+            if !text.is_empty() {
+                elements.blocks.push(Element::new("paragraph", text));
+            }
+            for c in p.children.iter() {
+                if let Node::Image(i) = c {
+                    elements.blocks.push(Element::new("image", i.url.clone()));
+                }
+            }
+        }
+        Node::List(l) => {
+            let kind = if l.ordered {
+                "numbered_list_item"
+            } else {
+                "bulleted_list_item"
+            };
+            for item in l.children.iter().filter_map(|c| match c {
+                Node::ListItem(li) => Some(li),
+                _ => None,
+            }) {
+                for c in item.children.iter() {
+                    match c {
+                        Node::Paragraph(p) => {
+                            count_inline(&p.children, &mut elements.inline);
+                            elements.blocks.push(Element::new(kind, inline_text(&p.children)));
+                        }
+                        other => collect_markdown(other, elements),
+                    }
+                }
+            }
+        }
+        Node::Code(c) => elements
+            .blocks
+            .push(Element::new("code", c.value.trim_end().to_string())),
+        Node::Table(t) => {
+            let mut rows = Vec::new();
+            for row in t.children.iter() {
+                let cells: Vec<String> = row
+                    .children()
+                    .map(|cells| {
+                        cells
+                            .iter()
+                            .map(|c| {
+                                let children = c.children().map(|c| c.as_slice()).unwrap_or(&[]);
+                                count_inline(children, &mut elements.inline);
+                                inline_text(children)
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                rows.push(format!("| {} |", cells.join(" | ")));
+            }
+            elements.blocks.push(Element::new("table", rows.join("\n")));
+        }
+        Node::BlockQuote(b) => {
+            let mut quoted = Elements::default();
+            for c in b.children.iter() {
+                collect_markdown(c, &mut quoted);
+            }
+            let text: Vec<String> = quoted.blocks.into_iter().map(|e| e.text).collect();
+            for (kind, count) in quoted.inline {
+                *elements.inline.entry(kind).or_default() += count;
+            }
+            elements.blocks.push(Element::new("quote", text.join(" ")));
+        }
+        Node::ThematicBreak(_) => elements.blocks.push(Element::new("divider", String::new())),
+        Node::Math(m) => elements
+            .blocks
+            .push(Element::new("equation", m.value.trim().to_string())),
+        Node::FootnoteDefinition(f) => {
+            let mut footnote = Elements::default();
+            for c in f.children.iter() {
+                collect_markdown(c, &mut footnote);
+            }
+            let text: Vec<String> = footnote.blocks.into_iter().map(|e| e.text).collect();
+            elements.blocks.push(Element::new("footnote", text.join(" ")));
+        }
+        Node::Html(_) | Node::Yaml(_) | Node::Toml(_) | Node::Definition(_) => {}
+        other => {
+            if let Some(children) = other.children() {
+                for c in children.iter() {
+                    collect_markdown(c, elements);
+                }
+            }
+        }
+    }
+}
+
+fn inline_text(nodes: &[Node]) -> String {
+    fn push(nodes: &[Node], out: &mut String) {
+        for n in nodes {
+            match n {
+                Node::Text(t) => out.push_str(&t.value),
+                Node::InlineCode(c) => out.push_str(&c.value),
+                Node::InlineMath(m) => out.push_str(&m.value),
+                Node::Break(_) => out.push(' '),
+                Node::Image(_) | Node::Html(_) | Node::FootnoteReference(_) => {}
+                other => {
+                    if let Some(children) = other.children() {
+                        push(children, out);
+                    }
+                }
+            }
+        }
+    }
+    let mut out = String::new();
+    push(nodes, &mut out);
+    normalize(&out)
+}
+
+fn count_inline(nodes: &[Node], inline: &mut BTreeMap<&'static str, usize>) {
+    for n in nodes {
+        let kind = match n {
+            Node::Strong(_) => Some("bold"),
+            Node::Emphasis(_) => Some("italic"),
+            Node::InlineCode(_) => Some("inline_code"),
+            Node::Delete(_) => Some("strikethrough"),
+            Node::Link(_) => Some("link"),
+            _ => None,
+        };
+        if let Some(kind) = kind {
+            *inline.entry(kind).or_default() += 1;
+        }
+        if let Some(children) = n.children() {
+            count_inline(children, inline);
+        }
+    }
+}
+
+/// The elements of blocks read back from Notion. Empty paragraphs are left out.
+pub fn block_elements(blocks: &[AppendBlockRequestChild]) -> Elements {
+    let mut elements = Elements::default();
+    for b in blocks {
+        let rich_text = b.get_rich_text_blocks().unwrap_or_default();
+        count_annotations(&rich_text, &mut elements.inline);
+        let text = rich_text_plain(&rich_text);
+        let element = match &b.content {
+            BlockContent::Heading1 { .. } | BlockContent::Heading2 { .. } | BlockContent::Heading3 { .. } => {
+                Element::new("heading", text)
+            }
+            BlockContent::Paragraph { .. } if text.is_empty() => continue,
+            BlockContent::Paragraph { .. } => Element::new("paragraph", text),
+            BlockContent::BulletedListItem { .. } => Element::new("bulleted_list_item", text),
+            BlockContent::NumberedListItem { .. } => Element::new("numbered_list_item", text),
+            BlockContent::Code { .. } => {
+                let code: String = rich_text
+                    .iter()
+                    .filter_map(|t| t.text.as_ref().map(|t| t.content.as_str()))
+                    .collect();
+                Element::new("code", code.trim_end().to_string())
+            }
+            BlockContent::Image { image } => match image.url() {
+                Some(url) => Element::new("image", url.to_string()),
+                None => continue,
+            },
+            BlockContent::File { file } => Element::new("file", file.external.url.clone()),
+            BlockContent::Table { table } => {
+                let rows: Vec<String> = table
+                    .children
+                    .iter()
+                    .filter_map(|r| r.table_row())
+                    .map(|r| {
+                        let cells: Vec<String> = r
+                            .cells
+                            .iter()
+                            .map(|c| {
+                                count_annotations(c, &mut elements.inline);
+                                rich_text_plain(c)
+                            })
+                            .collect();
+                        format!("| {} |", cells.join(" | "))
+                    })
+                    .collect();
+                Element::new("table", rows.join("\n"))
+            }
+            BlockContent::Divider { .. } => Element::new("divider", String::new()),
+            BlockContent::TableRow { .. }
+            | BlockContent::Callout { .. }
+            | BlockContent::Toggle { .. }
+            | BlockContent::ColumnList { .. }
+            | BlockContent::Column { .. }
+            | BlockContent::ChildDatabase { .. } => continue,
+        };
+        elements.blocks.push(element);
+    }
+    elements
+}
+
+fn rich_text_plain(rich_text: &[NotionBlock]) -> String {
+    let text: String = rich_text
+        .iter()
+        .filter_map(|t| t.text.as_ref().map(|t| t.content.as_str()))
+        .collect();
+    normalize(&text)
+}
+
+fn count_annotations(rich_text: &[NotionBlock], inline: &mut BTreeMap<&'static str, usize>) {
+    for t in rich_text {
+        if let Some(a) = &t.annotations {
+            for (kind, is_set) in [
+                ("bold", a.bold),
+                ("italic", a.italic),
+                ("inline_code", a.code),
+                ("strikethrough", a.strikethrough),
+            ] {
+                if is_set {
+                    *inline.entry(kind).or_default() += 1;
+                }
+            }
+        }
+        if t.text.as_ref().is_some_and(|t| t.link.is_some()) {
+            *inline.entry("link").or_default() += 1;
+        }
+    }
+}
+
+fn normalize(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::markdown::parse::{parse_file, ConversionOptions};
+    use crate::roundtrip::{block_elements, compare, markdown_elements};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_compare_conversion() {
+        let contents = "--title Test\n\n# Title\n\nSome **bold** and *italic* text.\n\n> a quote\n\n- one\n- two\n";
+        let dir = std::env::temp_dir().join(format!("notation-roundtrip-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("page.md");
+        std::fs::write(&path, contents).unwrap();
+        let conversion = parse_file(&path)
+            .await
+            .unwrap()
+            .to_notion("page", &HashMap::new(), &ConversionOptions::default())
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let expected = markdown_elements(contents).unwrap();
+        let actual = block_elements(&conversion.request.children);
+        let result = compare("page.md", &expected, &actual);
+        let fidelity = |kind: &str| {
+            let f = result.fidelity.iter().find(|f| f.kind == kind).unwrap();
+            (f.expected, f.matched)
+        };
+        assert_eq!(fidelity("heading"), (1, 1));
+        assert_eq!(fidelity("bulleted_list_item"), (2, 2));
+        assert_eq!(fidelity("bold"), (1, 1));
+        assert_eq!(fidelity("quote"), (1, 0));
+        assert!(!result.is_lossless());
+    }
+}
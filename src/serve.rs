@@ -0,0 +1,676 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use percent_encoding::percent_decode_str;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+
+use crate::commands::{all_files, clear, clear_plan, status, write_report, Workspace};
+use crate::github::{verify_signature, PushEvent};
+use crate::notify::notify;
+use crate::notion::client::ShipOptions;
+use crate::notion::report::ShipReport;
+use crate::notion::tree::Confirmation;
+use crate::redact::{redact, register_secret};
+use crate::settings::directory::SourceTree;
+use crate::sync::daemon::{last_shipped_commit, log, SyncLock};
+use crate::sync::git::{changed_since, path_in_repository, pull};
+use crate::sync::state::SyncState;
+use crate::sync::status::{ConflictError, ConflictPolicy, LocalChange, StatusReport};
+
+const MAX_HEAD: usize = 64 * 1024;
+const MAX_BODY: usize = 25 * 1024 * 1024;
+/// How long a client has to send its whole request.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+/// The most pages `POST /clear` archives, unless `?max_pages=` says otherwise.
+const DEFAULT_MAX_CLEARED_PAGES: usize = 100;
+
+/// An HTTP request, as much of it as the API looks at.
+#[derive(Clone, Debug, Default)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: HashMap<String, String>,
+    /// By lowercase name.
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// Parses the request line and headers, up to the blank line ending them.
+    pub fn parse_head(head: &str) -> Option<Self> {
+        let mut lines = head.lines();
+        let mut request_line = lines.next()?.split_whitespace();
+        let method = request_line.next()?.to_uppercase();
+        let target = request_line.next()?;
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let decode = |s: &str| percent_decode_str(&s.replace('+', " ")).decode_utf8_lossy().into_owned();
+        let query = query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (decode(name), decode(value))
+            })
+            .collect();
+        let headers = lines
+            .take_while(|l| !l.is_empty())
+            .filter_map(|l| l.split_once(':'))
+            .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+            .collect();
+        Some(Request {
+            method,
+            path: decode(path),
+            query,
+            headers,
+            body: Vec::new(),
+        })
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(|h| h.as_str())
+    }
+
+    /// Whether the request carries `Authorization: Bearer <token>`, compared in constant time.
+    /// Anything goes when no token is set.
+    pub fn is_authorized(&self, token: Option<&str>) -> bool {
+        match token.filter(|t| !t.is_empty()) {
+            Some(token) => self
+                .header("authorization")
+                .and_then(|h| h.strip_prefix("Bearer "))
+                .map(|t| t.trim())
+                .is_some_and(|t| t.len() == token.len() && openssl::memcmp::eq(t.as_bytes(), token.as_bytes())),
+            None => true,
+        }
+    }
+}
+
+/// What the next incremental ship would do, by file relative to the shipped root.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct PendingChanges {
+    pub new: Vec<String>,
+    pub modified: Vec<String>,
+    pub deleted: Vec<String>,
+    /// Pages edited in Notion that shipping would overwrite.
+    pub conflicts: Vec<String>,
+}
+
+impl PendingChanges {
+    pub fn from_status(report: &StatusReport) -> Self {
+        let with_local = |change: LocalChange| {
+            report
+                .pages
+                .iter()
+                .filter(|p| p.local == Some(change))
+                .map(|p| p.path.clone())
+                .collect()
+        };
+        PendingChanges {
+            new: report.new_files.clone(),
+            modified: with_local(LocalChange::Modified),
+            deleted: with_local(LocalChange::Deleted),
+            conflicts: report
+                .pages
+                .iter()
+                .filter(|p| p.is_conflict())
+                .map(|p| p.path.clone())
+                .collect(),
+        }
+    }
+}
+
+struct Server {
+    workspace: Workspace,
+    src: String,
+    root: PathBuf,
+    token: Option<String>,
+    lock_path: PathBuf,
+    state_path: PathBuf,
+}
+
+/// Serves the HTTP API on `addr` until the process is stopped:
+///
+/// - `GET /status`: the pages of the last ship, as `notation status` reports them
+/// - `GET /diff`: the files the next ship would create, update or archive, and the conflicts
+/// - `POST /ship`: ships what changed since the last ship, or since `?since=<ref>`, streaming
+///   progress as JSON lines. `?on_conflict=fail|overwrite|skip` is `fail` by default.
+/// - `POST /clear`: archives everything on the parent page, given `?confirm=<parent page name>`
+///   and no more than `?max_pages=<n>` pages, 100 by default
+/// - `POST /github`: ships the files a GitHub push changed, once the checkout is pulled. It
+///   takes the webhook's signature instead of the bearer token.
+pub async fn serve(workspace: Workspace, src: String, addr: SocketAddr) -> Result<()> {
+    let settings = workspace.client.settings();
+    let root = SourceTree::load(&src, settings.content.clone())?.root;
+    let state_path = SyncState::path(&root, settings.sync.state_file.as_deref());
+    let token = settings.serve.token.clone().filter(|t| !t.trim().is_empty());
+    for secret in token.iter().chain(settings.serve.github_secret.iter()) {
+        register_secret(secret);
+    }
+    let server = Arc::new(Server {
+        workspace,
+        src,
+        root,
+        token,
+        lock_path: state_path.with_extension("lock"),
+        state_path,
+    });
+    let listener = TcpListener::bind(addr).await?;
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let server = server.clone();
+        tokio::spawn(async move {
+            let _ = handle(stream, &server).await;
+        });
+    }
+}
+
+async fn handle(mut stream: TcpStream, server: &Arc<Server>) -> Result<()> {
+    let request = match tokio::time::timeout(READ_TIMEOUT, read_request(&mut stream)).await {
+        Ok(Ok(request)) => request,
+        Ok(Err(e)) => return respond(&mut stream, "400 Bad Request", &json!({ "error": e.to_string() })).await,
+        Err(_) => {
+            let error = format!("the request took more than {} seconds to arrive", READ_TIMEOUT.as_secs());
+            return respond(&mut stream, "408 Request Timeout", &json!({ "error": error })).await;
+        }
+    };
+    if (request.method.as_str(), request.path.as_str()) == ("POST", "/github") {
+        return github(&mut stream, server, &request).await;
+    }
+    if !request.is_authorized(server.token.as_deref()) {
+        return respond(&mut stream, "401 Unauthorized", &json!({ "error": "missing or wrong bearer token" })).await;
+    }
+    let client = &server.workspace.client;
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/status") => match status(client, &server.src).await {
+            Ok(report) => respond(&mut stream, "200 OK", &report).await,
+            Err(e) => respond_error(&mut stream, e).await,
+        },
+        ("GET", "/diff") => match status(client, &server.src).await {
+            Ok(report) => respond(&mut stream, "200 OK", &PendingChanges::from_status(&report)).await,
+            Err(e) => respond_error(&mut stream, e).await,
+        },
+        ("POST", "/ship") => ship(&mut stream, server, &request).await,
+        ("POST", "/clear") => clear_page(&mut stream, server, &request).await,
+        _ => respond(&mut stream, "404 Not Found", &json!({ "error": "no such endpoint" })).await,
+    }
+}
+
+/// Clears the parent page with the same guards as `notation clear`: no more pages than
+/// `?max_pages=`, and the name of the parent page as `?confirm=` in place of typing it.
+async fn clear_page(stream: &mut TcpStream, server: &Arc<Server>, request: &Request) -> Result<()> {
+    let max_pages = match request.query.get("max_pages").map(|m| m.parse::<usize>()) {
+        None => DEFAULT_MAX_CLEARED_PAGES,
+        Some(Ok(max_pages)) => max_pages,
+        Some(Err(_)) => {
+            return respond(stream, "400 Bad Request", &json!({ "error": "max_pages should be a number" })).await;
+        }
+    };
+    let _lock = match SyncLock::acquire(&server.lock_path) {
+        Ok(Some(lock)) => lock,
+        Ok(None) => return respond_busy(stream, server).await,
+        Err(e) => return respond_error(stream, e).await,
+    };
+    let plan = match clear_plan(&server.workspace).await {
+        Ok(plan) => plan,
+        Err(e) => return respond_error(stream, e).await,
+    };
+    let (pages, blocks) = (plan.page_count(), plan.items.len() - plan.page_count());
+    if pages > max_pages {
+        let error = format!("clearing would archive {} pages, more than max_pages {}", pages, max_pages);
+        return respond(stream, "409 Conflict", &json!({ "error": error, "pages": pages, "blocks": blocks })).await;
+    }
+    let name = server.workspace.client.parent_page_name();
+    if plan.confirmation() != Confirmation::None && request.query.get("confirm") != Some(&name) {
+        let error = format!("pass ?confirm={} to archive {} pages and {} blocks", name, pages, blocks);
+        return respond(stream, "409 Conflict", &json!({ "error": error, "pages": pages, "blocks": blocks })).await;
+    }
+    match clear(&server.workspace, &plan).await {
+        Ok(report) => respond(stream, "200 OK", &json!({ "removed": report.removed })).await,
+        Err(e) => respond_error(stream, e).await,
+    }
+}
+
+async fn ship(stream: &mut TcpStream, server: &Arc<Server>, request: &Request) -> Result<()> {
+    let on_conflict = match request.query.get("on_conflict").map(|s| s.as_str()) {
+        None | Some("fail") => ConflictPolicy::Fail,
+        Some("overwrite") => ConflictPolicy::Overwrite,
+        Some("skip") => ConflictPolicy::Skip,
+        Some(other) => {
+            let error = format!("unknown on_conflict \"{}\", use fail, overwrite or skip", other);
+            return respond(stream, "400 Bad Request", &json!({ "error": error })).await;
+        }
+    };
+    let since = match request.query.get("since") {
+        Some(since) => Some(since.clone()),
+        None => match last_shipped_commit(&server.workspace, &server.state_path) {
+            Ok(since) => since,
+            Err(e) => return respond_error(stream, e).await,
+        },
+    };
+    let lock = match SyncLock::acquire(&server.lock_path) {
+        Ok(Some(lock)) => lock,
+        Ok(None) => return respond_busy(stream, server).await,
+        Err(e) => return respond_error(stream, e).await,
+    };
+
+    let (sender, mut progress) = mpsc::unbounded_channel();
+    let options = ShipOptions {
+        since: since.clone(),
+        on_conflict,
+        parent_page_id: Some(server.workspace.parent_page_id.clone()),
+        progress: Some(sender),
+        ..Default::default()
+    };
+    // The ship goes on, and is reported, when whoever asked for it hangs up.
+    let task_server = server.clone();
+    let shipped = tokio::spawn(async move {
+        let _lock = lock;
+        let server = task_server;
+        let started = Utc::now();
+        let api = server.workspace.client.api_usage();
+        let outcome = server.workspace.client.ship(server.src.clone(), &options).await;
+        drop(options);
+        let mut report = ShipReport::new(&server.src, started);
+        report.add(
+            &server.workspace.client.parent_page_name(),
+            Some(server.workspace.parent_page_url.clone()),
+            outcome.as_ref().map_err(|e| redact(&format!("{:#}", e))),
+        );
+        let report = report.finish(Utc::now()).with_api_usage(server.workspace.client.api_usage() - api);
+        let _ = write_report(&server.workspace.client, &report);
+        let _ = notify(&server.workspace.client.settings().notify, &report).await;
+        let conflicts = outcome
+            .as_ref()
+            .err()
+            .and_then(|e| e.downcast_ref::<ConflictError>())
+            .map(|c| c.pages.clone());
+        (report, conflicts)
+    });
+
+    stream
+        .write_all(b"HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n")
+        .await?;
+    write_line(stream, &json!({ "event": "started", "since": since })).await?;
+    while let Some(p) = progress.recv().await {
+        write_line(stream, &serde_json::to_value(p)?).await?;
+    }
+    let last = match shipped.await {
+        Ok((report, conflicts)) => match report.destinations.iter().find_map(|d| d.error.clone()) {
+            Some(error) => json!({ "event": "failed", "error": error, "conflicts": conflicts, "report": report }),
+            None => json!({ "event": "finished", "report": report }),
+        },
+        Err(e) => json!({ "event": "failed", "error": e.to_string() }),
+    };
+    write_line(stream, &last).await
+}
+
+async fn github(stream: &mut TcpStream, server: &Arc<Server>, request: &Request) -> Result<()> {
+    let settings = &server.workspace.client.settings().serve;
+    let secret = match settings.github_secret.as_deref().filter(|s| !s.trim().is_empty()) {
+        Some(secret) => secret,
+        None => {
+            let error = "set [serve] github_secret to take GitHub webhooks";
+            return respond(stream, "404 Not Found", &json!({ "error": error })).await;
+        }
+    };
+    if !verify_signature(secret, &request.body, request.header("x-hub-signature-256")) {
+        return respond(stream, "401 Unauthorized", &json!({ "error": "missing or wrong X-Hub-Signature-256" })).await;
+    }
+    match request.header("x-github-event") {
+        Some("push") => {}
+        Some("ping") => return respond(stream, "200 OK", &json!({ "ok": true })).await,
+        event => {
+            let ignored = format!("{} event", event.unwrap_or("unknown"));
+            return respond(stream, "202 Accepted", &json!({ "ignored": ignored })).await;
+        }
+    }
+    let push: PushEvent = match serde_json::from_slice(&request.body) {
+        Ok(push) => push,
+        Err(e) => {
+            let error = format!("invalid push event: {}", e);
+            return respond(stream, "400 Bad Request", &json!({ "error": error })).await;
+        }
+    };
+    let branch = settings.branch.as_deref().unwrap_or(&push.repository.default_branch);
+    if push.deleted || push.branch() != Some(branch) {
+        return respond(stream, "202 Accepted", &json!({ "ignored": format!("not a push to {}", branch) })).await;
+    }
+    let root_in_repo = match path_in_repository(&server.root) {
+        Ok(path) => path,
+        Err(e) => return respond_error(stream, e).await,
+    };
+    if push.lists_every_change() && !push.touches(&root_in_repo) {
+        return respond(stream, "202 Accepted", &json!({ "ignored": "no documentation file changed" })).await;
+    }
+    let lock = match SyncLock::acquire(&server.lock_path) {
+        Ok(Some(lock)) => lock,
+        Ok(None) => return respond_busy(stream, server).await,
+        Err(e) => return respond_error(stream, e).await,
+    };
+    let after = push.after.clone();
+    // GitHub gives up on deliveries after 10 seconds, so the ship goes on after answering.
+    let task_server = server.clone();
+    tokio::spawn(async move {
+        let _lock = lock;
+        ship_push(&task_server, push).await;
+    });
+    respond(stream, "202 Accepted", &json!({ "shipping": after })).await
+}
+
+/// Ships what the push changed, as the pulled checkout tells it: the event's own list of files
+/// can be cut short or left empty.
+async fn ship_push(server: &Server, push: PushEvent) {
+    let client = &server.workspace.client;
+    log(
+        "info",
+        "push_received",
+        json!({ "repository": push.repository.full_name, "before": push.before, "after": push.after }),
+    );
+    if client.settings().serve.pull {
+        if let Err(e) = pull(&server.root).await {
+            log("error", "pull_failed", json!({ "error": e.to_string() }));
+            return;
+        }
+    }
+    let changed = match push.is_new_branch() {
+        true => None,
+        false => match changed_since(&server.root, &push.before) {
+            Ok(changed) => Some(changed),
+            Err(e) => {
+                log("warn", "diff_failed", json!({ "before": push.before, "error": e.to_string() }));
+                None
+            }
+        },
+    };
+    let only = match changed {
+        Some(changed) => Some(changed),
+        None => match all_files(&server.workspace, &server.src) {
+            Ok(all) => all,
+            Err(e) => {
+                log("error", "push_failed", json!({ "after": push.after, "error": format!("{:#}", e) }));
+                return;
+            }
+        },
+    };
+    let options = ShipOptions {
+        only,
+        on_conflict: ConflictPolicy::Skip,
+        parent_page_id: Some(server.workspace.parent_page_id.clone()),
+        ..Default::default()
+    };
+    let started = Utc::now();
+    let api = client.api_usage();
+    let outcome = client.ship(server.src.clone(), &options).await;
+    let mut report = ShipReport::new(&server.src, started);
+    report.add(
+        &client.parent_page_name(),
+        Some(server.workspace.parent_page_url.clone()),
+        outcome.as_ref().map_err(|e| redact(&format!("{:#}", e))),
+    );
+    let report = report.finish(Utc::now()).with_api_usage(client.api_usage() - api);
+    match &outcome {
+        Ok(summary) => log(
+            "info",
+            "push_shipped",
+            json!({ "after": push.after, "pages": summary.pages.len(), "skipped": summary.skipped, "duration_ms": report.duration_ms, "api": report.api }),
+        ),
+        Err(e) => log("error", "push_failed", json!({ "after": push.after, "error": format!("{:#}", e) })),
+    }
+    if let Err(e) = write_report(client, &report) {
+        log("warn", "report_failed", json!({ "error": e.to_string() }));
+    }
+    if let Err(e) = notify(&client.settings().notify, &report).await {
+        log("warn", "notify_failed", json!({ "error": e.to_string() }));
+    }
+}
+
+async fn read_request(stream: &mut TcpStream) -> Result<Request> {
+    let mut buffer = Vec::new();
+    let mut chunk = vec![0; 8192];
+    let end = loop {
+        if let Some(end) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break end;
+        }
+        if buffer.len() > MAX_HEAD {
+            return Err(anyhow!("request headers are too large"));
+        }
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Err(anyhow!("connection closed before the end of the request headers"));
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    };
+    let mut request = Request::parse_head(&String::from_utf8_lossy(&buffer[..end]))
+        .ok_or(anyhow!("malformed request line"))?;
+    let length: usize = match request.header("content-length") {
+        Some(length) => length.parse().map_err(|_| anyhow!("invalid Content-Length"))?,
+        None => 0,
+    };
+    if length > MAX_BODY {
+        return Err(anyhow!("request body is larger than {} bytes", MAX_BODY));
+    }
+    let mut body = buffer.split_off(end + 4);
+    while body.len() < length {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Err(anyhow!("connection closed before the end of the request body"));
+        }
+        body.extend_from_slice(&chunk[..read]);
+    }
+    body.truncate(length);
+    request.body = body;
+    Ok(request)
+}
+
+async fn respond(stream: &mut TcpStream, status: &str, body: &impl Serialize) -> Result<()> {
+    let body = redact(&serde_json::to_string(body)?);
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    Ok(())
+}
+
+async fn respond_error(stream: &mut TcpStream, e: anyhow::Error) -> Result<()> {
+    respond(stream, "500 Internal Server Error", &json!({ "error": format!("{:#}", e) })).await
+}
+
+async fn respond_busy(stream: &mut TcpStream, server: &Server) -> Result<()> {
+    let error = format!("another sync holds {}, remove it if none is running", server.lock_path.display());
+    respond(stream, "409 Conflict", &json!({ "error": error })).await
+}
+
+async fn write_line(stream: &mut TcpStream, line: &Value) -> Result<()> {
+    stream.write_all(format!("{}\n", redact(&line.to_string())).as_bytes()).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    use std::path::Path;
+
+    use git2::{Repository, Signature};
+
+    use crate::commands::Workspace;
+    use crate::github::PushEvent;
+    use crate::notion::client::ShipOptions;
+    use crate::notion::fake::FakeNotion;
+    use crate::serve::{serve, ship_push, PendingChanges, Request, Server};
+    use crate::settings::directory::SourceTree;
+    use crate::sync::state::SyncState;
+    use crate::sync::status::{LocalChange, PageStatus, StatusReport};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_parse_request() {
+        let head = "POST /ship?since=HEAD%7E3&on_conflict=skip&flag HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer s3cret\r\nContent-Length: 0";
+        let request = Request::parse_head(head).unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/ship");
+        assert_eq!(request.query.get("since").map(|s| s.as_str()), Some("HEAD~3"));
+        assert_eq!(request.query.get("on_conflict").map(|s| s.as_str()), Some("skip"));
+        assert_eq!(request.query.get("flag").map(|s| s.as_str()), Some(""));
+        assert_eq!(request.header("content-length"), Some("0"));
+
+        assert!(request.is_authorized(Some("s3cret")));
+        assert!(request.is_authorized(None));
+        assert!(!request.is_authorized(Some("other")));
+        let anonymous = Request::parse_head("GET /status HTTP/1.1\r\n").unwrap();
+        assert!(!anonymous.is_authorized(Some("s3cret")));
+        assert!(anonymous.is_authorized(Some("")));
+        assert!(Request::parse_head("").is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_pending_changes() {
+        let page = |path: &str, local: Option<LocalChange>, is_edited: bool| PageStatus {
+            path: path.to_string(),
+            page_id: "id".to_string(),
+            url: "https://www.notion.so/id".to_string(),
+            local,
+            is_edited_in_notion: Some(is_edited),
+            is_in_trash: false,
+        };
+        let report = StatusReport {
+            pages: vec![
+                page("", None, false),
+                page("guide.md", Some(LocalChange::Unchanged), false),
+                page("api.md", Some(LocalChange::Modified), true),
+                page("old.md", Some(LocalChange::Deleted), false),
+            ],
+            new_files: vec!["faq.md".to_string()],
+            index_refreshed_at: None,
+        };
+        assert_eq!(
+            PendingChanges::from_status(&report),
+            PendingChanges {
+                new: vec!["faq.md".to_string()],
+                modified: vec!["api.md".to_string()],
+                deleted: vec!["old.md".to_string()],
+                conflicts: vec!["api.md".to_string()],
+            }
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_serve_clear() {
+        let fake = FakeNotion::start().await;
+        let docs = fake.add_page(None, "Docs");
+        fake.add_paragraph(&docs, "Welcome");
+        fake.add_page(Some(&docs), "Guides");
+        let workspace = Workspace {
+            client: fake.client(&docs, json!({ "serve": { "token": "s3cret" } })),
+            parent_page_id: docs.clone(),
+            parent_page_url: "https://www.notion.so/docs".to_string(),
+        };
+        let src = std::env::temp_dir().join(format!("notation-serve-clear-{}", std::process::id()));
+        std::fs::create_dir_all(&src).unwrap();
+        let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        tokio::spawn(serve(workspace, src.display().to_string(), addr));
+        let post = |target: &str, token: &str| {
+            let request = format!("POST {} HTTP/1.1\r\nAuthorization: Bearer {}\r\n\r\n", target, token);
+            async move {
+                let mut stream = loop {
+                    match TcpStream::connect(addr).await {
+                        Ok(stream) => break stream,
+                        Err(_) => tokio::time::sleep(std::time::Duration::from_millis(10)).await,
+                    }
+                };
+                stream.write_all(request.as_bytes()).await.unwrap();
+                let mut response = String::new();
+                stream.read_to_string(&mut response).await.unwrap();
+                response
+            }
+        };
+
+        assert!(post("/clear?confirm=Docs", "s3cre").await.starts_with("HTTP/1.1 401"));
+        let unconfirmed = post("/clear", "s3cret").await;
+        assert!(unconfirmed.starts_with("HTTP/1.1 409"));
+        assert!(unconfirmed.contains("pass ?confirm=Docs to archive 1 pages and 1 blocks"));
+        assert!(post("/clear?confirm=Docs&max_pages=0", "s3cret").await.contains("more than max_pages 0"));
+        assert_eq!(fake.children(&docs).len(), 2);
+
+        assert!(post("/clear?confirm=Docs", "s3cret").await.starts_with("HTTP/1.1 200"));
+        assert!(fake.children(&docs).is_empty());
+        std::fs::remove_dir_all(&src).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_ship_push() {
+        let dir = std::env::temp_dir().join(format!("notation-ship-push-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("docs")).unwrap();
+        let repo = Repository::init(&dir).unwrap();
+        let commit = |files: &[(&str, &str)]| {
+            let mut index = repo.index().unwrap();
+            for (file, contents) in files {
+                std::fs::write(dir.join("docs").join(file), contents).unwrap();
+                index.add_path(&Path::new("docs").join(file)).unwrap();
+            }
+            index.write().unwrap();
+            let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+            let signature = Signature::now("Docs", "docs@example.com").unwrap();
+            let parents: Vec<_> = repo.head().ok().map(|h| h.peel_to_commit().unwrap()).into_iter().collect();
+            let parents: Vec<_> = parents.iter().collect();
+            repo.commit(Some("HEAD"), &signature, &signature, "docs", &tree, &parents).unwrap().to_string()
+        };
+        let first = commit(&[("a.md", "# A\n\nFirst\n")]);
+
+        let fake = FakeNotion::start().await;
+        let docs = fake.add_page(None, "Docs");
+        let src = dir.join("docs").display().to_string();
+        let client = fake.client(&docs, json!({ "serve": { "pull": false } }));
+        client.ship(src.clone(), &ShipOptions::default()).await.unwrap();
+        let server = Server {
+            root: SourceTree::load(&src, client.settings().content.clone()).unwrap().root,
+            workspace: Workspace {
+                client,
+                parent_page_id: docs.clone(),
+                parent_page_url: String::new(),
+            },
+            src: src.clone(),
+            token: None,
+            lock_path: dir.join("lock"),
+            state_path: dir.join("state"),
+        };
+
+        // A force push lists no commits, what it changed comes from the checkout.
+        commit(&[("a.md", "# A\n\nSecond\n"), ("b.md", "# B\n")]);
+        let forced = PushEvent {
+            before: first,
+            forced: true,
+            ..Default::default()
+        };
+        ship_push(&server, forced).await;
+        assert_eq!(fake.page_titles(&docs).len(), 2);
+        let state = SyncState::load(&SyncState::path(&dir.join("docs"), None)).unwrap().unwrap();
+        let text = |key: &str| serde_json::to_string(&fake.children(&state.pages[key].page_id)).unwrap();
+        assert!(text("a.md").contains("Second"));
+
+        // A new branch has nothing to compare with, so every page is shipped again, over the
+        // pages they have.
+        commit(&[("b.md", "# B\n\nMore\n")]);
+        let created = PushEvent {
+            before: "0".repeat(40),
+            ..Default::default()
+        };
+        ship_push(&server, created).await;
+        assert_eq!(fake.page_titles(&docs).len(), 2);
+        assert!(text("b.md").contains("More"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,252 @@
+use std::fmt::Display;
+use std::path::Path;
+
+use config::Config;
+
+use crate::notion::client::NotionClient;
+use crate::settings::notation::{NotationSettings, PARENT_PAGE_ENV, SECRET_ENV};
+
+const TOKEN_PREFIXES: [&str; 2] = ["secret_", "ntn_"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CheckOutcome {
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Clone, Debug)]
+pub struct ConfigCheck {
+    pub name: String,
+    pub outcome: CheckOutcome,
+    pub detail: String,
+}
+
+impl ConfigCheck {
+    fn pass(name: &str, detail: impl Display) -> Self {
+        ConfigCheck {
+            name: name.to_string(),
+            outcome: CheckOutcome::Pass,
+            detail: detail.to_string(),
+        }
+    }
+
+    fn warn(name: &str, detail: impl Display) -> Self {
+        ConfigCheck {
+            name: name.to_string(),
+            outcome: CheckOutcome::Warn,
+            detail: detail.to_string(),
+        }
+    }
+
+    fn fail(name: &str, detail: impl Display) -> Self {
+        ConfigCheck {
+            name: name.to_string(),
+            outcome: CheckOutcome::Fail,
+            detail: detail.to_string(),
+        }
+    }
+}
+
+/// Validates the configuration step by step, stopping at the first check that later checks
+/// depend on. The last checks call the Notion API with the configured secret.
+pub async fn check_config() -> Vec<ConfigCheck> {
+    let mut checks = Vec::new();
+
+    let vars = std::env::vars().collect();
+    let env_only = NotationSettings::is_env_only(&vars);
+    match NotationSettings::config_path(&vars) {
+        Some(path) if path.is_file() => {
+            checks.push(ConfigCheck::pass("config file", path.display()));
+            if let Some(c) = check_permissions(&path) {
+                checks.push(c);
+            }
+        }
+        Some(path) if env_only => checks.push(ConfigCheck::pass(
+            "config file",
+            format!("{} not found, using environment variables only", path.display()),
+        )),
+        Some(path) => {
+            checks.push(ConfigCheck::fail(
+                "config file",
+                format!(
+                    "{} not found, create it or set both {} and {}",
+                    path.display(),
+                    SECRET_ENV,
+                    PARENT_PAGE_ENV
+                ),
+            ));
+            return checks;
+        }
+        None if env_only => checks.push(ConfigCheck::pass(
+            "config file",
+            "using environment variables only",
+        )),
+        None => {
+            checks.push(ConfigCheck::fail(
+                "config file",
+                format!(
+                    "no home directory to find ~/.notation/Notation.toml, set NOTATION_CONFIG or both {} and {}",
+                    SECRET_ENV, PARENT_PAGE_ENV
+                ),
+            ));
+            return checks;
+        }
+    }
+
+    let config = match NotationSettings::load_config(&vars) {
+        Ok(c) => c,
+        Err(e) => {
+            checks.push(ConfigCheck::fail("schema", format!("could not be parsed: {}", e)));
+            return checks;
+        }
+    };
+    let mut missing_field = false;
+    for (key, hint) in [
+        ("notion.secret", "the secret of your Notion integration"),
+        ("notion.parent_page", "the title of the page hosting your documentation"),
+    ] {
+        if let Some(c) = check_required_string(&config, key, hint) {
+            checks.push(c);
+            missing_field = true;
+        }
+    }
+    if missing_field {
+        return checks;
+    }
+    let settings: NotationSettings = match config.try_deserialize() {
+        Ok(s) => s,
+        Err(e) => {
+            checks.push(ConfigCheck::fail("schema", e));
+            return checks;
+        }
+    };
+    checks.push(ConfigCheck::pass("schema", "all fields are valid"));
+
+    match check_token_format(&settings.notion.secret) {
+        Ok(()) => checks.push(ConfigCheck::pass("secret format", "looks like a Notion integration secret")),
+        Err(e) => {
+            checks.push(ConfigCheck::fail("secret format", e));
+            return checks;
+        }
+    }
+
+    let parent_page = settings.notion.parent_page.clone();
+    let nc = match NotionClient::from_settings(settings) {
+        Ok(nc) => nc,
+        Err(e) => {
+            checks.push(ConfigCheck::fail("notion.secret", e));
+            return checks;
+        }
+    };
+    match nc.get_current_user().await {
+        Ok(user) => checks.push(ConfigCheck::pass(
+            "users/me",
+            format!(
+                "authenticated as \"{}\"",
+                user.name.unwrap_or(user.id)
+            ),
+        )),
+        Err(e) => {
+            checks.push(ConfigCheck::fail(
+                "users/me",
+                format!("notion.secret was rejected by Notion: {}", e),
+            ));
+            return checks;
+        }
+    }
+    match nc.parent_page_id().await {
+        Ok(id) => checks.push(ConfigCheck::pass(
+            "notion.parent_page",
+            format!("\"{}\" resolves to page {}", parent_page, id),
+        )),
+        Err(e) => checks.push(ConfigCheck::fail(
+            "notion.parent_page",
+            format!(
+                "\"{}\" {}; is the page shared with your integration under Connections?",
+                parent_page, e
+            ),
+        )),
+    }
+
+    checks
+}
+
+fn check_required_string(config: &Config, key: &str, hint: &str) -> Option<ConfigCheck> {
+    let (section, field) = key.split_once('.').unwrap_or(("", key));
+    match config.get::<String>(key) {
+        Ok(v) if v.trim().is_empty() => Some(ConfigCheck::fail(
+            key,
+            format!("`{}` in [{}] is empty, it should be {}", field, section, hint),
+        )),
+        Ok(_) => None,
+        Err(config::ConfigError::NotFound(_)) => Some(ConfigCheck::fail(
+            key,
+            format!("`{}` is missing from [{}], it should be {}", field, section, hint),
+        )),
+        Err(e) => Some(ConfigCheck::fail(key, e)),
+    }
+}
+
+#[cfg(unix)]
+fn check_permissions(path: &Path) -> Option<ConfigCheck> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = match std::fs::metadata(path) {
+        Ok(m) => m.permissions().mode(),
+        Err(e) => return Some(ConfigCheck::fail("permissions", e)),
+    };
+    if mode & 0o077 != 0 {
+        Some(ConfigCheck::warn(
+            "permissions",
+            format!(
+                "{} is readable by other users (mode {:o}) but holds your secret, run `chmod 600 {}`",
+                path.display(),
+                mode & 0o777,
+                path.display()
+            ),
+        ))
+    } else {
+        Some(ConfigCheck::pass("permissions", format!("mode {:o}", mode & 0o777)))
+    }
+}
+
+#[cfg(not(unix))]
+fn check_permissions(_path: &Path) -> Option<ConfigCheck> {
+    None
+}
+
+pub fn check_token_format(secret: &str) -> Result<(), String> {
+    if secret.starts_with("Bearer ") {
+        return Err("notion.secret should not include the \"Bearer \" prefix".to_string());
+    }
+    if secret.trim() != secret {
+        return Err("notion.secret has leading or trailing whitespace".to_string());
+    }
+    let rest = TOKEN_PREFIXES
+        .iter()
+        .find_map(|p| secret.strip_prefix(p))
+        .ok_or(format!(
+            "notion.secret should start with one of {}, copy the \"Internal Integration Secret\" of your integration",
+            TOKEN_PREFIXES.join(", ")
+        ))?;
+    if rest.is_empty() || !rest.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err("notion.secret contains unexpected characters after its prefix".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::settings::check::check_token_format;
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_check_token_format() {
+        assert!(check_token_format("secret_abcDEF123").is_ok());
+        assert!(check_token_format("ntn_abcDEF123").is_ok());
+        assert!(check_token_format("Bearer secret_abc").is_err());
+        assert!(check_token_format("secret_abc ").is_err());
+        assert!(check_token_format("abc").is_err());
+        assert!(check_token_format("secret_").is_err());
+    }
+}
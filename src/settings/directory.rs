@@ -0,0 +1,409 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use config::Config;
+use glob::{glob, Pattern};
+use serde::{Deserialize, Serialize};
+
+use crate::markdown::parse::{get_source_glob_pattern, parse_file_sync, plain_path, NotationDocArguments};
+use crate::notion::client::get_intro_path;
+use crate::markdown::source::is_source_file;
+use crate::settings::notation::{ContentSettings, HeadingStrategy, RedactSettings, SymlinkPolicy, TitleStrategy};
+
+pub const DIRECTORY_SETTINGS_FILENAME: &str = ".notation.toml";
+
+/// Settings read from a `.notation.toml` inside a shipped directory, under the arguments of
+/// the directory's intro.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DirectorySettings {
+    pub title: Option<String>,
+    pub emoji: Option<String>,
+    /// The URL of the cover of the directory's page, over the one `[cover]` gives it.
+    pub cover: Option<String>,
+    /// The description of the directory's database row.
+    pub description: Option<String>,
+    /// File or subdirectory names shipped first, in this order. The rest follow alphabetically.
+    #[serde(default)]
+    pub order: Vec<String>,
+    /// Glob patterns, relative to this directory, of markdown files to leave out.
+    #[serde(default)]
+    pub ignore: Vec<String>,
+    pub heading_strategy: Option<HeadingStrategy>,
+    pub title_strategy: Option<TitleStrategy>,
+    /// Ship every markdown file under this directory, in order, as sections of the directory's
+    /// page instead of as subpages.
+    #[serde(default)]
+    pub merge: bool,
+    /// The first `#` heading of the directory's intro, its title with `title_strategy =
+    /// "first_heading"` when it has no other.
+    #[serde(skip)]
+    pub heading: Option<String>,
+}
+
+impl DirectorySettings {
+    pub fn load(dir: &Path) -> Result<Option<Self>> {
+        let path = dir.join(DIRECTORY_SETTINGS_FILENAME);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let s = Config::builder()
+            .add_source(config::File::from(path.clone()))
+            .build()?;
+        let result: Self = s
+            .try_deserialize()
+            .map_err(|e| anyhow!("failed to read {}: {}", path.display(), e))?;
+        Ok(Some(result))
+    }
+
+    /// These settings under `arguments`, those of the directory's intro, which win.
+    pub fn with_arguments(self, arguments: NotationDocArguments) -> Self {
+        DirectorySettings {
+            title: arguments.title.or(self.title),
+            emoji: arguments.emoji.or(self.emoji),
+            cover: arguments.cover.or(self.cover),
+            description: arguments.description.or(self.description),
+            order: if arguments.order.is_empty() { self.order } else { arguments.order },
+            ..self
+        }
+    }
+
+    fn order_rank(&self, name: &str) -> usize {
+        let stem = Path::new(name)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or(name);
+        self.order
+            .iter()
+            .position(|o| o == name || o == stem)
+            .unwrap_or(usize::MAX)
+    }
+}
+
+/// The documentation files a ship of `dir` picks up, with the directory settings that apply to
+/// them.
+#[derive(Clone, Debug)]
+pub struct SourceTree {
+    /// `dir` itself, or its parent directory when `dir` is a single file.
+    pub root: PathBuf,
+    /// The files that aren't ignored, relative to `root`, in shipping order.
+    pub files: Vec<PathBuf>,
+    pub settings: DirectorySettingsTree,
+}
+
+impl SourceTree {
+    pub fn load(dir: &str, global: ContentSettings) -> Result<Self> {
+        SourceTree::load_redacted(dir, global, &RedactSettings::default())
+    }
+
+    /// Like [`SourceTree::load`], with the headings that title directories taken from their
+    /// intros after `redact`, for trees that are shipped.
+    pub fn load_redacted(dir: &str, global: ContentSettings, redact: &RedactSettings) -> Result<Self> {
+        let dir_path = plain_path(Path::new(dir));
+        let root = if dir_path.is_file() {
+            dir_path.parent().unwrap_or(Path::new("")).to_path_buf()
+        } else {
+            dir_path
+        };
+        let is_skipping_symlinks = global.symlinks == SymlinkPolicy::Skip;
+        // Glob leaves the `./` of a pattern out of the paths it finds.
+        let glob_root = if root == Path::new(".") { PathBuf::new() } else { root.clone() };
+        let mut relative_paths = Vec::new();
+        for entry in glob(&get_source_glob_pattern(dir.to_string()))? {
+            let path = entry?;
+            if path.is_file() && is_source_file(&path) {
+                let relative = path.strip_prefix(&glob_root)?.to_path_buf();
+                if is_skipping_symlinks && is_through_symlink(&root, &relative) {
+                    continue;
+                }
+                relative_paths.push(relative);
+            }
+        }
+        let settings = DirectorySettingsTree::load(&root, &relative_paths, global, redact)?;
+        let mut files = Vec::new();
+        for p in relative_paths {
+            if !settings.is_ignored(&p)? {
+                files.push(p);
+            }
+        }
+        settings.sort(&mut files);
+        Ok(SourceTree {
+            root,
+            files,
+            settings,
+        })
+    }
+}
+
+/// Whether `relative`, a path below `root`, is reached through a symlink.
+fn is_through_symlink(root: &Path, relative: &Path) -> bool {
+    relative
+        .ancestors()
+        .filter(|a| !a.as_os_str().is_empty())
+        .any(|a| root.join(a).symlink_metadata().is_ok_and(|m| m.file_type().is_symlink()))
+}
+
+/// Directory settings for every directory of a shipped tree, keyed by the directory's path
+/// relative to the shipped root (the root itself is the empty path), merged over the global
+/// [`ContentSettings`].
+#[derive(Clone, Debug, Default)]
+pub struct DirectorySettingsTree {
+    global: ContentSettings,
+    directories: HashMap<PathBuf, DirectorySettings>,
+}
+
+impl DirectorySettingsTree {
+    pub fn new(global: ContentSettings, directories: HashMap<PathBuf, DirectorySettings>) -> Self {
+        DirectorySettingsTree {
+            global,
+            directories,
+        }
+    }
+
+    /// Loads the `.notation.toml` and the intro arguments of every directory containing (or
+    /// above) one of `files`, which are relative to `root`, with the first heading of each
+    /// intro after `redact`.
+    pub fn load(root: &Path, files: &[PathBuf], global: ContentSettings, redact: &RedactSettings) -> Result<Self> {
+        let mut directories = HashMap::new();
+        for f in files {
+            for dir in f.ancestors().skip(1) {
+                if directories.contains_key(dir) {
+                    continue;
+                }
+                let settings = DirectorySettings::load(&root.join(dir))?;
+                let intro_path = get_intro_path(root, dir);
+                let settings = if intro_path.is_file() {
+                    let intro = parse_file_sync(&intro_path)?;
+                    let settings = settings.unwrap_or_default().with_arguments(intro.get_arguments()?);
+                    Some(DirectorySettings {
+                        heading: intro.first_heading(redact)?,
+                        ..settings
+                    })
+                } else {
+                    settings
+                };
+                if let Some(settings) = settings {
+                    directories.insert(dir.to_path_buf(), settings);
+                }
+            }
+        }
+        Ok(DirectorySettingsTree::new(global, directories))
+    }
+
+    pub fn get(&self, dir: &Path) -> Option<&DirectorySettings> {
+        self.directories.get(dir)
+    }
+
+    /// Whether the relative `file` matches an ignore pattern, either from the global settings
+    /// or from the `.notation.toml` of one of its ancestor directories.
+    pub fn is_ignored(&self, file: &Path) -> Result<bool> {
+        for dir in file.ancestors().skip(1) {
+            let mut patterns: Vec<&String> = self
+                .get(dir)
+                .map(|d| d.ignore.iter().collect())
+                .unwrap_or_default();
+            if dir.as_os_str().is_empty() {
+                patterns.extend(self.global.ignore.iter());
+            }
+            let relative = file.strip_prefix(dir)?;
+            for p in patterns {
+                let pattern = Pattern::new(p)
+                    .map_err(|e| anyhow!("invalid ignore pattern \"{}\": {}", p, e))?;
+                if relative
+                    .ancestors()
+                    .any(|a| !a.as_os_str().is_empty() && pattern.matches_path(a))
+                {
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    /// The heading strategy for a relative `file`, taken from its nearest ancestor directory
+    /// that sets one.
+    pub fn heading_strategy(&self, file: &Path) -> HeadingStrategy {
+        file.ancestors()
+            .skip(1)
+            .find_map(|dir| self.get(dir).and_then(|d| d.heading_strategy))
+            .unwrap_or(self.global.heading_strategy)
+    }
+
+    /// The title strategy for a relative `file`, taken from its nearest ancestor directory
+    /// that sets one.
+    pub fn title_strategy(&self, file: &Path) -> TitleStrategy {
+        file.ancestors()
+            .skip(1)
+            .find_map(|dir| self.get(dir).and_then(|d| d.title_strategy))
+            .unwrap_or(self.global.title_strategy)
+    }
+
+    /// Whether the page of the relative directory `dir` is titled after the first heading of
+    /// its intro, which is then left out of the page like the title heading of a file. Only
+    /// with `title_strategy = "first_heading"` and no other title, and never for the shipped
+    /// root, whose page is the parent page.
+    pub fn is_titled_by_intro(&self, dir: &Path) -> bool {
+        !dir.as_os_str().is_empty()
+            && self.title_strategy(&dir.join("intro.md")) == TitleStrategy::FirstHeading
+            && self.get(dir).is_some_and(|d| d.title.is_none() && d.heading.is_some())
+    }
+
+    /// The outermost directory above a relative `file` that merges its files into one page.
+    pub fn merged_directory(&self, file: &Path) -> Option<PathBuf> {
+        file.ancestors()
+            .skip(1)
+            .filter(|dir| self.get(dir).is_some_and(|d| d.merge))
+            .last()
+            .map(Path::to_path_buf)
+    }
+
+    /// Orders relative file paths so that every directory's children follow its `order`.
+    pub fn sort(&self, files: &mut [PathBuf]) {
+        files.sort_by(|a, b| self.compare(a, b));
+    }
+
+    fn compare(&self, a: &Path, b: &Path) -> Ordering {
+        let mut dir = PathBuf::new();
+        for (ac, bc) in a.components().zip(b.components()) {
+            if ac != bc {
+                let an = ac.as_os_str().to_string_lossy();
+                let bn = bc.as_os_str().to_string_lossy();
+                let (ar, br) = match self.get(&dir) {
+                    Some(d) => (d.order_rank(&an), d.order_rank(&bn)),
+                    None => (usize::MAX, usize::MAX),
+                };
+                return ar.cmp(&br).then_with(|| an.cmp(&bn));
+            }
+            dir.push(ac);
+        }
+        a.components().count().cmp(&b.components().count())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    use crate::settings::directory::{DirectorySettings, DirectorySettingsTree, SourceTree};
+    use crate::settings::notation::{ContentSettings, HeadingStrategy};
+
+    fn tree() -> DirectorySettingsTree {
+        let mut directories = HashMap::new();
+        directories.insert(
+            PathBuf::new(),
+            DirectorySettings {
+                order: vec!["getting_started.md".to_string(), "api".to_string()],
+                ..Default::default()
+            },
+        );
+        directories.insert(
+            PathBuf::from("api"),
+            DirectorySettings {
+                ignore: vec!["drafts".to_string(), "*.wip.md".to_string()],
+                heading_strategy: Some(HeadingStrategy::Paragraph),
+                ..Default::default()
+            },
+        );
+        directories.insert(
+            PathBuf::from("guides"),
+            DirectorySettings {
+                merge: true,
+                ..Default::default()
+            },
+        );
+        directories.insert(
+            PathBuf::from("guides/setup"),
+            DirectorySettings {
+                merge: true,
+                ..Default::default()
+            },
+        );
+        let global = ContentSettings {
+            ignore: vec!["partials/**".to_string()],
+            ..Default::default()
+        };
+        DirectorySettingsTree::new(global, directories)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_sort_follows_order() {
+        let mut files = vec![
+            PathBuf::from("zebra.md"),
+            PathBuf::from("api/endpoints.md"),
+            PathBuf::from("about.md"),
+            PathBuf::from("getting_started.md"),
+        ];
+        tree().sort(&mut files);
+        assert_eq!(
+            files,
+            vec![
+                PathBuf::from("getting_started.md"),
+                PathBuf::from("api/endpoints.md"),
+                PathBuf::from("about.md"),
+                PathBuf::from("zebra.md"),
+            ]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_is_ignored() {
+        let t = tree();
+        assert!(t.is_ignored(&PathBuf::from("partials/setup.md")).unwrap());
+        assert!(t.is_ignored(&PathBuf::from("api/drafts/next.md")).unwrap());
+        assert!(t.is_ignored(&PathBuf::from("api/auth.wip.md")).unwrap());
+        assert!(!t.is_ignored(&PathBuf::from("api/auth.md")).unwrap());
+        assert!(!t.is_ignored(&PathBuf::from("drafts/next.md")).unwrap());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_heading_strategy_inherits() {
+        let t = tree();
+        assert_eq!(
+            t.heading_strategy(&PathBuf::from("api/v1/auth.md")),
+            HeadingStrategy::Paragraph
+        );
+        assert_eq!(
+            t.heading_strategy(&PathBuf::from("about.md")),
+            HeadingStrategy::Clamp
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_merged_directory() {
+        let t = tree();
+        assert_eq!(
+            t.merged_directory(&PathBuf::from("guides/setup/linux.md")),
+            Some(PathBuf::from("guides"))
+        );
+        assert_eq!(
+            t.merged_directory(&PathBuf::from("guides/intro.md")),
+            Some(PathBuf::from("guides"))
+        );
+        assert_eq!(t.merged_directory(&PathBuf::from("api/auth.md")), None);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_intro_arguments() {
+        let root = std::env::temp_dir().join(format!("notation-intro-arguments-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("api")).unwrap();
+        std::fs::write(root.join("api/.notation.toml"), "title = \"API\"\nemoji = \"🔌\"\norder = [\"auth.md\"]\n").unwrap();
+        std::fs::write(
+            root.join("api/intro.md"),
+            "<!-- notation: --emoji 🚀 --cover https://example.com/api.png --order errors,auth -->\n\nThe API",
+        )
+        .unwrap();
+        for f in ["auth.md", "errors.md", "basics.md"] {
+            std::fs::write(root.join("api").join(f), "# Page\n").unwrap();
+        }
+        let tree = SourceTree::load(&root.display().to_string(), ContentSettings::default()).unwrap();
+        let api = tree.settings.get(&PathBuf::from("api")).unwrap();
+        assert_eq!(api.title.as_deref(), Some("API"));
+        assert_eq!(api.emoji.as_deref(), Some("🚀"));
+        assert_eq!(api.cover.as_deref(), Some("https://example.com/api.png"));
+        let files: Vec<String> = tree.files.iter().map(|f| f.display().to_string()).collect();
+        assert_eq!(files, vec!["api/errors.md", "api/auth.md", "api/basics.md", "api/intro.md"]);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
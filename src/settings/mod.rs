@@ -1 +1,3 @@
+pub mod check;
+pub mod directory;
 pub mod notation;
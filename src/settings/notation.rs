@@ -1,32 +1,977 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::env;
-use anyhow::Result;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Result};
 use config::Config;
 use serde::{Deserialize, Serialize};
 
+use crate::redact::REDACTED;
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct NotationSettings {
     pub notion: Notion,
+    #[serde(default)]
+    pub content: ContentSettings,
+    #[serde(default)]
+    pub ci: CiSettings,
+    #[serde(default)]
+    pub sync: SyncSettings,
+    #[serde(default)]
+    pub footer: FooterSettings,
+    #[serde(default)]
+    pub code_check: CodeCheckSettings,
+    #[serde(default)]
+    pub lint: LintSettings,
+    #[serde(default)]
+    pub link_check: LinkCheckSettings,
+    #[serde(default)]
+    pub report: ReportSettings,
+    #[serde(default)]
+    pub notify: NotifySettings,
+    #[serde(default)]
+    pub serve: ServeSettings,
+    #[serde(default)]
+    pub redact: RedactSettings,
+    #[serde(default)]
+    pub emoji: EmojiSettings,
+    #[serde(default)]
+    pub cover: CoverSettings,
+    #[serde(default)]
+    pub database: DatabaseSettings,
+    #[serde(default)]
+    pub index: IndexSettings,
+    #[serde(default)]
+    pub templates: TemplateSettings,
+    /// Parent pages `ship` fans out to instead of `[notion] parent_page`, like a staging and
+    /// a public wiki.
+    #[serde(default)]
+    pub destinations: Vec<Destination>,
+    /// The languages of a tree like `docs/en` and `docs/ja`, each shipped to its own parent
+    /// page.
+    #[serde(default)]
+    pub locales: Vec<Locale>,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Notion {
     pub secret: String,
+    /// The title of the parent page, or its path like `Engineering/Docs` when other pages
+    /// share its title.
+    pub parent_page: String,
+    /// The id of the parent page, which is then used over `parent_page` to find it.
+    #[serde(default)]
+    pub parent_page_id: Option<String>,
+}
+
+/// A parent page a ship goes to, under `[[destinations]]`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Destination {
+    /// Picks the destination with `ship --destination` and labels it in the summary.
+    pub name: String,
     pub parent_page: String,
+    /// The integration token of the destination's workspace, `[notion] secret` when unset.
+    #[serde(default)]
+    pub secret: Option<String>,
+    /// Where the sync state of ships to this destination is kept, relative to the shipped
+    /// directory. Defaults to `.notation/state-<name>.json`.
+    #[serde(default)]
+    pub state_file: Option<String>,
+    /// The title of the destination's pages, over `[content] title_template`.
+    #[serde(default)]
+    pub title_template: Option<String>,
+}
+
+/// A language of the docs, under `[[locales]]`, whose subdirectory ships to a parent page of
+/// its own.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Locale {
+    /// Labels the locale in the summary and picks it with `ship --locale`, like `en`.
+    pub name: String,
+    pub parent_page: String,
+    /// The subdirectory of the shipped directory holding the locale's docs, `name` when unset.
+    #[serde(default)]
+    pub path: Option<String>,
+    /// The integration token of the locale's workspace, `[notion] secret` when unset.
+    #[serde(default)]
+    pub secret: Option<String>,
+}
+
+impl Locale {
+    /// The directory of the locale's docs, under the shipped directory `src`.
+    pub fn src(&self, src: &str) -> String {
+        Path::new(src)
+            .join(self.path.as_deref().unwrap_or(&self.name))
+            .display()
+            .to_string()
+    }
+
+    /// The destination the locale's docs ship to. Its sync state stays in its own
+    /// subdirectory.
+    pub fn destination(&self) -> Destination {
+        Destination {
+            name: self.name.clone(),
+            parent_page: self.parent_page.clone(),
+            secret: self.secret.clone(),
+            state_file: None,
+            title_template: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ContentSettings {
+    /// Glob patterns, relative to the shipped directory, of markdown files to leave out.
+    #[serde(default)]
+    pub ignore: Vec<String>,
+    #[serde(default)]
+    pub heading_strategy: HeadingStrategy,
+    #[serde(default)]
+    pub title_strategy: TitleStrategy,
+    /// Whether titles taken from file and directory names are made readable, `Getting
+    /// Started` for `getting_started.md`.
+    #[serde(default)]
+    pub pretty_titles: bool,
+    /// Whether lines highlighted on a code fence (```` ```rust {1,3-5} ````) are bolded, since
+    /// Notion code blocks have no line highlighting.
+    #[serde(default)]
+    pub bold_highlighted_lines: bool,
+    /// Whether code fences without a language get one guessed from their shebang, a first
+    /// line comment naming a file, or content like JSON, instead of plain text.
+    #[serde(default)]
+    pub detect_code_language: bool,
+    /// Where images referenced by a relative path are served from, like
+    /// `https://raw.githubusercontent.com/org/repo/main/`. Their path in the repository is
+    /// appended to it.
+    #[serde(default)]
+    pub image_base_url: Option<String>,
+    #[serde(default)]
+    pub outside_links: OutsideLinkPolicy,
+    /// Where links out of the shipped directory are rewritten to with
+    /// `outside_links = "rewrite"`, like `https://github.com/org/repo/blob/main/`. Their path
+    /// in the repository is appended to it.
+    #[serde(default)]
+    pub link_base_url: Option<String>,
+    #[serde(default)]
+    pub svg: SvgStrategy,
+    #[serde(default)]
+    pub unsupported_nodes: UnsupportedNodePolicy,
+    #[serde(default)]
+    pub linked_images: LinkedImageStrategy,
+    #[serde(default)]
+    pub line_break: LineBreakPolicy,
+    #[serde(default)]
+    pub symlinks: SymlinkPolicy,
+    /// The title of every shipped page, where `{title}` becomes the page's own title, like
+    /// `[Staging] {title}`.
+    #[serde(default)]
+    pub title_template: Option<String>,
+}
+
+impl ContentSettings {
+    /// `title` through `title_template`.
+    pub fn page_title(&self, title: &str) -> String {
+        match &self.title_template {
+            Some(template) => template.replace("{title}", title),
+            None => title.to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CiSettings {
+    /// Whether `--ci` runs fail when a conversion raised warnings.
+    #[serde(default = "default_true")]
+    pub fail_on_warnings: bool,
+}
+
+impl Default for CiSettings {
+    fn default() -> Self {
+        CiSettings {
+            fail_on_warnings: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SyncSettings {
+    /// Where the sync state of a shipped directory is kept, relative to that directory.
+    /// Defaults to `.notation/state.json`.
+    pub state_file: Option<String>,
+    /// How many pages a ship writes the content of at the same time. The blocks of one page
+    /// are always appended in order.
+    #[serde(default = "default_sync_concurrency")]
+    pub concurrency: usize,
+}
+
+impl Default for SyncSettings {
+    fn default() -> Self {
+        SyncSettings {
+            state_file: None,
+            concurrency: default_sync_concurrency(),
+        }
+    }
+}
+
+fn default_sync_concurrency() -> usize {
+    1
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CodeCheckSettings {
+    /// The command `--check-code` runs on the fenced code blocks of a language, keyed by the
+    /// language. `{file}` becomes the file holding the block, which is appended when missing.
+    #[serde(default)]
+    pub commands: HashMap<String, String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LintSettings {
+    #[serde(default = "default_true")]
+    pub trailing_whitespace: bool,
+    /// Two spaces between words, outside of indentation and tables.
+    #[serde(default = "default_true")]
+    pub double_spaces: bool,
+    /// Words and phrases reported wherever they show up in the prose, regardless of case.
+    #[serde(default)]
+    pub banned_words: Vec<String>,
+    /// A hunspell dictionary, like `en_US`, to spellcheck with. Needs `hunspell` installed.
+    #[serde(default)]
+    pub hunspell: Option<String>,
+    /// Words the spellcheck accepts, like product names.
+    #[serde(default)]
+    pub allowed_words: Vec<String>,
+    /// Whether `ship` lints first, and ships nothing when the lint found issues.
+    #[serde(default)]
+    pub block_ship: bool,
+}
+
+impl Default for LintSettings {
+    fn default() -> Self {
+        LintSettings {
+            trailing_whitespace: true,
+            double_spaces: true,
+            banned_words: vec![],
+            hunspell: None,
+            allowed_words: vec![],
+            block_ship: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LinkCheckSettings {
+    /// How many links are requested at the same time.
+    #[serde(default = "default_link_concurrency")]
+    pub concurrency: usize,
+    #[serde(default = "default_link_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Glob patterns of URLs never requested, like hosts that need a login.
+    #[serde(default)]
+    pub allow: Vec<String>,
+}
+
+impl Default for LinkCheckSettings {
+    fn default() -> Self {
+        LinkCheckSettings {
+            concurrency: default_link_concurrency(),
+            timeout_secs: default_link_timeout_secs(),
+            allow: vec![],
+        }
+    }
+}
+
+fn default_link_concurrency() -> usize {
+    8
+}
+
+fn default_link_timeout_secs() -> u64 {
+    10
+}
+
+pub const DEFAULT_REPORT_PATH: &str = "notation-report.json";
+pub const DEFAULT_FOOTER_TEMPLATE: &str = "Source: {path} · commit {commit} · shipped {timestamp}";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FooterSettings {
+    /// Whether every shipped page ends with a divider and a footer line.
+    #[serde(default)]
+    pub enabled: bool,
+    /// The footer line, where `{path}`, `{commit}` and `{timestamp}` become the source file,
+    /// the short commit SHA and the time of the ship.
+    #[serde(default = "default_footer_template")]
+    pub template: String,
+}
+
+impl Default for FooterSettings {
+    fn default() -> Self {
+        FooterSettings {
+            enabled: false,
+            template: default_footer_template(),
+        }
+    }
+}
+
+/// A local index of the pages the integration can see, written by `notation index refresh`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct IndexSettings {
+    /// Look the parent page up in the index, and read edit times from it in `status`, instead
+    /// of asking Notion every time.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Where the index is kept, relative to the working directory. Defaults to
+    /// `~/.notation/index-<workspace>.json`, one per integration secret.
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
+/// The templates `notation new` starts pages from.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TemplateSettings {
+    /// The directory of `<name>.md` templates, relative to the working directory. Defaults to
+    /// `~/.notation/templates`.
+    #[serde(default)]
+    pub dir: Option<String>,
+    /// Values of template variables every new page gets, like `team = "Docs"`.
+    #[serde(default)]
+    pub variables: BTreeMap<String, String>,
+}
+
+impl TemplateSettings {
+    /// The directory of the templates, `None` when there is no home directory to default to.
+    pub fn dir(&self) -> Option<PathBuf> {
+        match &self.dir {
+            Some(dir) => Some(PathBuf::from(dir)),
+            None => dirs::home_dir().map(|h| h.join(".notation").join("templates")),
+        }
+    }
+}
+
+/// The JSON report `ship` writes once it's done, to archive publishing runs.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReportSettings {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Where the report is written, relative to the working directory.
+    #[serde(default = "default_report_path")]
+    pub path: String,
+}
+
+impl Default for ReportSettings {
+    fn default() -> Self {
+        ReportSettings {
+            enabled: true,
+            path: default_report_path(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ServeSettings {
+    /// The bearer token requests to `notation serve` need, when set.
+    #[serde(default)]
+    pub token: Option<String>,
+    /// The secret of the GitHub webhook POSTing pushes to `/github`, which only takes
+    /// requests when set.
+    #[serde(default)]
+    pub github_secret: Option<String>,
+    /// The branch whose pushes are shipped, the default branch of the repository when unset.
+    #[serde(default)]
+    pub branch: Option<String>,
+    /// Whether to `git pull` the checkout before shipping a push.
+    #[serde(default = "default_true")]
+    pub pull: bool,
+}
+
+impl Default for ServeSettings {
+    fn default() -> Self {
+        ServeSettings {
+            token: None,
+            github_secret: None,
+            branch: None,
+            pull: true,
+        }
+    }
+}
+
+/// A webhook told about every ship, like a Slack incoming webhook.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NotifySettings {
+    /// Where the summary of a ship is POSTed, nowhere when unset.
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    #[serde(default = "default_true")]
+    pub on_success: bool,
+    #[serde(default = "default_true")]
+    pub on_failure: bool,
+}
+
+impl Default for NotifySettings {
+    fn default() -> Self {
+        NotifySettings {
+            webhook_url: None,
+            on_success: true,
+            on_failure: true,
+        }
+    }
+}
+
+/// The emojis pages that don't declare one get, under `[emoji]`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct EmojiSettings {
+    /// Emojis by directory, relative to the shipped directory, for its page and the pages
+    /// under it. The deepest directory wins.
+    #[serde(default)]
+    pub directories: BTreeMap<String, String>,
+    /// Whether the other pages get an emoji picked from `pool` by their title, the same one
+    /// from ship to ship.
+    #[serde(default)]
+    pub hash_titles: bool,
+    /// The emojis titles are hashed into, a built-in list of book and tool emojis when empty.
+    #[serde(default)]
+    pub pool: Vec<String>,
+    /// The emoji of the pages no rule gave one.
+    #[serde(default)]
+    pub default: Option<String>,
+}
+
+pub const DEFAULT_EMOJI_POOL: [&str; 16] = [
+    "📘", "📗", "📙", "📕", "📓", "📒", "🧭", "🧩", "🛠️", "⚙️", "🚀", "💡", "🗂️", "🔍", "🧪", "📦",
+];
+
+impl EmojiSettings {
+    /// The emoji of the page of `relative_path`, a file or a directory, titled `title`.
+    pub fn emoji_for(&self, relative_path: &Path, title: &str) -> Option<String> {
+        let by_directory = self
+            .directories
+            .iter()
+            .filter(|(d, _)| relative_path.starts_with(d.trim_matches('/')))
+            .max_by_key(|(d, _)| Path::new(d.trim_matches('/')).components().count())
+            .map(|(_, e)| e.clone());
+        by_directory
+            .or_else(|| self.hash_titles.then(|| self.hashed(title)).flatten())
+            .or_else(|| self.default.clone())
+    }
+
+    fn hashed(&self, title: &str) -> Option<String> {
+        let pool: Vec<&str> = if self.pool.is_empty() {
+            DEFAULT_EMOJI_POOL.to_vec()
+        } else {
+            self.pool.iter().map(|e| e.as_str()).collect()
+        };
+        pick(title, &pool).map(|e| e.to_string())
+    }
+}
+
+/// One of `options`, picked by the hash of `key`.
+fn pick<'a>(key: &str, options: &[&'a str]) -> Option<&'a str> {
+    let hash = openssl::sha::sha256(key.as_bytes());
+    let index = u64::from_be_bytes(hash[..8].try_into().ok()?) % options.len().max(1) as u64;
+    options.get(index as usize).copied()
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CoverStyle {
+    #[default]
+    None,
+    /// One of Notion's solid color covers.
+    Solid,
+    /// One of Notion's gradient covers.
+    Gradient,
+}
+
+const NOTION_COVERS_URL: &str = "https://www.notion.so/images/page-cover";
+const SOLID_COVERS: [&str; 4] = ["solid_red.png", "solid_yellow.png", "solid_blue.png", "solid_beige.png"];
+const GRADIENT_COVERS: [&str; 5] = [
+    "gradients_2.png",
+    "gradients_3.png",
+    "gradients_4.png",
+    "gradients_5.png",
+    "gradients_8.png",
+];
+
+/// The covers of shipped pages, under `[cover]`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CoverSettings {
+    /// The kind of cover picked by the page's title, the same one from ship to ship.
+    #[serde(default)]
+    pub style: CoverStyle,
+    /// The URL of every page's cover, where `{title}` becomes the page's title, URL encoded.
+    /// Wins over `style`.
+    #[serde(default)]
+    pub template: Option<String>,
+}
+
+impl CoverSettings {
+    /// The URL of the cover of the page titled `title`, if it gets one.
+    pub fn url_for(&self, title: &str) -> Option<String> {
+        if let Some(template) = &self.template {
+            let title = percent_encoding::utf8_percent_encode(title, percent_encoding::NON_ALPHANUMERIC);
+            return Some(template.replace("{title}", &title.to_string()));
+        }
+        let covers: &[&str] = match self.style {
+            CoverStyle::None => return None,
+            CoverStyle::Solid => &SOLID_COVERS,
+            CoverStyle::Gradient => &GRADIENT_COVERS,
+        };
+        pick(title, covers).map(|c| format!("{}/{}", NOTION_COVERS_URL, c))
+    }
+}
+
+/// A database the top level pages of a ship are created in, as its rows, under `[database]`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DatabaseSettings {
+    /// The id of the database, from its URL. Top level pages go under the parent page when
+    /// unset.
+    #[serde(default)]
+    pub id: Option<String>,
+    /// The rich text property of the rows the first paragraph of their page is written to,
+    /// like `Description`.
+    #[serde(default)]
+    pub description_property: Option<String>,
+    /// The most characters of that excerpt.
+    #[serde(default = "default_description_length")]
+    pub description_length: usize,
+    /// The multi-select property of the rows the `tags:` of their page are written to, like
+    /// `Tags`. The options the database doesn't have yet are added to it.
+    #[serde(default)]
+    pub tags_property: Option<String>,
+    /// Takes the `#tags` of the text of a page as tags too.
+    #[serde(default)]
+    pub hashtags: bool,
+    /// The date property of the rows stamped with when their page was last updated, like
+    /// `Last Updated`.
+    #[serde(default)]
+    pub updated_property: Option<String>,
+    #[serde(default)]
+    pub updated_from: UpdatedFrom,
+    /// The rich text property of the rows the `version:` of their page is written to, like
+    /// `Version` for the releases of `notation changelog`.
+    #[serde(default)]
+    pub version_property: Option<String>,
+    /// The date property of the rows the `date:` of their page, like `2024-05-01`, is written to.
+    #[serde(default)]
+    pub date_property: Option<String>,
+    /// The select property of the rows the `status:` of their page is written to, like
+    /// `Status` for the records of `notation adr`.
+    #[serde(default)]
+    pub status_property: Option<String>,
+}
+
+/// Where the last updated date of a database row comes from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdatedFrom {
+    /// The time of the ship that last changed the page.
+    #[default]
+    Ship,
+    /// The date of the last commit changing the page's file, the ship's when it has none.
+    Commit,
+}
+
+impl Default for DatabaseSettings {
+    fn default() -> Self {
+        DatabaseSettings {
+            id: None,
+            description_property: None,
+            description_length: default_description_length(),
+            tags_property: None,
+            hashtags: false,
+            updated_property: None,
+            updated_from: UpdatedFrom::default(),
+            version_property: None,
+            date_property: None,
+            status_property: None,
+        }
+    }
+}
+
+fn default_description_length() -> usize {
+    200
+}
+
+/// What is taken out of pages before their blocks are built. Sections marked
+/// `<!-- private -->` always are.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RedactSettings {
+    /// Regular expressions of text masked wherever it appears, like API keys or internal
+    /// hostnames.
+    #[serde(default)]
+    pub patterns: Vec<String>,
+    /// What masked text is replaced with.
+    #[serde(default = "default_redact_mask")]
+    pub mask: String,
+    /// Headings whose sections are left out, matched without regard to case.
+    #[serde(default)]
+    pub headings: Vec<String>,
+}
+
+impl Default for RedactSettings {
+    fn default() -> Self {
+        RedactSettings {
+            patterns: vec![],
+            mask: default_redact_mask(),
+            headings: vec![],
+        }
+    }
+}
+
+fn default_redact_mask() -> String {
+    REDACTED.to_string()
+}
+
+fn default_report_path() -> String {
+    DEFAULT_REPORT_PATH.to_string()
+}
+
+fn default_footer_template() -> String {
+    DEFAULT_FOOTER_TEMPLATE.to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// How headings deeper than Notion's three levels are rendered.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HeadingStrategy {
+    /// `####` and deeper become `heading_3` blocks.
+    #[default]
+    Clamp,
+    /// `####` and deeper become bold paragraphs.
+    Paragraph,
+}
+
+/// How SVG images are shipped, since Notion doesn't always render them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SvgStrategy {
+    /// As an image like any other.
+    #[default]
+    PassThrough,
+    /// Rendered to a PNG that is uploaded to Notion. Needs the `svg` feature.
+    Png,
+    /// As a file block, which Notion shows as a download.
+    File,
+}
+
+/// What happens to markdown notation has no conversion for, like block quotes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnsupportedNodePolicy {
+    /// Left out silently.
+    Ignore,
+    /// Left out, with a warning naming the node and where it is.
+    #[default]
+    Warn,
+    /// Fails the conversion.
+    Error,
+    /// Shipped as a code block holding its markdown.
+    #[serde(alias = "render-as-code")]
+    RenderAsCode,
+}
+
+/// How an image wrapped in a link, like a README badge (`[![build](badge.svg)](ci-url)`), is
+/// shipped, since Notion images can't link anywhere.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkedImageStrategy {
+    /// As an image whose caption, its alt text, links to the target.
+    #[default]
+    Caption,
+    /// As its alt text, linking to the target, in the paragraph around it.
+    Text,
+}
+
+/// What a relative link to a file out of the shipped directory, like
+/// `../../other-repo/readme.md`, is shipped as.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutsideLinkPolicy {
+    /// Nothing, the conversion fails.
+    #[default]
+    Error,
+    /// Its text, without the link, with a warning.
+    Text,
+    /// A link to the file under `[content] link_base_url`, with a warning.
+    Rewrite,
 }
 
+/// What a newline inside a paragraph, a soft break, becomes in Notion.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineBreakPolicy {
+    /// A space, so the paragraph reflows like it does on GitHub.
+    #[default]
+    Reflow,
+    /// A line break, for files written with one sentence or clause per line.
+    Preserve,
+}
+
+/// What a ship does with symlinks in the shipped tree.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SymlinkPolicy {
+    /// Ships the files they point to, and resolves links through them to the same pages.
+    #[default]
+    Follow,
+    /// Leaves out the files reached through one.
+    Skip,
+}
+
+/// Where the title of a page comes from when its file has no `--title` argument.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TitleStrategy {
+    /// The file name without its extension.
+    #[default]
+    FileStem,
+    /// The first `#` heading, which is left out of the page, or the file stem when there is
+    /// none.
+    FirstHeading,
+}
+
+pub const CONFIG_PATH_ENV: &str = "NOTATION_CONFIG";
+pub const SECRET_ENV: &str = "NOTATION_NOTION__SECRET";
+pub const PARENT_PAGE_ENV: &str = "NOTATION_NOTION__PARENT_PAGE";
+
 impl NotationSettings {
+    /// Reads `$NOTATION_CONFIG` (or `~/.notation/Notation.toml`), overridden by `NOTATION_*`
+    /// environment variables, where `__` separates nested keys (`NOTATION_NOTION__SECRET`).
+    /// The file is optional when both the secret and the parent page come from the environment.
     pub fn new() -> Result<Self> {
-        let config_path = env::var("NOTATION_CONFIG").unwrap_or_else(|_| {
-            let mut home_dir = dirs::home_dir().expect("Could not find home directory");
-            home_dir.push(".notation/Notation.toml");
-            home_dir.to_str().unwrap().to_string()
-        });
-        let path_buf = std::path::PathBuf::from(&config_path);
-        let s = Config::builder()
-            .add_source(config::File::from(path_buf))
-            .add_source(config::Environment::with_prefix("NOTATION"))
-            .build()?;
+        Self::from_env(&env::vars().collect())
+    }
+
+    /// Like [`NotationSettings::new`], with `vars` in place of the environment of the process.
+    pub fn from_env(vars: &HashMap<String, String>) -> Result<Self> {
+        let s = Self::load_config(vars)?;
         let result: Self = s.try_deserialize()?;
         Ok(result)
     }
+
+    /// The settings of a ship to `destination`: its parent page, token and sync state, with
+    /// everything else shared.
+    pub fn for_destination(&self, destination: &Destination) -> Self {
+        let mut settings = self.clone();
+        settings.notion.parent_page = destination.parent_page.clone();
+        settings.notion.parent_page_id = None;
+        if let Some(secret) = &destination.secret {
+            settings.notion.secret = secret.clone();
+        }
+        settings.sync.state_file = Some(
+            destination
+                .state_file
+                .clone()
+                .unwrap_or_else(|| format!(".notation/state-{}.json", destination.name)),
+        );
+        if let Some(template) = &destination.title_template {
+            settings.content.title_template = Some(template.clone());
+        }
+        settings.destinations.clear();
+        settings.locales.clear();
+        settings
+    }
+
+    /// The configured destinations named in `names`, or all of them when `names` is empty.
+    pub fn destinations(&self, names: &[String]) -> Result<Vec<&Destination>> {
+        let mut seen = HashSet::new();
+        for d in self.destinations.iter() {
+            if d.name.is_empty() {
+                return Err(anyhow!("destination of parent page \"{}\" has no name", d.parent_page));
+            }
+            if !seen.insert(d.name.as_str()) {
+                return Err(anyhow!("more than one destination is named \"{}\"", d.name));
+            }
+        }
+        if let Some(unknown) = names.iter().find(|n| !seen.contains(n.as_str())) {
+            return Err(anyhow!("no destination named \"{}\" under [[destinations]]", unknown));
+        }
+        Ok(self
+            .destinations
+            .iter()
+            .filter(|d| names.is_empty() || names.contains(&d.name))
+            .collect())
+    }
+
+    /// The configured locales named in `names`, or all of them when `names` is empty.
+    pub fn locales(&self, names: &[String]) -> Result<Vec<&Locale>> {
+        let mut seen = HashSet::new();
+        for l in self.locales.iter() {
+            if l.name.is_empty() {
+                return Err(anyhow!("locale of parent page \"{}\" has no name", l.parent_page));
+            }
+            if !seen.insert(l.name.as_str()) {
+                return Err(anyhow!("more than one locale is named \"{}\"", l.name));
+            }
+        }
+        if let Some(unknown) = names.iter().find(|n| !seen.contains(n.as_str())) {
+            return Err(anyhow!("no locale named \"{}\" under [[locales]]", unknown));
+        }
+        Ok(self
+            .locales
+            .iter()
+            .filter(|l| names.is_empty() || names.contains(&l.name))
+            .collect())
+    }
+
+    pub fn config_path(vars: &HashMap<String, String>) -> Option<PathBuf> {
+        vars.get(CONFIG_PATH_ENV)
+            .map(PathBuf::from)
+            .or_else(|| dirs::home_dir().map(|h| h.join(".notation/Notation.toml")))
+    }
+
+    pub fn is_env_only(vars: &HashMap<String, String>) -> bool {
+        vars.contains_key(SECRET_ENV) && vars.contains_key(PARENT_PAGE_ENV)
+    }
+
+    /// The config file, with the `NOTATION_` variables of `vars` over it.
+    pub fn load_config(vars: &HashMap<String, String>) -> Result<Config> {
+        let env_only = Self::is_env_only(vars);
+        let mut builder = Config::builder();
+        match Self::config_path(vars) {
+            Some(path_buf) => {
+                builder = builder.add_source(config::File::from(path_buf).required(!env_only));
+            }
+            None if !env_only => {
+                return Err(anyhow!(
+                    "could not find a home directory for ~/.notation/Notation.toml, set {} or both {} and {}",
+                    CONFIG_PATH_ENV,
+                    SECRET_ENV,
+                    PARENT_PAGE_ENV
+                ));
+            }
+            None => {}
+        }
+        let s = builder
+            .add_source(
+                config::Environment::with_prefix("NOTATION")
+                    .prefix_separator("_")
+                    .separator("__")
+                    .source(Some(vars.clone().into_iter().collect())),
+            )
+            .build()?;
+        Ok(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::Path;
+
+    use crate::settings::notation::{
+        CoverSettings, CoverStyle, EmojiSettings, HeadingStrategy, NotationSettings, CONFIG_PATH_ENV, PARENT_PAGE_ENV, SECRET_ENV,
+    };
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_env_only_settings() {
+        let mut vars = HashMap::from([
+            (CONFIG_PATH_ENV.to_string(), "/nonexistent/Notation.toml".to_string()),
+            (SECRET_ENV.to_string(), "secret_abc".to_string()),
+            (PARENT_PAGE_ENV.to_string(), "Engineering Docs".to_string()),
+            ("NOTATION_CONTENT__HEADING_STRATEGY".to_string(), "paragraph".to_string()),
+        ]);
+        let settings = NotationSettings::from_env(&vars).unwrap();
+        assert_eq!(settings.notion.secret, "secret_abc");
+        assert_eq!(settings.notion.parent_page, "Engineering Docs");
+        assert_eq!(settings.content.heading_strategy, HeadingStrategy::Paragraph);
+
+        vars.remove(PARENT_PAGE_ENV);
+        assert!(!NotationSettings::is_env_only(&vars));
+        assert!(NotationSettings::from_env(&vars).is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_destinations() {
+        let settings: NotationSettings = serde_json::from_value(serde_json::json!({
+            "notion": {"secret": "secret_main", "parent_page": "Docs"},
+            "destinations": [
+                {"name": "staging", "parent_page": "Staging Docs", "title_template": "[Staging] {title}"},
+                {"name": "public", "parent_page": "Docs", "secret": "secret_public", "state_file": "public.json"}
+            ]
+        }))
+        .unwrap();
+        assert_eq!(settings.destinations(&[]).unwrap().len(), 2);
+        let public = settings.destinations(&["public".to_string()]).unwrap();
+        assert_eq!(public.len(), 1);
+        assert!(settings.destinations(&["prod".to_string()]).is_err());
+
+        let staging = settings.for_destination(&settings.destinations[0]);
+        assert_eq!(staging.notion.parent_page, "Staging Docs");
+        assert_eq!(staging.notion.secret, "secret_main");
+        assert_eq!(staging.sync.state_file.as_deref(), Some(".notation/state-staging.json"));
+        assert!(staging.destinations.is_empty());
+        assert_eq!(staging.content.page_title("Guide"), "[Staging] Guide");
+        let public = settings.for_destination(public[0]);
+        assert_eq!(public.notion.secret, "secret_public");
+        assert_eq!(public.sync.state_file.as_deref(), Some("public.json"));
+        assert_eq!(public.content.page_title("Guide"), "Guide");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_emoji_for() {
+        let settings: EmojiSettings = serde_json::from_value(serde_json::json!({
+            "directories": {"api": "🔌", "api/internal": "🔒"},
+            "hash_titles": true,
+            "pool": ["📘", "📗", "📙"],
+        }))
+        .unwrap();
+        assert_eq!(settings.emoji_for(Path::new("api"), "API").as_deref(), Some("🔌"));
+        assert_eq!(settings.emoji_for(Path::new("api/auth.md"), "Auth").as_deref(), Some("🔌"));
+        assert_eq!(settings.emoji_for(Path::new("api/internal/keys.md"), "Keys").as_deref(), Some("🔒"));
+        assert_eq!(settings.emoji_for(Path::new("apis.md"), "APIs"), settings.emoji_for(Path::new("b.md"), "APIs"));
+        let hashed = settings.emoji_for(Path::new("guide.md"), "Guide").unwrap();
+        assert!(settings.pool.contains(&hashed));
+
+        let settings = EmojiSettings {
+            default: Some("📄".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(settings.emoji_for(Path::new("guide.md"), "Guide").as_deref(), Some("📄"));
+        assert_eq!(EmojiSettings::default().emoji_for(Path::new("guide.md"), "Guide"), None);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_cover_url() {
+        let gradient = CoverSettings {
+            style: CoverStyle::Gradient,
+            ..Default::default()
+        };
+        let url = gradient.url_for("Guide").unwrap();
+        assert!(url.starts_with("https://www.notion.so/images/page-cover/gradients_"));
+        assert_eq!(gradient.url_for("Guide").unwrap(), url);
+        assert_eq!(CoverSettings::default().url_for("Guide"), None);
+
+        let template = CoverSettings {
+            style: CoverStyle::Solid,
+            template: Some("https://covers.example.com/{title}.png".to_string()),
+        };
+        assert_eq!(
+            template.url_for("API & Auth").as_deref(),
+            Some("https://covers.example.com/API%20%26%20Auth.png")
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_locales() {
+        let settings: NotationSettings = serde_json::from_value(serde_json::json!({
+            "notion": {"secret": "secret_main", "parent_page": "Docs"},
+            "locales": [
+                {"name": "en", "parent_page": "Docs"},
+                {"name": "ja", "parent_page": "ドキュメント", "path": "ja-JP"}
+            ]
+        }))
+        .unwrap();
+        let locales = settings.locales(&[]).unwrap();
+        assert_eq!(locales[0].src("docs"), "docs/en");
+        assert_eq!(locales[1].src("docs"), "docs/ja-JP");
+        assert!(settings.locales(&["fr".to_string()]).is_err());
+
+        let ja = settings.for_destination(&locales[1].destination());
+        assert_eq!(ja.notion.parent_page, "ドキュメント");
+        assert_eq!(ja.sync.state_file.as_deref(), Some(".notation/state-ja.json"));
+        assert!(ja.locales.is_empty());
+    }
 }
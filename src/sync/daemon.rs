@@ -0,0 +1,296 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{anyhow, Result};
+use chrono::{SecondsFormat, Utc};
+use serde_json::{json, Value};
+use tokio::sync::watch;
+
+use crate::commands::{ship, write_report, Workspace};
+use crate::notify::notify;
+use crate::notion::report::ShipReport;
+use crate::redact::redact;
+use crate::settings::directory::SourceTree;
+use crate::sync::git::head_commit;
+use crate::sync::state::SyncState;
+use crate::sync::status::ConflictPolicy;
+
+/// A lock held longer than this was left behind, no sync takes that long.
+const STALE_LOCK_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Parses an interval like `30s`, `15m`, `1h30m` or `1d`. A bare number is in seconds.
+pub fn parse_interval(interval: &str) -> Result<Duration> {
+    let interval = interval.trim();
+    let invalid = || anyhow!("invalid interval \"{}\", use a number of s, m, h or d like 15m", interval);
+    let too_long = || anyhow!("interval \"{}\" is too long", interval);
+    if let Ok(seconds) = interval.parse::<u64>() {
+        return Some(seconds).filter(|s| *s > 0).map(Duration::from_secs).ok_or_else(invalid);
+    }
+    let mut seconds = 0;
+    let mut number = String::new();
+    for c in interval.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            _ => return Err(invalid()),
+        };
+        let n: u64 = number.parse().map_err(|_| too_long())?;
+        seconds = n.checked_mul(unit).and_then(|s| s.checked_add(seconds)).ok_or_else(too_long)?;
+        number.clear();
+    }
+    if !number.is_empty() || seconds == 0 {
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Prints one JSON line, for log collectors to pick up.
+pub fn log(level: &str, event: &str, fields: Value) {
+    let mut line = json!({
+        "time": Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+        "level": level,
+        "event": event,
+    });
+    if let (Some(line), Value::Object(fields)) = (line.as_object_mut(), fields) {
+        line.extend(fields);
+    }
+    println!("{}", redact(&line.to_string()));
+}
+
+/// A lock file held for the length of a sync, so two syncs of the same directory don't
+/// overlap. It is removed when dropped.
+pub struct SyncLock {
+    path: PathBuf,
+}
+
+impl SyncLock {
+    /// Takes the lock at `path`, `None` when someone else holds it. A lock left behind by a
+    /// process that isn't running anymore, or older than a day, is taken over.
+    pub fn acquire(path: &Path) -> Result<Option<Self>> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = match create_lock(path)? {
+            Some(f) => f,
+            None if is_stale(path) => {
+                // Whoever removes it first takes it, the others find the new one held.
+                let _ = std::fs::remove_file(path);
+                match create_lock(path)? {
+                    Some(f) => f,
+                    None => return Ok(None),
+                }
+            }
+            None => return Ok(None),
+        };
+        writeln!(file, "{} {}", std::process::id(), Utc::now().to_rfc3339())?;
+        Ok(Some(SyncLock {
+            path: path.to_path_buf(),
+        }))
+    }
+}
+
+fn create_lock(path: &Path) -> Result<Option<File>> {
+    match OpenOptions::new().write(true).create_new(true).open(path) {
+        Ok(f) => Ok(Some(f)),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Ok(None),
+        Err(e) => Err(anyhow!("failed to create lock file {}: {}", path.display(), e)),
+    }
+}
+
+/// Whether the lock at `path` was left behind: the process that took it, by the id it wrote
+/// first, isn't running anymore, or it was taken longer ago than [`STALE_LOCK_AGE`].
+fn is_stale(path: &Path) -> bool {
+    let pid = std::fs::read_to_string(path)
+        .ok()
+        .and_then(|c| c.split_whitespace().next().and_then(|p| p.parse::<u32>().ok()));
+    if pid.and_then(is_running) == Some(false) {
+        return true;
+    }
+    let age = std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|m| SystemTime::now().duration_since(m).ok());
+    age.is_some_and(|a| a > STALE_LOCK_AGE)
+}
+
+/// Whether the process `pid` is running, where `/proc` tells, `None` elsewhere.
+fn is_running(pid: u32) -> Option<bool> {
+    let proc = Path::new("/proc");
+    proc.join("self").exists().then(|| proc.join(pid.to_string()).exists())
+}
+
+impl Drop for SyncLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Ships `src` every `interval`, incrementally from the commit of the last ship, until Ctrl-C
+/// or SIGTERM. A sync under way when asked to stop is finished first. Pages edited in Notion
+/// since the last ship are left as they are.
+pub async fn run(workspace: &Workspace, src: &str, interval: Duration) -> Result<()> {
+    let settings = workspace.client.settings();
+    let root = SourceTree::load(src, settings.content.clone())?.root;
+    if head_commit(&root).is_none() {
+        return Err(anyhow!("daemon ships what changed since the last ship, {} needs to be inside a git repository", root.display()));
+    }
+    let state_path = SyncState::path(&root, settings.sync.state_file.as_deref());
+    let lock_path = state_path.with_extension("lock");
+
+    let (stop, mut stopped) = watch::channel(false);
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        log("info", "shutdown_requested", json!({}));
+        let _ = stop.send(true);
+    });
+
+    log("info", "daemon_started", json!({ "src": src, "interval_seconds": interval.as_secs() }));
+    loop {
+        match SyncLock::acquire(&lock_path)? {
+            Some(_lock) => sync_once(workspace, src, &state_path).await,
+            None => log(
+                "warn",
+                "sync_skipped",
+                json!({ "reason": format!("another sync holds {}, remove it if none is running", lock_path.display()) }),
+            ),
+        }
+        if *stopped.borrow() {
+            break;
+        }
+        tokio::select! {
+            _ = stopped.changed() => break,
+            _ = tokio::time::sleep(interval) => {}
+        }
+    }
+    log("info", "daemon_stopped", json!({}));
+    Ok(())
+}
+
+/// The commit the last ship to the workspace's parent page was made from, for the next one to
+/// ship what changed since. `None` when there was none, or it wasn't in a git repository.
+pub fn last_shipped_commit(workspace: &Workspace, state_path: &Path) -> Result<Option<String>> {
+    Ok(SyncState::load(state_path)?
+        .filter(|s| s.parent_page_id == workspace.parent_page_id)
+        .and_then(|s| s.commit))
+}
+
+async fn sync_once(workspace: &Workspace, src: &str, state_path: &Path) {
+    let since = match last_shipped_commit(workspace, state_path) {
+        Ok(since) => since,
+        Err(e) => {
+            log("error", "sync_failed", json!({ "error": e.to_string() }));
+            return;
+        }
+    };
+    log("info", "sync_started", json!({ "since": since }));
+    let started = Utc::now();
+    let api = workspace.client.api_usage();
+    let outcome = ship(workspace, src.to_string(), since, ConflictPolicy::Skip).await;
+
+    let mut report = ShipReport::new(src, started);
+    report.add(
+        &workspace.client.parent_page_name(),
+        Some(workspace.parent_page_url.clone()),
+        outcome.as_ref().map_err(|e| redact(&format!("{:#}", e))),
+    );
+    let report = report.finish(Utc::now()).with_api_usage(workspace.client.api_usage() - api);
+    match &outcome {
+        Ok(summary) => log(
+            "info",
+            "sync_finished",
+            json!({
+                "pages": summary.pages.len(),
+                "unchanged": summary.unchanged.len(),
+                "skipped": summary.skipped,
+                "warnings": summary.warnings.iter().map(|w| w.to_string()).collect::<Vec<_>>(),
+                "duration_ms": report.duration_ms,
+                "api": report.api,
+            }),
+        ),
+        Err(e) => log(
+            "error",
+            "sync_failed",
+            json!({ "error": format!("{:#}", e), "duration_ms": report.duration_ms, "api": report.api }),
+        ),
+    }
+    if let Err(e) = write_report(&workspace.client, &report) {
+        log("warn", "report_failed", json!({ "error": e.to_string() }));
+    }
+    if let Err(e) = notify(&workspace.client.settings().notify, &report).await {
+        log("warn", "notify_failed", json!({ "error": e.to_string() }));
+    }
+}
+
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        if let Ok(mut terminate) = signal(SignalKind::terminate()) {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = terminate.recv() => {}
+            }
+            return;
+        }
+    }
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::time::{Duration, SystemTime};
+
+    use crate::sync::daemon::{parse_interval, SyncLock};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_parse_interval() {
+        assert_eq!(parse_interval("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_interval("15m").unwrap(), Duration::from_secs(15 * 60));
+        assert_eq!(parse_interval("1h30m").unwrap(), Duration::from_secs(90 * 60));
+        assert_eq!(parse_interval("1d").unwrap(), Duration::from_secs(24 * 60 * 60));
+        assert!(parse_interval("0").is_err());
+        assert!(parse_interval("m").is_err());
+        assert!(parse_interval("15x").is_err());
+        assert!(parse_interval("1h30").is_err());
+        assert!(parse_interval("0m").is_err());
+        assert!(parse_interval("213503982334602d").unwrap_err().to_string().contains("too long"));
+        assert!(parse_interval("18446744073709551615s1s").unwrap_err().to_string().contains("too long"));
+        assert!(parse_interval("99999999999999999999m").unwrap_err().to_string().contains("too long"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_sync_lock() {
+        let dir = std::env::temp_dir().join(format!("notation-lock-{}", std::process::id()));
+        let path = dir.join(".notation").join("state.lock");
+        let lock = SyncLock::acquire(&path).unwrap();
+        assert!(lock.is_some());
+        assert!(SyncLock::acquire(&path).unwrap().is_none());
+        drop(lock);
+        assert!(!path.exists());
+        let lock = SyncLock::acquire(&path).unwrap();
+        assert!(lock.is_some());
+        drop(lock);
+
+        // Left behind by a process that is gone, where /proc tells.
+        if Path::new("/proc/self").exists() {
+            std::fs::write(&path, format!("{} 2026-03-04T10:15:00+00:00\n", u32::MAX)).unwrap();
+            assert!(SyncLock::acquire(&path).unwrap().is_some());
+        }
+        // Held by a running process, but for more than a day.
+        std::fs::write(&path, format!("{} 2026-03-04T10:15:00+00:00\n", std::process::id())).unwrap();
+        assert!(SyncLock::acquire(&path).unwrap().is_none());
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(2 * 24 * 60 * 60)).unwrap();
+        assert!(SyncLock::acquire(&path).unwrap().is_some());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,182 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use git2::{Delta, DiffOptions, Repository, Sort};
+use tokio::process::Command;
+
+use crate::markdown::source::is_source_file;
+
+/// Computes the documentation files under `root` that were added or modified between `since` and
+/// the working tree, including uncommitted and untracked files. Paths are relative to `root`.
+/// Deleted files are left out, they are found by comparing the tree against the sync state.
+pub fn changed_since(root: &Path, since: &str) -> Result<HashSet<PathBuf>> {
+    let repo = Repository::discover(root)
+        .map_err(|e| anyhow!("--since needs {} to be inside a git repository: {}", root.display(), e.message()))?;
+    let root_in_repo = relative_to_workdir(&repo, root)?;
+    let tree = repo
+        .revparse_single(since)
+        .and_then(|o| o.peel_to_tree())
+        .map_err(|e| anyhow!("failed to resolve git ref \"{}\": {}", since, e.message()))?;
+
+    let mut diff_options = DiffOptions::new();
+    diff_options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true);
+    let diff = repo.diff_tree_to_workdir_with_index(Some(&tree), Some(&mut diff_options))?;
+
+    let mut changed = HashSet::new();
+    for delta in diff.deltas() {
+        let file = match delta.status() {
+            Delta::Added | Delta::Modified | Delta::Renamed | Delta::Copied | Delta::Untracked
+            | Delta::Typechange => delta.new_file(),
+            _ => continue,
+        };
+        let path = match file.path() {
+            Some(p) => p,
+            None => continue,
+        };
+        if !is_source_file(path) {
+            continue;
+        }
+        if let Ok(relative) = path.strip_prefix(&root_in_repo) {
+            changed.insert(relative.to_path_buf());
+        }
+    }
+    Ok(changed)
+}
+
+/// The working directory of the repository containing `path`, if any.
+pub fn repository_root(path: &Path) -> Option<PathBuf> {
+    let repo = Repository::discover(path).ok()?;
+    repo.workdir().map(|w| w.to_path_buf())
+}
+
+/// The commit checked out in the repository containing `root`, if any.
+pub fn head_commit(root: &Path) -> Option<String> {
+    let repo = Repository::discover(root).ok()?;
+    let commit = repo.head().ok()?.peel_to_commit().ok()?;
+    Some(commit.id().to_string())
+}
+
+/// The contents of `relative`, a path relative to `root`, at `commit`. `None` when the file
+/// wasn't in that commit.
+pub fn file_at(root: &Path, commit: &str, relative: &Path) -> Result<Option<String>> {
+    let repo = Repository::discover(root)
+        .map_err(|e| anyhow!("{} is not inside a git repository: {}", root.display(), e.message()))?;
+    let path = relative_to_workdir(&repo, root)?.join(relative);
+    let tree = repo
+        .revparse_single(commit)
+        .and_then(|o| o.peel_to_tree())
+        .map_err(|e| anyhow!("failed to resolve git ref \"{}\": {}", commit, e.message()))?;
+    let entry = match tree.get_path(&path) {
+        Ok(entry) => entry,
+        Err(_) => return Ok(None),
+    };
+    let blob = entry.to_object(&repo)?.peel_to_blob()?;
+    Ok(Some(String::from_utf8_lossy(blob.content()).into_owned()))
+}
+
+/// When `relative`, a path relative to `root`, was last changed by a commit reachable from
+/// the one checked out. `None` when it was never committed.
+pub fn last_commit_time(root: &Path, relative: &Path) -> Result<Option<DateTime<Utc>>> {
+    let repo = Repository::discover(root)
+        .map_err(|e| anyhow!("{} is not inside a git repository: {}", root.display(), e.message()))?;
+    let path = relative_to_workdir(&repo, root)?.join(relative);
+    let mut walk = repo.revwalk()?;
+    if walk.push_head().is_err() {
+        return Ok(None);
+    }
+    walk.set_sorting(Sort::TIME)?;
+    for oid in walk {
+        let commit = repo.find_commit(oid?)?;
+        let entry = match commit.tree()?.get_path(&path) {
+            Ok(entry) => entry.id(),
+            Err(_) => continue,
+        };
+        // A commit changed the file when it differs from every parent, merges included.
+        let is_changed = commit.parents().all(|p| {
+            p.tree()
+                .ok()
+                .and_then(|t| t.get_path(&path).ok())
+                .is_none_or(|e| e.id() != entry)
+        });
+        if is_changed {
+            return Ok(DateTime::from_timestamp(commit.time().seconds(), 0));
+        }
+    }
+    Ok(None)
+}
+
+/// Where `root` is in the working directory of the repository containing it.
+pub fn path_in_repository(root: &Path) -> Result<PathBuf> {
+    let repo = Repository::discover(root)
+        .map_err(|e| anyhow!("{} is not inside a git repository: {}", root.display(), e.message()))?;
+    relative_to_workdir(&repo, root)
+}
+
+/// Fast-forwards the checkout containing `root` to its upstream branch.
+pub async fn pull(root: &Path) -> Result<()> {
+    let output = Command::new("git")
+        .args(["pull", "--ff-only"])
+        .current_dir(root)
+        .output()
+        .await
+        .map_err(|e| anyhow!("failed to run `git pull`: {}", e))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "`git pull --ff-only` exited with {}: {}",
+        output.status,
+        String::from_utf8_lossy(&output.stderr).trim()
+    ))
+}
+
+fn relative_to_workdir(repo: &Repository, root: &Path) -> Result<PathBuf> {
+    let workdir = repo
+        .workdir()
+        .ok_or(anyhow!("--since does not support bare git repositories"))?
+        .canonicalize()?;
+    let root = root.canonicalize()?;
+    Ok(root.strip_prefix(&workdir)?.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use git2::{Repository, Signature, Time};
+
+    use crate::sync::git::last_commit_time;
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_last_commit_time() {
+        let dir = std::env::temp_dir().join(format!("notation-last-commit-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("docs")).unwrap();
+        let repo = Repository::init(&dir).unwrap();
+        let commit = |file: &str, seconds: i64| {
+            std::fs::write(dir.join("docs").join(file), format!("# {}\n", seconds)).unwrap();
+            let mut index = repo.index().unwrap();
+            index.add_path(&Path::new("docs").join(file)).unwrap();
+            index.write().unwrap();
+            let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+            let signature = Signature::new("Docs", "docs@example.com", &Time::new(seconds, 0)).unwrap();
+            let parents: Vec<_> = repo.head().ok().map(|h| h.peel_to_commit().unwrap()).into_iter().collect();
+            let parents: Vec<_> = parents.iter().collect();
+            repo.commit(Some("HEAD"), &signature, &signature, file, &tree, &parents).unwrap();
+        };
+        commit("a.md", 1_700_000_000);
+        commit("b.md", 1_700_100_000);
+        commit("a.md", 1_700_200_000);
+        commit("c.md", 1_700_300_000);
+
+        let root = dir.join("docs");
+        let time = |file: &str| last_commit_time(&root, Path::new(file)).unwrap().map(|t| t.timestamp());
+        assert_eq!(time("a.md"), Some(1_700_200_000));
+        assert_eq!(time("b.md"), Some(1_700_100_000));
+        assert_eq!(time("missing.md"), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,342 @@
+use anyhow::{anyhow, Result};
+use markdown::mdast::Node;
+use markdown::ParseOptions;
+use similar::{capture_diff_slices, Algorithm, DiffOp};
+
+use crate::markdown::diagnostic::Diagnostic;
+use crate::markdown::parse::parse_file;
+use crate::notion::block::{AppendBlockRequestChild, BlockType};
+use crate::notion::client::NotionClient;
+use crate::roundtrip::{block_elements, is_arguments_paragraph, node_elements, Element};
+use crate::settings::directory::SourceTree;
+use crate::settings::notation::TitleStrategy;
+use crate::sync::git::file_at;
+use crate::sync::state::SyncState;
+use crate::sync::status::is_edited_after;
+
+pub const LOCAL_MARKER: &str = "<<<<<<< local";
+pub const BASE_MARKER: &str = "||||||| shipped";
+pub const SEPARATOR_MARKER: &str = "=======";
+pub const NOTION_MARKER: &str = ">>>>>>> notion";
+
+/// A top-level block of a page, compared across versions by its elements.
+#[derive(Clone, Debug)]
+pub struct MergeBlock {
+    pub elements: Vec<Element>,
+    /// What sits between the block before and this one, like blank lines, and nodes Notion
+    /// doesn't show, like HTML comments.
+    pub prefix: String,
+    pub source: String,
+}
+
+/// The blocks of a markdown document, and what follows the last one.
+#[derive(Clone, Debug, Default)]
+pub struct MarkdownBlocks {
+    pub blocks: Vec<MergeBlock>,
+    pub tail: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct MergeResult {
+    pub text: String,
+    pub conflicts: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct MergedFile {
+    pub path: String,
+    pub conflicts: usize,
+    /// Whether the file was rewritten.
+    pub is_changed: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MergeReport {
+    pub files: Vec<MergedFile>,
+    /// Files whose page was edited in Notion but that couldn't be merged.
+    pub skipped: Vec<Diagnostic>,
+}
+
+/// Splits markdown into blocks at its top-level nodes. Nodes Notion never shows, like the
+/// arguments line, frontmatter and comments, go along with the next block. With
+/// `skip_title`, so does the first `#` heading, which became the page title.
+pub fn markdown_blocks(contents: &str, skip_title: bool) -> Result<MarkdownBlocks> {
+    let mut options = ParseOptions::gfm();
+    options.constructs.frontmatter = true;
+    let root = markdown::to_mdast(contents, &options).map_err(|e| anyhow!(e))?;
+    let mut blocks = Vec::new();
+    let mut start = 0;
+    let mut is_title_skipped = !skip_title;
+    for (i, node) in root.children().into_iter().flatten().enumerate() {
+        let (node_start, end) = match node.position() {
+            Some(p) => (p.start.offset, p.end.offset),
+            None => continue,
+        };
+        let is_title = !is_title_skipped && matches!(node, Node::Heading(h) if h.depth == 1);
+        is_title_skipped |= is_title;
+        if is_title || (i == 0 && is_arguments_paragraph(node)) {
+            continue;
+        }
+        let elements = node_elements(node).blocks;
+        if elements.is_empty() {
+            continue;
+        }
+        // Lists can end with a line ending, which belongs between the blocks.
+        let source = contents[node_start..end].trim_end();
+        blocks.push(MergeBlock {
+            elements,
+            prefix: contents[start..node_start].to_string(),
+            source: source.to_string(),
+        });
+        start = node_start + source.len();
+    }
+    Ok(MarkdownBlocks {
+        blocks,
+        tail: contents[start..].to_string(),
+    })
+}
+
+/// The blocks of a page read back from Notion, with list items grouped into lists like
+/// markdown has them.
+pub fn notion_blocks(blocks: &[AppendBlockRequestChild]) -> Vec<MergeBlock> {
+    let mut grouped: Vec<Vec<Element>> = Vec::new();
+    for e in block_elements(blocks).blocks {
+        let is_list_item = matches!(e.kind, "bulleted_list_item" | "numbered_list_item");
+        match grouped.last_mut() {
+            Some(last) if is_list_item && last[0].kind == e.kind => last.push(e),
+            _ => grouped.push(vec![e]),
+        }
+    }
+    grouped
+        .into_iter()
+        .map(|elements| {
+            let markdown: Vec<String> = elements.iter().map(Element::to_markdown).collect();
+            MergeBlock {
+                elements,
+                prefix: "\n\n".to_string(),
+                source: markdown.join("\n"),
+            }
+        })
+        .collect()
+}
+
+/// Merges the changes made in Notion since `base` was shipped into `local`, block by block.
+/// Blocks changed on one side only take that side; blocks changed differently on both are
+/// written with conflict markers.
+pub fn merge(base: &MarkdownBlocks, local: &MarkdownBlocks, notion: &[MergeBlock]) -> MergeResult {
+    let keys = |blocks: &[MergeBlock]| -> Vec<Vec<Element>> {
+        blocks.iter().map(|b| b.elements.clone()).collect()
+    };
+    let (base_keys, local_keys, notion_keys) = (keys(&base.blocks), keys(&local.blocks), keys(notion));
+    let local_matches = matches(&base_keys, &local_keys);
+    let notion_matches = matches(&base_keys, &notion_keys);
+
+    let mut text = String::new();
+    let mut conflicts = 0;
+    let (mut i, mut a, mut b) = (0, 0, 0);
+    loop {
+        while i < base_keys.len() && local_matches[i] == Some(a) && notion_matches[i] == Some(b) {
+            text.push_str(&concat(&local.blocks[a..=a]));
+            i += 1;
+            a += 1;
+            b += 1;
+        }
+        let next = (i..base_keys.len()).find_map(|j| match (local_matches[j], notion_matches[j]) {
+            (Some(x), Some(y)) if x >= a && y >= b => Some((j, x, y)),
+            _ => None,
+        });
+        let (j, x, y) = next.unwrap_or((base_keys.len(), local_keys.len(), notion_keys.len()));
+        let (base_chunk, local_chunk, notion_chunk) =
+            (&base.blocks[i..j], &local.blocks[a..x], &notion[b..y]);
+        if local_keys[a..x] == base_keys[i..j] {
+            text.push_str(&pinned(local_chunk));
+            text.push_str(&concat(notion_chunk));
+        } else if notion_keys[b..y] == base_keys[i..j] || local_keys[a..x] == notion_keys[b..y] {
+            text.push_str(&concat(local_chunk));
+        } else {
+            conflicts += 1;
+            text.push_str(&pinned(local_chunk));
+            text.push_str(&format!(
+                "\n\n{}\n{}\n{}\n{}\n{}\n{}\n{}",
+                LOCAL_MARKER,
+                sources(local_chunk),
+                BASE_MARKER,
+                sources(base_chunk),
+                SEPARATOR_MARKER,
+                sources(notion_chunk),
+                NOTION_MARKER
+            ));
+        }
+        if next.is_none() {
+            break;
+        }
+        (i, a, b) = (j, x, y);
+    }
+    let text = text.trim_start_matches('\n').to_string();
+    let tail = if local.tail.is_empty() && !text.is_empty() {
+        "\n"
+    } else {
+        &local.tail
+    };
+    MergeResult {
+        text: text + tail,
+        conflicts,
+    }
+}
+
+/// For every block of `base`, the block of `other` it is kept as.
+fn matches(base: &[Vec<Element>], other: &[Vec<Element>]) -> Vec<Option<usize>> {
+    let mut matched = vec![None; base.len()];
+    for op in capture_diff_slices(Algorithm::Myers, base, other) {
+        if let DiffOp::Equal {
+            old_index,
+            new_index,
+            len,
+        } = op
+        {
+            for k in 0..len {
+                matched[old_index + k] = Some(new_index + k);
+            }
+        }
+    }
+    matched
+}
+
+fn concat(blocks: &[MergeBlock]) -> String {
+    blocks.iter().map(|b| format!("{}{}", b.prefix, b.source)).collect()
+}
+
+fn sources(blocks: &[MergeBlock]) -> String {
+    blocks.iter().map(|b| b.source.as_str()).collect::<Vec<_>>().join("\n\n")
+}
+
+/// The prefixes of `blocks` holding more than blank lines, kept when the blocks are replaced.
+fn pinned(blocks: &[MergeBlock]) -> String {
+    blocks
+        .iter()
+        .filter(|b| !b.prefix.trim().is_empty())
+        .map(|b| b.prefix.trim_end())
+        .collect()
+}
+
+/// Merges the edits made in Notion since the last ship of `dir` into its markdown files. The
+/// files as they were shipped are read from git, at the commit of the last ship, so only
+/// pages of files shipped from a commit are merged. Pages not edited in Notion are left
+/// alone.
+pub async fn merge_tree(client: &NotionClient, dir: &str) -> Result<MergeReport> {
+    let settings = client.settings();
+    let tree = SourceTree::load(dir, settings.content.clone())?;
+    let state_path = SyncState::path(&tree.root, settings.sync.state_file.as_deref());
+    let state = SyncState::load(&state_path)?.ok_or(anyhow!(
+        "no sync state at {}, ship first",
+        state_path.display()
+    ))?;
+    let commit = state.commit.clone().ok_or(anyhow!(
+        "the last ship wasn't from a git repository, merge reads the shipped files from git"
+    ))?;
+
+    let mut report = MergeReport::default();
+    for (key, tracked) in state.pages.iter() {
+        let path = tree.root.join(key);
+        let display = path.display().to_string();
+        let shipped_time = match (&tracked.last_edited_time, path.is_file()) {
+            (Some(t), true) => t,
+            _ => continue,
+        };
+        let page = client.get_page(&tracked.page_id).await?;
+        if page.in_trash || !is_edited_after(shipped_time, &page.last_edited_time) {
+            continue;
+        }
+        let base = match file_at(&tree.root, &commit, std::path::Path::new(key))? {
+            Some(base) => base,
+            None => {
+                report.skipped.push(Diagnostic::warning(
+                    &display,
+                    None,
+                    format!("not in commit {} of the last ship, it can't be merged", commit),
+                ));
+                continue;
+            }
+        };
+        let local = std::fs::read_to_string(&path)?;
+        let parsed = parse_file(&path).await?;
+        let skip_title = tree.settings.title_strategy(std::path::Path::new(key))
+            == TitleStrategy::FirstHeading
+            && parsed.get_arguments()?.title.is_none();
+
+        let mut blocks = client.get_page_blocks(&tracked.page_id).await?;
+        if settings.footer.enabled {
+            strip_footer(&mut blocks);
+        }
+        let result = merge(
+            &markdown_blocks(&base, skip_title)?,
+            &markdown_blocks(&local, skip_title)?,
+            &notion_blocks(&blocks),
+        );
+        let is_changed = result.text != local;
+        if is_changed {
+            std::fs::write(&path, &result.text)?;
+        }
+        report.files.push(MergedFile {
+            path: display,
+            conflicts: result.conflicts,
+            is_changed,
+        });
+    }
+    Ok(report)
+}
+
+/// Drops the divider and footer line a ship ends every page with.
+fn strip_footer(blocks: &mut Vec<AppendBlockRequestChild>) {
+    let n = blocks.len();
+    if n >= 2
+        && matches!(blocks[n - 2].block_type(), BlockType::Divider)
+        && matches!(blocks[n - 1].block_type(), BlockType::Paragraph)
+    {
+        blocks.truncate(n - 2);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sync::merge::{markdown_blocks, merge, MergeBlock, LOCAL_MARKER};
+
+    /// Blocks as [`crate::sync::merge::notion_blocks`] reads them back.
+    fn notion(contents: &str) -> Vec<MergeBlock> {
+        let mut blocks = markdown_blocks(contents, false).unwrap().blocks;
+        for b in blocks.iter_mut() {
+            b.prefix = "\n\n".to_string();
+        }
+        blocks
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_merge() {
+        let base = markdown_blocks("# Page\n\nIntro.\n\nMiddle.\n\n- one\n- two\n", true).unwrap();
+        let local = markdown_blocks("# Page\n\n<!-- kept -->\nIntro, **reworded**.\n\nMiddle.\n\n- one\n- two\n", true).unwrap();
+        assert_eq!(local.blocks.len(), 3);
+
+        // The markdown changed the intro, Notion changed the list and added a paragraph.
+        let result = merge(&base, &local, &notion("Intro.\n\nMiddle.\n\n- one\n- three\n\nAdded.\n"));
+        assert_eq!(result.conflicts, 0);
+        assert_eq!(
+            result.text,
+            "# Page\n\n<!-- kept -->\nIntro, **reworded**.\n\nMiddle.\n\n- one\n- three\n\nAdded.\n"
+        );
+
+        // Both changed the intro.
+        let result = merge(&base, &local, &notion("Intro, edited in Notion.\n\nMiddle.\n\n- one\n- two\n"));
+        assert_eq!(result.conflicts, 1);
+        assert_eq!(
+            result.text,
+            format!(
+                "# Page\n\n<!-- kept -->\n\n{}\nIntro, **reworded**.\n||||||| shipped\nIntro.\n=======\nIntro, edited in Notion.\n>>>>>>> notion\n\nMiddle.\n\n- one\n- two\n",
+                LOCAL_MARKER
+            )
+        );
+
+        // Notion changed the block the title sits above.
+        let result = merge(&base, &base, &notion("Intro, edited in Notion.\n\nMiddle.\n\n- one\n- two\n"));
+        assert_eq!(result.text, "# Page\n\nIntro, edited in Notion.\n\nMiddle.\n\n- one\n- two\n");
+    }
+}
@@ -0,0 +1,6 @@
+pub mod daemon;
+pub mod git;
+pub mod merge;
+pub mod state;
+pub mod status;
+pub mod trash;
@@ -0,0 +1,348 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_STATE_FILE: &str = ".notation/state.json";
+/// How many archived pages the state remembers, the latest ones.
+pub const MAX_ARCHIVED_PAGES: usize = 200;
+
+/// A Notion page created for a markdown file or a directory.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TrackedPage {
+    pub page_id: String,
+    pub title: String,
+    /// The `--slug` of the file, which finds the page again after the file is renamed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slug: Option<String>,
+    /// The [`content_hash`] of the file when it was shipped.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_hash: Option<String>,
+    /// The `last_edited_time` of the page right after it was shipped, so an edit made in
+    /// Notion since then shows up as a later time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_edited_time: Option<String>,
+}
+
+/// A tracked page that was archived, by a ship after its file was removed or by a clear.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ArchivedPage {
+    /// The key the page was tracked under.
+    pub key: String,
+    #[serde(default)]
+    pub is_directory: bool,
+    pub archived_at: String,
+    #[serde(flatten)]
+    pub page: TrackedPage,
+}
+
+/// The hex SHA-256 of a file's contents.
+pub fn content_hash(contents: &[u8]) -> String {
+    openssl::sha::sha256(contents)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// What the last ship of a directory created, keyed by paths relative to the shipped root, so
+/// later ships can update those pages instead of creating new ones.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SyncState {
+    pub parent_page_id: String,
+    /// The commit checked out when the state was last written, if shipped from a git repository.
+    pub commit: Option<String>,
+    #[serde(default)]
+    pub directories: BTreeMap<String, TrackedPage>,
+    #[serde(default)]
+    pub pages: BTreeMap<String, TrackedPage>,
+    /// Files shipped as sections of a merged directory's page, mapped to that directory.
+    #[serde(default)]
+    pub merged_pages: BTreeMap<String, String>,
+    /// Pages archived since they were tracked, the latest last.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub archived: Vec<ArchivedPage>,
+}
+
+impl SyncState {
+    pub fn new(parent_page_id: String) -> Self {
+        SyncState {
+            parent_page_id,
+            ..Default::default()
+        }
+    }
+
+    /// Where the state of the directory shipped from `root` lives: `state_file` when set
+    /// (relative paths are relative to `root`), otherwise `.notation/state.json` under `root`.
+    pub fn path(root: &Path, state_file: Option<&str>) -> PathBuf {
+        root.join(state_file.unwrap_or(DEFAULT_STATE_FILE))
+    }
+
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let contents = std::fs::read_to_string(path)?;
+        let state = serde_json::from_str(&contents)
+            .map_err(|e| anyhow!("failed to read sync state {}: {}", path.display(), e))?;
+        Ok(Some(state))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// The key of a tracked page with `slug`, other than `key`.
+    pub fn find_slug(&self, slug: &str, key: &str) -> Option<String> {
+        self.pages
+            .iter()
+            .find(|(k, p)| p.slug.as_deref() == Some(slug) && k.as_str() != key)
+            .map(|(k, _)| k.clone())
+    }
+
+    /// Moves the page tracked under `old_key` to `key`, for a file renamed since the last ship.
+    /// Notion can't move pages between parents, so a file moved to another directory isn't
+    /// renamed and `false` is returned.
+    pub fn rename_page(&mut self, old_key: &str, key: &str) -> bool {
+        let parent = |k: &str| Path::new(k).parent().map(Path::to_path_buf);
+        if parent(old_key) != parent(key) {
+            return false;
+        }
+        match self.pages.remove(old_key) {
+            Some(page) => {
+                self.pages.insert(key.to_string(), page);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Points the pages tracked under the ids in `page_ids` at the pages they map to, like pages
+    /// recreated from a snapshot. Their edit times are forgotten. Returns how many were.
+    pub fn replace_page_ids(&mut self, page_ids: &HashMap<String, String>) -> usize {
+        let page_ids: HashMap<String, &String> = page_ids
+            .iter()
+            .map(|(old, new)| (old.replace("-", ""), new))
+            .collect();
+        let mut replaced = 0;
+        for page in self.directories.values_mut().chain(self.pages.values_mut()) {
+            if let Some(new) = page_ids.get(&page.page_id.replace("-", "")) {
+                page.page_id = new.to_string();
+                page.last_edited_time = None;
+                replaced += 1;
+            }
+        }
+        replaced
+    }
+
+    /// The pages tracked for `key`, a file or directory, and for what is under it, leaving out
+    /// those under a tracked directory among them: its page holds theirs. By key.
+    pub fn tracked_under(&self, key: &str) -> Vec<(String, TrackedPage)> {
+        let is_under = |k: &str, key: &str| key.is_empty() || k == key || k.starts_with(&format!("{}/", key));
+        let directories: Vec<&String> = self.directories.keys().filter(|k| is_under(k, key)).collect();
+        self.directories
+            .iter()
+            .chain(self.pages.iter())
+            .filter(|(k, _)| is_under(k, key))
+            .filter(|(k, _)| !directories.iter().any(|d| d != k && is_under(k, d)))
+            .map(|(k, p)| (k.clone(), p.clone()))
+            .collect()
+    }
+
+    /// Remembers that the page tracked under `key` was archived, for `notation trash` to find.
+    pub fn record_archived(&mut self, key: &str, is_directory: bool, page: TrackedPage) {
+        self.archived.retain(|a| a.page.page_id != page.page_id);
+        self.archived.push(ArchivedPage {
+            key: key.to_string(),
+            is_directory,
+            archived_at: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            page,
+        });
+        if self.archived.len() > MAX_ARCHIVED_PAGES {
+            self.archived.drain(..self.archived.len() - MAX_ARCHIVED_PAGES);
+        }
+    }
+
+    /// The ids of the archived pages under the key of the archived directory page `page_id`,
+    /// which come back with it unless they were archived on their own.
+    pub fn archived_under(&self, page_id: &str) -> Vec<String> {
+        let archived = self
+            .archived
+            .iter()
+            .find(|a| a.is_directory && a.page.page_id.replace("-", "") == page_id.replace("-", ""));
+        let prefix = match archived {
+            Some(a) => format!("{}/", a.key),
+            None => return Vec::new(),
+        };
+        self.archived
+            .iter()
+            .filter(|a| a.key.starts_with(&prefix))
+            .map(|a| a.page.page_id.clone())
+            .collect()
+    }
+
+    /// Tracks the archived page `page_id` again, under its old key, unless another page took
+    /// that key since. The pages archived with a directory's page come back with it, but for
+    /// those `in_trash` still, which were archived on their own. Returns whether it did.
+    pub fn retrack(&mut self, page_id: &str, in_trash: &[String]) -> bool {
+        let position = self
+            .archived
+            .iter()
+            .position(|a| a.page.page_id.replace("-", "") == page_id.replace("-", ""));
+        let position = match position {
+            Some(p) => p,
+            None => return false,
+        };
+        let archived = &self.archived[position];
+        let pages = if archived.is_directory { &mut self.directories } else { &mut self.pages };
+        if pages.contains_key(&archived.key) {
+            return false;
+        }
+        let archived = self.archived.remove(position);
+        pages.insert(archived.key.clone(), archived.page);
+        if archived.is_directory {
+            let prefix = format!("{}/", archived.key);
+            let under: Vec<String> = self
+                .archived
+                .iter()
+                .filter(|a| a.key.starts_with(&prefix) && !in_trash.contains(&a.page.page_id))
+                .map(|a| a.page.page_id.clone())
+                .collect();
+            for page_id in under {
+                self.retrack(&page_id, in_trash);
+            }
+        }
+        true
+    }
+
+    /// Stops tracking the pages of `key`, a file or directory, and of what is under it, which
+    /// were archived. Returns how many there were.
+    pub fn archive_under(&mut self, key: &str) -> usize {
+        let is_under = |k: &str| key.is_empty() || k == key || k.starts_with(&format!("{}/", key));
+        let archived: Vec<(String, bool)> = self
+            .directories
+            .keys()
+            .map(|k| (k.clone(), true))
+            .chain(self.pages.keys().map(|k| (k.clone(), false)))
+            .filter(|(k, _)| is_under(k))
+            .collect();
+        for (k, is_directory) in archived.iter() {
+            let pages = if *is_directory { &mut self.directories } else { &mut self.pages };
+            if let Some(page) = pages.remove(k) {
+                self.record_archived(k, *is_directory, page);
+            }
+        }
+        self.merged_pages.retain(|k, _| !is_under(k));
+        archived.len()
+    }
+
+    /// The state key of a path relative to the shipped root, with `/` separators on every
+    /// platform.
+    pub fn key(relative: &Path) -> String {
+        relative
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(n) => Some(n.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+
+    use crate::sync::state::{SyncState, TrackedPage};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_state_round_trip() {
+        let root = std::env::temp_dir().join(format!("notation-state-{}", std::process::id()));
+        let path = SyncState::path(&root, None);
+        assert_eq!(path, root.join(".notation/state.json"));
+        assert!(SyncState::load(&path).unwrap().is_none());
+
+        let mut state = SyncState::new("parent".to_string());
+        state.pages.insert(
+            SyncState::key(&PathBuf::from("api").join("auth.md")),
+            TrackedPage {
+                page_id: "page".to_string(),
+                title: "auth".to_string(),
+                slug: Some("auth".to_string()),
+                ..Default::default()
+            },
+        );
+        state.save(&path).unwrap();
+        let loaded = SyncState::load(&path).unwrap().unwrap();
+        assert_eq!(loaded.parent_page_id, "parent");
+        assert_eq!(loaded.pages["api/auth.md"].page_id, "page");
+        assert_eq!(SyncState::key(Path::new("./intro.md")), "intro.md");
+
+        let mut renamed = loaded.clone();
+        assert_eq!(renamed.find_slug("auth", "api/login.md"), Some("api/auth.md".to_string()));
+        assert!(renamed.rename_page("api/auth.md", "api/login.md"));
+        assert_eq!(renamed.pages["api/login.md"].page_id, "page");
+        assert!(!renamed.pages.contains_key("api/auth.md"));
+        assert!(!renamed.clone().rename_page("api/login.md", "guides/login.md"));
+
+        let mut restored = loaded.clone();
+        restored.pages.get_mut("api/auth.md").unwrap().last_edited_time = Some("2026-01-01T00:00:00.000Z".to_string());
+        let page_ids = HashMap::from([("pa-ge".to_string(), "new-page".to_string())]);
+        assert_eq!(restored.replace_page_ids(&page_ids), 1);
+        assert_eq!(restored.pages["api/auth.md"].page_id, "new-page");
+        assert!(restored.pages["api/auth.md"].last_edited_time.is_none());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_tracked_under() {
+        let page = |id: &str| TrackedPage {
+            page_id: id.to_string(),
+            ..Default::default()
+        };
+        let mut state = SyncState::new("parent".to_string());
+        state.directories.insert("guides".to_string(), page("d1"));
+        state.directories.insert("guides/setup".to_string(), page("d2"));
+        state.pages.insert("guides/intro.md".to_string(), page("p1"));
+        state.pages.insert("guides/setup/linux.md".to_string(), page("p2"));
+        state.pages.insert("guidesfaq.md".to_string(), page("p3"));
+        state.pages.insert("api/auth.md".to_string(), page("p4"));
+        state.merged_pages.insert("guides/setup/mac.md".to_string(), "guides/setup".to_string());
+
+        let ids = |key: &str| -> Vec<String> { state.tracked_under(key).into_iter().map(|(_, p)| p.page_id).collect() };
+        assert_eq!(ids("guides"), vec!["d1"]);
+        assert_eq!(ids("guides/setup"), vec!["d2"]);
+        assert_eq!(ids("guides/intro.md"), vec!["p1"]);
+        assert_eq!(ids("api"), vec!["p4"]);
+        assert!(ids("docs").is_empty());
+
+        assert_eq!(state.archive_under("guides"), 4);
+        assert_eq!(state.pages.keys().collect::<Vec<_>>(), vec!["api/auth.md", "guidesfaq.md"]);
+        assert!(state.merged_pages.is_empty());
+        assert_eq!(state.archived.len(), 4);
+
+        let auth = state.pages.remove("api/auth.md").unwrap();
+        state.record_archived("api/auth.md", false, auth.clone());
+        state.record_archived("api/auth.md", false, auth);
+        assert_eq!(state.archived.len(), 5);
+        assert!(!state.retrack("unknown", &[]));
+        assert!(state.retrack("p4", &[]));
+        assert_eq!(state.pages["api/auth.md"].page_id, "p4");
+
+        assert_eq!(state.archived_under("d1").len(), 3);
+        assert!(state.archived_under("p1").is_empty());
+        assert!(state.retrack("d1", &["p1".to_string()]));
+        assert_eq!(state.directories.keys().collect::<Vec<_>>(), vec!["guides", "guides/setup"]);
+        assert!(state.pages.contains_key("guides/setup/linux.md"));
+        assert!(!state.pages.contains_key("guides/intro.md"));
+        assert_eq!(state.archived.len(), 1);
+    }
+}
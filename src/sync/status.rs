@@ -0,0 +1,205 @@
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use chrono::DateTime;
+use serde::Serialize;
+
+use crate::notion::client::NotionClient;
+use crate::notion::index::PageIndex;
+use crate::notion::page::PageResponse;
+use crate::notion::summary::page_url;
+use crate::settings::directory::SourceTree;
+use crate::sync::state::{content_hash, SyncState, TrackedPage};
+
+/// How a shipped file compares to what was shipped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LocalChange {
+    Unchanged,
+    Modified,
+    Deleted,
+    /// Shipped before hashes were recorded.
+    Unknown,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PageStatus {
+    /// The file, or directory, relative to the shipped root.
+    pub path: String,
+    pub page_id: String,
+    pub url: String,
+    /// `None` for directory pages, which have no file of their own.
+    pub local: Option<LocalChange>,
+    /// Whether the page was edited in Notion after the last ship, `None` when no edit time
+    /// was recorded.
+    pub is_edited_in_notion: Option<bool>,
+    pub is_in_trash: bool,
+}
+
+impl PageStatus {
+    /// Whether shipping would overwrite edits made in Notion.
+    pub fn is_conflict(&self) -> bool {
+        self.is_edited_in_notion == Some(true) && !self.is_in_trash
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct StatusReport {
+    pub pages: Vec<PageStatus>,
+    /// Files that were never shipped.
+    pub new_files: Vec<String>,
+    /// When the page index the edit times were read from was refreshed, if they were.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index_refreshed_at: Option<String>,
+}
+
+/// What an incremental ship does with pages edited in Notion since they were shipped.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Ship nothing and fail with a [`ConflictError`].
+    #[default]
+    Fail,
+    Overwrite,
+    /// Leave those pages as they are in Notion, and ship the rest.
+    Skip,
+}
+
+/// The pages an incremental ship would overwrite edits of, by file or directory relative to
+/// the shipped root. Nothing was shipped.
+#[derive(Clone, Debug)]
+pub struct ConflictError {
+    pub pages: Vec<String>,
+}
+
+impl Display for ConflictError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} page(s) were edited in Notion since the last ship ({}), ship with --force to overwrite them or --skip-conflicts to leave them",
+            self.pages.len(),
+            self.pages.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for ConflictError {}
+
+/// Compares the tracked pages of `dir` with the files under it and with the pages in Notion.
+pub async fn status(client: &NotionClient, dir: &str) -> Result<StatusReport> {
+    let settings = client.settings();
+    let tree = SourceTree::load(dir, settings.content.clone())?;
+    let state_path = SyncState::path(&tree.root, settings.sync.state_file.as_deref());
+    let state = SyncState::load(&state_path)?.ok_or(anyhow!(
+        "no sync state at {}, ship first",
+        state_path.display()
+    ))?;
+
+    let index = client.page_index()?;
+    let mut report = StatusReport {
+        index_refreshed_at: index.as_ref().map(|i| i.refreshed_at.clone()),
+        ..Default::default()
+    };
+    for (key, tracked) in state.directories.iter() {
+        report.pages.push(page_status(client, index.as_ref(), key, tracked, None).await?);
+    }
+    for (key, tracked) in state.pages.iter() {
+        let local = local_change(&tree.root, key, tracked)?;
+        report.pages.push(page_status(client, index.as_ref(), key, tracked, Some(local)).await?);
+    }
+    report.new_files = tree
+        .files
+        .iter()
+        .map(|p| SyncState::key(p))
+        .filter(|k| !state.pages.contains_key(k) && !state.merged_pages.contains_key(k))
+        .collect();
+    Ok(report)
+}
+
+/// The status of a tracked page, read from `index` when it has the page. Pages in the trash
+/// aren't indexed, so those are always read from Notion.
+async fn page_status(
+    client: &NotionClient,
+    index: Option<&PageIndex>,
+    key: &str,
+    tracked: &TrackedPage,
+    local: Option<LocalChange>,
+) -> Result<PageStatus> {
+    let page = match index.and_then(|i| i.get(&tracked.page_id)) {
+        Some(indexed) => PageResponse {
+            id: indexed.id.clone(),
+            last_edited_time: indexed.last_edited_time.clone(),
+            in_trash: false,
+            icon: None,
+        },
+        None => client.get_page(&tracked.page_id).await?,
+    };
+    Ok(PageStatus {
+        path: key.to_string(),
+        page_id: tracked.page_id.clone(),
+        url: page_url(&tracked.page_id),
+        local,
+        is_edited_in_notion: tracked
+            .last_edited_time
+            .as_deref()
+            .map(|shipped| is_edited_after(shipped, &page.last_edited_time)),
+        is_in_trash: page.in_trash,
+    })
+}
+
+pub fn local_change(root: &Path, key: &str, tracked: &TrackedPage) -> Result<LocalChange> {
+    let path = root.join(key);
+    if !path.is_file() {
+        return Ok(LocalChange::Deleted);
+    }
+    let shipped = match &tracked.source_hash {
+        Some(h) => h,
+        None => return Ok(LocalChange::Unknown),
+    };
+    if *shipped == content_hash(&std::fs::read(&path)?) {
+        Ok(LocalChange::Unchanged)
+    } else {
+        Ok(LocalChange::Modified)
+    }
+}
+
+/// Whether `current`, a `last_edited_time` read from Notion, is later than `shipped`. Times
+/// that don't parse are compared as they are.
+pub fn is_edited_after(shipped: &str, current: &str) -> bool {
+    match (
+        DateTime::parse_from_rfc3339(shipped),
+        DateTime::parse_from_rfc3339(current),
+    ) {
+        (Ok(s), Ok(c)) => c > s,
+        _ => shipped != current,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sync::state::{content_hash, TrackedPage};
+    use crate::sync::status::{is_edited_after, local_change, ConflictError, LocalChange};
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_status() {
+        assert!(is_edited_after("2024-05-01T12:34:00.000Z", "2024-05-01T12:35:00.000Z"));
+        assert!(!is_edited_after("2024-05-01T12:34:00.000Z", "2024-05-01T12:34:00.000Z"));
+        assert!(!is_edited_after("2024-05-01T12:34:00.000Z", "2024-05-01T12:33:00.000Z"));
+        let conflicts = ConflictError {
+            pages: vec!["api".to_string(), "guide.md".to_string()],
+        };
+        assert!(conflicts.to_string().starts_with("2 page(s) were edited in Notion since the last ship (api, guide.md)"));
+
+        let root = std::env::temp_dir().join(format!("notation-status-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("page.md"), "# Page\n").unwrap();
+        let mut tracked = TrackedPage::default();
+        assert_eq!(local_change(&root, "page.md", &tracked).unwrap(), LocalChange::Unknown);
+        tracked.source_hash = Some(content_hash(b"# Page\n"));
+        assert_eq!(local_change(&root, "page.md", &tracked).unwrap(), LocalChange::Unchanged);
+        std::fs::write(root.join("page.md"), "# Page\n\nMore.\n").unwrap();
+        assert_eq!(local_change(&root, "page.md", &tracked).unwrap(), LocalChange::Modified);
+        assert_eq!(local_change(&root, "gone.md", &tracked).unwrap(), LocalChange::Deleted);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,96 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+
+use crate::notion::client::NotionClient;
+use crate::notion::summary::page_url;
+use crate::settings::directory::SourceTree;
+use crate::sync::state::SyncState;
+
+/// A page of a shipped directory that is in the Notion trash.
+#[derive(Clone, Debug, Serialize)]
+pub struct TrashedPage {
+    pub page_id: String,
+    pub title: String,
+    /// The file, or directory, relative to the shipped root, it was shipped from.
+    pub path: String,
+    pub url: String,
+    /// When it was archived, or, for pages archived in Notion, last edited.
+    pub archived_at: String,
+    /// Whether the sync state still tracks it, as pages archived in Notion are.
+    pub is_tracked: bool,
+}
+
+/// The pages of `dir` in the trash: those the sync state remembers archiving, and tracked
+/// pages archived in Notion since. Every one is checked with Notion, pages taken out of the
+/// trash or deleted for good are left out. The latest first.
+pub async fn list(client: &NotionClient, dir: &str) -> Result<Vec<TrashedPage>> {
+    let (_, state) = load(client, dir)?;
+    let mut trashed = Vec::new();
+    for archived in state.archived.iter() {
+        let page = match client.find_page(&archived.page.page_id).await? {
+            Some(page) if page.in_trash => page,
+            _ => continue,
+        };
+        trashed.push(TrashedPage {
+            page_id: page.id,
+            title: archived.page.title.clone(),
+            path: archived.key.clone(),
+            url: page_url(&archived.page.page_id),
+            archived_at: archived.archived_at.clone(),
+            is_tracked: false,
+        });
+    }
+    for (key, tracked) in state.directories.iter().chain(state.pages.iter()) {
+        let page = match client.find_page(&tracked.page_id).await? {
+            Some(page) if page.in_trash => page,
+            _ => continue,
+        };
+        trashed.push(TrashedPage {
+            page_id: page.id,
+            title: tracked.title.clone(),
+            path: key.clone(),
+            url: page_url(&tracked.page_id),
+            archived_at: page.last_edited_time,
+            is_tracked: true,
+        });
+    }
+    trashed.sort_by(|a, b| b.archived_at.cmp(&a.archived_at));
+    Ok(trashed)
+}
+
+/// Takes the page `page_id` out of the trash, with the pages under it that were archived with
+/// it. With `dir`, a page the sync state of `dir` remembers archiving is tracked again, so the
+/// next ship updates it instead of creating a new one, and so are the pages that came back
+/// with it. Returns whether it was.
+pub async fn restore(client: &NotionClient, dir: Option<&str>, page_id: &str) -> Result<bool> {
+    client.restore_page(page_id).await?;
+    let (state_path, mut state) = match dir {
+        Some(dir) => load(client, dir)?,
+        None => return Ok(false),
+    };
+    // Pages archived on their own before their directory's page stay in the trash.
+    let mut in_trash = Vec::new();
+    for id in state.archived_under(page_id) {
+        if client.find_page(&id).await?.is_none_or(|p| p.in_trash) {
+            in_trash.push(id);
+        }
+    }
+    if !state.retrack(page_id, &in_trash) {
+        return Ok(false);
+    }
+    state.save(&state_path)?;
+    Ok(true)
+}
+
+fn load(client: &NotionClient, dir: &str) -> Result<(PathBuf, SyncState)> {
+    let settings = client.settings();
+    let tree = SourceTree::load(dir, settings.content.clone())?;
+    let state_path = SyncState::path(&tree.root, settings.sync.state_file.as_deref());
+    let state = SyncState::load(&state_path)?.ok_or(anyhow!(
+        "no sync state at {}, ship first",
+        state_path.display()
+    ))?;
+    Ok((state_path, state))
+}